@@ -6,6 +6,7 @@ use proc_macro::TokenStream;
 use proc_macro2::{Span as Span2, TokenStream as TokenStream2};
 use quote::{format_ident, quote, ToTokens};
 use syn::{
+    ext::IdentExt,
     parse_quote,
     punctuated::Punctuated,
     token::{Comma, Eq as SynEq, Paren},
@@ -195,6 +196,98 @@ impl Layers {
             }
         }
     }
+    fn try_for_each_named_parameter(&self) -> TokenStream2 {
+        let named = |layer: TokenStream2, prefix: String| {
+            quote! {
+                #layer.try_for_each_named_parameter(|name: &str, parameter| {
+                    if name.is_empty() {
+                        f(#prefix, parameter)
+                    } else {
+                        f(&::std::format!("{}.{}", #prefix, name), parameter)
+                    }
+                })
+            }
+        };
+        match self {
+            Self::Struct(layers) => {
+                let named = layers
+                    .iter()
+                    .map(|layer| named(quote!(self.#layer), layer.name()));
+                quote! {
+                    #(#named?;)*
+                    Ok(())
+                }
+            }
+            Self::Enum(layers) => {
+                let named = layers
+                    .iter()
+                    .map(|layer| named(quote!(layer), layer.name()));
+                quote! {
+                    match self {
+                        #(
+                            Self::#layers(layer) => #named,
+                        )*
+                    }
+                }
+            }
+        }
+    }
+    fn for_each(&self, method: Ident, arg: TokenStream2) -> TokenStream2 {
+        match self {
+            Self::Struct(layers) => {
+                quote! {
+                    #(self.#layers.#method(#arg);)*
+                }
+            }
+            Self::Enum(layers) => {
+                quote! {
+                    match self {
+                        #(
+                            Self::#layers(layer) => layer.#method(#arg),
+                        )*
+                    }
+                }
+            }
+        }
+    }
+    fn register_hook(&self, autograph: &Path, ident: &Ident, method: Ident) -> TokenStream2 {
+        let not_found = quote! {
+            if name.is_empty() {
+                #autograph::anyhow::bail!("{} does not support hooks!", ::std::stringify!(#ident));
+            }
+            #autograph::anyhow::bail!("Layer {:?} not found in {}!", name, ::std::stringify!(#ident))
+        };
+        let register = match self {
+            Self::Struct(layers) => {
+                let names = layers.iter().map(Layer::name);
+                quote! {
+                    match head {
+                        #(
+                            #names => return self.#layers.#method(tail, hook),
+                        )*
+                        _ => (),
+                    }
+                }
+            }
+            Self::Enum(layers) => {
+                let names = layers.iter().map(Layer::name);
+                quote! {
+                    match self {
+                        #(
+                            Self::#layers(layer) if head == #names => return layer.#method(tail, hook),
+                        )*
+                        _ => (),
+                    }
+                }
+            }
+        };
+        quote! {
+            let (head, tail) = name.split_once('.').unwrap_or((name, ""));
+            #register
+            let _ = (tail, hook);
+            #not_found
+        }
+    }
 }
 
 enum Layer {
@@ -212,6 +305,12 @@ impl Layer {
             Ok(Some(Self::Index(index.into())))
         }
     }
+    fn name(&self) -> String {
+        match self {
+            Self::Ident(ident) => ident.unraw().to_string(),
+            Self::Index(index) => index.index.to_string(),
+        }
+    }
     fn parse_variant(variant: &Variant) -> Result<Self> {
         if let Fields::Unnamed(fields) = &variant.fields {
             if fields.unnamed.len() != 1 {
@@ -247,25 +346,37 @@ fn layer_impl(input: TokenStream2) -> Result<TokenStream2> {
     let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
     let try_for_each_parameter =
         layers.try_for_each(format_ident!("try_for_each_parameter"), quote! { &mut f });
+    let try_for_each_named_parameter = layers.try_for_each_named_parameter();
     let try_for_each_parameter_view_mut = layers.try_for_each(
         format_ident!("try_for_each_parameter_view_mut"),
         quote! { &mut f },
     );
     let cast_mut = layers.try_for_each(format_ident!("cast_mut"), quote!(scalar_type));
     let to_device_mut = layers.try_for_each(format_ident!("to_device_mut"), quote!(device.clone()));
+    let set_training = layers.for_each(format_ident!("set_training"), quote!(training));
+    let register_forward_hook =
+        layers.register_hook(&autograph, ident, format_ident!("register_forward_hook"));
+    let register_backward_hook =
+        layers.register_hook(&autograph, ident, format_ident!("register_backward_hook"));
     Ok(quote! {
         #[automatically_derived]
         impl #impl_generics Layer for #ident #ty_generics #where_clause {
-            fn try_for_each_parameter<F, E>(&self, mut f: F) -> #autograph::anyhow::Result<(), E>
+            fn try_for_each_parameter<__F, __E>(&self, mut f: __F) -> #autograph::anyhow::Result<(), __E>
                 where
-                    F: FnMut(#autograph::learn::neural_network::autograd::ParameterD) -> #autograph::anyhow::Result<(), E>,
+                    __F: FnMut(#autograph::learn::neural_network::autograd::ParameterD) -> #autograph::anyhow::Result<(), __E>,
                 {
                 #try_for_each_parameter
             }
-            fn try_for_each_parameter_view_mut<F, E>(&mut self, mut f: F) -> #autograph::anyhow::Result<()>
+            fn try_for_each_named_parameter<__F, __E>(&self, mut f: __F) -> #autograph::anyhow::Result<(), __E>
+                where
+                    __F: FnMut(&str, #autograph::learn::neural_network::autograd::ParameterD) -> #autograph::anyhow::Result<(), __E>,
+                {
+                #try_for_each_named_parameter
+            }
+            fn try_for_each_parameter_view_mut<__F, __E>(&mut self, mut f: __F) -> #autograph::anyhow::Result<()>
                where
-                   F: FnMut(#autograph::learn::neural_network::autograd::ParameterViewMutD) -> #autograph::anyhow::Result<(), E>,
-                   #autograph::anyhow::Error: From<E>,
+                   __F: FnMut(#autograph::learn::neural_network::autograd::ParameterViewMutD) -> #autograph::anyhow::Result<(), __E>,
+                   #autograph::anyhow::Error: From<__E>,
             {
                 #try_for_each_parameter_view_mut
             }
@@ -275,6 +386,15 @@ fn layer_impl(input: TokenStream2) -> Result<TokenStream2> {
             fn to_device_mut(&mut self, device: #autograph::krnl::device::Device) -> #autograph::anyhow::Result<()> {
                 #to_device_mut
             }
+            fn set_training(&mut self, training: bool) {
+                #set_training
+            }
+            fn register_forward_hook(&mut self, name: &str, hook: #autograph::learn::neural_network::layer::ForwardHook) -> #autograph::anyhow::Result<()> {
+                #register_forward_hook
+            }
+            fn register_backward_hook(&mut self, name: &str, hook: #autograph::learn::neural_network::layer::BackwardHook) -> #autograph::anyhow::Result<()> {
+                #register_backward_hook
+            }
         }
     })
 }
@@ -81,6 +81,7 @@ impl LeNet5Classifier {
         let optimizer = self.optimizer.as_ref().unwrap();
         let learning_rate = 0.01;
         self.model.update(learning_rate, optimizer)?;
+        self.device.wait()?;
         Ok(())
     }
 }
@@ -122,7 +123,7 @@ impl LeNet5 {
             .build()?;
         let relu2 = Relu;
         let pool2 = MaxPool2::builder().filter([2, 2]).build();
-        let flatten = Flatten;
+        let flatten = Flatten::default();
         let dense1 = Dense::builder()
             .device(device.clone())
             .scalar_type(scalar_type)
@@ -1,28 +1,30 @@
 use autograph::{
-    anyhow::Result,
-    dataset::mnist::{Mnist, MnistKind},
+    anyhow::{bail, Result},
+    dataset::{
+        mnist::{Mnist, MnistKind},
+        DataLoader, Dataset as _,
+    },
     krnl::{
         device::Device,
         krnl_core::half::bf16,
         scalar::{ScalarElem, ScalarType},
     },
     learn::{
-        criterion::{Accuracy, CrossEntropyLoss},
+        criterion::{Accuracy, AccuracyCounter, CrossEntropyLoss},
         neural_network::{
-            autograd::{Variable2, Variable4},
+            autograd::{ParameterViewMutD, Variable2, Variable4},
             layer::{Conv2, Dense, Flatten, Forward, Layer, MaxPool2, Relu},
-            optimizer::{Optimizer, SGD},
+            optimizer::{Adam, Optimizer, SGD},
         },
     },
     ndarray::{self, ArrayView1, ArrayView4, Axis, Dimension},
     tensor::{
-        CowTensor4, ScalarArcTensor, ScalarCowTensor, ScalarTensor1, ScalarTensor4, Tensor,
-        TensorView1, TensorView4,
+        ScalarArcTensor, ScalarTensor, ScalarTensor1, ScalarTensor4, Tensor1, Tensor4, TensorView0,
+        TensorView3,
     },
 };
 use clap::{Parser, ValueEnum};
 use num_format::{Locale, ToFormattedString};
-use rand::{seq::index::sample, thread_rng};
 use std::{fmt::Debug, time::Instant};
 
 #[derive(Layer, Forward, Debug)]
@@ -62,7 +64,7 @@ impl LeNet5 {
             .build()?;
         let relu2 = Relu;
         let pool2 = MaxPool2::builder().filter([2, 2]).build();
-        let flatten = Flatten;
+        let flatten = Flatten::default();
         let dense1 = Dense::builder()
             .device(device.clone())
             .scalar_type(scalar_type)
@@ -117,6 +119,29 @@ enum ScalarKind {
     F32,
 }
 
+#[derive(Clone, Copy, derive_more::Display, Debug, ValueEnum)]
+enum OptimizerKind {
+    #[display(fmt = "sgd")]
+    Sgd,
+    #[display(fmt = "adam")]
+    Adam,
+}
+
+#[derive(Debug)]
+enum AnyOptimizer {
+    Sgd(SGD),
+    Adam(Adam),
+}
+
+impl Optimizer for AnyOptimizer {
+    fn update(&self, learning_rate: f32, parameter: ParameterViewMutD) -> Result<()> {
+        match self {
+            Self::Sgd(optimizer) => optimizer.update(learning_rate, parameter),
+            Self::Adam(optimizer) => optimizer.update(learning_rate, parameter),
+        }
+    }
+}
+
 impl From<ScalarKind> for ScalarType {
     fn from(kind: ScalarKind) -> Self {
         match kind {
@@ -141,6 +166,8 @@ struct Options {
     train_batch_size: usize,
     #[arg(long, default_value_t = 1000)]
     test_batch_size: usize,
+    #[arg(long, default_value_t = OptimizerKind::Sgd)]
+    optimizer: OptimizerKind,
     #[arg(long, default_value_t = 0.1)]
     learning_rate: f32,
     #[arg(long)]
@@ -175,12 +202,15 @@ fn main() -> Result<()> {
     }
     let scalar_type = ScalarType::from(options.scalar_type);
     let mut model = LeNet5::new(device.clone(), scalar_type)?;
-    let optimizer = {
-        let mut builder = SGD::builder();
-        if let Some(momentum) = options.momentum {
-            builder = builder.momentum(momentum);
+    let optimizer = match options.optimizer {
+        OptimizerKind::Sgd => {
+            let mut builder = SGD::builder();
+            if let Some(momentum) = options.momentum {
+                builder = builder.momentum(momentum);
+            }
+            AnyOptimizer::Sgd(builder.build())
         }
-        builder.build()
+        OptimizerKind::Adam => AnyOptimizer::Adam(Adam::builder().build()?),
     };
     println!("model: {model:#?}");
     let parameter_count = {
@@ -198,33 +228,49 @@ fn main() -> Result<()> {
         ScalarKind::BF16 => ScalarElem::BF16(bf16::from_f32(image_scale)),
         ScalarKind::F32 => ScalarElem::F32(image_scale),
     };
+    let mut train_loader = DataLoader::new(
+        MnistDataset {
+            images: train_images.view(),
+            classes: train_classes.view(),
+        },
+        options.train_batch_size,
+    )?
+    .shuffle(true)
+    .device(device.clone());
+    let mut test_loader = DataLoader::new(
+        MnistDataset {
+            images: test_images.view(),
+            classes: test_classes.view(),
+        },
+        options.test_batch_size,
+    )?
+    .device(device.clone());
     let start = Instant::now();
     for epoch in 1..=options.epochs {
         let epoch_start = Instant::now();
-        let train_iter = shuffled_batches(
-            train_images.view(),
-            image_scale,
-            train_classes.view(),
+        let train_iter = train_loader
+            .iter()
+            .map(|batch| scale_images(batch?, image_scale));
+        let train_stats = train(
+            &mut model,
+            &optimizer,
+            options.learning_rate,
+            train_iter,
             device.clone(),
-            options.train_batch_size,
-        );
-        let train_stats = train(&mut model, &optimizer, options.learning_rate, train_iter)?;
+        )?;
         let train_count = train_stats.count;
         let train_correct = train_stats.correct;
         let train_loss = train_stats.mean_loss();
         let train_acc = train_stats.accuracy();
-        let test_iter = batches(
-            test_images.view(),
-            image_scale,
-            test_classes.view(),
-            device.clone(),
-            options.test_batch_size,
-        );
+        let test_iter = test_loader
+            .iter()
+            .map(|batch| scale_images(batch?, image_scale));
         let test_stats = test(&model, test_iter)?;
         let test_count = test_stats.count;
         let test_correct = test_stats.correct;
         let test_loss = test_stats.mean_loss();
         let test_acc = test_stats.accuracy();
+        device.wait()?;
         let epoch_elapsed = epoch_start.elapsed();
         print!("[{epoch}] train_loss: {train_loss:.5} train_acc: {train_acc:.2}% {train_correct}/{train_count} ");
         println!("test_loss: {test_loss:.5} test_acc: {test_acc:.2}% {test_correct}/{test_count} elapsed: {epoch_elapsed:.2?}");
@@ -233,70 +279,36 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-fn images_to_device_and_scale(
-    mut images: CowTensor4<u8>,
-    device: Device,
-    scale: ScalarElem,
-) -> Result<ScalarTensor4> {
-    images.to_device_mut(device)?;
-    ScalarCowTensor::from(images).scaled_cast(scale)
-}
-
-fn batches<'a>(
+#[derive(Clone, Copy)]
+struct MnistDataset<'a> {
     images: ArrayView4<'a, u8>,
-    image_scale: ScalarElem,
     classes: ArrayView1<'a, u8>,
-    device: Device,
-    batch_size: usize,
-) -> impl Iterator<Item = Result<(ScalarTensor4, ScalarTensor1)>> + 'a {
-    let (count, _inputs, _height, _width) = images.dim();
-    (0..count).step_by(batch_size).map(move |index| {
-        let end = (index + batch_size).min(count);
-        let images: TensorView4<u8> = images
-            .slice_axis(
-                Axis(0),
-                ndarray::Slice::new(index as isize, Some(end as isize), 1),
-            )
-            .try_into()
-            .unwrap();
-        let classes: TensorView1<u8> = classes
-            .slice_axis(
-                Axis(0),
-                ndarray::Slice::new(index as isize, Some(end as isize), 1),
-            )
-            .try_into()
-            .unwrap();
-        let images = images_to_device_and_scale(images.into(), device.clone(), image_scale)?;
-        let classes = classes.into_device(device.clone())?.into_scalar_tensor();
-        Ok((images, classes))
-    })
 }
 
-fn shuffled_batches<'a>(
-    images: ArrayView4<'a, u8>,
-    image_scale: ScalarElem,
-    classes: ArrayView1<'a, u8>,
-    device: Device,
-    batch_size: usize,
-) -> impl Iterator<Item = Result<(ScalarTensor4, ScalarTensor1)>> + 'a {
-    let (count, inputs, height, width) = images.dim();
-    let mut index_iter = sample(&mut thread_rng(), count, count).into_iter();
-    (0..count).step_by(batch_size).map(move |index| {
-        let batch_size = (index..count).take(batch_size).len();
-        let mut output_images = Vec::<u8>::with_capacity(batch_size * inputs * height * width);
-        let mut output_classes = Vec::<u8>::with_capacity(batch_size);
-        for index in index_iter.by_ref().take(batch_size) {
-            output_images.extend_from_slice(images.index_axis(Axis(0), index).as_slice().unwrap());
-            output_classes.push(classes[index]);
+impl<'a> autograph::dataset::Dataset for MnistDataset<'a> {
+    type Item = (TensorView3<'a, u8>, TensorView0<'a, u8>);
+    fn len(&self) -> usize {
+        self.classes.len()
+    }
+    fn get(&self, index: usize) -> Result<Self::Item> {
+        if index >= self.len() {
+            bail!(
+                "MnistDataset index {index} out of bounds for dataset with len {}!",
+                self.len()
+            );
         }
-        let images = Tensor::from(output_images)
-            .into_shape([batch_size, inputs, height, width])
-            .unwrap();
-        let classes = Tensor::from(output_classes);
-        let images = images_to_device_and_scale(images.into(), device.clone(), image_scale)?;
-        let classes = classes.into_device(device.clone())?.into();
-        Ok((images, classes))
-    })
+        let image = self.images.index_axis_move(Axis(0), index);
+        let class = self.classes.index_axis_move(Axis(0), index);
+        Ok((image.try_into().unwrap(), class.try_into().unwrap()))
+    }
+}
+
+fn scale_images(
+    (images, classes): (Tensor4<u8>, Tensor1<u8>),
+    scale: ScalarElem,
+) -> Result<(ScalarTensor4, ScalarTensor1)> {
+    let images = ScalarTensor::from(images).scaled_cast(scale)?;
+    Ok((images, classes.into()))
 }
 
 #[derive(Default)]
@@ -315,29 +327,32 @@ impl Stats {
     }
 }
 
-fn train<M, O, I>(model: &mut M, optimizer: &O, learning_rate: f32, mut iter: I) -> Result<Stats>
+fn train<M, O, I>(
+    model: &mut M,
+    optimizer: &O,
+    learning_rate: f32,
+    mut iter: I,
+    device: Device,
+) -> Result<Stats>
 where
     M: Layer + Forward<Variable4, Output = Variable2>,
     O: Optimizer,
     I: Iterator<Item = Result<(ScalarTensor4, ScalarTensor1)>>,
 {
     let mut stats = Stats::default();
+    let mut correct = AccuracyCounter::new(device)?;
     while let Some((x, t)) = iter.by_ref().next().transpose()? {
         stats.count += x.shape().first().unwrap();
         model.init_parameter_grads()?;
         let y = model.forward(x.into())?;
         let t = ScalarArcTensor::from(t);
-        stats.correct += y.value().accuracy(t.view())?;
+        y.value().accuracy_with(t.view(), &mut correct)?;
         let loss = y.cross_entropy_loss(t)?;
-        stats.loss += loss
-            .value()
-            .to_device(Device::host())?
-            .cast_into_tensor::<f32>()?
-            .into_array()?
-            .into_scalar();
+        stats.loss += loss.value().view().cast_into_tensor::<f32>()?.item()?;
         loss.backward()?;
         model.update(learning_rate, optimizer)?;
     }
+    stats.correct = correct.correct()?;
     Ok(stats)
 }
 
@@ -349,7 +364,7 @@ where
     let mut stats = Stats::default();
     while let Some((x, t)) = iter.by_ref().next().transpose()? {
         stats.count += x.shape().first().unwrap();
-        let y = model.forward(x.into())?.into_value();
+        let y = model.forward_values(x)?;
         stats.correct += y.accuracy(t.view())?;
         stats.loss += y.cross_entropy_loss(t.view())?;
     }
@@ -1,6 +1,497 @@
+use crate::tensor::{stack, Tensor, Tensor2, TensorBase};
+use anyhow::{bail, Result};
+use krnl::{buffer::Data, device::Device, scalar::Scalar};
+use ndarray::{Array, Array2, Axis, CowArray, Dimension, Slice};
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+/// CSV datasets.
+pub mod csv;
+/// Image folder datasets.
+#[cfg(feature = "image-folder")]
+pub mod image_folder;
 /// The Iris dataset.
 #[cfg(feature = "iris")]
 pub mod iris;
 /// The MNIST dataset.
 #[cfg(feature = "mnist")]
 pub mod mnist;
+/// Samplers.
+pub mod sampler;
+/// Transforms.
+pub mod transforms;
+
+/// Dataset.
+///
+/// An indexable collection of items.
+///
+/// Implemented for [`Vec`] and slices, by cloning the items, and for references to datasets,
+/// such that combinators can borrow a dataset.
+///
+/// # Example
+///```
+/// # use autograph::dataset::Dataset;
+/// # fn main() -> anyhow::Result<()> {
+/// let dataset: Vec<u32> = (0..10).collect();
+/// let [train, test] = <[_; 2]>::try_from((&dataset).random_split(&[0.8, 0.2], 0)?).unwrap();
+/// assert_eq!(train.len(), 8);
+/// assert_eq!(test.len(), 2);
+/// let dataset = train.concat(test);
+/// assert_eq!(dataset.len(), 10);
+/// # Ok(())
+/// # }
+///```
+pub trait Dataset {
+    /// The type of the items.
+    type Item;
+    /// The number of items.
+    fn len(&self) -> usize;
+    /// Whether the dataset is empty.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Gets the item at `index`.
+    ///
+    /// # Errors
+    /// - `index` is out of bounds.
+    /// - The item could not be loaded.
+    fn get(&self, index: usize) -> Result<Self::Item>;
+    /// A subset of the dataset with `indices`.
+    ///
+    /// Item `i` of the subset is item `indices[i]` of the dataset.
+    ///
+    /// # Errors
+    /// Any of the `indices` are out of bounds.
+    fn subset(self, indices: Vec<usize>) -> Result<Subset<Self>>
+    where
+        Self: Sized,
+    {
+        let len = self.len();
+        if let Some(index) = indices.iter().find(|i| **i >= len) {
+            bail!("Subset index {index} out of bounds for dataset with len {len}!");
+        }
+        Ok(Subset {
+            dataset: self,
+            indices,
+        })
+    }
+    /// Chains the dataset with `other`.
+    fn concat<D: Dataset<Item = Self::Item>>(self, other: D) -> Concat<Self, D>
+    where
+        Self: Sized,
+    {
+        Concat {
+            first: self,
+            second: other,
+        }
+    }
+    /// Randomly splits the dataset into subsets with sizes proportional to `fractions`.
+    ///
+    /// The items are shuffled with `seed`. When the sizes do not divide the dataset evenly, the
+    /// remaining items are added to the first subsets.
+    ///
+    /// # Errors
+    /// The `fractions` are negative or do not sum to 1.
+    fn random_split(self, fractions: &[f32], seed: u64) -> Result<Vec<Subset<Self>>>
+    where
+        Self: Clone,
+    {
+        let sum: f32 = fractions.iter().sum();
+        if fractions.iter().any(|f| !f.is_finite() || *f < 0.) || (sum - 1.).abs() > 1e-4 {
+            bail!("random_split fractions {fractions:?} must be positive and sum to 1!");
+        }
+        let len = self.len();
+        let mut sizes: Vec<usize> = fractions
+            .iter()
+            .map(|f| (*f as f64 * len as f64).floor() as usize)
+            .collect();
+        let remainder = len - sizes.iter().sum::<usize>().min(len);
+        for size in sizes.iter_mut().take(remainder) {
+            *size += 1;
+        }
+        let mut indices: Vec<usize> = (0..len).collect();
+        indices.shuffle(&mut StdRng::seed_from_u64(seed));
+        let mut indices = indices.into_iter();
+        sizes
+            .into_iter()
+            .map(|size| self.clone().subset(indices.by_ref().take(size).collect()))
+            .collect()
+    }
+}
+
+impl<T: Dataset + ?Sized> Dataset for &T {
+    type Item = T::Item;
+    fn len(&self) -> usize {
+        (**self).len()
+    }
+    fn get(&self, index: usize) -> Result<Self::Item> {
+        (**self).get(index)
+    }
+}
+
+impl<T: Clone> Dataset for [T] {
+    type Item = T;
+    fn len(&self) -> usize {
+        <[T]>::len(self)
+    }
+    fn get(&self, index: usize) -> Result<T> {
+        if let Some(item) = <[T]>::get(self, index) {
+            Ok(item.clone())
+        } else {
+            bail!(
+                "Dataset index {index} out of bounds for dataset with len {}!",
+                <[T]>::len(self)
+            );
+        }
+    }
+}
+
+impl<T: Clone> Dataset for Vec<T> {
+    type Item = T;
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+    fn get(&self, index: usize) -> Result<T> {
+        Dataset::get(self.as_slice(), index)
+    }
+}
+
+/// Splits `n_samples` indices into `k` folds for cross validation.
+///
+/// Returns `k` pairs of (train_indices, val_indices). The indices are shuffled with `seed`, and
+/// the validation folds partition the indices. When `k` does not divide `n_samples` evenly, the
+/// first folds have 1 more index. The train indices of each fold are the indices of the other
+/// folds.
+///
+/// See [`Dataset::subset()`].
+///
+/// # Errors
+/// `k` is less than 2 or greater than `n_samples`.
+///
+/// # Example
+///```
+/// # use autograph::dataset::{kfold_split, Dataset};
+/// # fn main() -> anyhow::Result<()> {
+/// let dataset: Vec<u32> = (0..10).collect();
+/// for (train_indices, val_indices) in kfold_split(dataset.len(), 5, 0)? {
+///     let train = (&dataset).subset(train_indices)?;
+///     let val = (&dataset).subset(val_indices)?;
+///     assert_eq!(train.len(), 8);
+///     assert_eq!(val.len(), 2);
+/// }
+/// # Ok(())
+/// # }
+///```
+pub fn kfold_split(n_samples: usize, k: usize, seed: u64) -> Result<Vec<(Vec<usize>, Vec<usize>)>> {
+    if k < 2 || k > n_samples {
+        bail!("kfold_split k = {k} must be at least 2 and at most n_samples = {n_samples}!");
+    }
+    let mut indices: Vec<usize> = (0..n_samples).collect();
+    indices.shuffle(&mut StdRng::seed_from_u64(seed));
+    let fold_size = n_samples / k;
+    let remainder = n_samples % k;
+    let mut start = 0;
+    let folds = (0..k)
+        .map(|i| {
+            let end = start + fold_size + (i < remainder) as usize;
+            let train_indices = indices[..start]
+                .iter()
+                .chain(&indices[end..])
+                .copied()
+                .collect();
+            let val_indices = indices[start..end].to_vec();
+            start = end;
+            (train_indices, val_indices)
+        })
+        .collect();
+    Ok(folds)
+}
+
+/// Pads `sequences` to the same length and stacks them.
+///
+/// Stacks sequences with shapes \[L_i, ...\] into a tensor with shape \[B, L_max, ...\], filled
+/// with `pad_value` after the end of each sequence. Returns the padded tensor and the length of
+/// each sequence. Use [`sequence_mask()`] to create a mask from the lengths.
+///
+/// Computed on the host, the output is on the device of the sequences.
+///
+/// # Errors
+/// - `sequences` is empty or the sequences are 0 dimensional.
+/// - The sequences are on different devices or their trailing dimensions differ.
+/// - See [`TensorBase::to_device()`].
+///
+/// # Example
+///```
+/// # use autograph::{dataset::{pad_sequence, sequence_mask}, tensor::Tensor, krnl::device::Device};
+/// # fn main() -> anyhow::Result<()> {
+/// let sequences = [Tensor::from(vec![1u32, 2, 3]), Tensor::from(vec![4])];
+/// let (x, lengths) = pad_sequence(&sequences, 0)?;
+/// assert_eq!(x.into_array()?.into_raw_vec(), [1, 2, 3, 4, 0, 0]);
+/// assert_eq!(lengths, [3, 1]);
+/// let mask = sequence_mask(&lengths, Device::host())?;
+/// assert_eq!(mask.into_array()?.into_raw_vec(), [1, 1, 1, 1, 0, 0]);
+/// # Ok(())
+/// # }
+///```
+pub fn pad_sequence<T: Scalar, S: Data<Elem = T>, D: Dimension>(
+    sequences: &[TensorBase<S, D>],
+    pad_value: T,
+) -> Result<(Tensor<T, D::Larger>, Vec<usize>)> {
+    let Some(first) = sequences.first() else {
+        bail!("pad_sequence sequences is empty!");
+    };
+    if first.ndim() == 0 {
+        bail!("pad_sequence expected sequences with at least 1 dimension!");
+    }
+    let device = first.device();
+    let lengths: Vec<usize> = sequences.iter().map(|x| x.shape()[0]).collect();
+    let max_len = lengths.iter().copied().max().unwrap();
+    let mut dim = D::Larger::zeros(first.ndim() + 1);
+    dim[0] = sequences.len();
+    dim[1] = max_len;
+    dim.slice_mut()[2..].copy_from_slice(&first.shape()[1..]);
+    let mut output = Array::from_elem(dim, pad_value);
+    for (mut y, x) in output.outer_iter_mut().zip(sequences) {
+        if x.device() != device {
+            bail!("pad_sequence expected {device:?}, found {:?}!", x.device());
+        }
+        if x.shape()[1..] != first.shape()[1..] {
+            bail!(
+                "pad_sequence expected shape [_, {:?}], found {:?}!",
+                &first.shape()[1..],
+                x.shape()
+            );
+        }
+        let x: CowArray<T, D> = if let Some(x) = x.as_array() {
+            x.into()
+        } else {
+            x.to_device(Device::host())?.into_array()?.into()
+        };
+        y.slice_axis_mut(Axis(0), Slice::from(0..x.shape()[0]))
+            .assign(&x);
+    }
+    Tensor::from(output)
+        .into_device(device)
+        .map(|output| (output, lengths))
+}
+
+/// Creates a mask from the `lengths` of sequences.
+///
+/// Returns a mask with shape \[B, L_max\] that is 1 for the elements of each sequence and 0 for
+/// the padding.
+///
+/// See [`pad_sequence()`].
+///
+/// # Errors
+/// See [`TensorBase::into_device()`].
+pub fn sequence_mask(lengths: &[usize], device: Device) -> Result<Tensor2<u8>> {
+    let max_len = lengths.iter().copied().max().unwrap_or_default();
+    let mask = Array2::from_shape_fn([lengths.len(), max_len], |(i, j)| (j < lengths[i]) as u8);
+    Tensor::from(mask).into_device(device)
+}
+
+/// Data loader.
+///
+/// Iterates over a [`Dataset`] of (input, target) tensors in batches. The items of each batch
+/// are stacked and transferred to the device.
+///
+/// # Example
+///```
+/// # use autograph::{dataset::DataLoader, tensor::ArcTensor, ndarray::arr0};
+/// # fn main() -> anyhow::Result<()> {
+/// let dataset: Vec<_> = (0..10u32)
+///     .map(|i| (ArcTensor::from(vec![i as f32; 2]), ArcTensor::from(arr0(i))))
+///     .collect();
+/// let mut loader = DataLoader::new(dataset, 4)?.shuffle(true).seed(0);
+/// assert_eq!(loader.num_batches(), 3);
+/// for batch in loader.iter() {
+///     let (x, t) = batch?;
+///     assert_eq!(x.shape()[1], 2);
+///     assert_eq!(x.shape()[0], t.shape()[0]);
+/// }
+/// # Ok(())
+/// # }
+///```
+#[derive(Debug)]
+pub struct DataLoader<D> {
+    dataset: D,
+    batch_size: usize,
+    shuffle: bool,
+    drop_last: bool,
+    device: Device,
+    rng: StdRng,
+}
+
+impl<D: Dataset> DataLoader<D> {
+    /// Creates a new data loader with `batch_size`.
+    ///
+    /// Defaults to not shuffling, keeping the last batch, and the host.
+    ///
+    /// # Errors
+    /// `batch_size` is 0.
+    pub fn new(dataset: D, batch_size: usize) -> Result<Self> {
+        if batch_size == 0 {
+            bail!("DataLoader batch_size must be positive!");
+        }
+        Ok(Self {
+            dataset,
+            batch_size,
+            shuffle: false,
+            drop_last: false,
+            device: Device::host(),
+            rng: StdRng::from_entropy(),
+        })
+    }
+    /// Shuffles the items each epoch.
+    pub fn shuffle(self, shuffle: bool) -> Self {
+        Self { shuffle, ..self }
+    }
+    /// Drops the last batch if it is smaller than the batch size.
+    pub fn drop_last(self, drop_last: bool) -> Self {
+        Self { drop_last, ..self }
+    }
+    /// Transfers the batches to `device`.
+    pub fn device(self, device: Device) -> Self {
+        Self { device, ..self }
+    }
+    /// Seeds the random number generator used for shuffling. Defaults to a random seed.
+    pub fn seed(self, seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+            ..self
+        }
+    }
+    /// Borrows the dataset.
+    pub fn dataset(&self) -> &D {
+        &self.dataset
+    }
+    /// The batch size.
+    pub fn batch_size(&self) -> usize {
+        self.batch_size
+    }
+    /// The number of batches per epoch.
+    pub fn num_batches(&self) -> usize {
+        let len = self.dataset.len();
+        if self.drop_last {
+            len / self.batch_size
+        } else {
+            (len + self.batch_size - 1) / self.batch_size
+        }
+    }
+}
+
+impl<T1, S1, D1, T2, S2, D2, D> DataLoader<D>
+where
+    T1: Scalar,
+    S1: Data<Elem = T1>,
+    D1: Dimension,
+    T2: Scalar,
+    S2: Data<Elem = T2>,
+    D2: Dimension,
+    D: Dataset<Item = (TensorBase<S1, D1>, TensorBase<S2, D2>)>,
+{
+    /// Iterates over the batches of an epoch.
+    ///
+    /// Yields (input, target) batches with the batch as the first axis, on the device. When
+    /// shuffling, the items are shuffled each epoch.
+    ///
+    /// # Errors
+    /// See [`Dataset::get()`], [`stack()`] and [`TensorBase::into_device()`].
+    #[allow(clippy::type_complexity)]
+    pub fn iter(
+        &mut self,
+    ) -> impl Iterator<Item = Result<(Tensor<T1, D1::Larger>, Tensor<T2, D2::Larger>)>> + '_ {
+        let mut indices: Vec<usize> = (0..self.dataset.len()).collect();
+        if self.shuffle {
+            indices.shuffle(&mut self.rng);
+        }
+        let mut indices = indices.into_iter();
+        let batch_size = self.batch_size;
+        let drop_last = self.drop_last;
+        std::iter::from_fn(move || {
+            let batch: Vec<usize> = indices.by_ref().take(batch_size).collect();
+            if batch.is_empty() || (drop_last && batch.len() < batch_size) {
+                None
+            } else {
+                Some(self.collate(&batch))
+            }
+        })
+    }
+    #[allow(clippy::type_complexity)]
+    fn collate(
+        &self,
+        indices: &[usize],
+    ) -> Result<(Tensor<T1, D1::Larger>, Tensor<T2, D2::Larger>)> {
+        let mut inputs = Vec::with_capacity(indices.len());
+        let mut targets = Vec::with_capacity(indices.len());
+        for index in indices.iter().copied() {
+            let (input, target) = self.dataset.get(index)?;
+            inputs.push(input);
+            targets.push(target);
+        }
+        let inputs: Vec<_> = inputs.iter().map(TensorBase::view).collect();
+        let targets: Vec<_> = targets.iter().map(TensorBase::view).collect();
+        let inputs = stack(Axis(0), &inputs)?.into_device(self.device.clone())?;
+        let targets = stack(Axis(0), &targets)?.into_device(self.device.clone())?;
+        Ok((inputs, targets))
+    }
+}
+
+/// Subset.
+///
+/// See [`Dataset::subset()`].
+#[derive(Debug, Clone)]
+pub struct Subset<D> {
+    dataset: D,
+    indices: Vec<usize>,
+}
+
+impl<D> Subset<D> {
+    /// The indices into the dataset.
+    pub fn indices(&self) -> &[usize] {
+        &self.indices
+    }
+    /// Borrows the dataset.
+    pub fn dataset(&self) -> &D {
+        &self.dataset
+    }
+}
+
+impl<D: Dataset> Dataset for Subset<D> {
+    type Item = D::Item;
+    fn len(&self) -> usize {
+        self.indices.len()
+    }
+    fn get(&self, index: usize) -> Result<Self::Item> {
+        let Some(index) = self.indices.as_slice().get(index).copied() else {
+            bail!(
+                "Subset index {index} out of bounds for subset with len {}!",
+                self.indices.len()
+            );
+        };
+        self.dataset.get(index)
+    }
+}
+
+/// Concat.
+///
+/// See [`Dataset::concat()`].
+#[derive(Debug, Clone)]
+pub struct Concat<D1, D2> {
+    first: D1,
+    second: D2,
+}
+
+impl<D1: Dataset, D2: Dataset<Item = D1::Item>> Dataset for Concat<D1, D2> {
+    type Item = D1::Item;
+    fn len(&self) -> usize {
+        self.first.len() + self.second.len()
+    }
+    fn get(&self, index: usize) -> Result<Self::Item> {
+        let len = self.first.len();
+        if index < len {
+            self.first.get(index)
+        } else {
+            self.second.get(index - len)
+        }
+    }
+}
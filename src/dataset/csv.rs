@@ -0,0 +1,180 @@
+use crate::tensor::{Tensor, Tensor1, Tensor2};
+use anyhow::{bail, Result};
+use ndarray::Array2;
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Read},
+    path::Path,
+};
+
+/// Csv builder.
+pub mod builders {
+    use super::{Csv, Result};
+    use std::{io::Read, path::Path};
+
+    /// Csv builder.
+    #[derive(Debug)]
+    pub struct CsvBuilder {
+        pub(super) label_column: Option<usize>,
+        pub(super) has_header: bool,
+        pub(super) delimiter: char,
+    }
+
+    impl Default for CsvBuilder {
+        fn default() -> Self {
+            Self {
+                label_column: None,
+                has_header: false,
+                delimiter: ',',
+            }
+        }
+    }
+
+    impl CsvBuilder {
+        /// The index of the label column. Defaults to no labels.
+        ///
+        /// The remaining columns are the features.
+        pub fn label_column(self, label_column: usize) -> Self {
+            Self {
+                label_column: Some(label_column),
+                ..self
+            }
+        }
+        /// Whether the first row is a header. Defaults to false.
+        pub fn has_header(self, has_header: bool) -> Self {
+            Self { has_header, ..self }
+        }
+        /// The field delimiter. Defaults to ','.
+        pub fn delimiter(self, delimiter: char) -> Self {
+            Self { delimiter, ..self }
+        }
+        /// Loads the dataset from `reader`.
+        ///
+        /// # Errors
+        /// - Reading failed.
+        /// - A field could not be parsed as a number, or a row has a different number of columns.
+        /// - The label column is out of bounds.
+        #[allow(clippy::wrong_self_convention)]
+        pub fn from_reader<R: Read>(self, reader: R) -> Result<Csv> {
+            Csv::from_reader(self, reader)
+        }
+        /// Loads the dataset from the file at `path`.
+        ///
+        /// # Errors
+        /// - The file could not be opened.
+        /// - See [`.from_reader()`](CsvBuilder::from_reader).
+        #[allow(clippy::wrong_self_convention)]
+        pub fn from_path(self, path: impl AsRef<Path>) -> Result<Csv> {
+            Csv::from_path(self, path.as_ref())
+        }
+    }
+}
+use builders::CsvBuilder;
+
+/// A tabular dataset loaded from CSV.
+///
+/// Fields are parsed as numbers. Empty lines are skipped.
+#[derive(Debug)]
+pub struct Csv {
+    /// The header, if any.
+    pub header: Option<Vec<String>>,
+    /// The features.
+    ///
+    /// Shape = \[rows, columns\], excluding the label column.
+    pub features: Tensor2<f32>,
+    /// The labels, if a label column was specified.
+    ///
+    /// Shape = \[rows\].
+    pub labels: Option<Tensor1<f32>>,
+}
+
+impl Csv {
+    /// Returns a [`CsvBuilder`] used to specify options.
+    /**
+    ```
+    # use autograph::{anyhow::Result, dataset::csv::Csv};
+    # fn main() -> Result<()> {
+    let data = "x,y,class\n1.0,2.0,0\n3.0,4.0,1\n";
+    let csv = Csv::builder()
+        .has_header(true)
+        .label_column(2)
+        .from_reader(data.as_bytes())?;
+    assert_eq!(csv.features.shape(), [2, 2]);
+    assert_eq!(csv.labels.unwrap().into_array()?.to_vec(), [0., 1.]);
+    # Ok(())
+    # }
+    ```
+    */
+    pub fn builder() -> CsvBuilder {
+        CsvBuilder::default()
+    }
+    fn from_path(builder: CsvBuilder, path: &Path) -> Result<Self> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) => bail!("Csv failed to open {path:?}: {e}"),
+        };
+        Self::from_reader(builder, file)
+    }
+    fn from_reader<R: Read>(builder: CsvBuilder, reader: R) -> Result<Self> {
+        let CsvBuilder {
+            label_column,
+            has_header,
+            delimiter,
+        } = builder;
+        let mut header = None;
+        let mut columns = None;
+        let mut rows = 0;
+        let mut features = Vec::new();
+        let mut labels = Vec::new();
+        for (index, line) in BufReader::new(reader).lines().enumerate() {
+            let line = line?;
+            let row = index + 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.split(delimiter).map(str::trim).collect();
+            if let Some(columns) = columns {
+                if fields.len() != columns {
+                    bail!(
+                        "Csv row {row} expected {columns} columns, found {}!",
+                        fields.len()
+                    );
+                }
+            } else {
+                if let Some(label_column) = label_column {
+                    if label_column >= fields.len() {
+                        bail!(
+                            "Csv label_column {label_column} out of bounds for {} columns!",
+                            fields.len()
+                        );
+                    }
+                }
+                columns.replace(fields.len());
+                if has_header {
+                    header.replace(fields.iter().map(|x| x.to_string()).collect());
+                    continue;
+                }
+            }
+            for (column, field) in fields.iter().enumerate() {
+                let Ok(x) = field.parse::<f32>() else {
+                    bail!("Csv row {row} column {column} failed to parse {field:?} as a number!");
+                };
+                if Some(column) == label_column {
+                    labels.push(x);
+                } else {
+                    features.push(x);
+                }
+            }
+            rows += 1;
+        }
+        let columns = columns
+            .unwrap_or_default()
+            .saturating_sub(label_column.is_some() as usize);
+        let features = Array2::from_shape_vec([rows, columns], features).unwrap();
+        Ok(Self {
+            header,
+            features: Tensor::from(features),
+            labels: label_column.map(|_| Tensor::from(labels)),
+        })
+    }
+}
@@ -0,0 +1,182 @@
+use super::Dataset;
+use crate::tensor::{Tensor, Tensor3};
+use anyhow::{bail, Result};
+use image::imageops::FilterType;
+use ndarray::Array3;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// ImageFolder builder.
+pub mod builders {
+    use super::{ImageFolder, Result};
+    use std::path::PathBuf;
+
+    /// ImageFolder builder.
+    #[derive(Debug)]
+    pub struct ImageFolderBuilder {
+        pub(super) path: PathBuf,
+        pub(super) resize: Option<[u32; 2]>,
+    }
+
+    impl ImageFolderBuilder {
+        /// Resizes the images to \[height, width\]. Defaults to no resizing.
+        ///
+        /// Batching requires that all images have the same shape.
+        pub fn resize(self, resize: [u32; 2]) -> Self {
+            Self {
+                resize: Some(resize),
+                ..self
+            }
+        }
+        /// Builds the dataset.
+        ///
+        /// # Errors
+        /// - The directory could not be read.
+        /// - There are no class folders.
+        pub fn build(self) -> Result<ImageFolder> {
+            ImageFolder::build(self)
+        }
+    }
+}
+use builders::ImageFolderBuilder;
+
+/// A dataset of images in class folders.
+///
+/// Each subfolder of the root is a class, with images stored as JPEG or PNG files. Classes are
+/// sorted by name, such that the label of each image is the index of its folder. Images are
+/// decoded lazily in [`.get()`](Dataset::get), as RGB with shape \[3, H, W\].
+///
+/// ```text
+/// root/
+///     cat/
+///         1.png
+///     dog/
+///         1.jpg
+/// ```
+#[derive(Debug, Clone)]
+pub struct ImageFolder {
+    classes: Vec<String>,
+    samples: Vec<(PathBuf, u32)>,
+    resize: Option<[u32; 2]>,
+}
+
+impl ImageFolder {
+    /// Returns an [`ImageFolderBuilder`] for the folder at `path`.
+    /**
+    ```no_run
+    # use autograph::{anyhow::Result, dataset::{image_folder::ImageFolder, Dataset}};
+    # fn main() -> Result<()> {
+    let dataset = ImageFolder::builder("data/train").resize([32, 32]).build()?;
+    let (image, class) = dataset.get(0)?;
+    assert_eq!(image.shape(), [3, 32, 32]);
+    println!("{}", dataset.classes()[class as usize]);
+    # Ok(())
+    # }
+    ```
+    */
+    pub fn builder(path: impl Into<PathBuf>) -> ImageFolderBuilder {
+        ImageFolderBuilder {
+            path: path.into(),
+            resize: None,
+        }
+    }
+    fn build(builder: ImageFolderBuilder) -> Result<Self> {
+        let ImageFolderBuilder { path, resize } = builder;
+        let mut class_paths = Vec::new();
+        for entry in read_dir(&path)? {
+            if entry.is_dir() {
+                class_paths.push(entry);
+            }
+        }
+        class_paths.sort();
+        if class_paths.is_empty() {
+            bail!("ImageFolder found no class folders in {path:?}!");
+        }
+        let mut classes = Vec::with_capacity(class_paths.len());
+        let mut samples = Vec::new();
+        for (class, class_path) in class_paths.iter().enumerate() {
+            let mut images: Vec<PathBuf> = read_dir(class_path)?
+                .into_iter()
+                .filter(|path| path.is_file() && is_image(path))
+                .collect();
+            images.sort();
+            samples.extend(images.into_iter().map(|path| (path, class as u32)));
+            classes.push(
+                class_path
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .into_owned(),
+            );
+        }
+        Ok(Self {
+            classes,
+            samples,
+            resize,
+        })
+    }
+    /// The class names, sorted.
+    pub fn classes(&self) -> &[String] {
+        &self.classes
+    }
+    /// The paths of the images and their classes.
+    pub fn samples(&self) -> &[(PathBuf, u32)] {
+        &self.samples
+    }
+    fn load(&self, path: &Path) -> Result<Tensor3<u8>> {
+        let image = match image::open(path) {
+            Ok(image) => image,
+            Err(e) => bail!("ImageFolder failed to decode {path:?}: {e}"),
+        };
+        let image = if let Some([height, width]) = self.resize {
+            image.resize_exact(width, height, FilterType::Triangle)
+        } else {
+            image
+        };
+        let image = image.into_rgb8();
+        let (width, height) = image.dimensions();
+        let array = Array3::from_shape_vec([height as usize, width as usize, 3], image.into_raw())
+            .unwrap()
+            .permuted_axes([2, 0, 1]);
+        Ok(Tensor::from(array.as_standard_layout().into_owned()))
+    }
+}
+
+impl Dataset for ImageFolder {
+    type Item = (Tensor3<u8>, u32);
+    fn len(&self) -> usize {
+        self.samples.len()
+    }
+    fn get(&self, index: usize) -> Result<Self::Item> {
+        let Some((path, class)) = self.samples.as_slice().get(index) else {
+            bail!(
+                "ImageFolder index {index} out of bounds for dataset with len {}!",
+                self.samples.len()
+            );
+        };
+        Ok((self.load(path)?, *class))
+    }
+}
+
+fn read_dir(path: &Path) -> Result<Vec<PathBuf>> {
+    let entries = match fs::read_dir(path) {
+        Ok(entries) => entries,
+        Err(e) => bail!("ImageFolder failed to read {path:?}: {e}"),
+    };
+    let paths = entries
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<_>>()?;
+    Ok(paths)
+}
+
+fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .map_or(false, |extension| {
+            ["jpg", "jpeg", "png"]
+                .iter()
+                .any(|x| extension.eq_ignore_ascii_case(x))
+        })
+}
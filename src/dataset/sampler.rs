@@ -0,0 +1,117 @@
+use anyhow::{bail, Result};
+use rand::{
+    distributions::{Distribution, WeightedIndex},
+    rngs::StdRng,
+    seq::index::sample_weighted,
+    SeedableRng,
+};
+
+/// Weighted random sampler.
+///
+/// Samples indices with probability proportional to `weights`, with or without replacement.
+/// For class imbalanced datasets, the weight of each sample can be the inverse of the frequency
+/// of its class, such that classes are sampled equally.
+///
+/// # Example
+///```
+/// # use autograph::dataset::sampler::WeightedRandomSampler;
+/// # fn main() -> anyhow::Result<()> {
+/// let classes = [0, 0, 0, 1];
+/// let weights = classes.iter().map(|c| if *c == 0 { 1. / 3. } else { 1. }).collect();
+/// let mut sampler = WeightedRandomSampler::new(weights, 4, true)?.seed(0);
+/// for batch in sampler.batches(2) {
+///     assert!(batch.len() == 2);
+/// }
+/// # Ok(())
+/// # }
+///```
+#[derive(Debug, Clone)]
+pub struct WeightedRandomSampler {
+    weights: Vec<f32>,
+    num_samples: usize,
+    replacement: bool,
+    rng: StdRng,
+}
+
+impl WeightedRandomSampler {
+    /// Creates a new sampler that samples `num_samples` indices per epoch.
+    ///
+    /// # Errors
+    /// - `weights` are empty, negative, not finite, or sum to 0.
+    /// - Without `replacement`, `num_samples` is greater than the number of positive weights.
+    pub fn new(weights: Vec<f32>, num_samples: usize, replacement: bool) -> Result<Self> {
+        if weights.iter().any(|w| !w.is_finite() || *w < 0.) {
+            bail!("WeightedRandomSampler weights must be finite and not negative!");
+        }
+        let positive = weights.iter().filter(|w| **w > 0.).count();
+        if positive == 0 {
+            bail!("WeightedRandomSampler expected at least 1 positive weight!");
+        }
+        if !replacement && num_samples > positive {
+            bail!("WeightedRandomSampler num_samples {num_samples} without replacement is greater than the number of positive weights {positive}!");
+        }
+        Ok(Self {
+            weights,
+            num_samples,
+            replacement,
+            rng: StdRng::from_entropy(),
+        })
+    }
+    /// Seeds the random number generator. Defaults to a random seed.
+    pub fn seed(self, seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+            ..self
+        }
+    }
+    /// The weights.
+    pub fn weights(&self) -> &[f32] {
+        &self.weights
+    }
+    /// The number of samples per epoch.
+    pub fn num_samples(&self) -> usize {
+        self.num_samples
+    }
+    /// Whether indices are sampled with replacement.
+    pub fn replacement(&self) -> bool {
+        self.replacement
+    }
+    /// Samples `num_samples` indices.
+    pub fn sample(&mut self) -> Vec<usize> {
+        if self.replacement {
+            let dist = WeightedIndex::new(&self.weights).unwrap();
+            dist.sample_iter(&mut self.rng)
+                .take(self.num_samples)
+                .collect()
+        } else {
+            let weights = &self.weights;
+            sample_weighted(
+                &mut self.rng,
+                weights.len(),
+                |i| weights[i],
+                self.num_samples,
+            )
+            .unwrap()
+            .into_vec()
+        }
+    }
+    /// Samples `num_samples` indices and splits them into batches of `batch_size`.
+    ///
+    /// The last batch may be smaller than `batch_size`.
+    ///
+    /// # Panics
+    /// Panics if `batch_size` is 0.
+    pub fn batches(&mut self, batch_size: usize) -> impl Iterator<Item = Vec<usize>> {
+        assert!(batch_size > 0, "batch_size must be positive!");
+        let indices = self.sample();
+        let mut iter = indices.into_iter();
+        std::iter::from_fn(move || {
+            let batch: Vec<usize> = iter.by_ref().take(batch_size).collect();
+            if batch.is_empty() {
+                None
+            } else {
+                Some(batch)
+            }
+        })
+    }
+}
@@ -0,0 +1,500 @@
+#[cfg(feature = "device")]
+use crate::tensor::{ScalarTensorView, ScalarTensorViewMut, Tensor1};
+use crate::tensor::{Tensor, Tensor4, TensorView4};
+use anyhow::{bail, Result};
+#[cfg(feature = "device")]
+use dry::macro_for;
+#[cfg(feature = "device")]
+use half::{bf16, f16};
+#[cfg(feature = "device")]
+use krnl::macros::module;
+use krnl::scalar::Scalar;
+use ndarray::Array;
+use num_traits::Float;
+#[cfg(feature = "device")]
+use num_traits::ToPrimitive;
+#[cfg(feature = "device")]
+use paste::paste;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// Random horizontal flip.
+///
+/// Flips the width axis of each sample of \[N, C, H, W\] images with probability `p`.
+///
+/// The flips are sampled on the host and applied to the whole batch in one kernel on the device
+/// of the input.
+///
+/// # Example
+///```
+/// # use autograph::{dataset::transforms::RandomHorizontalFlip, tensor::Tensor};
+/// # fn main() -> anyhow::Result<()> {
+/// let x = Tensor::from(vec![1u8, 2, 3]).into_shape([1, 1, 1, 3])?;
+/// let mut flip = RandomHorizontalFlip::new(1.)?;
+/// let y = flip.apply(x.view())?;
+/// assert_eq!(y.into_array()?.into_raw_vec(), [3, 2, 1]);
+/// # Ok(())
+/// # }
+///```
+#[derive(Debug, Clone)]
+pub struct RandomHorizontalFlip {
+    p: f32,
+    rng: StdRng,
+}
+
+impl RandomHorizontalFlip {
+    /// Creates a new transform that flips samples with probability `p`.
+    ///
+    /// # Errors
+    /// `p` is not in the range 0 ..= 1.
+    pub fn new(p: f32) -> Result<Self> {
+        if !(0. ..=1.).contains(&p) {
+            bail!("RandomHorizontalFlip p {p} must be in 0 ..= 1!");
+        }
+        Ok(Self {
+            p,
+            rng: StdRng::from_entropy(),
+        })
+    }
+    /// Seeds the random number generator. Defaults to a random seed.
+    pub fn seed(self, seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+            ..self
+        }
+    }
+    /// The probability of flipping a sample.
+    pub fn p(&self) -> f32 {
+        self.p
+    }
+    /// Applies the transform to `input`.
+    ///
+    /// # Errors
+    /// The operation could not be executed on the device.
+    pub fn apply<T: Scalar>(&mut self, input: TensorView4<T>) -> Result<Tensor4<T>> {
+        let flips: Vec<u32> = (0..input.shape()[0])
+            .map(|_| (self.rng.gen::<f32>() < self.p) as u32)
+            .collect();
+        if let Some(x) = input.as_array() {
+            let width = x.shape()[3];
+            let output = Array::from_shape_fn(x.raw_dim(), |(n, c, h, w)| {
+                x[(n, c, h, kernels::flip_index(w, width, flips[n] != 0))]
+            });
+            return Ok(output.into());
+        }
+        #[cfg(not(feature = "device"))]
+        {
+            unreachable!()
+        }
+        #[cfg(feature = "device")]
+        {
+            let device = input.device();
+            let mut output = unsafe { Tensor::uninit(device.clone(), input.raw_dim())? };
+            if output.is_empty() {
+                return Ok(output);
+            }
+            let flips = Tensor1::from(flips).into_device(device.clone())?;
+            let [_, channels, height, width] = dims_u32(input.shape());
+            let [sx0, sx1, sx2, sx3] = strides_i32(input.strides());
+            macro_for!($T in [u8, i8, u16, i16, f16, bf16, u32, i32, f32, u64, i64, f64] {
+                if T::SCALAR_TYPE == $T::SCALAR_TYPE {
+                    let x = ScalarTensorView::from(input.view())
+                        .try_into_tensor_view::<$T>()
+                        .unwrap();
+                    let (x, offset_x) = x.as_raw_slice_offset();
+                    let mut y = ScalarTensorViewMut::from(output.view_mut())
+                        .try_into_tensor_view_mut::<$T>()
+                        .unwrap();
+                    paste! {
+                        kernels::[<horizontal_flip_ $T>]::builder()?
+                            .build(device)?
+                            .dispatch(
+                                channels,
+                                height,
+                                width,
+                                flips.as_slice().unwrap(),
+                                x,
+                                sx0,
+                                sx1,
+                                sx2,
+                                sx3,
+                                offset_x.to_u32().unwrap(),
+                                y.as_slice_mut().unwrap(),
+                            )?;
+                    }
+                    return Ok(output);
+                }
+            });
+            unreachable!()
+        }
+    }
+}
+
+/// Normalize.
+///
+/// Normalizes each channel of \[N, C, H, W\] images with `(x - mean) / std`. Typically applied
+/// after scaling the images to \[0, 1\], see
+/// [`.scaled_cast()`](crate::tensor::TensorBase::scaled_cast).
+///
+/// Executed on the device of the input, without transferring to the host.
+///
+/// # Example
+///```
+/// # use autograph::{dataset::transforms::Normalize, tensor::Tensor};
+/// # fn main() -> anyhow::Result<()> {
+/// let x = Tensor::from(vec![0f32, 1., 0., 1.]).into_shape([1, 2, 1, 2])?;
+/// let normalize = Normalize::new(vec![0.5, 0.], vec![0.5, 1.])?;
+/// let y = normalize.apply(x.view())?;
+/// assert_eq!(y.into_array()?.into_raw_vec(), [-1., 1., 0., 1.]);
+/// # Ok(())
+/// # }
+///```
+#[derive(Debug, Clone)]
+pub struct Normalize {
+    mean: Vec<f32>,
+    std: Vec<f32>,
+}
+
+impl Normalize {
+    /// Creates a new transform with the `mean` and `std` of each channel.
+    ///
+    /// # Errors
+    /// - `mean` and `std` are empty or have different lengths.
+    /// - `std` is not positive and finite.
+    pub fn new(mean: Vec<f32>, std: Vec<f32>) -> Result<Self> {
+        if mean.is_empty() || mean.len() != std.len() {
+            bail!(
+                "Normalize expected mean and std with the same number of channels, found {} and {}!",
+                mean.len(),
+                std.len()
+            );
+        }
+        if std.iter().any(|x| !x.is_finite() || *x <= 0.) {
+            bail!("Normalize std {std:?} must be positive!");
+        }
+        Ok(Self { mean, std })
+    }
+    /// The mean of each channel.
+    pub fn mean(&self) -> &[f32] {
+        &self.mean
+    }
+    /// The standard deviation of each channel.
+    pub fn std(&self) -> &[f32] {
+        &self.std
+    }
+    /// Applies the transform to `input`.
+    ///
+    /// # Errors
+    /// - The number of channels of `input` does not match.
+    /// - The operation could not be executed on the device.
+    pub fn apply<T: Scalar + Float>(&self, input: TensorView4<T>) -> Result<Tensor4<T>> {
+        let channels = self.mean.len();
+        if input.shape()[1] != channels {
+            bail!(
+                "Normalize expected input with {channels} channels, found shape {:?}!",
+                input.shape()
+            );
+        }
+        let device = input.device();
+        let neg_mean = Array::from_iter(self.mean.iter().map(|x| (-x).cast::<T>()))
+            .into_shape([1, channels, 1, 1])
+            .unwrap();
+        let neg_mean = Tensor::from(neg_mean).into_device(device.clone())?;
+        let std = Array::from_iter(self.std.iter().map(|x| x.cast::<T>()))
+            .into_shape([1, channels, 1, 1])
+            .unwrap();
+        let std = Tensor::from(std).into_device(device)?;
+        input.add(&neg_mean)?.div(&std)
+    }
+}
+
+/// Padding mode.
+///
+/// See [`RandomCrop`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum PaddingMode {
+    /// Pads with zeros.
+    Zero,
+    /// Pads with the reflection of the image, excluding the edge.
+    ///
+    /// For example, \[1, 2, 3\] with padding 2 is \[3, 2, 1, 2, 3, 2, 1\].
+    Reflect,
+}
+
+/// Random crop.
+///
+/// Pads the height and width of \[N, C, H, W\] images and crops each sample to \[out_h, out_w\]
+/// at a random offset.
+///
+/// The offsets are sampled on the host and the whole batch is cropped in one kernel on the device
+/// of the input. The padding is computed while cropping, without a padded copy of the input.
+///
+/// # Example
+///```
+/// # use autograph::{dataset::transforms::RandomCrop, krnl::device::Device, tensor::Tensor};
+/// # fn main() -> anyhow::Result<()> {
+/// let x = Tensor::<f32, _>::zeros(Device::host(), [2, 3, 32, 32])?;
+/// let mut crop = RandomCrop::new([32, 32]).padding(4);
+/// let y = crop.apply(x.view())?;
+/// assert_eq!(y.shape(), [2, 3, 32, 32]);
+/// # Ok(())
+/// # }
+///```
+#[derive(Debug, Clone)]
+pub struct RandomCrop {
+    size: [usize; 2],
+    padding: usize,
+    padding_mode: PaddingMode,
+    rng: StdRng,
+}
+
+impl RandomCrop {
+    /// Creates a new transform that crops to `size` \[out_h, out_w\].
+    pub fn new(size: [usize; 2]) -> Self {
+        Self {
+            size,
+            padding: 0,
+            padding_mode: PaddingMode::Zero,
+            rng: StdRng::from_entropy(),
+        }
+    }
+    /// Pads each side of the height and width by `padding`. Defaults to 0.
+    pub fn padding(self, padding: usize) -> Self {
+        Self { padding, ..self }
+    }
+    /// The padding mode. Defaults to [`PaddingMode::Zero`].
+    pub fn padding_mode(self, padding_mode: PaddingMode) -> Self {
+        Self {
+            padding_mode,
+            ..self
+        }
+    }
+    /// Seeds the random number generator. Defaults to a random seed.
+    pub fn seed(self, seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+            ..self
+        }
+    }
+    /// Applies the transform to `input`.
+    ///
+    /// # Errors
+    /// - The size is larger than the padded input.
+    /// - For [`PaddingMode::Reflect`], the padding is not less than the height and width.
+    /// - The operation could not be executed on the device.
+    pub fn apply<T: Scalar>(&mut self, input: TensorView4<T>) -> Result<Tensor4<T>> {
+        let [batch_size, channels, height, width]: [usize; 4] = input.shape().try_into().unwrap();
+        let padding = self.padding;
+        let [out_h, out_w] = self.size;
+        let (padded_h, padded_w) = (height + 2 * padding, width + 2 * padding);
+        if out_h > padded_h || out_w > padded_w {
+            bail!(
+                "RandomCrop size {:?} is larger than padded input [{padded_h}, {padded_w}]!",
+                self.size
+            );
+        }
+        if self.padding_mode == PaddingMode::Reflect && padding >= height.min(width) {
+            bail!(
+                "RandomCrop reflect padding {padding} must be less than the height and width of input with shape {:?}!",
+                input.shape()
+            );
+        }
+        let offsets: Vec<u32> = (0..batch_size)
+            .flat_map(|_| {
+                let top = self.rng.gen_range(0..=padded_h - out_h);
+                let left = self.rng.gen_range(0..=padded_w - out_w);
+                [top as u32, left as u32]
+            })
+            .collect();
+        let reflect = self.padding_mode == PaddingMode::Reflect;
+        if let Some(x) = input.as_array() {
+            let output =
+                Array::from_shape_fn([batch_size, channels, out_h, out_w], |(n, c, i, j)| {
+                    let (top, left) = (offsets[2 * n] as usize, offsets[2 * n + 1] as usize);
+                    let row = kernels::crop_index(top + i, height, padding, reflect);
+                    let col = kernels::crop_index(left + j, width, padding, reflect);
+                    if let (Some(row), Some(col)) = (row, col) {
+                        x[(n, c, row, col)]
+                    } else {
+                        T::zero()
+                    }
+                });
+            return Ok(output.into());
+        }
+        #[cfg(not(feature = "device"))]
+        {
+            unreachable!()
+        }
+        #[cfg(feature = "device")]
+        {
+            let device = input.device();
+            let mut output =
+                unsafe { Tensor::uninit(device.clone(), [batch_size, channels, out_h, out_w])? };
+            if output.is_empty() {
+                return Ok(output);
+            }
+            let offsets = Tensor1::from(offsets).into_device(device.clone())?;
+            let [_, channels, height, width] = dims_u32(input.shape());
+            let [out_h, out_w] = dims_u32(&self.size);
+            let [sx0, sx1, sx2, sx3] = strides_i32(input.strides());
+            macro_for!($T in [u8, i8, u16, i16, f16, bf16, u32, i32, f32, u64, i64, f64] {
+                if T::SCALAR_TYPE == $T::SCALAR_TYPE {
+                    let x = ScalarTensorView::from(input.view())
+                        .try_into_tensor_view::<$T>()
+                        .unwrap();
+                    let (x, offset_x) = x.as_raw_slice_offset();
+                    let mut y = ScalarTensorViewMut::from(output.view_mut())
+                        .try_into_tensor_view_mut::<$T>()
+                        .unwrap();
+                    paste! {
+                        kernels::[<random_crop_ $T>]::builder()?
+                            .build(device)?
+                            .dispatch(
+                                channels,
+                                height,
+                                width,
+                                out_h,
+                                out_w,
+                                padding.to_u32().unwrap(),
+                                reflect.into(),
+                                offsets.as_slice().unwrap(),
+                                x,
+                                sx0,
+                                sx1,
+                                sx2,
+                                sx3,
+                                offset_x.to_u32().unwrap(),
+                                y.as_slice_mut().unwrap(),
+                            )?;
+                    }
+                    return Ok(output);
+                }
+            });
+            unreachable!()
+        }
+    }
+}
+
+#[cfg(feature = "device")]
+fn dims_u32<const N: usize>(shape: &[usize]) -> [u32; N] {
+    std::array::from_fn(|i| shape[i].to_u32().unwrap())
+}
+
+#[cfg(feature = "device")]
+fn strides_i32(strides: &[isize]) -> [i32; 4] {
+    std::array::from_fn(|i| strides[i].to_i32().unwrap())
+}
+
+#[cfg_attr(feature = "device", module)]
+mod kernels {
+    #[cfg(any(feature = "device", target_arch = "spirv"))]
+    use dry::macro_for;
+    #[cfg(not(target_arch = "spirv"))]
+    use krnl::krnl_core;
+    #[cfg(any(feature = "device", target_arch = "spirv"))]
+    use krnl_core::macros::kernel;
+    #[cfg(target_arch = "spirv")]
+    use krnl_core::{
+        half::{bf16, f16},
+        scalar::Scalar,
+    };
+    #[cfg(any(feature = "device", target_arch = "spirv"))]
+    use paste::paste;
+
+    // The index of the input column for output column `w`.
+    pub fn flip_index(w: usize, width: usize, flip: bool) -> usize {
+        if flip {
+            width - 1 - w
+        } else {
+            w
+        }
+    }
+
+    // The index of the input for index `i` of the padded axis, or None if `i` is a zero pad.
+    pub fn crop_index(i: usize, len: usize, padding: usize, reflect: bool) -> Option<usize> {
+        if i < padding {
+            if reflect {
+                Some(padding - i)
+            } else {
+                None
+            }
+        } else if i - padding < len {
+            Some(i - padding)
+        } else if reflect {
+            Some(2 * (len - 1) + padding - i)
+        } else {
+            None
+        }
+    }
+
+    #[cfg(any(feature = "device", target_arch = "spirv"))]
+    macro_for!($T in [u8, i8, u16, i16, f16, bf16, u32, i32, f32, u64, i64, f64] {
+        paste! {
+            // x has shape [N, C, H, W] with strides sx and y is in standard layout.
+            #[allow(clippy::too_many_arguments)]
+            #[kernel]
+            pub fn [<horizontal_flip_ $T>](
+                channels: u32,
+                height: u32,
+                width: u32,
+                #[global] flips: Slice<u32>,
+                #[global] x: Slice<$T>,
+                sx0: i32,
+                sx1: i32,
+                sx2: i32,
+                sx3: i32,
+                offset_x: u32,
+                #[item] y: &mut $T,
+            ) {
+                let [channels, height, width] = [channels as usize, height as usize, width as usize];
+                let idx = kernel.item_id();
+                let n = idx / (channels * height * width);
+                let c = (idx / (height * width)) % channels;
+                let h = (idx / width) % height;
+                let w = flip_index(idx % width, width, flips[n] != 0);
+                let [n, c, h, w] = [n as i32, c as i32, h as i32, w as i32];
+                *y = x[(n * sx0 + c * sx1 + h * sx2 + w * sx3 + offset_x as i32) as usize];
+            }
+
+            // x has shape [N, C, H, W] with strides sx, y has shape [N, C, out_h, out_w] in
+            // standard layout and offsets has the (top, left) of each sample.
+            #[allow(clippy::too_many_arguments)]
+            #[kernel]
+            pub fn [<random_crop_ $T>](
+                channels: u32,
+                height: u32,
+                width: u32,
+                out_h: u32,
+                out_w: u32,
+                padding: u32,
+                reflect: u32,
+                #[global] offsets: Slice<u32>,
+                #[global] x: Slice<$T>,
+                sx0: i32,
+                sx1: i32,
+                sx2: i32,
+                sx3: i32,
+                offset_x: u32,
+                #[item] y: &mut $T,
+            ) {
+                let [channels, out_h, out_w] = [channels as usize, out_h as usize, out_w as usize];
+                let idx = kernel.item_id();
+                let n = idx / (channels * out_h * out_w);
+                let c = (idx / (out_h * out_w)) % channels;
+                let i = (idx / out_w) % out_h;
+                let j = idx % out_w;
+                let top = offsets[2 * n] as usize;
+                let left = offsets[2 * n + 1] as usize;
+                let padding = padding as usize;
+                let reflect = reflect != 0;
+                let row = crop_index(top + i, height as usize, padding, reflect);
+                let col = crop_index(left + j, width as usize, padding, reflect);
+                *y = if let (Some(row), Some(col)) = (row, col) {
+                    let [n, c, row, col] = [n as i32, c as i32, row as i32, col as i32];
+                    x[(n * sx0 + c * sx1 + row * sx2 + col * sx3 + offset_x as i32) as usize]
+                } else {
+                    $T::default()
+                };
+            }
+        }
+    });
+}
@@ -1,5 +1,11 @@
+/// Clustering.
+pub mod cluster;
 /// Criterion.
 pub mod criterion;
+/// Decomposition.
+pub mod decomposition;
+/// Linear models.
+pub mod linear_model;
 /// Neural Network.
 #[cfg(feature = "neural-network")]
 pub mod neural_network;
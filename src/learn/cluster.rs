@@ -0,0 +1,255 @@
+use crate::tensor::{ArcTensor, ArcTensor2, Tensor, Tensor1, TensorView2};
+use anyhow::{bail, Result};
+use krnl::device::Device;
+use ndarray::{linalg::Dot, Array1, Array2, ArrayView1, ArrayView2};
+use rand::{
+    distributions::{Distribution, WeightedIndex},
+    rngs::StdRng,
+    Rng, SeedableRng,
+};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// K-Means clustering.
+///
+/// Partitions data into `k` clusters, each sample belonging to the cluster with the nearest
+/// centroid. The centroids are initialized with k-means++ and fitted with Lloyd's algorithm.
+///
+/// The distances to the centroids are computed on the device of the data, with matrix
+/// multiplication. The centroids are updated on the host.
+///
+/// # Example
+///```
+/// # use autograph::{learn::cluster::KMeans, tensor::Tensor};
+/// # use ndarray::Array;
+/// # fn main() -> anyhow::Result<()> {
+/// let x = Tensor::from(Array::from_shape_vec([4, 1], vec![0f32, 1., 10., 11.])?);
+/// let mut kmeans = KMeans::new(2).seed(0);
+/// kmeans.fit(x.view())?;
+/// let labels = kmeans.predict(x.view())?.into_array()?;
+/// assert_eq!(labels[0], labels[1]);
+/// assert_ne!(labels[1], labels[2]);
+/// # Ok(())
+/// # }
+///```
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct KMeans {
+    k: usize,
+    max_iterations: usize,
+    tolerance: f32,
+    seed: Option<u64>,
+    centroids: Option<ArcTensor2<f32>>,
+    iterations: usize,
+}
+
+impl KMeans {
+    /// Creates a new [`KMeans`] with `k` clusters.
+    pub fn new(k: usize) -> Self {
+        Self {
+            k,
+            max_iterations: 300,
+            tolerance: 1e-4,
+            seed: None,
+            centroids: None,
+            iterations: 0,
+        }
+    }
+    /// Sets the maximum number of iterations. Defaults to 300.
+    pub fn max_iterations(self, max_iterations: usize) -> Self {
+        Self {
+            max_iterations,
+            ..self
+        }
+    }
+    /// Sets the convergence tolerance. Defaults to 1e-4.
+    ///
+    /// Fitting stops when the squared distance that each centroid moves is at most `tolerance`.
+    pub fn tolerance(self, tolerance: f32) -> Self {
+        Self { tolerance, ..self }
+    }
+    /// Seeds the random number generator used to initialize the centroids. Defaults to a random
+    /// seed.
+    pub fn seed(self, seed: u64) -> Self {
+        Self {
+            seed: Some(seed),
+            ..self
+        }
+    }
+    /// The number of clusters.
+    pub fn k(&self) -> usize {
+        self.k
+    }
+    /// The centroids, with shape \[k, features\].
+    ///
+    /// `None` if not fitted.
+    pub fn centroids(&self) -> Option<TensorView2<f32>> {
+        self.centroids.as_ref().map(|x| x.view())
+    }
+    /// The number of iterations of the last fit.
+    pub fn iterations(&self) -> usize {
+        self.iterations
+    }
+    /// Fits the centroids to `x`.
+    ///
+    /// Shapes:
+    /// - x: \[samples, features\]
+    ///
+    /// # Errors
+    /// - `k` is 0 or greater than the number of samples.
+    pub fn fit(&mut self, x: TensorView2<f32>) -> Result<()> {
+        let (samples, _features) = x.dim();
+        if self.k == 0 || self.k > samples {
+            bail!(
+                "KMeans k {} must be positive and at most samples {samples}!",
+                self.k
+            );
+        }
+        let x_host;
+        let x_array = if let Some(x) = x.as_array() {
+            x
+        } else {
+            x_host = x.to_device(Device::host())?;
+            x_host.as_array().unwrap()
+        };
+        let x_norms = row_norms_squared(x_array);
+        let mut rng = if let Some(seed) = self.seed {
+            StdRng::seed_from_u64(seed)
+        } else {
+            StdRng::from_entropy()
+        };
+        let mut centroids = kmeans_plus_plus(x_array, x_norms.view(), self.k, &mut rng);
+        self.iterations = 0;
+        while self.iterations < self.max_iterations {
+            self.iterations += 1;
+            let labels = assign(x.view(), x_norms.view(), centroids.view())?;
+            let mut sums = Array2::<f32>::zeros(centroids.raw_dim());
+            let mut counts = vec![0usize; self.k];
+            for (x, label) in x_array.outer_iter().zip(labels.iter().copied()) {
+                let mut sum = sums.row_mut(label as usize);
+                sum += &x;
+                counts[label as usize] += 1;
+            }
+            let mut shift = 0f32;
+            for ((mut centroid, sum), count) in centroids
+                .outer_iter_mut()
+                .zip(sums.outer_iter())
+                .zip(counts)
+            {
+                // empty clusters keep their centroid
+                if count > 0 {
+                    let new_centroid = &sum / count as f32;
+                    let diff = &new_centroid - &centroid;
+                    shift = shift.max(diff.dot(&diff));
+                    centroid.assign(&new_centroid);
+                }
+            }
+            if shift <= self.tolerance {
+                break;
+            }
+        }
+        self.centroids.replace(ArcTensor::from(centroids));
+        Ok(())
+    }
+    /// Predicts the cluster of each sample of `x`.
+    ///
+    /// Shapes:
+    /// - x: \[samples, features\]
+    /// - output: \[samples\]
+    ///
+    /// # Errors
+    /// - Not fitted.
+    /// - The features of `x` do not match the fitted data.
+    pub fn predict(&self, x: TensorView2<f32>) -> Result<Tensor1<u32>> {
+        let Some(centroids) = self.centroids.as_ref() else {
+            bail!("KMeans::predict not fitted!");
+        };
+        let centroids = centroids.as_array().unwrap();
+        let (_, features) = x.dim();
+        if features != centroids.ncols() {
+            bail!(
+                "KMeans::predict expected {} features, found {features}!",
+                centroids.ncols()
+            );
+        }
+        let x_host;
+        let x_array = if let Some(x) = x.as_array() {
+            x
+        } else {
+            x_host = x.to_device(Device::host())?;
+            x_host.as_array().unwrap()
+        };
+        let x_norms = row_norms_squared(x_array);
+        let labels = assign(x.view(), x_norms.view(), centroids)?;
+        Tensor::from(labels).into_device(x.device())
+    }
+}
+
+fn row_norms_squared(x: ArrayView2<f32>) -> Array1<f32> {
+    x.outer_iter().map(|x| x.dot(&x)).collect()
+}
+
+// Chooses the initial centroids, each sampled with probability proportional to the squared
+// distance to the nearest centroid already chosen.
+fn kmeans_plus_plus(
+    x: ArrayView2<f32>,
+    x_norms: ArrayView1<f32>,
+    k: usize,
+    rng: &mut StdRng,
+) -> Array2<f32> {
+    let (samples, features) = x.dim();
+    let mut centroids = Array2::zeros([k, features]);
+    centroids
+        .row_mut(0)
+        .assign(&x.row(rng.gen_range(0..samples)));
+    let mut distances = Array1::from_elem(samples, f32::INFINITY);
+    for i in 1..k {
+        let centroid = centroids.row(i - 1);
+        let centroid_norm = centroid.dot(&centroid);
+        for ((x, x_norm), distance) in x.outer_iter().zip(x_norms.iter()).zip(distances.iter_mut())
+        {
+            let d = (x_norm - 2. * x.dot(&centroid) + centroid_norm).max(0.);
+            *distance = distance.min(d);
+        }
+        let index = if let Ok(dist) = WeightedIndex::new(distances.iter()) {
+            dist.sample(rng)
+        } else {
+            // all samples coincide with a centroid
+            rng.gen_range(0..samples)
+        };
+        centroids.row_mut(i).assign(&x.row(index));
+    }
+    centroids
+}
+
+// Returns the index of the nearest centroid for each sample.
+fn assign(
+    x: TensorView2<f32>,
+    x_norms: ArrayView1<f32>,
+    centroids: ArrayView2<f32>,
+) -> Result<Vec<u32>> {
+    let centroids_t =
+        Tensor::from(centroids.t().as_standard_layout().into_owned()).into_device(x.device())?;
+    let products = x.dot(&centroids_t)?.into_device(Device::host())?;
+    let products = products.as_array().unwrap();
+    let centroid_norms = row_norms_squared(centroids);
+    let labels = products
+        .outer_iter()
+        .zip(x_norms.iter())
+        .map(|(products, x_norm)| {
+            let mut label = 0;
+            let mut min = f32::INFINITY;
+            for (i, (product, centroid_norm)) in
+                products.iter().zip(centroid_norms.iter()).enumerate()
+            {
+                let d = x_norm - 2. * product + centroid_norm;
+                if d < min {
+                    min = d;
+                    label = i as u32;
+                }
+            }
+            label
+        })
+        .collect();
+    Ok(labels)
+}
@@ -1,6 +1,8 @@
-use crate::tensor::{ScalarTensorBase, ScalarTensorView, TensorBase, TensorView1, TensorView2};
-#[cfg(feature = "device")]
-use crate::tensor::{ScalarTensorView1, ScalarTensorView2, Tensor};
+use crate::tensor::{
+    ReduceOp, ScalarTensor, ScalarTensor2, ScalarTensorBase, ScalarTensorView, ScalarTensorView1,
+    ScalarTensorView2, ScalarTensorViewD, Tensor, Tensor0, Tensor1, Tensor2, TensorBase, TensorD,
+    TensorView, TensorView1, TensorView2,
+};
 use anyhow::{bail, Result};
 use dry::macro_for;
 use half::bf16;
@@ -10,9 +12,10 @@ use krnl::buffer::Slice;
 use krnl::macros::module;
 use krnl::{
     buffer::{Data, ScalarData},
-    scalar::Scalar,
+    device::Device,
+    scalar::{Scalar, ScalarType},
 };
-use ndarray::{ArrayView1, ArrayView2, Ix1, Ix2};
+use ndarray::{Array, Array1, Array2, ArrayView1, ArrayView2, Axis, Dimension, Ix1, Ix2, Zip};
 #[cfg(feature = "device")]
 use num_traits::ToPrimitive;
 use num_traits::{Float, Unsigned};
@@ -25,6 +28,393 @@ pub trait Accuracy<T> {
     ///
     /// Returns the number of correct predictions.
     fn accuracy(&self, target: T) -> Result<usize>;
+    /// Accuracy of a prediction given `target`, added to `counter`.
+    ///
+    /// Unlike [`.accuracy()`](Accuracy::accuracy), does not wait for the result when
+    /// computed on the device of `counter`.
+    fn accuracy_with(&self, target: T, counter: &mut AccuracyCounter) -> Result<()> {
+        counter.add(self.accuracy(target)?);
+        Ok(())
+    }
+}
+
+/// Accuracy counter.
+///
+/// Accumulates the number of correct predictions on a device, so that the result only needs
+/// to be read periodically, for example to update a progress bar.
+///
+/// See [`Accuracy::accuracy_with()`].
+///
+/// # Example
+///```no_run
+/// # use autograph::{krnl::device::Device, tensor::{Tensor2, Tensor1}};
+/// # use autograph::learn::criterion::{Accuracy, AccuracyCounter};
+/// # fn main() -> anyhow::Result<()> {
+/// # let device = Device::host();
+/// # let batches: Vec<(Tensor2<f32>, Tensor1<u8>)> = Vec::new();
+/// let mut counter = AccuracyCounter::new(device.clone())?;
+/// for (y, t) in batches {
+///     y.accuracy_with(t, &mut counter)?;
+/// }
+/// let correct = counter.correct()?;
+/// # Ok(())
+/// # }
+///```
+#[derive(Debug)]
+pub struct AccuracyCounter {
+    correct: usize,
+    device_correct: Tensor0<u32>,
+}
+
+impl AccuracyCounter {
+    /// Creates a new [`AccuracyCounter`] on `device`.
+    pub fn new(device: Device) -> Result<Self> {
+        Ok(Self {
+            correct: 0,
+            device_correct: Tensor::zeros(device, ())?,
+        })
+    }
+    /// The device.
+    pub fn device(&self) -> Device {
+        self.device_correct.device()
+    }
+    /// Adds `correct` predictions.
+    pub fn add(&mut self, correct: usize) {
+        self.correct += correct;
+    }
+    /// The number of correct predictions.
+    ///
+    /// Waits for pending operations on the device.
+    pub fn correct(&self) -> Result<usize> {
+        let device_correct = self.device_correct.item()?;
+        Ok(self.correct + device_correct as usize)
+    }
+    /// Resets the counter to 0.
+    pub fn reset(&mut self) -> Result<()> {
+        self.correct = 0;
+        self.device_correct.fill(0)
+    }
+}
+
+/// Evaluator.
+///
+/// Accumulates the confusion matrix and the cross entropy loss across batches, so that
+/// metrics can be computed for a whole dataset at the end of an evaluation.
+///
+/// The predicted class of each sample is the first index with the maximum value. Like
+/// [`AccuracyCounter`], the confusion matrix and the loss are accumulated on the device, so
+/// that the result is only transferred by [`.finish()`](Evaluator::finish).
+///
+/// # Example
+///```no_run
+/// # use autograph::{krnl::device::Device, tensor::{ScalarTensor2, ScalarTensor1}};
+/// # use autograph::learn::criterion::Evaluator;
+/// # fn main() -> anyhow::Result<()> {
+/// # let device = Device::host();
+/// # let batches: Vec<(ScalarTensor2, ScalarTensor1)> = Vec::new();
+/// let mut evaluator = Evaluator::new(device.clone(), 10)?;
+/// for (y, t) in batches {
+///     evaluator.add(&y, &t)?;
+/// }
+/// let metrics = evaluator.finish()?;
+/// println!("accuracy: {} loss: {}", metrics.accuracy, metrics.loss);
+/// # Ok(())
+/// # }
+///```
+#[derive(Debug)]
+pub struct Evaluator {
+    samples: u64,
+    device_confusion_matrix: Tensor2<u32>,
+    device_loss: Tensor0<f32>,
+}
+
+/// Metrics computed by an [`Evaluator`].
+#[derive(Debug)]
+pub struct EvaluatorMetrics {
+    /// The number of samples.
+    pub samples: u64,
+    /// The fraction of correct predictions.
+    pub accuracy: f32,
+    /// The mean cross entropy loss.
+    pub loss: f32,
+    /// The precision of each class.
+    ///
+    /// The fraction of predictions of the class that are correct, or 0 if the class was not
+    /// predicted.
+    pub precision: Array1<f32>,
+    /// The recall of each class.
+    ///
+    /// The fraction of samples of the class that were predicted correctly, or 0 if there are no
+    /// samples of the class.
+    pub recall: Array1<f32>,
+    /// The confusion matrix with shape \[classes, classes\], on the host.
+    ///
+    /// The element `[t, y]` is the number of samples with target `t` that were predicted `y`.
+    pub confusion_matrix: Tensor2<u32>,
+}
+
+impl Evaluator {
+    /// Creates a new [`Evaluator`] for `classes` on `device`.
+    pub fn new(device: Device, classes: usize) -> Result<Self> {
+        Ok(Self {
+            samples: 0,
+            device_confusion_matrix: Tensor::zeros(device.clone(), [classes, classes])?,
+            device_loss: Tensor::zeros(device, ())?,
+        })
+    }
+    /// The device.
+    pub fn device(&self) -> Device {
+        self.device_confusion_matrix.device()
+    }
+    /// The number of classes.
+    pub fn classes(&self) -> usize {
+        self.device_confusion_matrix.shape()[0]
+    }
+    /// Adds a batch of predictions `input` given `target`.
+    ///
+    /// Shapes:
+    /// - input: \[batch_size, classes\]
+    /// - target: \[batch_size\]
+    ///
+    /// Implemented for:
+    /// - input: bf16, f32
+    /// - target: u8, u16, u32
+    ///
+    /// Does not wait for the result when computed on the device of the evaluator.
+    ///
+    /// # Errors
+    /// - The shapes do not match.
+    /// - The tensors are not on the same device.
+    /// - `target` indices are out of bounds on the host. On a device, they are reported by
+    ///   [`.finish()`](Evaluator::finish).
+    /// - The operation could not be executed on the device.
+    ///
+    /// See [`CrossEntropyLoss`].
+    pub fn add<S1: ScalarData, S2: ScalarData>(
+        &mut self,
+        input: &ScalarTensorBase<S1, Ix2>,
+        target: &ScalarTensorBase<S2, Ix1>,
+    ) -> Result<()> {
+        let (batch_size, classes) = input.dim();
+        if classes != self.classes() || target.dim() != batch_size {
+            bail!(
+                "Evaluator expected input {:?} and target {:?} with {} classes!",
+                input.shape(),
+                target.shape(),
+                self.classes()
+            );
+        }
+        if target.device().is_host() {
+            let target = target.view().cast_into_tensor::<u32>()?;
+            let target = target.as_array().unwrap();
+            if let Some(t) = target.iter().find(|t| **t as usize >= classes) {
+                bail!("Evaluator target {t} out of bounds for {classes} classes!");
+            }
+        }
+        let matrix = confusion_matrix_impl(input.view().into_dyn(), target.view(), classes)?
+            .into_device(self.device())?;
+        let loss = input
+            .view()
+            .cross_entropy_loss_per_sample(target.view(), &CrossEntropyLossOptions::default())?
+            .into_device(self.device())?;
+        self.device_confusion_matrix.scaled_add(1, &matrix)?;
+        loss.sum_with(1., &mut self.device_loss)?;
+        self.samples += batch_size as u64;
+        Ok(())
+    }
+    /// The number of samples added.
+    pub fn samples(&self) -> u64 {
+        self.samples
+    }
+    /// Computes the metrics for all of the samples added.
+    ///
+    /// Transfers the confusion matrix and the loss to the host, waiting for pending operations
+    /// on the device.
+    ///
+    /// # Errors
+    /// - `target` indices were out of bounds.
+    /// - The transfer failed.
+    pub fn finish(&self) -> Result<EvaluatorMetrics> {
+        let confusion_matrix = self.device_confusion_matrix.to_device(Device::host())?;
+        let loss = self.device_loss.item()?;
+        let matrix = confusion_matrix.as_array().unwrap();
+        let samples = self.samples;
+        if matrix.iter().map(|x| *x as u64).sum::<u64>() != samples {
+            bail!(
+                "Evaluator target out of bounds for {} classes!",
+                self.classes()
+            );
+        }
+        let sum = |x: ArrayView1<u32>| x.iter().map(|x| *x as u64).sum::<u64>();
+        let ratio = |a: u64, b: u64| if b > 0 { a as f32 / b as f32 } else { 0. };
+        let correct = sum(matrix.diag());
+        let precision = matrix
+            .axis_iter(Axis(1))
+            .enumerate()
+            .map(|(i, y)| ratio(y[i] as u64, sum(y)))
+            .collect();
+        let recall = matrix
+            .outer_iter()
+            .enumerate()
+            .map(|(i, t)| ratio(t[i] as u64, sum(t)))
+            .collect();
+        Ok(EvaluatorMetrics {
+            samples,
+            accuracy: ratio(correct, samples),
+            loss: if samples > 0 {
+                loss / samples as f32
+            } else {
+                0.
+            },
+            precision,
+            recall,
+            confusion_matrix,
+        })
+    }
+    /// Resets the evaluator, removing all samples.
+    pub fn reset(&mut self) -> Result<()> {
+        self.samples = 0;
+        self.device_confusion_matrix.fill(0)?;
+        self.device_loss.fill(0.)
+    }
+}
+
+/// Computes the confusion matrix of `input` given `target`.
+///
+/// The `input` is either the predictions with shape \[batch_size, classes\], where the predicted
+/// class of each sample is the first index with the maximum value, or the predicted classes with
+/// shape \[batch_size\].
+///
+/// Returns a tensor with shape \[classes, classes\] on the device of `input`, where the element
+/// `[t, y]` is the number of samples with target `t` that were predicted `y`.
+///
+/// Implemented for:
+/// - input: bf16, f32 predictions, or u8, u16, u32 classes
+/// - target: u8, u16, u32
+///
+/// Each element of the matrix counts its samples on the device, then the number of samples counted
+/// is checked, waiting for the result.
+///
+/// # Errors
+/// - The shapes do not match.
+/// - The tensors are not on the same device.
+/// - Classes are out of bounds.
+/// - The operation could not be executed on the device.
+///
+/// See [`Evaluator`].
+///
+/// # Example
+///```no_run
+/// # use autograph::{krnl::device::Device, tensor::{Tensor, Tensor1}};
+/// # use autograph::learn::criterion::confusion_matrix;
+/// # fn main() -> anyhow::Result<()> {
+/// let y = Tensor1::from(vec![0u8, 1, 1]);
+/// let t = Tensor1::from(vec![0u8, 0, 1]);
+/// let matrix = confusion_matrix(&y.into_scalar_tensor(), &t.into_scalar_tensor(), 2)?;
+/// // [[1, 1], [0, 1]]
+/// # Ok(())
+/// # }
+///```
+pub fn confusion_matrix<S1: ScalarData, D: Dimension, S2: ScalarData>(
+    input: &ScalarTensorBase<S1, D>,
+    target: &ScalarTensorBase<S2, Ix1>,
+    classes: usize,
+) -> Result<Tensor2<u32>> {
+    let batch_size = input.shape().first().copied().unwrap_or_default();
+    let input_classes = if input.ndim() == 2 {
+        Some(input.shape()[1])
+    } else {
+        None
+    };
+    let valid_input = match input.ndim() {
+        1 => matches!(
+            input.scalar_type(),
+            ScalarType::U8 | ScalarType::U16 | ScalarType::U32
+        ),
+        2 => matches!(input.scalar_type(), ScalarType::BF16 | ScalarType::F32),
+        _ => false,
+    };
+    if !valid_input
+        || !matches!(
+            target.scalar_type(),
+            ScalarType::U8 | ScalarType::U16 | ScalarType::U32
+        )
+    {
+        bail!(
+            "confusion_matrix {:?} {:?} with input shape {:?} unimplemented!",
+            input.scalar_type(),
+            target.scalar_type(),
+            input.shape()
+        );
+    }
+    if target.dim() != batch_size || input_classes.map_or(false, |c| c != classes) {
+        bail!(
+            "confusion_matrix expected input {:?} and target {:?} with {classes} classes!",
+            input.shape(),
+            target.shape()
+        );
+    }
+    let matrix = confusion_matrix_impl(input.view().into_dyn(), target.view(), classes)?;
+    if matrix.sum()? as usize != batch_size {
+        bail!("confusion_matrix target or prediction out of bounds for {classes} classes!");
+    }
+    Ok(matrix)
+}
+
+// Counts the samples of each target and predicted class, skipping classes that are out of bounds.
+fn confusion_matrix_impl(
+    input: ScalarTensorViewD,
+    target: ScalarTensorView1,
+    classes: usize,
+) -> Result<Tensor2<u32>> {
+    let device = input.device();
+    if target.device() != device {
+        bail!(
+            "confusion_matrix expected target on {device:?}, found {:?}!",
+            target.device()
+        );
+    }
+    let predicted = if input.ndim() == 2 {
+        let input = input.into_dimensionality::<Ix2>()?;
+        let mut predicted = None;
+        macro_for!($T in [bf16, f32] {
+            if let Ok(input) = TensorView2::<$T>::try_from(input.view()) {
+                predicted.replace(input.argmax_axis(Axis(1))?);
+            }
+        });
+        predicted.unwrap()
+    } else {
+        input
+            .into_dimensionality::<Ix1>()?
+            .cast_into_tensor::<u32>()?
+    };
+    let target = target.cast_into_tensor::<u32>()?;
+    if let Some((predicted, target)) = predicted.as_array().zip(target.as_array()) {
+        let mut matrix = Array2::<u32>::zeros([classes, classes]);
+        for (t, y) in target.iter().zip(predicted) {
+            if let Some(count) = matrix.get_mut((*t as usize, *y as usize)) {
+                *count += 1;
+            }
+        }
+        return Ok(matrix.into());
+    }
+    #[cfg(not(feature = "device"))]
+    {
+        unreachable!()
+    }
+    #[cfg(feature = "device")]
+    {
+        let mut matrix = unsafe { Tensor::<u32, _>::uninit(device.clone(), [classes, classes])? };
+        kernels::confusion_matrix::builder()?
+            .build(device)?
+            .dispatch(
+                classes.to_u32().unwrap(),
+                target.as_slice().unwrap(),
+                predicted.as_slice().unwrap(),
+                matrix.as_slice_mut().unwrap(),
+            )?;
+        Ok(matrix)
+    }
 }
 
 fn accuracy_host<T1: Scalar + Float, T2: Scalar + Unsigned>(
@@ -62,6 +452,19 @@ impl<T1: Scalar + Float, S1: Data<Elem = T1>, T2: Scalar + Unsigned, S2: Data<El
             ScalarTensorView::from(self.view()).accuracy(ScalarTensorView::from(target.view()))
         }
     }
+    fn accuracy_with(
+        &self,
+        target: TensorBase<S2, Ix1>,
+        counter: &mut AccuracyCounter,
+    ) -> Result<()> {
+        if let Some((input, target)) = self.as_array().zip(target.as_array()) {
+            counter.add(accuracy_host(input, target));
+            Ok(())
+        } else {
+            ScalarTensorView::from(self.view())
+                .accuracy_with(ScalarTensorView::from(target.view()), counter)
+        }
+    }
 }
 
 /// Implemented for:
@@ -99,36 +502,73 @@ impl<S1: ScalarData, S2: ScalarData> Accuracy<ScalarTensorBase<S2, Ix1>>
         }
         #[cfg(feature = "device")]
         {
-            let (batch_size, classes) = self.dim();
-            macro_for!($T1 in [bf16, f32] {
-                macro_for!($T2 in [u8, u16, u32] {
-                    if self.scalar_type() == $T1::SCALAR_TYPE && target.scalar_type() == $T2::SCALAR_TYPE {
-                        let input = Slice::<$T1>::try_from(self.as_scalar_slice().unwrap()).unwrap();
-                        let target = Slice::<$T2>::try_from(target.as_scalar_slice().unwrap()).unwrap();
-                        let mut output = unsafe { Tensor::<u32, _>::uninit(input.device(), batch_size)? };
-                        paste! {
-                            kernels::[<accuracy_ $T1 _ $T2>]::builder()?
-                                .build(device)?
-                                .dispatch(
-                                    input,
-                                    target,
-                                    classes.to_u32().unwrap(),
-                                    output.as_slice_mut().unwrap(),
-                                )?;
-                        }
-                        return output.sum().map(|x| x.try_into().unwrap());
-                    }
-                });
-            });
-            bail!(
-                "Accuracy {:?} {:?} not implemented!",
-                self.scalar_type(),
-                target.scalar_type()
-            );
+            accuracy_device(self.view(), target.view())?
+                .sum()
+                .map(|x| x.try_into().unwrap())
+        }
+    }
+    fn accuracy_with(
+        &self,
+        target: ScalarTensorBase<S2, Ix1>,
+        counter: &mut AccuracyCounter,
+    ) -> Result<()> {
+        let device = self.device();
+        if device.is_host() || device != counter.device() {
+            counter.add(self.accuracy(target)?);
+            return Ok(());
+        }
+        #[cfg(not(feature = "device"))]
+        {
+            unreachable!()
+        }
+        #[cfg(feature = "device")]
+        {
+            accuracy_device(self.view(), target.view())?.sum_with(1, &mut counter.device_correct)
         }
     }
 }
 
+#[cfg(feature = "device")]
+fn accuracy_device(input: ScalarTensorView2, target: ScalarTensorView1) -> Result<Tensor1<u32>> {
+    let device = input.device();
+    let (batch_size, classes) = input.dim();
+    macro_for!($T1 in [bf16, f32] {
+        macro_for!($T2 in [u8, u16, u32] {
+            if input.scalar_type() == $T1::SCALAR_TYPE && target.scalar_type() == $T2::SCALAR_TYPE {
+                let input = Slice::<$T1>::try_from(input.as_scalar_slice().unwrap()).unwrap();
+                let target = Slice::<$T2>::try_from(target.as_scalar_slice().unwrap()).unwrap();
+                let mut output = unsafe { Tensor::<u32, _>::uninit(input.device(), batch_size)? };
+                paste! {
+                    kernels::[<accuracy_ $T1 _ $T2>]::builder()?
+                        .build(device)?
+                        .dispatch(
+                            input,
+                            target,
+                            classes.to_u32().unwrap(),
+                            output.as_slice_mut().unwrap(),
+                        )?;
+                }
+                return Ok(output);
+            }
+        });
+    });
+    bail!(
+        "Accuracy {:?} {:?} not implemented!",
+        input.scalar_type(),
+        target.scalar_type()
+    );
+}
+
+/// Options for [`CrossEntropyLoss`].
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CrossEntropyLossOptions {
+    /// Samples with a target equal to `ignore_index`, ie padding, do not contribute to the loss
+    /// or the gradient. Defaults to None.
+    ///
+    /// With `ignore_index`, the gradient is averaged over the samples that are not ignored.
+    pub ignore_index: Option<usize>,
+}
+
 /// Cross Entropy Loss.
 ///
 /// Shapes:
@@ -137,8 +577,43 @@ impl<S1: ScalarData, S2: ScalarData> Accuracy<ScalarTensorBase<S2, Ix1>>
 pub trait CrossEntropyLoss<T> {
     /// Type of the output.
     type Output;
+    /// Type of the per sample output.
+    type PerSampleOutput;
+    /// Type of the output with a [`Reduction`].
+    type ReducedOutput;
     /// Computes the loss given `target`.
-    fn cross_entropy_loss(&self, target: T) -> Result<Self::Output>;
+    ///
+    /// Returns the sum of the losses of each sample, while the gradient is averaged over the
+    /// samples. See [`.cross_entropy_loss_with()`](CrossEntropyLoss::cross_entropy_loss_with).
+    fn cross_entropy_loss(&self, target: T) -> Result<Self::Output> {
+        self.cross_entropy_loss_with_options(target, &CrossEntropyLossOptions::default())
+    }
+    /// Computes the loss given `target` with `options`.
+    fn cross_entropy_loss_with_options(
+        &self,
+        target: T,
+        options: &CrossEntropyLossOptions,
+    ) -> Result<Self::Output>;
+    /// Computes the loss of each sample given `target` with `options`.
+    ///
+    /// Returns a tensor with shape \[batch_size\], the unreduced loss. Ignored samples have a
+    /// loss of 0.
+    fn cross_entropy_loss_per_sample(
+        &self,
+        target: T,
+        options: &CrossEntropyLossOptions,
+    ) -> Result<Self::PerSampleOutput>;
+    /// Computes the loss given `target` with `reduction`.
+    ///
+    /// Returns the loss with shape \[\] for [`Reduction::Mean`] and [`Reduction::Sum`], and the
+    /// gradient is scaled by `1 / batch_size` or 1 respectively. For [`Reduction::None`], returns
+    /// the loss of each sample with shape \[batch_size\], like
+    /// [`.cross_entropy_loss_per_sample()`](CrossEntropyLoss::cross_entropy_loss_per_sample).
+    fn cross_entropy_loss_with(
+        &self,
+        target: T,
+        reduction: Reduction,
+    ) -> Result<Self::ReducedOutput>;
 }
 
 /// Implemented for:
@@ -151,12 +626,18 @@ impl<S1: ScalarData, S2: ScalarData> CrossEntropyLoss<ScalarTensorBase<S2, Ix1>>
     for ScalarTensorBase<S1, Ix2>
 {
     type Output = f32;
-    fn cross_entropy_loss(&self, target: ScalarTensorBase<S2, Ix1>) -> Result<Self::Output> {
+    type PerSampleOutput = Tensor1<f32>;
+    type ReducedOutput = TensorD<f32>;
+    fn cross_entropy_loss_with_options(
+        &self,
+        target: ScalarTensorBase<S2, Ix1>,
+        options: &CrossEntropyLossOptions,
+    ) -> Result<Self::Output> {
         macro_for!($T1 in [bf16, f32] {
             if let Ok(input) = TensorView2::<$T1>::try_from(self.view()) {
                 macro_for!($T2 in [u8, u16, u32] {
                     if let Ok(target) = TensorView1::<$T2>::try_from(target.view()) {
-                        return input.cross_entropy_loss(target).map(Into::into);
+                        return input.cross_entropy_loss_with_options(target, options);
                     }
                 });
             }
@@ -167,6 +648,35 @@ impl<S1: ScalarData, S2: ScalarData> CrossEntropyLoss<ScalarTensorBase<S2, Ix1>>
             target.scalar_type()
         )
     }
+    fn cross_entropy_loss_per_sample(
+        &self,
+        target: ScalarTensorBase<S2, Ix1>,
+        options: &CrossEntropyLossOptions,
+    ) -> Result<Self::PerSampleOutput> {
+        macro_for!($T1 in [bf16, f32] {
+            if let Ok(input) = TensorView2::<$T1>::try_from(self.view()) {
+                macro_for!($T2 in [u8, u16, u32] {
+                    if let Ok(target) = TensorView1::<$T2>::try_from(target.view()) {
+                        return input.cross_entropy_loss_per_sample(target, options);
+                    }
+                });
+            }
+        });
+        bail!(
+            "CrosEntropyLoss {:?} {:?} unimplemented!",
+            self.scalar_type(),
+            target.scalar_type()
+        )
+    }
+    fn cross_entropy_loss_with(
+        &self,
+        target: ScalarTensorBase<S2, Ix1>,
+        reduction: Reduction,
+    ) -> Result<Self::ReducedOutput> {
+        let loss =
+            self.cross_entropy_loss_per_sample(target, &CrossEntropyLossOptions::default())?;
+        reduce_loss_tensor("CrossEntropyLoss", loss, reduction)
+    }
 }
 
 /// Implemented for:
@@ -179,16 +689,28 @@ impl<T1: Scalar + Float, S1: Data<Elem = T1>, T2: Scalar + Unsigned, S2: Data<El
     CrossEntropyLoss<TensorBase<S2, Ix1>> for TensorBase<S1, Ix2>
 {
     type Output = f32;
-    fn cross_entropy_loss(&self, target: TensorBase<S2, Ix1>) -> Result<Self::Output> {
-        let (batch_size, _classes) = self.dim();
-        if target.dim() != batch_size {
-            bail!(
-                "Expected target with shape [{batch_size}], found [{}]",
-                target.dim()
-            );
+    type PerSampleOutput = Tensor1<f32>;
+    type ReducedOutput = TensorD<f32>;
+    fn cross_entropy_loss_with_options(
+        &self,
+        target: TensorBase<S2, Ix1>,
+        options: &CrossEntropyLossOptions,
+    ) -> Result<Self::Output> {
+        if let Some((input, target)) = self.as_array().zip(target.as_array()) {
+            check_cross_entropy_loss_target(self.dim(), target.dim())?;
+            Ok(cross_entropy_loss_host(input, target, options.ignore_index).sum())
+        } else {
+            self.cross_entropy_loss_per_sample(target, options)?.sum()
         }
+    }
+    fn cross_entropy_loss_per_sample(
+        &self,
+        target: TensorBase<S2, Ix1>,
+        options: &CrossEntropyLossOptions,
+    ) -> Result<Self::PerSampleOutput> {
+        check_cross_entropy_loss_target(self.dim(), target.dim())?;
         if let Some((input, target)) = self.as_array().zip(target.as_array()) {
-            Ok(cross_entropy_loss_host(input, target))
+            Ok(cross_entropy_loss_host(input, target, options.ignore_index).into())
         } else {
             #[cfg(not(feature = "device"))]
             {
@@ -196,20 +718,64 @@ impl<T1: Scalar + Float, S1: Data<Elem = T1>, T2: Scalar + Unsigned, S2: Data<El
             }
             #[cfg(feature = "device")]
             {
-                cross_entropy_loss_device(self.view().into(), target.view().into())
+                cross_entropy_loss_device(
+                    self.view().into(),
+                    target.view().into(),
+                    options.ignore_index,
+                )
             }
         }
     }
+    fn cross_entropy_loss_with(
+        &self,
+        target: TensorBase<S2, Ix1>,
+        reduction: Reduction,
+    ) -> Result<Self::ReducedOutput> {
+        let loss =
+            self.cross_entropy_loss_per_sample(target, &CrossEntropyLossOptions::default())?;
+        reduce_loss_tensor("CrossEntropyLoss", loss, reduction)
+    }
+}
+
+// Applies the reduction to the loss of each sample or element, on the device of `loss`.
+fn reduce_loss_tensor<D: Dimension>(
+    name: &str,
+    loss: Tensor<f32, D>,
+    reduction: Reduction,
+) -> Result<TensorD<f32>> {
+    if reduction == Reduction::None {
+        return Ok(loss.into_dyn());
+    }
+    let device = loss.device();
+    let loss = reduction.reduce_tensor(name, loss.view())?;
+    Tensor::from(Array::from_elem((), loss))
+        .into_device(device)
+        .map(Tensor::into_dyn)
+}
+
+fn check_cross_entropy_loss_target(
+    (batch_size, _classes): (usize, usize),
+    target_dim: usize,
+) -> Result<()> {
+    if target_dim != batch_size {
+        bail!("Expected target with shape [{batch_size}], found [{target_dim}]");
+    }
+    Ok(())
 }
 
+// Returns the loss of each sample, 0 for ignored samples.
 fn cross_entropy_loss_host<T1: Scalar + Float, T2: Scalar + Unsigned>(
     input: ArrayView2<T1>,
     target: ArrayView1<T2>,
-) -> f32 {
+    ignore_index: Option<usize>,
+) -> Array1<f32> {
     let x = input;
     let t = target;
-    let mut y = 0f32;
-    for (x, t) in x.outer_iter().zip(t.iter().copied()) {
+    let mut y = Array1::zeros(t.len());
+    for ((x, t), y) in x.outer_iter().zip(t.iter().copied()).zip(y.iter_mut()) {
+        if Some(t.to_usize().unwrap()) == ignore_index {
+            continue;
+        }
         let m = x
             .iter()
             .map(|x| x.cast::<f32>())
@@ -220,13 +786,20 @@ fn cross_entropy_loss_host<T1: Scalar + Float, T2: Scalar + Unsigned>(
             .map(|x| (x.cast::<f32>() - m).exp())
             .sum::<f32>();
         let x = x[t.to_usize().unwrap()];
-        y += s.ln() - (x.cast::<f32>() - m);
+        *y = s.ln() - (x.cast::<f32>() - m);
     }
     y
 }
 
 #[cfg(feature = "device")]
-fn cross_entropy_loss_device(input: ScalarTensorView2, target: ScalarTensorView1) -> Result<f32> {
+fn cross_entropy_loss_device(
+    input: ScalarTensorView2,
+    target: ScalarTensorView1,
+    ignore_index: Option<usize>,
+) -> Result<Tensor1<f32>> {
+    let ignore_index = ignore_index
+        .and_then(|ignore_index| ignore_index.to_u32())
+        .unwrap_or(u32::MAX);
     macro_for!($T1 in [bf16, f32] {
         if let Ok(input) = TensorView2::<$T1>::try_from(input.view()) {
             let (batch_size, classes) = input.dim();
@@ -240,8 +813,14 @@ fn cross_entropy_loss_device(input: ScalarTensorView2, target: ScalarTensorView1
                         kernels::[<cross_entropy_loss_ $T1 _ $T2>]::builder()?
                         .build(output.device())?
                     };
-                    kernel.dispatch(input, target, classes, output.as_slice_mut().unwrap())?;
-                    return output.sum();
+                    kernel.dispatch(
+                        input,
+                        target,
+                        classes,
+                        ignore_index,
+                        output.as_slice_mut().unwrap(),
+                    )?;
+                    return Ok(output);
                 }
             });
         }
@@ -253,17 +832,1451 @@ fn cross_entropy_loss_device(input: ScalarTensorView2, target: ScalarTensorView1
     )
 }
 
+/// Negative Log Likelihood Loss.
+///
+/// Computes `-input[i, target[i]]` for each sample, where the input are log probabilities, ie
+/// the output of a log softmax, and returns the mean over the batch. The gradient of the input
+/// is `-1 / batch_size` at the target of each sample and 0 elsewhere.
+///
+/// With `weight`, the loss of each sample is scaled by `weight[target[i]]`, and the sum is
+/// divided by the sum of those weights instead of the batch size.
+///
+/// Shapes:
+/// - input: \[batch_size, classes\]
+/// - target: \[batch_size\]
+/// - weight: \[classes\]
+pub trait NllLoss<T> {
+    /// Type of the output.
+    type Output;
+    /// Computes the loss given `target`.
+    fn nll_loss(&self, target: T) -> Result<Self::Output> {
+        self.nll_loss_with_weight(target, None)
+    }
+    /// Computes the loss given `target`, weighting each class by `weight`.
+    fn nll_loss_with_weight(
+        &self,
+        target: T,
+        weight: Option<ScalarTensorView1>,
+    ) -> Result<Self::Output>;
+}
+
+/// Implemented for:
+/// - input, weight: bf16, f32
+/// - target: u8, u16, u32
+///
+/// On a device, the target indices are checked with a reduction before the loss is computed.
+impl<S1: ScalarData, S2: ScalarData> NllLoss<ScalarTensorBase<S2, Ix1>>
+    for ScalarTensorBase<S1, Ix2>
+{
+    type Output = f32;
+    fn nll_loss_with_weight(
+        &self,
+        target: ScalarTensorBase<S2, Ix1>,
+        weight: Option<ScalarTensorView1>,
+    ) -> Result<f32> {
+        if self.device().is_host() {
+            let (input, target, weight) = nll_loss_arrays(self.view(), target.view(), weight)?;
+            return Ok(nll_loss_host(
+                input.view(),
+                &target,
+                weight.as_ref().map(Array1::view),
+            ));
+        }
+        #[cfg(not(feature = "device"))]
+        {
+            unreachable!()
+        }
+        #[cfg(feature = "device")]
+        {
+            nll_loss_device(self.view(), target.view(), weight)?
+                .loss
+                .item()
+        }
+    }
+}
+
+/// Implemented for:
+/// - input, weight: bf16, f32
+/// - target: u8, u16, u32
+///
+/// See the implementation for [`ScalarTensorBase`].
+impl<T1: Scalar + Float, S1: Data<Elem = T1>, T2: Scalar + Unsigned, S2: Data<Elem = T2>>
+    NllLoss<TensorBase<S2, Ix1>> for TensorBase<S1, Ix2>
+{
+    type Output = f32;
+    fn nll_loss_with_weight(
+        &self,
+        target: TensorBase<S2, Ix1>,
+        weight: Option<ScalarTensorView1>,
+    ) -> Result<f32> {
+        ScalarTensorView::from(self.view())
+            .nll_loss_with_weight(ScalarTensorView::from(target.view()), weight)
+    }
+}
+
+// Returns the input, the target indices, and the weight on the host.
+pub(crate) fn nll_loss_arrays(
+    input: ScalarTensorView2,
+    target: ScalarTensorView1,
+    weight: Option<ScalarTensorView1>,
+) -> Result<(Array2<f32>, Vec<usize>, Option<Array1<f32>>)> {
+    let [input] = float_arrays("NllLoss", [input])?;
+    let (batch_size, classes) = input.dim();
+    let target = target.to_device(Device::host())?;
+    let mut target_indices = None;
+    macro_for!($T in [u8, u16, u32] {
+        if let Ok(target) = TensorView1::<$T>::try_from(target.view()) {
+            target_indices.replace(class_indices(target.as_array().unwrap()));
+        }
+    });
+    let Some(target) = target_indices else {
+        bail!("NllLoss target {:?} unimplemented!", target.scalar_type());
+    };
+    check_cross_entropy_loss_target((batch_size, classes), target.len())?;
+    if let Some(t) = target.iter().find(|t| **t >= classes) {
+        bail!("NllLoss target {t} out of bounds for {classes} classes!");
+    }
+    let weight = if let Some(weight) = weight {
+        let [weight] = float_arrays("NllLoss", [weight])?;
+        if weight.len() != classes {
+            bail!(
+                "NllLoss expected weight with shape [{classes}], found [{}]!",
+                weight.len()
+            );
+        }
+        Some(weight)
+    } else {
+        None
+    };
+    Ok((input, target, weight))
+}
+
+// The loss of [`NllLoss`] computed on the device, with the state needed for the backward pass.
 #[cfg(feature = "device")]
-#[module]
+pub(crate) struct NllLossDevice {
+    // The mean loss.
+    pub(crate) loss: Tensor0<f32>,
+    // The target index of each sample.
+    pub(crate) target: Tensor1<u32>,
+    // The weight of each sample.
+    pub(crate) weight: Tensor1<f32>,
+    // The sum of the weights.
+    pub(crate) total: Tensor0<f32>,
+}
+
+// Computes the loss on the device, gathering the input and the weight at the target of each
+// sample.
+#[cfg(feature = "device")]
+pub(crate) fn nll_loss_device(
+    input: ScalarTensorView2,
+    target: ScalarTensorView1,
+    weight: Option<ScalarTensorView1>,
+) -> Result<NllLossDevice> {
+    let device = input.device();
+    if target.device() != device || weight.as_ref().is_some_and(|w| w.device() != device) {
+        bail!("NllLoss expected tensors on {device:?}!");
+    }
+    check_float_inputs("NllLoss", &[input.view()])?;
+    let (batch_size, classes) = input.dim();
+    check_cross_entropy_loss_target((batch_size, classes), target.dim())?;
+    if !matches!(
+        target.scalar_type(),
+        ScalarType::U8 | ScalarType::U16 | ScalarType::U32
+    ) {
+        bail!("NllLoss target {:?} unimplemented!", target.scalar_type());
+    }
+    let target = target.cast_into_tensor::<u32>()?;
+    if !target.is_empty() {
+        let t = target.max()? as usize;
+        if t >= classes {
+            bail!("NllLoss target {t} out of bounds for {classes} classes!");
+        }
+    }
+    let weight = if let Some(weight) = weight {
+        check_float_inputs("NllLoss", &[weight.view()])?;
+        if weight.len() != classes {
+            bail!(
+                "NllLoss expected weight with shape [{classes}], found [{}]!",
+                weight.len()
+            );
+        }
+        weight
+            .cast_into_tensor::<f32>()?
+            .gather(Axis(0), target.view())?
+    } else {
+        Tensor::from_elem(device.clone(), batch_size, 1f32)?
+    };
+    let mut total = Tensor::zeros(device.clone(), ())?;
+    let mut loss = Tensor::zeros(device.clone(), ())?;
+    if batch_size == 0 {
+        return Ok(NllLossDevice {
+            loss,
+            target,
+            weight,
+            total,
+        });
+    }
+    weight.sum_with(0., &mut total)?;
+    let input = input.as_standard_layout()?;
+    let mut output = unsafe { Tensor::<f32, _>::uninit(device.clone(), batch_size)? };
+    macro_for!($T in [bf16, f32] {
+        if let Ok(x) = TensorView2::<$T>::try_from(input.view()) {
+            let kernel = paste! {
+                kernels::[<nll_loss_ $T>]::builder()?.build(device.clone())?
+            };
+            kernel.dispatch(
+                classes.to_u32().unwrap(),
+                x.as_slice().unwrap(),
+                total.as_slice().unwrap(),
+                target.as_slice().unwrap(),
+                weight.as_slice().unwrap(),
+                output.as_slice_mut().unwrap(),
+            )?;
+        }
+    });
+    output.sum_with(0., &mut loss)?;
+    Ok(NllLossDevice {
+        loss,
+        target,
+        weight,
+        total,
+    })
+}
+
+fn class_indices<T: Scalar + Unsigned>(target: ArrayView1<T>) -> Vec<usize> {
+    target.iter().map(|t| t.to_usize().unwrap()).collect()
+}
+
+pub(crate) fn nll_loss_host(
+    input: ArrayView2<f32>,
+    target: &[usize],
+    weight: Option<ArrayView1<f32>>,
+) -> f32 {
+    let mut loss = 0f32;
+    let mut total = 0f32;
+    for (x, t) in input.outer_iter().zip(target.iter().copied()) {
+        let w = weight.map_or(1., |w| w[t]);
+        loss -= w * x[t];
+        total += w;
+    }
+    if total != 0. {
+        loss / total
+    } else {
+        0.
+    }
+}
+
+// Computes the gradient of the input given the gradient of the loss.
+pub(crate) fn nll_loss_backward_host(
+    input_dim: (usize, usize),
+    target: &[usize],
+    weight: Option<ArrayView1<f32>>,
+    dy: f32,
+) -> Array2<f32> {
+    let total: f32 = if let Some(weight) = weight {
+        target.iter().map(|t| weight[*t]).sum()
+    } else {
+        target.len() as f32
+    };
+    let mut dx = Array2::zeros(input_dim);
+    if total == 0. {
+        return dx;
+    }
+    for (mut dx, t) in dx.outer_iter_mut().zip(target.iter().copied()) {
+        let w = weight.map_or(1., |w| w[t]);
+        dx[t] = -w * dy / total;
+    }
+    dx
+}
+
+/// Reduction.
+///
+/// How the losses of each sample are combined.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reduction {
+    /// The mean of the losses.
+    #[default]
+    Mean,
+    /// The sum of the losses.
+    Sum,
+    /// No reduction.
+    ///
+    /// Returns the loss of each sample or element. Not supported by methods that return a
+    /// single loss, like [`.triplet_margin_loss()`](TripletMarginLoss::triplet_margin_loss).
+    None,
+}
+
+impl Reduction {
+    pub(crate) fn reduce(self, name: &str, loss: ArrayView1<f32>) -> Result<f32> {
+        match self {
+            Self::Mean => Ok(loss.mean().unwrap_or_default()),
+            Self::Sum => Ok(loss.sum()),
+            Self::None => {
+                bail!("{name} Reduction::None is not supported, use the per sample method!")
+            }
+        }
+    }
+    // Like `reduce()`, on the device of `loss`.
+    pub(crate) fn reduce_tensor<D: Dimension>(
+        self,
+        name: &str,
+        loss: TensorView<f32, D>,
+    ) -> Result<f32> {
+        match self {
+            Self::Mean if loss.is_empty() => Ok(0.),
+            Self::Mean => Ok(loss.sum()? / loss.len() as f32),
+            Self::Sum => loss.sum(),
+            Self::None => {
+                bail!("{name} Reduction::None is not supported, use the per sample method!")
+            }
+        }
+    }
+}
+
+/// Distance.
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Distance {
+    /// Euclidean distance, `||x1 - x2||`.
+    #[default]
+    L2,
+    /// Cosine distance, `1 - x1 · x2 / (||x1|| ||x2||)`.
+    Cosine,
+}
+
+impl Distance {
+    #[cfg(feature = "device")]
+    fn as_u32(self) -> u32 {
+        self as u32
+    }
+}
+
+/// Options for [`TripletMarginLoss`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TripletMarginLossOptions {
+    /// The margin. Defaults to 1.
+    pub margin: f32,
+    /// The distance. Defaults to [`Distance::L2`].
+    pub distance: Distance,
+    /// The reduction. Defaults to [`Reduction::Mean`].
+    pub reduction: Reduction,
+}
+
+impl Default for TripletMarginLossOptions {
+    fn default() -> Self {
+        Self {
+            margin: 1.,
+            distance: Distance::default(),
+            reduction: Reduction::default(),
+        }
+    }
+}
+
+/// Triplet Margin Loss.
+///
+/// Computes `max(d(anchor, positive) - d(anchor, negative) + margin, 0)` for each sample,
+/// where `d` is the [`Distance`].
+///
+/// Shapes:
+/// - anchor, positive, negative: \[batch_size, features\]
+pub trait TripletMarginLoss<T> {
+    /// Type of the output.
+    type Output;
+    /// Type of the per sample output.
+    type PerSampleOutput;
+    /// Computes the loss of `self` as the anchor given `positive` and `negative`.
+    ///
+    /// # Errors
+    /// The reduction must not be [`Reduction::None`].
+    fn triplet_margin_loss(
+        &self,
+        positive: T,
+        negative: T,
+        options: &TripletMarginLossOptions,
+    ) -> Result<Self::Output>;
+    /// Computes the loss of each sample of `self` as the anchor given `positive` and `negative`.
+    ///
+    /// Returns a tensor with shape \[batch_size\]. The reduction is not applied.
+    fn triplet_margin_loss_per_sample(
+        &self,
+        positive: T,
+        negative: T,
+        options: &TripletMarginLossOptions,
+    ) -> Result<Self::PerSampleOutput>;
+}
+
+/// Implemented for bf16 and f32.
+impl<S1: ScalarData, S2: ScalarData> TripletMarginLoss<ScalarTensorBase<S2, Ix2>>
+    for ScalarTensorBase<S1, Ix2>
+{
+    type Output = f32;
+    type PerSampleOutput = Tensor1<f32>;
+    fn triplet_margin_loss(
+        &self,
+        positive: ScalarTensorBase<S2, Ix2>,
+        negative: ScalarTensorBase<S2, Ix2>,
+        options: &TripletMarginLossOptions,
+    ) -> Result<Self::Output> {
+        let loss = self.triplet_margin_loss_per_sample(positive, negative, options)?;
+        options
+            .reduction
+            .reduce_tensor("TripletMarginLoss", loss.view())
+    }
+    fn triplet_margin_loss_per_sample(
+        &self,
+        positive: ScalarTensorBase<S2, Ix2>,
+        negative: ScalarTensorBase<S2, Ix2>,
+        options: &TripletMarginLossOptions,
+    ) -> Result<Self::PerSampleOutput> {
+        triplet_margin_loss_per_sample(self.view(), positive.view(), negative.view(), options)
+    }
+}
+
+/// Implemented for bf16 and f32.
+impl<T: Scalar + Float, S1: Data<Elem = T>, S2: Data<Elem = T>>
+    TripletMarginLoss<TensorBase<S2, Ix2>> for TensorBase<S1, Ix2>
+{
+    type Output = f32;
+    type PerSampleOutput = Tensor1<f32>;
+    fn triplet_margin_loss(
+        &self,
+        positive: TensorBase<S2, Ix2>,
+        negative: TensorBase<S2, Ix2>,
+        options: &TripletMarginLossOptions,
+    ) -> Result<Self::Output> {
+        ScalarTensorView::from(self.view()).triplet_margin_loss(
+            ScalarTensorView::from(positive.view()),
+            ScalarTensorView::from(negative.view()),
+            options,
+        )
+    }
+    fn triplet_margin_loss_per_sample(
+        &self,
+        positive: TensorBase<S2, Ix2>,
+        negative: TensorBase<S2, Ix2>,
+        options: &TripletMarginLossOptions,
+    ) -> Result<Self::PerSampleOutput> {
+        ScalarTensorView::from(self.view()).triplet_margin_loss_per_sample(
+            ScalarTensorView::from(positive.view()),
+            ScalarTensorView::from(negative.view()),
+            options,
+        )
+    }
+}
+
+// Checks that the inputs are all bf16 or f32 and have the same shape.
+pub(crate) fn check_float_inputs<D: Dimension>(
+    name: &str,
+    inputs: &[ScalarTensorView<D>],
+) -> Result<()> {
+    let scalar_types: Vec<_> = inputs.iter().map(|x| x.scalar_type()).collect();
+    if !matches!(scalar_types[0], ScalarType::BF16 | ScalarType::F32)
+        || scalar_types.iter().any(|x| *x != scalar_types[0])
+    {
+        bail!("{name} {scalar_types:?} unimplemented!");
+    }
+    let shapes: Vec<_> = inputs.iter().map(|x| x.shape()).collect();
+    if shapes.iter().any(|x| *x != shapes[0]) {
+        bail!("{name} expected inputs with the same shape, found {shapes:?}!");
+    }
+    Ok(())
+}
+
+// Converts the inputs to f32 arrays on the host, see `check_float_inputs()`.
+pub(crate) fn float_arrays<D: Dimension, const N: usize>(
+    name: &str,
+    inputs: [ScalarTensorView<D>; N],
+) -> Result<[Array<f32, D>; N]> {
+    check_float_inputs(name, &inputs)?;
+    let arrays = inputs
+        .into_iter()
+        .map(|x| {
+            x.to_device(Device::host())?
+                .cast_into_tensor::<f32>()?
+                .into_array()
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(arrays.try_into().unwrap())
+}
+
+// Returns the distance and the gradients of x1 and x2.
+fn distance_backward(
+    x1: ArrayView1<f32>,
+    x2: ArrayView1<f32>,
+    distance: Distance,
+) -> (f32, Array1<f32>, Array1<f32>) {
+    let eps = f32::EPSILON;
+    match distance {
+        Distance::L2 => {
+            let diff = &x1 - &x2;
+            let d = diff.dot(&diff).sqrt();
+            let dx1 = diff.mapv(|x| if d > eps { x / d } else { 0. });
+            let dx2 = dx1.mapv(|x| -x);
+            (d, dx1, dx2)
+        }
+        Distance::Cosine => {
+            let n1 = x1.dot(&x1).sqrt().max(eps);
+            let n2 = x2.dot(&x2).sqrt().max(eps);
+            let cos = x1.dot(&x2) / (n1 * n2);
+            let dx1 = &x1 * (cos / (n1 * n1)) - &x2 / (n1 * n2);
+            let dx2 = &x2 * (cos / (n2 * n2)) - &x1 / (n1 * n2);
+            (1. - cos, dx1, dx2)
+        }
+    }
+}
+
+fn triplet_margin_loss_per_sample_host(
+    anchor: ArrayView2<f32>,
+    positive: ArrayView2<f32>,
+    negative: ArrayView2<f32>,
+    options: &TripletMarginLossOptions,
+) -> Array1<f32> {
+    anchor
+        .outer_iter()
+        .zip(positive.outer_iter())
+        .zip(negative.outer_iter())
+        .map(|((a, p), n)| {
+            let (d_ap, _, _) = distance_backward(a, p, options.distance);
+            let (d_an, _, _) = distance_backward(a, n, options.distance);
+            (d_ap - d_an + options.margin).max(0.)
+        })
+        .collect()
+}
+
+// Computes the gradients given the gradient of the loss of each sample.
+fn triplet_margin_loss_backward_host(
+    anchor: ArrayView2<f32>,
+    positive: ArrayView2<f32>,
+    negative: ArrayView2<f32>,
+    options: &TripletMarginLossOptions,
+    dy: ArrayView1<f32>,
+) -> [Array2<f32>; 3] {
+    let mut da = Array2::zeros(anchor.raw_dim());
+    let mut dp = Array2::zeros(positive.raw_dim());
+    let mut dn = Array2::zeros(negative.raw_dim());
+    for (i, (((a, p), n), dy)) in anchor
+        .outer_iter()
+        .zip(positive.outer_iter())
+        .zip(negative.outer_iter())
+        .zip(dy.iter().copied())
+        .enumerate()
+    {
+        let (d_ap, da_ap, dp_ap) = distance_backward(a, p, options.distance);
+        let (d_an, da_an, dn_an) = distance_backward(a, n, options.distance);
+        if d_ap - d_an + options.margin > 0. {
+            da.index_axis_mut(Axis(0), i)
+                .assign(&((da_ap - da_an) * dy));
+            dp.index_axis_mut(Axis(0), i).assign(&(dp_ap * dy));
+            dn.index_axis_mut(Axis(0), i).assign(&(dn_an * -dy));
+        }
+    }
+    [da, dp, dn]
+}
+
+// Computes the loss of each sample.
+pub(crate) fn triplet_margin_loss_per_sample(
+    anchor: ScalarTensorView2,
+    positive: ScalarTensorView2,
+    negative: ScalarTensorView2,
+    options: &TripletMarginLossOptions,
+) -> Result<Tensor1<f32>> {
+    check_float_inputs(
+        "TripletMarginLoss",
+        &[anchor.view(), positive.view(), negative.view()],
+    )?;
+    if anchor.device().is_host() {
+        let [anchor, positive, negative] =
+            float_arrays("TripletMarginLoss", [anchor, positive, negative])?;
+        return Ok(triplet_margin_loss_per_sample_host(
+            anchor.view(),
+            positive.view(),
+            negative.view(),
+            options,
+        )
+        .into());
+    }
+    #[cfg(not(feature = "device"))]
+    {
+        unreachable!()
+    }
+    #[cfg(feature = "device")]
+    {
+        let (batch_size, features) = anchor.dim();
+        macro_for!($T in [bf16, f32] {
+            if let Ok(a) = TensorView2::<$T>::try_from(anchor.view()) {
+                let a = a.as_standard_layout()?;
+                let p = TensorView2::<$T>::try_from(positive.view()).unwrap().as_standard_layout()?;
+                let n = TensorView2::<$T>::try_from(negative.view()).unwrap().as_standard_layout()?;
+                let mut y = unsafe { Tensor::<f32, _>::uninit(a.device(), batch_size)? };
+                let kernel = paste! {
+                    kernels::[<triplet_margin_loss_ $T>]::builder()?.build(y.device())?
+                };
+                kernel.dispatch(
+                    a.as_slice().unwrap(),
+                    p.as_slice().unwrap(),
+                    n.as_slice().unwrap(),
+                    features.to_u32().unwrap(),
+                    options.margin,
+                    options.distance.as_u32(),
+                    y.as_slice_mut().unwrap(),
+                )?;
+                return Ok(y);
+            }
+        });
+        unreachable!()
+    }
+}
+
+// Computes the gradients of the anchor, positive, and negative given the gradient of the loss of
+// each sample.
+pub(crate) fn triplet_margin_loss_backward(
+    anchor: ScalarTensorView2,
+    positive: ScalarTensorView2,
+    negative: ScalarTensorView2,
+    options: &TripletMarginLossOptions,
+    output_grad: TensorView1<f32>,
+) -> Result<[ScalarTensor2; 3]> {
+    let scalar_type = anchor.scalar_type();
+    if let Some(dy) = output_grad.as_array() {
+        let [anchor, positive, negative] =
+            float_arrays("TripletMarginLoss", [anchor, positive, negative])?;
+        let [da, dp, dn] = triplet_margin_loss_backward_host(
+            anchor.view(),
+            positive.view(),
+            negative.view(),
+            options,
+            dy,
+        );
+        return Ok([
+            ScalarTensor::from(Tensor::from(da)).cast_into(scalar_type)?,
+            ScalarTensor::from(Tensor::from(dp)).cast_into(scalar_type)?,
+            ScalarTensor::from(Tensor::from(dn)).cast_into(scalar_type)?,
+        ]);
+    }
+    #[cfg(not(feature = "device"))]
+    {
+        unreachable!()
+    }
+    #[cfg(feature = "device")]
+    {
+        let (batch_size, features) = anchor.dim();
+        macro_for!($T in [bf16, f32] {
+            if let Ok(a) = TensorView2::<$T>::try_from(anchor.view()) {
+                let a = a.as_standard_layout()?;
+                let p = TensorView2::<$T>::try_from(positive.view()).unwrap().as_standard_layout()?;
+                let n = TensorView2::<$T>::try_from(negative.view()).unwrap().as_standard_layout()?;
+                let dy = output_grad.as_standard_layout()?;
+                let device = a.device();
+                let mut da = unsafe { Tensor::<$T, _>::uninit(device.clone(), a.raw_dim())? };
+                let mut dp = unsafe { Tensor::<$T, _>::uninit(device.clone(), a.raw_dim())? };
+                let mut dn = unsafe { Tensor::<$T, _>::uninit(device.clone(), a.raw_dim())? };
+                let kernel = paste! {
+                    kernels::[<triplet_margin_loss_backward_ $T>]::builder()?
+                        .with_threads(256)
+                        .build(device)?
+                };
+                kernel
+                    .with_global_threads(batch_size.to_u32().unwrap())
+                    .dispatch(
+                        a.as_slice().unwrap(),
+                        p.as_slice().unwrap(),
+                        n.as_slice().unwrap(),
+                        features.to_u32().unwrap(),
+                        options.margin,
+                        options.distance.as_u32(),
+                        dy.as_slice().unwrap(),
+                        da.as_slice_mut().unwrap(),
+                        dp.as_slice_mut().unwrap(),
+                        dn.as_slice_mut().unwrap(),
+                    )?;
+                return Ok([da.into(), dp.into(), dn.into()]);
+            }
+        });
+        unreachable!()
+    }
+}
+
+/// InfoNCE Loss.
+///
+/// The normalized temperature-scaled cross entropy loss (NT-Xent) used for contrastive learning,
+/// ie SimCLR.
+///
+/// Computes the cosine similarity of each row of `self` with each row of `positive`, divided by
+/// `temperature`, and then the cross entropy of each row of the similarity matrix. The target of
+/// row `i` is `i`, such that `positive[i]` is the positive of `self[i]` and the other rows of
+/// `positive` are its negatives.
+///
+/// Returns the mean over the batch.
+///
+/// Shapes:
+/// - self, positive: \[batch_size, features\]
+pub trait InfoNCELoss<T> {
+    /// Type of the output.
+    type Output;
+    /// Computes the loss of `self` given `positive`, scaling the similarities by `1 / temperature`.
+    fn info_nce_loss(&self, positive: T, temperature: f32) -> Result<Self::Output>;
+}
+
+/// Implemented for bf16 and f32.
+///
+/// Computed on the host.
+impl<S1: ScalarData, S2: ScalarData> InfoNCELoss<ScalarTensorBase<S2, Ix2>>
+    for ScalarTensorBase<S1, Ix2>
+{
+    type Output = f32;
+    fn info_nce_loss(
+        &self,
+        positive: ScalarTensorBase<S2, Ix2>,
+        temperature: f32,
+    ) -> Result<Self::Output> {
+        let [anchor, positive] = float_arrays("InfoNCELoss", [self.view(), positive.view()])?;
+        Ok(info_nce_loss_host(
+            anchor.view(),
+            positive.view(),
+            temperature,
+        ))
+    }
+}
+
+/// Implemented for bf16 and f32.
+///
+/// Computed on the host.
+impl<T: Scalar + Float, S1: Data<Elem = T>, S2: Data<Elem = T>> InfoNCELoss<TensorBase<S2, Ix2>>
+    for TensorBase<S1, Ix2>
+{
+    type Output = f32;
+    fn info_nce_loss(&self, positive: TensorBase<S2, Ix2>, temperature: f32) -> Result<f32> {
+        ScalarTensorView::from(self.view())
+            .info_nce_loss(ScalarTensorView::from(positive.view()), temperature)
+    }
+}
+
+// Returns the rows of x normalized to unit length and their norms.
+fn normalize_rows(x: ArrayView2<f32>) -> (Array2<f32>, Array1<f32>) {
+    let norms: Array1<f32> = x
+        .outer_iter()
+        .map(|x| x.dot(&x).sqrt().max(f32::EPSILON))
+        .collect();
+    let x = &x / &norms.view().insert_axis(Axis(1));
+    (x, norms)
+}
+
+// Returns the cosine similarity matrix divided by the temperature.
+fn info_nce_similarity(
+    anchor: ArrayView2<f32>,
+    positive: ArrayView2<f32>,
+    temperature: f32,
+) -> Array2<f32> {
+    let (anchor, _) = normalize_rows(anchor);
+    let (positive, _) = normalize_rows(positive);
+    anchor.dot(&positive.t()) / temperature
+}
+
+fn info_nce_loss_host(anchor: ArrayView2<f32>, positive: ArrayView2<f32>, temperature: f32) -> f32 {
+    let similarity = info_nce_similarity(anchor, positive, temperature);
+    let loss: Array1<f32> = similarity
+        .outer_iter()
+        .enumerate()
+        .map(|(i, x)| {
+            let m = x.fold(f32::NEG_INFINITY, |m, x| m.max(*x));
+            let s: f32 = x.iter().map(|x| (x - m).exp()).sum();
+            s.ln() + m - x[i]
+        })
+        .collect();
+    loss.mean().unwrap_or_default()
+}
+
+/// Mean Squared Error Loss.
+///
+/// Computes `mean((input - target)^2)` over all elements.
+///
+/// Shapes:
+/// - input, target: any, the same for both
+pub trait MeanSquaredError<T> {
+    /// Type of the output.
+    type Output;
+    /// Type of the output with a [`Reduction`].
+    type ReducedOutput;
+    /// Computes the loss given `target`.
+    fn mse_loss(&self, target: T) -> Result<Self::Output>;
+    /// Computes the loss given `target` with `reduction`.
+    ///
+    /// Returns the loss with shape \[\] for [`Reduction::Mean`] and [`Reduction::Sum`]. For
+    /// [`Reduction::None`], returns the loss of each element with the shape of the input.
+    fn mse_loss_with(&self, target: T, reduction: Reduction) -> Result<Self::ReducedOutput>;
+}
+
+/// Implemented for bf16 and f32.
+impl<S1: ScalarData, S2: ScalarData, D: Dimension> MeanSquaredError<ScalarTensorBase<S2, D>>
+    for ScalarTensorBase<S1, D>
+{
+    type Output = f32;
+    type ReducedOutput = TensorD<f32>;
+    fn mse_loss(&self, target: ScalarTensorBase<S2, D>) -> Result<f32> {
+        let loss = elementwise_loss(
+            "MeanSquaredError",
+            ElementwiseLoss::MeanSquaredError,
+            self.view(),
+            target.view(),
+        )?;
+        Reduction::Mean.reduce_tensor("MeanSquaredError", loss.view())
+    }
+    fn mse_loss_with(
+        &self,
+        target: ScalarTensorBase<S2, D>,
+        reduction: Reduction,
+    ) -> Result<TensorD<f32>> {
+        let loss = elementwise_loss(
+            "MeanSquaredError",
+            ElementwiseLoss::MeanSquaredError,
+            self.view(),
+            target.view(),
+        )?;
+        reduce_loss_tensor("MeanSquaredError", loss, reduction)
+    }
+}
+
+/// Implemented for bf16 and f32.
+impl<T: Scalar + Float, S1: Data<Elem = T>, S2: Data<Elem = T>, D: Dimension>
+    MeanSquaredError<TensorBase<S2, D>> for TensorBase<S1, D>
+{
+    type Output = f32;
+    type ReducedOutput = TensorD<f32>;
+    fn mse_loss(&self, target: TensorBase<S2, D>) -> Result<f32> {
+        ScalarTensorView::from(self.view()).mse_loss(ScalarTensorView::from(target.view()))
+    }
+    fn mse_loss_with(
+        &self,
+        target: TensorBase<S2, D>,
+        reduction: Reduction,
+    ) -> Result<TensorD<f32>> {
+        ScalarTensorView::from(self.view())
+            .mse_loss_with(ScalarTensorView::from(target.view()), reduction)
+    }
+}
+
+// Losses computed for each element of the input and the target, see `elementwise_loss()`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum ElementwiseLoss {
+    MeanSquaredError,
+    MeanAbsoluteError,
+    Huber { delta: f32 },
+    BinaryCrossEntropy,
+}
+
+impl ElementwiseLoss {
+    // The loss and its parameter, passed to `kernels::elementwise_loss_impl()`.
+    fn kernel_args(self) -> (u32, f32) {
+        match self {
+            Self::MeanSquaredError => (1, 0.),
+            Self::MeanAbsoluteError => (2, 0.),
+            Self::Huber { delta } => (3, delta),
+            Self::BinaryCrossEntropy => (4, 0.),
+        }
+    }
+}
+
+// Computes the loss of each element.
+pub(crate) fn elementwise_loss<D: Dimension>(
+    name: &str,
+    loss: ElementwiseLoss,
+    input: ScalarTensorView<D>,
+    target: ScalarTensorView<D>,
+) -> Result<Tensor<f32, D>> {
+    check_float_inputs(name, &[input.view(), target.view()])?;
+    let (loss, delta) = loss.kernel_args();
+    macro_for!($T in [bf16, f32] {
+        if let Ok(x) = TensorView::<$T, D>::try_from(input.view()) {
+            let t = TensorView::<$T, D>::try_from(target.view()).unwrap();
+            if let Some((x, t)) = x.as_array().zip(t.as_array()) {
+                let y = Zip::from(&x)
+                    .and(&t)
+                    .map_collect(|x, t| kernels::elementwise_loss_impl(loss, delta, x.cast(), t.cast()));
+                return Ok(y.into());
+            }
+            #[cfg(feature = "device")]
+            {
+                let x = x.as_standard_layout()?;
+                let t = t.as_standard_layout()?;
+                let mut y = unsafe { Tensor::<f32, D>::uninit(x.device(), x.raw_dim())? };
+                let kernel = paste! {
+                    kernels::[<elementwise_loss_ $T>]::builder()?.build(y.device())?
+                };
+                kernel.dispatch(
+                    loss,
+                    delta,
+                    x.as_slice().unwrap(),
+                    t.as_slice().unwrap(),
+                    y.as_slice_mut().unwrap(),
+                )?;
+                return Ok(y);
+            }
+        }
+    });
+    unreachable!()
+}
+
+// Computes the gradient of the input given the gradient of the loss of each element.
+pub(crate) fn elementwise_loss_backward<D: Dimension>(
+    loss: ElementwiseLoss,
+    input: ScalarTensorView<D>,
+    target: ScalarTensorView<D>,
+    output_grad: TensorView<f32, D>,
+) -> Result<ScalarTensor<D>> {
+    let (loss, delta) = loss.kernel_args();
+    macro_for!($T in [bf16, f32] {
+        if let Ok(x) = TensorView::<$T, D>::try_from(input.view()) {
+            let t = TensorView::<$T, D>::try_from(target.view()).unwrap();
+            if let Some(((x, t), dy)) = x.as_array().zip(t.as_array()).zip(output_grad.as_array()) {
+                let dx = Zip::from(&x).and(&t).and(&dy).map_collect(|x, t, dy| {
+                    (dy * kernels::elementwise_loss_grad_impl(loss, delta, x.cast(), t.cast())).cast::<$T>()
+                });
+                return Ok(Tensor::from(dx).into());
+            }
+            #[cfg(feature = "device")]
+            {
+                let x = x.as_standard_layout()?;
+                let t = t.as_standard_layout()?;
+                let dy = output_grad.as_standard_layout()?;
+                let mut dx = unsafe { Tensor::<$T, D>::uninit(x.device(), x.raw_dim())? };
+                let kernel = paste! {
+                    kernels::[<elementwise_loss_backward_ $T>]::builder()?.build(dx.device())?
+                };
+                kernel.dispatch(
+                    loss,
+                    delta,
+                    x.as_slice().unwrap(),
+                    t.as_slice().unwrap(),
+                    dy.as_slice().unwrap(),
+                    dx.as_slice_mut().unwrap(),
+                )?;
+                return Ok(dx.into());
+            }
+        }
+    });
+    unreachable!()
+}
+
+/// Mean Absolute Error Loss, ie L1 Loss.
+///
+/// Computes `mean(|input - target|)` over all elements. Less sensitive to outliers than
+/// [`MeanSquaredError`].
+///
+/// The gradient is `sign(input - target) / n`, 0 where the input is equal to the target.
+///
+/// Shapes:
+/// - input, target: any, the same for both
+pub trait MeanAbsoluteError<T> {
+    /// Type of the output.
+    type Output;
+    /// Type of the output with a [`Reduction`].
+    type ReducedOutput;
+    /// Computes the loss given `target`.
+    fn l1_loss(&self, target: T) -> Result<Self::Output>;
+    /// Computes the loss given `target` with `reduction`.
+    ///
+    /// Returns the loss with shape \[\] for [`Reduction::Mean`] and [`Reduction::Sum`]. For
+    /// [`Reduction::None`], returns the loss of each element with the shape of the input.
+    fn l1_loss_with(&self, target: T, reduction: Reduction) -> Result<Self::ReducedOutput>;
+}
+
+/// Implemented for bf16 and f32.
+impl<S1: ScalarData, S2: ScalarData, D: Dimension> MeanAbsoluteError<ScalarTensorBase<S2, D>>
+    for ScalarTensorBase<S1, D>
+{
+    type Output = f32;
+    type ReducedOutput = TensorD<f32>;
+    fn l1_loss(&self, target: ScalarTensorBase<S2, D>) -> Result<f32> {
+        let loss = elementwise_loss(
+            "MeanAbsoluteError",
+            ElementwiseLoss::MeanAbsoluteError,
+            self.view(),
+            target.view(),
+        )?;
+        Reduction::Mean.reduce_tensor("MeanAbsoluteError", loss.view())
+    }
+    fn l1_loss_with(
+        &self,
+        target: ScalarTensorBase<S2, D>,
+        reduction: Reduction,
+    ) -> Result<TensorD<f32>> {
+        let loss = elementwise_loss(
+            "MeanAbsoluteError",
+            ElementwiseLoss::MeanAbsoluteError,
+            self.view(),
+            target.view(),
+        )?;
+        reduce_loss_tensor("MeanAbsoluteError", loss, reduction)
+    }
+}
+
+/// Implemented for bf16 and f32.
+impl<T: Scalar + Float, S1: Data<Elem = T>, S2: Data<Elem = T>, D: Dimension>
+    MeanAbsoluteError<TensorBase<S2, D>> for TensorBase<S1, D>
+{
+    type Output = f32;
+    type ReducedOutput = TensorD<f32>;
+    fn l1_loss(&self, target: TensorBase<S2, D>) -> Result<f32> {
+        ScalarTensorView::from(self.view()).l1_loss(ScalarTensorView::from(target.view()))
+    }
+    fn l1_loss_with(
+        &self,
+        target: TensorBase<S2, D>,
+        reduction: Reduction,
+    ) -> Result<TensorD<f32>> {
+        ScalarTensorView::from(self.view())
+            .l1_loss_with(ScalarTensorView::from(target.view()), reduction)
+    }
+}
+
+/// Huber Loss, ie Smooth L1 Loss.
+///
+/// Computes the mean over all elements of
+/// - `0.5 * (input - target)^2` where `|input - target| <= delta`
+/// - `delta * (|input - target| - 0.5 * delta)` otherwise
+///
+/// Quadratic like [`MeanSquaredError`] for small errors and linear like [`MeanAbsoluteError`]
+/// for large errors. The gradient is `clamp(input - target, -delta, delta) / n`.
+///
+/// Shapes:
+/// - input, target: any, the same for both
+pub trait HuberLoss<T> {
+    /// Type of the output.
+    type Output;
+    /// Type of the output with a [`Reduction`].
+    type ReducedOutput;
+    /// Computes the loss given `target`.
+    ///
+    /// # Errors
+    /// The `delta` must be positive.
+    fn huber_loss(&self, target: T, delta: f32) -> Result<Self::Output>;
+    /// Computes the loss given `target` with `reduction`.
+    ///
+    /// Returns the loss with shape \[\] for [`Reduction::Mean`] and [`Reduction::Sum`]. For
+    /// [`Reduction::None`], returns the loss of each element with the shape of the input.
+    ///
+    /// # Errors
+    /// The `delta` must be positive.
+    fn huber_loss_with(
+        &self,
+        target: T,
+        delta: f32,
+        reduction: Reduction,
+    ) -> Result<Self::ReducedOutput>;
+}
+
+/// Implemented for bf16 and f32.
+impl<S1: ScalarData, S2: ScalarData, D: Dimension> HuberLoss<ScalarTensorBase<S2, D>>
+    for ScalarTensorBase<S1, D>
+{
+    type Output = f32;
+    type ReducedOutput = TensorD<f32>;
+    fn huber_loss(&self, target: ScalarTensorBase<S2, D>, delta: f32) -> Result<f32> {
+        check_huber_loss_delta(delta)?;
+        let loss = elementwise_loss(
+            "HuberLoss",
+            ElementwiseLoss::Huber { delta },
+            self.view(),
+            target.view(),
+        )?;
+        Reduction::Mean.reduce_tensor("HuberLoss", loss.view())
+    }
+    fn huber_loss_with(
+        &self,
+        target: ScalarTensorBase<S2, D>,
+        delta: f32,
+        reduction: Reduction,
+    ) -> Result<TensorD<f32>> {
+        check_huber_loss_delta(delta)?;
+        let loss = elementwise_loss(
+            "HuberLoss",
+            ElementwiseLoss::Huber { delta },
+            self.view(),
+            target.view(),
+        )?;
+        reduce_loss_tensor("HuberLoss", loss, reduction)
+    }
+}
+
+/// Implemented for bf16 and f32.
+impl<T: Scalar + Float, S1: Data<Elem = T>, S2: Data<Elem = T>, D: Dimension>
+    HuberLoss<TensorBase<S2, D>> for TensorBase<S1, D>
+{
+    type Output = f32;
+    type ReducedOutput = TensorD<f32>;
+    fn huber_loss(&self, target: TensorBase<S2, D>, delta: f32) -> Result<f32> {
+        ScalarTensorView::from(self.view()).huber_loss(ScalarTensorView::from(target.view()), delta)
+    }
+    fn huber_loss_with(
+        &self,
+        target: TensorBase<S2, D>,
+        delta: f32,
+        reduction: Reduction,
+    ) -> Result<TensorD<f32>> {
+        ScalarTensorView::from(self.view()).huber_loss_with(
+            ScalarTensorView::from(target.view()),
+            delta,
+            reduction,
+        )
+    }
+}
+
+pub(crate) fn check_huber_loss_delta(delta: f32) -> Result<()> {
+    if delta.is_nan() || delta <= 0. {
+        bail!("HuberLoss delta {delta} must be positive!");
+    }
+    Ok(())
+}
+
+/// Binary Cross Entropy Loss.
+///
+/// Computes `mean(max(x, 0) - x * t + ln(1 + exp(-|x|)))` over all elements, where the input `x`
+/// are logits and the target `t` are probabilities in \[0, 1\]. Equivalent to the cross entropy
+/// of `sigmoid(x)` and `t`, but numerically stable.
+///
+/// Shapes:
+/// - input, target: any, the same for both
+pub trait BinaryCrossEntropyLoss<T> {
+    /// Type of the output.
+    type Output;
+    /// Type of the output with a [`Reduction`].
+    type ReducedOutput;
+    /// Computes the loss given `target`.
+    ///
+    /// # Errors
+    /// The `target` must be in \[0, 1\].
+    fn bce_loss(&self, target: T) -> Result<Self::Output>;
+    /// Computes the loss given `target` with `reduction`.
+    ///
+    /// Returns the loss with shape \[\] for [`Reduction::Mean`] and [`Reduction::Sum`]. For
+    /// [`Reduction::None`], returns the loss of each element with the shape of the input.
+    ///
+    /// # Errors
+    /// The `target` must be in \[0, 1\].
+    fn bce_loss_with(&self, target: T, reduction: Reduction) -> Result<Self::ReducedOutput>;
+}
+
+/// Implemented for bf16 and f32.
+impl<S1: ScalarData, S2: ScalarData, D: Dimension> BinaryCrossEntropyLoss<ScalarTensorBase<S2, D>>
+    for ScalarTensorBase<S1, D>
+{
+    type Output = f32;
+    type ReducedOutput = TensorD<f32>;
+    fn bce_loss(&self, target: ScalarTensorBase<S2, D>) -> Result<f32> {
+        let loss = elementwise_loss(
+            "BinaryCrossEntropyLoss",
+            ElementwiseLoss::BinaryCrossEntropy,
+            self.view(),
+            target.view(),
+        )?;
+        check_bce_loss_target(target.view())?;
+        Reduction::Mean.reduce_tensor("BinaryCrossEntropyLoss", loss.view())
+    }
+    fn bce_loss_with(
+        &self,
+        target: ScalarTensorBase<S2, D>,
+        reduction: Reduction,
+    ) -> Result<TensorD<f32>> {
+        let loss = elementwise_loss(
+            "BinaryCrossEntropyLoss",
+            ElementwiseLoss::BinaryCrossEntropy,
+            self.view(),
+            target.view(),
+        )?;
+        check_bce_loss_target(target.view())?;
+        reduce_loss_tensor("BinaryCrossEntropyLoss", loss, reduction)
+    }
+}
+
+/// Implemented for bf16 and f32.
+impl<T: Scalar + Float, S1: Data<Elem = T>, S2: Data<Elem = T>, D: Dimension>
+    BinaryCrossEntropyLoss<TensorBase<S2, D>> for TensorBase<S1, D>
+{
+    type Output = f32;
+    type ReducedOutput = TensorD<f32>;
+    fn bce_loss(&self, target: TensorBase<S2, D>) -> Result<f32> {
+        ScalarTensorView::from(self.view()).bce_loss(ScalarTensorView::from(target.view()))
+    }
+    fn bce_loss_with(
+        &self,
+        target: TensorBase<S2, D>,
+        reduction: Reduction,
+    ) -> Result<TensorD<f32>> {
+        ScalarTensorView::from(self.view())
+            .bce_loss_with(ScalarTensorView::from(target.view()), reduction)
+    }
+}
+
+// Checks that the target is in [0, 1], with reductions on the device of `target`.
+pub(crate) fn check_bce_loss_target<D: Dimension>(target: ScalarTensorView<D>) -> Result<()> {
+    if target.is_empty() {
+        return Ok(());
+    }
+    let target = target.as_standard_layout()?;
+    let target = target.view().into_shape(target.len())?;
+    macro_for!($T in [bf16, f32] {
+        if let Ok(target) = TensorView1::<$T>::try_from(target.view()) {
+            let reduce = |init: $T, op: ReduceOp| -> Result<$T> {
+                Ok(target
+                    .reduce(Axis(0), init, op)?
+                    .into_device(Device::host())?
+                    .into_array()?
+                    .into_scalar())
+            };
+            let min = reduce(1f32.cast(), ReduceOp::Min)?;
+            let max = reduce(0f32.cast(), ReduceOp::Max)?;
+            for t in [min, max] {
+                if !(0. ..=1.).contains(&t.cast::<f32>()) {
+                    bail!("BinaryCrossEntropyLoss expected target in [0, 1], found {t}!");
+                }
+            }
+        }
+    });
+    Ok(())
+}
+
+#[cfg_attr(feature = "device", module)]
 mod kernels {
+    #[cfg(any(feature = "device", target_arch = "spirv"))]
     use dry::macro_for;
     #[cfg(not(target_arch = "spirv"))]
     use krnl::krnl_core;
+    #[cfg(any(feature = "device", target_arch = "spirv"))]
     use krnl_core::macros::kernel;
     #[cfg(target_arch = "spirv")]
-    use krnl_core::{half::bf16, num_traits::Float, scalar::Scalar};
+    use krnl_core::{buffer::UnsafeIndex, half::bf16, num_traits::Float, scalar::Scalar};
+    #[cfg(any(feature = "device", target_arch = "spirv"))]
     use paste::paste;
 
+    // The loss of input x given target t, see `ElementwiseLoss`.
+    pub fn elementwise_loss_impl(loss: u32, delta: f32, x: f32, t: f32) -> f32 {
+        match loss {
+            1 => (x - t) * (x - t),
+            2 => (x - t).abs(),
+            3 => {
+                let d = (x - t).abs();
+                if d <= delta {
+                    0.5 * d * d
+                } else {
+                    delta * (d - 0.5 * delta)
+                }
+            }
+            // x are logits
+            4 => x.max(0.) - x * t + (-x.abs()).exp().ln_1p(),
+            _ => 0.,
+        }
+    }
+
+    // The derivative of `elementwise_loss_impl()` with respect to x.
+    pub fn elementwise_loss_grad_impl(loss: u32, delta: f32, x: f32, t: f32) -> f32 {
+        match loss {
+            1 => 2. * (x - t),
+            // f32::signum returns 1 for 0
+            2 => {
+                if x > t {
+                    1.
+                } else if x < t {
+                    -1.
+                } else {
+                    0.
+                }
+            }
+            3 => (x - t).clamp(-delta, delta),
+            4 => 1. / (1. + (-x).exp()) - t,
+            _ => 0.,
+        }
+    }
+
+    // The distance, see `Distance`, given the squared L2 distance d2 and the dot products of x1
+    // and x2.
+    #[cfg(any(feature = "device", target_arch = "spirv"))]
+    pub fn distance_impl(distance: u32, d2: f32, x11: f32, x22: f32, x12: f32) -> f32 {
+        if distance == 0 {
+            d2.sqrt()
+        } else {
+            let eps = f32::EPSILON;
+            1. - x12 / (x11.sqrt().max(eps) * x22.sqrt().max(eps))
+        }
+    }
+
+    // The gradients of the distance d, see `distance_impl()`, with respect to x1 and x2.
+    #[allow(clippy::too_many_arguments)]
+    #[cfg(any(feature = "device", target_arch = "spirv"))]
+    pub fn distance_grad_impl(
+        distance: u32,
+        d: f32,
+        x1: f32,
+        x2: f32,
+        x11: f32,
+        x22: f32,
+        x12: f32,
+    ) -> [f32; 2] {
+        let eps = f32::EPSILON;
+        if distance == 0 {
+            let dx1 = if d > eps { (x1 - x2) / d } else { 0. };
+            [dx1, -dx1]
+        } else {
+            let n1 = x11.sqrt().max(eps);
+            let n2 = x22.sqrt().max(eps);
+            let cos = x12 / (n1 * n2);
+            [
+                x1 * (cos / (n1 * n1)) - x2 / (n1 * n2),
+                x2 * (cos / (n2 * n2)) - x1 / (n1 * n2),
+            ]
+        }
+    }
+
+    #[cfg(any(feature = "device", target_arch = "spirv"))]
+    macro_for!($T in [bf16, f32] {
+        paste! {
+            #[kernel]
+            pub fn [<triplet_margin_loss_ $T>](
+                #[global] a: Slice<$T>,
+                #[global] p: Slice<$T>,
+                #[global] n: Slice<$T>,
+                features: u32,
+                margin: f32,
+                distance: u32,
+                #[item] y: &mut f32,
+            ) {
+                let features = features as usize;
+                let start = kernel.item_id() * features;
+                let [mut d2_ap, mut d2_an, mut aa, mut pp, mut nn, mut ap, mut an] = [0f32; 7];
+                for i in start..start + features {
+                    let (x_a, x_p, x_n) = (a[i].cast::<f32>(), p[i].cast::<f32>(), n[i].cast::<f32>());
+                    d2_ap += (x_a - x_p) * (x_a - x_p);
+                    d2_an += (x_a - x_n) * (x_a - x_n);
+                    aa += x_a * x_a;
+                    pp += x_p * x_p;
+                    nn += x_n * x_n;
+                    ap += x_a * x_p;
+                    an += x_a * x_n;
+                }
+                let d_ap = distance_impl(distance, d2_ap, aa, pp, ap);
+                let d_an = distance_impl(distance, d2_an, aa, nn, an);
+                *y = (d_ap - d_an + margin).max(0.);
+            }
+
+            #[allow(clippy::too_many_arguments)]
+            #[kernel]
+            pub fn [<triplet_margin_loss_backward_ $T>](
+                #[global] a: Slice<$T>,
+                #[global] p: Slice<$T>,
+                #[global] n: Slice<$T>,
+                features: u32,
+                margin: f32,
+                distance: u32,
+                #[global] dy: Slice<f32>,
+                #[global] da: UnsafeSlice<$T>,
+                #[global] dp: UnsafeSlice<$T>,
+                #[global] dn: UnsafeSlice<$T>,
+            ) {
+                let idx = kernel.global_id();
+                if idx >= dy.len() {
+                    return;
+                }
+                let features = features as usize;
+                let start = idx * features;
+                let [mut d2_ap, mut d2_an, mut aa, mut pp, mut nn, mut ap, mut an] = [0f32; 7];
+                for i in start..start + features {
+                    let (x_a, x_p, x_n) = (a[i].cast::<f32>(), p[i].cast::<f32>(), n[i].cast::<f32>());
+                    d2_ap += (x_a - x_p) * (x_a - x_p);
+                    d2_an += (x_a - x_n) * (x_a - x_n);
+                    aa += x_a * x_a;
+                    pp += x_p * x_p;
+                    nn += x_n * x_n;
+                    ap += x_a * x_p;
+                    an += x_a * x_n;
+                }
+                let d_ap = distance_impl(distance, d2_ap, aa, pp, ap);
+                let d_an = distance_impl(distance, d2_an, aa, nn, an);
+                let dy = if d_ap - d_an + margin > 0. { dy[idx] } else { 0. };
+                for i in start..start + features {
+                    let (x_a, x_p, x_n) = (a[i].cast::<f32>(), p[i].cast::<f32>(), n[i].cast::<f32>());
+                    let [da_ap, dp_ap] = distance_grad_impl(distance, d_ap, x_a, x_p, aa, pp, ap);
+                    let [da_an, dn_an] = distance_grad_impl(distance, d_an, x_a, x_n, aa, nn, an);
+                    unsafe {
+                        *da.unsafe_index_mut(i) = ((da_ap - da_an) * dy).cast();
+                        *dp.unsafe_index_mut(i) = (dp_ap * dy).cast();
+                        *dn.unsafe_index_mut(i) = (-dn_an * dy).cast();
+                    }
+                }
+            }
+        }
+    });
+
+    #[cfg(any(feature = "device", target_arch = "spirv"))]
+    macro_for!($T in [bf16, f32] {
+        paste! {
+            #[kernel]
+            pub fn [<elementwise_loss_ $T>](
+                loss: u32,
+                delta: f32,
+                #[item] x: $T,
+                #[item] t: $T,
+                #[item] y: &mut f32,
+            ) {
+                *y = elementwise_loss_impl(loss, delta, x.cast(), t.cast());
+            }
+
+            #[kernel]
+            pub fn [<elementwise_loss_backward_ $T>](
+                loss: u32,
+                delta: f32,
+                #[item] x: $T,
+                #[item] t: $T,
+                #[item] dy: f32,
+                #[item] dx: &mut $T,
+            ) {
+                *dx = (dy * elementwise_loss_grad_impl(loss, delta, x.cast(), t.cast())).cast();
+            }
+        }
+    });
+
+    #[cfg(any(feature = "device", target_arch = "spirv"))]
+    macro_for!($T in [bf16, f32] {
+        paste! {
+            // The loss of each sample, -w * x[t] / total, or 0 if the total weight is 0.
+            #[kernel]
+            pub fn [<nll_loss_ $T>](
+                classes: u32,
+                #[global] x: Slice<$T>,
+                #[global] total: Slice<f32>,
+                #[item] t: u32,
+                #[item] w: f32,
+                #[item] y: &mut f32,
+            ) {
+                let total = total[0];
+                *y = if total != 0. {
+                    let x = x[kernel.item_id() * classes as usize + t as usize].cast::<f32>();
+                    -w * x / total
+                } else {
+                    0.
+                };
+            }
+        }
+    });
+
+    // Each element [t, y] counts the samples with target t that were predicted y.
+    #[cfg(any(feature = "device", target_arch = "spirv"))]
+    #[kernel]
+    pub fn confusion_matrix(
+        classes: u32,
+        #[global] t: Slice<u32>,
+        #[global] y: Slice<u32>,
+        #[item] matrix: &mut u32,
+    ) {
+        let idx = kernel.item_id() as u32;
+        let (row, col) = (idx / classes, idx % classes);
+        let mut count = 0;
+        for i in 0..t.len() {
+            if t[i] == row && y[i] == col {
+                count += 1;
+            }
+        }
+        *matrix = count;
+    }
+
+    #[cfg(any(feature = "device", target_arch = "spirv"))]
     macro_for!($T1 in [bf16, f32] {
         macro_for!($T2 in [u8, u16, u32] {
             paste! {
@@ -300,10 +2313,15 @@ mod kernels {
                     #[global] x: Slice<$T1>,
                     #[global] t: Slice<$T2>,
                     classes: u32,
+                    ignore_index: u32,
                     #[item] y: &mut f32,
                 ) {
                     let classes = classes as usize;
                     let idx = kernel.item_id();
+                    if t[idx] as u32 == ignore_index {
+                        *y = 0f32;
+                        return;
+                    }
                     let mut m = x[(idx * classes) as usize].cast::<f32>();
                     for i in 1..classes {
                         let x = x[(idx * classes + i) as usize].cast::<f32>();
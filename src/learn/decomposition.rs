@@ -0,0 +1,216 @@
+use crate::tensor::{ArcTensor, ArcTensor1, ArcTensor2, Tensor, Tensor2, TensorView1, TensorView2};
+use anyhow::{bail, Result};
+use krnl::device::Device;
+use ndarray::{Array1, Array2, ArrayView2, Axis};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Principal Component Analysis.
+///
+/// Projects data onto the directions of greatest variance, for dimensionality reduction and
+/// visualization.
+///
+/// The principal components are the eigenvectors of the covariance matrix with the largest
+/// eigenvalues. Computed on the host.
+///
+/// # Example
+///```
+/// # use autograph::{learn::decomposition::Pca, tensor::Tensor};
+/// # use ndarray::Array;
+/// # fn main() -> anyhow::Result<()> {
+/// let x = Tensor::from(Array::from_shape_fn([10, 3], |(i, j)| (i * (j + 1)) as f32));
+/// let mut pca = Pca::new(2);
+/// pca.fit(x.view())?;
+/// let y = pca.transform(x.view())?;
+/// assert_eq!(y.shape(), [10, 2]);
+/// # Ok(())
+/// # }
+///```
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Pca {
+    n_components: usize,
+    fit: Option<PcaFit>,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+struct PcaFit {
+    mean: ArcTensor1<f32>,
+    components: ArcTensor2<f32>,
+    explained_variance: ArcTensor1<f32>,
+}
+
+impl Pca {
+    /// Creates a new [`Pca`] that projects onto `n_components`.
+    pub fn new(n_components: usize) -> Self {
+        Self {
+            n_components,
+            fit: None,
+        }
+    }
+    /// The number of components.
+    pub fn n_components(&self) -> usize {
+        self.n_components
+    }
+    /// The mean of the data, with shape \[features\].
+    ///
+    /// `None` if not fitted.
+    pub fn mean(&self) -> Option<TensorView1<f32>> {
+        self.fit.as_ref().map(|fit| fit.mean.view())
+    }
+    /// The principal components, with shape \[n_components, features\].
+    ///
+    /// The components are orthonormal and sorted by decreasing explained variance. `None` if not
+    /// fitted.
+    pub fn components(&self) -> Option<TensorView2<f32>> {
+        self.fit.as_ref().map(|fit| fit.components.view())
+    }
+    /// The variance of the data along each component, with shape \[n_components\].
+    ///
+    /// `None` if not fitted.
+    pub fn explained_variance(&self) -> Option<TensorView1<f32>> {
+        self.fit.as_ref().map(|fit| fit.explained_variance.view())
+    }
+    /// Fits the principal components to `x`.
+    ///
+    /// Shapes:
+    /// - x: \[samples, features\]
+    ///
+    /// # Errors
+    /// - `x` is empty.
+    /// - `n_components` is 0 or greater than the number of features.
+    pub fn fit(&mut self, x: TensorView2<f32>) -> Result<()> {
+        let x_host;
+        let x = if let Some(x) = x.as_array() {
+            x
+        } else {
+            x_host = x.to_device(Device::host())?;
+            x_host.as_array().unwrap()
+        };
+        let (samples, features) = x.dim();
+        if samples == 0 {
+            bail!("Pca::fit x is empty!");
+        }
+        if self.n_components == 0 || self.n_components > features {
+            bail!(
+                "Pca n_components {} must be positive and at most features {features}!",
+                self.n_components
+            );
+        }
+        let x = x.mapv(f64::from);
+        let mean = x.mean_axis(Axis(0)).unwrap();
+        let x = x - &mean;
+        let covariance = x.t().dot(&x) / (samples.max(2) - 1) as f64;
+        let (eigenvalues, eigenvectors) = symmetric_eigen(covariance);
+        let mut order: Vec<usize> = (0..features).collect();
+        order.sort_by(|a, b| eigenvalues[*b].total_cmp(&eigenvalues[*a]));
+        let mut components = Array2::zeros([self.n_components, features]);
+        let mut explained_variance = Array1::zeros(self.n_components);
+        for ((index, mut component), variance) in order
+            .into_iter()
+            .zip(components.outer_iter_mut())
+            .zip(explained_variance.iter_mut())
+        {
+            let mut eigenvector = eigenvectors.column(index).mapv(|x| x as f32);
+            // deterministic sign, with the largest element positive
+            let max = eigenvector
+                .iter()
+                .copied()
+                .fold(0f32, |m, x| if x.abs() > m.abs() { x } else { m });
+            if max < 0. {
+                eigenvector.mapv_inplace(|x| -x);
+            }
+            component.assign(&eigenvector);
+            *variance = eigenvalues[index].max(0.) as f32;
+        }
+        self.fit.replace(PcaFit {
+            mean: ArcTensor::from(mean.mapv(|x| x as f32)),
+            components: ArcTensor::from(components),
+            explained_variance: ArcTensor::from(explained_variance),
+        });
+        Ok(())
+    }
+    /// Projects `x` onto the principal components.
+    ///
+    /// Shapes:
+    /// - x: \[samples, features\]
+    /// - output: \[samples, n_components\]
+    ///
+    /// # Errors
+    /// - Not fitted.
+    /// - The features of `x` do not match the fitted data.
+    pub fn transform(&self, x: TensorView2<f32>) -> Result<Tensor2<f32>> {
+        let Some(fit) = self.fit.as_ref() else {
+            bail!("Pca::transform not fitted!");
+        };
+        let mean = fit.mean.as_array().unwrap();
+        let components = fit.components.as_array().unwrap();
+        let (_, features) = x.dim();
+        if features != mean.len() {
+            bail!(
+                "Pca::transform expected {} features, found {features}!",
+                mean.len()
+            );
+        }
+        let device = x.device();
+        let x_host;
+        let x = if let Some(x) = x.as_array() {
+            x
+        } else {
+            x_host = x.to_device(Device::host())?;
+            x_host.as_array().unwrap()
+        };
+        let y = (&x - &mean).dot(&components.t());
+        Tensor::from(y).into_device(device)
+    }
+}
+
+// Eigendecomposition of a symmetric matrix with the cyclic Jacobi method.
+// Returns the eigenvalues and the eigenvectors as columns.
+fn symmetric_eigen(mut a: Array2<f64>) -> (Array1<f64>, Array2<f64>) {
+    let n = a.nrows();
+    let mut v = Array2::eye(n);
+    let total: f64 = a.iter().map(|x| x * x).sum();
+    for _ in 0..100 {
+        let off_diagonal = off_diagonal_norm_squared(a.view());
+        if off_diagonal <= f64::EPSILON * f64::EPSILON * total {
+            break;
+        }
+        for p in 0..n {
+            for q in p + 1..n {
+                let apq = a[[p, q]];
+                if apq == 0. {
+                    continue;
+                }
+                let theta = (a[[q, q]] - a[[p, p]]) / (2. * apq);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.).sqrt());
+                let c = 1. / (t * t + 1.).sqrt();
+                let s = t * c;
+                for k in 0..n {
+                    let (akp, akq) = (a[[k, p]], a[[k, q]]);
+                    a[[k, p]] = c * akp - s * akq;
+                    a[[k, q]] = s * akp + c * akq;
+                }
+                for k in 0..n {
+                    let (apk, aqk) = (a[[p, k]], a[[q, k]]);
+                    a[[p, k]] = c * apk - s * aqk;
+                    a[[q, k]] = s * apk + c * aqk;
+                }
+                for k in 0..n {
+                    let (vkp, vkq) = (v[[k, p]], v[[k, q]]);
+                    v[[k, p]] = c * vkp - s * vkq;
+                    v[[k, q]] = s * vkp + c * vkq;
+                }
+            }
+        }
+    }
+    (a.diag().to_owned(), v)
+}
+
+fn off_diagonal_norm_squared(a: ArrayView2<f64>) -> f64 {
+    a.indexed_iter()
+        .filter(|((i, j), _)| i != j)
+        .map(|(_, x)| x * x)
+        .sum()
+}
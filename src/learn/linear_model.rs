@@ -0,0 +1,358 @@
+use crate::tensor::{ArcTensor, ArcTensor1, Tensor, Tensor1, TensorView, TensorView1, TensorView2};
+use anyhow::{bail, Result};
+use krnl::{device::Device, scalar::Scalar};
+use ndarray::{s, Array1, Array2, ArrayView1, Axis, CowArray, Dimension};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Linear Regression.
+///
+/// Fits `y = x · weights + intercept` by least squares, in closed form via the normal equations.
+///
+/// Computed on the host.
+///
+/// # Example
+///```
+/// # use autograph::{learn::linear_model::LinearRegression, tensor::Tensor};
+/// # use ndarray::Array;
+/// # fn main() -> anyhow::Result<()> {
+/// let x = Tensor::from(Array::from_shape_vec([3, 1], vec![0f32, 1., 2.])?);
+/// let y = Tensor::from(vec![1f32, 3., 5.]);
+/// let mut model = LinearRegression::new();
+/// model.fit(x.view(), y.view())?;
+/// assert!((model.intercept().unwrap() - 1.).abs() < 1e-4);
+/// # Ok(())
+/// # }
+///```
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LinearRegression {
+    fit_intercept: bool,
+    weights: Option<ArcTensor1<f32>>,
+    intercept: f32,
+}
+
+impl Default for LinearRegression {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LinearRegression {
+    /// Creates a new [`LinearRegression`].
+    pub fn new() -> Self {
+        Self {
+            fit_intercept: true,
+            weights: None,
+            intercept: 0.,
+        }
+    }
+    /// Whether to fit the intercept. Defaults to true.
+    ///
+    /// If false, the intercept is 0.
+    pub fn fit_intercept(self, fit_intercept: bool) -> Self {
+        Self {
+            fit_intercept,
+            ..self
+        }
+    }
+    /// The weights, with shape \[features\].
+    ///
+    /// `None` if not fitted.
+    pub fn weights(&self) -> Option<TensorView1<f32>> {
+        self.weights.as_ref().map(|x| x.view())
+    }
+    /// The intercept.
+    ///
+    /// `None` if not fitted.
+    pub fn intercept(&self) -> Option<f32> {
+        self.weights.as_ref().map(|_| self.intercept)
+    }
+    /// Fits the model to `x` and `y`.
+    ///
+    /// Shapes:
+    /// - x: \[samples, features\]
+    /// - y: \[samples\]
+    ///
+    /// # Errors
+    /// - The shapes do not match.
+    /// - `x` is rank deficient, ie there are fewer samples than features.
+    pub fn fit(&mut self, x: TensorView2<f32>, y: TensorView1<f32>) -> Result<()> {
+        let x = host_array(&x)?;
+        let y = host_array(&y)?;
+        let (samples, features) = x.dim();
+        if y.len() != samples {
+            bail!(
+                "LinearRegression::fit expected y with shape [{samples}], found [{}]!",
+                y.len()
+            );
+        }
+        let x = x.mapv(f64::from);
+        let y = y.mapv(f64::from);
+        let (x, y, x_mean, y_mean) = if self.fit_intercept && samples > 0 {
+            let x_mean = x.mean_axis(Axis(0)).unwrap();
+            let y_mean = y.mean().unwrap();
+            (x - &x_mean, y - y_mean, x_mean, y_mean)
+        } else {
+            (x, y, Array1::zeros(features), 0.)
+        };
+        let weights = solve(x.t().dot(&x), x.t().dot(&y))?;
+        self.intercept = (y_mean - x_mean.dot(&weights)) as f32;
+        self.weights
+            .replace(ArcTensor::from(weights.mapv(|x| x as f32)));
+        Ok(())
+    }
+    /// Predicts `y` given `x`.
+    ///
+    /// Shapes:
+    /// - x: \[samples, features\]
+    /// - output: \[samples\]
+    ///
+    /// # Errors
+    /// - Not fitted.
+    /// - The features of `x` do not match the fitted data.
+    pub fn predict(&self, x: TensorView2<f32>) -> Result<Tensor1<f32>> {
+        let Some(weights) = self.weights.as_ref() else {
+            bail!("LinearRegression::predict not fitted!");
+        };
+        let device = x.device();
+        let y = linear(x, weights.as_array().unwrap(), self.intercept)?;
+        Tensor::from(y).into_device(device)
+    }
+}
+
+/// Logistic Regression.
+///
+/// Binary classifier that models the probability of class 1 as
+/// `sigmoid(x · weights + intercept)`. Fits by minimizing the cross entropy loss with Newton's
+/// method, with an optional L2 penalty on the weights.
+///
+/// Computed on the host.
+///
+/// # Example
+///```
+/// # use autograph::{learn::linear_model::LogisticRegression, tensor::Tensor};
+/// # use ndarray::Array;
+/// # fn main() -> anyhow::Result<()> {
+/// let x = Tensor::from(Array::from_shape_vec([4, 1], vec![-2f32, -1., 1., 2.])?);
+/// let y = Tensor::from(vec![0u8, 0, 1, 1]);
+/// let mut model = LogisticRegression::new().penalty(1.);
+/// model.fit(x.view(), y.view())?;
+/// let classes = model.predict(x.view())?.into_array()?;
+/// assert_eq!(classes.as_slice().unwrap(), &[0, 0, 1, 1]);
+/// # Ok(())
+/// # }
+///```
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LogisticRegression {
+    penalty: f32,
+    max_iterations: usize,
+    tolerance: f32,
+    weights: Option<ArcTensor1<f32>>,
+    intercept: f32,
+    iterations: usize,
+}
+
+impl Default for LogisticRegression {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LogisticRegression {
+    /// Creates a new [`LogisticRegression`].
+    pub fn new() -> Self {
+        Self {
+            penalty: 0.,
+            max_iterations: 100,
+            tolerance: 1e-6,
+            weights: None,
+            intercept: 0.,
+            iterations: 0,
+        }
+    }
+    /// Sets the L2 penalty. Defaults to 0.
+    ///
+    /// The loss is `sum(cross_entropy) + penalty / 2 * ||weights||²`. The intercept is not
+    /// penalized. A positive penalty is required for the model to converge on linearly
+    /// separable data.
+    pub fn penalty(self, penalty: f32) -> Self {
+        Self { penalty, ..self }
+    }
+    /// Sets the maximum number of iterations. Defaults to 100.
+    pub fn max_iterations(self, max_iterations: usize) -> Self {
+        Self {
+            max_iterations,
+            ..self
+        }
+    }
+    /// Sets the convergence tolerance. Defaults to 1e-6.
+    ///
+    /// Fitting stops when the largest update of the weights and intercept is at most
+    /// `tolerance`.
+    pub fn tolerance(self, tolerance: f32) -> Self {
+        Self { tolerance, ..self }
+    }
+    /// The weights, with shape \[features\].
+    ///
+    /// `None` if not fitted.
+    pub fn weights(&self) -> Option<TensorView1<f32>> {
+        self.weights.as_ref().map(|x| x.view())
+    }
+    /// The intercept.
+    ///
+    /// `None` if not fitted.
+    pub fn intercept(&self) -> Option<f32> {
+        self.weights.as_ref().map(|_| self.intercept)
+    }
+    /// The number of iterations of the last fit.
+    pub fn iterations(&self) -> usize {
+        self.iterations
+    }
+    /// Fits the model to `x` and the classes `y`.
+    ///
+    /// Shapes:
+    /// - x: \[samples, features\]
+    /// - y: \[samples\]
+    ///
+    /// # Errors
+    /// - The shapes do not match.
+    /// - The classes are not 0 or 1.
+    /// - The Hessian is singular, ie the data is separable and `penalty` is 0.
+    pub fn fit(&mut self, x: TensorView2<f32>, y: TensorView1<u8>) -> Result<()> {
+        let x = host_array(&x)?;
+        let y = host_array(&y)?;
+        let (samples, features) = x.dim();
+        if y.len() != samples {
+            bail!(
+                "LogisticRegression::fit expected y with shape [{samples}], found [{}]!",
+                y.len()
+            );
+        }
+        if let Some(class) = y.iter().find(|y| **y > 1) {
+            bail!("LogisticRegression::fit expected classes 0 or 1, found {class}!");
+        }
+        // the last column is the intercept
+        let mut x_intercept = Array2::<f64>::ones([samples, features + 1]);
+        x_intercept
+            .slice_mut(s![.., ..features])
+            .assign(&x.mapv(f64::from));
+        let y = y.mapv(f64::from);
+        let penalty = f64::from(self.penalty);
+        let mut weights = Array1::<f64>::zeros(features + 1);
+        self.iterations = 0;
+        while self.iterations < self.max_iterations {
+            self.iterations += 1;
+            let p = x_intercept.dot(&weights).mapv(sigmoid);
+            let mut gradient = x_intercept.t().dot(&(&p - &y));
+            let mut hessian = x_intercept
+                .t()
+                .dot(&(&x_intercept * &(&p * &(1. - &p)).insert_axis(Axis(1))));
+            for i in 0..features {
+                gradient[i] += penalty * weights[i];
+                hessian[[i, i]] += penalty;
+            }
+            let step = solve(hessian, gradient)?;
+            weights -= &step;
+            let max_step = step.iter().fold(0f64, |m, x| m.max(x.abs()));
+            if max_step <= f64::from(self.tolerance) {
+                break;
+            }
+        }
+        self.intercept = weights[features] as f32;
+        self.weights.replace(ArcTensor::from(
+            weights.slice(s![..features]).mapv(|x| x as f32),
+        ));
+        Ok(())
+    }
+    /// Predicts the probability of class 1 given `x`.
+    ///
+    /// Shapes:
+    /// - x: \[samples, features\]
+    /// - output: \[samples\]
+    ///
+    /// # Errors
+    /// - Not fitted.
+    /// - The features of `x` do not match the fitted data.
+    pub fn predict_proba(&self, x: TensorView2<f32>) -> Result<Tensor1<f32>> {
+        let Some(weights) = self.weights.as_ref() else {
+            bail!("LogisticRegression::predict_proba not fitted!");
+        };
+        let device = x.device();
+        let y = linear(x, weights.as_array().unwrap(), self.intercept)?
+            .mapv(|x| sigmoid(x.into()) as f32);
+        Tensor::from(y).into_device(device)
+    }
+    /// Predicts the class given `x`.
+    ///
+    /// The class is 1 if the probability is greater than 0.5.
+    ///
+    /// See [`.predict_proba()`](Self::predict_proba).
+    pub fn predict(&self, x: TensorView2<f32>) -> Result<Tensor1<u8>> {
+        let device = x.device();
+        let y = self
+            .predict_proba(x)?
+            .into_device(Device::host())?
+            .into_array()?
+            .mapv(|p| (p > 0.5) as u8);
+        Tensor::from(y).into_device(device)
+    }
+}
+
+fn sigmoid(x: f64) -> f64 {
+    1. / (1. + (-x).exp())
+}
+
+fn host_array<'a, T: Scalar, D: Dimension>(x: &'a TensorView<T, D>) -> Result<CowArray<'a, T, D>> {
+    if let Some(x) = x.as_array() {
+        Ok(x.into())
+    } else {
+        Ok(x.to_device(Device::host())?.into_array()?.into())
+    }
+}
+
+fn linear(x: TensorView2<f32>, weights: ArrayView1<f32>, intercept: f32) -> Result<Array1<f32>> {
+    if x.dim().1 != weights.len() {
+        bail!(
+            "expected x with {} features, found {}!",
+            weights.len(),
+            x.dim().1
+        );
+    }
+    let x = host_array(&x)?;
+    Ok(x.dot(&weights) + intercept)
+}
+
+// Solves `a · x = b` with Gaussian elimination with partial pivoting.
+fn solve(mut a: Array2<f64>, mut b: Array1<f64>) -> Result<Array1<f64>> {
+    let n = b.len();
+    let scale = a.iter().fold(0f64, |m, x| m.max(x.abs()));
+    for k in 0..n {
+        let pivot = (k..n)
+            .max_by(|i, j| a[[*i, k]].abs().total_cmp(&a[[*j, k]].abs()))
+            .unwrap();
+        if a[[pivot, k]].abs() <= scale * n as f64 * f64::EPSILON {
+            bail!("matrix is singular!");
+        }
+        if pivot != k {
+            for j in 0..n {
+                a.swap([k, j], [pivot, j]);
+            }
+            b.swap(k, pivot);
+        }
+        for i in k + 1..n {
+            let factor = a[[i, k]] / a[[k, k]];
+            for j in k..n {
+                a[[i, j]] -= factor * a[[k, j]];
+            }
+            b[i] -= factor * b[k];
+        }
+    }
+    let mut x = Array1::zeros(n);
+    for k in (0..n).rev() {
+        let sum: f64 = (k + 1..n).map(|j| a[[k, j]] * x[j]).sum();
+        x[k] = (b[k] - sum) / a[[k, k]];
+    }
+    Ok(x)
+}
@@ -31,6 +31,13 @@ During training, nodes and edges form a backward graph, connecting the node of a
 The [`optimizer`](neural_network::optimizer), such as [`SGD`](neural_network::optimizer::SGD), [`updates`](neural_network::optimizer::Optimizer::update)
 the parameters. For convenience, this can be called on the layer via [`Layer::update()`](neural_network::layer::Layer::update).
 
+# Batch Size
+Layers accept inputs with any batch size. On the device, some kernels, like matrix multiplication and the
+convolution kernels, are specialized for the shapes of their inputs, including the batch size. Kernels are
+compiled the first time they are used with a specialization and cached by the device. A smaller final batch
+results in additional compilations in the first epoch, which are reused in subsequent epochs. To avoid these entirely,
+drop or pad the final batch so that all batches have the same size.
+
 # Example
 ```
 # use anyhow::Result;
@@ -78,7 +85,7 @@ impl LeNet5 {
             .build()?;
         let relu2 = Relu;
         let pool2 = MaxPool2::builder().filter([2, 2]).build();
-        let flatten = Flatten;
+        let flatten = Flatten::default();
         let dense1 = Dense::builder()
             .device(device.clone())
             .scalar_type(scalar_type)
@@ -138,7 +145,7 @@ pub mod __private {
     pub mod criterion {
         use crate::{
             krnl::scalar::Scalar,
-            tensor::{Tensor2, TensorView1, TensorView2},
+            tensor::{Tensor, Tensor2, TensorView1, TensorView2},
         };
         use anyhow::Result;
         use num_traits::{Float, Unsigned};
@@ -148,7 +155,9 @@ pub mod __private {
             t: TensorView1<T2>,
             dy: f32,
         ) -> Result<Tensor2<T1>> {
-            super::super::criterion::cross_entropy_loss_backward(x, t, dy)
+            let batch_size = t.len();
+            let dy = Tensor::from_elem(x.device(), batch_size, dy / batch_size.max(1) as f32)?;
+            super::super::criterion::cross_entropy_loss_backward(x, t, dy.view(), None)
         }
     }
 }
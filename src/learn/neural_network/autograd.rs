@@ -31,7 +31,8 @@ use paste::paste;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::{
     any::TypeId,
-    collections::VecDeque,
+    cell::Cell,
+    collections::{HashMap, HashSet, VecDeque},
     fmt::{self, Debug},
     marker::PhantomData,
     sync::{Arc, Weak},
@@ -177,10 +178,104 @@ struct NodeInner {
     edges: Mutex<Vec<EdgeInner>>,
 }
 
+type NodeId = *const RwLock<Option<ScalarArcTensorD>>;
+
 impl NodeInner {
     fn ready(&self) -> bool {
         Arc::weak_count(&self.grad) == 0
     }
+    // Nodes created from the same parameter share the gradient.
+    fn id(&self) -> NodeId {
+        Arc::as_ptr(&self.grad)
+    }
+    fn children(&self) -> Vec<Arc<NodeInner>> {
+        self.edges
+            .lock()
+            .iter()
+            .map(|edge| edge.node.clone())
+            .collect()
+    }
+    // Nodes with a path to one of `leaves`.
+    //
+    // Depth first with an explicit stack, so that deep graphs do not overflow the stack.
+    fn required_nodes(&self, leaves: &HashSet<NodeId>) -> HashSet<NodeId> {
+        let mut visited = HashMap::new();
+        visited.insert(self.id(), leaves.contains(&self.id()));
+        // Nodes being visited, with their children that have not been visited yet.
+        let mut stack = vec![(self.id(), self.children())];
+        while let Some((id, children)) = stack.last_mut() {
+            let id = *id;
+            if let Some(child) = children.pop() {
+                let child_id = child.id();
+                // The graph is acyclic, so visited children are complete.
+                if let Some(required) = visited.get(&child_id).copied() {
+                    if required {
+                        visited.insert(id, true);
+                    }
+                } else {
+                    visited.insert(child_id, leaves.contains(&child_id));
+                    stack.push((child_id, child.children()));
+                }
+            } else {
+                stack.pop();
+                if visited[&id] {
+                    if let Some((parent, _)) = stack.last() {
+                        visited.insert(*parent, true);
+                    }
+                }
+            }
+        }
+        visited
+            .into_iter()
+            .filter_map(|(id, required)| required.then_some(id))
+            .collect()
+    }
+    // Nodes and edges reachable from this node.
+    //
+    // Depth first with an explicit stack, like required_nodes().
+    fn graph_stats(&self) -> GraphStats {
+        fn visit(node: &NodeInner, stats: &mut GraphStats) -> Vec<Arc<NodeInner>> {
+            stats.nodes += 1;
+            stats.bytes += node.dim.size() * node.scalar_type.size();
+            if let Some(grad) = node.grad.read().as_ref() {
+                stats.bytes += grad.len() * grad.scalar_type().size();
+            }
+            let children = node.children();
+            stats.edges += children.len();
+            children
+        }
+        let mut stats = GraphStats::default();
+        let mut visited = HashSet::new();
+        visited.insert(self.id());
+        let mut stack = visit(self, &mut stats);
+        while let Some(node) = stack.pop() {
+            if visited.insert(node.id()) {
+                stack.extend(visit(&node, &mut stats));
+            }
+        }
+        stats
+    }
+}
+
+/// Graph statistics.
+///
+/// A summary of the graph that will be traversed in the backward pass, useful for debugging
+/// memory usage. Edges are consumed by the backward pass, so a graph that keeps growing
+/// across training steps indicates that variables are being retained.
+///
+/// See [`Variable::graph_stats()`].
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GraphStats {
+    /// The number of nodes.
+    pub nodes: usize,
+    /// The number of edges.
+    pub edges: usize,
+    /// The estimated number of bytes retained by the graph.
+    ///
+    /// The sum of the size of the value of each node and of the gradients that have been
+    /// computed. Tensors captured by edges are not visible, typically they are values of
+    /// the nodes.
+    pub bytes: usize,
 }
 
 /// Node.
@@ -237,6 +332,44 @@ impl<D: Dimension> Node<D> {
     }
     /// Executes the backward pass with `grad`.
     pub fn backward_grad(&self, grad: ScalarArcTensor<D>) -> Result<()> {
+        self.backward_grad_impl(grad, None)
+    }
+    /// Executes the backward pass, only computing the gradients of `leaves`.
+    ///
+    /// Edges that do not lead to one of `leaves` are not computed, so the gradients of other
+    /// variables and parameters are not computed. Leaves may be inputs or parameters, see
+    /// [`Parameter::to_variable()`]. Leaves without a node are ignored.
+    pub fn backward_for(&self, leaves: &[VariableD]) -> Result<()> {
+        self.backward_grad_for(
+            ScalarArcTensor::ones(
+                self.inner.device.clone(),
+                self.inner.dim.slice(),
+                self.inner.scalar_type,
+            )?
+            .into_dimensionality::<D>()
+            .map_err(Error::msg)?,
+            leaves,
+        )
+    }
+    /// Executes the backward pass with `grad`, only computing the gradients of `leaves`.
+    ///
+    /// See [`.backward_for()`](Self::backward_for).
+    pub fn backward_grad_for(&self, grad: ScalarArcTensor<D>, leaves: &[VariableD]) -> Result<()> {
+        let leaves: HashSet<_> = leaves
+            .iter()
+            .filter_map(|leaf| Some(leaf.node()?.inner.id()))
+            .collect();
+        let required = self.inner.required_nodes(&leaves);
+        if !required.contains(&self.inner.id()) {
+            return Ok(());
+        }
+        self.backward_grad_impl(grad, Some(&required))
+    }
+    fn backward_grad_impl(
+        &self,
+        grad: ScalarArcTensor<D>,
+        required: Option<&HashSet<NodeId>>,
+    ) -> Result<()> {
         {
             let mut guard = self.inner.grad.write();
             if guard.is_some() {
@@ -250,6 +383,11 @@ impl<D: Dimension> Node<D> {
             let edges = std::mem::take(&mut *node.edges.lock());
             std::mem::drop(node);
             for mut edge in edges {
+                if let Some(required) = required {
+                    if !required.contains(&edge.node.id()) {
+                        continue;
+                    }
+                }
                 (edge.op)()?;
                 let node = edge.node;
                 if node.ready() {
@@ -259,6 +397,12 @@ impl<D: Dimension> Node<D> {
         }
         Ok(())
     }
+    /// Statistics of the graph reachable from this node.
+    ///
+    /// See [`GraphStats`].
+    pub fn graph_stats(&self) -> GraphStats {
+        self.inner.graph_stats()
+    }
     fn into_dyn(self) -> Node<IxDyn> {
         Node {
             inner: self.inner,
@@ -321,6 +465,34 @@ impl<D: Dimension> Variable<D> {
     pub fn node(&self) -> Option<&Node<D>> {
         self.node.as_ref()
     }
+    /// Adds a [`Node`] if the variable does not have one.
+    ///
+    /// The gradient of the variable will be computed in the backward pass, for example to
+    /// compute the gradient of the loss with respect to the input. See [`.grad()`](Self::grad).
+    pub fn requires_grad(self) -> Self {
+        if self.node.is_some() {
+            self
+        } else {
+            Variable::builder().node().build(self.value)
+        }
+    }
+    /// The gradient.
+    ///
+    /// Returns `None` if the variable does not have a [`Node`] or the gradient was not
+    /// computed. See [`Node::grad()`].
+    pub fn grad(&self) -> Option<ScalarArcTensor<D>> {
+        self.node.as_ref()?.grad()
+    }
+    /// Statistics of the backward graph of the variable.
+    ///
+    /// Returns the default, an empty graph, if the variable does not have a [`Node`].
+    /// See [`Node::graph_stats()`].
+    pub fn graph_stats(&self) -> GraphStats {
+        self.node
+            .as_ref()
+            .map(Node::graph_stats)
+            .unwrap_or_default()
+    }
     /// Maps the variable with `F`.
     ///
     /// Shortcut for `f.forward(self)`. This allows chaining methods together.
@@ -379,6 +551,15 @@ impl Variable0 {
         }
         Ok(())
     }
+    /// Executes the backward pass, only computing the gradients of `leaves`.
+    ///
+    /// See [`Node::backward_for`].
+    pub fn backward_for(&self, leaves: &[VariableD]) -> Result<()> {
+        if let Some(node) = self.node.as_ref() {
+            node.backward_for(leaves)?;
+        }
+        Ok(())
+    }
 }
 
 impl<D: Dimension + 'static> Variable<D> {
@@ -830,11 +1011,39 @@ impl<S: ScalarData, D: Dimension> ParameterBase<S, D> {
     }
 }
 
+thread_local! {
+    static NO_GRAD: Cell<bool> = const { Cell::new(false) };
+}
+
+// Executes `f` with gradients disabled on this thread.
+//
+// Parameters are converted to variables without a node, so no graph is created.
+pub(crate) fn no_grad<R>(f: impl FnOnce() -> R) -> R {
+    struct Guard(bool);
+
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            NO_GRAD.with(|no_grad| no_grad.set(self.0));
+        }
+    }
+
+    let _guard = Guard(NO_GRAD.with(|no_grad| no_grad.replace(true)));
+    f()
+}
+
 impl<D: Dimension> Parameter<D> {
     /// Converts to a `Variable`.
+    ///
+    /// The variable does not have a [`Node`] if gradients are disabled, for example within
+    /// [`Layer::forward_values()`](super::layer::Layer::forward_values).
     pub fn to_variable(&self) -> Variable<D> {
         let value = self.value.clone();
-        let node = self.grad.get().map(|grad| {
+        let grad = if NO_GRAD.with(Cell::get) {
+            None
+        } else {
+            self.grad.get()
+        };
+        let node = grad.map(|grad| {
             Node::new(
                 value.device(),
                 value.raw_dim().into_dyn(),
@@ -1,9 +1,21 @@
-use super::autograd::{Variable0, Variable2};
+use super::autograd::{Variable, Variable0, Variable1, Variable2, VariableD};
 #[cfg(feature = "device")]
-use crate::tensor::{ScalarTensor, ScalarTensorView, Tensor};
+use crate::learn::criterion::{nll_loss_device, NllLossDevice};
+#[cfg(feature = "device")]
+use crate::tensor::ScalarTensorView;
 use crate::{
-    learn::criterion::CrossEntropyLoss,
-    tensor::{ScalarArcTensor, ScalarArcTensor1, Tensor2, TensorView1, TensorView2},
+    learn::criterion::{
+        check_bce_loss_target, check_float_inputs, check_huber_loss_delta, elementwise_loss,
+        elementwise_loss_backward, nll_loss_arrays, nll_loss_backward_host, nll_loss_host,
+        triplet_margin_loss_backward, triplet_margin_loss_per_sample, BinaryCrossEntropyLoss,
+        CrossEntropyLoss, CrossEntropyLossOptions, ElementwiseLoss, HuberLoss, InfoNCELoss,
+        MeanAbsoluteError, MeanSquaredError, NllLoss, Reduction, TripletMarginLoss,
+        TripletMarginLossOptions,
+    },
+    tensor::{
+        ScalarArcTensor, ScalarArcTensor1, ScalarTensor, ScalarTensorView1, Tensor, Tensor2,
+        TensorView1, TensorView2,
+    },
 };
 use anyhow::{bail, Result};
 use dry::macro_for;
@@ -14,7 +26,7 @@ use krnl::{
     device::Device,
     scalar::{Scalar, ScalarElem, ScalarType},
 };
-use ndarray::Array2;
+use ndarray::{linalg::Dot, Array1, Array2, ArrayView1, ArrayView2, Axis, Dimension};
 #[cfg(feature = "device")]
 use num_traits::ToPrimitive;
 use num_traits::{Float, Unsigned};
@@ -29,77 +41,472 @@ use paste::paste;
 /// Panics on the host if `target` indices are out of bounds.
 impl CrossEntropyLoss<ScalarArcTensor1> for Variable2 {
     type Output = Variable0;
-    fn cross_entropy_loss(&self, target: ScalarArcTensor1) -> Result<Variable0> {
-        if !matches!(self.scalar_type(), ScalarType::BF16 | ScalarType::F32)
-            || !matches!(
-                target.scalar_type(),
-                ScalarType::U8 | ScalarType::U16 | ScalarType::U32
-            )
-        {
-            bail!(
-                "CrossEntropyLoss {:?} {:?} unimplemented!",
-                self.scalar_type(),
-                target.scalar_type()
-            );
-        }
+    type PerSampleOutput = Variable1;
+    type ReducedOutput = VariableD;
+    fn cross_entropy_loss_with_options(
+        &self,
+        target: ScalarArcTensor1,
+        options: &CrossEntropyLossOptions,
+    ) -> Result<Variable0> {
+        check_cross_entropy_loss_types(self, &target)?;
         let mut builder = Variable0::builder();
         if let Some(node) = self.node() {
             let input = self.value().clone();
             let target = target.clone();
+            let ignore_index = options.ignore_index;
             builder.edge(node, move |output_grad| {
+                let dy = output_grad
+                    .into_device(Device::host())?
+                    .cast_into_tensor::<f32>()?
+                    .into_array()?
+                    .into_scalar();
                 macro_for!($X in [bf16, f32] {
                     macro_for!($T in [u8, u16, u32] {
                         if input.scalar_type() == $X::SCALAR_TYPE && target.scalar_type() == $T::SCALAR_TYPE {
                             let input = input.try_into_arc_tensor::<$X>().unwrap();
                             let target = target.try_into_arc_tensor::<$T>().unwrap();
-                            let dy = output_grad
-                                .into_device(Device::host())?
-                                .cast_into_tensor::<$X>()?
-                                .into_array()
-                                .unwrap()
-                                .into_scalar();
-                            return Ok(
-                                cross_entropy_loss_backward::<$X, $T>(input.view(), target.view(), dy.cast::<f32>())?
-                                    .into_scalar_tensor()
-                                    .into_shared()
-                                    .unwrap(),
-                            );
+                            let count = cross_entropy_loss_count(target.view(), ignore_index)?;
+                            let dy = Tensor::from_elem(input.device(), target.len(), dy / count.max(1) as f32)?;
+                            return cross_entropy_loss_backward::<$X, $T>(input.view(), target.view(), dy.view(), ignore_index)?
+                                .into_scalar_tensor()
+                                .into_shared();
                         }
                     });
                 });
                 unreachable!()
             });
         }
-        let loss = self.value().cross_entropy_loss(target)?;
+        let loss = self
+            .value()
+            .cross_entropy_loss_with_options(target, options)?;
         let value = ScalarArcTensor::from_elem(Device::host(), (), ScalarElem::F32(loss)).unwrap();
         Ok(builder.build(value))
     }
+    fn cross_entropy_loss_per_sample(
+        &self,
+        target: ScalarArcTensor1,
+        options: &CrossEntropyLossOptions,
+    ) -> Result<Variable1> {
+        check_cross_entropy_loss_types(self, &target)?;
+        let mut builder = Variable1::builder();
+        if let Some(node) = self.node() {
+            let input = self.value().clone();
+            let target = target.clone();
+            let ignore_index = options.ignore_index;
+            builder.edge(node, move |output_grad| {
+                let dy = output_grad.cast_into_tensor::<f32>()?;
+                macro_for!($X in [bf16, f32] {
+                    macro_for!($T in [u8, u16, u32] {
+                        if input.scalar_type() == $X::SCALAR_TYPE && target.scalar_type() == $T::SCALAR_TYPE {
+                            let input = input.try_into_arc_tensor::<$X>().unwrap();
+                            let target = target.try_into_arc_tensor::<$T>().unwrap();
+                            return cross_entropy_loss_backward::<$X, $T>(input.view(), target.view(), dy.view(), ignore_index)?
+                                .into_scalar_tensor()
+                                .into_shared();
+                        }
+                    });
+                });
+                unreachable!()
+            });
+        }
+        let loss = self
+            .value()
+            .cross_entropy_loss_per_sample(target, options)?;
+        Ok(builder.build(ScalarTensor::from(loss).into_shared()?))
+    }
+    fn cross_entropy_loss_with(
+        &self,
+        target: ScalarArcTensor1,
+        reduction: Reduction,
+    ) -> Result<VariableD> {
+        let loss =
+            self.cross_entropy_loss_per_sample(target, &CrossEntropyLossOptions::default())?;
+        reduce_loss_variable("CrossEntropyLoss", loss, reduction)
+    }
+}
+
+fn check_cross_entropy_loss_types(input: &Variable2, target: &ScalarArcTensor1) -> Result<()> {
+    if !matches!(input.scalar_type(), ScalarType::BF16 | ScalarType::F32)
+        || !matches!(
+            target.scalar_type(),
+            ScalarType::U8 | ScalarType::U16 | ScalarType::U32
+        )
+    {
+        bail!(
+            "CrossEntropyLoss {:?} {:?} unimplemented!",
+            input.scalar_type(),
+            target.scalar_type()
+        );
+    }
+    Ok(())
+}
+
+/// Implemented for bf16 and f32.
+impl TripletMarginLoss<Variable2> for Variable2 {
+    type Output = Variable0;
+    type PerSampleOutput = Variable1;
+    fn triplet_margin_loss(
+        &self,
+        positive: Variable2,
+        negative: Variable2,
+        options: &TripletMarginLossOptions,
+    ) -> Result<Variable0> {
+        if options.reduction == Reduction::None {
+            bail!("TripletMarginLoss Reduction::None is not supported, use the per sample method!");
+        }
+        let loss = self.triplet_margin_loss_per_sample(positive, negative, options)?;
+        Ok(
+            reduce_loss_variable("TripletMarginLoss", loss, options.reduction)?
+                .into_dimensionality()?,
+        )
+    }
+    fn triplet_margin_loss_per_sample(
+        &self,
+        positive: Variable2,
+        negative: Variable2,
+        options: &TripletMarginLossOptions,
+    ) -> Result<Variable1> {
+        let variables = [self, &positive, &negative];
+        let loss = triplet_margin_loss_per_sample(
+            self.value().view(),
+            positive.value().view(),
+            negative.value().view(),
+            options,
+        )?;
+        let mut builder = Variable1::builder();
+        for (i, variable) in variables.into_iter().enumerate() {
+            if let Some(node) = variable.node() {
+                let [anchor, positive, negative] = variables.map(|x| x.value().clone());
+                let options = *options;
+                builder.edge(node, move |output_grad| {
+                    let dy = output_grad.cast_into_tensor::<f32>()?;
+                    triplet_margin_loss_backward(
+                        anchor.view(),
+                        positive.view(),
+                        negative.view(),
+                        &options,
+                        dy.view(),
+                    )?
+                    .into_iter()
+                    .nth(i)
+                    .unwrap()
+                    .into_shared()
+                });
+            }
+        }
+        Ok(builder.build(ScalarTensor::from(loss).into_shared()?))
+    }
+}
+
+/// Implemented for bf16 and f32.
+///
+/// Composed from tensor ops in f32, the gradient flows through the similarity matrix.
+impl InfoNCELoss<Variable2> for Variable2 {
+    type Output = Variable0;
+    fn info_nce_loss(&self, positive: Variable2, temperature: f32) -> Result<Variable0> {
+        check_float_inputs(
+            "InfoNCELoss",
+            &[self.value().view(), positive.value().view()],
+        )?;
+        if self.device() != positive.device() {
+            bail!(
+                "InfoNCELoss expected inputs on the same device, found {:?} and {:?}!",
+                self.device(),
+                positive.device()
+            );
+        }
+        let anchor = normalize_rows_variable(self, 1. / temperature)?;
+        let positive = normalize_rows_variable(&positive, 1.)?;
+        let similarity = anchor.dot(&positive.t())?;
+        let batch_size = similarity.dim().0;
+        let target =
+            Tensor::from(Array1::from_iter(0..batch_size as u32)).into_device(self.device())?;
+        let target = ScalarTensor::from(target).into_shared()?;
+        Ok(similarity
+            .cross_entropy_loss_with(target, Reduction::Mean)?
+            .into_dimensionality()?)
+    }
+}
+
+/// Implemented for:
+/// - input, weight: bf16, f32
+/// - target: u8, u16, u32
+///
+/// On a device, the target indices are checked with a reduction before the loss is computed.
+impl NllLoss<ScalarArcTensor1> for Variable2 {
+    type Output = Variable0;
+    fn nll_loss_with_weight(
+        &self,
+        target: ScalarArcTensor1,
+        weight: Option<ScalarTensorView1>,
+    ) -> Result<Variable0> {
+        if self.device().is_host() {
+            return nll_loss_host_variable(self, target, weight);
+        }
+        #[cfg(not(feature = "device"))]
+        {
+            unreachable!()
+        }
+        #[cfg(feature = "device")]
+        {
+            let NllLossDevice {
+                loss,
+                target,
+                weight,
+                total,
+            } = nll_loss_device(self.value().view(), target.view(), weight)?;
+            let mut builder = Variable0::builder();
+            if let Some(node) = self.node() {
+                let (batch_size, classes) = self.dim();
+                let device = self.device();
+                let scalar_type = self.scalar_type();
+                builder.edge(node, move |output_grad| {
+                    let dy = output_grad.cast_into_tensor::<f32>()?;
+                    macro_for!($X in [bf16, f32] {
+                        if scalar_type == $X::SCALAR_TYPE {
+                            let mut dx = unsafe { Tensor::<$X, _>::uninit(device, [batch_size, classes])? };
+                            if !dx.is_empty() {
+                                let kernel = paste! {
+                                    kernels::[<nll_loss_backward_ $X>]::builder()?.build(dx.device())?
+                                };
+                                kernel.dispatch(
+                                    classes.to_u32().unwrap(),
+                                    target.as_slice().unwrap(),
+                                    weight.as_slice().unwrap(),
+                                    total.as_slice().unwrap(),
+                                    dy.as_slice().unwrap(),
+                                    dx.as_slice_mut().unwrap(),
+                                )?;
+                            }
+                            return dx.into_scalar_tensor().into_shared();
+                        }
+                    });
+                    unreachable!()
+                });
+            }
+            Ok(builder.build(ScalarTensor::from(loss).into_shared()?))
+        }
+    }
+}
+
+fn nll_loss_host_variable(
+    input: &Variable2,
+    target: ScalarArcTensor1,
+    weight: Option<ScalarTensorView1>,
+) -> Result<Variable0> {
+    let (x, t, w) = nll_loss_arrays(input.value().view(), target.view(), weight)?;
+    let loss = nll_loss_host(x.view(), &t, w.as_ref().map(Array1::view));
+    let mut builder = Variable0::builder();
+    if let Some(node) = input.node() {
+        let input_dim = x.dim();
+        let scalar_type = input.scalar_type();
+        builder.edge(node, move |output_grad| {
+            let dy = output_grad
+                .into_device(Device::host())?
+                .cast_into_tensor::<f32>()?
+                .into_array()?
+                .into_scalar();
+            let dx = nll_loss_backward_host(input_dim, &t, w.as_ref().map(Array1::view), dy);
+            ScalarTensor::from(Tensor::from(dx))
+                .cast_into(scalar_type)?
+                .into_shared()
+        });
+    }
+    let value = ScalarArcTensor::from_elem(Device::host(), (), ScalarElem::F32(loss)).unwrap();
+    Ok(builder.build(value))
+}
+
+/// Implemented for bf16 and f32.
+impl<D: Dimension + 'static> MeanSquaredError<ScalarArcTensor<D>> for Variable<D> {
+    type Output = Variable0;
+    type ReducedOutput = VariableD;
+    fn mse_loss(&self, target: ScalarArcTensor<D>) -> Result<Variable0> {
+        Ok(self
+            .mse_loss_with(target, Reduction::Mean)?
+            .into_dimensionality()?)
+    }
+    fn mse_loss_with(&self, target: ScalarArcTensor<D>, reduction: Reduction) -> Result<VariableD> {
+        elementwise_loss_variable(
+            "MeanSquaredError",
+            ElementwiseLoss::MeanSquaredError,
+            self,
+            target,
+            reduction,
+        )
+    }
+}
+
+/// Implemented for bf16 and f32.
+impl<D: Dimension + 'static> MeanAbsoluteError<ScalarArcTensor<D>> for Variable<D> {
+    type Output = Variable0;
+    type ReducedOutput = VariableD;
+    fn l1_loss(&self, target: ScalarArcTensor<D>) -> Result<Variable0> {
+        Ok(self
+            .l1_loss_with(target, Reduction::Mean)?
+            .into_dimensionality()?)
+    }
+    fn l1_loss_with(&self, target: ScalarArcTensor<D>, reduction: Reduction) -> Result<VariableD> {
+        elementwise_loss_variable(
+            "MeanAbsoluteError",
+            ElementwiseLoss::MeanAbsoluteError,
+            self,
+            target,
+            reduction,
+        )
+    }
+}
+
+/// Implemented for bf16 and f32.
+impl<D: Dimension + 'static> HuberLoss<ScalarArcTensor<D>> for Variable<D> {
+    type Output = Variable0;
+    type ReducedOutput = VariableD;
+    fn huber_loss(&self, target: ScalarArcTensor<D>, delta: f32) -> Result<Variable0> {
+        Ok(self
+            .huber_loss_with(target, delta, Reduction::Mean)?
+            .into_dimensionality()?)
+    }
+    fn huber_loss_with(
+        &self,
+        target: ScalarArcTensor<D>,
+        delta: f32,
+        reduction: Reduction,
+    ) -> Result<VariableD> {
+        check_huber_loss_delta(delta)?;
+        elementwise_loss_variable(
+            "HuberLoss",
+            ElementwiseLoss::Huber { delta },
+            self,
+            target,
+            reduction,
+        )
+    }
+}
+
+/// Implemented for bf16 and f32.
+impl<D: Dimension + 'static> BinaryCrossEntropyLoss<ScalarArcTensor<D>> for Variable<D> {
+    type Output = Variable0;
+    type ReducedOutput = VariableD;
+    fn bce_loss(&self, target: ScalarArcTensor<D>) -> Result<Variable0> {
+        Ok(self
+            .bce_loss_with(target, Reduction::Mean)?
+            .into_dimensionality()?)
+    }
+    fn bce_loss_with(&self, target: ScalarArcTensor<D>, reduction: Reduction) -> Result<VariableD> {
+        let loss = elementwise_loss_variable(
+            "BinaryCrossEntropyLoss",
+            ElementwiseLoss::BinaryCrossEntropy,
+            self,
+            target.clone(),
+            reduction,
+        )?;
+        check_bce_loss_target(target.view())?;
+        Ok(loss)
+    }
+}
+
+// Computes the loss of each element, see `elementwise_loss()`, and then applies the reduction.
+fn elementwise_loss_variable<D: Dimension + 'static>(
+    name: &str,
+    loss: ElementwiseLoss,
+    input: &Variable<D>,
+    target: ScalarArcTensor<D>,
+    reduction: Reduction,
+) -> Result<VariableD> {
+    let value = elementwise_loss(name, loss, input.value().view(), target.view())?;
+    let mut builder = Variable::<D>::builder();
+    if let Some(node) = input.node() {
+        let input = input.value().clone();
+        builder.edge(node, move |output_grad| {
+            let dy = output_grad.cast_into_tensor::<f32>()?;
+            elementwise_loss_backward(loss, input.view(), target.view(), dy.view())?.into_shared()
+        });
+    }
+    let output = builder.build(ScalarTensor::from(value).into_shared()?);
+    reduce_loss_variable(name, output, reduction)
+}
+
+// Applies the reduction to the loss of each sample or element, on the device of `loss`.
+fn reduce_loss_variable<D: Dimension + 'static>(
+    name: &str,
+    loss: Variable<D>,
+    reduction: Reduction,
+) -> Result<VariableD> {
+    if reduction == Reduction::None {
+        return Ok(loss.into_dyn());
+    }
+    let device = loss.device();
+    let value = reduction.reduce_tensor(
+        name,
+        loss.value().view().try_into_tensor_view::<f32>().unwrap(),
+    )?;
+    let mut builder = Variable0::builder();
+    if let Some(node) = loss.node() {
+        let device = device.clone();
+        let dim = loss.raw_dim();
+        builder.edge(node, move |output_grad| {
+            let mut dy = output_grad
+                .into_device(Device::host())?
+                .cast_into_tensor::<f32>()?
+                .into_array()?
+                .into_scalar();
+            if reduction == Reduction::Mean {
+                dy /= dim.size().max(1) as f32;
+            }
+            ScalarTensor::from(Tensor::from_elem(device, dim, dy)?).into_shared()
+        });
+    }
+    let value = ScalarArcTensor::from_elem(device, (), ScalarElem::F32(value))?;
+    Ok(builder.build(value).into_dyn())
+}
+
+// The number of samples that are not ignored.
+fn cross_entropy_loss_count<T: Scalar + Unsigned>(
+    t: TensorView1<T>,
+    ignore_index: Option<usize>,
+) -> Result<usize> {
+    let Some(ignore_index) = ignore_index else {
+        return Ok(t.len());
+    };
+    if let Some(t) = t.as_array() {
+        return Ok(t
+            .iter()
+            .filter(|t| t.to_usize().unwrap() != ignore_index)
+            .count());
+    }
+    #[cfg(not(feature = "device"))]
+    {
+        unreachable!()
+    }
+    #[cfg(feature = "device")]
+    {
+        let ignore_index = ignore_index.to_u32().unwrap_or(u32::MAX);
+        let mut mask = unsafe { Tensor::<u32, _>::uninit(t.device(), t.len())? };
+        macro_for!($T in [u8, u16, u32] {
+            if let Ok(t) = ScalarTensorView::from(t.view()).try_into_tensor_view::<$T>() {
+                let kernel = paste! {
+                    kernels::[<cross_entropy_loss_mask_ $T>]::builder()?.build(mask.device())?
+                };
+                kernel.dispatch(
+                    ignore_index,
+                    t.as_slice().unwrap(),
+                    mask.as_slice_mut().unwrap(),
+                )?;
+                return Ok(mask.sum()? as usize);
+            }
+        });
+        unreachable!()
+    }
 }
 
+// Computes the gradient of the input given the gradient of the loss of each sample `dy`.
 pub(crate) fn cross_entropy_loss_backward<T1: Scalar + Float, T2: Scalar + Unsigned>(
     x: TensorView2<T1>,
     t: TensorView1<T2>,
-    mut dy: f32,
+    dy: TensorView1<f32>,
+    ignore_index: Option<usize>,
 ) -> Result<Tensor2<T1>> {
-    dy /= x.dim().0 as f32;
-    if let Some((x, t)) = x.as_array().zip(t.as_array()) {
-        let mut dx = Array2::<T1>::zeros(x.raw_dim());
-        for ((x, t), mut dx) in x
-            .outer_iter()
-            .zip(t.iter().copied())
-            .zip(dx.outer_iter_mut())
-        {
-            let x_iter = x.iter().map(|x| x.cast::<f32>());
-            let m = x_iter
-                .clone()
-                .fold(x_iter.clone().next().unwrap_or_default(), |m, x| m.max(x));
-            let s: f32 = x_iter.clone().map(|x| (x - m).exp()).sum();
-            for (i, (x, dx)) in x_iter.zip(dx.iter_mut()).enumerate() {
-                let t = (i == t.to_usize().unwrap()) as u8 as f32;
-                *dx = (dy * ((x - m).exp() / s - t)).cast();
-            }
-        }
-        return Ok(dx.into());
+    if let Some(((x, t), dy)) = x.as_array().zip(t.as_array()).zip(dy.as_array()) {
+        return Ok(cross_entropy_loss_backward_host(x, t, dy, ignore_index).into());
     }
     #[cfg(not(feature = "device"))]
     {
@@ -108,6 +515,9 @@ pub(crate) fn cross_entropy_loss_backward<T1: Scalar + Float, T2: Scalar + Unsig
     #[cfg(feature = "device")]
     {
         let (batch_size, classes) = x.dim();
+        let ignore_index = ignore_index
+            .and_then(|ignore_index| ignore_index.to_u32())
+            .unwrap_or(u32::MAX);
         macro_for!($X in [bf16, f32] {
             macro_for!($T in [u8, u16, u32] {
                 if x.scalar_type() == $X::SCALAR_TYPE && t.scalar_type() == $T::SCALAR_TYPE {
@@ -125,7 +535,8 @@ pub(crate) fn cross_entropy_loss_backward<T1: Scalar + Float, T2: Scalar + Unsig
                             x.as_slice().unwrap(),
                             t.as_slice().unwrap(),
                             classes.to_u32().unwrap(),
-                            dy,
+                            ignore_index,
+                            dy.as_slice().unwrap(),
                             dx.as_slice_mut().unwrap(),
                         )?;
                     return Ok(ScalarTensor::from(dx).try_into_tensor().unwrap());
@@ -136,6 +547,64 @@ pub(crate) fn cross_entropy_loss_backward<T1: Scalar + Float, T2: Scalar + Unsig
     }
 }
 
+// Computes the gradient of the input given the gradient of the loss of each sample.
+fn cross_entropy_loss_backward_host<T1: Scalar + Float, T2: Scalar + Unsigned>(
+    x: ArrayView2<T1>,
+    t: ArrayView1<T2>,
+    dy: ArrayView1<f32>,
+    ignore_index: Option<usize>,
+) -> Array2<T1> {
+    let mut dx = Array2::<T1>::zeros(x.raw_dim());
+    for (((x, t), dy), mut dx) in x
+        .outer_iter()
+        .zip(t.iter().copied())
+        .zip(dy.iter().copied())
+        .zip(dx.outer_iter_mut())
+    {
+        if Some(t.to_usize().unwrap()) == ignore_index {
+            continue;
+        }
+        let x_iter = x.iter().map(|x| x.cast::<f32>());
+        let m = x_iter
+            .clone()
+            .fold(x_iter.clone().next().unwrap_or_default(), |m, x| m.max(x));
+        let s: f32 = x_iter.clone().map(|x| (x - m).exp()).sum();
+        for (i, (x, dx)) in x_iter.zip(dx.iter_mut()).enumerate() {
+            let t = (i == t.to_usize().unwrap()) as u8 as f32;
+            *dx = (dy * ((x - m).exp() / s - t)).cast();
+        }
+    }
+    dx
+}
+
+// Divides the rows of x by their norms and multiplies by scale, in f32.
+fn normalize_rows_variable(x: &Variable2, scale: f32) -> Result<Variable2> {
+    let scalar_type = x.scalar_type();
+    let input = x.value().view().cast_into_tensor::<f32>()?;
+    let rows = input.dim().0;
+    let norms = input
+        .mul(&input)?
+        .sum_axis(Axis(1))?
+        .sqrt()?
+        .clamp_min(ScalarElem::F32(f32::EPSILON))?
+        .into_shape([rows, 1])?;
+    let unit = input.div(&norms)?;
+    let value = unit.scaled_cast::<f32>(scale)?;
+    let mut builder = Variable2::builder();
+    if let Some(node) = x.node() {
+        builder.edge(node, move |output_grad| {
+            // dx = scale * (dy - (dy . u) u) / norm
+            let dy = output_grad.cast_into_tensor::<f32>()?;
+            let d = dy.mul(&unit)?.sum_axis(Axis(1))?.into_shape([rows, 1])?;
+            let mut dx = dy;
+            dx.scaled_add(-1., &unit.mul(&d)?)?;
+            let dx = dx.div(&norms)?.scaled_cast::<f32>(scale)?;
+            ScalarTensor::from(dx).cast_into(scalar_type)?.into_shared()
+        });
+    }
+    Ok(builder.build(ScalarTensor::from(value).into_shared()?))
+}
+
 #[cfg(feature = "device")]
 #[module]
 mod kernels {
@@ -147,6 +616,44 @@ mod kernels {
     use krnl_core::{buffer::UnsafeIndex, half::bf16, num_traits::Float, scalar::Scalar};
     use paste::paste;
 
+    macro_for!($T in [u8, u16, u32] {
+        paste! {
+            #[kernel]
+            pub fn [<cross_entropy_loss_mask_ $T>](
+                ignore_index: u32,
+                #[item] t: $T,
+                #[item] y: &mut u32,
+            ) {
+                *y = (t as u32 != ignore_index) as u32;
+            }
+        }
+    });
+
+    macro_for!($X in [bf16, f32] {
+        paste! {
+            // Scatters -w * dy / total to the target of each sample, and 0 elsewhere.
+            #[kernel]
+            pub fn [<nll_loss_backward_ $X>](
+                classes: u32,
+                #[global] t: Slice<u32>,
+                #[global] w: Slice<f32>,
+                #[global] total: Slice<f32>,
+                #[global] dy: Slice<f32>,
+                #[item] dx: &mut $X,
+            ) {
+                let classes = classes as usize;
+                let idx = kernel.item_id();
+                let row = idx / classes;
+                let total = total[0];
+                *dx = if t[row] as usize == idx % classes && total != 0. {
+                    (-w[row] * dy[0] / total).cast()
+                } else {
+                    Default::default()
+                };
+            }
+        }
+    });
+
     macro_for!($X in [bf16, f32] {
         macro_for!($T in [u8, u16, u32] {
             paste! {
@@ -155,14 +662,24 @@ mod kernels {
                     #[global] x: Slice<$X>,
                     #[global] t: Slice<$T>,
                     classes: u32,
-                    dy: f32,
+                    ignore_index: u32,
+                    #[global] dy: Slice<f32>,
                     #[global] dx: UnsafeSlice<$X>,
                 ) {
                     let idx = kernel.global_id();
-                    if idx > t.len() {
+                    if idx >= t.len() {
                         return;
                     }
                     let classes = classes as usize;
+                    if t[idx] as u32 == ignore_index {
+                        for i in 0..classes {
+                            unsafe {
+                                *dx.unsafe_index_mut(idx * classes + i) = Default::default();
+                            }
+                        }
+                        return;
+                    }
+                    let dy = dy[idx];
                     let mut m = x[idx * classes].cast::<f32>();
                     for i in 1..classes {
                         let x = x[idx * classes + i].cast::<f32>();
@@ -2,26 +2,31 @@
 use super::autograd::ParameterBase;
 use super::{
     autograd::{
-        Parameter, Parameter1, Parameter2, ParameterD, ParameterViewMut, ParameterViewMut1,
-        ParameterViewMut2, ParameterViewMutD, Variable, Variable1, Variable2, Variable3, Variable4,
+        no_grad, Parameter, Parameter1, Parameter2, Parameter4, ParameterD, ParameterViewMut,
+        ParameterViewMut1, ParameterViewMut2, ParameterViewMut4, ParameterViewMutD, Variable,
+        Variable1, Variable2, Variable3, Variable4, VariableD,
     },
     optimizer::Optimizer,
 };
+#[cfg(doc)]
+use crate::tensor::ScalarTensorView;
 use crate::{
     ops::{
         AddAssign, Col2ImConv2, Col2ImConv2Options, Im2ColConv2, Im2ColConv2Options, MaxPool2 as _,
         MaxPool2Backward as _, MaxPool2Options,
     },
     tensor::{
-        parallel::parallel_size, ScalarArcTensor, ScalarArcTensor4, ScalarTensor, ScalarTensor4,
-        ScalarTensorBase, ScalarTensorView4, Tensor, TensorView, TensorViewMut,
+        parallel::parallel_size, ArcTensor1, ArcTensor2, ScalarArcTensor, ScalarArcTensor2,
+        ScalarArcTensor4, ScalarArcTensorD, ScalarCowTensor, ScalarTensor, ScalarTensor4,
+        ScalarTensorBase, ScalarTensorView4, Tensor, Tensor1, Tensor2, Tensor4, TensorView,
+        TensorView1, TensorView2, TensorView4, TensorViewMut, TensorViewMut1,
     },
 };
 use anyhow::{bail, Error, Result};
 pub use autograph_derive::*;
 #[cfg(feature = "device")]
 use dry::macro_for;
-use half::bf16;
+use half::{bf16, f16};
 #[cfg(feature = "device")]
 use krnl::buffer::ScalarSliceMut;
 #[cfg(feature = "device")]
@@ -29,19 +34,38 @@ use krnl::macros::module;
 use krnl::{
     buffer::{Buffer, ScalarBuffer, ScalarData},
     device::Device,
-    scalar::{Scalar, ScalarType},
+    scalar::{Scalar, ScalarElem, ScalarType},
+};
+use ndarray::{
+    linalg::Dot, s, Array, Array1, Array2, Array4, ArrayView1, ArrayView2, ArrayView4, Axis,
+    Dimension, IntoDimension, Ix1, Ix2, IxDyn, Zip,
 };
-use ndarray::{linalg::Dot, Dimension, IntoDimension, Ix1, Ix2};
+#[cfg(feature = "device")]
+use num_traits::ToPrimitive;
+use parking_lot::Mutex;
 #[cfg(feature = "device")]
 use paste::paste;
 use rand::{
     distributions::{Distribution, Uniform},
-    thread_rng,
+    rngs::StdRng,
+    Rng, SeedableRng,
 };
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
-use std::mem::size_of;
+#[cfg(feature = "serde")]
+use std::path::Path;
+use std::{
+    any::type_name,
+    fmt::{self, Debug, Formatter},
+    marker::PhantomData,
+    mem::size_of,
+    ops::Range,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
 
 mod conv_direct;
 
@@ -239,15 +263,28 @@ pub mod builder {
         dim
     }
 
+    fn init_rng(seed: Option<u64>) -> StdRng {
+        if let Some(seed) = seed {
+            StdRng::seed_from_u64(seed)
+        } else {
+            StdRng::from_entropy()
+        }
+    }
+
     /// Builder for creating a [`Conv`].
     pub struct ConvBuilder<D: Dimension> {
         inputs: usize,
         outputs: usize,
         filter: D,
         padding: D,
+        padding_mode: PaddingMode,
         stride: D,
         dilation: D,
+        kernel_kind: KernelKind,
         bias: bool,
+        init_weight: Option<Init>,
+        init_bias: Option<Init>,
+        seed: Option<u64>,
         scalar_type: ScalarType,
         device: Device,
     }
@@ -259,9 +296,14 @@ pub mod builder {
                 outputs: 0,
                 filter: D::default(),
                 padding: D::default(),
+                padding_mode: PaddingMode::default(),
                 stride: dim_ones(),
                 dilation: dim_ones(),
+                kernel_kind: KernelKind::default(),
                 bias: false,
+                init_weight: None,
+                init_bias: None,
+                seed: None,
                 scalar_type: ScalarType::F32,
                 device: Device::host(),
             }
@@ -291,6 +333,15 @@ pub mod builder {
                 ..self
             }
         }
+        /// Sets the padding mode. Defaults to [`PaddingMode::Valid`].
+        ///
+        /// See [`PaddingMode`].
+        pub fn padding_mode(self, padding_mode: PaddingMode) -> Self {
+            Self {
+                padding_mode,
+                ..self
+            }
+        }
         /// Sets the stride. Defaults to 1.
         pub fn stride(self, stride: impl IntoDimension<Dim = D>) -> Self {
             Self {
@@ -305,10 +356,46 @@ pub mod builder {
                 ..self
             }
         }
+        /// Sets the kernel kind. Defaults to [`KernelKind::CrossCorrelation`].
+        ///
+        /// See [`KernelKind`].
+        pub fn kernel_kind(self, kernel_kind: KernelKind) -> Self {
+            Self {
+                kernel_kind,
+                ..self
+            }
+        }
         /// Add a bias. Defaults to false.
         pub fn bias(self, bias: bool) -> Self {
             Self { bias, ..self }
         }
+        /// Sets the initialization of the weight.
+        ///
+        /// Defaults to [`Init::Uniform`] with `high = sqrt(2 / FAN_IN)` and `low = -high`.
+        pub fn init_weight(self, init: Init) -> Self {
+            Self {
+                init_weight: Some(init),
+                ..self
+            }
+        }
+        /// Sets the initialization of the bias.
+        ///
+        /// Defaults to [`Init::Uniform`] with `high = sqrt(2 / FAN_IN)` and `low = -high`.
+        pub fn init_bias(self, init: Init) -> Self {
+            Self {
+                init_bias: Some(init),
+                ..self
+            }
+        }
+        /// Seeds the random number generator used to initialize parameters.
+        ///
+        /// By default, the generator is seeded from entropy.
+        pub fn seed(self, seed: u64) -> Self {
+            Self {
+                seed: Some(seed),
+                ..self
+            }
+        }
         /// Sets the scalar type. Defaults to F32.
         ///
         /// BF16 and F32 are implemented.
@@ -326,6 +413,7 @@ pub mod builder {
         ///
         /// # Errors
         /// - The `scalar_type` is not BF16 or F32.
+        /// - The `padding` is not 0 with [`PaddingMode::Same`].
         /// - Initializing parameters on the `device` failed.
         pub fn build(self) -> Result<Conv<D>> {
             let Self {
@@ -333,21 +421,30 @@ pub mod builder {
                 outputs,
                 filter,
                 padding,
+                padding_mode,
                 stride,
                 dilation,
+                kernel_kind,
                 bias,
+                init_weight,
+                init_bias,
+                seed,
                 scalar_type,
                 device,
             } = self;
             if !matches!(scalar_type, ScalarType::BF16 | ScalarType::F32) {
                 bail!("Conv {scalar_type:?} not implemented!");
             }
+            if padding_mode == PaddingMode::Same && padding.slice().iter().any(|p| *p != 0) {
+                bail!("Conv padding {padding:?} with {padding_mode:?} not supported!");
+            }
             let a = if inputs > 0 {
                 f32::sqrt(2. / (inputs * filter.size()) as f32)
             } else {
                 0.
             };
-            let mut rng = thread_rng();
+            let init_default = Init::Uniform { low: -a, high: a };
+            let mut rng = init_rng(seed);
             let mut weight_dim = <D::Larger as Dimension>::Larger::zeros(2 + filter.ndim());
             weight_dim[0] = outputs;
             weight_dim[1] = inputs;
@@ -356,33 +453,21 @@ pub mod builder {
                 .get_mut(2..)
                 .unwrap()
                 .copy_from_slice(filter.slice());
-            let weight_iter = Uniform::new(-a, a)
-                .sample_iter(&mut rng)
-                .take(weight_dim.size());
-            let weight = match scalar_type {
-                ScalarType::BF16 => ScalarBuffer::from(Buffer::from(
-                    weight_iter.map(bf16::from_f32).collect::<Vec<_>>(),
-                )),
-                ScalarType::F32 => {
-                    ScalarBuffer::from(Buffer::from(weight_iter.collect::<Vec<_>>()))
-                }
-                _ => unreachable!(),
-            };
-            let weight = weight.into_device(device.clone())?;
+            let weight = init_weight.unwrap_or(init_default).sample_buffer(
+                &mut rng,
+                weight_dim.size(),
+                scalar_type,
+                device.clone(),
+            )?;
             let weight =
                 Parameter::from(ScalarTensor::from(weight).into_shape(weight_dim).unwrap());
             let bias = if bias {
-                let bias_iter = Uniform::new(-a, a).sample_iter(rng).take(outputs);
-                let bias = match scalar_type {
-                    ScalarType::BF16 => ScalarBuffer::from(Buffer::from(
-                        bias_iter.map(bf16::from_f32).collect::<Vec<_>>(),
-                    )),
-                    ScalarType::F32 => {
-                        ScalarBuffer::from(Buffer::from(bias_iter.collect::<Vec<_>>()))
-                    }
-                    _ => unreachable!(),
-                };
-                let bias = bias.into_device(device)?;
+                let bias = init_bias.unwrap_or(init_default).sample_buffer(
+                    &mut rng,
+                    outputs,
+                    scalar_type,
+                    device,
+                )?;
                 Some(Parameter::from(ScalarTensor::from(bias)))
             } else {
                 None
@@ -390,9 +475,205 @@ pub mod builder {
             Ok(Conv {
                 weight,
                 padding,
+                padding_mode,
                 stride,
                 dilation,
+                kernel_kind,
+                bias,
+                hooks: Hooks::default(),
+            })
+        }
+    }
+
+    /// Builder for creating a [`ConvTranspose2`].
+    pub struct ConvTranspose2Builder {
+        inputs: usize,
+        outputs: usize,
+        filter: Ix2,
+        stride: Ix2,
+        padding: Ix2,
+        output_padding: Ix2,
+        bias: bool,
+        init_weight: Option<Init>,
+        init_bias: Option<Init>,
+        seed: Option<u64>,
+        scalar_type: ScalarType,
+        device: Device,
+    }
+
+    impl ConvTranspose2Builder {
+        pub(super) fn new() -> Self {
+            Self {
+                inputs: 0,
+                outputs: 0,
+                filter: Ix2(0, 0),
+                stride: Ix2(1, 1),
+                padding: Ix2(0, 0),
+                output_padding: Ix2(0, 0),
+                bias: false,
+                init_weight: None,
+                init_bias: None,
+                seed: None,
+                scalar_type: ScalarType::F32,
+                device: Device::host(),
+            }
+        }
+        /// Sets the number of input channels.
+        pub fn inputs(self, inputs: usize) -> Self {
+            Self { inputs, ..self }
+        }
+        /// Sets the number of output channels.
+        pub fn outputs(self, outputs: usize) -> Self {
+            Self { outputs, ..self }
+        }
+        /// Sets size of the filter.
+        pub fn filter(self, filter: impl IntoDimension<Dim = Ix2>) -> Self {
+            Self {
+                filter: filter.into_dimension(),
+                ..self
+            }
+        }
+        /// Sets the stride. Defaults to 1.
+        pub fn stride(self, stride: impl IntoDimension<Dim = Ix2>) -> Self {
+            Self {
+                stride: stride.into_dimension(),
+                ..self
+            }
+        }
+        /// Sets the padding, removed from both sides of the output. Defaults to 0.
+        pub fn padding(self, padding: impl IntoDimension<Dim = Ix2>) -> Self {
+            Self {
+                padding: padding.into_dimension(),
+                ..self
+            }
+        }
+        /// Sets the output padding, added to the end of the output. Defaults to 0.
+        ///
+        /// Selects between the output shapes that a convolution with `stride` maps to the same
+        /// input shape.
+        pub fn output_padding(self, output_padding: impl IntoDimension<Dim = Ix2>) -> Self {
+            Self {
+                output_padding: output_padding.into_dimension(),
+                ..self
+            }
+        }
+        /// Add a bias. Defaults to false.
+        pub fn bias(self, bias: bool) -> Self {
+            Self { bias, ..self }
+        }
+        /// Sets the initialization of the weight.
+        ///
+        /// Defaults to [`Init::Uniform`] with `high = sqrt(2 / FAN_IN)` and `low = -high`.
+        pub fn init_weight(self, init: Init) -> Self {
+            Self {
+                init_weight: Some(init),
+                ..self
+            }
+        }
+        /// Sets the initialization of the bias.
+        ///
+        /// Defaults to [`Init::Uniform`] with `high = sqrt(2 / FAN_IN)` and `low = -high`.
+        pub fn init_bias(self, init: Init) -> Self {
+            Self {
+                init_bias: Some(init),
+                ..self
+            }
+        }
+        /// Seeds the random number generator used to initialize parameters.
+        ///
+        /// By default, the generator is seeded from entropy.
+        pub fn seed(self, seed: u64) -> Self {
+            Self {
+                seed: Some(seed),
+                ..self
+            }
+        }
+        /// Sets the scalar type. Defaults to F32.
+        ///
+        /// BF16 and F32 are implemented.
+        pub fn scalar_type(self, scalar_type: ScalarType) -> Self {
+            Self {
+                scalar_type,
+                ..self
+            }
+        }
+        /// Sets the device. Defaults to the host.
+        pub fn device(self, device: Device) -> Self {
+            Self { device, ..self }
+        }
+        /// Builds the layer.
+        ///
+        /// # Errors
+        /// - The `scalar_type` is not BF16 or F32.
+        /// - The `stride` is 0.
+        /// - The `output_padding` is not less than the `stride`.
+        /// - Initializing parameters on the `device` failed.
+        pub fn build(self) -> Result<ConvTranspose2> {
+            let Self {
+                inputs,
+                outputs,
+                filter,
+                stride,
+                padding,
+                output_padding,
+                bias,
+                init_weight,
+                init_bias,
+                seed,
+                scalar_type,
+                device,
+            } = self;
+            if !matches!(scalar_type, ScalarType::BF16 | ScalarType::F32) {
+                bail!("ConvTranspose2 {scalar_type:?} not implemented!");
+            }
+            if stride.slice().contains(&0) {
+                bail!("ConvTranspose2 stride {stride:?} must be positive!");
+            }
+            if output_padding
+                .slice()
+                .iter()
+                .zip(stride.slice())
+                .any(|(op, s)| op >= s)
+            {
+                bail!(
+                    "ConvTranspose2 output_padding {output_padding:?} must be less than stride {stride:?}!"
+                );
+            }
+            let a = if inputs > 0 {
+                f32::sqrt(2. / (inputs * filter.size()) as f32)
+            } else {
+                0.
+            };
+            let init_default = Init::Uniform { low: -a, high: a };
+            let mut rng = init_rng(seed);
+            let (fh, fw) = filter.into_pattern();
+            let weight_dim = [inputs, outputs, fh, fw];
+            let weight = init_weight.unwrap_or(init_default).sample_buffer(
+                &mut rng,
+                weight_dim.iter().product(),
+                scalar_type,
+                device.clone(),
+            )?;
+            let weight =
+                Parameter::from(ScalarTensor::from(weight).into_shape(weight_dim).unwrap());
+            let bias = if bias {
+                let bias = init_bias.unwrap_or(init_default).sample_buffer(
+                    &mut rng,
+                    outputs,
+                    scalar_type,
+                    device,
+                )?;
+                Some(Parameter::from(ScalarTensor::from(bias)))
+            } else {
+                None
+            };
+            Ok(ConvTranspose2 {
+                weight,
+                stride,
+                padding,
+                output_padding,
                 bias,
+                hooks: Hooks::default(),
             })
         }
     }
@@ -402,6 +683,9 @@ pub mod builder {
         inputs: usize,
         outputs: usize,
         bias: bool,
+        init_weight: Option<Init>,
+        init_bias: Option<Init>,
+        seed: Option<u64>,
         scalar_type: ScalarType,
         device: Device,
     }
@@ -412,6 +696,9 @@ pub mod builder {
                 inputs: 0,
                 outputs: 0,
                 bias: false,
+                init_weight: None,
+                init_bias: None,
+                seed: None,
                 scalar_type: ScalarType::F32,
                 device: Device::host(),
             }
@@ -431,6 +718,33 @@ pub mod builder {
         pub fn bias(self, bias: bool) -> Self {
             Self { bias, ..self }
         }
+        /// Sets the initialization of the weight.
+        ///
+        /// Defaults to [`Init::Uniform`] with `high = sqrt(2 / FAN_IN)` and `low = -high`.
+        pub fn init_weight(self, init: Init) -> Self {
+            Self {
+                init_weight: Some(init),
+                ..self
+            }
+        }
+        /// Sets the initialization of the bias.
+        ///
+        /// Defaults to [`Init::Uniform`] with `high = sqrt(2 / FAN_IN)` and `low = -high`.
+        pub fn init_bias(self, init: Init) -> Self {
+            Self {
+                init_bias: Some(init),
+                ..self
+            }
+        }
+        /// Seeds the random number generator used to initialize parameters.
+        ///
+        /// By default, the generator is seeded from entropy.
+        pub fn seed(self, seed: u64) -> Self {
+            Self {
+                seed: Some(seed),
+                ..self
+            }
+        }
         /// Sets the scalar type. Defaults to F32.
         ///
         /// BF16 and F32 are implemented.
@@ -454,6 +768,9 @@ pub mod builder {
                 inputs,
                 outputs,
                 bias,
+                init_weight,
+                init_bias,
+                seed,
                 scalar_type,
                 device,
             } = self;
@@ -465,42 +782,137 @@ pub mod builder {
             } else {
                 0.
             };
-            let mut rng = thread_rng();
-            let weight_iter = Uniform::new(-a, a)
-                .sample_iter(&mut rng)
-                .take(inputs * outputs);
-            let weight = match scalar_type {
-                ScalarType::BF16 => ScalarBuffer::from(Buffer::from(
-                    weight_iter.map(bf16::from_f32).collect::<Vec<_>>(),
-                )),
-                ScalarType::F32 => {
-                    ScalarBuffer::from(Buffer::from(weight_iter.collect::<Vec<_>>()))
-                }
-                _ => unreachable!(),
-            };
-            let weight = weight.into_device(device.clone())?;
+            let init_default = Init::Uniform { low: -a, high: a };
+            let mut rng = init_rng(seed);
+            let weight = init_weight.unwrap_or(init_default).sample_buffer(
+                &mut rng,
+                inputs * outputs,
+                scalar_type,
+                device.clone(),
+            )?;
             let weight = Parameter::from(
                 ScalarTensor::from(weight)
                     .into_shape([inputs, outputs])
                     .unwrap(),
             );
             let bias = if bias {
-                let bias_iter = Uniform::new(-a, a).sample_iter(rng).take(outputs);
-                let bias = match scalar_type {
-                    ScalarType::BF16 => ScalarBuffer::from(Buffer::from(
-                        bias_iter.map(bf16::from_f32).collect::<Vec<_>>(),
-                    )),
-                    ScalarType::F32 => {
-                        ScalarBuffer::from(Buffer::from(bias_iter.collect::<Vec<_>>()))
-                    }
-                    _ => unreachable!(),
-                };
-                let bias = bias.into_device(device)?;
+                let bias = init_bias.unwrap_or(init_default).sample_buffer(
+                    &mut rng,
+                    outputs,
+                    scalar_type,
+                    device,
+                )?;
                 Some(Parameter::from(ScalarTensor::from(bias)))
             } else {
                 None
             };
-            Ok(Dense { weight, bias })
+            Ok(Dense {
+                weight,
+                bias,
+                hooks: Hooks::default(),
+            })
+        }
+    }
+
+    /// Builder for creating a [`Maxout`].
+    pub struct MaxoutBuilder {
+        dense: DenseBuilder,
+        outputs: usize,
+        num_pieces: usize,
+    }
+
+    impl MaxoutBuilder {
+        pub(super) fn new() -> Self {
+            Self {
+                dense: DenseBuilder::new(),
+                outputs: 0,
+                num_pieces: 1,
+            }
+        }
+    }
+
+    impl MaxoutBuilder {
+        /// Sets the number of input channels.
+        pub fn inputs(self, inputs: usize) -> Self {
+            Self {
+                dense: self.dense.inputs(inputs),
+                ..self
+            }
+        }
+        /// Sets the number of output channels.
+        pub fn outputs(self, outputs: usize) -> Self {
+            Self { outputs, ..self }
+        }
+        /// Sets the number of linear pieces per output. Defaults to 1.
+        pub fn num_pieces(self, num_pieces: usize) -> Self {
+            Self { num_pieces, ..self }
+        }
+        /// Adds a bias. Defaults to false.
+        pub fn bias(self, bias: bool) -> Self {
+            Self {
+                dense: self.dense.bias(bias),
+                ..self
+            }
+        }
+        /// Sets the initialization of the weight.
+        ///
+        /// See [`DenseBuilder::init_weight()`].
+        pub fn init_weight(self, init: Init) -> Self {
+            Self {
+                dense: self.dense.init_weight(init),
+                ..self
+            }
+        }
+        /// Sets the initialization of the bias.
+        ///
+        /// See [`DenseBuilder::init_bias()`].
+        pub fn init_bias(self, init: Init) -> Self {
+            Self {
+                dense: self.dense.init_bias(init),
+                ..self
+            }
+        }
+        /// Seeds the random number generator used to initialize parameters.
+        ///
+        /// By default, the generator is seeded from entropy.
+        pub fn seed(self, seed: u64) -> Self {
+            Self {
+                dense: self.dense.seed(seed),
+                ..self
+            }
+        }
+        /// Sets the scalar type. Defaults to F32.
+        ///
+        /// BF16 and F32 are implemented.
+        pub fn scalar_type(self, scalar_type: ScalarType) -> Self {
+            Self {
+                dense: self.dense.scalar_type(scalar_type),
+                ..self
+            }
+        }
+        /// Sets the device. Defaults to the host.
+        pub fn device(self, device: Device) -> Self {
+            Self {
+                dense: self.dense.device(device),
+                ..self
+            }
+        }
+        /// Builds the layer.
+        ///
+        /// # Errors
+        /// - `num_pieces` is 0.
+        /// - See [`DenseBuilder::build()`].
+        pub fn build(self) -> Result<Maxout> {
+            let Self {
+                dense,
+                outputs,
+                num_pieces,
+            } = self;
+            if num_pieces == 0 {
+                bail!("Maxout num_pieces must be greater than 0!");
+            }
+            let dense = dense.outputs(outputs * num_pieces).build()?;
+            Ok(Maxout { dense, num_pieces })
         }
     }
 
@@ -508,6 +920,7 @@ pub mod builder {
     pub struct MaxPoolBuilder<D: Dimension> {
         filter: D,
         stride: Option<D>,
+        padding_mode: PaddingMode,
     }
 
     impl<D: Dimension> MaxPoolBuilder<D> {
@@ -515,6 +928,7 @@ pub mod builder {
             Self {
                 filter: D::default(),
                 stride: None,
+                padding_mode: PaddingMode::default(),
             }
         }
         /// Sets the size of the pool filter.
@@ -531,11 +945,447 @@ pub mod builder {
                 ..self
             }
         }
+        /// Sets the padding mode. Defaults to [`PaddingMode::Valid`].
+        ///
+        /// With [`PaddingMode::Same`], the input is padded with -inf.
+        ///
+        /// See [`PaddingMode`].
+        pub fn padding_mode(self, padding_mode: PaddingMode) -> Self {
+            Self {
+                padding_mode,
+                ..self
+            }
+        }
         /// Builds the layer.
         pub fn build(self) -> MaxPool<D> {
-            let Self { filter, stride } = self;
+            let Self {
+                filter,
+                stride,
+                padding_mode,
+            } = self;
+            let stride = stride.unwrap_or(filter.clone());
+            MaxPool {
+                filter,
+                stride,
+                padding_mode,
+                hooks: Hooks::default(),
+            }
+        }
+    }
+
+    /// Builder for creating an [`AvgPool`].
+    pub struct AvgPoolBuilder<D: Dimension> {
+        filter: D,
+        stride: Option<D>,
+        padding_mode: PaddingMode,
+    }
+
+    impl<D: Dimension> AvgPoolBuilder<D> {
+        pub(super) fn new() -> Self {
+            Self {
+                filter: D::default(),
+                stride: None,
+                padding_mode: PaddingMode::default(),
+            }
+        }
+        /// Sets the size of the pool filter.
+        pub fn filter(self, filter: impl IntoDimension<Dim = D>) -> Self {
+            Self {
+                filter: filter.into_dimension(),
+                ..self
+            }
+        }
+        /// Sets the stride. Defaults to filter.
+        pub fn stride(self, stride: impl IntoDimension<Dim = D>) -> Self {
+            Self {
+                stride: Some(stride.into_dimension()),
+                ..self
+            }
+        }
+        /// Sets the padding mode. Defaults to [`PaddingMode::Valid`].
+        ///
+        /// With [`PaddingMode::Same`], padded elements are excluded from the average.
+        ///
+        /// See [`PaddingMode`].
+        pub fn padding_mode(self, padding_mode: PaddingMode) -> Self {
+            Self {
+                padding_mode,
+                ..self
+            }
+        }
+        /// Builds the layer.
+        pub fn build(self) -> AvgPool<D> {
+            let Self {
+                filter,
+                stride,
+                padding_mode,
+            } = self;
             let stride = stride.unwrap_or(filter.clone());
-            MaxPool { filter, stride }
+            AvgPool {
+                filter,
+                stride,
+                padding_mode,
+            }
+        }
+    }
+
+    /// Builder for creating a [`Softmax`].
+    pub struct SoftmaxBuilder {
+        axis: Option<usize>,
+    }
+
+    impl SoftmaxBuilder {
+        pub(super) fn new() -> Self {
+            Self { axis: None }
+        }
+        /// Sets the axis. Defaults to the last axis.
+        pub fn axis(self, axis: usize) -> Self {
+            Self { axis: Some(axis) }
+        }
+        /// Builds the layer.
+        pub fn build(self) -> Softmax {
+            Softmax { axis: self.axis }
+        }
+    }
+
+    /// Builder for creating a [`LogSoftmax`].
+    pub struct LogSoftmaxBuilder {
+        axis: Option<usize>,
+    }
+
+    impl LogSoftmaxBuilder {
+        pub(super) fn new() -> Self {
+            Self { axis: None }
+        }
+        /// Sets the axis. Defaults to the last axis.
+        pub fn axis(self, axis: usize) -> Self {
+            Self { axis: Some(axis) }
+        }
+        /// Builds the layer.
+        pub fn build(self) -> LogSoftmax {
+            LogSoftmax { axis: self.axis }
+        }
+    }
+
+    /// Builder for creating a [`Flatten`].
+    pub struct FlattenBuilder<D: Dimension> {
+        start_dim: usize,
+        end_dim: Option<usize>,
+        _m: PhantomData<D>,
+    }
+
+    impl<D: Dimension> FlattenBuilder<D> {
+        pub(super) fn new() -> Self {
+            Self {
+                start_dim: 1,
+                end_dim: None,
+                _m: PhantomData,
+            }
+        }
+        /// Sets the first axis to flatten. Defaults to 1.
+        pub fn start_dim(self, start_dim: usize) -> Self {
+            Self { start_dim, ..self }
+        }
+        /// Sets the last axis to flatten, inclusive. Defaults to the last axis.
+        pub fn end_dim(self, end_dim: usize) -> Self {
+            Self {
+                end_dim: Some(end_dim),
+                ..self
+            }
+        }
+        /// Builds the layer.
+        pub fn build(self) -> Flatten<D> {
+            Flatten {
+                start_dim: self.start_dim,
+                end_dim: self.end_dim,
+                _m: PhantomData,
+            }
+        }
+    }
+
+    /// Builder for creating a [`BatchNorm2`].
+    pub struct BatchNorm2Builder {
+        channels: usize,
+        epsilon: f32,
+        momentum: f32,
+        scalar_type: ScalarType,
+        device: Device,
+    }
+
+    impl BatchNorm2Builder {
+        pub(super) fn new() -> Self {
+            Self {
+                channels: 0,
+                epsilon: 1e-5,
+                momentum: 0.1,
+                scalar_type: ScalarType::F32,
+                device: Device::host(),
+            }
+        }
+        /// Sets the number of channels.
+        pub fn channels(self, channels: usize) -> Self {
+            Self { channels, ..self }
+        }
+        /// Sets the epsilon added to the variance. Defaults to 1e-5.
+        pub fn epsilon(self, epsilon: f32) -> Self {
+            Self { epsilon, ..self }
+        }
+        /// Sets the momentum of the running statistics. Defaults to 0.1.
+        pub fn momentum(self, momentum: f32) -> Self {
+            Self { momentum, ..self }
+        }
+        /// Sets the scalar type. Defaults to F32.
+        ///
+        /// BF16 and F32 are implemented.
+        pub fn scalar_type(self, scalar_type: ScalarType) -> Self {
+            Self {
+                scalar_type,
+                ..self
+            }
+        }
+        /// Sets the device. Defaults to the host.
+        pub fn device(self, device: Device) -> Self {
+            Self { device, ..self }
+        }
+        /// Builds the layer.
+        ///
+        /// `gamma` is initialized to ones and `beta` to zeros.
+        ///
+        /// # Errors
+        /// - The `scalar_type` is not implemented.
+        /// - `epsilon` is not positive.
+        /// - `momentum` is not in the range [0, 1].
+        /// - Initializing parameters on the `device` failed.
+        pub fn build(self) -> Result<BatchNorm2> {
+            let Self {
+                channels,
+                epsilon,
+                momentum,
+                scalar_type,
+                device,
+            } = self;
+            if !matches!(scalar_type, ScalarType::BF16 | ScalarType::F32) {
+                bail!("BatchNorm2 {scalar_type:?} not implemented!");
+            }
+            if epsilon.is_nan() || epsilon <= 0. {
+                bail!("BatchNorm2 epsilon {epsilon} must be positive!");
+            }
+            if !(0. ..=1.).contains(&momentum) {
+                bail!("BatchNorm2 momentum {momentum} must be in the range [0, 1]!");
+            }
+            let gamma = ScalarTensor::ones(device.clone(), channels, scalar_type)?;
+            let beta = ScalarTensor::zeros(device.clone(), channels, scalar_type)?;
+            Ok(BatchNorm2 {
+                gamma: Parameter::from(gamma),
+                beta: Parameter::from(beta),
+                running: Mutex::new(BatchNormStats {
+                    mean: Tensor::zeros(device.clone(), channels)?.into_shared()?,
+                    var: Tensor::ones(device.clone(), channels)?.into_shared()?,
+                }),
+                epsilon,
+                momentum,
+                training: true,
+            })
+        }
+    }
+
+    /// Builder for creating a [`LayerNorm`].
+    pub struct LayerNormBuilder {
+        normalized_shape: IxDyn,
+        epsilon: f32,
+        scalar_type: ScalarType,
+        device: Device,
+    }
+
+    impl LayerNormBuilder {
+        pub(super) fn new() -> Self {
+            Self {
+                normalized_shape: IxDyn(&[]),
+                epsilon: 1e-5,
+                scalar_type: ScalarType::F32,
+                device: Device::host(),
+            }
+        }
+        /// Sets the shape of the trailing dimensions to normalize over.
+        pub fn normalized_shape(self, normalized_shape: impl IntoDimension) -> Self {
+            Self {
+                normalized_shape: normalized_shape.into_dimension().into_dyn(),
+                ..self
+            }
+        }
+        /// Sets the epsilon added to the variance. Defaults to 1e-5.
+        pub fn epsilon(self, epsilon: f32) -> Self {
+            Self { epsilon, ..self }
+        }
+        /// Sets the scalar type. Defaults to F32.
+        ///
+        /// BF16 and F32 are implemented.
+        pub fn scalar_type(self, scalar_type: ScalarType) -> Self {
+            Self {
+                scalar_type,
+                ..self
+            }
+        }
+        /// Sets the device. Defaults to the host.
+        pub fn device(self, device: Device) -> Self {
+            Self { device, ..self }
+        }
+        /// Builds the layer.
+        ///
+        /// `gamma` is initialized to ones and `beta` to zeros.
+        ///
+        /// # Errors
+        /// - The `scalar_type` is not implemented.
+        /// - `normalized_shape` is empty.
+        /// - `epsilon` is not positive.
+        /// - Initializing parameters on the `device` failed.
+        pub fn build(self) -> Result<LayerNorm> {
+            let Self {
+                normalized_shape,
+                epsilon,
+                scalar_type,
+                device,
+            } = self;
+            if !matches!(scalar_type, ScalarType::BF16 | ScalarType::F32) {
+                bail!("LayerNorm {scalar_type:?} not implemented!");
+            }
+            if normalized_shape.ndim() == 0 {
+                bail!("LayerNorm normalized_shape must not be empty!");
+            }
+            if epsilon.is_nan() || epsilon <= 0. {
+                bail!("LayerNorm epsilon {epsilon} must be positive!");
+            }
+            let gamma = ScalarTensor::ones(device.clone(), normalized_shape.clone(), scalar_type)?;
+            let beta = ScalarTensor::zeros(device, normalized_shape, scalar_type)?;
+            Ok(LayerNorm {
+                gamma: Parameter::from(gamma),
+                beta: Parameter::from(beta),
+                epsilon,
+            })
+        }
+    }
+
+    /// Builder for creating an [`Embedding`].
+    pub struct EmbeddingBuilder {
+        num_embeddings: usize,
+        embedding_dim: usize,
+        padding_idx: Option<usize>,
+        init_weight: Init,
+        seed: Option<u64>,
+        scalar_type: ScalarType,
+        device: Device,
+    }
+
+    impl EmbeddingBuilder {
+        pub(super) fn new() -> Self {
+            Self {
+                num_embeddings: 0,
+                embedding_dim: 0,
+                padding_idx: None,
+                init_weight: Init::Normal { mean: 0., std: 1. },
+                seed: None,
+                scalar_type: ScalarType::F32,
+                device: Device::host(),
+            }
+        }
+        /// Sets the number of embeddings, ie the size of the vocabulary.
+        pub fn num_embeddings(self, num_embeddings: usize) -> Self {
+            Self {
+                num_embeddings,
+                ..self
+            }
+        }
+        /// Sets the size of each embedding vector.
+        pub fn embedding_dim(self, embedding_dim: usize) -> Self {
+            Self {
+                embedding_dim,
+                ..self
+            }
+        }
+        /// Sets the padding index.
+        ///
+        /// The row of the weight at `padding_idx` is initialized to zeros and does not
+        /// receive a gradient.
+        pub fn padding_idx(self, padding_idx: usize) -> Self {
+            Self {
+                padding_idx: Some(padding_idx),
+                ..self
+            }
+        }
+        /// Sets the initialization of the weight.
+        ///
+        /// Defaults to [`Init::Normal`] with `mean = 0` and `std = 1`.
+        pub fn init_weight(self, init_weight: Init) -> Self {
+            Self {
+                init_weight,
+                ..self
+            }
+        }
+        /// Seeds the random number generator used to initialize parameters.
+        ///
+        /// By default, the generator is seeded from entropy.
+        pub fn seed(self, seed: u64) -> Self {
+            Self {
+                seed: Some(seed),
+                ..self
+            }
+        }
+        /// Sets the scalar type. Defaults to F32.
+        ///
+        /// BF16 and F32 are implemented.
+        pub fn scalar_type(self, scalar_type: ScalarType) -> Self {
+            Self {
+                scalar_type,
+                ..self
+            }
+        }
+        /// Sets the device. Defaults to the host.
+        pub fn device(self, device: Device) -> Self {
+            Self { device, ..self }
+        }
+        /// Builds the layer.
+        ///
+        /// # Errors
+        /// - The `scalar_type` is not implemented.
+        /// - `padding_idx` is out of bounds.
+        /// - Initializing parameters on the `device` failed.
+        pub fn build(self) -> Result<Embedding> {
+            let Self {
+                num_embeddings,
+                embedding_dim,
+                padding_idx,
+                init_weight,
+                seed,
+                scalar_type,
+                device,
+            } = self;
+            if !matches!(scalar_type, ScalarType::BF16 | ScalarType::F32) {
+                bail!("Embedding {scalar_type:?} not implemented!");
+            }
+            if let Some(padding_idx) = padding_idx {
+                if padding_idx >= num_embeddings {
+                    bail!("Embedding padding_idx {padding_idx} is out of bounds for {num_embeddings} embeddings!");
+                }
+            }
+            let mut rng = init_rng(seed);
+            let weight = init_weight.sample_buffer(
+                &mut rng,
+                num_embeddings * embedding_dim,
+                ScalarType::F32,
+                Device::host(),
+            )?;
+            let mut weight = ScalarTensor::from(weight)
+                .cast_into_tensor::<f32>()?
+                .into_array()?
+                .into_shape([num_embeddings, embedding_dim])?;
+            if let Some(padding_idx) = padding_idx {
+                weight.row_mut(padding_idx).fill(0.);
+            }
+            let weight = host_f32_into_device(weight, scalar_type, device)?;
+            Ok(Embedding {
+                weight: Parameter::from(weight),
+                padding_idx,
+            })
         }
     }
 }
@@ -546,6 +1396,16 @@ use builder::*;
 /// Typically Layers implement [`Forward<Variable<D>>`](Forward) for the appropriate
 /// dimension `D`.
 ///
+/// # Thread Safety
+/// The layers provided by autograph are [`Send`] and [`Sync`]. Parameters are stored in
+/// reference counted buffers that are not modified in the forward pass, so forward passes
+/// (for example with [`.forward_values()`](Layer::forward_values)) can be executed
+/// concurrently on a shared layer from multiple threads.
+///
+/// Operations from all threads on the same [`Device`] are submitted to a single queue, and are
+/// executed in order. Concurrent inference on one device does not execute kernels in
+/// parallel, but allows host work like data loading and transfers to overlap with execution.
+///
 /// # Derive
 /// [`Layer`] and [`Forward`] can be derived for structs and enums:
 /**
@@ -594,6 +1454,19 @@ struct Custom<T: Layer>  {
 }
 ```
 */
+///
+/// # Hooks
+/// Derived layers name their fields, variants, and elements of [`Vec`]. Hooks can be
+/// registered on a nested layer by its path, ie "encoder.conv" or "layers.0". See
+/// [`.register_forward_hook()`](Layer::register_forward_hook) and
+/// [`.register_backward_hook()`](Layer::register_backward_hook). Parameters are named by the
+/// same paths, see [`.try_for_each_named_parameter()`](Layer::try_for_each_named_parameter).
+/// Any layer can be wrapped in [`Hooked`] to support hooks.
+///
+/// # Save and Load
+/// With the "serde" feature, the parameters can be saved to a file with
+/// [`.save()`](Layer::save) and loaded with [`.load()`](Layer::load), for example to
+/// checkpoint training between epochs.
 pub trait Layer {
     /// Applies a function `f` to each parameter in the layer.
     ///
@@ -613,22 +1486,129 @@ pub trait Layer {
     fn try_for_each_parameter<F, E>(&self, f: F) -> Result<(), E>
     where
         F: FnMut(ParameterD) -> Result<(), E>;
-    /// Applies a function `f` to mutable parameter views of the layer.
+    /// Applies a fallible function `f` to each parameter in the layer and its name.
     ///
-    /// Convenience method for  [`.try_for_each_parameter_view_mut()`](Layer::try_for_each_parameter_view_mut)
-    /// with an infallible function.
-    fn for_each_parameter_view_mut<F>(&mut self, mut f: F) -> Result<()>
+    /// The name is a path of field names, variant names, or indices, separated by '.', like
+    /// "conv.weight" or "layers.0.bias". See [Hooks](Layer#hooks). Parameters are visited in the
+    /// same order as [`.try_for_each_parameter()`](Layer::try_for_each_parameter).
+    ///
+    /// By default, parameters are named by their index.
+    fn try_for_each_named_parameter<F, E>(&self, mut f: F) -> Result<(), E>
     where
-        F: FnMut(ParameterViewMutD),
+        F: FnMut(&str, ParameterD) -> Result<(), E>,
     {
-        self.try_for_each_parameter_view_mut(move |p| -> Result<(), std::convert::Infallible> {
-            f(p);
-            Ok(())
+        let mut index = 0;
+        self.try_for_each_parameter(|parameter| {
+            let name = index.to_string();
+            index += 1;
+            f(&name, parameter)
         })
     }
-    /// Applies a fallible function `f` to mutable parameter views of the layer.
+    /// Saves the parameters to the file at `path`.
     ///
-    /// The mutable parameter views can be provided to [`Optimizer::update()`](Optimizer::update).
+    /// The name, shape, scalar type, and data of each parameter is saved. Parameters on a
+    /// device are copied to the host.
+    ///
+    /// See [`.load()`](Layer::load).
+    ///
+    /// # Errors
+    /// - The parameters could not be copied to the host.
+    /// - The file could not be written.
+    #[cfg(feature = "serde")]
+    fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let mut parameters = Vec::new();
+        self.try_for_each_named_parameter(|name, parameter| {
+            parameters.push(NamedParameter {
+                name: name.to_string(),
+                value: parameter.value().to_device_shared(Device::host())?,
+            });
+            Ok::<_, Error>(())
+        })?;
+        let path = path.as_ref();
+        let file = match std::fs::File::create(path) {
+            Ok(file) => file,
+            Err(e) => bail!("Layer::save failed to create {path:?}: {e}"),
+        };
+        bincode::serialize_into(std::io::BufWriter::new(file), &parameters)?;
+        Ok(())
+    }
+    /// Loads the parameters from the file at `path`.
+    ///
+    /// The parameters are copied to the device of each parameter of the layer.
+    ///
+    /// See [`.save()`](Layer::save).
+    ///
+    /// # Errors
+    /// - The file could not be read.
+    /// - The names, shapes, or scalar types of the parameters do not match.
+    /// - The parameters could not be copied to the device.
+    #[cfg(feature = "serde")]
+    fn load<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let file = match std::fs::File::open(path) {
+            Ok(file) => file,
+            Err(e) => bail!("Layer::load failed to open {path:?}: {e}"),
+        };
+        let parameters: Vec<NamedParameter> =
+            bincode::deserialize_from(std::io::BufReader::new(file))?;
+        let mut names = Vec::new();
+        self.try_for_each_named_parameter(|name, _| {
+            names.push(name.to_string());
+            Ok::<_, std::convert::Infallible>(())
+        })
+        .unwrap();
+        if names.len() != parameters.len() {
+            bail!(
+                "Layer::load expected {} parameters, found {}!",
+                names.len(),
+                parameters.len()
+            );
+        }
+        for (name, parameter) in names.iter().zip(parameters.iter()) {
+            if *name != parameter.name {
+                bail!(
+                    "Layer::load expected parameter {name:?}, found {:?}!",
+                    parameter.name
+                );
+            }
+        }
+        let mut parameters = parameters.into_iter();
+        self.try_for_each_parameter_view_mut(|mut parameter| {
+            let NamedParameter { name, value } = parameters.next().unwrap();
+            if value.shape() != parameter.value().shape() {
+                bail!(
+                    "Layer::load parameter {name:?} expected shape {:?}, found {:?}!",
+                    parameter.value().shape(),
+                    value.shape()
+                );
+            }
+            if value.scalar_type() != parameter.value().scalar_type() {
+                bail!(
+                    "Layer::load parameter {name:?} expected {:?}, found {:?}!",
+                    parameter.value().scalar_type(),
+                    value.scalar_type()
+                );
+            }
+            let value = value.to_device(parameter.value().device())?;
+            parameter.value_view_mut().assign(&value)
+        })
+    }
+    /// Applies a function `f` to mutable parameter views of the layer.
+    ///
+    /// Convenience method for  [`.try_for_each_parameter_view_mut()`](Layer::try_for_each_parameter_view_mut)
+    /// with an infallible function.
+    fn for_each_parameter_view_mut<F>(&mut self, mut f: F) -> Result<()>
+    where
+        F: FnMut(ParameterViewMutD),
+    {
+        self.try_for_each_parameter_view_mut(move |p| -> Result<(), std::convert::Infallible> {
+            f(p);
+            Ok(())
+        })
+    }
+    /// Applies a fallible function `f` to mutable parameter views of the layer.
+    ///
+    /// The mutable parameter views can be provided to [`Optimizer::update()`](Optimizer::update).
     ///
     /// # Errors
     /// - The parameters are not exclusive, and could not be copied on the device.
@@ -683,6 +1663,82 @@ pub trait Layer {
         self.to_device_mut(device)?;
         Ok(self)
     }
+    /// Sets training or eval mode.
+    ///
+    /// Layers like [`Dropout`] only apply in training mode. Derived layers forward to each
+    /// child layer. By default, this does nothing.
+    fn set_training(&mut self, training: bool) {
+        let _ = training;
+    }
+    /// Registers a forward hook on the layer `name`.
+    ///
+    /// `name` is a path of field names, variant names, or indices, separated by '.'. An
+    /// empty `name` refers to this layer. See [Hooks](Layer#hooks).
+    ///
+    /// `hook` is called with the output of the layer each time it is executed, for example
+    /// to capture intermediate features. Within [`Checkpointed`] the layer is executed again
+    /// in the backward pass, calling `hook` again.
+    ///
+    /// Hooks are supported by [`Conv`], [`ConvTranspose2`], [`Dense`], and [`MaxPool`]. Other
+    /// layers can be wrapped in [`Hooked`].
+    ///
+    /// # Errors
+    /// - `name` was not found.
+    /// - The layer does not support hooks.
+    fn register_forward_hook(&mut self, name: &str, hook: ForwardHook) -> Result<()> {
+        let _ = hook;
+        if name.is_empty() {
+            bail!("{} does not support hooks!", type_name::<Self>());
+        }
+        bail!("Layer {name:?} not found in {}!", type_name::<Self>());
+    }
+    /// Registers a backward hook on the layer `name`.
+    ///
+    /// `name` is a path of field names, variant names, or indices, separated by '.'. An
+    /// empty `name` refers to this layer. See [Hooks](Layer#hooks).
+    ///
+    /// `hook` is called with the gradient of the output of the layer during the backward pass,
+    /// for example to compute saliency maps like Grad-CAM with the output captured by a
+    /// [forward hook](Layer::register_forward_hook). It is not called if the output does not
+    /// require a gradient.
+    ///
+    /// Hooks are supported by [`Conv`], [`ConvTranspose2`], [`Dense`], and [`MaxPool`]. Other
+    /// layers can be wrapped in [`Hooked`].
+    ///
+    /// # Errors
+    /// - `name` was not found.
+    /// - The layer does not support hooks.
+    fn register_backward_hook(&mut self, name: &str, hook: BackwardHook) -> Result<()> {
+        let _ = hook;
+        if name.is_empty() {
+            bail!("{} does not support hooks!", type_name::<Self>());
+        }
+        bail!("Layer {name:?} not found in {}!", type_name::<Self>());
+    }
+    /// Executes the forward pass on tensors.
+    ///
+    /// Convenience method for inference, implemented via [`.forward()`](Forward::forward). The
+    /// `input` and parameters are converted to [`Variable`]s without a
+    /// [`Node`](super::autograd::Node), so no graph is created and gradients are not computed.
+    ///
+    /// `input` can be a [`ScalarTensorView`] or a [`ScalarTensor`], which is not copied.
+    ///
+    /// # Thread Safety
+    /// The forward pass does not modify the parameters, so a layer that is [`Sync`] can execute
+    /// forward passes concurrently from multiple threads. Internal state, like the running
+    /// statistics of [`BatchNorm2`] in training mode, is updated behind a lock.
+    fn forward_values<'a, D1, D2>(
+        &self,
+        input: impl Into<ScalarCowTensor<'a, D1>>,
+    ) -> Result<ScalarTensor<D2>>
+    where
+        Self: Forward<Variable<D1>, Output = Variable<D2>>,
+        D1: Dimension,
+        D2: Dimension,
+    {
+        let input = Variable::from(input.into().into_shared()?);
+        no_grad(|| self.forward(input))?.into_value().into_owned()
+    }
 }
 
 /// Forward.
@@ -719,12 +1775,26 @@ struct OptionField {
 struct VecField {
     layers: Vec<Dense>,
 }
+
+#[derive(Layer, Forward)]
+#[autograph(forward(Variable2, Output=Variable2))]
+struct GenericField<F: Layer + Forward<Variable2, Output=Variable2>> {
+    dense: Dense,
+    f: F,
+}
 ```
 */
 #[cfg(doc)]
 #[allow(dead_code)]
 enum DeriveTests {}
 
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+struct NamedParameter {
+    name: String,
+    value: ScalarArcTensorD,
+}
+
 impl<T: Layer> Layer for Option<T> {
     fn try_for_each_parameter<F, E>(&self, f: F) -> Result<(), E>
     where
@@ -736,6 +1806,16 @@ impl<T: Layer> Layer for Option<T> {
             Ok(())
         }
     }
+    fn try_for_each_named_parameter<F, E>(&self, f: F) -> Result<(), E>
+    where
+        F: FnMut(&str, ParameterD) -> Result<(), E>,
+    {
+        if let Some(layer) = self.as_ref() {
+            layer.try_for_each_named_parameter(f)
+        } else {
+            Ok(())
+        }
+    }
     fn try_for_each_parameter_view_mut<F, E>(&mut self, f: F) -> Result<()>
     where
         F: FnMut(ParameterViewMutD) -> Result<(), E>,
@@ -759,6 +1839,25 @@ impl<T: Layer> Layer for Option<T> {
         }
         Ok(())
     }
+    fn set_training(&mut self, training: bool) {
+        if let Some(layer) = self.as_mut() {
+            layer.set_training(training);
+        }
+    }
+    fn register_forward_hook(&mut self, name: &str, hook: ForwardHook) -> Result<()> {
+        if let Some(layer) = self.as_mut() {
+            layer.register_forward_hook(name, hook)
+        } else {
+            bail!("Layer {name:?} not found in None!");
+        }
+    }
+    fn register_backward_hook(&mut self, name: &str, hook: BackwardHook) -> Result<()> {
+        if let Some(layer) = self.as_mut() {
+            layer.register_backward_hook(name, hook)
+        } else {
+            bail!("Layer {name:?} not found in None!");
+        }
+    }
 }
 
 impl<X, T: Forward<X, Output = X>> Forward<X> for Option<T> {
@@ -780,6 +1879,20 @@ impl<T: Layer> Layer for Vec<T> {
         self.iter()
             .try_for_each(|layer| layer.try_for_each_parameter(&mut f))
     }
+    fn try_for_each_named_parameter<F, E>(&self, mut f: F) -> Result<(), E>
+    where
+        F: FnMut(&str, ParameterD) -> Result<(), E>,
+    {
+        self.iter().enumerate().try_for_each(|(index, layer)| {
+            layer.try_for_each_named_parameter(|name: &str, parameter| {
+                if name.is_empty() {
+                    f(&index.to_string(), parameter)
+                } else {
+                    f(&format!("{index}.{name}"), parameter)
+                }
+            })
+        })
+    }
     fn try_for_each_parameter_view_mut<F, E>(&mut self, mut f: F) -> Result<()>
     where
         F: FnMut(ParameterViewMutD) -> Result<(), E>,
@@ -796,6 +1909,105 @@ impl<T: Layer> Layer for Vec<T> {
         self.iter_mut()
             .try_for_each(|layer| layer.to_device_mut(device.clone()))
     }
+    fn set_training(&mut self, training: bool) {
+        for layer in self.iter_mut() {
+            layer.set_training(training);
+        }
+    }
+    fn register_forward_hook(&mut self, name: &str, hook: ForwardHook) -> Result<()> {
+        let (index, tail) = name.split_once('.').unwrap_or((name, ""));
+        if let Some(layer) = index
+            .parse()
+            .ok()
+            .and_then(|index: usize| self.get_mut(index))
+        {
+            layer.register_forward_hook(tail, hook)
+        } else {
+            bail!("Layer {name:?} not found in Vec!");
+        }
+    }
+    fn register_backward_hook(&mut self, name: &str, hook: BackwardHook) -> Result<()> {
+        let (index, tail) = name.split_once('.').unwrap_or((name, ""));
+        if let Some(layer) = index
+            .parse()
+            .ok()
+            .and_then(|index: usize| self.get_mut(index))
+        {
+            layer.register_backward_hook(tail, hook)
+        } else {
+            bail!("Layer {name:?} not found in Vec!");
+        }
+    }
+}
+
+/// Forward hook.
+///
+/// See [`Layer::register_forward_hook()`].
+pub type ForwardHook = Arc<dyn Fn(&VariableD) + Send + Sync>;
+
+/// Backward hook.
+///
+/// See [`Layer::register_backward_hook()`].
+pub type BackwardHook = Arc<dyn Fn(&ScalarArcTensorD) + Send + Sync>;
+
+#[derive(Default, Clone)]
+struct Hooks {
+    forward: Vec<ForwardHook>,
+    backward: Vec<BackwardHook>,
+}
+
+impl Hooks {
+    fn register_forward_hook(&mut self, layer: &str, name: &str, hook: ForwardHook) -> Result<()> {
+        if !name.is_empty() {
+            bail!("Layer {name:?} not found in {layer}!");
+        }
+        self.forward.push(hook);
+        Ok(())
+    }
+    fn register_backward_hook(
+        &mut self,
+        layer: &str,
+        name: &str,
+        hook: BackwardHook,
+    ) -> Result<()> {
+        if !name.is_empty() {
+            bail!("Layer {name:?} not found in {layer}!");
+        }
+        self.backward.push(hook);
+        Ok(())
+    }
+    fn output<D: Dimension>(&self, output: Variable<D>) -> Variable<D> {
+        if !self.forward.is_empty() {
+            let output = output.clone().into_dyn();
+            for hook in self.forward.iter() {
+                hook(&output);
+            }
+        }
+        if self.backward.is_empty() {
+            return output;
+        }
+        let mut builder = Variable::builder();
+        if let Some(node) = output.node() {
+            let hooks = self.backward.clone();
+            builder.edge(node, move |output_grad| {
+                let grad = output_grad.clone().into_dyn();
+                for hook in hooks.iter() {
+                    hook(&grad);
+                }
+                Ok(output_grad)
+            });
+        }
+        builder.build(output.into_value())
+    }
+}
+
+impl Debug for Hooks {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("Hooks")
+            .field("forward", &self.forward.len())
+            .field("backward", &self.backward.len())
+            .finish()
+    }
 }
 
 impl<X, T: Forward<X, Output = X>> Forward<X> for Vec<T> {
@@ -808,6 +2020,298 @@ impl<X, T: Forward<X, Output = X>> Forward<X> for Vec<T> {
     }
 }
 
+/// Parameter initialization.
+///
+/// See [`DenseBuilder::init_weight()`] and [`ConvBuilder::init_weight()`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Init {
+    /// Uniform distribution in the range [`low`, `high`).
+    Uniform {
+        /// The lower bound.
+        low: f32,
+        /// The upper bound.
+        high: f32,
+    },
+    /// Normal distribution.
+    Normal {
+        /// The mean.
+        mean: f32,
+        /// The standard deviation.
+        std: f32,
+    },
+    /// All zeros.
+    Zeros,
+    /// All `value`.
+    Constant {
+        /// The value.
+        value: f32,
+    },
+}
+
+impl Init {
+    /// Samples `len` values with `rng`.
+    ///
+    /// Values are generated on the host and transferred to the `device`. [`Init::Zeros`] and
+    /// [`Init::Constant`] are filled on the `device`.
+    ///
+    /// # Errors
+    /// - [`Init::Uniform`] with `low` not less than `high`.
+    /// - `scalar_type` is not bf16 or f32.
+    fn sample_buffer<R: Rng>(
+        self,
+        rng: &mut R,
+        len: usize,
+        scalar_type: ScalarType,
+        device: Device,
+    ) -> Result<ScalarBuffer> {
+        if !matches!(scalar_type, ScalarType::BF16 | ScalarType::F32) {
+            bail!("Init {scalar_type:?} unimplemented!");
+        }
+        let values: Vec<f32> = match self {
+            Self::Uniform { low, high } => {
+                if low >= high || low.is_nan() || high.is_nan() {
+                    bail!(
+                        "Init::Uniform expected low < high, found low = {low} and high = {high}!"
+                    );
+                }
+                Uniform::new(low, high).sample_iter(rng).take(len).collect()
+            }
+            Self::Normal { mean, std } => (0..len)
+                .map(|_| {
+                    // Box-Muller transform
+                    let u1 = 1. - rng.gen::<f32>();
+                    let u2 = rng.gen::<f32>();
+                    let z = (-2. * u1.ln()).sqrt() * (2. * std::f32::consts::PI * u2).cos();
+                    mean + std * z
+                })
+                .collect(),
+            Self::Zeros => return ScalarBuffer::zeros(device, len, scalar_type),
+            Self::Constant { value } => {
+                let elem = ScalarElem::F32(value).scalar_cast(scalar_type);
+                return ScalarBuffer::from_elem(device, len, elem);
+            }
+        };
+        let buffer = if scalar_type == ScalarType::BF16 {
+            ScalarBuffer::from(Buffer::from(
+                values.into_iter().map(bf16::from_f32).collect::<Vec<_>>(),
+            ))
+        } else {
+            ScalarBuffer::from(Buffer::from(values))
+        };
+        buffer.into_device(device)
+    }
+}
+
+/// Padding mode.
+///
+/// See [`ConvBuilder::padding_mode()`], [`MaxPoolBuilder::padding_mode()`], and
+/// [`AvgPoolBuilder::padding_mode()`].
+#[derive(Default, Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum PaddingMode {
+    /// Only the explicit padding is applied, which defaults to 0.
+    #[default]
+    Valid,
+    /// Pads the input such that the output size is `ceil(input / stride)`.
+    ///
+    /// When the total padding is odd, the extra padding is added to the end.
+    Same,
+}
+
+/// Kernel kind.
+///
+/// See [`ConvBuilder::kernel_kind()`].
+#[derive(Default, Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum KernelKind {
+    /// The filter is applied as is, like most deep learning frameworks.
+    #[default]
+    CrossCorrelation,
+    /// The filter is flipped along the spatial dimensions, ie true convolution.
+    Convolution,
+}
+
+/// Computes the padding for [`PaddingMode::Same`].
+///
+/// Returns \[pad_before, pad_after\] for each spatial dimension, such that the output size is
+/// `ceil(input / stride)`. When the total padding is odd, the extra padding is added to the end,
+/// like TensorFlow.
+///
+/// Useful for padding the input explicitly, see [`Pad2`].
+///
+/// # Example
+///```
+/// # use autograph::learn::neural_network::layer::conv_same_padding;
+/// let padding = conv_same_padding([6, 5], [3, 3], [2, 2], [1, 1]);
+/// assert_eq!(padding, [[0, 1], [1, 1]]);
+///```
+pub fn conv_same_padding<const N: usize>(
+    input: [usize; N],
+    filter: [usize; N],
+    stride: [usize; N],
+    dilation: [usize; N],
+) -> [[usize; 2]; N] {
+    std::array::from_fn(|i| same_padding(input[i], filter[i], stride[i], dilation[i]))
+}
+
+/// Computes the padding before and after for [`PaddingMode::Same`].
+fn same_padding(input: usize, filter: usize, stride: usize, dilation: usize) -> [usize; 2] {
+    let output = (input + stride - 1) / stride;
+    let padding =
+        ((output.max(1) - 1) * stride + dilation * (filter.max(1) - 1) + 1).saturating_sub(input);
+    [padding / 2, padding - padding / 2]
+}
+
+/// Computes the input range, the output range, and the output size for padding `input` with
+/// `[p1, p2]`, where negative padding crops.
+fn pad_range(input: usize, [p1, p2]: [isize; 2]) -> Result<(Range<usize>, Range<usize>, usize)> {
+    let crop1 = p1.min(0).unsigned_abs();
+    let crop2 = p2.min(0).unsigned_abs();
+    if crop1 + crop2 > input {
+        bail!(
+            "pad2 padding {:?} crops more than the input {input}!",
+            [p1, p2]
+        );
+    }
+    let len = input - crop1 - crop2;
+    let start = p1.max(0) as usize;
+    let output = start + len + p2.max(0) as usize;
+    Ok((crop1..crop1 + len, start..start + len, output))
+}
+
+/// Pads the spatial dimensions of `input` with `value`.
+///
+/// Negative padding crops the input.
+///
+/// The output is filled with `value` and the input is assigned into a slice of it, on the device
+/// of the input.
+fn pad2(input: Variable4, padding: [[isize; 2]; 2], value: f32) -> Result<Variable4> {
+    if padding.iter().flatten().all(|p| *p == 0) {
+        return Ok(input);
+    }
+    let (_, _, ih, iw) = input.dim();
+    let [ph, pw] = padding;
+    let (ih_range, oh_range, oh) = pad_range(ih, ph)?;
+    let (iw_range, ow_range, ow) = pad_range(iw, pw)?;
+    let mut builder = Variable::builder();
+    if let Some(node) = input.node() {
+        let (ih_range, oh_range) = (ih_range.clone(), oh_range.clone());
+        let (iw_range, ow_range) = (iw_range.clone(), ow_range.clone());
+        builder.edge(node, move |output_grad| {
+            scalar_pad2(
+                output_grad.view(),
+                [ih, iw],
+                [oh_range, ow_range],
+                [ih_range, iw_range],
+                0.,
+            )?
+            .into_shared()
+        });
+    }
+    let output = scalar_pad2(
+        input.value().view(),
+        [oh, ow],
+        [ih_range, iw_range],
+        [oh_range, ow_range],
+        value,
+    )?;
+    Ok(builder.build(output.into_shared()?))
+}
+
+fn scalar_pad2(
+    x: ScalarTensorView4,
+    output_dim: [usize; 2],
+    x_ranges: [Range<usize>; 2],
+    y_ranges: [Range<usize>; 2],
+    value: f32,
+) -> Result<ScalarTensor4> {
+    match x.scalar_type() {
+        ScalarType::BF16 => pad2_tensor::<bf16>(
+            x.try_into_tensor_view().unwrap(),
+            output_dim,
+            x_ranges,
+            y_ranges,
+            bf16::from_f32(value),
+        )
+        .map(Into::into),
+        ScalarType::F32 => pad2_tensor::<f32>(
+            x.try_into_tensor_view().unwrap(),
+            output_dim,
+            x_ranges,
+            y_ranges,
+            value,
+        )
+        .map(Into::into),
+        scalar_type => bail!("pad2 {scalar_type:?} unimplemented!"),
+    }
+}
+
+/// Copies `x[.., .., xh, xw]` into `y[.., .., yh, yw]`, where `y` is filled with `value`.
+fn pad2_tensor<T: Scalar>(
+    mut x: TensorView4<T>,
+    [oh, ow]: [usize; 2],
+    [xh, xw]: [Range<usize>; 2],
+    [yh, yw]: [Range<usize>; 2],
+    value: T,
+) -> Result<Tensor4<T>> {
+    let (n, c, _, _) = x.dim();
+    let mut y = unsafe { Tensor::uninit(x.device(), [n, c, oh, ow])? };
+    y.fill(value)?;
+    x.slice_axis_inplace(Axis(2), xh);
+    x.slice_axis_inplace(Axis(3), xw);
+    let mut y_slice = y.view_mut();
+    y_slice.slice_axis_inplace(Axis(2), yh);
+    y_slice.slice_axis_inplace(Axis(3), yw);
+    y_slice.assign(&x)?;
+    Ok(y)
+}
+
+/// Pad2.
+///
+/// Pads the spatial dimensions of the input with \[\[top, bottom\], \[left, right\]\]
+/// pixels. Negative padding crops that many pixels instead.
+///
+/// Implemented for bf16 and f32.
+///
+/// # Example
+///```no_run
+/// # use autograph::learn::neural_network::layer::Pad2;
+/// // crop 1 pixel from the top and pad 2 pixels on the right
+/// let pad = Pad2::new([[-1, 0], [0, 2]]);
+///```
+#[derive(Default, Clone, Copy, Debug, Layer)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[autograph(crate=crate)]
+pub struct Pad2 {
+    #[autograph(skip)]
+    padding: [[isize; 2]; 2],
+    #[autograph(skip)]
+    value: f32,
+}
+
+impl Pad2 {
+    /// Creates a new [`Pad2`] with `padding`.
+    pub fn new(padding: [[isize; 2]; 2]) -> Self {
+        Self { padding, value: 0. }
+    }
+    /// Sets the value to pad with. Defaults to 0.
+    pub fn value(self, value: f32) -> Self {
+        Self { value, ..self }
+    }
+    /// The padding.
+    pub fn padding(&self) -> [[isize; 2]; 2] {
+        self.padding
+    }
+}
+
+impl Forward<Variable4> for Pad2 {
+    type Output = Variable4;
+    fn forward(&self, input: Variable4) -> Result<Variable4> {
+        pad2(input, self.padding, self.value)
+    }
+}
+
 /// Convolutional layer.
 ///
 /// See [`Conv1`] and [`Conv2`].
@@ -830,7 +2334,7 @@ impl<X, T: Forward<X, Output = X>> Forward<X> for Vec<T> {
 /// # Ok(())
 /// # }
 ///```
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[cfg_attr(
     feature = "serde",
     derive(Serialize, Deserialize),
@@ -842,9 +2346,15 @@ impl<X, T: Forward<X, Output = X>> Forward<X> for Vec<T> {
 pub struct Conv<D: Dimension> {
     weight: Parameter<<D::Larger as Dimension>::Larger>,
     padding: D,
+    #[cfg_attr(feature = "serde", serde(default))]
+    padding_mode: PaddingMode,
     stride: D,
     dilation: D,
+    #[cfg_attr(feature = "serde", serde(default))]
+    kernel_kind: KernelKind,
     bias: Option<Parameter1>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    hooks: Hooks,
 }
 
 /// Convolutional layer with 1 dimension.
@@ -864,6 +2374,14 @@ impl<D: Dimension> Conv<D> {
 }
 
 impl<D: Dimension> Conv<D> {
+    /// The number of input channels.
+    pub fn in_channels(&self) -> usize {
+        self.weight.shape()[1]
+    }
+    /// The number of output channels.
+    pub fn out_channels(&self) -> usize {
+        self.weight.shape()[0]
+    }
     /// The weight as a mutable parameter view.
     pub fn weight_view_mut(
         &mut self,
@@ -874,6 +2392,47 @@ impl<D: Dimension> Conv<D> {
     pub fn bias_view_mut(&mut self) -> Result<Option<ParameterViewMut1>> {
         self.bias.as_mut().map(Parameter::make_view_mut).transpose()
     }
+    /// The kernel kind.
+    pub fn kernel_kind(&self) -> KernelKind {
+        self.kernel_kind
+    }
+}
+
+impl Conv2 {
+    /// Quantizes the layer to int8 for inference.
+    ///
+    /// See [`QuantizedConv2`].
+    ///
+    /// # Errors
+    /// See [`ScalarTensorBase::to_device()`].
+    pub fn quantize(&self) -> Result<QuantizedConv2> {
+        let weight = host_f32_array(self.weight.value())?;
+        let weight = if self.kernel_kind == KernelKind::Convolution {
+            flip2_array(weight.view()).into_array()?
+        } else {
+            weight
+        };
+        let (outputs, inputs, fh, fw) = weight.dim();
+        let weight = weight.into_shape([outputs, inputs * fh * fw]).unwrap();
+        let (weight, scales) = quantize_rows(weight.view());
+        let (ph, pw) = self.padding.into_pattern();
+        let (sh, sw) = self.stride.into_pattern();
+        let (dh, dw) = self.dilation.into_pattern();
+        let device = self.weight.value().device();
+        let (weight, scales, bias) =
+            quantized_into_device(weight, scales, self.bias.as_ref(), device)?;
+        Ok(QuantizedConv2 {
+            weight,
+            scales,
+            bias,
+            inputs,
+            filter: [fh, fw],
+            padding: [ph, pw],
+            padding_mode: self.padding_mode,
+            stride: [sh, sw],
+            dilation: [dh, dw],
+        })
+    }
 }
 
 impl<D: Dimension> Layer for Conv<D> {
@@ -887,6 +2446,16 @@ impl<D: Dimension> Layer for Conv<D> {
         }
         Ok(())
     }
+    fn try_for_each_named_parameter<F, E>(&self, mut f: F) -> Result<(), E>
+    where
+        F: FnMut(&str, ParameterD) -> Result<(), E>,
+    {
+        f("weight", self.weight.clone().into_dyn())?;
+        if let Some(bias) = self.bias.clone() {
+            f("bias", bias.into_dyn())?;
+        }
+        Ok(())
+    }
     fn try_for_each_parameter_view_mut<F, E>(&mut self, mut f: F) -> Result<()>
     where
         F: FnMut(ParameterViewMutD) -> Result<(), E>,
@@ -925,6 +2494,12 @@ impl<D: Dimension> Layer for Conv<D> {
             ..self
         })
     }
+    fn register_forward_hook(&mut self, name: &str, hook: ForwardHook) -> Result<()> {
+        self.hooks.register_forward_hook("Conv", name, hook)
+    }
+    fn register_backward_hook(&mut self, name: &str, hook: BackwardHook) -> Result<()> {
+        self.hooks.register_backward_hook("Conv", name, hook)
+    }
 }
 
 fn conv2(
@@ -941,12 +2516,47 @@ fn conv2(
     } else {
         conv2_im2col(input, weight, options)?
     };
-    if let Some(bias) = bias.as_ref() {
-        output.add_assign(bias)?;
+    if let Some(bias) = bias {
+        // broadcast over the spatial dimensions
+        let outputs = bias.dim();
+        output.add_assign(bias.into_shape([outputs, 1, 1]).map_err(Error::msg)?)?;
     }
     Ok(output)
 }
 
+/// Flips the spatial dimensions of the filter for [`KernelKind::Convolution`].
+fn flip2(weight: Variable4) -> Result<Variable4> {
+    let mut builder = Variable::builder();
+    if let Some(node) = weight.node() {
+        builder.edge(node, flip2_tensor);
+    }
+    let output = flip2_tensor(weight.into_value())?;
+    Ok(builder.build(output))
+}
+
+fn flip2_tensor(x: ScalarArcTensor4) -> Result<ScalarArcTensor4> {
+    let y: ScalarTensor4 = match x.scalar_type() {
+        ScalarType::BF16 => flip2_view::<bf16>(x.view().try_into_tensor_view().unwrap())?.into(),
+        ScalarType::F32 => flip2_view::<f32>(x.view().try_into_tensor_view().unwrap())?.into(),
+        scalar_type => bail!("flip2 {scalar_type:?} unimplemented!"),
+    };
+    y.into_shared()
+}
+
+// Copies the view with the spatial axes inverted, on the device of `x`.
+fn flip2_view<T: Scalar>(mut x: TensorView4<T>) -> Result<Tensor4<T>> {
+    x.invert_axis(Axis(2));
+    x.invert_axis(Axis(3));
+    x.into_standard_layout()
+}
+
+fn flip2_array<T: Scalar>(x: ArrayView4<T>) -> Tensor4<T> {
+    x.slice(s![.., .., ..;-1, ..;-1])
+        .as_standard_layout()
+        .into_owned()
+        .into()
+}
+
 fn conv2_im2col(
     input: Variable4,
     weight: Variable4,
@@ -965,9 +2575,26 @@ fn conv2_im2col(
         dilation: [dh, dw],
     };
     let [oh, ow] = options.output_shape([ih, iw]);
-    let im2col_matrix = input.value().im2col_conv2(&options)?;
-    let weight_matrix = weight
-        .value()
+    let col2im_options = Col2ImConv2Options {
+        shape: [oh, ow],
+        filter: [fh, fw],
+        padding: [ph, pw],
+        stride: [sh, sw],
+        dilation: [dh, dw],
+    };
+    // Crop the rows and columns after the last window, so that col2im produces the input shape.
+    let [ih2, iw2] = col2im_options.output_shape();
+    let input = pad2(
+        input,
+        [
+            [0, ih2 as isize - ih as isize],
+            [0, iw2 as isize - iw as isize],
+        ],
+        0.,
+    )?;
+    let im2col_matrix = input.value().im2col_conv2(&options)?;
+    let weight_matrix = weight
+        .value()
         .clone()
         .into_shape([outputs, inputs * fh * fw])
         .unwrap();
@@ -975,14 +2602,9 @@ fn conv2_im2col(
     let mut builder = Variable::builder();
     if let Some(node) = input.node() {
         builder.edge(node, move |output_grad| {
-            let options = Col2ImConv2Options {
-                shape: [oh, ow],
-                filter: [fh, fw],
-                ..Col2ImConv2Options::default()
-            };
             output_grad
                 .dot(&weight_matrix)?
-                .col2im_conv2(&options)
+                .col2im_conv2(&col2im_options)
                 .map(Into::into)
         });
     }
@@ -1015,11 +2637,29 @@ impl Forward<Variable3> for Conv1 {
             .to_variable()
             .into_shape([outputs, inputs, fh, 1])
             .map_err(Error::msg)?;
-        let ph = self.padding.into_pattern();
+        let weight = if self.kernel_kind == KernelKind::Convolution {
+            flip2(weight)?
+        } else {
+            weight
+        };
+        let (input, padding) = if self.padding_mode == PaddingMode::Same {
+            let [ph1, ph2] = same_padding(ih, fh, self.stride[0], self.dilation[0]);
+            if ph1 == ph2 {
+                (input, [ph1].into_dimension())
+            } else {
+                (
+                    pad2(input, [[ph1 as isize, ph2 as isize], [0, 0]], 0.)?,
+                    Ix1(0),
+                )
+            }
+        } else {
+            (input, self.padding)
+        };
+        let ph = padding.into_pattern();
         let sh = self.stride.into_pattern();
         let dh = self.dilation.into_pattern();
         let options = ConvOptions {
-            padding: [ph, 1].into_dimension(),
+            padding: [ph, 0].into_dimension(),
             stride: [sh, 1].into_dimension(),
             dilation: [dh, 1].into_dimension(),
         };
@@ -1028,7 +2668,8 @@ impl Forward<Variable3> for Conv1 {
         let (n2, oc, oh, ow) = output.dim();
         debug_assert_eq!(n, n2);
         debug_assert_eq!(ow, 1);
-        output.into_shape([n, oc, oh]).map_err(Error::msg)
+        let output = output.into_shape([n, oc, oh]).map_err(Error::msg)?;
+        Ok(self.hooks.output(output))
     }
 }
 
@@ -1036,13 +2677,345 @@ impl Forward<Variable4> for Conv2 {
     type Output = Variable4;
     fn forward(&self, input: Variable4) -> Result<Variable4> {
         let weight = self.weight.to_variable();
+        let weight = if self.kernel_kind == KernelKind::Convolution {
+            flip2(weight)?
+        } else {
+            weight
+        };
+        let (input, padding) = if self.padding_mode == PaddingMode::Same {
+            let (_, _, ih, iw) = input.dim();
+            let (_, _, fh, fw) = weight.dim();
+            let [ph1, ph2] = same_padding(ih, fh, self.stride[0], self.dilation[0]);
+            let [pw1, pw2] = same_padding(iw, fw, self.stride[1], self.dilation[1]);
+            if ph1 == ph2 && pw1 == pw2 {
+                (input, [ph1, pw1].into_dimension())
+            } else {
+                let padding = [[ph1, ph2], [pw1, pw2]].map(|p| p.map(|p| p as isize));
+                (pad2(input, padding, 0.)?, Ix2(0, 0))
+            }
+        } else {
+            (input, self.padding)
+        };
         let options = ConvOptions {
-            padding: self.padding,
+            padding,
             stride: self.stride,
             dilation: self.dilation,
         };
         let bias = self.bias.as_ref().map(Parameter::to_variable);
-        conv2(input, weight, &options, bias)
+        let output = conv2(input, weight, &options, bias)?;
+        Ok(self.hooks.output(output))
+    }
+}
+
+/// Transposed convolutional layer with 2 dimensions.
+///
+/// The forward pass is the gradient of a [`Conv2`] with respect to its input, and the backward
+/// pass with respect to the input is a [`Conv2`]. Useful for upsampling, e.g. in decoders and
+/// generators.
+///
+/// The weight has shape [inputs, outputs, fh, fw]. The output shape is
+/// `(input - 1) * stride - 2 * padding + filter + output_padding`.
+///
+/// Implemented for bf16 and f32.
+///
+/// # Example
+///```no_run
+/// # use autograph::{krnl::{scalar::ScalarType, device::Device}, learn::neural_network::layer::ConvTranspose2};
+/// # fn main() -> anyhow::Result<()> {
+/// # let device = Device::host();
+/// let conv_transpose = ConvTranspose2::builder()
+///    .inputs(8)
+///    .outputs(1)
+///    .filter([4, 4])
+///    .stride([2, 2])
+///    .padding([1, 1])
+///    .bias(true)
+///    .device(device.clone())
+///    .build()?;
+/// # Ok(())
+/// # }
+///```
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ConvTranspose2 {
+    weight: Parameter4,
+    stride: Ix2,
+    padding: Ix2,
+    output_padding: Ix2,
+    bias: Option<Parameter1>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    hooks: Hooks,
+}
+
+impl ConvTranspose2 {
+    /// Returns a builder for creating a [`ConvTranspose2`].
+    pub fn builder() -> ConvTranspose2Builder {
+        ConvTranspose2Builder::new()
+    }
+    /// The number of input channels.
+    pub fn in_channels(&self) -> usize {
+        self.weight.shape()[0]
+    }
+    /// The number of output channels.
+    pub fn out_channels(&self) -> usize {
+        self.weight.shape()[1]
+    }
+    /// The weight as a mutable parameter view.
+    pub fn weight_view_mut(&mut self) -> Result<ParameterViewMut4> {
+        self.weight.make_view_mut()
+    }
+    /// The bias as a mutable parameter_view.
+    pub fn bias_view_mut(&mut self) -> Result<Option<ParameterViewMut1>> {
+        self.bias.as_mut().map(Parameter::make_view_mut).transpose()
+    }
+}
+
+impl Layer for ConvTranspose2 {
+    fn try_for_each_parameter<F, E>(&self, mut f: F) -> Result<(), E>
+    where
+        F: FnMut(ParameterD) -> Result<(), E>,
+    {
+        f(self.weight.clone().into_dyn())?;
+        if let Some(bias) = self.bias.clone() {
+            f(bias.into_dyn())?;
+        }
+        Ok(())
+    }
+    fn try_for_each_named_parameter<F, E>(&self, mut f: F) -> Result<(), E>
+    where
+        F: FnMut(&str, ParameterD) -> Result<(), E>,
+    {
+        f("weight", self.weight.clone().into_dyn())?;
+        if let Some(bias) = self.bias.clone() {
+            f("bias", bias.into_dyn())?;
+        }
+        Ok(())
+    }
+    fn try_for_each_parameter_view_mut<F, E>(&mut self, mut f: F) -> Result<()>
+    where
+        F: FnMut(ParameterViewMutD) -> Result<(), E>,
+        anyhow::Error: From<E>,
+    {
+        f(self.weight.make_view_mut()?.into_dyn())?;
+        if let Some(bias) = self.bias.as_mut() {
+            f(bias.make_view_mut()?.into_dyn())?;
+        }
+        Ok(())
+    }
+    fn cast_mut(&mut self, scalar_type: ScalarType) -> Result<()> {
+        if !matches!(scalar_type, ScalarType::BF16 | ScalarType::F32) {
+            bail!("ConvTranspose2 {scalar_type:?} not implemented!");
+        }
+        self.weight.cast_mut(scalar_type)?;
+        if let Some(bias) = self.bias.as_mut() {
+            bias.cast_mut(scalar_type)?;
+        }
+        Ok(())
+    }
+    fn to_device_mut(&mut self, device: Device) -> Result<()> {
+        self.weight.to_device_mut(device.clone())?;
+        if let Some(bias) = self.bias.as_mut() {
+            bias.to_device_mut(device)?;
+        }
+        Ok(())
+    }
+    fn into_device(self, device: Device) -> Result<Self>
+    where
+        Self: Sized,
+    {
+        Ok(Self {
+            weight: self.weight.into_device(device.clone())?,
+            bias: self.bias.map(|b| b.into_device(device)).transpose()?,
+            ..self
+        })
+    }
+    fn register_forward_hook(&mut self, name: &str, hook: ForwardHook) -> Result<()> {
+        self.hooks
+            .register_forward_hook("ConvTranspose2", name, hook)
+    }
+    fn register_backward_hook(&mut self, name: &str, hook: BackwardHook) -> Result<()> {
+        self.hooks
+            .register_backward_hook("ConvTranspose2", name, hook)
+    }
+}
+
+impl Forward<Variable4> for ConvTranspose2 {
+    type Output = Variable4;
+    fn forward(&self, input: Variable4) -> Result<Variable4> {
+        let (batch_size, inputs, ih, iw) = input.dim();
+        let (inputs2, outputs, fh, fw) = self.weight.dim();
+        if inputs != inputs2 {
+            bail!("ConvTranspose2 expected {inputs2} input channels, found {inputs}!");
+        }
+        let (sh, sw) = self.stride.into_pattern();
+        let (ph, pw) = self.padding.into_pattern();
+        let (oph, opw) = self.output_padding.into_pattern();
+        let input_matrix = input
+            .permuted_axes([0, 2, 3, 1])
+            .to_standard_layout()?
+            .into_shape([batch_size * ih * iw, inputs])
+            .map_err(Error::msg)?;
+        let weight_matrix = self
+            .weight
+            .to_variable()
+            .into_shape([inputs, outputs * fh * fw])
+            .map_err(Error::msg)?;
+        let cols = input_matrix.dot(&weight_matrix)?;
+        let output = col2im_conv2(
+            cols,
+            Col2ImConv2Options {
+                shape: [ih, iw],
+                filter: [fh, fw],
+                padding: [0, 0],
+                stride: [sh, sw],
+                dilation: [1, 1],
+            },
+        )?;
+        // Remove the padding from both sides and add the output padding to the end.
+        let mut output = pad2(
+            output,
+            [
+                [-(ph as isize), oph as isize - ph as isize],
+                [-(pw as isize), opw as isize - pw as isize],
+            ],
+            0.,
+        )?;
+        if let Some(bias) = self.bias.as_ref().map(Parameter::to_variable) {
+            output.add_assign(bias.into_shape([outputs, 1, 1]).map_err(Error::msg)?)?;
+        }
+        Ok(self.hooks.output(output))
+    }
+}
+
+/// Sums the columns into an image, the backward is [`Im2ColConv2`].
+fn col2im_conv2(input: Variable2, options: Col2ImConv2Options) -> Result<Variable4> {
+    let Col2ImConv2Options {
+        filter,
+        padding,
+        stride,
+        dilation,
+        ..
+    } = options;
+    let im2col_options = Im2ColConv2Options {
+        filter,
+        padding,
+        stride,
+        dilation,
+    };
+    let mut builder = Variable::builder();
+    if let Some(node) = input.node() {
+        builder.edge(node, move |output_grad| {
+            output_grad.im2col_conv2(&im2col_options).map(Into::into)
+        });
+    }
+    let output = input.value().col2im_conv2(&options)?;
+    Ok(builder.build(output.into()))
+}
+
+/// Int8 quantized [`Conv2`] for inference.
+///
+/// Created with [`Conv2::quantize()`]. The weight is quantized with a scale for each output
+/// channel, and the input patches are quantized with a scale for each patch. The product is
+/// accumulated in i32 and then dequantized.
+///
+/// The output has the scalar type of the input. Does not have parameters, so it can not be
+/// trained. On devices, requires [`Features::INT8`](krnl::device::Features::INT8) and
+/// [`Features::BUFFER8`](krnl::device::Features::BUFFER8).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct QuantizedConv2 {
+    weight: ArcTensor2<i8>,
+    scales: ArcTensor1<f32>,
+    bias: Option<ArcTensor1<f32>>,
+    inputs: usize,
+    filter: [usize; 2],
+    padding: [usize; 2],
+    padding_mode: PaddingMode,
+    stride: [usize; 2],
+    dilation: [usize; 2],
+}
+
+impl QuantizedConv2 {
+    /// The number of input channels.
+    pub fn in_channels(&self) -> usize {
+        self.inputs
+    }
+    /// The number of output channels.
+    pub fn out_channels(&self) -> usize {
+        self.weight.shape()[0]
+    }
+}
+
+impl Forward<Variable4> for QuantizedConv2 {
+    type Output = Variable4;
+    fn forward(&self, input: Variable4) -> Result<Variable4> {
+        let (batch_size, inputs, ih, iw) = input.dim();
+        if inputs != self.inputs {
+            bail!(
+                "QuantizedConv2 expected {} input channels, found {inputs}!",
+                self.inputs
+            );
+        }
+        let [fh, fw] = self.filter;
+        let [sh, sw] = self.stride;
+        let [dh, dw] = self.dilation;
+        let scalar_type = input.scalar_type();
+        let (input, padding) = if self.padding_mode == PaddingMode::Same {
+            let [ph1, ph2] = same_padding(ih, fh, sh, dh);
+            let [pw1, pw2] = same_padding(iw, fw, sw, dw);
+            if ph1 == ph2 && pw1 == pw2 {
+                (input, [ph1, pw1])
+            } else {
+                let padding = [[ph1 as isize, ph2 as isize], [pw1 as isize, pw2 as isize]];
+                (pad2(input, padding, 0.)?, [0, 0])
+            }
+        } else {
+            (input, self.padding)
+        };
+        let options = Im2ColConv2Options {
+            filter: self.filter,
+            padding,
+            stride: self.stride,
+            dilation: self.dilation,
+        };
+        let (_, _, ih, iw) = input.dim();
+        let [oh, ow] = options.output_shape([ih, iw]);
+        let x = input.value().im2col_conv2(&options)?;
+        let y = quantized_dot_tensor(
+            "QuantizedConv2",
+            x.cast_into_tensor()?,
+            &self.weight,
+            &self.scales,
+            self.bias.as_ref(),
+        )?;
+        let y = y
+            .into_shape([batch_size, oh, ow, self.out_channels()])
+            .unwrap()
+            .permuted_axes([0, 3, 1, 2])
+            .into_standard_layout()?;
+        Ok(ScalarTensor::from(y).cast_into(scalar_type)?.into())
+    }
+}
+
+impl Layer for QuantizedConv2 {
+    fn try_for_each_parameter<F, E>(&self, _f: F) -> Result<(), E>
+    where
+        F: FnMut(ParameterD) -> Result<(), E>,
+    {
+        Ok(())
+    }
+    fn try_for_each_parameter_view_mut<F, E>(&mut self, _f: F) -> Result<()>
+    where
+        F: FnMut(ParameterViewMutD) -> Result<(), E>,
+        anyhow::Error: From<E>,
+    {
+        Ok(())
+    }
+    fn cast_mut(&mut self, _scalar_type: ScalarType) -> Result<()> {
+        Ok(())
+    }
+    fn to_device_mut(&mut self, device: Device) -> Result<()> {
+        quantized_to_device_mut(&mut self.weight, &mut self.scales, &mut self.bias, device)
     }
 }
 
@@ -1065,11 +3038,13 @@ impl Forward<Variable4> for Conv2 {
 /// # Ok(())
 /// # }
 ///```
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Dense {
     weight: Parameter2,
     bias: Option<Parameter1>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    hooks: Hooks,
 }
 
 impl Dense {
@@ -1080,6 +3055,14 @@ impl Dense {
 }
 
 impl Dense {
+    /// The number of input features.
+    pub fn in_features(&self) -> usize {
+        self.weight.shape()[0]
+    }
+    /// The number of output features.
+    pub fn out_features(&self) -> usize {
+        self.weight.shape()[1]
+    }
     /// The weight as a mutable parameter view.
     pub fn weight_view_mut(&mut self) -> Result<ParameterViewMut2> {
         self.weight.make_view_mut()
@@ -1088,6 +3071,24 @@ impl Dense {
     pub fn bias_view_mut(&mut self) -> Result<Option<ParameterViewMut1>> {
         self.bias.as_mut().map(Parameter::make_view_mut).transpose()
     }
+    /// Quantizes the layer to int8 for inference.
+    ///
+    /// See [`QuantizedDense`].
+    ///
+    /// # Errors
+    /// See [`ScalarTensorBase::to_device()`].
+    pub fn quantize(&self) -> Result<QuantizedDense> {
+        let weight = host_f32_array(self.weight.value())?;
+        let (weight, scales) = quantize_rows(weight.t());
+        let device = self.weight.value().device();
+        let (weight, scales, bias) =
+            quantized_into_device(weight, scales, self.bias.as_ref(), device)?;
+        Ok(QuantizedDense {
+            weight,
+            scales,
+            bias,
+        })
+    }
 }
 
 impl Layer for Dense {
@@ -1101,6 +3102,16 @@ impl Layer for Dense {
         }
         Ok(())
     }
+    fn try_for_each_named_parameter<F, E>(&self, mut f: F) -> Result<(), E>
+    where
+        F: FnMut(&str, ParameterD) -> Result<(), E>,
+    {
+        f("weight", self.weight.clone().into_dyn())?;
+        if let Some(bias) = self.bias.clone() {
+            f("bias", bias.into_dyn())?;
+        }
+        Ok(())
+    }
     fn try_for_each_parameter_view_mut<F, E>(&mut self, mut f: F) -> Result<()>
     where
         F: FnMut(ParameterViewMutD) -> Result<(), E>,
@@ -1129,6 +3140,12 @@ impl Layer for Dense {
         }
         Ok(())
     }
+    fn register_forward_hook(&mut self, name: &str, hook: ForwardHook) -> Result<()> {
+        self.hooks.register_forward_hook("Dense", name, hook)
+    }
+    fn register_backward_hook(&mut self, name: &str, hook: BackwardHook) -> Result<()> {
+        self.hooks.register_backward_hook("Dense", name, hook)
+    }
 }
 
 impl Forward<Variable2> for Dense {
@@ -1138,55 +3155,422 @@ impl Forward<Variable2> for Dense {
         if let Some(bias) = self.bias.as_ref() {
             output.add_assign(&bias.to_variable())?;
         }
-        Ok(output)
+        Ok(self.hooks.output(output))
     }
 }
 
-/// MaxPool.
+/// Moves the quantized weight, scales, and bias onto `device`.
+fn quantized_into_device(
+    weight: Array2<i8>,
+    scales: Array1<f32>,
+    bias: Option<&Parameter1>,
+    device: Device,
+) -> Result<(ArcTensor2<i8>, ArcTensor1<f32>, Option<ArcTensor1<f32>>)> {
+    let weight = Tensor::from(weight).into_device_shared(device.clone())?;
+    let scales = Tensor::from(scales).into_device_shared(device.clone())?;
+    let bias = bias
+        .map(|bias| {
+            bias.value()
+                .clone()
+                .cast_into_tensor::<f32>()?
+                .into_device_shared(device.clone())
+        })
+        .transpose()?;
+    Ok((weight, scales, bias))
+}
+
+fn quantized_to_device_mut(
+    weight: &mut ArcTensor2<i8>,
+    scales: &mut ArcTensor1<f32>,
+    bias: &mut Option<ArcTensor1<f32>>,
+    device: Device,
+) -> Result<()> {
+    weight.to_device_mut(device.clone())?;
+    scales.to_device_mut(device.clone())?;
+    if let Some(bias) = bias.as_mut() {
+        bias.to_device_mut(device)?;
+    }
+    Ok(())
+}
+
+fn host_f32_array<D: Dimension>(x: &ScalarArcTensor<D>) -> Result<Array<f32, D>> {
+    x.to_device(Device::host())?
+        .cast_into_tensor::<f32>()?
+        .into_array()
+}
+
+fn host_f32_into_device<D: Dimension>(
+    x: Array<f32, D>,
+    scalar_type: ScalarType,
+    device: Device,
+) -> Result<ScalarArcTensor<D>> {
+    ScalarTensor::from(Tensor::from(x))
+        .cast_into(scalar_type)?
+        .into_device(device)?
+        .into_shared()
+}
+
+/// Quantizes each row of `x` to int8, returning the quantized rows and the scale of each row.
+fn quantize_rows(x: ArrayView2<f32>) -> (Array2<i8>, Array1<f32>) {
+    let scales: Array1<f32> = x
+        .outer_iter()
+        .map(|x| x.iter().fold(0f32, |m, x| m.max(x.abs())) / 127.)
+        .collect();
+    let mut y = Array2::zeros(x.raw_dim());
+    for ((x, mut y), scale) in x.outer_iter().zip(y.outer_iter_mut()).zip(scales.iter()) {
+        y.zip_mut_with(&x, |y, x| *y = quantize_impl(*x, *scale));
+    }
+    (y, scales)
+}
+
+/// Computes `x` \[m, k\] dot `weight.t()` \[n, k\] in int8, accumulating in i32.
 ///
-/// See [`MaxPool1`] and [`MaxPool2`].
-/// Implemented for bf16 and f32.
-#[derive(Debug, Layer)]
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[autograph(skip, crate=crate)]
-pub struct MaxPool<D: Dimension> {
-    filter: D,
-    stride: D,
+/// `x` is quantized per row, and the output is dequantized with the scales and then the bias
+/// is added.
+fn quantized_dot(
+    x: ArrayView2<f32>,
+    weight: ArrayView2<i8>,
+    scales: ArrayView1<f32>,
+    bias: Option<ArrayView1<f32>>,
+) -> Array2<f32> {
+    let (x, x_scales) = quantize_rows(x);
+    let mut y = Array2::zeros([x.nrows(), weight.nrows()]);
+    Zip::from(y.outer_iter_mut())
+        .and(x.outer_iter())
+        .and(&x_scales)
+        .par_for_each(|mut y, x, x_scale| {
+            for (j, (y, w)) in y.iter_mut().zip(weight.outer_iter()).enumerate() {
+                let acc: i32 = x
+                    .iter()
+                    .zip(w.iter())
+                    .map(|(x, w)| *x as i32 * *w as i32)
+                    .sum();
+                *y = acc as f32 * x_scale * scales[j] + bias.map_or(0., |bias| bias[j]);
+            }
+        });
+    y
 }
 
-/// MaxPool with 1 dimension.
+/// Computes `x` \[m, k\] dot `weight.t()` \[n, k\] in int8, see [`quantized_dot()`].
 ///
-/// See [`MaxPool`].
-pub type MaxPool1 = MaxPool<Ix1>;
-/// MaxPool with 2 dimensions.
+/// `weight`, `scales`, and `bias` must be on the device of `x`.
+fn quantized_dot_tensor(
+    name: &str,
+    x: Tensor2<f32>,
+    weight: &ArcTensor2<i8>,
+    scales: &ArcTensor1<f32>,
+    bias: Option<&ArcTensor1<f32>>,
+) -> Result<Tensor2<f32>> {
+    let device = x.device();
+    if weight.device() != device {
+        bail!(
+            "{name} is on {:?}, found input on {device:?}!",
+            weight.device()
+        );
+    }
+    if let Some(x) = x.as_array() {
+        return Ok(quantized_dot(
+            x,
+            weight.as_array().unwrap(),
+            scales.as_array().unwrap(),
+            bias.map(|bias| bias.as_array().unwrap()),
+        )
+        .into());
+    }
+    #[cfg(not(feature = "device"))]
+    {
+        unreachable!()
+    }
+    #[cfg(feature = "device")]
+    {
+        use krnl::device::Features;
+
+        let info = device.info().unwrap();
+        let features = Features::INT8 | Features::BUFFER8;
+        if !info.features().contains(features) {
+            bail!(
+                "{name} requires {features:?}, {device:?} has {:?}!",
+                info.features()
+            );
+        }
+        let x = x.into_standard_layout()?;
+        let (m, k) = x.dim();
+        let n = weight.shape()[0];
+        let cols = k.to_u32().unwrap();
+        let mut x_scales = unsafe { Tensor::<f32, _>::uninit(device.clone(), m)? };
+        kernels::quantize_scales_f32::builder()?
+            .build(device.clone())?
+            .dispatch(
+                x.as_slice().unwrap(),
+                cols,
+                x_scales.as_slice_mut().unwrap(),
+            )?;
+        let mut x_quant = unsafe { Tensor::<i8, _>::uninit(device.clone(), [m, k])? };
+        kernels::quantize_f32::builder()?
+            .build(device.clone())?
+            .dispatch(
+                x_scales.as_slice().unwrap(),
+                cols,
+                x.as_slice().unwrap(),
+                x_quant.as_slice_mut().unwrap(),
+            )?;
+        let bias = if let Some(bias) = bias {
+            bias.clone()
+        } else {
+            Tensor::zeros(device.clone(), n)?.into()
+        };
+        let mut y = unsafe { Tensor::<f32, _>::uninit(device.clone(), [m, n])? };
+        kernels::quantized_dot_f32::builder()?
+            .build(device)?
+            .dispatch(
+                x_quant.as_slice().unwrap(),
+                x_scales.as_slice().unwrap(),
+                weight.as_slice().unwrap(),
+                scales.as_slice().unwrap(),
+                bias.as_slice().unwrap(),
+                cols,
+                n.to_u32().unwrap(),
+                y.as_slice_mut().unwrap(),
+            )?;
+        Ok(y)
+    }
+}
+
+/// Int8 quantized [`Dense`] for inference.
 ///
-/// See [`MaxPool`].
-pub type MaxPool2 = MaxPool<Ix2>;
+/// Created with [`Dense::quantize()`]. The weight is quantized with a scale for each output
+/// feature, and the input is quantized with a scale for each sample. The product is accumulated
+/// in i32 and then dequantized.
+///
+/// The output has the scalar type of the input. Does not have parameters, so it can not be
+/// trained. On devices, requires [`Features::INT8`](krnl::device::Features::INT8) and
+/// [`Features::BUFFER8`](krnl::device::Features::BUFFER8).
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct QuantizedDense {
+    weight: ArcTensor2<i8>,
+    scales: ArcTensor1<f32>,
+    bias: Option<ArcTensor1<f32>>,
+}
 
-impl<D: Dimension> MaxPool<D> {
-    /// Returns a builder for creating a [`MaxPool`].
-    pub fn builder() -> MaxPoolBuilder<D> {
-        MaxPoolBuilder::new()
+impl QuantizedDense {
+    /// The number of input features.
+    pub fn in_features(&self) -> usize {
+        self.weight.shape()[1]
+    }
+    /// The number of output features.
+    pub fn out_features(&self) -> usize {
+        self.weight.shape()[0]
     }
 }
 
-impl Forward<Variable3> for MaxPool1 {
-    type Output = Variable3;
-    fn forward(&self, input: Variable3) -> Result<Self::Output> {
-        let (n, c, ih) = input.dim();
-        let input = input.into_shape([n, c, ih, 1]).map_err(Error::msg)?;
+impl Forward<Variable2> for QuantizedDense {
+    type Output = Variable2;
+    fn forward(&self, input: Variable2) -> Result<Variable2> {
+        let (_, inputs) = input.dim();
+        if inputs != self.in_features() {
+            bail!(
+                "QuantizedDense expected {} input features, found {inputs}!",
+                self.in_features()
+            );
+        }
+        let scalar_type = input.scalar_type();
+        let y = quantized_dot_tensor(
+            "QuantizedDense",
+            input.into_value().cast_into_tensor()?,
+            &self.weight,
+            &self.scales,
+            self.bias.as_ref(),
+        )?;
+        Ok(ScalarTensor::from(y).cast_into(scalar_type)?.into())
+    }
+}
+
+impl Layer for QuantizedDense {
+    fn try_for_each_parameter<F, E>(&self, _f: F) -> Result<(), E>
+    where
+        F: FnMut(ParameterD) -> Result<(), E>,
+    {
+        Ok(())
+    }
+    fn try_for_each_parameter_view_mut<F, E>(&mut self, _f: F) -> Result<()>
+    where
+        F: FnMut(ParameterViewMutD) -> Result<(), E>,
+        anyhow::Error: From<E>,
+    {
+        Ok(())
+    }
+    fn cast_mut(&mut self, _scalar_type: ScalarType) -> Result<()> {
+        Ok(())
+    }
+    fn to_device_mut(&mut self, device: Device) -> Result<()> {
+        quantized_to_device_mut(&mut self.weight, &mut self.scales, &mut self.bias, device)
+    }
+}
+
+/// Maxout.
+///
+/// Computes `num_pieces` linear projections of the input for each output, and takes the max
+/// over them. The gradient is routed to the piece with the max value.
+///
+/// The projections are stored in a single [`Dense`] layer, where output `i` uses the
+/// columns `i * num_pieces .. (i + 1) * num_pieces`.
+///
+/// Implemented for bf16 and f32.
+///
+/// # Example
+///```no_run
+/// # use autograph::{krnl::device::Device, learn::neural_network::layer::Maxout};
+/// # fn main() -> anyhow::Result<()> {
+/// # let device = Device::host();
+/// let maxout = Maxout::builder()
+///    .inputs(16)
+///    .outputs(8)
+///    .num_pieces(4)
+///    .bias(true)
+///    .device(device.clone())
+///    .build()?;
+/// # Ok(())
+/// # }
+///```
+#[derive(Debug, Clone, Layer)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[autograph(crate=crate)]
+pub struct Maxout {
+    dense: Dense,
+    #[autograph(skip)]
+    num_pieces: usize,
+}
+
+impl Maxout {
+    /// Returns a builder for creating a [`Maxout`].
+    pub fn builder() -> MaxoutBuilder {
+        MaxoutBuilder::new()
+    }
+    /// The number of input features.
+    pub fn in_features(&self) -> usize {
+        self.dense.in_features()
+    }
+    /// The number of output features.
+    pub fn out_features(&self) -> usize {
+        self.dense.out_features() / self.num_pieces
+    }
+    /// The number of linear pieces per output.
+    pub fn num_pieces(&self) -> usize {
+        self.num_pieces
+    }
+    /// The linear projections.
+    pub fn dense(&self) -> &Dense {
+        &self.dense
+    }
+    /// The linear projections, mutably.
+    pub fn dense_mut(&mut self) -> &mut Dense {
+        &mut self.dense
+    }
+}
+
+impl Forward<Variable2> for Maxout {
+    type Output = Variable2;
+    fn forward(&self, input: Variable2) -> Result<Variable2> {
+        let (batch_size, _) = input.dim();
+        let outputs = self.out_features();
+        let num_pieces = self.num_pieces;
+        let output = self
+            .dense
+            .forward(input)?
+            .into_shape([batch_size, 1, outputs, num_pieces])
+            .map_err(Error::msg)?;
+        let pool = MaxPool2 {
+            filter: [1, num_pieces].into_dimension(),
+            stride: [1, num_pieces].into_dimension(),
+            padding_mode: PaddingMode::Valid,
+            hooks: Hooks::default(),
+        };
+        pool.forward(output)?
+            .into_shape([batch_size, outputs])
+            .map_err(Error::msg)
+    }
+}
+
+/// MaxPool.
+///
+/// See [`MaxPool1`] and [`MaxPool2`].
+/// Implemented for bf16 and f32.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MaxPool<D: Dimension> {
+    filter: D,
+    stride: D,
+    #[cfg_attr(feature = "serde", serde(default))]
+    padding_mode: PaddingMode,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    hooks: Hooks,
+}
+
+/// MaxPool with 1 dimension.
+///
+/// See [`MaxPool`].
+pub type MaxPool1 = MaxPool<Ix1>;
+/// MaxPool with 2 dimensions.
+///
+/// See [`MaxPool`].
+pub type MaxPool2 = MaxPool<Ix2>;
+
+impl<D: Dimension> MaxPool<D> {
+    /// Returns a builder for creating a [`MaxPool`].
+    pub fn builder() -> MaxPoolBuilder<D> {
+        MaxPoolBuilder::new()
+    }
+}
+
+impl<D: Dimension> Layer for MaxPool<D> {
+    fn try_for_each_parameter<F, E>(&self, _f: F) -> Result<(), E>
+    where
+        F: FnMut(ParameterD) -> Result<(), E>,
+    {
+        Ok(())
+    }
+    fn try_for_each_parameter_view_mut<F, E>(&mut self, _f: F) -> Result<()>
+    where
+        F: FnMut(ParameterViewMutD) -> Result<(), E>,
+        anyhow::Error: From<E>,
+    {
+        Ok(())
+    }
+    fn cast_mut(&mut self, _scalar_type: ScalarType) -> Result<()> {
+        Ok(())
+    }
+    fn to_device_mut(&mut self, _device: Device) -> Result<()> {
+        Ok(())
+    }
+    fn register_forward_hook(&mut self, name: &str, hook: ForwardHook) -> Result<()> {
+        self.hooks.register_forward_hook("MaxPool", name, hook)
+    }
+    fn register_backward_hook(&mut self, name: &str, hook: BackwardHook) -> Result<()> {
+        self.hooks.register_backward_hook("MaxPool", name, hook)
+    }
+}
+
+impl Forward<Variable3> for MaxPool1 {
+    type Output = Variable3;
+    fn forward(&self, input: Variable3) -> Result<Self::Output> {
+        let (n, c, ih) = input.dim();
+        let input = input.into_shape([n, c, ih, 1]).map_err(Error::msg)?;
         let fh = self.filter.into_pattern();
         let sh = self.stride.into_pattern();
         let output = MaxPool2 {
             filter: [fh, 1].into_dimension(),
             stride: [sh, 1].into_dimension(),
+            padding_mode: self.padding_mode,
+            hooks: Hooks::default(),
         }
         .forward(input)?;
         let (n2, c2, oh, ow) = output.dim();
         debug_assert_eq!(n, n2);
         debug_assert_eq!(c, c2);
         debug_assert_eq!(ow, 1);
-        output.into_shape([n, c, oh]).map_err(Error::msg)
+        let output = output.into_shape([n, c, oh]).map_err(Error::msg)?;
+        Ok(self.hooks.output(output))
     }
 }
 
@@ -1195,6 +3579,14 @@ impl Forward<Variable4> for MaxPool2 {
     fn forward(&self, input: Variable4) -> Result<Self::Output> {
         let (fh, fw) = self.filter.into_pattern();
         let (sh, sw) = self.stride.into_pattern();
+        let input = if self.padding_mode == PaddingMode::Same {
+            let (_, _, ih, iw) = input.dim();
+            let padding = [same_padding(ih, fh, sh, 1), same_padding(iw, fw, sw, 1)]
+                .map(|p| p.map(|p| p as isize));
+            pad2(input, padding, f32::NEG_INFINITY)?
+        } else {
+            input
+        };
         let options = MaxPool2Options {
             size: [fh, fw],
             strides: [sh, sw],
@@ -1210,59 +3602,2463 @@ impl Forward<Variable4> for MaxPool2 {
                 Ok(input)
             });
         }
-        let output = input.value().max_pool2(options)?;
-        Ok(builder.build(output.into()))
+        let output = input.value().max_pool2(options)?;
+        Ok(self.hooks.output(builder.build(output.into())))
+    }
+}
+
+/// Batch normalization with 2 spatial dimensions.
+///
+/// Normalizes each channel of the input \[n, c, h, w\] over the batch and spatial dimensions,
+/// then scales by `gamma` and adds `beta`.
+///
+/// In training mode, the statistics of the batch are used, and the running statistics are
+/// updated with `momentum`:
+///```text
+/// running_mean = (1 - momentum) * running_mean + momentum * mean
+/// running_var = (1 - momentum) * running_var + momentum * unbiased_var
+///```
+/// In eval mode, see [`Layer::set_training()`], the running statistics are used.
+///
+/// Implemented for bf16 and f32. Statistics are computed in f32. The running statistics are
+/// stored in f32 on the device of the layer.
+///
+/// # Example
+///```no_run
+/// # use autograph::{krnl::device::Device, anyhow::Result};
+/// # use autograph::learn::neural_network::layer::BatchNorm2;
+/// # fn main() -> Result<()> {
+/// # let device = Device::host();
+/// let batch_norm = BatchNorm2::builder()
+///    .channels(16)
+///    .device(device.clone())
+///    .build()?;
+/// # Ok(())
+/// # }
+///```
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct BatchNorm2 {
+    gamma: Parameter1,
+    beta: Parameter1,
+    running: Mutex<BatchNormStats>,
+    epsilon: f32,
+    momentum: f32,
+    training: bool,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+struct BatchNormStats {
+    mean: ArcTensor1<f32>,
+    var: ArcTensor1<f32>,
+}
+
+impl Clone for BatchNorm2 {
+    fn clone(&self) -> Self {
+        Self {
+            gamma: self.gamma.clone(),
+            beta: self.beta.clone(),
+            running: Mutex::new(self.running.lock().clone()),
+            epsilon: self.epsilon,
+            momentum: self.momentum,
+            training: self.training,
+        }
+    }
+}
+
+impl BatchNorm2 {
+    /// Returns a builder for creating a [`BatchNorm2`].
+    pub fn builder() -> BatchNorm2Builder {
+        BatchNorm2Builder::new()
+    }
+    /// The number of channels.
+    pub fn channels(&self) -> usize {
+        self.gamma.shape()[0]
+    }
+    /// The epsilon added to the variance.
+    pub fn epsilon(&self) -> f32 {
+        self.epsilon
+    }
+    /// The momentum of the running statistics.
+    pub fn momentum(&self) -> f32 {
+        self.momentum
+    }
+    /// Whether in training mode.
+    pub fn training(&self) -> bool {
+        self.training
+    }
+    /// The running mean.
+    ///
+    /// # Errors
+    /// See [`.to_device()`](crate::tensor::TensorBase::to_device).
+    pub fn running_mean(&self) -> Result<Array1<f32>> {
+        self.running
+            .lock()
+            .mean
+            .to_device(Device::host())?
+            .into_array()
+    }
+    /// The running variance.
+    ///
+    /// # Errors
+    /// See [`.to_device()`](crate::tensor::TensorBase::to_device).
+    pub fn running_var(&self) -> Result<Array1<f32>> {
+        self.running
+            .lock()
+            .var
+            .to_device(Device::host())?
+            .into_array()
+    }
+    /// The scale as a mutable parameter view.
+    pub fn gamma_view_mut(&mut self) -> Result<ParameterViewMut1> {
+        self.gamma.make_view_mut()
+    }
+    /// The shift as a mutable parameter view.
+    pub fn beta_view_mut(&mut self) -> Result<ParameterViewMut1> {
+        self.beta.make_view_mut()
+    }
+    // Returns the mean and variance for each channel, updating the running statistics in
+    // training mode.
+    fn statistics<T: Scalar>(
+        &self,
+        x: TensorView4<T>,
+    ) -> Result<(ArcTensor1<f32>, ArcTensor1<f32>)> {
+        let mut running = self.running.lock();
+        if !self.training {
+            return Ok((running.mean.clone(), running.var.clone()));
+        }
+        let (n, _, h, w) = x.dim();
+        let len = n * h * w;
+        if len < 2 {
+            bail!("BatchNorm2 expected more than 1 value per channel in training!");
+        }
+        let (mean, var) = batch_norm2_stats(x)?;
+        let unbiased = len as f32 / (len - 1) as f32;
+        let BatchNormStats {
+            mean: running_mean,
+            var: running_var,
+        } = &mut *running;
+        batch_norm_update_running(
+            running_mean.make_view_mut()?,
+            running_var.make_view_mut()?,
+            mean.view(),
+            var.view(),
+            self.momentum,
+            unbiased,
+        )?;
+        Ok((mean.into_shared()?, var.into_shared()?))
+    }
+    fn forward_impl<T: Scalar>(&self, input: Variable4) -> Result<Variable4> {
+        let x = TensorView4::<T>::try_from(input.value().view()).unwrap();
+        let x = x.as_standard_layout()?;
+        let (mean, var) = self.statistics(x.view())?;
+        let gamma = self.gamma.value().clone();
+        let beta = self.beta.value().clone();
+        let epsilon = self.epsilon;
+        let (x_hat, output) = batch_norm2(
+            x.view(),
+            mean.view(),
+            var.view(),
+            gamma.view().try_into().unwrap(),
+            beta.view().try_into().unwrap(),
+            epsilon,
+        )?;
+        let x_hat = x_hat.into_shared()?;
+        let mut builder = Variable::builder();
+        if let Some(node) = input.node() {
+            let x_hat = x_hat.clone();
+            let training = self.training;
+            builder.edge(node, move |output_grad| {
+                let dy = output_grad.as_standard_layout()?;
+                let dy = TensorView4::<T>::try_from(dy.view()).unwrap();
+                let (dy_sum, dy_x_hat_sum) = batch_norm2_sums(dy.view(), x_hat.view())?;
+                let dx = batch_norm2_backward(
+                    dy,
+                    x_hat.view(),
+                    gamma.view().try_into().unwrap(),
+                    var.view(),
+                    dy_sum.view(),
+                    dy_x_hat_sum.view(),
+                    epsilon,
+                    training,
+                )?;
+                Ok(dx.into_shared()?.into())
+            });
+        }
+        let gamma = self.gamma.to_variable();
+        if let Some(node) = gamma.node() {
+            let x_hat = x_hat.clone();
+            builder.edge(node, move |output_grad| {
+                let dy = output_grad.as_standard_layout()?;
+                let dy = TensorView4::<T>::try_from(dy.view()).unwrap();
+                let (_, dy_x_hat_sum) = batch_norm2_sums(dy, x_hat.view())?;
+                Ok(dy_x_hat_sum.cast_into::<T>()?.into_shared()?.into())
+            });
+        }
+        let beta = self.beta.to_variable();
+        if let Some(node) = beta.node() {
+            builder.edge(node, move |output_grad| {
+                let dy = output_grad.as_standard_layout()?;
+                let dy = TensorView4::<T>::try_from(dy.view()).unwrap();
+                let (dy_sum, _) = batch_norm2_sums(dy, x_hat.view())?;
+                Ok(dy_sum.cast_into::<T>()?.into_shared()?.into())
+            });
+        }
+        Ok(builder.build(output.into_shared()?.into()))
+    }
+}
+
+impl Layer for BatchNorm2 {
+    fn try_for_each_parameter<F, E>(&self, mut f: F) -> Result<(), E>
+    where
+        F: FnMut(ParameterD) -> Result<(), E>,
+    {
+        f(self.gamma.clone().into_dyn())?;
+        f(self.beta.clone().into_dyn())
+    }
+    fn try_for_each_named_parameter<F, E>(&self, mut f: F) -> Result<(), E>
+    where
+        F: FnMut(&str, ParameterD) -> Result<(), E>,
+    {
+        f("gamma", self.gamma.clone().into_dyn())?;
+        f("beta", self.beta.clone().into_dyn())
+    }
+    fn try_for_each_parameter_view_mut<F, E>(&mut self, mut f: F) -> Result<()>
+    where
+        F: FnMut(ParameterViewMutD) -> Result<(), E>,
+        anyhow::Error: From<E>,
+    {
+        f(self.gamma.make_view_mut()?.into_dyn())?;
+        f(self.beta.make_view_mut()?.into_dyn())?;
+        Ok(())
+    }
+    fn cast_mut(&mut self, scalar_type: ScalarType) -> Result<()> {
+        if !matches!(scalar_type, ScalarType::BF16 | ScalarType::F32) {
+            bail!("BatchNorm2 {scalar_type:?} not implemented!");
+        }
+        self.gamma.cast_mut(scalar_type)?;
+        self.beta.cast_mut(scalar_type)
+    }
+    fn to_device_mut(&mut self, device: Device) -> Result<()> {
+        self.gamma.to_device_mut(device.clone())?;
+        self.beta.to_device_mut(device.clone())?;
+        let running = self.running.get_mut();
+        running.mean = running.mean.to_device(device.clone())?.into_shared()?;
+        running.var = running.var.to_device(device)?.into_shared()?;
+        Ok(())
+    }
+    /// Sets training or eval mode. Defaults to training.
+    fn set_training(&mut self, training: bool) {
+        self.training = training;
+    }
+}
+
+impl Forward<Variable4> for BatchNorm2 {
+    type Output = Variable4;
+    fn forward(&self, input: Variable4) -> Result<Self::Output> {
+        let (_, channels, _, _) = input.dim();
+        if channels != self.channels() {
+            bail!(
+                "BatchNorm2 expected {} channels, found {channels}!",
+                self.channels()
+            );
+        }
+        let scalar_type = input.scalar_type();
+        dry::macro_for!($T in [bf16, f32] {
+            if scalar_type == $T::SCALAR_TYPE {
+                return self.forward_impl::<$T>(input);
+            }
+        });
+        bail!("BatchNorm2 {scalar_type:?} not implemented!")
+    }
+}
+
+// Returns the mean and biased variance of each channel.
+fn batch_norm2_stats<T: Scalar>(x: TensorView4<T>) -> Result<(Tensor1<f32>, Tensor1<f32>)> {
+    let (n, c, h, w) = x.dim();
+    if let Some(x) = x.as_array() {
+        let len = (n * h * w) as f32;
+        let mean: Array1<f32> = x
+            .axis_iter(Axis(1))
+            .map(|x| x.fold(0f32, |acc, x| acc + x.cast::<f32>()) / len)
+            .collect();
+        let var: Array1<f32> = x
+            .axis_iter(Axis(1))
+            .zip(mean.iter())
+            .map(|(x, mean)| x.fold(0f32, |acc, x| acc + (x.cast::<f32>() - mean).powi(2)) / len)
+            .collect();
+        return Ok((mean.into(), var.into()));
+    }
+    #[cfg(not(feature = "device"))]
+    {
+        unreachable!()
+    }
+    #[cfg(feature = "device")]
+    {
+        let device = x.device();
+        let mut mean = unsafe { Tensor::uninit(device.clone(), c)? };
+        let mut var = unsafe { Tensor::uninit(device.clone(), c)? };
+        let x = x.as_slice().unwrap();
+        macro_for!($T in [bf16, f32] {
+            if let Ok(x) = x.as_scalar_slice().try_into() {
+                let kernel = paste! {
+                    kernels::[<batch_norm2_stats_ $T>]::builder()?.build(device)?
+                };
+                kernel.dispatch(
+                    x,
+                    n.to_u32().unwrap(),
+                    c.to_u32().unwrap(),
+                    (h * w).to_u32().unwrap(),
+                    mean.as_slice_mut().unwrap(),
+                    var.as_slice_mut().unwrap(),
+                )?;
+                return Ok((mean, var));
+            }
+        });
+        bail!("batch_norm2_stats {:?} unimplemented!", T::SCALAR_TYPE)
+    }
+}
+
+fn batch_norm_update_running(
+    mut running_mean: TensorViewMut1<f32>,
+    mut running_var: TensorViewMut1<f32>,
+    mean: TensorView1<f32>,
+    var: TensorView1<f32>,
+    momentum: f32,
+    unbiased: f32,
+) -> Result<()> {
+    if let Some(((mut running_mean, mut running_var), (mean, var))) = running_mean
+        .as_array_mut()
+        .zip(running_var.as_array_mut())
+        .zip(mean.as_array().zip(var.as_array()))
+    {
+        running_mean.zip_mut_with(&mean, |r, m| {
+            *r = batch_norm_running_impl(*r, *m, momentum);
+        });
+        running_var.zip_mut_with(&var, |r, v| {
+            *r = batch_norm_running_impl(*r, v * unbiased, momentum);
+        });
+        return Ok(());
+    }
+    #[cfg(not(feature = "device"))]
+    {
+        unreachable!()
+    }
+    #[cfg(feature = "device")]
+    {
+        let device = mean.device();
+        let mean = mean.as_standard_layout()?;
+        let var = var.as_standard_layout()?;
+        kernels::batch_norm_update_running::builder()?
+            .build(device)?
+            .dispatch(
+                momentum,
+                unbiased,
+                mean.as_slice().unwrap(),
+                var.as_slice().unwrap(),
+                running_mean.as_slice_mut().unwrap(),
+                running_var.as_slice_mut().unwrap(),
+            )
+    }
+}
+
+// Returns x_hat in f32 and the output.
+fn batch_norm2<T: Scalar>(
+    x: TensorView4<T>,
+    mean: TensorView1<f32>,
+    var: TensorView1<f32>,
+    gamma: TensorView1<T>,
+    beta: TensorView1<T>,
+    epsilon: f32,
+) -> Result<(Tensor4<f32>, Tensor4<T>)> {
+    let (_, c, h, w) = x.dim();
+    if let Some((x, (mean, var))) = x.as_array().zip(mean.as_array().zip(var.as_array())) {
+        let (gamma, beta) = (gamma.as_array().unwrap(), beta.as_array().unwrap());
+        let mut x_hat = Array::zeros(x.raw_dim());
+        let mut y = Array::from_elem(x.raw_dim(), T::zero());
+        for (ch, ((x, mut x_hat), mut y)) in x
+            .axis_iter(Axis(1))
+            .zip(x_hat.axis_iter_mut(Axis(1)))
+            .zip(y.axis_iter_mut(Axis(1)))
+            .enumerate()
+        {
+            let (gamma, beta) = (gamma[ch].cast::<f32>(), beta[ch].cast::<f32>());
+            Zip::from(&x)
+                .and(&mut x_hat)
+                .and(&mut y)
+                .for_each(|x, x_hat, y| {
+                    *x_hat = batch_norm_impl(x.cast(), mean[ch], var[ch], epsilon);
+                    *y = (*x_hat * gamma + beta).cast();
+                });
+        }
+        return Ok((x_hat.into(), y.into()));
+    }
+    #[cfg(not(feature = "device"))]
+    {
+        unreachable!()
+    }
+    #[cfg(feature = "device")]
+    {
+        let device = x.device();
+        let mut x_hat = unsafe { Tensor::uninit(device.clone(), x.raw_dim())? };
+        let mut y = unsafe { Tensor::<T, _>::uninit(device.clone(), x.raw_dim())? };
+        let x = x.as_slice().unwrap();
+        let (mean, var) = (mean.as_standard_layout()?, var.as_standard_layout()?);
+        let (gamma, beta) = (gamma.as_standard_layout()?, beta.as_standard_layout()?);
+        macro_for!($T in [bf16, f32] {
+            if let Ok(x) = x.as_scalar_slice().try_into() {
+                let kernel = paste! {
+                    kernels::[<batch_norm2_ $T>]::builder()?.build(device)?
+                };
+                kernel.dispatch(
+                    mean.as_slice().unwrap(),
+                    var.as_slice().unwrap(),
+                    gamma.as_slice().unwrap().as_scalar_slice().try_into().unwrap(),
+                    beta.as_slice().unwrap().as_scalar_slice().try_into().unwrap(),
+                    c.to_u32().unwrap(),
+                    (h * w).to_u32().unwrap(),
+                    epsilon,
+                    x,
+                    x_hat.as_slice_mut().unwrap(),
+                    ScalarSliceMut::from(y.as_slice_mut().unwrap()).try_into().unwrap(),
+                )?;
+                return Ok((x_hat, y));
+            }
+        });
+        bail!("batch_norm2 {:?} unimplemented!", T::SCALAR_TYPE)
+    }
+}
+
+// Returns sum(dy) and sum(dy * x_hat) for each channel.
+fn batch_norm2_sums<T: Scalar>(
+    dy: TensorView4<T>,
+    x_hat: TensorView4<f32>,
+) -> Result<(Tensor1<f32>, Tensor1<f32>)> {
+    let (n, c, h, w) = dy.dim();
+    if let Some((dy, x_hat)) = dy.as_array().zip(x_hat.as_array()) {
+        let (dy_sum, dy_x_hat_sum): (Vec<f32>, Vec<f32>) = dy
+            .axis_iter(Axis(1))
+            .zip(x_hat.axis_iter(Axis(1)))
+            .map(|(dy, x_hat)| {
+                let dy_x_hat_sum: f32 = dy
+                    .iter()
+                    .zip(x_hat)
+                    .map(|(dy, x_hat)| dy.cast::<f32>() * x_hat)
+                    .sum();
+                (
+                    dy.fold(0f32, |acc, dy| acc + dy.cast::<f32>()),
+                    dy_x_hat_sum,
+                )
+            })
+            .unzip();
+        return Ok((Tensor::from(dy_sum), Tensor::from(dy_x_hat_sum)));
+    }
+    #[cfg(not(feature = "device"))]
+    {
+        unreachable!()
+    }
+    #[cfg(feature = "device")]
+    {
+        let device = dy.device();
+        let mut dy_sum = unsafe { Tensor::uninit(device.clone(), c)? };
+        let mut dy_x_hat_sum = unsafe { Tensor::uninit(device.clone(), c)? };
+        let dy = dy.as_slice().unwrap();
+        macro_for!($T in [bf16, f32] {
+            if let Ok(dy) = dy.as_scalar_slice().try_into() {
+                let kernel = paste! {
+                    kernels::[<batch_norm2_sums_ $T>]::builder()?.build(device)?
+                };
+                kernel.dispatch(
+                    dy,
+                    x_hat.as_slice().unwrap(),
+                    n.to_u32().unwrap(),
+                    c.to_u32().unwrap(),
+                    (h * w).to_u32().unwrap(),
+                    dy_sum.as_slice_mut().unwrap(),
+                    dy_x_hat_sum.as_slice_mut().unwrap(),
+                )?;
+                return Ok((dy_sum, dy_x_hat_sum));
+            }
+        });
+        bail!("batch_norm2_sums {:?} unimplemented!", T::SCALAR_TYPE)
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn batch_norm2_backward<T: Scalar>(
+    dy: TensorView4<T>,
+    x_hat: TensorView4<f32>,
+    gamma: TensorView1<T>,
+    var: TensorView1<f32>,
+    dy_sum: TensorView1<f32>,
+    dy_x_hat_sum: TensorView1<f32>,
+    epsilon: f32,
+    training: bool,
+) -> Result<Tensor4<T>> {
+    let (n, c, h, w) = dy.dim();
+    let len = (n * h * w) as f32;
+    if let Some((dy, x_hat)) = dy.as_array().zip(x_hat.as_array()) {
+        let (gamma, var) = (gamma.as_array().unwrap(), var.as_array().unwrap());
+        let (dy_sum, dy_x_hat_sum) = (dy_sum.as_array().unwrap(), dy_x_hat_sum.as_array().unwrap());
+        let mut dx = Array::from_elem(dy.raw_dim(), T::zero());
+        for (ch, ((dy, x_hat), mut dx)) in dy
+            .axis_iter(Axis(1))
+            .zip(x_hat.axis_iter(Axis(1)))
+            .zip(dx.axis_iter_mut(Axis(1)))
+            .enumerate()
+        {
+            let scale = gamma[ch].cast::<f32>() / (var[ch] + epsilon).sqrt();
+            let (dy_mean, dy_x_hat_mean) = if training {
+                (dy_sum[ch] / len, dy_x_hat_sum[ch] / len)
+            } else {
+                (0., 0.)
+            };
+            Zip::from(&dy)
+                .and(&x_hat)
+                .and(&mut dx)
+                .for_each(|dy, x_hat, dx| {
+                    *dx =
+                        batch_norm_backward_impl(dy.cast(), *x_hat, scale, dy_mean, dy_x_hat_mean)
+                            .cast();
+                });
+        }
+        return Ok(dx.into());
+    }
+    #[cfg(not(feature = "device"))]
+    {
+        unreachable!()
+    }
+    #[cfg(feature = "device")]
+    {
+        let device = dy.device();
+        let mut dx = unsafe { Tensor::<T, _>::uninit(device.clone(), dy.raw_dim())? };
+        let dy = dy.as_slice().unwrap();
+        let gamma = gamma.as_standard_layout()?;
+        let var = var.as_standard_layout()?;
+        macro_for!($T in [bf16, f32] {
+            if let Ok(dy) = dy.as_scalar_slice().try_into() {
+                let kernel = paste! {
+                    kernels::[<batch_norm2_backward_ $T>]::builder()?.build(device)?
+                };
+                kernel.dispatch(
+                    gamma.as_slice().unwrap().as_scalar_slice().try_into().unwrap(),
+                    var.as_slice().unwrap(),
+                    dy_sum.as_slice().unwrap(),
+                    dy_x_hat_sum.as_slice().unwrap(),
+                    c.to_u32().unwrap(),
+                    (h * w).to_u32().unwrap(),
+                    epsilon,
+                    len,
+                    u32::from(training),
+                    x_hat.as_slice().unwrap(),
+                    dy,
+                    ScalarSliceMut::from(dx.as_slice_mut().unwrap()).try_into().unwrap(),
+                )?;
+                return Ok(dx);
+            }
+        });
+        bail!("batch_norm2_backward {:?} unimplemented!", T::SCALAR_TYPE)
+    }
+}
+
+/// Embedding.
+///
+/// Maps U32 indices to rows of the weight \[num_embeddings, embedding_dim\]. An input with
+/// shape \[..\] produces an output with shape \[.., embedding_dim\]. The backward pass adds the
+/// gradient of each output row to the gradient of the weight row at its index.
+///
+/// Implemented for bf16 and f32. Indices on the host are checked, on the device out of bounds
+/// indices produce zeros and are ignored in the backward pass.
+///
+/// # Example
+///```no_run
+/// # use autograph::{krnl::device::Device, anyhow::Result};
+/// # use autograph::learn::neural_network::layer::Embedding;
+/// # fn main() -> Result<()> {
+/// # let device = Device::host();
+/// let embedding = Embedding::builder()
+///    .num_embeddings(1000)
+///    .embedding_dim(64)
+///    .padding_idx(0)
+///    .device(device.clone())
+///    .build()?;
+/// # Ok(())
+/// # }
+///```
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Embedding {
+    weight: Parameter2,
+    padding_idx: Option<usize>,
+}
+
+impl Embedding {
+    /// Returns a builder for creating an [`Embedding`].
+    pub fn builder() -> EmbeddingBuilder {
+        EmbeddingBuilder::new()
+    }
+    /// The number of embeddings.
+    pub fn num_embeddings(&self) -> usize {
+        self.weight.shape()[0]
+    }
+    /// The size of each embedding vector.
+    pub fn embedding_dim(&self) -> usize {
+        self.weight.shape()[1]
+    }
+    /// The padding index.
+    pub fn padding_idx(&self) -> Option<usize> {
+        self.padding_idx
+    }
+    /// The weight as a mutable parameter view.
+    pub fn weight_view_mut(&mut self) -> Result<ParameterViewMut2> {
+        self.weight.make_view_mut()
+    }
+}
+
+impl Layer for Embedding {
+    fn try_for_each_parameter<F, E>(&self, mut f: F) -> Result<(), E>
+    where
+        F: FnMut(ParameterD) -> Result<(), E>,
+    {
+        f(self.weight.clone().into_dyn())
+    }
+    fn try_for_each_named_parameter<F, E>(&self, mut f: F) -> Result<(), E>
+    where
+        F: FnMut(&str, ParameterD) -> Result<(), E>,
+    {
+        f("weight", self.weight.clone().into_dyn())
+    }
+    fn try_for_each_parameter_view_mut<F, E>(&mut self, mut f: F) -> Result<()>
+    where
+        F: FnMut(ParameterViewMutD) -> Result<(), E>,
+        anyhow::Error: From<E>,
+    {
+        f(self.weight.make_view_mut()?.into_dyn())?;
+        Ok(())
+    }
+    fn cast_mut(&mut self, scalar_type: ScalarType) -> Result<()> {
+        if !matches!(scalar_type, ScalarType::BF16 | ScalarType::F32) {
+            bail!("Embedding {scalar_type:?} not implemented!");
+        }
+        self.weight.cast_mut(scalar_type)
+    }
+    fn to_device_mut(&mut self, device: Device) -> Result<()> {
+        self.weight.to_device_mut(device)
+    }
+}
+
+impl<D: Dimension + 'static> Forward<Variable<D>> for Embedding
+where
+    D::Larger: 'static,
+{
+    type Output = Variable<D::Larger>;
+    fn forward(&self, input: Variable<D>) -> Result<Self::Output> {
+        if input.scalar_type() != ScalarType::U32 {
+            bail!(
+                "Embedding expected U32 indices, found {:?}!",
+                input.scalar_type()
+            );
+        }
+        let scalar_type = self.weight.scalar_type();
+        dry::macro_for!($T in [bf16, f32] {
+            if scalar_type == $T::SCALAR_TYPE {
+                return self.forward_impl::<$T, D>(input);
+            }
+        });
+        bail!("Embedding {scalar_type:?} not implemented!")
+    }
+}
+
+impl Embedding {
+    fn forward_impl<T: Scalar, D: Dimension + 'static>(
+        &self,
+        input: Variable<D>,
+    ) -> Result<Variable<D::Larger>>
+    where
+        D::Larger: 'static,
+    {
+        let (num_embeddings, embedding_dim) = (self.num_embeddings(), self.embedding_dim());
+        let len = input.value().len();
+        let indices = input
+            .value()
+            .to_standard_layout_shared()?
+            .try_into_arc_tensor::<u32>()
+            .unwrap()
+            .into_shape(len)?;
+        let weight = self.weight.value().as_standard_layout()?;
+        let output = embedding(
+            indices.view(),
+            TensorView2::<T>::try_from(weight.view()).unwrap(),
+        )?;
+        let mut shape = input.shape().to_vec();
+        shape.push(embedding_dim);
+        let output = output
+            .into_shape(shape)?
+            .into_dimensionality::<D::Larger>()?;
+        let mut builder = Variable::builder();
+        let weight = self.weight.to_variable();
+        if let Some(node) = weight.node() {
+            let padding_idx = self.padding_idx;
+            builder.edge(node, move |output_grad| {
+                let dy = output_grad.as_standard_layout()?;
+                let dy = TensorView::<T, D::Larger>::try_from(dy.view())
+                    .unwrap()
+                    .into_shape([len, embedding_dim])?;
+                let dw = embedding_backward(indices.view(), dy, num_embeddings, padding_idx)?;
+                Ok(dw.into_shared()?.into())
+            });
+        }
+        Ok(builder.build(output.into_shared()?.into()))
+    }
+}
+
+fn embedding<T: Scalar>(indices: TensorView1<u32>, weight: TensorView2<T>) -> Result<Tensor2<T>> {
+    let (num_embeddings, embedding_dim) = weight.dim();
+    if let Some((indices, weight)) = indices.as_array().zip(weight.as_array()) {
+        if let Some(index) = indices.iter().find(|i| **i as usize >= num_embeddings) {
+            bail!("Embedding index {index} is out of bounds for {num_embeddings} embeddings!");
+        }
+        let mut output = Array::from_elem([indices.len(), embedding_dim], T::zero());
+        for (mut y, index) in output.outer_iter_mut().zip(indices.iter()) {
+            y.assign(&weight.row(*index as usize));
+        }
+        return Ok(output.into());
+    }
+    #[cfg(not(feature = "device"))]
+    {
+        unreachable!()
+    }
+    #[cfg(feature = "device")]
+    {
+        let device = weight.device();
+        let mut output =
+            unsafe { Tensor::<T, _>::uninit(device.clone(), [indices.len(), embedding_dim])? };
+        let indices = indices.as_standard_layout()?;
+        let weight = weight.as_slice().unwrap();
+        macro_for!($T in [bf16, f32] {
+            if let Ok(weight) = weight.as_scalar_slice().try_into() {
+                let kernel = paste! {
+                    kernels::[<embedding_ $T>]::builder()?.build(device)?
+                };
+                kernel.dispatch(
+                    weight,
+                    indices.as_slice().unwrap(),
+                    num_embeddings.to_u32().unwrap(),
+                    embedding_dim.to_u32().unwrap(),
+                    ScalarSliceMut::from(output.as_slice_mut().unwrap()).try_into().unwrap(),
+                )?;
+                return Ok(output);
+            }
+        });
+        bail!("embedding {:?} unimplemented!", T::SCALAR_TYPE)
+    }
+}
+
+fn embedding_backward<T: Scalar>(
+    indices: TensorView1<u32>,
+    dy: TensorView2<T>,
+    num_embeddings: usize,
+    padding_idx: Option<usize>,
+) -> Result<Tensor2<T>> {
+    let embedding_dim = dy.dim().1;
+    if let Some((indices, dy)) = indices.as_array().zip(dy.as_array()) {
+        let mut dw = Array::from_elem([num_embeddings, embedding_dim], T::zero());
+        for (dy, index) in dy.outer_iter().zip(indices.iter()) {
+            let index = *index as usize;
+            if Some(index) != padding_idx {
+                dw.row_mut(index)
+                    .zip_mut_with(&dy, |dw, dy| *dw = embedding_backward_impl(*dw, *dy));
+            }
+        }
+        return Ok(dw.into());
+    }
+    #[cfg(not(feature = "device"))]
+    {
+        unreachable!()
+    }
+    #[cfg(feature = "device")]
+    {
+        let device = dy.device();
+        let mut dw =
+            unsafe { Tensor::<T, _>::uninit(device.clone(), [num_embeddings, embedding_dim])? };
+        let indices = indices.as_standard_layout()?;
+        let dy = dy.as_slice().unwrap();
+        let padding_idx = padding_idx.map_or(u32::MAX, |x| x.to_u32().unwrap());
+        macro_for!($T in [bf16, f32] {
+            if let Ok(dy) = dy.as_scalar_slice().try_into() {
+                let kernel = paste! {
+                    kernels::[<embedding_backward_ $T>]::builder()?
+                        .with_threads(256)
+                        .build(device)?
+                };
+                kernel
+                    .with_global_threads(num_embeddings.to_u32().unwrap())
+                    .dispatch(
+                        indices.as_slice().unwrap(),
+                        dy,
+                        num_embeddings.to_u32().unwrap(),
+                        embedding_dim.to_u32().unwrap(),
+                        padding_idx,
+                        ScalarSliceMut::from(dw.as_slice_mut().unwrap()).try_into().unwrap(),
+                    )?;
+                return Ok(dw);
+            }
+        });
+        bail!("embedding_backward {:?} unimplemented!", T::SCALAR_TYPE)
+    }
+}
+
+/// Layer normalization.
+///
+/// Normalizes each sample over the trailing dimensions given by `normalized_shape`, then scales
+/// by `gamma` and adds `beta`, which have shape `normalized_shape`.
+///
+/// Implemented for bf16 and f32. Statistics are computed in f32.
+///
+/// # Example
+///```no_run
+/// # use autograph::{krnl::device::Device, anyhow::Result};
+/// # use autograph::learn::neural_network::layer::LayerNorm;
+/// # fn main() -> Result<()> {
+/// # let device = Device::host();
+/// // normalize the features of inputs [batch_size, sequence_length, 64]
+/// let layer_norm = LayerNorm::builder()
+///    .normalized_shape(64)
+///    .device(device.clone())
+///    .build()?;
+/// # Ok(())
+/// # }
+///```
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct LayerNorm {
+    gamma: ParameterD,
+    beta: ParameterD,
+    epsilon: f32,
+}
+
+impl LayerNorm {
+    /// Returns a builder for creating a [`LayerNorm`].
+    pub fn builder() -> LayerNormBuilder {
+        LayerNormBuilder::new()
+    }
+    /// The shape of the trailing dimensions normalized over.
+    pub fn normalized_shape(&self) -> &[usize] {
+        self.gamma.shape()
+    }
+    /// The epsilon added to the variance.
+    pub fn epsilon(&self) -> f32 {
+        self.epsilon
+    }
+    /// The scale as a mutable parameter view.
+    pub fn gamma_view_mut(&mut self) -> Result<ParameterViewMutD> {
+        self.gamma.make_view_mut()
+    }
+    /// The shift as a mutable parameter view.
+    pub fn beta_view_mut(&mut self) -> Result<ParameterViewMutD> {
+        self.beta.make_view_mut()
+    }
+}
+
+impl Layer for LayerNorm {
+    fn try_for_each_parameter<F, E>(&self, mut f: F) -> Result<(), E>
+    where
+        F: FnMut(ParameterD) -> Result<(), E>,
+    {
+        f(self.gamma.clone())?;
+        f(self.beta.clone())
+    }
+    fn try_for_each_named_parameter<F, E>(&self, mut f: F) -> Result<(), E>
+    where
+        F: FnMut(&str, ParameterD) -> Result<(), E>,
+    {
+        f("gamma", self.gamma.clone())?;
+        f("beta", self.beta.clone())
+    }
+    fn try_for_each_parameter_view_mut<F, E>(&mut self, mut f: F) -> Result<()>
+    where
+        F: FnMut(ParameterViewMutD) -> Result<(), E>,
+        anyhow::Error: From<E>,
+    {
+        f(self.gamma.make_view_mut()?)?;
+        f(self.beta.make_view_mut()?)?;
+        Ok(())
+    }
+    fn cast_mut(&mut self, scalar_type: ScalarType) -> Result<()> {
+        if !matches!(scalar_type, ScalarType::BF16 | ScalarType::F32) {
+            bail!("LayerNorm {scalar_type:?} not implemented!");
+        }
+        self.gamma.cast_mut(scalar_type)?;
+        self.beta.cast_mut(scalar_type)
+    }
+    fn to_device_mut(&mut self, device: Device) -> Result<()> {
+        self.gamma.to_device_mut(device.clone())?;
+        self.beta.to_device_mut(device)
+    }
+}
+
+impl<D: Dimension + 'static> Forward<Variable<D>> for LayerNorm {
+    type Output = Variable<D>;
+    fn forward(&self, input: Variable<D>) -> Result<Self::Output> {
+        let normalized_shape = self.normalized_shape();
+        if !input.shape().ends_with(normalized_shape) {
+            bail!(
+                "LayerNorm expected input with trailing shape {normalized_shape:?}, found {:?}!",
+                input.shape()
+            );
+        }
+        let scalar_type = input.scalar_type();
+        dry::macro_for!($T in [bf16, f32] {
+            if scalar_type == $T::SCALAR_TYPE {
+                return self.forward_impl::<$T, D>(input);
+            }
+        });
+        bail!("LayerNorm {scalar_type:?} not implemented!")
+    }
+}
+
+impl LayerNorm {
+    fn forward_impl<T: Scalar, D: Dimension + 'static>(
+        &self,
+        input: Variable<D>,
+    ) -> Result<Variable<D>> {
+        let dim = input.raw_dim();
+        let cols = self.normalized_shape().iter().product::<usize>();
+        let rows = dim.size() / cols.max(1);
+        let x = TensorView::<T, D>::try_from(input.value().view()).unwrap();
+        let x = x.as_standard_layout()?;
+        let gamma = self.gamma.value().as_standard_layout()?.into_shared()?;
+        let beta = self.beta.value().as_standard_layout()?;
+        let (x_hat, inv_std, output) = layer_norm(
+            x.view().into_shape([rows, cols])?,
+            TensorView::<T, IxDyn>::try_from(gamma.view())
+                .unwrap()
+                .into_shape(cols)?,
+            TensorView::<T, IxDyn>::try_from(beta.view())
+                .unwrap()
+                .into_shape(cols)?,
+            self.epsilon,
+        )?;
+        let output = output.into_shape(dim.clone())?;
+        let x_hat = x_hat.into_shared()?;
+        let mut builder = Variable::builder();
+        if let Some(node) = input.node() {
+            let x_hat = x_hat.clone();
+            builder.edge(node, move |output_grad| {
+                let dy = output_grad.as_standard_layout()?;
+                let dy = TensorView::<T, D>::try_from(dy.view()).unwrap();
+                let dx = layer_norm_backward(
+                    dy.into_shape([rows, cols])?,
+                    x_hat.view(),
+                    inv_std.view(),
+                    TensorView::<T, IxDyn>::try_from(gamma.view())
+                        .unwrap()
+                        .into_shape(cols)?,
+                )?;
+                Ok(dx.into_shape(dim)?.into_shared()?.into())
+            });
+        }
+        let gamma = self.gamma.to_variable();
+        if let Some(node) = gamma.node() {
+            let x_hat = x_hat.clone();
+            let dim = gamma.raw_dim();
+            builder.edge(node, move |output_grad| {
+                let dy = output_grad.as_standard_layout()?;
+                let dy = TensorView::<T, D>::try_from(dy.view()).unwrap();
+                let (dgamma, _) =
+                    layer_norm_param_grads(dy.into_shape([rows, cols])?, x_hat.view())?;
+                Ok(dgamma.into_shape(dim)?.into_shared()?.into())
+            });
+        }
+        let beta = self.beta.to_variable();
+        if let Some(node) = beta.node() {
+            let dim = beta.raw_dim();
+            builder.edge(node, move |output_grad| {
+                let dy = output_grad.as_standard_layout()?;
+                let dy = TensorView::<T, D>::try_from(dy.view()).unwrap();
+                let (_, dbeta) =
+                    layer_norm_param_grads(dy.into_shape([rows, cols])?, x_hat.view())?;
+                Ok(dbeta.into_shape(dim)?.into_shared()?.into())
+            });
+        }
+        Ok(builder.build(output.into_shared()?.into()))
+    }
+}
+
+// Returns x_hat in f32, the inverse standard deviation of each row, and the output.
+fn layer_norm<T: Scalar>(
+    x: TensorView2<T>,
+    gamma: TensorView1<T>,
+    beta: TensorView1<T>,
+    epsilon: f32,
+) -> Result<(Tensor2<f32>, Tensor1<f32>, Tensor2<T>)> {
+    let (rows, cols) = x.dim();
+    if let Some(x) = x.as_array() {
+        let (gamma, beta) = (gamma.as_array().unwrap(), beta.as_array().unwrap());
+        let mut x_hat = x.map(|x| x.cast::<f32>());
+        let mut inv_std = Array1::zeros(rows);
+        for (mut x_hat, inv_std) in x_hat.outer_iter_mut().zip(inv_std.iter_mut()) {
+            let mean = x_hat.mean().unwrap_or_default();
+            x_hat.mapv_inplace(|x| x - mean);
+            let var = x_hat.fold(0., |acc, x| acc + x * x) / cols as f32;
+            *inv_std = 1. / (var + epsilon).sqrt();
+            x_hat.mapv_inplace(|x| x * *inv_std);
+        }
+        let y = Zip::from(&x_hat)
+            .and_broadcast(&gamma)
+            .and_broadcast(&beta)
+            .map_collect(|x_hat, gamma, beta| {
+                (x_hat * gamma.cast::<f32>() + beta.cast::<f32>()).cast::<T>()
+            });
+        return Ok((x_hat.into(), inv_std.into(), y.into()));
+    }
+    #[cfg(not(feature = "device"))]
+    {
+        unreachable!()
+    }
+    #[cfg(feature = "device")]
+    {
+        let device = x.device();
+        let mut mean = unsafe { Tensor::<f32, _>::uninit(device.clone(), rows)? };
+        let mut inv_std = unsafe { Tensor::uninit(device.clone(), rows)? };
+        let mut x_hat = unsafe { Tensor::uninit(device.clone(), [rows, cols])? };
+        let mut y = unsafe { Tensor::<T, _>::uninit(device.clone(), [rows, cols])? };
+        let x = x.as_slice().unwrap();
+        let (gamma, beta) = (gamma.as_standard_layout()?, beta.as_standard_layout()?);
+        let cols = cols.to_u32().unwrap();
+        macro_for!($T in [bf16, f32] {
+            if let Ok(x) = x.as_scalar_slice().try_into() {
+                paste! {
+                    kernels::[<layer_norm_stats_ $T>]::builder()?
+                        .build(device.clone())?
+                        .dispatch(
+                            x,
+                            cols,
+                            epsilon,
+                            mean.as_slice_mut().unwrap(),
+                            inv_std.as_slice_mut().unwrap(),
+                        )?;
+                    kernels::[<layer_norm_ $T>]::builder()?
+                        .build(device)?
+                        .dispatch(
+                            mean.as_slice().unwrap(),
+                            inv_std.as_slice().unwrap(),
+                            gamma.as_slice().unwrap().as_scalar_slice().try_into().unwrap(),
+                            beta.as_slice().unwrap().as_scalar_slice().try_into().unwrap(),
+                            cols,
+                            x,
+                            x_hat.as_slice_mut().unwrap(),
+                            ScalarSliceMut::from(y.as_slice_mut().unwrap()).try_into().unwrap(),
+                        )?;
+                }
+                return Ok((x_hat, inv_std, y));
+            }
+        });
+        bail!("layer_norm {:?} unimplemented!", T::SCALAR_TYPE)
+    }
+}
+
+fn layer_norm_backward<T: Scalar>(
+    dy: TensorView2<T>,
+    x_hat: TensorView2<f32>,
+    inv_std: TensorView1<f32>,
+    gamma: TensorView1<T>,
+) -> Result<Tensor2<T>> {
+    let (rows, cols) = dy.dim();
+    if let Some((dy, x_hat)) = dy.as_array().zip(x_hat.as_array()) {
+        let (inv_std, gamma) = (inv_std.as_array().unwrap(), gamma.as_array().unwrap());
+        let mut dx = Array::from_elem(dy.raw_dim(), T::zero());
+        for (((dy, x_hat), inv_std), mut dx) in dy
+            .outer_iter()
+            .zip(x_hat.outer_iter())
+            .zip(inv_std.iter().copied())
+            .zip(dx.outer_iter_mut())
+        {
+            // gradient of x_hat
+            let dx_hat = Zip::from(&dy)
+                .and(&gamma)
+                .map_collect(|dy, gamma| dy.cast::<f32>() * gamma.cast::<f32>());
+            let dx_mean = dx_hat.mean().unwrap_or_default();
+            let dx_x_hat_mean = dx_hat.dot(&x_hat) / cols as f32;
+            Zip::from(&mut dx)
+                .and(&dx_hat)
+                .and(&x_hat)
+                .for_each(|dx, dx_hat, x_hat| {
+                    *dx =
+                        batch_norm_backward_impl(*dx_hat, *x_hat, inv_std, dx_mean, dx_x_hat_mean)
+                            .cast();
+                });
+        }
+        return Ok(dx.into());
+    }
+    #[cfg(not(feature = "device"))]
+    {
+        unreachable!()
+    }
+    #[cfg(feature = "device")]
+    {
+        let device = dy.device();
+        let mut dx_mean = unsafe { Tensor::<f32, _>::uninit(device.clone(), rows)? };
+        let mut dx_x_hat_mean = unsafe { Tensor::<f32, _>::uninit(device.clone(), rows)? };
+        let mut dx = unsafe { Tensor::<T, _>::uninit(device.clone(), [rows, cols])? };
+        let dy = dy.as_slice().unwrap();
+        let x_hat = x_hat.as_slice().unwrap();
+        let inv_std = inv_std.as_standard_layout()?;
+        let gamma = gamma.as_standard_layout()?;
+        let cols = cols.to_u32().unwrap();
+        macro_for!($T in [bf16, f32] {
+            if let Some((dy, gamma)) = dy
+                .as_scalar_slice()
+                .try_into()
+                .ok()
+                .zip(gamma.as_slice().unwrap().as_scalar_slice().try_into().ok())
+            {
+                paste! {
+                    kernels::[<layer_norm_backward_sums_ $T>]::builder()?
+                        .build(device.clone())?
+                        .dispatch(
+                            dy.clone(),
+                            x_hat.clone(),
+                            gamma.clone(),
+                            cols,
+                            dx_mean.as_slice_mut().unwrap(),
+                            dx_x_hat_mean.as_slice_mut().unwrap(),
+                        )?;
+                    kernels::[<layer_norm_backward_ $T>]::builder()?
+                        .build(device)?
+                        .dispatch(
+                            gamma,
+                            inv_std.as_slice().unwrap(),
+                            dx_mean.as_slice().unwrap(),
+                            dx_x_hat_mean.as_slice().unwrap(),
+                            cols,
+                            x_hat,
+                            dy,
+                            ScalarSliceMut::from(dx.as_slice_mut().unwrap()).try_into().unwrap(),
+                        )?;
+                }
+                return Ok(dx);
+            }
+        });
+        bail!("layer_norm_backward {:?} unimplemented!", T::SCALAR_TYPE)
+    }
+}
+
+// Returns the gradients of gamma and beta.
+fn layer_norm_param_grads<T: Scalar>(
+    dy: TensorView2<T>,
+    x_hat: TensorView2<f32>,
+) -> Result<(Tensor1<T>, Tensor1<T>)> {
+    let (rows, cols) = dy.dim();
+    if let Some((dy, x_hat)) = dy.as_array().zip(x_hat.as_array()) {
+        let dy = dy.map(|dy| dy.cast::<f32>());
+        let dgamma = (&dy * &x_hat).sum_axis(Axis(0)).map(|x| x.cast::<T>());
+        let dbeta = dy.sum_axis(Axis(0)).map(|x| x.cast::<T>());
+        return Ok((dgamma.into(), dbeta.into()));
+    }
+    #[cfg(not(feature = "device"))]
+    {
+        unreachable!()
+    }
+    #[cfg(feature = "device")]
+    {
+        let device = dy.device();
+        let mut dgamma = unsafe { Tensor::<T, _>::uninit(device.clone(), cols)? };
+        let mut dbeta = unsafe { Tensor::<T, _>::uninit(device.clone(), cols)? };
+        let dy = dy.as_slice().unwrap();
+        macro_for!($T in [bf16, f32] {
+            if let Ok(dy) = dy.as_scalar_slice().try_into() {
+                let kernel = paste! {
+                    kernels::[<layer_norm_param_grads_ $T>]::builder()?.build(device)?
+                };
+                kernel.dispatch(
+                    dy,
+                    x_hat.as_slice().unwrap(),
+                    rows.to_u32().unwrap(),
+                    cols.to_u32().unwrap(),
+                    ScalarSliceMut::from(dgamma.as_slice_mut().unwrap()).try_into().unwrap(),
+                    ScalarSliceMut::from(dbeta.as_slice_mut().unwrap()).try_into().unwrap(),
+                )?;
+                return Ok((dgamma, dbeta));
+            }
+        });
+        bail!("layer_norm_param_grads {:?} unimplemented!", T::SCALAR_TYPE)
+    }
+}
+
+/// AvgPool.
+///
+/// Computes the mean of each window of the input.
+///
+/// See [`AvgPool1`] and [`AvgPool2`].
+/// Implemented for bf16 and f32.
+#[derive(Debug, Clone, Layer)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[autograph(crate=crate)]
+pub struct AvgPool<D: Dimension> {
+    #[autograph(skip)]
+    filter: D,
+    #[autograph(skip)]
+    stride: D,
+    #[autograph(skip)]
+    padding_mode: PaddingMode,
+}
+
+/// AvgPool with 1 dimension.
+///
+/// See [`AvgPool`].
+pub type AvgPool1 = AvgPool<Ix1>;
+/// AvgPool with 2 dimensions.
+///
+/// See [`AvgPool`].
+pub type AvgPool2 = AvgPool<Ix2>;
+
+impl<D: Dimension> AvgPool<D> {
+    /// Returns a builder for creating an [`AvgPool`].
+    pub fn builder() -> AvgPoolBuilder<D> {
+        AvgPoolBuilder::new()
+    }
+}
+
+impl Forward<Variable3> for AvgPool1 {
+    type Output = Variable3;
+    fn forward(&self, input: Variable3) -> Result<Self::Output> {
+        let (n, c, ih) = input.dim();
+        let input = input.into_shape([n, c, ih, 1]).map_err(Error::msg)?;
+        let fh = self.filter.into_pattern();
+        let sh = self.stride.into_pattern();
+        let output = AvgPool2 {
+            filter: [fh, 1].into_dimension(),
+            stride: [sh, 1].into_dimension(),
+            padding_mode: self.padding_mode,
+        }
+        .forward(input)?;
+        let (_, _, oh, _) = output.dim();
+        output.into_shape([n, c, oh]).map_err(Error::msg)
+    }
+}
+
+impl Forward<Variable4> for AvgPool2 {
+    type Output = Variable4;
+    fn forward(&self, input: Variable4) -> Result<Self::Output> {
+        let (fh, fw) = self.filter.into_pattern();
+        let (sh, sw) = self.stride.into_pattern();
+        let (_, _, ih, iw) = input.dim();
+        let [padding_h, padding_w] = if self.padding_mode == PaddingMode::Same {
+            [same_padding(ih, fh, sh, 1), same_padding(iw, fw, sw, 1)]
+        } else {
+            [[0, 0], [0, 0]]
+        };
+        let rows = pool_ranges(ih, fh, sh, padding_h)?;
+        let cols = pool_ranges(iw, fw, sw, padding_w)?;
+        avg_pool2(input, rows, cols)
+    }
+}
+
+/// AdaptiveAvgPool with 2 dimensions.
+///
+/// Averages the input \[n, c, h, w\] over regions, producing an output \[n, c, out_h, out_w\].
+/// Region `i` along an axis covers the input range `floor(i * input / output)..ceil((i + 1) *
+/// input / output)`, so the input size does not need to be divisible by the output size.
+///
+/// An `output_size` of \[1, 1\] computes a global average pool.
+///
+/// Implemented for bf16 and f32.
+///
+/// # Example
+///```no_run
+/// # use autograph::anyhow::Result;
+/// # use autograph::learn::neural_network::layer::AdaptiveAvgPool2;
+/// # fn main() -> Result<()> {
+/// // global average pool
+/// let pool = AdaptiveAvgPool2::new([1, 1])?;
+/// # Ok(())
+/// # }
+///```
+#[derive(Debug, Clone, Copy, Layer)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[autograph(crate=crate)]
+pub struct AdaptiveAvgPool2 {
+    #[autograph(skip)]
+    output_size: [usize; 2],
+}
+
+impl AdaptiveAvgPool2 {
+    /// Creates a new [`AdaptiveAvgPool2`] with `output_size` \[out_h, out_w\].
+    ///
+    /// # Errors
+    /// - `output_size` has a 0.
+    pub fn new(output_size: [usize; 2]) -> Result<Self> {
+        if output_size.contains(&0) {
+            bail!("AdaptiveAvgPool2 output_size {output_size:?} must be greater than 0!");
+        }
+        Ok(Self { output_size })
+    }
+    /// The output size \[out_h, out_w\].
+    pub fn output_size(&self) -> [usize; 2] {
+        self.output_size
+    }
+}
+
+impl Forward<Variable4> for AdaptiveAvgPool2 {
+    type Output = Variable4;
+    fn forward(&self, input: Variable4) -> Result<Self::Output> {
+        let (_, _, ih, iw) = input.dim();
+        let [oh, ow] = self.output_size;
+        avg_pool2(
+            input,
+            adaptive_pool_ranges(ih, oh),
+            adaptive_pool_ranges(iw, ow),
+        )
+    }
+}
+
+fn adaptive_pool_ranges(input: usize, output: usize) -> Vec<Range<usize>> {
+    (0..output)
+        .map(|o| (o * input / output)..((o + 1) * input + output - 1) / output)
+        .collect()
+}
+
+/// Returns the input range of each window along an axis, excluding padding.
+fn pool_ranges(
+    input: usize,
+    filter: usize,
+    stride: usize,
+    [p1, p2]: [usize; 2],
+) -> Result<Vec<Range<usize>>> {
+    if filter == 0 || stride == 0 {
+        bail!("AvgPool filter {filter} and stride {stride} must be greater than 0!");
+    }
+    let padded = input + p1 + p2;
+    if padded < filter {
+        bail!("AvgPool filter {filter} is larger than the padded input {padded}!");
+    }
+    let output = (padded - filter) / stride + 1;
+    Ok((0..output)
+        .map(|o| {
+            let start = o * stride;
+            start.saturating_sub(p1)..(start + filter).saturating_sub(p1).min(input)
+        })
+        .collect())
+}
+
+/// Averages `input` over each window of `rows` and `cols`.
+///
+/// The gradient of each output element is distributed evenly across its window.
+fn avg_pool2(
+    input: Variable4,
+    rows: Vec<Range<usize>>,
+    cols: Vec<Range<usize>>,
+) -> Result<Variable4> {
+    let scalar_type = input.scalar_type();
+    dry::macro_for!($T in [bf16, f32] {
+        if scalar_type == $T::SCALAR_TYPE {
+            return avg_pool2_impl::<$T>(input, rows, cols);
+        }
+    });
+    bail!("AvgPool {scalar_type:?} unimplemented!")
+}
+
+fn avg_pool2_impl<T: Scalar>(
+    input: Variable4,
+    rows: Vec<Range<usize>>,
+    cols: Vec<Range<usize>>,
+) -> Result<Variable4> {
+    let (_, _, ih, iw) = input.dim();
+    let x = input.value().as_standard_layout()?;
+    let output = avg_pool2_forward(TensorView4::<T>::try_from(x.view()).unwrap(), &rows, &cols)?;
+    let mut builder = Variable::builder();
+    if let Some(node) = input.node() {
+        builder.edge(node, move |output_grad| {
+            let dy = output_grad.as_standard_layout()?;
+            let dy = TensorView4::<T>::try_from(dy.view()).unwrap();
+            let dx = avg_pool2_backward(dy, [ih, iw], &rows, &cols)?;
+            Ok(dx.into_shared()?.into())
+        });
+    }
+    Ok(builder.build(output.into_shared()?.into()))
+}
+
+// The starts followed by the ends of `ranges`.
+#[cfg(feature = "device")]
+fn pool_ranges_u32(device: Device, ranges: &[Range<usize>]) -> Result<Tensor1<u32>> {
+    let starts = ranges.iter().map(|r| r.start.to_u32().unwrap());
+    let ends = ranges.iter().map(|r| r.end.to_u32().unwrap());
+    Tensor::from(starts.chain(ends).collect::<Vec<_>>()).into_device(device)
+}
+
+fn avg_pool2_forward<T: Scalar>(
+    x: TensorView4<T>,
+    rows: &[Range<usize>],
+    cols: &[Range<usize>],
+) -> Result<Tensor4<T>> {
+    let (n, c, ih, iw) = x.dim();
+    let (oh, ow) = (rows.len(), cols.len());
+    if let Some(x) = x.as_array() {
+        let mut y = Array::from_elem([n, c, oh, ow], T::zero());
+        for (i, rows) in rows.iter().enumerate() {
+            for (j, cols) in cols.iter().enumerate() {
+                let count = (rows.len() * cols.len()).max(1) as f32;
+                let x = x.slice(s![.., .., rows.clone(), cols.clone()]);
+                for ((b, ch), y) in y.slice_mut(s![.., .., i, j]).indexed_iter_mut() {
+                    let sum = x
+                        .slice(s![b, ch, .., ..])
+                        .fold(0f32, |acc, x| acc + x.cast::<f32>());
+                    *y = (sum / count).cast();
+                }
+            }
+        }
+        return Ok(y.into());
+    }
+    #[cfg(not(feature = "device"))]
+    {
+        unreachable!()
+    }
+    #[cfg(feature = "device")]
+    {
+        let device = x.device();
+        let rows = pool_ranges_u32(device.clone(), rows)?;
+        let cols = pool_ranges_u32(device.clone(), cols)?;
+        let mut y = unsafe { Tensor::<T, _>::uninit(device.clone(), [n, c, oh, ow])? };
+        let x = x.as_slice().unwrap();
+        macro_for!($T in [bf16, f32] {
+            if let Ok(x) = x.as_scalar_slice().try_into() {
+                let kernel = paste! {
+                    kernels::[<avg_pool2_ $T>]::builder()?.build(device)?
+                };
+                kernel.dispatch(
+                    x,
+                    rows.as_slice().unwrap(),
+                    cols.as_slice().unwrap(),
+                    ih.to_u32().unwrap(),
+                    iw.to_u32().unwrap(),
+                    oh.to_u32().unwrap(),
+                    ow.to_u32().unwrap(),
+                    ScalarSliceMut::from(y.as_slice_mut().unwrap()).try_into().unwrap(),
+                )?;
+                return Ok(y);
+            }
+        });
+        bail!("avg_pool2 {:?} unimplemented!", T::SCALAR_TYPE)
+    }
+}
+
+fn avg_pool2_backward<T: Scalar>(
+    dy: TensorView4<T>,
+    [ih, iw]: [usize; 2],
+    rows: &[Range<usize>],
+    cols: &[Range<usize>],
+) -> Result<Tensor4<T>> {
+    let (n, c, oh, ow) = dy.dim();
+    if let Some(dy) = dy.as_array() {
+        let mut dx = Array4::<f32>::zeros([n, c, ih, iw]);
+        for (i, rows) in rows.iter().enumerate() {
+            for (j, cols) in cols.iter().enumerate() {
+                let count = (rows.len() * cols.len()).max(1) as f32;
+                let dy = dy
+                    .slice(s![.., .., i, j])
+                    .map(|dy| dy.cast::<f32>() / count)
+                    .insert_axis(Axis(2))
+                    .insert_axis(Axis(3));
+                let mut dx = dx.slice_mut(s![.., .., rows.clone(), cols.clone()]);
+                dx += &dy;
+            }
+        }
+        return Ok(dx.map(|dx| dx.cast::<T>()).into());
+    }
+    #[cfg(not(feature = "device"))]
+    {
+        unreachable!()
+    }
+    #[cfg(feature = "device")]
+    {
+        let device = dy.device();
+        let rows = pool_ranges_u32(device.clone(), rows)?;
+        let cols = pool_ranges_u32(device.clone(), cols)?;
+        let mut dx = unsafe { Tensor::<T, _>::uninit(device.clone(), [n, c, ih, iw])? };
+        let dy = dy.as_slice().unwrap();
+        macro_for!($T in [bf16, f32] {
+            if let Ok(dy) = dy.as_scalar_slice().try_into() {
+                let kernel = paste! {
+                    kernels::[<avg_pool2_backward_ $T>]::builder()?.build(device)?
+                };
+                kernel.dispatch(
+                    dy,
+                    rows.as_slice().unwrap(),
+                    cols.as_slice().unwrap(),
+                    ih.to_u32().unwrap(),
+                    iw.to_u32().unwrap(),
+                    oh.to_u32().unwrap(),
+                    ow.to_u32().unwrap(),
+                    ScalarSliceMut::from(dx.as_slice_mut().unwrap()).try_into().unwrap(),
+                )?;
+                return Ok(dx);
+            }
+        });
+        bail!("avg_pool2_backward {:?} unimplemented!", T::SCALAR_TYPE)
+    }
+}
+
+/// Flatten.
+///
+/// Flattens the axes `start_dim..=end_dim` into one axis, with output dimension `D`. By default,
+/// flattens all axes after the first into 2 dimensions, like
+/// [`Variable::flatten()`](Variable::flatten).
+///
+/// # Example
+///```no_run
+/// # use autograph::learn::neural_network::layer::Flatten;
+/// # use autograph::ndarray::Ix3;
+/// // [n, t, c, h, w] -> [n, t, c * h * w]
+/// let flatten = Flatten::<Ix3>::builder().start_dim(2).build();
+///```
+#[derive(Clone, Copy, Debug, Layer)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[autograph(crate=crate)]
+pub struct Flatten<D: Dimension = Ix2> {
+    #[autograph(skip)]
+    start_dim: usize,
+    #[autograph(skip)]
+    end_dim: Option<usize>,
+    #[autograph(skip)]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    _m: PhantomData<D>,
+}
+
+impl<D: Dimension> Flatten<D> {
+    /// Returns a builder for creating a [`Flatten`].
+    pub fn builder() -> FlattenBuilder<D> {
+        FlattenBuilder::new()
+    }
+    /// The first axis to flatten.
+    pub fn start_dim(&self) -> usize {
+        self.start_dim
+    }
+    /// The last axis to flatten, or None for the last axis.
+    pub fn end_dim(&self) -> Option<usize> {
+        self.end_dim
+    }
+}
+
+impl<D: Dimension> Default for Flatten<D> {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+impl<D1: Dimension + 'static, D2: Dimension> Forward<Variable<D1>> for Flatten<D2> {
+    type Output = Variable<D2>;
+    fn forward(&self, input: Variable<D1>) -> Result<Variable<D2>> {
+        let shape = input.shape();
+        let ndim = shape.len();
+        let start = self.start_dim;
+        let end = if let Some(end_dim) = self.end_dim {
+            if end_dim < start || end_dim >= ndim {
+                bail!("Flatten start_dim {start} end_dim {end_dim} out of bounds for input with shape {shape:?}!");
+            }
+            end_dim + 1
+        } else {
+            ndim.max(start)
+        };
+        // Missing leading axes and an empty range are treated as 1, ie [n] -> [n, 1].
+        let mut dim: Vec<usize> = (0..start)
+            .map(|i| shape.get(i).copied().unwrap_or(1))
+            .collect();
+        dim.push(shape.get(start..end).map_or(1, |s| s.iter().product()));
+        dim.extend_from_slice(&shape[end.min(ndim)..]);
+        if let Some(output_ndim) = D2::NDIM {
+            if dim.len() != output_ndim {
+                bail!(
+                    "Flatten expected output with {output_ndim} dimensions, found {dim:?} from input with shape {shape:?}!"
+                );
+            }
+        }
+        input
+            .into_shape(IxDyn(&dim))
+            .map_err(Error::msg)?
+            .into_dimensionality()
+            .map_err(Error::msg)
+    }
+}
+
+/// PixelShuffle.
+///
+/// Rearranges an input with shape \[n, c * r * r, h, w\] into \[n, c, h * r, w * r\], where
+/// `r` is the upscale factor. Used for efficient upsampling, ie sub-pixel convolution.
+///
+/// See [`PixelUnshuffle`].
+///
+/// # Example
+///```no_run
+/// # use autograph::learn::neural_network::layer::PixelShuffle;
+/// // [n, 12, h, w] -> [n, 3, 2 * h, 2 * w]
+/// let shuffle = PixelShuffle::new(2);
+///```
+#[derive(Clone, Copy, Debug, Layer)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[autograph(crate=crate)]
+pub struct PixelShuffle {
+    #[autograph(skip)]
+    upscale_factor: usize,
+}
+
+impl PixelShuffle {
+    /// Creates a new [`PixelShuffle`] with `upscale_factor`.
+    pub fn new(upscale_factor: usize) -> Self {
+        Self { upscale_factor }
+    }
+    /// The upscale factor.
+    pub fn upscale_factor(&self) -> usize {
+        self.upscale_factor
+    }
+}
+
+impl Forward<Variable4> for PixelShuffle {
+    type Output = Variable4;
+    fn forward(&self, input: Variable4) -> Result<Variable4> {
+        let r = self.upscale_factor;
+        let (n, c, h, w) = input.dim();
+        if r == 0 || c % (r * r) != 0 {
+            bail!("PixelShuffle channels {c} not divisible by upscale_factor {r} squared!");
+        }
+        let c = c / (r * r);
+        input
+            .to_standard_layout()?
+            .into_shape([n, c, r, r, h, w])
+            .map_err(Error::msg)?
+            .permuted_axes([0, 1, 4, 2, 5, 3])
+            .to_standard_layout()?
+            .into_shape([n, c, h * r, w * r])
+            .map_err(Error::msg)
+    }
+}
+
+/// PixelUnshuffle.
+///
+/// The inverse of [`PixelShuffle`], rearranges an input with shape \[n, c, h * r, w * r\]
+/// into \[n, c * r * r, h, w\], where `r` is the downscale factor.
+#[derive(Clone, Copy, Debug, Layer)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[autograph(crate=crate)]
+pub struct PixelUnshuffle {
+    #[autograph(skip)]
+    downscale_factor: usize,
+}
+
+impl PixelUnshuffle {
+    /// Creates a new [`PixelUnshuffle`] with `downscale_factor`.
+    pub fn new(downscale_factor: usize) -> Self {
+        Self { downscale_factor }
+    }
+    /// The downscale factor.
+    pub fn downscale_factor(&self) -> usize {
+        self.downscale_factor
+    }
+}
+
+impl Forward<Variable4> for PixelUnshuffle {
+    type Output = Variable4;
+    fn forward(&self, input: Variable4) -> Result<Variable4> {
+        let r = self.downscale_factor;
+        let (n, c, h, w) = input.dim();
+        if r == 0 || h % r != 0 || w % r != 0 {
+            bail!(
+                "PixelUnshuffle shape {:?} not divisible by downscale_factor {r}!",
+                [h, w]
+            );
+        }
+        let (h, w) = (h / r, w / r);
+        input
+            .to_standard_layout()?
+            .into_shape([n, c, h, r, w, r])
+            .map_err(Error::msg)?
+            .permuted_axes([0, 1, 3, 5, 2, 4])
+            .to_standard_layout()?
+            .into_shape([n, c * r * r, h, w])
+            .map_err(Error::msg)
+    }
+}
+
+/// Dropout.
+///
+/// During training, zeroes each element of the input with probability `p`, and scales the
+/// remaining elements by `1 / (1 - p)`. In eval mode, see [`Layer::set_training()`],
+/// the input is returned unchanged, unless [MC dropout](Dropout::with_mc_dropout) is enabled.
+///
+/// The mask is generated on the device of the input, by hashing the index of each element
+/// with a key drawn from the generator. The backward pass multiplies the gradient by the
+/// same mask.
+///
+/// Implemented for bf16 and f32.
+///
+/// # Example
+///```no_run
+/// # use autograph::anyhow::Result;
+/// # use autograph::learn::neural_network::layer::{Dropout, Layer};
+/// # fn main() -> Result<()> {
+/// // Monte Carlo dropout with reproducible masks.
+/// let mut dropout = Dropout::new(0.5)?.with_seed(0).with_mc_dropout(true);
+/// dropout.set_training(false);
+/// # Ok(())
+/// # }
+///```
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Dropout {
+    p: f32,
+    seed: Option<u64>,
+    training: bool,
+    mc_dropout: bool,
+    // The number of masks generated from the seed.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    step: AtomicU64,
+}
+
+impl Clone for Dropout {
+    fn clone(&self) -> Self {
+        Self {
+            p: self.p,
+            seed: self.seed,
+            training: self.training,
+            mc_dropout: self.mc_dropout,
+            step: AtomicU64::new(self.step.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+impl Dropout {
+    /// Creates a new [`Dropout`] with drop probability `p`.
+    ///
+    /// # Errors
+    /// - `p` is not in the range [0, 1).
+    pub fn new(p: f32) -> Result<Self> {
+        if !(0. ..1.).contains(&p) {
+            bail!("Dropout p {p} must be in the range [0, 1)!");
+        }
+        Ok(Self {
+            p,
+            seed: None,
+            training: true,
+            mc_dropout: false,
+            step: AtomicU64::new(0),
+        })
+    }
+    /// Seeds the random number generator.
+    ///
+    /// The generator advances with each forward pass, so each pass generates a new mask, and
+    /// a new [`Dropout`] with the same seed generates the same sequence of masks. By default,
+    /// the generator is seeded from entropy.
+    pub fn with_seed(self, seed: u64) -> Self {
+        Self {
+            seed: Some(seed),
+            step: AtomicU64::new(0),
+            ..self
+        }
+    }
+    /// Applies dropout in eval mode, for Monte Carlo dropout. Defaults to false.
+    pub fn with_mc_dropout(self, mc_dropout: bool) -> Self {
+        Self { mc_dropout, ..self }
+    }
+    /// The drop probability.
+    pub fn p(&self) -> f32 {
+        self.p
+    }
+    /// The seed.
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+    /// Whether in training mode.
+    pub fn training(&self) -> bool {
+        self.training
+    }
+    /// Whether dropout is applied in eval mode.
+    pub fn mc_dropout(&self) -> bool {
+        self.mc_dropout
+    }
+    fn mask<D: Dimension>(
+        &self,
+        device: Device,
+        dim: D,
+        scalar_type: ScalarType,
+    ) -> Result<ScalarArcTensor<D>> {
+        let mut rng = if let Some(seed) = self.seed {
+            let step = self.step.fetch_add(1, Ordering::Relaxed);
+            let mut rng_seed = <StdRng as SeedableRng>::Seed::default();
+            rng_seed[..8].copy_from_slice(&seed.to_le_bytes());
+            rng_seed[8..16].copy_from_slice(&step.to_le_bytes());
+            StdRng::from_seed(rng_seed)
+        } else {
+            StdRng::from_entropy()
+        };
+        let [key0, key1] = [rng.gen::<u32>(), rng.gen::<u32>()];
+        let p = self.p;
+        let scale = 1. / (1. - p);
+        let len = dim.size();
+        if u32::try_from(len).is_err() {
+            bail!("Dropout len {len} exceeds u32::MAX!");
+        }
+        if device.is_host() {
+            dry::macro_for!($T in [bf16, f32] {
+                if scalar_type == $T::SCALAR_TYPE {
+                    let mask: Vec<$T> = (0..len)
+                        .map(|i| dropout_mask_impl(key0, key1, i as u32, p, scale).cast())
+                        .collect();
+                    let mask = Array::from_shape_vec(dim, mask).unwrap();
+                    return Ok(Tensor::from(mask).into_shared()?.into());
+                }
+            });
+            bail!("Dropout {scalar_type:?} unimplemented!");
+        }
+        #[cfg(not(feature = "device"))]
+        {
+            unreachable!()
+        }
+        #[cfg(feature = "device")]
+        {
+            macro_for!($T in [bf16, f32] {
+                if scalar_type == $T::SCALAR_TYPE {
+                    let mut mask = unsafe { Tensor::<$T, D>::uninit(device.clone(), dim)? };
+                    let kernel = paste! {
+                        kernels::[<dropout_mask_ $T>]::builder()?.build(device)?
+                    };
+                    kernel.dispatch(key0, key1, p, scale, mask.as_slice_mut().unwrap())?;
+                    return Ok(mask.into_shared()?.into());
+                }
+            });
+            bail!("Dropout {scalar_type:?} unimplemented!")
+        }
+    }
+}
+
+impl Layer for Dropout {
+    fn try_for_each_parameter<F, E>(&self, _f: F) -> Result<(), E>
+    where
+        F: FnMut(ParameterD) -> Result<(), E>,
+    {
+        Ok(())
+    }
+    fn try_for_each_parameter_view_mut<F, E>(&mut self, _f: F) -> Result<()>
+    where
+        F: FnMut(ParameterViewMutD) -> Result<(), E>,
+        anyhow::Error: From<E>,
+    {
+        Ok(())
+    }
+    fn cast_mut(&mut self, _scalar_type: ScalarType) -> Result<()> {
+        Ok(())
+    }
+    fn to_device_mut(&mut self, _device: Device) -> Result<()> {
+        Ok(())
+    }
+    /// Sets training or eval mode. Defaults to training.
+    fn set_training(&mut self, training: bool) {
+        self.training = training;
+    }
+}
+
+impl<D: Dimension + 'static> Forward<Variable<D>> for Dropout {
+    type Output = Variable<D>;
+    fn forward(&self, input: Variable<D>) -> Result<Self::Output> {
+        if self.p == 0. || !(self.training || self.mc_dropout) {
+            return Ok(input);
+        }
+        let scalar_type = input.scalar_type();
+        if !matches!(scalar_type, ScalarType::BF16 | ScalarType::F32) {
+            bail!("Dropout {scalar_type:?} unimplemented!");
+        }
+        let mask = self.mask(input.device(), input.raw_dim(), scalar_type)?;
+        let one = ScalarElem::one(scalar_type);
+        let mut builder = Variable::builder();
+        if let Some(node) = input.node() {
+            let mask = mask.clone();
+            builder.edge(node, move |output_grad| {
+                let mut input_grad = output_grad.into_owned()?;
+                input_grad.scaled_mul(one, &mask)?;
+                input_grad.into_shared()
+            });
+        }
+        let mut output = input.into_value().into_owned()?;
+        output.scaled_mul(one, &mask)?;
+        Ok(builder.build(output.into_shared()?))
+    }
+}
+
+/// Identity.
+#[derive(Default, Clone, Copy, Debug, Layer)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[autograph(crate=crate)]
+pub struct Identity;
+
+impl<X> Forward<X> for Identity {
+    type Output = X;
+    fn forward(&self, input: X) -> Result<Self::Output> {
+        Ok(input)
+    }
+}
+
+/// Functional.
+///
+/// Wraps a function `f` as a layer, for inserting ad-hoc transformations like reshapes
+/// into a model without defining a new type.
+///
+/// Has no trainable parameters. Participates in autograd via the operations `f` applies
+/// to its input.
+///
+/// # Example
+///```no_run
+/// # use autograph::learn::neural_network::{autograd::{Variable4, Variable2}, layer::{Functional, Forward}};
+/// # use autograph::anyhow::Error;
+/// # fn main() -> anyhow::Result<()> {
+/// let flatten = Functional::new(|x: Variable4| x.flatten().map_err(Error::msg));
+/// # Ok(())
+/// # }
+///```
+#[derive(Clone, Copy, Layer)]
+#[autograph(skip, crate=crate)]
+pub struct Functional<F> {
+    f: F,
+}
+
+impl<F: Send + Sync> Functional<F> {
+    /// Creates a new [`Functional`] from `f`.
+    pub fn new(f: F) -> Self {
+        Self { f }
+    }
+}
+
+impl<F> Debug for Functional<F> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("Functional").finish_non_exhaustive()
+    }
+}
+
+impl<X, Y, F> Forward<X> for Functional<F>
+where
+    F: Fn(X) -> Result<Y> + Send + Sync,
+{
+    type Output = Y;
+    fn forward(&self, input: X) -> Result<Y> {
+        (self.f)(input)
+    }
+}
+
+/// Checkpointed.
+///
+/// Wraps a layer with gradient checkpointing, trading compute for memory.
+///
+/// The forward pass of `layer` is computed without retaining intermediate activations. During
+/// the backward pass, the forward pass is computed again to regenerate them and the gradients
+/// of the input and parameters are computed from the recomputed graph.
+#[derive(Debug, Clone, Layer)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[autograph(crate=crate)]
+pub struct Checkpointed<L: Layer> {
+    layer: L,
+}
+
+impl<L: Layer> Checkpointed<L> {
+    /// Creates a new [`Checkpointed`] from `layer`.
+    pub fn new(layer: L) -> Self {
+        Self { layer }
+    }
+    /// Borrows the layer.
+    pub fn layer(&self) -> &L {
+        &self.layer
+    }
+    /// Converts into the layer.
+    pub fn into_layer(self) -> L {
+        self.layer
+    }
+}
+
+impl<L, D1, D2> Forward<Variable<D1>> for Checkpointed<L>
+where
+    L: Layer + Forward<Variable<D1>, Output = Variable<D2>> + Clone + Send + Sync + 'static,
+    D1: Dimension + 'static,
+    D2: Dimension + 'static,
+{
+    type Output = Variable<D2>;
+    fn forward(&self, input: Variable<D1>) -> Result<Self::Output> {
+        let mut requires_grad = input.node().is_some();
+        self.layer.for_each_parameter(|parameter| {
+            requires_grad |= parameter.to_variable().node().is_some();
+        });
+        // No graph is recorded, it is rebuilt in the backward pass.
+        let output =
+            no_grad(|| self.layer.forward(Variable::from(input.value().clone())))?.into_value();
+        if !requires_grad {
+            return Ok(output.into());
+        }
+        let mut builder = Variable::builder();
+        let node = if let Some(node) = input.node() {
+            node.clone()
+        } else {
+            Variable::builder()
+                .node()
+                .build(input.value().clone())
+                .node()
+                .unwrap()
+                .clone()
+        };
+        let layer = self.layer.clone();
+        let input = input.into_value();
+        builder.edge(&node, move |output_grad| {
+            let input = Variable::builder().node().build(input);
+            let output = layer.forward(input.clone())?;
+            if let Some(node) = output.node() {
+                node.backward_grad(output_grad)?;
+            }
+            if let Some(input_grad) = input.node().unwrap().grad() {
+                Ok(input_grad)
+            } else {
+                ScalarArcTensor::zeros(input.device(), input.raw_dim(), input.scalar_type())
+            }
+        });
+        Ok(builder.build(output))
+    }
+}
+
+/// Hooked.
+///
+/// Wraps a layer to support [hooks](Layer#hooks), for layers that do not support them
+/// directly like [`Relu`] or custom layers.
+///
+/// An empty `name` refers to this layer, other names are passed to `layer`. Parameters
+/// have the same names as in `layer`.
+///
+/// # Example
+///```no_run
+/// # use autograph::learn::neural_network::{autograd::VariableD, layer::{Hooked, Layer, Relu}};
+/// # use std::sync::Arc;
+/// # fn main() -> anyhow::Result<()> {
+/// let mut relu = Hooked::new(Relu);
+/// relu.register_forward_hook("", Arc::new(|output: &VariableD| println!("{:?}", output.shape())))?;
+/// # Ok(())
+/// # }
+///```
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Hooked<L> {
+    layer: L,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    hooks: Hooks,
+}
+
+impl<L: Layer> Hooked<L> {
+    /// Creates a new [`Hooked`] from `layer`.
+    pub fn new(layer: L) -> Self {
+        Self {
+            layer,
+            hooks: Hooks::default(),
+        }
+    }
+    /// Borrows the layer.
+    pub fn layer(&self) -> &L {
+        &self.layer
+    }
+    /// Converts into the layer.
+    ///
+    /// The hooks are dropped.
+    pub fn into_layer(self) -> L {
+        self.layer
+    }
+}
+
+impl<L: Layer> Layer for Hooked<L> {
+    fn try_for_each_parameter<F, E>(&self, f: F) -> Result<(), E>
+    where
+        F: FnMut(ParameterD) -> Result<(), E>,
+    {
+        self.layer.try_for_each_parameter(f)
+    }
+    fn try_for_each_named_parameter<F, E>(&self, f: F) -> Result<(), E>
+    where
+        F: FnMut(&str, ParameterD) -> Result<(), E>,
+    {
+        self.layer.try_for_each_named_parameter(f)
+    }
+    fn try_for_each_parameter_view_mut<F, E>(&mut self, f: F) -> Result<()>
+    where
+        F: FnMut(ParameterViewMutD) -> Result<(), E>,
+        anyhow::Error: From<E>,
+    {
+        self.layer.try_for_each_parameter_view_mut(f)
+    }
+    fn cast_mut(&mut self, scalar_type: ScalarType) -> Result<()> {
+        self.layer.cast_mut(scalar_type)
+    }
+    fn to_device_mut(&mut self, device: Device) -> Result<()> {
+        self.layer.to_device_mut(device)
+    }
+    fn set_training(&mut self, training: bool) {
+        self.layer.set_training(training);
+    }
+    fn register_forward_hook(&mut self, name: &str, hook: ForwardHook) -> Result<()> {
+        if name.is_empty() {
+            self.hooks.register_forward_hook("Hooked", name, hook)
+        } else {
+            self.layer.register_forward_hook(name, hook)
+        }
+    }
+    fn register_backward_hook(&mut self, name: &str, hook: BackwardHook) -> Result<()> {
+        if name.is_empty() {
+            self.hooks.register_backward_hook("Hooked", name, hook)
+        } else {
+            self.layer.register_backward_hook(name, hook)
+        }
+    }
+}
+
+impl<L, D1, D2> Forward<Variable<D1>> for Hooked<L>
+where
+    L: Forward<Variable<D1>, Output = Variable<D2>>,
+    D1: Dimension,
+    D2: Dimension,
+{
+    type Output = Variable<D2>;
+    fn forward(&self, input: Variable<D1>) -> Result<Self::Output> {
+        Ok(self.hooks.output(self.layer.forward(input)?))
+    }
+}
+
+/// ReLU.
+///
+/// Implemented for bf16 and f32.
+#[derive(Default, Clone, Copy, Debug, Layer)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[autograph(crate=crate)]
+pub struct Relu;
+
+impl<D: Dimension + 'static> Forward<Variable<D>> for Relu {
+    type Output = Variable<D>;
+    fn forward(&self, input: Variable<D>) -> Result<Self::Output> {
+        let mut builder = Variable::builder();
+        if let Some(node) = input.node() {
+            let input = input.value().clone();
+            builder.edge(node, move |output_grad| {
+                scalar_relu_backward(input, output_grad)
+            });
+        }
+        let output = scalar_relu(input.into_value())?;
+        Ok(builder.build(output))
+    }
+}
+
+/// Sigmoid.
+///
+/// Computes `1 / (1 + exp(-x))`.
+///
+/// Implemented for bf16 and f32.
+#[derive(Default, Clone, Copy, Debug, Layer)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[autograph(crate=crate)]
+pub struct Sigmoid;
+
+impl<D: Dimension + 'static> Forward<Variable<D>> for Sigmoid {
+    type Output = Variable<D>;
+    fn forward(&self, input: Variable<D>) -> Result<Self::Output> {
+        activation(Activation::Sigmoid, input)
+    }
+}
+
+/// Tanh.
+///
+/// Computes the hyperbolic tangent.
+///
+/// Implemented for bf16 and f32.
+#[derive(Default, Clone, Copy, Debug, Layer)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[autograph(crate=crate)]
+pub struct Tanh;
+
+impl<D: Dimension + 'static> Forward<Variable<D>> for Tanh {
+    type Output = Variable<D>;
+    fn forward(&self, input: Variable<D>) -> Result<Self::Output> {
+        activation(Activation::Tanh, input)
+    }
+}
+
+/// Softmax.
+///
+/// Computes exp(x) / sum(exp(x)) along an axis, subtracting the max for numerical stability.
+///
+/// Implemented for bf16, f16, f32, and f64. Computed in f32 on the device of the input.
+///
+/// # Example
+///```no_run
+/// # use autograph::learn::neural_network::layer::Softmax;
+/// // normalize each row
+/// let softmax = Softmax::builder().axis(1).build();
+///```
+#[derive(Default, Clone, Copy, Debug, Layer)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[autograph(crate=crate)]
+pub struct Softmax {
+    #[autograph(skip)]
+    axis: Option<usize>,
+}
+
+impl Softmax {
+    /// Returns a builder for creating a [`Softmax`].
+    pub fn builder() -> SoftmaxBuilder {
+        SoftmaxBuilder::new()
+    }
+    /// The axis, or None for the last axis.
+    pub fn axis(&self) -> Option<usize> {
+        self.axis
+    }
+}
+
+impl Forward<Variable2> for Softmax {
+    type Output = Variable2;
+    fn forward(&self, input: Variable2) -> Result<Self::Output> {
+        let axis = self.axis.unwrap_or(1);
+        if axis >= 2 {
+            bail!("Softmax axis {axis} is out of bounds for 2 dimensions!");
+        }
+        softmax(input, Axis(axis), false)
+    }
+}
+
+/// LogSoftmax.
+///
+/// Computes x - max(x) - ln(sum(exp(x - max(x)))) along an axis, the log of [`Softmax`].
+/// Combined with [`NllLoss`](crate::learn::criterion::NllLoss), equivalent to
+/// [`CrossEntropyLoss`](crate::learn::criterion::CrossEntropyLoss).
+///
+/// Implemented for bf16, f16, f32, and f64. Computed in f32 on the device of the input.
+///
+/// # Example
+///```no_run
+/// # use autograph::learn::neural_network::layer::LogSoftmax;
+/// // log probabilities of each row
+/// let log_softmax = LogSoftmax::builder().axis(1).build();
+///```
+#[derive(Default, Clone, Copy, Debug, Layer)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[autograph(crate=crate)]
+pub struct LogSoftmax {
+    #[autograph(skip)]
+    axis: Option<usize>,
+}
+
+impl LogSoftmax {
+    /// Returns a builder for creating a [`LogSoftmax`].
+    pub fn builder() -> LogSoftmaxBuilder {
+        LogSoftmaxBuilder::new()
+    }
+    /// The axis, or None for the last axis.
+    pub fn axis(&self) -> Option<usize> {
+        self.axis
+    }
+}
+
+impl Forward<Variable2> for LogSoftmax {
+    type Output = Variable2;
+    fn forward(&self, input: Variable2) -> Result<Self::Output> {
+        let axis = self.axis.unwrap_or(1);
+        if axis >= 2 {
+            bail!("LogSoftmax axis {axis} is out of bounds for 2 dimensions!");
+        }
+        softmax(input, Axis(axis), true)
     }
 }
 
-/// Flatten.
-///
-/// See [`Variable::flatten()`](Variable::flatten).
-#[derive(Default, Clone, Copy, Debug, Layer)]
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[autograph(crate=crate)]
-pub struct Flatten;
-
-impl<D: Dimension + 'static> Forward<Variable<D>> for Flatten {
-    type Output = Variable2;
-    fn forward(&self, input: Variable<D>) -> Result<Variable2> {
-        input.flatten().map_err(Error::msg)
+// Softmax, or LogSoftmax if `log`.
+fn softmax(input: Variable2, axis: Axis, log: bool) -> Result<Variable2> {
+    let output = scalar_softmax(input.value().clone(), axis, log)?;
+    let mut builder = Variable::builder();
+    if let Some(node) = input.node() {
+        let output = output.clone();
+        builder.edge(node, move |output_grad| {
+            scalar_softmax_backward(output, output_grad, axis, log)
+        });
     }
+    Ok(builder.build(output))
 }
 
-/// Identity.
-#[derive(Default, Clone, Copy, Debug, Layer)]
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[autograph(crate=crate)]
-pub struct Identity;
+#[derive(Clone, Copy, Debug)]
+enum Activation {
+    Sigmoid,
+    Tanh,
+}
 
-impl<X> Forward<X> for Identity {
-    type Output = X;
-    fn forward(&self, input: X) -> Result<Self::Output> {
-        Ok(input)
+// Applies an activation whose gradient is computed from the output.
+fn activation<D: Dimension + 'static>(
+    activation: Activation,
+    input: Variable<D>,
+) -> Result<Variable<D>> {
+    let output = scalar_activation(activation, input.value().clone())?;
+    let mut builder = Variable::builder();
+    if let Some(node) = input.node() {
+        let output = output.clone();
+        builder.edge(node, move |output_grad| {
+            scalar_activation_backward(activation, output, output_grad)
+        });
     }
+    Ok(builder.build(output))
 }
 
-/// ReLU.
-///
-/// Implemented for bf16 and f32.
-#[derive(Default, Clone, Copy, Debug, Layer)]
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[autograph(crate=crate)]
-pub struct Relu;
+fn scalar_activation<D: Dimension>(
+    activation: Activation,
+    input: ScalarArcTensor<D>,
+) -> Result<ScalarArcTensor<D>> {
+    let input = if input.device().is_device() && !input.is_standard_layout() {
+        input.to_standard_layout_shared()?
+    } else {
+        input
+    };
+    let scalar_type = input.scalar_type();
+    macro_for!($T in [bf16, f32] {
+        if scalar_type == $T::SCALAR_TYPE {
+            let output = activation_impl::<$T, D>(activation, input.view().try_into().unwrap())?;
+            return Ok(output.into_shared()?.into());
+        }
+    });
+    bail!("{activation:?} {scalar_type:?} unimplemented!")
+}
 
-impl<D: Dimension + 'static> Forward<Variable<D>> for Relu {
-    type Output = Variable<D>;
-    fn forward(&self, input: Variable<D>) -> Result<Self::Output> {
-        let mut builder = Variable::builder();
-        if let Some(node) = input.node() {
-            let input = input.value().clone();
-            builder.edge(node, move |output_grad| {
-                scalar_relu_backward(input, output_grad)
-            });
+fn activation_impl<T: Scalar, D: Dimension>(
+    activation: Activation,
+    input: TensorView<T, D>,
+) -> Result<Tensor<T, D>> {
+    if let Some(x) = input.as_array() {
+        let f: fn(T) -> T = match activation {
+            Activation::Sigmoid => sigmoid_impl::<T>,
+            Activation::Tanh => tanh_impl::<T>,
+        };
+        let y =
+            if 2 * x.len() * size_of::<T>() > parallel_size() && rayon::current_num_threads() > 1 {
+                ndarray::Zip::from(&x).par_map_collect(|x| f(*x))
+            } else {
+                x.map(|x| f(*x))
+            };
+        return Ok(y.into());
+    }
+    #[cfg(not(feature = "device"))]
+    {
+        unreachable!()
+    }
+    #[cfg(feature = "device")]
+    {
+        let device = input.device();
+        macro_for!($T in [bf16, f32] {
+            if let Ok(x) = input.as_slice().unwrap().as_scalar_slice().try_into() {
+                let mut output = unsafe { Tensor::<$T, D>::uninit(device.clone(), input.raw_dim())? };
+                let y = output.as_slice_mut().unwrap();
+                match activation {
+                    Activation::Sigmoid => paste! {
+                        kernels::[<sigmoid_ $T>]::builder()?
+                            .build(device)?
+                            .dispatch(x, y)?
+                    },
+                    Activation::Tanh => paste! {
+                        kernels::[<tanh_ $T>]::builder()?
+                            .build(device)?
+                            .dispatch(x, y)?
+                    },
+                }
+                return Ok(output.cast_into().unwrap());
+            }
+        });
+        bail!("{activation:?} {:?} unimplemented!", T::SCALAR_TYPE)
+    }
+}
+
+fn scalar_activation_backward<D: Dimension>(
+    activation: Activation,
+    output: ScalarArcTensor<D>,
+    output_grad: ScalarArcTensor<D>,
+) -> Result<ScalarArcTensor<D>> {
+    let scalar_type = output.scalar_type();
+    if output_grad.scalar_type() != scalar_type {
+        bail!(
+            "{activation:?} backward {scalar_type:?} != {:?}!",
+            output_grad.scalar_type()
+        );
+    }
+    let (output, output_grad) = if output.device().is_device() {
+        (
+            output.to_standard_layout_shared()?,
+            output_grad.to_standard_layout_shared()?,
+        )
+    } else {
+        (output, output_grad)
+    };
+    macro_for!($T in [bf16, f32] {
+        if scalar_type == $T::SCALAR_TYPE {
+            let input_grad = activation_backward_impl::<$T, D>(
+                activation,
+                output.view().try_into().unwrap(),
+                output_grad.view().try_into().unwrap(),
+            )?;
+            return Ok(input_grad.into_shared()?.into());
         }
-        let output = scalar_relu(input.into_value())?;
-        Ok(builder.build(output))
+    });
+    bail!("{activation:?} backward {scalar_type:?} unimplemented!")
+}
+
+fn activation_backward_impl<T: Scalar, D: Dimension>(
+    activation: Activation,
+    output: TensorView<T, D>,
+    output_grad: TensorView<T, D>,
+) -> Result<Tensor<T, D>> {
+    if let Some((y, dy)) = output.as_array().zip(output_grad.as_array()) {
+        let f: fn(T, T) -> T = match activation {
+            Activation::Sigmoid => sigmoid_backward_impl::<T>,
+            Activation::Tanh => tanh_backward_impl::<T>,
+        };
+        let dx = if (y.len() + dy.len()) * size_of::<T>() > parallel_size()
+            && rayon::current_num_threads() > 1
+        {
+            ndarray::Zip::from(&y)
+                .and(&dy)
+                .par_map_collect(|y, dy| f(*y, *dy))
+        } else {
+            ndarray::Zip::from(&y)
+                .and(&dy)
+                .map_collect(|y, dy| f(*y, *dy))
+        };
+        return Ok(dx.into());
+    }
+    #[cfg(not(feature = "device"))]
+    {
+        unreachable!()
+    }
+    #[cfg(feature = "device")]
+    {
+        let device = output.device();
+        let y = output.as_slice().unwrap();
+        let dy = output_grad.as_slice().unwrap();
+        macro_for!($T in [bf16, f32] {
+            if let Some((y, dy)) = y
+                .as_scalar_slice()
+                .try_into()
+                .ok()
+                .zip(dy.as_scalar_slice().try_into().ok())
+            {
+                let mut input_grad = unsafe { Tensor::<$T, D>::uninit(device.clone(), output.raw_dim())? };
+                let dx = input_grad.as_slice_mut().unwrap();
+                match activation {
+                    Activation::Sigmoid => paste! {
+                        kernels::[<sigmoid_backward_ $T>]::builder()?
+                            .build(device)?
+                            .dispatch(y, dy, dx)?
+                    },
+                    Activation::Tanh => paste! {
+                        kernels::[<tanh_backward_ $T>]::builder()?
+                            .build(device)?
+                            .dispatch(y, dy, dx)?
+                    },
+                }
+                return Ok(input_grad.cast_into().unwrap());
+            }
+        });
+        bail!(
+            "{activation:?} backward {:?} unimplemented!",
+            T::SCALAR_TYPE
+        )
     }
 }
 
@@ -1509,17 +6305,92 @@ fn relu_backward<T: Scalar, D: Dimension>(
     }
 }
 
+// Computed in f32 with device reductions, like the host, and cast back to the input type.
+fn scalar_softmax(x: ScalarArcTensor2, axis: Axis, log: bool) -> Result<ScalarArcTensor2> {
+    let scalar_type = x.scalar_type();
+    if !matches!(
+        scalar_type,
+        ScalarType::BF16 | ScalarType::F16 | ScalarType::F32 | ScalarType::F64
+    ) {
+        bail!("Softmax {scalar_type:?} unimplemented!");
+    }
+    let x = x.view().cast_into_tensor::<f32>()?;
+    let y = softmax_tensor(x.view(), axis, log)?;
+    ScalarTensor::from(y).cast_into(scalar_type)?.into_shared()
+}
+
+// y = exp(x - max(x)) / sum(exp(x - max(x))), or y = x - max(x) - ln(sum(exp(x - max(x)))) if log
+fn softmax_tensor(x: TensorView2<f32>, axis: Axis, log: bool) -> Result<Tensor2<f32>> {
+    let dim = softmax_reduced_dim(x.raw_dim(), axis);
+    let max = x.max_axis(axis)?.into_shape(dim)?;
+    let mut y = x.to_owned()?;
+    y.scaled_add(-1., &max)?;
+    if log {
+        let log_sum = y.exp()?.sum_axis(axis)?.ln()?.into_shape(dim)?;
+        y.scaled_add(-1., &log_sum)?;
+        Ok(y)
+    } else {
+        let y = y.exp()?;
+        let sum = y.sum_axis(axis)?.into_shape(dim)?;
+        y.div(&sum)
+    }
+}
+
+// The shape of a reduction along `axis`, keeping the axis with length 1 to broadcast.
+fn softmax_reduced_dim(mut dim: Ix2, Axis(axis): Axis) -> Ix2 {
+    dim[axis] = 1;
+    dim
+}
+
+fn scalar_softmax_backward(
+    y: ScalarArcTensor2,
+    dy: ScalarArcTensor2,
+    axis: Axis,
+    log: bool,
+) -> Result<ScalarArcTensor2> {
+    let scalar_type = y.scalar_type();
+    if dy.scalar_type() != scalar_type {
+        bail!("Softmax {scalar_type:?} != {:?}!", dy.scalar_type());
+    }
+    let y = y.view().cast_into_tensor::<f32>()?;
+    let dy = dy.view().cast_into_tensor::<f32>()?;
+    let dx = softmax_backward_tensor(y.view(), dy.view(), axis, log)?;
+    ScalarTensor::from(dx).cast_into(scalar_type)?.into_shared()
+}
+
+// dx = y * (dy - sum(dy * y)), or dx = dy - exp(y) * sum(dy) if log
+fn softmax_backward_tensor(
+    y: TensorView2<f32>,
+    dy: TensorView2<f32>,
+    axis: Axis,
+    log: bool,
+) -> Result<Tensor2<f32>> {
+    let dim = softmax_reduced_dim(y.raw_dim(), axis);
+    let mut dx = dy.to_owned()?;
+    if log {
+        let sum = dy.sum_axis(axis)?.into_shape(dim)?;
+        dx.scaled_add(-1., &y.exp()?.mul(&sum)?)?;
+        Ok(dx)
+    } else {
+        let dot = dy.mul(&y)?.sum_axis(axis)?.into_shape(dim)?;
+        dx.scaled_add(-1., &dot)?;
+        dx.mul(&y)
+    }
+}
+
 #[cfg_attr(feature = "device", module)]
 mod kernels {
     #[cfg(any(feature = "device", target_arch = "spirv"))]
     use dry::macro_for;
     #[cfg(not(target_arch = "spirv"))]
     use krnl::krnl_core;
-    #[cfg(target_arch = "spirv")]
-    use krnl_core::half::bf16;
     #[cfg(any(feature = "device", target_arch = "spirv"))]
     use krnl_core::macros::kernel;
+    #[cfg(target_arch = "spirv")]
+    use krnl_core::num_traits::Float;
     use krnl_core::scalar::Scalar;
+    #[cfg(target_arch = "spirv")]
+    use krnl_core::{buffer::UnsafeIndex, half::bf16};
     #[cfg(any(feature = "device", target_arch = "spirv"))]
     use paste::paste;
 
@@ -1539,9 +6410,477 @@ mod kernels {
         }
     }
 
+    pub fn sigmoid_impl<T: Scalar>(x: T) -> T {
+        let x = x.cast::<f32>();
+        (1. / (1. + (-x).exp())).cast()
+    }
+
+    pub fn sigmoid_backward_impl<T: Scalar>(y: T, dy: T) -> T {
+        let (y, dy) = (y.cast::<f32>(), dy.cast::<f32>());
+        (dy * y * (1. - y)).cast()
+    }
+
+    pub fn tanh_impl<T: Scalar>(x: T) -> T {
+        x.cast::<f32>().tanh().cast()
+    }
+
+    pub fn tanh_backward_impl<T: Scalar>(y: T, dy: T) -> T {
+        let (y, dy) = (y.cast::<f32>(), dy.cast::<f32>());
+        (dy * (1. - y * y)).cast()
+    }
+
+    pub fn batch_norm_impl(x: f32, mean: f32, var: f32, epsilon: f32) -> f32 {
+        (x - mean) / (var + epsilon).sqrt()
+    }
+
+    pub fn batch_norm_backward_impl(
+        dy: f32,
+        x_hat: f32,
+        scale: f32,
+        dy_mean: f32,
+        dy_x_hat_mean: f32,
+    ) -> f32 {
+        scale * (dy - dy_mean - x_hat * dy_x_hat_mean)
+    }
+
+    pub fn embedding_backward_impl<T: Scalar>(dw: T, dy: T) -> T {
+        (dw.cast::<f32>() + dy.cast::<f32>()).cast()
+    }
+
+    pub fn batch_norm_running_impl(running: f32, x: f32, momentum: f32) -> f32 {
+        (1. - momentum) * running + momentum * x
+    }
+
+    /// Hashes `x` with the PCG output permutation.
+    pub fn pcg_hash(x: u32) -> u32 {
+        let state = x.wrapping_mul(747796405).wrapping_add(2891336453);
+        let word = ((state >> ((state >> 28) + 4)) ^ state).wrapping_mul(277803737);
+        (word >> 22) ^ word
+    }
+
+    /// The [`Dropout`](super::Dropout) mask of element `idx`, 0 with probability `p` and
+    /// `scale` otherwise.
+    pub fn dropout_mask_impl(key0: u32, key1: u32, idx: u32, p: f32, scale: f32) -> f32 {
+        let x = pcg_hash(pcg_hash(idx ^ key0) ^ key1);
+        // 24 bits are exactly representable in f32.
+        let u = (x >> 8) as f32 * (1. / (1u32 << 24) as f32);
+        if u < p {
+            0.
+        } else {
+            scale
+        }
+    }
+
+    /// Quantizes `x` to int8 with `scale`, see [`quantize_rows()`](super::quantize_rows).
+    pub fn quantize_impl(x: f32, scale: f32) -> i8 {
+        if scale > 0. {
+            (x / scale).round().clamp(-127., 127.) as i8
+        } else {
+            0
+        }
+    }
+
+    #[cfg(any(feature = "device", target_arch = "spirv"))]
+    #[kernel]
+    pub fn quantize_scales_f32(#[global] x: Slice<f32>, cols: u32, #[item] scale: &mut f32) {
+        let cols = cols as usize;
+        let start = kernel.item_id() * cols;
+        let mut m = 0f32;
+        for i in start..start + cols {
+            m = m.max(x[i].abs());
+        }
+        *scale = m / 127.;
+    }
+
+    #[cfg(any(feature = "device", target_arch = "spirv"))]
+    #[kernel]
+    pub fn quantize_f32(
+        #[global] scales: Slice<f32>,
+        cols: u32,
+        #[item] x: f32,
+        #[item] y: &mut i8,
+    ) {
+        *y = quantize_impl(x, scales[kernel.item_id() / cols as usize]);
+    }
+
+    #[cfg(any(feature = "device", target_arch = "spirv"))]
+    #[kernel]
+    pub fn quantized_dot_f32(
+        #[global] x: Slice<i8>,
+        #[global] x_scales: Slice<f32>,
+        #[global] w: Slice<i8>,
+        #[global] w_scales: Slice<f32>,
+        #[global] bias: Slice<f32>,
+        k: u32,
+        n: u32,
+        #[item] y: &mut f32,
+    ) {
+        let (k, n) = (k as usize, n as usize);
+        let idx = kernel.item_id();
+        let (row, col) = (idx / n, idx % n);
+        let mut acc = 0i32;
+        for i in 0..k {
+            acc += x[row * k + i] as i32 * w[col * k + i] as i32;
+        }
+        *y = acc as f32 * x_scales[row] * w_scales[col] + bias[col];
+    }
+
+    #[cfg(any(feature = "device", target_arch = "spirv"))]
+    #[kernel]
+    pub fn batch_norm_update_running(
+        momentum: f32,
+        unbiased: f32,
+        #[item] mean: f32,
+        #[item] var: f32,
+        #[item] running_mean: &mut f32,
+        #[item] running_var: &mut f32,
+    ) {
+        *running_mean = batch_norm_running_impl(*running_mean, mean, momentum);
+        *running_var = batch_norm_running_impl(*running_var, var * unbiased, momentum);
+    }
+
     #[cfg(any(feature = "device", target_arch = "spirv"))]
     macro_for!($T in [bf16, f32] {
         paste! {
+            #[allow(clippy::too_many_arguments)]
+            #[kernel]
+            pub fn [<avg_pool2_ $T>](
+                #[global] x: Slice<$T>,
+                #[global] rows: Slice<u32>,
+                #[global] cols: Slice<u32>,
+                ih: u32,
+                iw: u32,
+                oh: u32,
+                ow: u32,
+                #[item] y: &mut $T,
+            ) {
+                let idx = kernel.item_id() as u32;
+                let bcid = idx / (oh * ow);
+                let hwid = idx % (oh * ow);
+                let hid = hwid / ow;
+                let wid = hwid % ow;
+                let [r0, r1] = [rows[hid as usize], rows[(oh + hid) as usize]];
+                let [c0, c1] = [cols[wid as usize], cols[(ow + wid) as usize]];
+                let x_start = bcid * ih * iw;
+                let mut acc = 0f32;
+                for row in r0..r1 {
+                    for col in c0..c1 {
+                        acc += x[(x_start + row * iw + col) as usize].cast::<f32>();
+                    }
+                }
+                let count = ((r1 - r0) * (c1 - c0)).max(1) as f32;
+                *y = (acc / count).cast();
+            }
+
+            #[allow(clippy::too_many_arguments)]
+            #[kernel]
+            pub fn [<avg_pool2_backward_ $T>](
+                #[global] dy: Slice<$T>,
+                #[global] rows: Slice<u32>,
+                #[global] cols: Slice<u32>,
+                ih: u32,
+                iw: u32,
+                oh: u32,
+                ow: u32,
+                #[item] dx: &mut $T,
+            ) {
+                let idx = kernel.item_id() as u32;
+                let bcid = idx / (ih * iw);
+                let hwid = idx % (ih * iw);
+                let row = hwid / iw;
+                let col = hwid % iw;
+                let dy_start = bcid * oh * ow;
+                let mut acc = 0f32;
+                for hid in 0..oh {
+                    let [r0, r1] = [rows[hid as usize], rows[(oh + hid) as usize]];
+                    if r0 <= row && row < r1 {
+                        for wid in 0..ow {
+                            let [c0, c1] = [cols[wid as usize], cols[(ow + wid) as usize]];
+                            if c0 <= col && col < c1 {
+                                let count = ((r1 - r0) * (c1 - c0)) as f32;
+                                acc += dy[(dy_start + hid * ow + wid) as usize].cast::<f32>() / count;
+                            }
+                        }
+                    }
+                }
+                *dx = acc.cast();
+            }
+
+            #[kernel]
+            pub fn [<embedding_ $T>](
+                #[global] weight: Slice<$T>,
+                #[global] indices: Slice<u32>,
+                num_embeddings: u32,
+                embedding_dim: u32,
+                #[item] y: &mut $T,
+            ) {
+                let embedding_dim = embedding_dim as usize;
+                let idx = kernel.item_id();
+                let (row, col) = (idx / embedding_dim, idx % embedding_dim);
+                let index = indices[row];
+                *y = if index < num_embeddings {
+                    weight[index as usize * embedding_dim + col]
+                } else {
+                    $T::zero()
+                };
+            }
+
+            #[kernel]
+            pub fn [<embedding_backward_ $T>](
+                #[global] indices: Slice<u32>,
+                #[global] dy: Slice<$T>,
+                num_embeddings: u32,
+                embedding_dim: u32,
+                padding_idx: u32,
+                #[global] dw: UnsafeSlice<$T>,
+            ) {
+                let index = kernel.global_id() as u32;
+                if index >= num_embeddings {
+                    return;
+                }
+                let embedding_dim = embedding_dim as usize;
+                let start = index as usize * embedding_dim;
+                for col in 0..embedding_dim {
+                    unsafe {
+                        *dw.unsafe_index_mut(start + col) = $T::zero();
+                    }
+                }
+                if index == padding_idx {
+                    return;
+                }
+                for row in 0..indices.len() {
+                    if indices[row] == index {
+                        for col in 0..embedding_dim {
+                            let dw = unsafe { dw.unsafe_index_mut(start + col) };
+                            *dw = embedding_backward_impl(*dw, dy[row * embedding_dim + col]);
+                        }
+                    }
+                }
+            }
+
+            #[kernel]
+            pub fn [<layer_norm_stats_ $T>](
+                #[global] x: Slice<$T>,
+                cols: u32,
+                epsilon: f32,
+                #[item] mean: &mut f32,
+                #[item] inv_std: &mut f32,
+            ) {
+                let cols = cols as usize;
+                let start = kernel.item_id() * cols;
+                let mut sum = 0f32;
+                for i in start..start + cols {
+                    sum += x[i].cast::<f32>();
+                }
+                let m = sum / cols as f32;
+                let mut sum_sq = 0f32;
+                for i in start..start + cols {
+                    let d = x[i].cast::<f32>() - m;
+                    sum_sq += d * d;
+                }
+                *mean = m;
+                *inv_std = 1. / (sum_sq / cols as f32 + epsilon).sqrt();
+            }
+
+            #[allow(clippy::too_many_arguments)]
+            #[kernel]
+            pub fn [<layer_norm_ $T>](
+                #[global] mean: Slice<f32>,
+                #[global] inv_std: Slice<f32>,
+                #[global] gamma: Slice<$T>,
+                #[global] beta: Slice<$T>,
+                cols: u32,
+                #[item] x: $T,
+                #[item] x_hat: &mut f32,
+                #[item] y: &mut $T,
+            ) {
+                let cols = cols as usize;
+                let idx = kernel.item_id();
+                let (row, col) = (idx / cols, idx % cols);
+                *x_hat = (x.cast::<f32>() - mean[row]) * inv_std[row];
+                *y = (*x_hat * gamma[col].cast::<f32>() + beta[col].cast::<f32>()).cast();
+            }
+
+            #[kernel]
+            pub fn [<layer_norm_backward_sums_ $T>](
+                #[global] dy: Slice<$T>,
+                #[global] x_hat: Slice<f32>,
+                #[global] gamma: Slice<$T>,
+                cols: u32,
+                #[item] dx_mean: &mut f32,
+                #[item] dx_x_hat_mean: &mut f32,
+            ) {
+                let cols = cols as usize;
+                let start = kernel.item_id() * cols;
+                let mut acc = 0f32;
+                let mut acc_x_hat = 0f32;
+                for col in 0..cols {
+                    let dx_hat = dy[start + col].cast::<f32>() * gamma[col].cast::<f32>();
+                    acc += dx_hat;
+                    acc_x_hat += dx_hat * x_hat[start + col];
+                }
+                *dx_mean = acc / cols as f32;
+                *dx_x_hat_mean = acc_x_hat / cols as f32;
+            }
+
+            #[allow(clippy::too_many_arguments)]
+            #[kernel]
+            pub fn [<layer_norm_backward_ $T>](
+                #[global] gamma: Slice<$T>,
+                #[global] inv_std: Slice<f32>,
+                #[global] dx_mean: Slice<f32>,
+                #[global] dx_x_hat_mean: Slice<f32>,
+                cols: u32,
+                #[item] x_hat: f32,
+                #[item] dy: $T,
+                #[item] dx: &mut $T,
+            ) {
+                let cols = cols as usize;
+                let idx = kernel.item_id();
+                let (row, col) = (idx / cols, idx % cols);
+                let dx_hat = dy.cast::<f32>() * gamma[col].cast::<f32>();
+                *dx = batch_norm_backward_impl(
+                    dx_hat,
+                    x_hat,
+                    inv_std[row],
+                    dx_mean[row],
+                    dx_x_hat_mean[row],
+                )
+                .cast();
+            }
+
+            #[kernel]
+            pub fn [<layer_norm_param_grads_ $T>](
+                #[global] dy: Slice<$T>,
+                #[global] x_hat: Slice<f32>,
+                rows: u32,
+                cols: u32,
+                #[item] dgamma: &mut $T,
+                #[item] dbeta: &mut $T,
+            ) {
+                let (rows, cols) = (rows as usize, cols as usize);
+                let col = kernel.item_id();
+                let mut acc_gamma = 0f32;
+                let mut acc_beta = 0f32;
+                for row in 0..rows {
+                    let idx = row * cols + col;
+                    let dy = dy[idx].cast::<f32>();
+                    acc_gamma += dy * x_hat[idx];
+                    acc_beta += dy;
+                }
+                *dgamma = acc_gamma.cast();
+                *dbeta = acc_beta.cast();
+            }
+
+            #[kernel]
+            pub fn [<batch_norm2_stats_ $T>](
+                #[global] x: Slice<$T>,
+                n: u32,
+                c: u32,
+                hw: u32,
+                #[item] mean: &mut f32,
+                #[item] var: &mut f32,
+            ) {
+                let (n, c, hw) = (n as usize, c as usize, hw as usize);
+                let channel = kernel.item_id();
+                let len = (n * hw) as f32;
+                let mut sum = 0f32;
+                for b in 0..n {
+                    let start = (b * c + channel) * hw;
+                    for i in start..start + hw {
+                        sum += x[i].cast::<f32>();
+                    }
+                }
+                let m = sum / len;
+                let mut sum_sq = 0f32;
+                for b in 0..n {
+                    let start = (b * c + channel) * hw;
+                    for i in start..start + hw {
+                        let d = x[i].cast::<f32>() - m;
+                        sum_sq += d * d;
+                    }
+                }
+                *mean = m;
+                *var = sum_sq / len;
+            }
+
+            #[allow(clippy::too_many_arguments)]
+            #[kernel]
+            pub fn [<batch_norm2_ $T>](
+                #[global] mean: Slice<f32>,
+                #[global] var: Slice<f32>,
+                #[global] gamma: Slice<$T>,
+                #[global] beta: Slice<$T>,
+                c: u32,
+                hw: u32,
+                epsilon: f32,
+                #[item] x: $T,
+                #[item] x_hat: &mut f32,
+                #[item] y: &mut $T,
+            ) {
+                let channel = (kernel.item_id() / hw as usize) % c as usize;
+                *x_hat = batch_norm_impl(x.cast(), mean[channel], var[channel], epsilon);
+                *y = (*x_hat * gamma[channel].cast::<f32>() + beta[channel].cast::<f32>()).cast();
+            }
+
+            #[kernel]
+            pub fn [<batch_norm2_sums_ $T>](
+                #[global] dy: Slice<$T>,
+                #[global] x_hat: Slice<f32>,
+                n: u32,
+                c: u32,
+                hw: u32,
+                #[item] dy_sum: &mut f32,
+                #[item] dy_x_hat_sum: &mut f32,
+            ) {
+                let (n, c, hw) = (n as usize, c as usize, hw as usize);
+                let channel = kernel.item_id();
+                let mut acc = 0f32;
+                let mut acc_x_hat = 0f32;
+                for b in 0..n {
+                    let start = (b * c + channel) * hw;
+                    for i in start..start + hw {
+                        let dy = dy[i].cast::<f32>();
+                        acc += dy;
+                        acc_x_hat += dy * x_hat[i];
+                    }
+                }
+                *dy_sum = acc;
+                *dy_x_hat_sum = acc_x_hat;
+            }
+
+            #[allow(clippy::too_many_arguments)]
+            #[kernel]
+            pub fn [<batch_norm2_backward_ $T>](
+                #[global] gamma: Slice<$T>,
+                #[global] var: Slice<f32>,
+                #[global] dy_sum: Slice<f32>,
+                #[global] dy_x_hat_sum: Slice<f32>,
+                c: u32,
+                hw: u32,
+                epsilon: f32,
+                len: f32,
+                training: u32,
+                #[item] x_hat: f32,
+                #[item] dy: $T,
+                #[item] dx: &mut $T,
+            ) {
+                let channel = (kernel.item_id() / hw as usize) % c as usize;
+                let scale = gamma[channel].cast::<f32>() / (var[channel] + epsilon).sqrt();
+                let (dy_mean, dy_x_hat_mean) = if training != 0 {
+                    (dy_sum[channel] / len, dy_x_hat_sum[channel] / len)
+                } else {
+                    (0., 0.)
+                };
+                *dx = batch_norm_backward_impl(dy.cast(), x_hat, scale, dy_mean, dy_x_hat_mean)
+                    .cast();
+            }
+
+            #[kernel]
+            pub fn [<dropout_mask_ $T>](key0: u32, key1: u32, p: f32, scale: f32, #[item] y: &mut $T) {
+                *y = dropout_mask_impl(key0, key1, kernel.item_id() as u32, p, scale).cast();
+            }
+
             #[kernel]
             pub fn [<relu_mut_ $T>](#[item] x: &mut $T) {
                 *x = relu_impl(*x);
@@ -1561,7 +6900,31 @@ mod kernels {
             pub fn [<relu_backward_ $T>](#[item] x: $T, #[item] dy: $T, #[item] dx: &mut $T) {
                 *dx = relu_backward_impl(x, dy);
             }
+
+            #[kernel]
+            pub fn [<sigmoid_ $T>](#[item] x: $T, #[item] y: &mut $T) {
+                *y = sigmoid_impl(x);
+            }
+
+            #[kernel]
+            pub fn [<sigmoid_backward_ $T>](#[item] y: $T, #[item] dy: $T, #[item] dx: &mut $T) {
+                *dx = sigmoid_backward_impl(y, dy);
+            }
+
+            #[kernel]
+            pub fn [<tanh_ $T>](#[item] x: $T, #[item] y: &mut $T) {
+                *y = tanh_impl(x);
+            }
+
+            #[kernel]
+            pub fn [<tanh_backward_ $T>](#[item] y: $T, #[item] dy: $T, #[item] dx: &mut $T) {
+                *dx = tanh_backward_impl(y, dy);
+            }
         }
     });
 }
-use kernels::{relu_backward_impl, relu_impl};
+use kernels::{
+    batch_norm_backward_impl, batch_norm_impl, batch_norm_running_impl, dropout_mask_impl,
+    embedding_backward_impl, quantize_impl, relu_backward_impl, relu_impl, sigmoid_backward_impl,
+    sigmoid_impl, tanh_backward_impl, tanh_impl,
+};
@@ -6,7 +6,7 @@ use crate::tensor::{
     TensorViewMutD,
 };
 #[cfg(feature = "device")]
-use crate::tensor::{ScalarTensorView, ScalarTensorViewMut};
+use crate::tensor::{ScalarTensorView, ScalarTensorViewMut, Tensor};
 use anyhow::{bail, Result};
 #[cfg(feature = "device")]
 use dry::macro_for;
@@ -17,7 +17,7 @@ use krnl::{
     device::Device,
     scalar::{Scalar, ScalarElem, ScalarType},
 };
-use ndarray::Zip;
+use ndarray::{Array, Zip};
 #[cfg(feature = "device")]
 use paste::paste;
 #[cfg(feature = "serde")]
@@ -101,6 +101,236 @@ pub mod builder {
             SGD { momentum }
         }
     }
+
+    /// Builder for creating a [`RMSprop`].
+    pub struct RMSpropBuilder {
+        alpha: f32,
+        epsilon: f32,
+        momentum: Option<f32>,
+        centered: bool,
+    }
+
+    impl RMSpropBuilder {
+        pub(super) fn new() -> Self {
+            Self {
+                alpha: 0.99,
+                epsilon: 1e-8,
+                momentum: None,
+                centered: false,
+            }
+        }
+        /// Sets the decay rate of the average of the squared gradient. Defaults to 0.99.
+        pub fn alpha(self, alpha: f32) -> Self {
+            Self { alpha, ..self }
+        }
+        /// Sets the epsilon added to the denominator. Defaults to 1e-8.
+        pub fn epsilon(self, epsilon: f32) -> Self {
+            Self { epsilon, ..self }
+        }
+        /// Momentum. Default is 0.
+        ///
+        /// If `momentum` is greater than 0, a "momentum_buffer" tensor will
+        /// be added to the [`State`] of each [`Parameter`].
+        pub fn momentum(self, momentum: f32) -> Self {
+            Self {
+                momentum: Some(momentum),
+                ..self
+            }
+        }
+        /// Normalizes by the estimated variance of the gradient. Defaults to false.
+        ///
+        /// If true, a "grad_avg" tensor will be added to the [`State`] of each [`Parameter`].
+        pub fn centered(self, centered: bool) -> Self {
+            Self { centered, ..self }
+        }
+        /// Builds the optimizer.
+        ///
+        /// # Errors
+        /// - `alpha` is not in the range [0, 1).
+        pub fn build(self) -> Result<RMSprop> {
+            let Self {
+                alpha,
+                epsilon,
+                momentum,
+                centered,
+            } = self;
+            if !(0. ..1.).contains(&alpha) {
+                bail!("RMSprop alpha {alpha} must be in the range [0, 1)!");
+            }
+            Ok(RMSprop {
+                alpha,
+                epsilon,
+                momentum: momentum.filter(|momentum| *momentum > 0.),
+                centered,
+            })
+        }
+    }
+
+    /// Builder for creating a [`RAdam`].
+    pub struct RAdamBuilder {
+        beta1: f32,
+        beta2: f32,
+        epsilon: f32,
+    }
+
+    impl RAdamBuilder {
+        pub(super) fn new() -> Self {
+            Self {
+                beta1: 0.9,
+                beta2: 0.999,
+                epsilon: 1e-8,
+            }
+        }
+        /// Sets the decay rate of the first moment. Defaults to 0.9.
+        pub fn beta1(self, beta1: f32) -> Self {
+            Self { beta1, ..self }
+        }
+        /// Sets the decay rate of the second moment. Defaults to 0.999.
+        pub fn beta2(self, beta2: f32) -> Self {
+            Self { beta2, ..self }
+        }
+        /// Sets the epsilon added to the denominator. Defaults to 1e-8.
+        pub fn epsilon(self, epsilon: f32) -> Self {
+            Self { epsilon, ..self }
+        }
+        /// Builds the optimizer.
+        ///
+        /// # Errors
+        /// - `beta1` or `beta2` is not in the range [0, 1).
+        pub fn build(self) -> Result<RAdam> {
+            let Self {
+                beta1,
+                beta2,
+                epsilon,
+            } = self;
+            if !(0. ..1.).contains(&beta1) || !(0. ..1.).contains(&beta2) {
+                bail!("RAdam beta1 {beta1} and beta2 {beta2} must be in the range [0, 1)!");
+            }
+            Ok(RAdam {
+                beta1,
+                beta2,
+                epsilon,
+            })
+        }
+    }
+
+    /// Builder for creating an [`Adam`].
+    pub struct AdamBuilder {
+        beta1: f32,
+        beta2: f32,
+        epsilon: f32,
+        weight_decay: f32,
+    }
+
+    impl AdamBuilder {
+        pub(super) fn new() -> Self {
+            Self {
+                beta1: 0.9,
+                beta2: 0.999,
+                epsilon: 1e-8,
+                weight_decay: 0.,
+            }
+        }
+        /// Sets the decay rate of the first moment. Defaults to 0.9.
+        pub fn beta1(self, beta1: f32) -> Self {
+            Self { beta1, ..self }
+        }
+        /// Sets the decay rate of the second moment. Defaults to 0.999.
+        pub fn beta2(self, beta2: f32) -> Self {
+            Self { beta2, ..self }
+        }
+        /// Sets the epsilon added to the denominator. Defaults to 1e-8.
+        pub fn epsilon(self, epsilon: f32) -> Self {
+            Self { epsilon, ..self }
+        }
+        /// Sets the L2 penalty, added to the gradient. Defaults to 0.
+        pub fn weight_decay(self, weight_decay: f32) -> Self {
+            Self {
+                weight_decay,
+                ..self
+            }
+        }
+        /// Builds the optimizer.
+        ///
+        /// # Errors
+        /// - `beta1` or `beta2` is not in the range [0, 1).
+        pub fn build(self) -> Result<Adam> {
+            let Self {
+                beta1,
+                beta2,
+                epsilon,
+                weight_decay,
+            } = self;
+            if !(0. ..1.).contains(&beta1) || !(0. ..1.).contains(&beta2) {
+                bail!("Adam beta1 {beta1} and beta2 {beta2} must be in the range [0, 1)!");
+            }
+            Ok(Adam {
+                beta1,
+                beta2,
+                epsilon,
+                weight_decay,
+            })
+        }
+    }
+
+    /// Builder for creating a [`Lamb`].
+    pub struct LambBuilder {
+        beta1: f32,
+        beta2: f32,
+        epsilon: f32,
+        weight_decay: f32,
+    }
+
+    impl LambBuilder {
+        pub(super) fn new() -> Self {
+            Self {
+                beta1: 0.9,
+                beta2: 0.999,
+                epsilon: 1e-6,
+                weight_decay: 0.,
+            }
+        }
+        /// Sets the decay rate of the first moment. Defaults to 0.9.
+        pub fn beta1(self, beta1: f32) -> Self {
+            Self { beta1, ..self }
+        }
+        /// Sets the decay rate of the second moment. Defaults to 0.999.
+        pub fn beta2(self, beta2: f32) -> Self {
+            Self { beta2, ..self }
+        }
+        /// Sets the epsilon added to the denominator. Defaults to 1e-6.
+        pub fn epsilon(self, epsilon: f32) -> Self {
+            Self { epsilon, ..self }
+        }
+        /// Sets the decoupled weight decay, added to the update. Defaults to 0.
+        pub fn weight_decay(self, weight_decay: f32) -> Self {
+            Self {
+                weight_decay,
+                ..self
+            }
+        }
+        /// Builds the optimizer.
+        ///
+        /// # Errors
+        /// - `beta1` or `beta2` is not in the range [0, 1).
+        pub fn build(self) -> Result<Lamb> {
+            let Self {
+                beta1,
+                beta2,
+                epsilon,
+                weight_decay,
+            } = self;
+            if !(0. ..1.).contains(&beta1) || !(0. ..1.).contains(&beta2) {
+                bail!("Lamb beta1 {beta1} and beta2 {beta2} must be in the range [0, 1)!");
+            }
+            Ok(Lamb {
+                beta1,
+                beta2,
+                epsilon,
+                weight_decay,
+            })
+        }
+    }
 }
 use builder::*;
 
@@ -156,6 +386,13 @@ impl<'a> ValueMut<'a> {
             panic!("Expected tensor!")
         }
     }
+    fn unwrap_elem(self) -> &'a mut ScalarElem {
+        if let Self::Elem(elem) = self {
+            elem
+        } else {
+            panic!("Expected elem!")
+        }
+    }
 }
 
 /// Optimizer State.
@@ -499,23 +736,824 @@ fn sgd_update_with_momentum<T: Scalar>(
     }
 }
 
+/// RMSprop.
+///
+/// Divides the gradient by the root of the running average of the squared gradient.
+///
+/// The [`State`] of each [`Parameter`] has the "square_avg" tensor, and "grad_avg" and
+/// "momentum_buffer" tensors if [centered](RMSpropBuilder::centered) and with
+/// [momentum](RMSpropBuilder::momentum). The tensors are allocated on the first update,
+/// on the device and with the scalar type of the parameter.
+///
+/// Implemented for bf16 and f32.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RMSprop {
+    alpha: f32,
+    epsilon: f32,
+    momentum: Option<f32>,
+    centered: bool,
+}
+
+impl Default for RMSprop {
+    fn default() -> Self {
+        Self::builder().build().unwrap()
+    }
+}
+
+impl RMSprop {
+    /// An RMSprop builder.
+    pub fn builder() -> RMSpropBuilder {
+        RMSpropBuilder::new()
+    }
+    fn state_keys(&self) -> impl Iterator<Item = &'static str> {
+        std::iter::once("square_avg")
+            .chain(self.centered.then_some("grad_avg"))
+            .chain(self.momentum.is_some().then_some("momentum_buffer"))
+    }
+    fn init_state(&self, parameter: &mut ParameterViewMutD) -> Result<()> {
+        if let Some(state) = parameter.optimizer_state() {
+            if state.id() == TypeId::of::<Self>()
+                && state.iter().map(|(key, _)| key).eq(self.state_keys())
+            {
+                return Ok(());
+            }
+        }
+        let mut key_values = Vec::new();
+        for key in self.state_keys() {
+            let tensor = ScalarTensor::zeros(
+                parameter.device(),
+                parameter.raw_dim(),
+                parameter.scalar_type(),
+            )?;
+            key_values.push((
+                key.to_string(),
+                Value::Tensor(
+                    TensorValue::builder(tensor)
+                        .parameter_device(true)
+                        .parameter_type(true)
+                        .build(),
+                ),
+            ));
+        }
+        parameter.init_optimizer_state("RMSprop", TypeId::of::<Self>(), key_values)
+    }
+}
+
+/// Implemented for bf16 and f32.
+impl Optimizer for RMSprop {
+    fn update(&self, learning_rate: f32, mut parameter: ParameterViewMutD) -> Result<()> {
+        let scalar_type = parameter.scalar_type();
+        if !matches!(scalar_type, ScalarType::BF16 | ScalarType::F32) {
+            bail!("RMSprop {scalar_type:?} unimplemented!");
+        }
+        self.init_state(&mut parameter)?;
+        if let Some(grad) = parameter.take_grad() {
+            let (value, state) = parameter.value_view_optimizer_state_mut();
+            let mut state = state.unwrap().iter_mut();
+            let square_avg = state.next().unwrap().1.unwrap_tensor();
+            let grad_avg = self
+                .centered
+                .then(|| state.next().unwrap().1.unwrap_tensor());
+            let momentum_buffer = self
+                .momentum
+                .map(|_| state.next().unwrap().1.unwrap_tensor());
+            let options = RMSpropUpdateOptions {
+                learning_rate,
+                alpha: self.alpha,
+                epsilon: self.epsilon,
+                momentum: self.momentum.unwrap_or_default(),
+            };
+            let grad = grad.view();
+            match scalar_type {
+                ScalarType::BF16 => rmsprop_update::<bf16>(
+                    value.try_into().unwrap(),
+                    grad.try_into().unwrap(),
+                    square_avg.try_into().unwrap(),
+                    grad_avg.map(|x| x.try_into().unwrap()),
+                    momentum_buffer.map(|x| x.try_into().unwrap()),
+                    &options,
+                )?,
+                ScalarType::F32 => rmsprop_update::<f32>(
+                    value.try_into().unwrap(),
+                    grad.try_into().unwrap(),
+                    square_avg.try_into().unwrap(),
+                    grad_avg.map(|x| x.try_into().unwrap()),
+                    momentum_buffer.map(|x| x.try_into().unwrap()),
+                    &options,
+                )?,
+                _ => unreachable!(),
+            }
+        }
+        Ok(())
+    }
+}
+
+struct RMSpropUpdateOptions {
+    learning_rate: f32,
+    alpha: f32,
+    epsilon: f32,
+    momentum: f32,
+}
+
+fn rmsprop_update<T: Scalar>(
+    mut value: TensorViewMutD<T>,
+    grad: TensorViewD<T>,
+    mut square_avg: TensorViewMutD<T>,
+    mut grad_avg: Option<TensorViewMutD<T>>,
+    mut momentum_buffer: Option<TensorViewMutD<T>>,
+    options: &RMSpropUpdateOptions,
+) -> Result<()> {
+    // The state is on the device of the parameter.
+    let on_host = value.device().is_host() && grad.device().is_host();
+    if on_host {
+        let &RMSpropUpdateOptions {
+            learning_rate,
+            alpha,
+            epsilon,
+            momentum,
+        } = options;
+        let f = |value: &mut T,
+                 grad: &T,
+                 square_avg: &mut T,
+                 grad_avg: Option<&mut T>,
+                 momentum_buffer: Option<&mut T>| {
+            let mut w = value.cast::<f32>();
+            let mut v = square_avg.cast::<f32>();
+            let mut g = grad_avg.as_ref().map_or(0., |x| x.cast::<f32>());
+            let mut b = momentum_buffer.as_ref().map_or(0., |x| x.cast::<f32>());
+            kernels::rmsprop_update(
+                &mut w,
+                grad.cast::<f32>(),
+                learning_rate,
+                alpha,
+                epsilon,
+                momentum,
+                grad_avg.is_some(),
+                momentum_buffer.is_some(),
+                &mut v,
+                &mut g,
+                &mut b,
+            );
+            *value = w.cast();
+            *square_avg = v.cast();
+            if let Some(grad_avg) = grad_avg {
+                *grad_avg = g.cast();
+            }
+            if let Some(momentum_buffer) = momentum_buffer {
+                *momentum_buffer = b.cast();
+            }
+        };
+        let parallel =
+            value.len() * 5 * size_of::<T>() > parallel_size() && rayon::current_num_threads() > 1;
+        let zip = Zip::from(value.as_array_mut().unwrap())
+            .and(grad.as_array().unwrap())
+            .and(square_avg.as_array_mut().unwrap());
+        macro_rules! zip_for_each {
+            ($zip:expr, $f:expr) => {
+                if parallel {
+                    $zip.par_for_each($f);
+                } else {
+                    $zip.for_each($f);
+                }
+            };
+        }
+        match (
+            grad_avg.as_mut().map(|x| x.as_array_mut().unwrap()),
+            momentum_buffer.as_mut().map(|x| x.as_array_mut().unwrap()),
+        ) {
+            (Some(grad_avg), Some(momentum_buffer)) => {
+                zip_for_each!(zip.and(grad_avg).and(momentum_buffer), |w, g, v, a, b| f(
+                    w,
+                    g,
+                    v,
+                    Some(a),
+                    Some(b)
+                ))
+            }
+            (Some(grad_avg), None) => {
+                zip_for_each!(zip.and(grad_avg), |w, g, v, a| f(w, g, v, Some(a), None))
+            }
+            (None, Some(momentum_buffer)) => {
+                zip_for_each!(zip.and(momentum_buffer), |w, g, v, b| f(
+                    w,
+                    g,
+                    v,
+                    None,
+                    Some(b)
+                ))
+            }
+            (None, None) => zip_for_each!(zip, |w, g, v| f(w, g, v, None, None)),
+        }
+        return Ok(());
+    }
+    #[cfg(not(feature = "device"))]
+    {
+        unreachable!()
+    }
+    #[cfg(feature = "device")]
+    {
+        let &RMSpropUpdateOptions {
+            learning_rate,
+            alpha,
+            epsilon,
+            momentum,
+        } = options;
+        macro_for!($T in [bf16, f32] {
+            if value.scalar_type() == $T::SCALAR_TYPE {
+                let mut value = ScalarTensorViewMut::from(value)
+                    .try_into_tensor_view_mut::<$T>()
+                    .unwrap();
+                let grad = ScalarTensorView::from(grad)
+                    .try_into_tensor_view::<$T>()
+                    .unwrap();
+                let mut square_avg = ScalarTensorViewMut::from(square_avg)
+                    .try_into_tensor_view_mut::<$T>()
+                    .unwrap();
+                let mut grad_avg = grad_avg.map(|x| {
+                    ScalarTensorViewMut::from(x)
+                        .try_into_tensor_view_mut::<$T>()
+                        .unwrap()
+                });
+                let mut momentum_buffer = momentum_buffer.map(|x| {
+                    ScalarTensorViewMut::from(x)
+                        .try_into_tensor_view_mut::<$T>()
+                        .unwrap()
+                });
+                let device = value.device();
+                let w = value.as_slice_mut().unwrap();
+                let dw = grad.as_slice().unwrap();
+                let v = square_avg.as_slice_mut().unwrap();
+                match (
+                    grad_avg.as_mut().map(|x| x.as_slice_mut().unwrap()),
+                    momentum_buffer.as_mut().map(|x| x.as_slice_mut().unwrap()),
+                ) {
+                    (Some(g), Some(b)) => {
+                        let kernel = paste! {
+                            kernels::[<rmsprop_update_centered_momentum_ $T>]::builder()?
+                            .build(device)?
+                        };
+                        kernel.dispatch(w, dw, learning_rate, alpha, epsilon, momentum, v, g, b)?;
+                    }
+                    (Some(g), None) => {
+                        let kernel = paste! {
+                            kernels::[<rmsprop_update_centered_ $T>]::builder()?
+                            .build(device)?
+                        };
+                        kernel.dispatch(w, dw, learning_rate, alpha, epsilon, v, g)?;
+                    }
+                    (None, Some(b)) => {
+                        let kernel = paste! {
+                            kernels::[<rmsprop_update_momentum_ $T>]::builder()?
+                            .build(device)?
+                        };
+                        kernel.dispatch(w, dw, learning_rate, alpha, epsilon, momentum, v, b)?;
+                    }
+                    (None, None) => {
+                        let kernel = paste! {
+                            kernels::[<rmsprop_update_ $T>]::builder()?
+                            .build(device)?
+                        };
+                        kernel.dispatch(w, dw, learning_rate, alpha, epsilon, v)?;
+                    }
+                }
+                return Ok(());
+            }
+        });
+        unreachable!()
+    }
+}
+
+// Initializes the state of adaptive moment optimizers.
+//
+// The state has the keys "step", "moment1", and "moment2".
+fn init_moment_state<O: 'static>(name: &str, parameter: &mut ParameterViewMutD) -> Result<()> {
+    if let Some(state) = parameter.optimizer_state() {
+        if state.id() == TypeId::of::<O>() {
+            return Ok(());
+        }
+    }
+    let mut key_values = vec![("step".to_string(), Value::Elem(ScalarElem::U32(0)))];
+    for key in ["moment1", "moment2"] {
+        let moment = ScalarTensor::zeros(
+            parameter.device(),
+            parameter.raw_dim(),
+            parameter.scalar_type(),
+        )?;
+        key_values.push((
+            key.to_string(),
+            Value::Tensor(
+                TensorValue::builder(moment)
+                    .parameter_device(true)
+                    .parameter_type(true)
+                    .build(),
+            ),
+        ));
+    }
+    parameter.init_optimizer_state(name, TypeId::of::<O>(), key_values)
+}
+
+/// Rectified Adam.
+///
+/// Adam with the variance of the adaptive learning rate rectified, see
+/// [On the Variance of the Adaptive Learning Rate and Beyond](https://arxiv.org/abs/1908.03265).
+/// In early steps, when the variance is intractable, updates with the bias corrected first moment
+/// like SGD with momentum. See [`.rectification()`](RAdam::rectification).
+///
+/// The [`State`] of each [`Parameter`] has the step count "step", and the "moment1" and
+/// "moment2" tensors.
+///
+/// Implemented for bf16 and f32.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct RAdam {
+    beta1: f32,
+    beta2: f32,
+    epsilon: f32,
+}
+
+impl Default for RAdam {
+    fn default() -> Self {
+        Self::builder().build().unwrap()
+    }
+}
+
+impl RAdam {
+    /// A RAdam builder.
+    pub fn builder() -> RAdamBuilder {
+        RAdamBuilder::new()
+    }
+    /// The rectification term for `step`, starting at 1.
+    ///
+    /// Returns None when the length of the approximated simple moving average is not greater
+    /// than 5, in which case the adaptive learning rate is not applied.
+    pub fn rectification(&self, step: u32) -> Option<f32> {
+        let beta2 = self.beta2 as f64;
+        let t = step as f64;
+        let rho_inf = 2. / (1. - beta2) - 1.;
+        let beta2_t = beta2.powf(t);
+        let rho_t = rho_inf - 2. * t * beta2_t / (1. - beta2_t);
+        if rho_t > 5. {
+            let r =
+                ((rho_t - 4.) * (rho_t - 2.) * rho_inf) / ((rho_inf - 4.) * (rho_inf - 2.) * rho_t);
+            Some(r.sqrt() as f32)
+        } else {
+            None
+        }
+    }
+}
+
+/// Implemented for bf16 and f32.
+impl Optimizer for RAdam {
+    fn update(&self, learning_rate: f32, parameter: ParameterViewMutD) -> Result<()> {
+        moment_update::<Self>("RAdam", parameter, |t| AdamUpdateOptions {
+            learning_rate,
+            beta1: self.beta1,
+            beta2: self.beta2,
+            epsilon: self.epsilon,
+            weight_decay: 0.,
+            step: t,
+            rectification: self.rectification(t),
+            trust_ratio: false,
+        })
+    }
+}
+
+/// Adam.
+///
+/// See [Adam: A Method for Stochastic Optimization](https://arxiv.org/abs/1412.6980).
+///
+/// The [`State`] of each [`Parameter`] has the step count "step", and the "moment1" and
+/// "moment2" tensors, like [`RAdam`]. The moments are allocated on the first update, on the
+/// device and with the scalar type of the parameter.
+///
+/// Implemented for bf16 and f32.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Adam {
+    beta1: f32,
+    beta2: f32,
+    epsilon: f32,
+    weight_decay: f32,
+}
+
+impl Default for Adam {
+    fn default() -> Self {
+        Self::builder().build().unwrap()
+    }
+}
+
+impl Adam {
+    /// An Adam builder.
+    pub fn builder() -> AdamBuilder {
+        AdamBuilder::new()
+    }
+}
+
+/// Implemented for bf16 and f32.
+impl Optimizer for Adam {
+    fn update(&self, learning_rate: f32, parameter: ParameterViewMutD) -> Result<()> {
+        moment_update::<Self>("Adam", parameter, |t| AdamUpdateOptions {
+            learning_rate,
+            beta1: self.beta1,
+            beta2: self.beta2,
+            epsilon: self.epsilon,
+            weight_decay: self.weight_decay,
+            step: t,
+            rectification: Some(1.),
+            trust_ratio: false,
+        })
+    }
+}
+
+/// Layer-wise Adaptive Moments optimizer for Batch training.
+///
+/// Computes the Adam update for each parameter and scales it by the trust ratio
+/// `||w|| / ||update||`, see [Large Batch Optimization for Deep Learning](https://arxiv.org/abs/1904.00962).
+/// The trust ratio is 1 if either norm is 0.
+///
+/// The [`State`] of each [`Parameter`] has the step count "step", and the "moment1" and
+/// "moment2" tensors, like [`RAdam`].
+///
+/// Implemented for bf16 and f32.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Lamb {
+    beta1: f32,
+    beta2: f32,
+    epsilon: f32,
+    weight_decay: f32,
+}
+
+impl Default for Lamb {
+    fn default() -> Self {
+        Self::builder().build().unwrap()
+    }
+}
+
+impl Lamb {
+    /// A Lamb builder.
+    pub fn builder() -> LambBuilder {
+        LambBuilder::new()
+    }
+}
+
+/// Implemented for bf16 and f32.
+impl Optimizer for Lamb {
+    fn update(&self, learning_rate: f32, parameter: ParameterViewMutD) -> Result<()> {
+        moment_update::<Self>("Lamb", parameter, |t| AdamUpdateOptions {
+            learning_rate,
+            beta1: self.beta1,
+            beta2: self.beta2,
+            epsilon: self.epsilon,
+            weight_decay: self.weight_decay,
+            step: t,
+            rectification: Some(1.),
+            trust_ratio: true,
+        })
+    }
+}
+
+// Increments the step and updates the parameter with the options for the step.
+fn moment_update<O: 'static>(
+    name: &str,
+    mut parameter: ParameterViewMutD,
+    options: impl FnOnce(u32) -> AdamUpdateOptions,
+) -> Result<()> {
+    let scalar_type = parameter.scalar_type();
+    if !matches!(scalar_type, ScalarType::BF16 | ScalarType::F32) {
+        bail!("{name} {scalar_type:?} unimplemented!");
+    }
+    init_moment_state::<O>(name, &mut parameter)?;
+    if let Some(grad) = parameter.take_grad() {
+        let (value, state) = parameter.value_view_optimizer_state_mut();
+        let mut state = state.unwrap().iter_mut();
+        let ScalarElem::U32(step) = state.next().unwrap().1.unwrap_elem() else {
+            bail!("{name} expected step to be U32!");
+        };
+        *step += 1;
+        let options = options(*step);
+        let moment1 = state.next().unwrap().1.unwrap_tensor();
+        let moment2 = state.next().unwrap().1.unwrap_tensor();
+        let grad = grad.view();
+        match scalar_type {
+            ScalarType::BF16 => adam_update::<bf16>(
+                value.try_into().unwrap(),
+                grad.try_into().unwrap(),
+                moment1.try_into().unwrap(),
+                moment2.try_into().unwrap(),
+                &options,
+            )?,
+            ScalarType::F32 => adam_update::<f32>(
+                value.try_into().unwrap(),
+                grad.try_into().unwrap(),
+                moment1.try_into().unwrap(),
+                moment2.try_into().unwrap(),
+                &options,
+            )?,
+            _ => unreachable!(),
+        }
+    }
+    Ok(())
+}
+
+struct AdamUpdateOptions {
+    learning_rate: f32,
+    beta1: f32,
+    beta2: f32,
+    epsilon: f32,
+    // Added to the gradient, or to the update with trust_ratio.
+    weight_decay: f32,
+    step: u32,
+    // None to update with the first moment only.
+    rectification: Option<f32>,
+    // Scales the update by ||w|| / ||update||.
+    trust_ratio: bool,
+}
+
+fn adam_update<T: Scalar>(
+    mut value: TensorViewMutD<T>,
+    grad: TensorViewD<T>,
+    mut moment1: TensorViewMutD<T>,
+    mut moment2: TensorViewMutD<T>,
+    options: &AdamUpdateOptions,
+) -> Result<()> {
+    if let Some((((mut value, grad), moment1), moment2)) = value
+        .as_array_mut()
+        .zip(grad.as_array())
+        .zip(moment1.as_array_mut())
+        .zip(moment2.as_array_mut())
+    {
+        let &AdamUpdateOptions {
+            learning_rate,
+            beta1,
+            beta2,
+            epsilon,
+            weight_decay,
+            step,
+            rectification,
+            trust_ratio,
+        } = options;
+        let bias_correction1 = 1. - beta1.powi(step as i32);
+        let bias_correction2 = 1. - beta2.powi(step as i32);
+        let l2 = if trust_ratio { 0. } else { weight_decay };
+        let mut update = Array::zeros(value.raw_dim());
+        let parallel = (value.len() + grad.len() + moment1.len() + moment2.len()) * size_of::<T>()
+            > parallel_size()
+            && rayon::current_num_threads() > 1;
+        let zip = Zip::from(&mut update)
+            .and(&value)
+            .and(grad)
+            .and(moment1)
+            .and(moment2);
+        let f = |update: &mut f32, value: &T, grad: &T, moment1: &mut T, moment2: &mut T| {
+            let value = value.cast::<f32>();
+            let mut m = moment1.cast::<f32>();
+            let mut v = moment2.cast::<f32>();
+            *update = kernels::adam_moments(
+                grad.cast::<f32>() + l2 * value,
+                beta1,
+                beta2,
+                epsilon,
+                bias_correction1,
+                bias_correction2,
+                rectification.is_some(),
+                rectification.unwrap_or(1.),
+                &mut m,
+                &mut v,
+            );
+            if trust_ratio {
+                *update += weight_decay * value;
+            }
+            *moment1 = m.cast();
+            *moment2 = v.cast();
+        };
+        if parallel {
+            zip.par_for_each(f);
+        } else {
+            zip.for_each(f);
+        }
+        let alpha = if trust_ratio {
+            let weight_norm = value
+                .iter()
+                .map(|x| x.cast::<f32>().powi(2))
+                .sum::<f32>()
+                .sqrt();
+            let update_norm = update.iter().map(|x| x * x).sum::<f32>().sqrt();
+            if weight_norm > 0. && update_norm > 0. {
+                learning_rate * weight_norm / update_norm
+            } else {
+                learning_rate
+            }
+        } else {
+            learning_rate
+        };
+        let zip = Zip::from(&mut value).and(&update);
+        let f = |value: &mut T, update: &f32| {
+            *value = (value.cast::<f32>() - alpha * update).cast();
+        };
+        if parallel {
+            zip.par_for_each(f);
+        } else {
+            zip.for_each(f);
+        }
+        return Ok(());
+    }
+    #[cfg(not(feature = "device"))]
+    {
+        unreachable!()
+    }
+    #[cfg(feature = "device")]
+    {
+        adam_update_device(value, grad, moment1, moment2, options)
+    }
+}
+
+#[cfg(feature = "device")]
+fn adam_update_device<T: Scalar>(
+    value: TensorViewMutD<T>,
+    grad: TensorViewD<T>,
+    moment1: TensorViewMutD<T>,
+    moment2: TensorViewMutD<T>,
+    options: &AdamUpdateOptions,
+) -> Result<()> {
+    let &AdamUpdateOptions {
+        learning_rate,
+        beta1,
+        beta2,
+        epsilon,
+        weight_decay,
+        step,
+        rectification,
+        trust_ratio,
+    } = options;
+    let bias_correction1 = 1. - beta1.powi(step as i32);
+    let bias_correction2 = 1. - beta2.powi(step as i32);
+    macro_for!($T in [bf16, f32] {
+        if value.scalar_type() == $T::SCALAR_TYPE && trust_ratio {
+            let mut value = ScalarTensorViewMut::from(value)
+                .try_into_tensor_view_mut::<$T>()
+                .unwrap();
+            let grad = ScalarTensorView::from(grad)
+                .try_into_tensor_view::<$T>()
+                .unwrap();
+            let mut moment1 = ScalarTensorViewMut::from(moment1)
+                .try_into_tensor_view_mut::<$T>()
+                .unwrap();
+            let mut moment2 = ScalarTensorViewMut::from(moment2)
+                .try_into_tensor_view_mut::<$T>()
+                .unwrap();
+            let mut update = unsafe { Tensor::<f32, _>::uninit(value.device(), value.raw_dim())? };
+            let kernel = paste! {
+                kernels::[<lamb_update_ $T>]::builder()?
+                .build(value.device())?
+            };
+            kernel.dispatch(
+                value.as_slice().unwrap(),
+                grad.as_slice().unwrap(),
+                beta1,
+                beta2,
+                epsilon,
+                weight_decay,
+                bias_correction1,
+                bias_correction2,
+                moment1.as_slice_mut().unwrap(),
+                moment2.as_slice_mut().unwrap(),
+                update.as_slice_mut().unwrap(),
+            )?;
+            let weight = value.scaled_cast(1f32)?;
+            let weight_norm = weight.mul(&weight)?.sum()?.sqrt();
+            let update_norm = update.mul(&update)?.sum()?.sqrt();
+            let alpha = if weight_norm > 0. && update_norm > 0. {
+                learning_rate * weight_norm / update_norm
+            } else {
+                learning_rate
+            };
+            let kernel = paste! {
+                kernels::[<lamb_step_ $T>]::builder()?
+                .build(value.device())?
+            };
+            return kernel.dispatch(
+                value.as_slice_mut().unwrap(),
+                alpha,
+                update.as_slice().unwrap(),
+            );
+        } else if value.scalar_type() == $T::SCALAR_TYPE {
+            let mut value = ScalarTensorViewMut::from(value)
+                .try_into_tensor_view_mut::<$T>()
+                .unwrap();
+            let grad = ScalarTensorView::from(grad)
+                .try_into_tensor_view::<$T>()
+                .unwrap();
+            let mut moment1 = ScalarTensorViewMut::from(moment1)
+                .try_into_tensor_view_mut::<$T>()
+                .unwrap();
+            let mut moment2 = ScalarTensorViewMut::from(moment2)
+                .try_into_tensor_view_mut::<$T>()
+                .unwrap();
+            let kernel = paste! {
+                kernels::[<adam_update_ $T>]::builder()?
+                .build(value.device())?
+            };
+            return kernel.dispatch(
+                value.as_slice_mut().unwrap(),
+                grad.as_slice().unwrap(),
+                learning_rate,
+                beta1,
+                beta2,
+                epsilon,
+                weight_decay,
+                bias_correction1,
+                bias_correction2,
+                rectification.is_some().into(),
+                rectification.unwrap_or(1.),
+                moment1.as_slice_mut().unwrap(),
+                moment2.as_slice_mut().unwrap(),
+            );
+        }
+    });
+    unreachable!()
+}
+
 #[cfg_attr(feature = "device", module)]
 mod kernels {
     #[cfg(all(feature = "device", not(target_arch = "spirv")))]
     use krnl::krnl_core;
     #[cfg(any(feature = "device", target_arch = "spirv"))]
     use krnl_core::macros::kernel;
+    #[cfg(target_arch = "spirv")]
+    use krnl_core::num_traits::Float;
 
     pub fn sgd_update_with_momentum(w: &mut f32, dw: f32, lr: f32, m: f32, v: &mut f32) {
         *v = m * *v + dw;
         *w -= lr * *v;
     }
 
+    // Updates the running average of the squared gradient `v` with the gradient `dw`, and the
+    // running average of the gradient `g` if `centered` and the momentum buffer `b` if
+    // `with_momentum`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn rmsprop_update(
+        w: &mut f32,
+        dw: f32,
+        lr: f32,
+        alpha: f32,
+        epsilon: f32,
+        momentum: f32,
+        centered: bool,
+        with_momentum: bool,
+        v: &mut f32,
+        g: &mut f32,
+        b: &mut f32,
+    ) {
+        *v = alpha * *v + (1. - alpha) * dw * dw;
+        let avg = if centered {
+            *g = alpha * *g + (1. - alpha) * dw;
+            *v - *g * *g
+        } else {
+            *v
+        };
+        let mut update = dw / (avg.sqrt() + epsilon);
+        if with_momentum {
+            update += momentum * *b;
+            *b = update;
+        }
+        *w -= lr * update;
+    }
+
+    // Updates the moments `m` and `v` with the gradient `dw`, returning the update.
+    //
+    // Without `adaptive`, the update is the bias corrected first moment.
+    #[allow(clippy::too_many_arguments)]
+    pub fn adam_moments(
+        dw: f32,
+        beta1: f32,
+        beta2: f32,
+        epsilon: f32,
+        bias_correction1: f32,
+        bias_correction2: f32,
+        adaptive: bool,
+        rectification: f32,
+        m: &mut f32,
+        v: &mut f32,
+    ) -> f32 {
+        *m = beta1 * *m + (1. - beta1) * dw;
+        *v = beta2 * *v + (1. - beta2) * dw * dw;
+        let m_hat = *m / bias_correction1;
+        if adaptive {
+            rectification * m_hat / ((*v / bias_correction2).sqrt() + epsilon)
+        } else {
+            m_hat
+        }
+    }
+
     #[cfg(any(feature = "device", target_arch = "spirv"))]
     pub mod device {
         use super::*;
+        use dry::macro_for;
         #[cfg(target_arch = "spirv")]
-        use krnl_core::half::bf16;
+        use krnl_core::{half::bf16, scalar::Scalar};
+        use paste::paste;
 
         #[kernel]
         pub fn sgd_update_with_momentum_bf16(
@@ -542,6 +1580,217 @@ mod kernels {
         ) {
             sgd_update_with_momentum(w, dw, lr, m, v);
         }
+
+        macro_for!($T in [bf16, f32] {
+            paste! {
+                #[kernel]
+                pub fn [<rmsprop_update_ $T>](
+                    #[item] w: &mut $T,
+                    #[item] dw: $T,
+                    lr: f32,
+                    alpha: f32,
+                    epsilon: f32,
+                    #[item] v: &mut $T,
+                ) {
+                    let mut w_f32 = w.cast::<f32>();
+                    let mut v_f32 = v.cast::<f32>();
+                    let (mut g, mut b) = (0f32, 0f32);
+                    rmsprop_update(
+                        &mut w_f32,
+                        dw.cast::<f32>(),
+                        lr,
+                        alpha,
+                        epsilon,
+                        0.,
+                        false,
+                        false,
+                        &mut v_f32,
+                        &mut g,
+                        &mut b,
+                    );
+                    *w = w_f32.cast();
+                    *v = v_f32.cast();
+                }
+
+                #[allow(clippy::too_many_arguments)]
+                #[kernel]
+                pub fn [<rmsprop_update_centered_ $T>](
+                    #[item] w: &mut $T,
+                    #[item] dw: $T,
+                    lr: f32,
+                    alpha: f32,
+                    epsilon: f32,
+                    #[item] v: &mut $T,
+                    #[item] g: &mut $T,
+                ) {
+                    let mut w_f32 = w.cast::<f32>();
+                    let mut v_f32 = v.cast::<f32>();
+                    let mut g_f32 = g.cast::<f32>();
+                    let mut b = 0f32;
+                    rmsprop_update(
+                        &mut w_f32,
+                        dw.cast::<f32>(),
+                        lr,
+                        alpha,
+                        epsilon,
+                        0.,
+                        true,
+                        false,
+                        &mut v_f32,
+                        &mut g_f32,
+                        &mut b,
+                    );
+                    *w = w_f32.cast();
+                    *v = v_f32.cast();
+                    *g = g_f32.cast();
+                }
+
+                #[allow(clippy::too_many_arguments)]
+                #[kernel]
+                pub fn [<rmsprop_update_momentum_ $T>](
+                    #[item] w: &mut $T,
+                    #[item] dw: $T,
+                    lr: f32,
+                    alpha: f32,
+                    epsilon: f32,
+                    momentum: f32,
+                    #[item] v: &mut $T,
+                    #[item] b: &mut $T,
+                ) {
+                    let mut w_f32 = w.cast::<f32>();
+                    let mut v_f32 = v.cast::<f32>();
+                    let mut g = 0f32;
+                    let mut b_f32 = b.cast::<f32>();
+                    rmsprop_update(
+                        &mut w_f32,
+                        dw.cast::<f32>(),
+                        lr,
+                        alpha,
+                        epsilon,
+                        momentum,
+                        false,
+                        true,
+                        &mut v_f32,
+                        &mut g,
+                        &mut b_f32,
+                    );
+                    *w = w_f32.cast();
+                    *v = v_f32.cast();
+                    *b = b_f32.cast();
+                }
+
+                #[allow(clippy::too_many_arguments)]
+                #[kernel]
+                pub fn [<rmsprop_update_centered_momentum_ $T>](
+                    #[item] w: &mut $T,
+                    #[item] dw: $T,
+                    lr: f32,
+                    alpha: f32,
+                    epsilon: f32,
+                    momentum: f32,
+                    #[item] v: &mut $T,
+                    #[item] g: &mut $T,
+                    #[item] b: &mut $T,
+                ) {
+                    let mut w_f32 = w.cast::<f32>();
+                    let mut v_f32 = v.cast::<f32>();
+                    let mut g_f32 = g.cast::<f32>();
+                    let mut b_f32 = b.cast::<f32>();
+                    rmsprop_update(
+                        &mut w_f32,
+                        dw.cast::<f32>(),
+                        lr,
+                        alpha,
+                        epsilon,
+                        momentum,
+                        true,
+                        true,
+                        &mut v_f32,
+                        &mut g_f32,
+                        &mut b_f32,
+                    );
+                    *w = w_f32.cast();
+                    *v = v_f32.cast();
+                    *g = g_f32.cast();
+                    *b = b_f32.cast();
+                }
+
+                #[allow(clippy::too_many_arguments)]
+                #[kernel]
+                pub fn [<adam_update_ $T>](
+                    #[item] w: &mut $T,
+                    #[item] dw: $T,
+                    lr: f32,
+                    beta1: f32,
+                    beta2: f32,
+                    epsilon: f32,
+                    weight_decay: f32,
+                    bias_correction1: f32,
+                    bias_correction2: f32,
+                    adaptive: u32,
+                    rectification: f32,
+                    #[item] m: &mut $T,
+                    #[item] v: &mut $T,
+                ) {
+                    let w_f32 = w.cast::<f32>();
+                    let mut m_f32 = m.cast::<f32>();
+                    let mut v_f32 = v.cast::<f32>();
+                    let update = adam_moments(
+                        dw.cast::<f32>() + weight_decay * w_f32,
+                        beta1,
+                        beta2,
+                        epsilon,
+                        bias_correction1,
+                        bias_correction2,
+                        adaptive != 0,
+                        rectification,
+                        &mut m_f32,
+                        &mut v_f32,
+                    );
+                    *w = (w_f32 - lr * update).cast();
+                    *m = m_f32.cast();
+                    *v = v_f32.cast();
+                }
+
+                #[allow(clippy::too_many_arguments)]
+                #[kernel]
+                pub fn [<lamb_update_ $T>](
+                    #[item] w: $T,
+                    #[item] dw: $T,
+                    beta1: f32,
+                    beta2: f32,
+                    epsilon: f32,
+                    weight_decay: f32,
+                    bias_correction1: f32,
+                    bias_correction2: f32,
+                    #[item] m: &mut $T,
+                    #[item] v: &mut $T,
+                    #[item] update: &mut f32,
+                ) {
+                    let mut m_f32 = m.cast::<f32>();
+                    let mut v_f32 = v.cast::<f32>();
+                    *update = adam_moments(
+                        dw.cast::<f32>(),
+                        beta1,
+                        beta2,
+                        epsilon,
+                        bias_correction1,
+                        bias_correction2,
+                        true,
+                        1.,
+                        &mut m_f32,
+                        &mut v_f32,
+                    ) + weight_decay * w.cast::<f32>();
+                    *m = m_f32.cast();
+                    *v = v_f32.cast();
+                }
+
+                #[kernel]
+                pub fn [<lamb_step_ $T>](#[item] w: &mut $T, alpha: f32, #[item] update: f32) {
+                    *w = (w.cast::<f32>() - alpha * update).cast();
+                }
+            }
+        });
     }
     #[cfg(any(feature = "device", target_arch = "spirv"))]
     pub use device::*;
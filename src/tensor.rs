@@ -32,6 +32,27 @@ let c_view = c.as_array().unwrap();
 // Move into an array
 let c = c.into_array()?;
 
+# Ok(())
+# }
+```
+
+# Synchronization
+Operations on a device are queued and execute asynchronously. Transferring a tensor to the host,
+for example with [`.into_array()`](TensorBase::into_array), waits for the operations that produce
+it. To wait for all queued operations to complete, for example when measuring the time of a
+computation, use [`Device::wait()`](krnl::device::Device::wait).
+```
+# use krnl::device::Device;
+# use autograph::tensor::Tensor;
+# use ndarray::linalg::Dot;
+# use std::time::Instant;
+# fn main() -> anyhow::Result<()> {
+# let device = Device::host();
+let a = Tensor::<f32, _>::ones(device.clone(), [64, 64])?;
+let start = Instant::now();
+let b = a.dot(&a)?;
+device.wait()?;
+let elapsed = start.elapsed();
 # Ok(())
 # }
 ```
@@ -63,12 +84,20 @@ use num_traits::ToPrimitive;
 use paste::paste;
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
-use std::fmt::{self, Debug};
+use std::{
+    fmt::{self, Debug},
+    ops::Range,
+};
 
+mod fallback;
+pub use fallback::{allow_host_fallback, host_fallback_ops, set_allow_host_fallback};
 mod linalg;
+pub use linalg::outer;
 mod ops;
+pub use ops::{stack, where_};
 pub(crate) mod parallel;
 mod reduce;
+pub use reduce::ReduceOp;
 
 fn strides_from_array<S, D>(array: &ArrayBase<S, D>) -> D
 where
@@ -458,6 +487,34 @@ impl<S: ScalarDataOwned, D: Dimension> ScalarTensorBase<S, D> {
             offset: 0,
         })
     }
+    /// Creates a scalar tensor from `buffer` with `shape` without copying.
+    ///
+    /// The scalar type and device of the tensor are those of `buffer`.
+    ///
+    /// See [`TensorBase::from_buffer()`].
+    ///
+    /// # Errors
+    /// The length of `buffer` must equal the size of `shape`.
+    pub fn from_buffer<Sh>(buffer: ScalarBuffer, shape: Sh) -> Result<Self>
+    where
+        Sh: ndarray::ShapeBuilder<Dim = D>,
+    {
+        let (dim, strides) = dim_strides_from_shape(shape.into_shape());
+        if buffer.len() != dim.size() {
+            bail!(
+                "ScalarTensor::from_buffer buffer.len() {} != shape {:?} size {}!",
+                buffer.len(),
+                dim.slice(),
+                dim.size()
+            );
+        }
+        Ok(Self {
+            dim,
+            strides,
+            buffer: ScalarBufferBase::from_scalar_buffer(buffer),
+            offset: 0,
+        })
+    }
     /// Creates a tensor on `device` with `shape` filled with `elem`.
     ///
     /// # Errors
@@ -522,6 +579,18 @@ impl<S: ScalarData, D: Dimension> ScalarTensorBase<S, D> {
     pub fn strides(&self) -> &[isize] {
         bytemuck::cast_slice(self.strides.slice())
     }
+    /// The offset of the first element of the tensor into the buffer.
+    ///
+    /// See [`.buffer()`](ScalarTensorBase::buffer).
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+    /// Borrows the underlying buffer.
+    ///
+    /// See [`TensorBase::buffer()`].
+    pub fn buffer(&self) -> &ScalarBufferBase<S> {
+        &self.buffer
+    }
     /// The length of the tensor.
     pub fn len(&self) -> usize {
         self.dim.size()
@@ -989,6 +1058,19 @@ impl<D: Dimension> ScalarArcTensor<D> {
             offset: self.offset,
         })
     }
+    /// Transfers the tensor to the `device`, sharing the buffer when possible.
+    ///
+    /// If `device` is the current device, returns a clone of `self` that shares the underlying
+    /// buffer, without allocating. Otherwise the data is copied to `device`.
+    ///
+    /// See [`.to_device_shared()`](ScalarTensorBase::to_device_shared).
+    pub fn clone_to_device(&self, device: Device) -> Result<Self> {
+        if self.device() == device {
+            Ok(self.clone())
+        } else {
+            self.to_device(device).map(Into::into)
+        }
+    }
 }
 
 impl<S: ScalarDataOwned> From<ScalarBuffer> for ScalarTensorBase<S, Ix1> {
@@ -1164,6 +1246,79 @@ impl<S: ScalarData, D: Dimension> ScalarTensorBase<S, D> {
             })
         }
     }
+    /// Returns the element of a tensor with 1 element.
+    ///
+    /// See [`TensorBase::item()`].
+    pub fn item(&self) -> Result<ScalarElem> {
+        use krnl::krnl_core::half::{bf16, f16};
+
+        macro_for!($T in [u8, i8, u16, i16, f16, bf16, u32, i32, f32, u64, i64, f64] {
+            if let Ok(x) = self.view().try_into_tensor_view::<$T>() {
+                return x.item().map(Into::into);
+            }
+        });
+        unreachable!()
+    }
+    /// Fills the tensor with `elem`.
+    ///
+    /// # Errors
+    /// - The scalar type of `elem` does not match the tensor.
+    ///
+    /// See [`TensorBase::fill()`].
+    pub fn fill(&mut self, elem: ScalarElem) -> Result<()>
+    where
+        S: ScalarDataMut,
+    {
+        use krnl::krnl_core::half::{bf16, f16};
+
+        if elem.scalar_type() != self.scalar_type() {
+            bail!(
+                "ScalarTensorBase::fill elem {:?} does not match scalar_type {:?}!",
+                elem.scalar_type(),
+                self.scalar_type()
+            );
+        }
+        macro_for!($T in [u8, i8, u16, i16, f16, bf16, u32, i32, f32, u64, i64, f64] {
+            if let Ok(mut x) = self.view_mut().try_into_tensor_view_mut::<$T>() {
+                return x.fill($T::try_from(elem).unwrap());
+            }
+        });
+        unreachable!()
+    }
+    /// Fills the tensor with zeros.
+    ///
+    /// See [`TensorBase::zero()`].
+    pub fn zero(&mut self) -> Result<()>
+    where
+        S: ScalarDataMut,
+    {
+        self.fill(ScalarElem::U8(0).scalar_cast(self.scalar_type()))
+    }
+    /// Casts the tensor into a [`ScalarArcTensor`].
+    ///
+    /// Does not copy if the tensor is already `scalar_type` and can be converted into a
+    /// [`ScalarArcTensor`] without copying.
+    ///
+    /// See [`.cast_into()`](ScalarTensorBase::cast_into).
+    pub fn cast_into_shared(self, scalar_type: ScalarType) -> Result<ScalarArcTensor<D>> {
+        if self.scalar_type() == scalar_type {
+            self.into_shared()
+        } else {
+            self.cast(scalar_type).map(Into::into)
+        }
+    }
+    /// Casts the tensor to a [`ScalarArcTensor`].
+    ///
+    /// Does not copy if the tensor is an arc tensor and is already `scalar_type`.
+    ///
+    /// See [`.cast()`](ScalarTensorBase::cast).
+    pub fn cast_shared(&self, scalar_type: ScalarType) -> Result<ScalarArcTensor<D>> {
+        if self.scalar_type() == scalar_type {
+            self.to_shared()
+        } else {
+            self.cast(scalar_type).map(Into::into)
+        }
+    }
     /// Casts the tensor in place.
     ///
     /// See [`BufferBase::cast()`].
@@ -1387,6 +1542,40 @@ impl<T: Scalar, S: DataOwned<Elem = T>, D: Dimension> TensorBase<S, D> {
             offset: 0,
         })
     }
+    /// Creates a tensor from `buffer` with `shape` without copying.
+    ///
+    /// Wraps a buffer produced by [`krnl`], for example the output of a custom kernel. The tensor
+    /// takes ownership of `buffer`, and is on the same device. The buffer is freed when the
+    /// tensor is dropped, and may outlive the tensor if the tensor is converted into an
+    /// [`ArcTensor`] that is shared. Use [`.into_shape()`](TensorBase::into_shape) to reshape an
+    /// existing tensor.
+    ///
+    /// The scalar type of the tensor is `T`. A [`ScalarBuffer`] can be wrapped with
+    /// [`ScalarTensorBase::from_buffer()`] and converted with
+    /// [`.try_into_tensor()`](ScalarTensorBase::try_into_tensor), which checks the scalar type.
+    ///
+    /// # Errors
+    /// The length of `buffer` must equal the size of `shape`.
+    pub fn from_buffer<Sh>(buffer: Buffer<T>, shape: Sh) -> Result<Self>
+    where
+        Sh: ndarray::ShapeBuilder<Dim = D>,
+    {
+        let (dim, strides) = dim_strides_from_shape(shape.into_shape());
+        if buffer.len() != dim.size() {
+            bail!(
+                "Tensor::from_buffer buffer.len() {} != shape {:?} size {}!",
+                buffer.len(),
+                dim.slice(),
+                dim.size()
+            );
+        }
+        Ok(Self {
+            dim,
+            strides,
+            buffer: BufferBase::from_buffer(buffer),
+            offset: 0,
+        })
+    }
     /// Creates a tensor on `device` with `shape` filled with `elem`.
     ///
     /// # Errors
@@ -1424,6 +1613,22 @@ impl<T: Scalar, S: DataOwned<Elem = T>, D: Dimension> TensorBase<S, D> {
     {
         Self::from_elem(device, shape, T::one())
     }
+    /// Creates a tensor on `device` with `shape`, computing each element with `f`.
+    ///
+    /// `f` is called with the index of each element, see [`ArrayBase::from_shape_fn()`].
+    /// The tensor is created on the host and then transferred to `device`.
+    ///
+    /// # Errors
+    /// See [`.to_device_mut()`](TensorBase::to_device_mut).
+    pub fn from_shape_fn<Sh, F>(device: Device, shape: Sh, f: F) -> Result<Self>
+    where
+        Sh: ndarray::ShapeBuilder<Dim = D>,
+        F: FnMut(D::Pattern) -> T,
+    {
+        let mut tensor = Self::from(Array::from_shape_fn(shape, f));
+        tensor.to_device_mut(device)?;
+        Ok(tensor)
+    }
 }
 
 impl<T: Scalar, S: Data<Elem = T>, D: Dimension> TensorBase<S, D> {
@@ -1451,6 +1656,28 @@ impl<T: Scalar, S: Data<Elem = T>, D: Dimension> TensorBase<S, D> {
     pub fn strides(&self) -> &[isize] {
         bytemuck::cast_slice(self.strides.slice())
     }
+    /// The offset of the first element of the tensor into the buffer.
+    ///
+    /// See [`.buffer()`](TensorBase::buffer).
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+    /// Borrows the underlying buffer.
+    ///
+    /// Provides access to the [`krnl`] buffer backing the tensor without copying, for example to
+    /// pass to a custom kernel. Device buffers are only accessible via [`krnl`], host buffers can be
+    /// borrowed as a slice with [`BufferBase::as_host_slice()`].
+    ///
+    /// The buffer may be larger than the tensor, which is laid out in the buffer with
+    /// [`.offset()`](TensorBase::offset) and [`.strides()`](TensorBase::strides), and strides may
+    /// be negative. Use [`.as_slice()`](TensorBase::as_slice) to borrow just the elements of a
+    /// standard layout tensor.
+    ///
+    /// The buffer can not be modified through this reference. Use
+    /// [`.as_slice_mut()`](TensorBase::as_slice_mut) to mutate the elements of the tensor.
+    pub fn buffer(&self) -> &BufferBase<S> {
+        &self.buffer
+    }
     /// The length of the tensor.
     pub fn len(&self) -> usize {
         self.dim.size()
@@ -1642,6 +1869,41 @@ impl<T: Scalar, S: Data<Elem = T>, D: Dimension> TensorBase<S, D> {
     pub fn t(&self) -> TensorView<T, D> {
         self.view().reversed_axes()
     }
+    /// Reverses the order of the elements along `axis`.
+    ///
+    /// Negates the stride of `axis` and does not copy the data.
+    ///
+    /// # Panics
+    /// `axis` is out of bounds.
+    pub fn invert_axis(&mut self, axis: Axis) {
+        let Axis(index) = axis;
+        let len = self.dim[index];
+        let stride = self.strides[index] as isize;
+        if len > 1 {
+            self.offset = (self.offset as isize + (len as isize - 1) * stride) as usize;
+        }
+        self.strides[index] = stride.wrapping_neg() as usize;
+    }
+    /// Restricts the tensor to `range` along `axis`.
+    ///
+    /// Modifies the shape and offset and does not copy the data.
+    ///
+    /// # Panics
+    /// `axis` or `range` is out of bounds.
+    pub fn slice_axis_inplace(&mut self, axis: Axis, range: Range<usize>) {
+        let Axis(index) = axis;
+        let len = self.dim[index];
+        assert!(
+            range.start <= range.end && range.end <= len,
+            "range {range:?} out of bounds for axis {index} with length {len}!"
+        );
+        if !range.is_empty() {
+            self.offset = (self.offset as isize
+                + range.start as isize * self.strides[index] as isize)
+                as usize;
+        }
+        self.dim[index] = range.len();
+    }
     /// Returns a view restricted to index along the `axis`, with the `axis` removed.
     ///
     /// # Panics
@@ -1897,23 +2159,37 @@ impl<T: Scalar, S: Data<Elem = T>, D: Dimension> TensorBase<S, D> {
     }
     /// Fills the tensor with `elem`.
     ///
-    /// # Errors
-    /// Device tensors must be contiguous.
+    /// Only the elements of the tensor are set, ie for views other elements of the buffer are
+    /// not modified. Contiguous tensors are filled in place without allocating.
     ///
-    /// See [`BufferBase::fill()`].
+    /// # Errors
+    /// - See [`BufferBase::fill()`].
+    /// - See [`.assign()`](TensorBase::assign()).
     pub fn fill(&mut self, elem: T) -> Result<()>
     where
         S: DataMut,
     {
-        if self.is_contiguous() {
-            self.buffer.as_slice_mut().fill(elem)
-        } else if let Some(mut array) = self.as_array_mut() {
+        if let Some(mut array) = self.as_array_mut() {
             array.fill(elem);
             Ok(())
+        } else if let Some(mut slice) = self.as_slice_memory_order_mut() {
+            slice.fill(elem)
         } else {
-            bail!("TensorBase::fill tensor is not contiguous!")
+            let elem = Tensor::from_elem(self.device(), (), elem)?;
+            self.assign(&elem)
         }
     }
+    /// Fills the tensor with zeros.
+    ///
+    /// Useful for resetting accumulators without reallocating.
+    ///
+    /// See [`.fill()`](TensorBase::fill()).
+    pub fn zero(&mut self) -> Result<()>
+    where
+        S: DataMut,
+    {
+        self.fill(T::default())
+    }
     /// Moves the tensor into an [`Array`].
     ///
     /// # Errors
@@ -1932,6 +2208,27 @@ impl<T: Scalar, S: Data<Elem = T>, D: Dimension> TensorBase<S, D> {
             bail!("TensorBase::into_array tensor is not contiguous!")
         }
     }
+    /// Returns the element of a tensor with 1 element, for example a loss.
+    ///
+    /// Transfers the element to the host if necessary.
+    ///
+    /// # Errors
+    /// - The tensor does not have exactly 1 element.
+    /// - See [`Slice::to_device()`].
+    pub fn item(&self) -> Result<T> {
+        if self.len() != 1 {
+            bail!("TensorBase::item expected 1 element, found {}!", self.len());
+        }
+        if let Some(array) = self.as_array() {
+            return Ok(array.iter().copied().next().unwrap());
+        }
+        let (slice, offset) = self.as_raw_slice_offset();
+        let buffer = slice
+            .slice(offset..offset + 1)
+            .unwrap()
+            .to_device(Device::host())?;
+        Ok(buffer.as_host_slice().unwrap()[0])
+    }
     /// Borrows the tensor as an array view if on the host.
     pub fn as_array(&self) -> Option<ArrayView<T, D>> {
         use ndarray::ShapeBuilder;
@@ -1964,6 +2261,127 @@ impl<T: Scalar, S: Data<Elem = T>, D: Dimension> TensorBase<S, D> {
             None
         }
     }
+    /// Applies `f` by reference to each element, returning a new tensor.
+    ///
+    /// **Host only.** Closures can not be executed on a device, transfer device tensors to the
+    /// host with [`.to_device()`](TensorBase::to_device) first.
+    ///
+    /// # Errors
+    /// - The tensor is not on the host.
+    ///
+    /// See [`ArrayBase::map()`].
+    pub fn map<T2, F>(&self, f: F) -> Result<Tensor<T2, D>>
+    where
+        T2: Scalar,
+        F: FnMut(&T) -> T2,
+    {
+        let Some(array) = self.as_array() else {
+            bail!("TensorBase::map is only supported on the host!");
+        };
+        Ok(array.map(f).into())
+    }
+    /// Applies `f` by value to each element, returning a new tensor.
+    ///
+    /// **Host only.** Closures can not be executed on a device, transfer device tensors to the
+    /// host with [`.to_device()`](TensorBase::to_device) first.
+    ///
+    /// # Errors
+    /// - The tensor is not on the host.
+    ///
+    /// See [`ArrayBase::mapv()`].
+    pub fn mapv<T2, F>(&self, f: F) -> Result<Tensor<T2, D>>
+    where
+        T2: Scalar,
+        F: FnMut(T) -> T2,
+    {
+        let Some(array) = self.as_array() else {
+            bail!("TensorBase::mapv is only supported on the host!");
+        };
+        Ok(array.mapv(f).into())
+    }
+}
+
+impl<T: Scalar, S: Data<Elem = T>> TensorBase<S, Ix2> {
+    /// Fills the diagonal of the matrix with `elem`.
+    ///
+    /// The matrix does not need to be square, the diagonal has `min(rows, cols)` elements.
+    ///
+    /// # Errors
+    /// See [`.fill()`](TensorBase::fill()).
+    pub fn fill_diagonal(&mut self, elem: T) -> Result<()>
+    where
+        S: DataMut,
+    {
+        let (rows, cols) = self.dim();
+        let stride = self.strides().iter().sum::<isize>() as usize;
+        let mut diagonal = TensorViewMut {
+            dim: Ix1(rows.min(cols)),
+            strides: Ix1(stride),
+            buffer: self.buffer.as_slice_mut(),
+            offset: self.offset,
+        };
+        diagonal.fill(elem)
+    }
+    /// Returns the lower triangle of the matrix.
+    ///
+    /// Elements above `diagonal` are set to 0. A `diagonal` of 0 is the main diagonal, positive
+    /// values are above and negative values are below the main diagonal.
+    ///
+    /// # Errors
+    /// - The operation could not be executed on the device.
+    pub fn tril(&self, diagonal: isize) -> Result<Tensor2<T>> {
+        self.triangle(diagonal, true)
+    }
+    /// Returns the upper triangle of the matrix.
+    ///
+    /// Elements below `diagonal` are set to 0.
+    ///
+    /// See [`.tril()`](TensorBase::tril()).
+    pub fn triu(&self, diagonal: isize) -> Result<Tensor2<T>> {
+        self.triangle(diagonal, false)
+    }
+    fn triangle(&self, diagonal: isize, lower: bool) -> Result<Tensor2<T>> {
+        let mut output = self.view().into_standard_layout()?;
+        ops::triangle_in_place(&mut output, diagonal, lower)?;
+        Ok(output)
+    }
+}
+
+impl<T: Scalar> Tensor2<T> {
+    /// Creates a lower triangular mask on `device` with shape `[n, n]`.
+    ///
+    /// Elements on or below `diagonal` are 1, others are 0. For example, `tril_mask(device, n, 0)`
+    /// is the causal mask for attention.
+    ///
+    /// See [`.tril()`](TensorBase::tril()).
+    ///
+    /// # Errors
+    /// - The operation could not be executed on the device.
+    pub fn tril_mask(device: Device, n: usize, diagonal: isize) -> Result<Self> {
+        Self::triangle_mask(device, n, diagonal, true)
+    }
+    /// Creates an upper triangular mask on `device` with shape `[n, n]`.
+    ///
+    /// Elements on or above `diagonal` are 1, others are 0.
+    ///
+    /// See [`Tensor::tril_mask()`].
+    pub fn triu_mask(device: Device, n: usize, diagonal: isize) -> Result<Self> {
+        Self::triangle_mask(device, n, diagonal, false)
+    }
+    fn triangle_mask(device: Device, n: usize, diagonal: isize, lower: bool) -> Result<Self> {
+        let mut mask = Self::ones(device, [n, n])?;
+        ops::triangle_in_place(&mut mask, diagonal, lower)?;
+        Ok(mask)
+    }
+}
+
+fn in_triangle(i: usize, j: usize, diagonal: isize, lower: bool) -> bool {
+    let k = j as isize - i as isize;
+    if lower {
+        k <= diagonal
+    } else {
+        k >= diagonal
+    }
 }
 
 impl<T: Scalar, D: Dimension> Tensor<T, D> {
@@ -0,0 +1,119 @@
+use super::*;
+#[cfg(feature = "device")]
+use anyhow::Error;
+#[cfg(feature = "device")]
+use krnl::device::Features;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Mutex,
+};
+
+static ALLOW_HOST_FALLBACK: AtomicBool = AtomicBool::new(false);
+static HOST_FALLBACK_OPS: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+
+/// Allows operations that are not supported by a device to be computed on the host. Defaults to
+/// false.
+///
+/// Devices may not support all features, for example 64 bit or 8 bit scalars, so some
+/// operations can not be executed. With host fallback, the inputs are transferred to the
+/// host, the operation is computed on the host, and the output is transferred back to the
+/// device. This is slow but allows running on minimal devices.
+///
+/// Only operations that fail because the device lacks the [features](Features) required for
+/// their scalar types fall back, other errors are returned. The first time each operation falls
+/// back, a warning is logged with [`log`]. See [`host_fallback_ops()`].
+///
+/// Implemented for:
+/// - [`.assign()`](TensorBase::assign()), [`.scaled_add()`](TensorBase::scaled_add()), and casts
+/// - [`.sum()`](TensorBase::sum()), [`.sum_axis()`](TensorBase::sum_axis()), and
+///   [`.reduce()`](TensorBase::reduce())
+/// - [`Dot`](ndarray::linalg::Dot) for matrices and [`.bmm()`](TensorBase::bmm())
+pub fn set_allow_host_fallback(allow: bool) {
+    ALLOW_HOST_FALLBACK.store(allow, Ordering::SeqCst);
+}
+
+/// Whether operations may be computed on the host.
+///
+/// See [`set_allow_host_fallback()`].
+pub fn allow_host_fallback() -> bool {
+    ALLOW_HOST_FALLBACK.load(Ordering::SeqCst)
+}
+
+/// The operations that have fallen back to the host.
+///
+/// See [`set_allow_host_fallback()`].
+pub fn host_fallback_ops() -> Vec<&'static str> {
+    HOST_FALLBACK_OPS.lock().unwrap().clone()
+}
+
+// The device features required by kernels for `scalar_type`.
+#[cfg(feature = "device")]
+fn features_for_scalar(scalar_type: ScalarType) -> Features {
+    let features = match scalar_type.size() {
+        1 => Features::INT8 | Features::BUFFER8 | Features::PUSH_CONSTANT8,
+        2 => Features::INT16 | Features::BUFFER16 | Features::PUSH_CONSTANT16,
+        8 => Features::INT64,
+        _ => Features::empty(),
+    };
+    if scalar_type == ScalarType::F64 {
+        features | Features::FLOAT64
+    } else {
+        features
+    }
+}
+
+// Whether `op` should be computed on the host after failing on `device` with `error`.
+//
+// Only falls back if `device` does not support the features required for `scalar_types`.
+#[cfg(feature = "device")]
+pub(super) fn host_fallback(
+    op: &'static str,
+    device: &Device,
+    scalar_types: &[ScalarType],
+    error: &Error,
+) -> bool {
+    if !allow_host_fallback() {
+        return false;
+    }
+    let Some(info) = device.info() else {
+        return false;
+    };
+    let features = scalar_types
+        .iter()
+        .fold(Features::empty(), |features, scalar_type| {
+            features | features_for_scalar(*scalar_type)
+        });
+    if info.features().contains(features) {
+        return false;
+    }
+    let mut ops = HOST_FALLBACK_OPS.lock().unwrap();
+    if !ops.contains(&op) {
+        log::warn!("{op} is not supported on {device:?}, falling back to the host: {error}");
+        ops.push(op);
+    }
+    true
+}
+
+// Copies the buffer of `x` to the host, preserving the layout.
+//
+// Unlike .to_device(), does not execute kernels for strided tensors.
+#[cfg(feature = "device")]
+pub(super) fn raw_to_host<D: Dimension>(x: ScalarTensorView<D>) -> Result<ScalarTensor<D>> {
+    let (slice, offset) = x.as_raw_scalar_slice_offset();
+    Ok(ScalarTensor {
+        dim: x.dim.clone(),
+        strides: x.strides.clone(),
+        buffer: slice.to_device(Device::host())?,
+        offset,
+    })
+}
+
+// Copies the buffer of `x`, from raw_to_host(), back to `y`.
+#[cfg(feature = "device")]
+pub(super) fn raw_copy_from_host<D: Dimension>(
+    mut y: ScalarTensorViewMut<D>,
+    x: &ScalarTensor<D>,
+) -> Result<()> {
+    let (mut y, _) = y.as_raw_scalar_slice_offset_mut();
+    y.copy_from_scalar_slice(&x.buffer.as_scalar_slice())
+}
@@ -42,6 +42,9 @@ mod kernels {
                     const CSB: i32,
                     const RSC: i32,
                     const CSC: i32,
+                    const BSA: i32,
+                    const BSB: i32,
+                    const BSC: i32,
                 >(
                     alpha: $a,
                     #[global] a: Slice<$t>,
@@ -77,7 +80,13 @@ mod kernels {
                     let groups_mn = groups_m * groups_n;
                     let global_unroll = groups_k * unroll;
 
+                    let groups_kmn = groups_k * groups_mn;
                     let group_id = kernel.group_id();
+                    let batch = (group_id / groups_kmn) as i32;
+                    let group_id = group_id % groups_kmn;
+                    let offset_a = offset_a as i32 + batch * BSA;
+                    let offset_b = offset_b as i32 + batch * BSB;
+                    let offset_c = offset_c as i32 + batch * BSC;
                     let group_k = group_id / groups_mn;
                     let group_mn = group_id % groups_mn;
                     let group_m = group_mn / groups_n;
@@ -140,7 +149,7 @@ mod kernels {
                                     let tile_k = u * threads_k_a + thread_k_a;
                                     let global_k = global_k + tile_k;
                                     a_prefetch[u] = if global_m < m && global_k < k {
-                                        a[(global_m as i32 * RSA + global_k as i32 * CSA + offset_a as i32) as usize]
+                                        a[(global_m as i32 * RSA + global_k as i32 * CSA + offset_a) as usize]
                                     } else {
                                         T::zero()
                                     };
@@ -153,7 +162,7 @@ mod kernels {
                                     let tile_k = u * threads_k_b + thread_k_b;
                                     let global_k = global_k + tile_k;
                                     b_prefetch[u] = if global_k < k && global_n < n {
-                                        b[(global_k as i32 * RSB + global_n as i32 * CSB + offset_b as i32) as usize]
+                                        b[(global_k as i32 * RSB + global_n as i32 * CSB + offset_b) as usize]
                                     } else {
                                         T::zero()
                                     };
@@ -211,7 +220,7 @@ mod kernels {
                         unroll! { for j in 0 .. 2 {
                             let global_n = global_n + j * threads_n + thread_n;
                             if global_m < m && global_n < n {
-                                let index = ((global_m as i32 * RSC + global_n as i32 * CSC + offset_c as i32) as usize) * groups_k + group_k;
+                                let index = ((global_m as i32 * RSC + global_n as i32 * CSC + offset_c) as usize) * groups_k + group_k;
                                 if beta == A::zero() {
                                     unsafe {
                                         *c.unsafe_index_mut(index) = (alpha * c_thread[i][j]).cast();
@@ -247,11 +256,29 @@ mod kernels {
 
 #[cfg(feature = "device")]
 fn gemm(
+    alpha: ScalarElem,
+    a: ScalarTensorView2,
+    b: ScalarTensorView2,
+    beta: ScalarElem,
+    c: ScalarTensorViewMut2,
+) -> Result<()> {
+    gemm_batched(alpha, a, b, beta, c, 1, [0; 3])
+}
+
+// Multiplies `batch_size` matrices in one dispatch.
+//
+// `a`, `b` and `c` are the first matrices of each batch, and `batch_strides` are the strides
+// between the matrices of `a`, `b` and `c`. If the k dimension is split across groups, `c`
+// must be contiguous.
+#[cfg(feature = "device")]
+fn gemm_batched(
     alpha: ScalarElem,
     a: ScalarTensorView2,
     b: ScalarTensorView2,
     beta: ScalarElem,
     mut c: ScalarTensorViewMut2,
+    batch_size: u32,
+    batch_strides: [isize; 3],
 ) -> Result<()> {
     let a_scalar_type = a.scalar_type();
     let b_scalar_type = b.scalar_type();
@@ -291,6 +318,8 @@ fn gemm(
     let [rsc, csc]: [isize; 2] = c.strides().try_into().unwrap();
     let [rsc, csc] = [rsc.to_i32().unwrap(), csc.to_i32().unwrap()];
 
+    let [bsa, bsb, bsc] = batch_strides.map(|stride| stride.to_i32().unwrap());
+
     let (a, offset_a) = a.as_raw_scalar_slice_offset();
     let offset_a = offset_a.to_u32().unwrap();
     let (b, offset_b) = b.as_raw_scalar_slice_offset();
@@ -317,13 +346,16 @@ fn gemm(
                 let gemm_kernel = paste! {
                     kernels::[<gemm_ $T>]::builder()?
                     .with_threads(64)
-                    .specialize(m, k, n, groups_k, rsa, csa, rsb, csb, rsc, csc)
+                    .specialize(m, k, n, groups_k, rsa, csa, rsb, csb, rsc, csc, bsa, bsb, bsc)
                     .build(device.clone())?
-                    .with_groups(groups_k * groups_m * groups_n)
+                    .with_groups(batch_size * groups_k * groups_m * groups_n)
                 };
                 if groups_k > 1 {
                     let mut c_tmp = unsafe {
-                        Tensor::<$T, _>::uninit(device.clone(), [(m * n) as usize, groups_k as usize])?
+                        Tensor::<$T, _>::uninit(
+                            device.clone(),
+                            [(batch_size * m * n) as usize, groups_k as usize],
+                        )?
                     };
                     unsafe {
                         gemm_kernel.dispatch(
@@ -409,19 +441,84 @@ impl<T: Scalar, S1: Data<Elem = T>, S2: Data<Elem = T>> Dot<TensorBase<S2, Ix2>>
         }
         #[cfg(feature = "device")]
         {
-            let mut output = unsafe { Tensor::uninit(self.device(), [self.dim().0, rhs.dim().1])? };
-            gemm(
-                T::one().into(),
-                self.view().into(),
-                rhs.view().into(),
-                T::zero().into(),
-                output.view_mut().into(),
-            )?;
-            Ok(output)
+            ScalarTensorView::from(self.view())
+                .dot(&ScalarTensorView::from(rhs.view()))
+                .map(|output| output.try_into_tensor().unwrap())
         }
     }
 }
 
+impl<T: Scalar, S1: Data<Elem = T>, S2: Data<Elem = T>> Dot<TensorBase<S2, Ix1>>
+    for TensorBase<S1, Ix2>
+{
+    type Output = Result<Tensor1<T>>;
+    /// Matrix vector multiplication.
+    ///
+    /// Computed as a matrix multiplication with `rhs` as a column.
+    fn dot(&self, rhs: &TensorBase<S2, Ix1>) -> Self::Output {
+        let (m, k) = self.dim();
+        if k != rhs.dim() {
+            bail!(
+                "Can not dot matrix with shape {:?} and vector with shape {:?}!",
+                self.shape(),
+                rhs.shape()
+            );
+        }
+        let rhs = TensorView {
+            dim: Ix2(k, 1),
+            strides: Ix2(rhs.strides[0], 1),
+            buffer: rhs.buffer.as_slice(),
+            offset: rhs.offset,
+        };
+        Ok(self.dot(&rhs)?.into_shape(m)?)
+    }
+}
+
+/// The outer product of two vectors.
+///
+/// The output has shape \[a.len(), b.len()\], where `output[i][j] = a[i] * b[j]`.
+///
+/// Computed as a matrix multiplication of `a` as a column and `b` as a row.
+///
+/// # Errors
+/// - The tensors are on different devices.
+/// - The operation could not be executed on the device.
+///
+/// # Example
+/// ```
+/// # use autograph::tensor::{outer, Tensor};
+/// # use ndarray::array;
+/// # fn main() -> anyhow::Result<()> {
+/// let a = Tensor::from(vec![1f32, 2.]);
+/// let b = Tensor::from(vec![1f32, 2., 3.]);
+/// let c = outer(a.view(), b.view())?;
+/// assert_eq!(c.into_array()?, array![[1f32, 2., 3.], [2., 4., 6.]]);
+/// # Ok(())
+/// # }
+/// ```
+pub fn outer<T: Scalar>(a: TensorView1<T>, b: TensorView1<T>) -> Result<Tensor2<T>> {
+    if a.device() != b.device() {
+        bail!(
+            "outer expected tensors on the same device, found {:?} and {:?}!",
+            a.device(),
+            b.device()
+        );
+    }
+    let a = TensorView {
+        dim: Ix2(a.dim(), 1),
+        strides: Ix2(a.strides[0], 1),
+        buffer: a.buffer,
+        offset: a.offset,
+    };
+    let b = TensorView {
+        dim: Ix2(1, b.dim()),
+        strides: Ix2(1, b.strides[0]),
+        buffer: b.buffer,
+        offset: b.offset,
+    };
+    a.dot(&b)
+}
+
 impl<S1: ScalarData, S2: ScalarData> Dot<ScalarTensorBase<S2, Ix2>> for ScalarTensorBase<S1, Ix2> {
     type Output = Result<ScalarTensor2>;
     fn dot(&self, rhs: &ScalarTensorBase<S2, Ix2>) -> Self::Output {
@@ -455,13 +552,94 @@ impl<S1: ScalarData, S2: ScalarData> Dot<ScalarTensorBase<S2, Ix2>> for ScalarTe
             let mut output = unsafe {
                 ScalarTensor::uninit(self.device(), [self.dim().0, rhs.dim().1], scalar_type)?
             };
-            gemm(
+            if let Err(error) = gemm(
                 ScalarElem::one(scalar_type),
                 self.view(),
                 rhs.view(),
                 ScalarElem::zero(scalar_type),
                 output.view_mut(),
-            )?;
+            ) {
+                if fallback::host_fallback("dot", &device, &[scalar_type], &error) {
+                    let lhs = fallback::raw_to_host(self.view())?;
+                    let rhs = fallback::raw_to_host(rhs.view())?;
+                    return lhs.dot(&rhs)?.into_device(device);
+                }
+                return Err(error);
+            }
+            Ok(output)
+        }
+    }
+}
+
+impl<T: Scalar, S1: Data<Elem = T>> TensorBase<S1, Ix3> {
+    /// Batched matrix multiplication.
+    ///
+    /// Multiplies \[batch, m, k\] by \[batch, k, n\] to \[batch, m, n\]. Either input may be
+    /// transposed, ie with [`.permuted_axes([0, 2, 1])`](TensorBase::permuted_axes()).
+    ///
+    /// On the device, the batches are computed in one dispatch of the gemm kernel used by
+    /// [`Dot`].
+    ///
+    /// # Errors
+    /// - The batch sizes or inner dimensions do not match.
+    /// - The tensors are on different devices.
+    /// - The operation could not be executed on the device.
+    pub fn bmm<S2: Data<Elem = T>>(&self, rhs: &TensorBase<S2, Ix3>) -> Result<Tensor3<T>> {
+        let (batch_size, m, k) = self.dim();
+        let (batch_size2, k2, n) = rhs.dim();
+        if batch_size != batch_size2 || k != k2 {
+            bail!(
+                "TensorBase::bmm can not multiply {:?} by {:?}!",
+                self.shape(),
+                rhs.shape()
+            );
+        }
+        let device = self.device();
+        if device != rhs.device() {
+            bail!(
+                "TensorBase::bmm expected rhs on {device:?}, found {:?}!",
+                rhs.device()
+            );
+        }
+        if let Some((lhs_array, rhs_array)) = self.as_array().zip(rhs.as_array()) {
+            let mut output = Array::zeros([batch_size, m, n]);
+            for ((a, b), mut c) in lhs_array
+                .outer_iter()
+                .zip(rhs_array.outer_iter())
+                .zip(output.outer_iter_mut())
+            {
+                ndarray::linalg::general_mat_mul(T::one(), &a, &b, T::zero(), &mut c);
+            }
+            return Ok(output.into());
+        }
+        #[cfg(not(feature = "device"))]
+        {
+            unreachable!()
+        }
+        #[cfg(feature = "device")]
+        {
+            let mut output = unsafe { Tensor::uninit(device.clone(), [batch_size, m, n])? };
+            if output.is_empty() {
+                return Ok(output);
+            }
+            let batch_strides = [self.strides()[0], rhs.strides()[0], output.strides()[0]];
+            let result = gemm_batched(
+                ScalarElem::one(T::SCALAR_TYPE),
+                self.index_axis(Axis(0), 0).into(),
+                rhs.index_axis(Axis(0), 0).into(),
+                ScalarElem::zero(T::SCALAR_TYPE),
+                output.index_axis_mut(Axis(0), 0).into(),
+                batch_size.to_u32().unwrap(),
+                batch_strides,
+            );
+            if let Err(error) = result {
+                if fallback::host_fallback("bmm", &device, &[T::SCALAR_TYPE], &error) {
+                    let lhs = self.to_device(Device::host())?;
+                    let rhs = rhs.to_device(Device::host())?;
+                    return lhs.bmm(&rhs)?.into_device(device);
+                }
+                return Err(error);
+            }
             Ok(output)
         }
     }
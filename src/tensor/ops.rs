@@ -14,10 +14,11 @@ use dry::{macro_for, macro_wrap};
 use half::{bf16, f16};
 #[cfg(feature = "device")]
 use krnl::macros::module;
+use ndarray::DimMax;
 #[cfg(feature = "neural-network")]
 use ndarray::{Array2, Array4, Data as ArrayData, DataMut as ArrayDataMut};
-use num_traits::Unsigned;
-use std::mem::size_of;
+use num_traits::{Float, Unsigned};
+use std::{mem::size_of, ops::Range};
 
 impl<S: ScalarData, D: Dimension> ScalarTensorBase<S, D> {
     /// Converts to standard layout.
@@ -86,6 +87,182 @@ impl<S: ScalarData, D: Dimension> ScalarTensorBase<S, D> {
             self.view_mut().into_dyn(),
         )
     }
+    /// Adds `self` and `rhs`, returning a new tensor.
+    ///
+    /// Broadcasts `self` and `rhs` to a common shape, aligned from the trailing axis.
+    ///
+    /// # Errors
+    /// - The scalar types are not the same.
+    /// - Broadcasting is not possible.
+    /// - The operation could not be executed on the device.
+    ///
+    /// See [`TensorBase::add()`].
+    pub fn add<S2, D2>(
+        &self,
+        rhs: &ScalarTensorBase<S2, D2>,
+    ) -> Result<ScalarTensor<<D as DimMax<D2>>::Output>>
+    where
+        S2: ScalarData,
+        D2: Dimension,
+        D: DimMax<D2>,
+    {
+        self.binary_op(BinaryOp::Add, rhs)
+    }
+    /// Multiplies `self` and `rhs`, returning a new tensor.
+    ///
+    /// See [`.add()`](ScalarTensorBase::add()) and [`TensorBase::mul()`].
+    pub fn mul<S2, D2>(
+        &self,
+        rhs: &ScalarTensorBase<S2, D2>,
+    ) -> Result<ScalarTensor<<D as DimMax<D2>>::Output>>
+    where
+        S2: ScalarData,
+        D2: Dimension,
+        D: DimMax<D2>,
+    {
+        self.binary_op(BinaryOp::Mul, rhs)
+    }
+    /// Divides `self` by `rhs`, returning a new tensor.
+    ///
+    /// See [`.add()`](ScalarTensorBase::add()) and [`TensorBase::div()`].
+    pub fn div<S2, D2>(
+        &self,
+        rhs: &ScalarTensorBase<S2, D2>,
+    ) -> Result<ScalarTensor<<D as DimMax<D2>>::Output>>
+    where
+        S2: ScalarData,
+        D2: Dimension,
+        D: DimMax<D2>,
+    {
+        self.binary_op(BinaryOp::Div, rhs)
+    }
+    fn binary_op<S2, D2>(
+        &self,
+        op: BinaryOp,
+        rhs: &ScalarTensorBase<S2, D2>,
+    ) -> Result<ScalarTensor<<D as DimMax<D2>>::Output>>
+    where
+        S2: ScalarData,
+        D2: Dimension,
+        D: DimMax<D2>,
+    {
+        if self.scalar_type() != rhs.scalar_type() {
+            bail!(
+                "{op:?} {:?} != {:?}!",
+                self.scalar_type(),
+                rhs.scalar_type()
+            );
+        }
+        let dim = co_broadcast(&self.raw_dim(), &rhs.raw_dim())?;
+        let mut output = unsafe { ScalarTensor::uninit(self.device(), dim, self.scalar_type())? };
+        let alpha = ScalarElem::one(self.scalar_type());
+        // The op is applied as `output = op(x, output)`.
+        if op.is_div() {
+            output.assign(rhs)?;
+            scalar_assign(
+                op,
+                alpha,
+                self.view().into_dyn(),
+                output.view_mut().into_dyn(),
+            )?;
+        } else {
+            output.assign(self)?;
+            scalar_assign(
+                op,
+                alpha,
+                rhs.view().into_dyn(),
+                output.view_mut().into_dyn(),
+            )?;
+        }
+        Ok(output)
+    }
+    /// Limits each element to the range \[`min`, `max`\], returning a new tensor.
+    ///
+    /// The bounds are cast to the scalar type of the tensor. NaN is unchanged.
+    ///
+    /// # Errors
+    /// - `min` is greater than `max`.
+    /// - The operation could not be executed on the device.
+    pub fn clamp(&self, min: ScalarElem, max: ScalarElem) -> Result<ScalarTensor<D>> {
+        self.clamp_impl(Some(min), Some(max))
+    }
+    /// Limits each element to at least `min`, returning a new tensor.
+    ///
+    /// See [`.clamp()`](ScalarTensorBase::clamp()).
+    pub fn clamp_min(&self, min: ScalarElem) -> Result<ScalarTensor<D>> {
+        self.clamp_impl(Some(min), None)
+    }
+    /// Limits each element to at most `max`, returning a new tensor.
+    ///
+    /// See [`.clamp()`](ScalarTensorBase::clamp()).
+    pub fn clamp_max(&self, max: ScalarElem) -> Result<ScalarTensor<D>> {
+        self.clamp_impl(None, Some(max))
+    }
+    fn clamp_impl(
+        &self,
+        min: Option<ScalarElem>,
+        max: Option<ScalarElem>,
+    ) -> Result<ScalarTensor<D>> {
+        let scalar_type = self.scalar_type();
+        macro_for!($T in [u8, i8, u16, i16, f16, bf16, u32, i32, f32, u64, i64, f64] {
+            if scalar_type == $T::SCALAR_TYPE {
+                let min = min.map(|x| $T::try_from(x.scalar_cast(scalar_type)).unwrap());
+                let max = max.map(|x| $T::try_from(x.scalar_cast(scalar_type)).unwrap());
+                if let Some((min, max)) = min.zip(max) {
+                    if min > max {
+                        bail!("clamp min {min:?} > max {max:?}!");
+                    }
+                }
+                let input = self.view().try_into_tensor_view::<$T>().unwrap();
+                if let Some(input) = input.as_array() {
+                    let output = input.as_standard_layout().mapv(|x| {
+                        match (min, max) {
+                            (Some(min), _) if x < min => min,
+                            (_, Some(max)) if x > max => max,
+                            _ => x,
+                        }
+                    });
+                    return Ok(Tensor::from(output).into());
+                }
+                #[cfg(feature = "device")]
+                {
+                    let input = input.as_standard_layout()?;
+                    let mut output = unsafe { Tensor::<$T, _>::uninit(input.device(), input.raw_dim())? };
+                    let kernel = paste! {
+                        kernels::[<clamp_ $T>]::builder()?.build(output.device())?
+                    };
+                    kernel.dispatch(
+                        min.is_some().into(),
+                        min.unwrap_or_default(),
+                        max.is_some().into(),
+                        max.unwrap_or_default(),
+                        input.as_slice().unwrap(),
+                        output.as_slice_mut().unwrap(),
+                    )?;
+                    return Ok(output.into());
+                }
+            }
+        });
+        bail!("clamp {scalar_type:?} unimplemented!");
+    }
+    // Performs the operation `self *= alpha * rhs`, broadcasting `rhs`.
+    pub(crate) fn scaled_mul<S2, D2>(
+        &mut self,
+        alpha: ScalarElem,
+        rhs: &ScalarTensorBase<S2, D2>,
+    ) -> Result<()>
+    where
+        S: ScalarDataMut,
+        S2: ScalarData,
+        D2: Dimension,
+    {
+        scalar_assign(
+            BinaryOp::Mul,
+            alpha,
+            rhs.view().into_dyn(),
+            self.view_mut().into_dyn(),
+        )
+    }
     /// Performs the operation `self as _ * alpha`.
     ///
     /// # Errors
@@ -201,6 +378,381 @@ impl<T: Scalar, S: Data<Elem = T>, D: Dimension> TensorBase<S, D> {
             self.view_mut().into_dyn(),
         )
     }
+    /// Permutes the axes of the tensor, returning a new tensor in standard layout.
+    ///
+    /// Equivalent to [`.permuted_axes()`](TensorBase::permuted_axes()) followed by
+    /// [`.into_standard_layout()`](TensorBase::into_standard_layout()).
+    ///
+    /// # Errors
+    /// - `axes` is not a permutation of 0 .. ndim.
+    /// - The operation could not be executed on the device.
+    pub fn permute<A>(&self, axes: A) -> Result<Tensor<T, D>>
+    where
+        A: IntoDimension<Dim = D>,
+    {
+        let axes = axes.into_dimension();
+        let mut used = vec![false; self.ndim()];
+        let valid = axes.ndim() == self.ndim()
+            && axes
+                .slice()
+                .iter()
+                .all(|a| *a < used.len() && !std::mem::replace(&mut used[*a], true));
+        if !valid {
+            bail!(
+                "TensorBase::permute axes {:?} is not a permutation of 0..{}!",
+                axes.slice(),
+                self.ndim()
+            );
+        }
+        self.view().permuted_axes(axes).into_standard_layout()
+    }
+    /// Adds `self` and `rhs`, returning a new tensor.
+    ///
+    /// Broadcasts `self` and `rhs` to a common shape, aligned from the trailing axis, where axes
+    /// of length 1 are repeated. For example, \[n, c, h, w\] + \[c, 1, 1\] is \[n, c, h, w\].
+    ///
+    /// # Errors
+    /// - Broadcasting is not possible.
+    /// - The operation could not be executed on the device.
+    ///
+    /// See [`.scaled_add()`](TensorBase::scaled_add()).
+    pub fn add<S2, D2>(
+        &self,
+        rhs: &TensorBase<S2, D2>,
+    ) -> Result<Tensor<T, <D as DimMax<D2>>::Output>>
+    where
+        S2: Data<Elem = T>,
+        D2: Dimension,
+        D: DimMax<D2>,
+    {
+        self.binary_op(BinaryOp::Add, rhs)
+    }
+    /// Multiplies `self` and `rhs`, returning a new tensor.
+    ///
+    /// Broadcasts like [`.add()`](TensorBase::add()).
+    ///
+    /// # Errors
+    /// - Broadcasting is not possible.
+    /// - The operation could not be executed on the device.
+    pub fn mul<S2, D2>(
+        &self,
+        rhs: &TensorBase<S2, D2>,
+    ) -> Result<Tensor<T, <D as DimMax<D2>>::Output>>
+    where
+        S2: Data<Elem = T>,
+        D2: Dimension,
+        D: DimMax<D2>,
+    {
+        self.binary_op(BinaryOp::Mul, rhs)
+    }
+    /// Divides `self` by `rhs`, returning a new tensor.
+    ///
+    /// Broadcasts like [`.add()`](TensorBase::add()).
+    ///
+    /// Integer division truncates toward zero. Float division follows IEEE 754, dividing by zero
+    /// produces inf or NaN.
+    ///
+    /// # Errors
+    /// - Broadcasting is not possible.
+    /// - Integer division by zero on the host. On a device the result is unspecified.
+    /// - The operation could not be executed on the device.
+    pub fn div<S2, D2>(
+        &self,
+        rhs: &TensorBase<S2, D2>,
+    ) -> Result<Tensor<T, <D as DimMax<D2>>::Output>>
+    where
+        S2: Data<Elem = T>,
+        D2: Dimension,
+        D: DimMax<D2>,
+    {
+        self.binary_op(BinaryOp::Div, rhs)
+    }
+    fn binary_op<S2, D2>(
+        &self,
+        op: BinaryOp,
+        rhs: &TensorBase<S2, D2>,
+    ) -> Result<Tensor<T, <D as DimMax<D2>>::Output>>
+    where
+        S2: Data<Elem = T>,
+        D2: Dimension,
+        D: DimMax<D2>,
+    {
+        let dim = co_broadcast(&self.raw_dim(), &rhs.raw_dim())?;
+        let mut output = unsafe { Tensor::uninit(self.device(), dim)? };
+        // The op is applied as `output = op(x, output)`.
+        if op.is_div() {
+            output.assign(rhs)?;
+            assign(
+                op,
+                T::one(),
+                self.view().into_dyn(),
+                output.view_mut().into_dyn(),
+            )?;
+        } else {
+            output.assign(self)?;
+            assign(
+                op,
+                T::one(),
+                rhs.view().into_dyn(),
+                output.view_mut().into_dyn(),
+            )?;
+        }
+        Ok(output)
+    }
+    /// Whether each element is equal to `rhs`, returning a mask of 1 or 0.
+    ///
+    /// Broadcasts like [`.add()`](TensorBase::add()).
+    ///
+    /// # Errors
+    /// - Broadcasting is not possible.
+    /// - The tensors are not on the same device.
+    /// - The operation could not be executed on the device.
+    pub fn eq<S2, D2>(
+        &self,
+        rhs: &TensorBase<S2, D2>,
+    ) -> Result<Tensor<u8, <D as DimMax<D2>>::Output>>
+    where
+        S2: Data<Elem = T>,
+        D2: Dimension,
+        D: DimMax<D2>,
+    {
+        self.compare(rhs, CompareOp::Eq)
+    }
+    /// Whether each element is not equal to `rhs`, returning a mask of 1 or 0.
+    ///
+    /// See [`.eq()`](TensorBase::eq()).
+    pub fn ne<S2, D2>(
+        &self,
+        rhs: &TensorBase<S2, D2>,
+    ) -> Result<Tensor<u8, <D as DimMax<D2>>::Output>>
+    where
+        S2: Data<Elem = T>,
+        D2: Dimension,
+        D: DimMax<D2>,
+    {
+        self.compare(rhs, CompareOp::Ne)
+    }
+    /// Whether each element is greater than `rhs`, returning a mask of 1 or 0.
+    ///
+    /// See [`.eq()`](TensorBase::eq()).
+    pub fn gt<S2, D2>(
+        &self,
+        rhs: &TensorBase<S2, D2>,
+    ) -> Result<Tensor<u8, <D as DimMax<D2>>::Output>>
+    where
+        S2: Data<Elem = T>,
+        D2: Dimension,
+        D: DimMax<D2>,
+    {
+        self.compare(rhs, CompareOp::Gt)
+    }
+    /// Whether each element is greater than or equal to `rhs`, returning a mask of 1 or 0.
+    ///
+    /// See [`.eq()`](TensorBase::eq()).
+    pub fn ge<S2, D2>(
+        &self,
+        rhs: &TensorBase<S2, D2>,
+    ) -> Result<Tensor<u8, <D as DimMax<D2>>::Output>>
+    where
+        S2: Data<Elem = T>,
+        D2: Dimension,
+        D: DimMax<D2>,
+    {
+        self.compare(rhs, CompareOp::Ge)
+    }
+    /// Whether each element is less than `rhs`, returning a mask of 1 or 0.
+    ///
+    /// See [`.eq()`](TensorBase::eq()).
+    pub fn lt<S2, D2>(
+        &self,
+        rhs: &TensorBase<S2, D2>,
+    ) -> Result<Tensor<u8, <D as DimMax<D2>>::Output>>
+    where
+        S2: Data<Elem = T>,
+        D2: Dimension,
+        D: DimMax<D2>,
+    {
+        self.compare(rhs, CompareOp::Lt)
+    }
+    /// Whether each element is less than or equal to `rhs`, returning a mask of 1 or 0.
+    ///
+    /// See [`.eq()`](TensorBase::eq()).
+    pub fn le<S2, D2>(
+        &self,
+        rhs: &TensorBase<S2, D2>,
+    ) -> Result<Tensor<u8, <D as DimMax<D2>>::Output>>
+    where
+        S2: Data<Elem = T>,
+        D2: Dimension,
+        D: DimMax<D2>,
+    {
+        self.compare(rhs, CompareOp::Le)
+    }
+    fn compare<S2, D2>(
+        &self,
+        rhs: &TensorBase<S2, D2>,
+        op: CompareOp,
+    ) -> Result<Tensor<u8, <D as DimMax<D2>>::Output>>
+    where
+        S2: Data<Elem = T>,
+        D2: Dimension,
+        D: DimMax<D2>,
+    {
+        use ndarray::Zip;
+
+        if self.device() != rhs.device() {
+            bail!(
+                "TensorBase::compare expected rhs on {:?}, found {:?}!",
+                self.device(),
+                rhs.device()
+            );
+        }
+        let dim = co_broadcast(&self.raw_dim(), &rhs.raw_dim())?;
+        if let Some((lhs, rhs)) = self.as_array().zip(rhs.as_array()) {
+            let output = Zip::from(lhs.broadcast(dim.clone()).unwrap())
+                .and(rhs.broadcast(dim).unwrap())
+                .map_collect(|a, b| op.eval(*a, *b) as u8);
+            return Ok(output.into());
+        }
+        #[cfg(not(feature = "device"))]
+        {
+            unreachable!()
+        }
+        #[cfg(feature = "device")]
+        {
+            // Broadcasts with the assign kernels, then compares elementwise.
+            let mut lhs = unsafe { Tensor::<T, _>::uninit(self.device(), dim.clone())? };
+            lhs.assign(self)?;
+            let mut rhs_broadcast = unsafe { Tensor::<T, _>::uninit(self.device(), dim.clone())? };
+            rhs_broadcast.assign(rhs)?;
+            let mut output = unsafe { Tensor::<u8, _>::uninit(self.device(), dim)? };
+            if output.is_empty() {
+                return Ok(output);
+            }
+            macro_for!($T in [u8, i8, u16, i16, f16, bf16, u32, i32, f32, u64, i64, f64] {
+                if T::SCALAR_TYPE == $T::SCALAR_TYPE {
+                    let a = ScalarTensorView::from(lhs.view()).try_into_tensor_view::<$T>().unwrap();
+                    let b = ScalarTensorView::from(rhs_broadcast.view())
+                        .try_into_tensor_view::<$T>()
+                        .unwrap();
+                    let kernel = paste! {
+                        kernels::[<compare_ $T>]::builder()?
+                            .specialize(op.as_u32())
+                            .build(output.device())?
+                    };
+                    kernel.dispatch(
+                        a.as_slice().unwrap(),
+                        b.as_slice().unwrap(),
+                        output.as_slice_mut().unwrap(),
+                    )?;
+                    return Ok(output);
+                }
+            });
+            unreachable!()
+        }
+    }
+    /// Sets elements where `mask` is nonzero to `value`, returning a new tensor.
+    ///
+    /// Broadcasts `mask` to the shape of `self`.
+    ///
+    /// # Errors
+    /// - Broadcasting is not possible.
+    /// - The tensors are not on the same device.
+    /// - The operation could not be executed on the device.
+    pub fn masked_fill<S2, D2>(&self, mask: &TensorBase<S2, D2>, value: T) -> Result<Tensor<T, D>>
+    where
+        S2: Data<Elem = u8>,
+        D2: Dimension,
+    {
+        if self.device() != mask.device() {
+            bail!(
+                "TensorBase::masked_fill expected mask on {:?}, found {:?}!",
+                self.device(),
+                mask.device()
+            );
+        }
+        if let Some(mut output) = self.as_array().map(|x| x.to_owned()) {
+            let mask_array = mask.as_array().unwrap();
+            let Some(mask) = mask_array.broadcast(output.raw_dim()) else {
+                bail!(
+                    "Broadcast not possible! {:?} -> {:?}",
+                    mask.shape(),
+                    output.shape()
+                );
+            };
+            output.zip_mut_with(&mask, |y, m| {
+                if *m != 0 {
+                    *y = value;
+                }
+            });
+            return Ok(output.into());
+        }
+        #[cfg(not(feature = "device"))]
+        {
+            unreachable!()
+        }
+        #[cfg(feature = "device")]
+        {
+            let Some(mask_broadcast) = mask.broadcast(self.raw_dim()) else {
+                bail!(
+                    "Broadcast not possible! {:?} -> {:?}",
+                    mask.shape(),
+                    self.shape()
+                );
+            };
+            let mut mask = unsafe { Tensor::<u8, _>::uninit(self.device(), self.raw_dim())? };
+            mask.assign(&mask_broadcast)?;
+            let mut output = unsafe { Tensor::<T, _>::uninit(self.device(), self.raw_dim())? };
+            output.assign(self)?;
+            if output.is_empty() {
+                return Ok(output);
+            }
+            macro_for!($T in [u8, i8, u16, i16, f16, bf16, u32, i32, f32, u64, i64, f64] {
+                if T::SCALAR_TYPE == $T::SCALAR_TYPE {
+                    let mut y = ScalarTensorViewMut::from(output.view_mut())
+                        .try_into_tensor_view_mut::<$T>()
+                        .unwrap();
+                    let kernel = paste! {
+                        kernels::[<masked_fill_ $T>]::builder()?.build(y.device())?
+                    };
+                    kernel.dispatch(
+                        value.cast(),
+                        mask.as_slice().unwrap(),
+                        y.as_slice_mut().unwrap(),
+                    )?;
+                    return Ok(output);
+                }
+            });
+            unreachable!()
+        }
+    }
+    /// Limits each element to the range \[`min`, `max`\], returning a new tensor.
+    ///
+    /// See [`ScalarTensorBase::clamp()`].
+    pub fn clamp(&self, min: ScalarElem, max: ScalarElem) -> Result<Tensor<T, D>> {
+        Ok(ScalarTensorView::from(self.view())
+            .clamp(min, max)?
+            .try_into_tensor()
+            .unwrap())
+    }
+    /// Limits each element to at least `min`, returning a new tensor.
+    ///
+    /// See [`ScalarTensorBase::clamp_min()`].
+    pub fn clamp_min(&self, min: ScalarElem) -> Result<Tensor<T, D>> {
+        Ok(ScalarTensorView::from(self.view())
+            .clamp_min(min)?
+            .try_into_tensor()
+            .unwrap())
+    }
+    /// Limits each element to at most `max`, returning a new tensor.
+    ///
+    /// See [`ScalarTensorBase::clamp_max()`].
+    pub fn clamp_max(&self, max: ScalarElem) -> Result<Tensor<T, D>> {
+        Ok(ScalarTensorView::from(self.view())
+            .clamp_max(max)?
+            .try_into_tensor()
+            .unwrap())
+    }
     /// Performs the operation `self as T2 * alpha`.
     ///
     /// # Errors
@@ -235,6 +787,496 @@ impl<T: Scalar, S: Data<Elem = T>, D: Dimension> TensorBase<S, D> {
             self.view_mut().into_dyn(),
         )
     }
+    /// Copies `src` into the slice `range` along `axis`, in place.
+    ///
+    /// Elements outside of the slice are not modified.
+    ///
+    /// # Errors
+    /// - `axis` or `range` is out of bounds.
+    /// - The shape of `src` does not match the slice.
+    /// - The operation could not be executed on the device.
+    pub fn assign_slice<S2, D2>(
+        &mut self,
+        axis: Axis,
+        range: Range<usize>,
+        src: &TensorBase<S2, D2>,
+    ) -> Result<()>
+    where
+        S: DataMut,
+        S2: Data<Elem = T>,
+        D2: Dimension,
+    {
+        let Axis(index) = axis;
+        if index >= self.ndim() {
+            bail!(
+                "TensorBase::assign_slice axis {index} out of bounds for tensor with ndim {}!",
+                self.ndim()
+            );
+        }
+        let len = self.shape()[index];
+        if range.start > range.end || range.end > len {
+            bail!("TensorBase::assign_slice range {range:?} out of bounds for axis {index} with length {len}!");
+        }
+        let mut view = self.view_mut();
+        if !range.is_empty() {
+            view.offset =
+                (view.offset as isize + range.start as isize * view.strides()[index]) as usize;
+        }
+        view.dim[index] = range.len();
+        if src.shape() != view.shape() {
+            bail!(
+                "TensorBase::assign_slice expected src with shape {:?}, found {:?}!",
+                view.shape(),
+                src.shape()
+            );
+        }
+        view.assign(src)
+    }
+    /// Gathers elements along `axis` at `indices`.
+    ///
+    /// The output has the shape of `indices`, where `output[i][j] = self[indices[i][j]][j]` for
+    /// `axis` 0 and `output[i][j] = self[i][indices[i][j]]` for `axis` 1. The length of each
+    /// other axis of `indices` must not be greater than that of `self`.
+    ///
+    /// # Errors
+    /// - `axis` is out of bounds.
+    /// - The shape of `indices` is not compatible.
+    /// - The tensors are not on the same device.
+    /// - An index is out of bounds.
+    /// - The operation could not be executed on the device.
+    ///
+    /// # Example
+    /// ```
+    /// # use autograph::tensor::Tensor;
+    /// # use ndarray::{array, Axis};
+    /// # fn main() -> anyhow::Result<()> {
+    /// let x = Tensor::from(array![[1f32, 2.], [3., 4.]]);
+    /// let indices = Tensor::from(array![[1u32, 1], [0, 1]]);
+    /// let y = x.gather(Axis(1), indices.view())?;
+    /// assert_eq!(y.into_array()?, array![[2f32, 2.], [3., 4.]]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn gather(&self, axis: Axis, indices: TensorView<u32, D>) -> Result<Tensor<T, D>> {
+        check_gather_scatter("gather", axis, self.shape(), indices.shape())?;
+        check_gather_scatter_device("gather", &self.device(), &indices.device())?;
+        let len = self.shape()[axis.0];
+        if let Some((input, indices)) = self.as_array().zip(indices.as_array()) {
+            let dim = indices.raw_dim();
+            let input = input.into_dyn();
+            let mut output = Vec::with_capacity(indices.len());
+            for (mut index, i) in indices.into_dyn().indexed_iter() {
+                let i = *i as usize;
+                if i >= len {
+                    bail!("TensorBase::gather index {i} out of bounds for axis with length {len}!");
+                }
+                index[axis.0] = i;
+                output.push(input[index.slice()]);
+            }
+            let output = Array::from_shape_vec(dim, output)?;
+            return Ok(output.into());
+        }
+        #[cfg(not(feature = "device"))]
+        {
+            unreachable!()
+        }
+        #[cfg(feature = "device")]
+        {
+            check_gather_scatter_indices("gather", indices.view(), len)?;
+            let [_, m, inner] = outer_axis_inner(indices.shape(), axis);
+            let input =
+                gather_scatter_view(self.view(), axis, indices.shape()).as_standard_layout()?;
+            let indices = indices.as_standard_layout()?;
+            macro_for!($T in [u8, i8, u16, i16, f16, bf16, u32, i32, f32, u64, i64, f64] {
+                if T::SCALAR_TYPE == $T::SCALAR_TYPE {
+                    let x = ScalarTensorView::from(input.view()).try_into_tensor_view::<$T>().unwrap();
+                    let mut y = unsafe { Tensor::<$T, _>::uninit(x.device(), indices.raw_dim())? };
+                    if !y.is_empty() {
+                        let kernel = paste! {
+                            kernels::[<gather_ $T>]::builder()?.build(y.device())?
+                        };
+                        kernel.dispatch(
+                            len.to_u32().unwrap(),
+                            m,
+                            inner,
+                            x.as_slice().unwrap(),
+                            indices.as_slice().unwrap(),
+                            y.as_slice_mut().unwrap(),
+                        )?;
+                    }
+                    return Ok(y.cast_into().unwrap());
+                }
+            });
+            unreachable!()
+        }
+    }
+    /// Adds `src` into the tensor along `axis` at `indices`.
+    ///
+    /// The inverse of [`.gather()`](TensorBase::gather()), where `self[indices[i][j]][j] += src[i][j]`
+    /// for `axis` 0 and `self[i][indices[i][j]] += src[i][j]` for `axis` 1. Repeated indices are
+    /// accumulated. `src` must have the shape of `indices`.
+    ///
+    /// On the device, each element sums the values of `src` at its index without atomics, so
+    /// repeated indices are accumulated in order. The tensor is not modified on error.
+    ///
+    /// # Errors
+    /// - `axis` is out of bounds.
+    /// - The shape of `indices` or `src` is not compatible.
+    /// - The tensors are not on the same device.
+    /// - An index is out of bounds.
+    /// - The operation could not be executed on the device.
+    pub fn scatter_add(
+        &mut self,
+        axis: Axis,
+        indices: TensorView<u32, D>,
+        src: TensorView<T, D>,
+    ) -> Result<()>
+    where
+        S: DataMut,
+    {
+        check_gather_scatter("scatter_add", axis, self.shape(), indices.shape())?;
+        if src.shape() != indices.shape() {
+            bail!(
+                "TensorBase::scatter_add expected src with shape {:?}, found {:?}!",
+                indices.shape(),
+                src.shape()
+            );
+        }
+        check_gather_scatter_device("scatter_add", &self.device(), &indices.device())?;
+        check_gather_scatter_device("scatter_add", &self.device(), &src.device())?;
+        let len = self.shape()[axis.0];
+        if self.device().is_host() {
+            let indices = indices.as_array().unwrap().into_dyn();
+            let src = src.as_array().unwrap();
+            if let Some(i) = indices.iter().find(|i| **i as usize >= len) {
+                bail!(
+                    "TensorBase::scatter_add index {i} out of bounds for axis with length {len}!"
+                );
+            }
+            let mut output = self.as_array_mut().unwrap().into_dyn();
+            for ((mut index, i), x) in indices.indexed_iter().zip(src.iter()) {
+                index[axis.0] = *i as usize;
+                let y = &mut output[index.slice()];
+                *y = *y + *x;
+            }
+            return Ok(());
+        }
+        #[cfg(not(feature = "device"))]
+        {
+            unreachable!()
+        }
+        #[cfg(feature = "device")]
+        {
+            check_gather_scatter_indices("scatter_add", indices.view(), len)?;
+            let [_, m, inner] = outer_axis_inner(indices.shape(), axis);
+            let indices = indices.as_standard_layout()?;
+            let src = src.as_standard_layout()?;
+            let mut dim = indices.raw_dim();
+            dim[axis.0] = len;
+            macro_for!($T in [u8, i8, u16, i16, f16, bf16, u32, i32, f32, u64, i64, f64] {
+                if T::SCALAR_TYPE == $T::SCALAR_TYPE {
+                    let x = ScalarTensorView::from(src.view()).try_into_tensor_view::<$T>().unwrap();
+                    let mut y = unsafe { Tensor::<$T, _>::uninit(x.device(), dim)? };
+                    if !y.is_empty() {
+                        let kernel = paste! {
+                            kernels::[<scatter_add_ $T>]::builder()?.build(y.device())?
+                        };
+                        kernel.dispatch(
+                            len.to_u32().unwrap(),
+                            m,
+                            inner,
+                            indices.as_slice().unwrap(),
+                            x.as_slice().unwrap(),
+                            y.as_slice_mut().unwrap(),
+                        )?;
+                    }
+                    let y = y.cast_into::<T>().unwrap();
+                    let mut output = gather_scatter_view(self.view_mut(), axis, indices.shape());
+                    return output.scaled_add(T::one(), &y);
+                }
+            });
+            unreachable!()
+        }
+    }
+    /// Splits the tensor along `axis` into tensors with lengths `sizes`.
+    ///
+    /// Returns owned tensors in standard layout.
+    ///
+    /// # Errors
+    /// - `axis` is out of bounds.
+    /// - `sizes` does not sum to the length of `axis`.
+    /// - The operation could not be executed on the device.
+    pub fn split(&self, axis: Axis, sizes: &[usize]) -> Result<Vec<Tensor<T, D>>> {
+        let Axis(index) = axis;
+        if index >= self.ndim() {
+            bail!(
+                "TensorBase::split axis {index} out of bounds for tensor with ndim {}!",
+                self.ndim()
+            );
+        }
+        let len = self.shape()[index];
+        if sizes.iter().sum::<usize>() != len {
+            bail!(
+                "TensorBase::split sizes {sizes:?} do not sum to the length {len} of axis {index}!"
+            );
+        }
+        let mut start = 0;
+        sizes
+            .iter()
+            .map(|size| {
+                let mut view = self.view();
+                if *size > 0 {
+                    view.offset =
+                        (view.offset as isize + start as isize * view.strides()[index]) as usize;
+                }
+                view.dim[index] = *size;
+                start += size;
+                let mut output = unsafe { Tensor::uninit(self.device(), view.raw_dim())? };
+                output.assign(&view)?;
+                Ok(output)
+            })
+            .collect()
+    }
+}
+
+/// Selects elements from `a` where `cond` is nonzero, otherwise from `b`.
+///
+/// Broadcasts `cond`, `a`, and `b` to a common shape like [`TensorBase::add()`]. The tensors must be
+/// on the same device.
+///
+/// # Errors
+/// - Broadcasting is not possible.
+/// - The devices are not the same.
+/// - The operation could not be executed on the device.
+///
+/// # Example
+/// ```
+/// # use autograph::tensor::{where_, Tensor};
+/// # fn main() -> anyhow::Result<()> {
+/// let cond = Tensor::from(vec![1u8, 0, 1]);
+/// let a = Tensor::from(vec![1f32, 2., 3.]);
+/// let b = Tensor::from_elem(a.device(), (), 0f32)?;
+/// let y = where_(cond.view(), a.view(), b.view())?;
+/// assert_eq!(y.into_array()?.to_vec(), [1., 0., 3.]);
+/// # Ok(())
+/// # }
+/// ```
+#[allow(clippy::type_complexity)]
+pub fn where_<T, D1, D2, D3>(
+    cond: TensorView<u8, D1>,
+    a: TensorView<T, D2>,
+    b: TensorView<T, D3>,
+) -> Result<Tensor<T, <<D1 as DimMax<D2>>::Output as DimMax<D3>>::Output>>
+where
+    T: Scalar,
+    D1: Dimension + DimMax<D2>,
+    D2: Dimension,
+    D3: Dimension,
+    <D1 as DimMax<D2>>::Output: DimMax<D3>,
+{
+    use ndarray::Zip;
+
+    let device = cond.device();
+    if a.device() != device || b.device() != device {
+        bail!(
+            "where_ expected tensors on the same device, found {:?}, {:?}, {:?}!",
+            device,
+            a.device(),
+            b.device()
+        );
+    }
+    let dim = co_broadcast(&co_broadcast(&cond.raw_dim(), &a.raw_dim())?, &b.raw_dim())?;
+    if let Some(((cond, a), b)) = cond.as_array().zip(a.as_array()).zip(b.as_array()) {
+        let output = Zip::from(cond.broadcast(dim.clone()).unwrap())
+            .and(a.broadcast(dim.clone()).unwrap())
+            .and(b.broadcast(dim).unwrap())
+            .map_collect(|c, a, b| if *c != 0 { *a } else { *b });
+        return Ok(output.into());
+    }
+    #[cfg(not(feature = "device"))]
+    {
+        unreachable!()
+    }
+    #[cfg(feature = "device")]
+    {
+        // Broadcasts with the assign kernels, then selects elementwise.
+        let mut cond_broadcast = unsafe { Tensor::<u8, _>::uninit(device.clone(), dim.clone())? };
+        cond_broadcast.assign(&cond)?;
+        let mut a_broadcast = unsafe { Tensor::<T, _>::uninit(device.clone(), dim.clone())? };
+        a_broadcast.assign(&a)?;
+        let mut b_broadcast = unsafe { Tensor::<T, _>::uninit(device.clone(), dim.clone())? };
+        b_broadcast.assign(&b)?;
+        let mut output = unsafe { Tensor::<T, _>::uninit(device, dim)? };
+        if output.is_empty() {
+            return Ok(output);
+        }
+        macro_for!($T in [u8, i8, u16, i16, f16, bf16, u32, i32, f32, u64, i64, f64] {
+            if T::SCALAR_TYPE == $T::SCALAR_TYPE {
+                let a = ScalarTensorView::from(a_broadcast.view())
+                    .try_into_tensor_view::<$T>()
+                    .unwrap();
+                let b = ScalarTensorView::from(b_broadcast.view())
+                    .try_into_tensor_view::<$T>()
+                    .unwrap();
+                let mut y = ScalarTensorViewMut::from(output.view_mut())
+                    .try_into_tensor_view_mut::<$T>()
+                    .unwrap();
+                let kernel = paste! {
+                    kernels::[<where_ $T>]::builder()?.build(y.device())?
+                };
+                kernel.dispatch(
+                    cond_broadcast.as_slice().unwrap(),
+                    a.as_slice().unwrap(),
+                    b.as_slice().unwrap(),
+                    y.as_slice_mut().unwrap(),
+                )?;
+                return Ok(output);
+            }
+        });
+        unreachable!()
+    }
+}
+
+// Checks the shapes for gather and scatter_add along `axis`.
+fn check_gather_scatter(
+    name: &str,
+    Axis(axis): Axis,
+    shape: &[usize],
+    indices_shape: &[usize],
+) -> Result<()> {
+    if axis >= shape.len() {
+        bail!(
+            "TensorBase::{name} axis {axis} out of bounds for tensor with ndim {}!",
+            shape.len()
+        );
+    }
+    if indices_shape.len() != shape.len()
+        || indices_shape
+            .iter()
+            .zip(shape)
+            .enumerate()
+            .any(|(i, (a, b))| i != axis && a > b)
+    {
+        bail!("TensorBase::{name} indices with shape {indices_shape:?} not compatible with tensor with shape {shape:?} along axis {axis}!");
+    }
+    Ok(())
+}
+
+fn check_gather_scatter_device(name: &str, device: &Device, indices_device: &Device) -> Result<()> {
+    if device != indices_device {
+        bail!("TensorBase::{name} expected indices on {device:?}, found {indices_device:?}!");
+    }
+    Ok(())
+}
+
+// Checks the indices on the device, before the kernel, which can not report errors.
+#[cfg(feature = "device")]
+fn check_gather_scatter_indices<D: Dimension>(
+    name: &str,
+    indices: TensorView<u32, D>,
+    len: usize,
+) -> Result<()> {
+    if !indices.is_empty() {
+        let i = indices.max()? as usize;
+        if i >= len {
+            bail!("TensorBase::{name} index {i} out of bounds for axis with length {len}!");
+        }
+    }
+    Ok(())
+}
+
+// The lengths of `shape` before, along, and after `axis`.
+#[cfg(feature = "device")]
+fn outer_axis_inner(shape: &[usize], Axis(axis): Axis) -> [u32; 3] {
+    [
+        shape[..axis].iter().product::<usize>(),
+        shape[axis],
+        shape[axis + 1..].iter().product::<usize>(),
+    ]
+    .map(|x| x.to_u32().unwrap())
+}
+
+// Restricts each axis of `x` other than `axis` to the length of `indices_shape`.
+#[cfg(feature = "device")]
+fn gather_scatter_view<T: Scalar, S: Data<Elem = T>, D: Dimension>(
+    mut x: TensorBase<S, D>,
+    Axis(axis): Axis,
+    indices_shape: &[usize],
+) -> TensorBase<S, D> {
+    for (i, len) in indices_shape.iter().copied().enumerate() {
+        if i != axis {
+            x.slice_axis_inplace(Axis(i), 0..len);
+        }
+    }
+    x
+}
+
+/// Stacks `tensors` along a new `axis`.
+///
+/// The tensors must have the same shape and device. The output has the shape of the tensors with
+/// the length of `tensors` inserted at `axis`.
+///
+/// # Errors
+/// - `tensors` is empty.
+/// - `axis` is greater than the number of dimensions of the tensors.
+/// - The shapes or devices are not the same.
+/// - The operation could not be executed on the device.
+///
+/// See [`ndarray::stack()`].
+///
+/// # Example
+/// ```
+/// # use autograph::tensor::{stack, Tensor};
+/// # use ndarray::Axis;
+/// # fn main() -> anyhow::Result<()> {
+/// let a = Tensor::from(vec![1f32, 2.]);
+/// let b = Tensor::from(vec![3f32, 4.]);
+/// let c = stack(Axis(0), &[a.view(), b.view()])?;
+/// assert_eq!(c.shape(), &[2, 2]);
+/// # Ok(())
+/// # }
+/// ```
+pub fn stack<T: Scalar, D: Dimension>(
+    axis: Axis,
+    tensors: &[TensorView<T, D>],
+) -> Result<Tensor<T, D::Larger>> {
+    let Some(first) = tensors.first() else {
+        bail!("stack expected at least one tensor!");
+    };
+    let ndim = first.ndim();
+    if axis.0 > ndim {
+        bail!("stack axis {} out of bounds for ndim {ndim}!", axis.0);
+    }
+    for tensor in tensors.iter().skip(1) {
+        if tensor.shape() != first.shape() {
+            bail!(
+                "stack expected tensors with shape {:?}, found {:?}!",
+                first.shape(),
+                tensor.shape()
+            );
+        }
+        if tensor.device() != first.device() {
+            bail!(
+                "stack expected tensors on {:?}, found {:?}!",
+                first.device(),
+                tensor.device()
+            );
+        }
+    }
+    let mut dim = D::Larger::zeros(ndim + 1);
+    let mut shape = first.shape().iter().copied();
+    for (i, d) in dim.slice_mut().iter_mut().enumerate() {
+        *d = if i == axis.0 {
+            tensors.len()
+        } else {
+            shape.next().unwrap()
+        };
+    }
+    let mut output = unsafe { Tensor::uninit(first.device(), dim)? };
+    for (i, tensor) in tensors.iter().enumerate() {
+        output.index_axis_mut(axis, i).assign(tensor)?;
+    }
+    Ok(output)
 }
 
 impl<T: Scalar, S: DataMut<Elem = T>, D: Dimension, S2: Data<Elem = T>, D2: Dimension>
@@ -245,12 +1287,84 @@ impl<T: Scalar, S: DataMut<Elem = T>, D: Dimension, S2: Data<Elem = T>, D2: Dime
     }
 }
 
-impl<T: Scalar, S: DataMut<Elem = T>, D: Dimension, S2: Data<Elem = T>, D2: Dimension>
-    AddAssign<&TensorBase<S2, D2>> for TensorBase<S, D>
+impl<T: Scalar, S: DataMut<Elem = T>, D: Dimension, S2: Data<Elem = T>, D2: Dimension>
+    AddAssign<&TensorBase<S2, D2>> for TensorBase<S, D>
+{
+    fn add_assign(&mut self, rhs: &TensorBase<S2, D2>) -> Result<()> {
+        self.scaled_add(T::one(), rhs)
+    }
+}
+
+// Sets the elements of the matrix outside of the triangle bounded by `diagonal` to 0.
+//
+// `y` must be in standard layout.
+pub(super) fn triangle_in_place<T: Scalar>(
+    y: &mut Tensor2<T>,
+    diagonal: isize,
+    lower: bool,
+) -> Result<()> {
+    if let Some(mut y) = y.as_array_mut() {
+        for ((i, j), y) in y.indexed_iter_mut() {
+            if !in_triangle(i, j, diagonal, lower) {
+                *y = T::zero();
+            }
+        }
+        return Ok(());
+    }
+    #[cfg(not(feature = "device"))]
+    {
+        unreachable!()
+    }
+    #[cfg(feature = "device")]
+    {
+        if y.is_empty() {
+            return Ok(());
+        }
+        let (rows, cols) = y.dim();
+        let bound = rows.max(cols) as isize;
+        let diagonal = diagonal.clamp(-bound, bound).to_i32().unwrap();
+        let cols = cols.to_u32().unwrap();
+        macro_for!($T in [u8, i8, u16, i16, f16, bf16, u32, i32, f32, u64, i64, f64] {
+            if T::SCALAR_TYPE == $T::SCALAR_TYPE {
+                let mut y = ScalarTensorViewMut::from(y.view_mut())
+                    .try_into_tensor_view_mut::<$T>()
+                    .unwrap();
+                let kernel = paste! {
+                    kernels::[<triangle_ $T>]::builder()?.build(y.device())?
+                };
+                kernel.dispatch(cols, diagonal, lower.into(), y.as_slice_mut().unwrap())?;
+                return Ok(());
+            }
+        });
+        unreachable!()
+    }
+}
+
+// The shape of `lhs` and `rhs` broadcast together.
+fn co_broadcast<D1, D2>(lhs: &D1, rhs: &D2) -> Result<<D1 as DimMax<D2>>::Output>
+where
+    D1: Dimension + DimMax<D2>,
+    D2: Dimension,
 {
-    fn add_assign(&mut self, rhs: &TensorBase<S2, D2>) -> Result<()> {
-        self.scaled_add(T::one(), rhs)
+    let mut dim = <D1 as DimMax<D2>>::Output::zeros(lhs.ndim().max(rhs.ndim()));
+    let mut lhs_iter = lhs.slice().iter().rev();
+    let mut rhs_iter = rhs.slice().iter().rev();
+    for d in dim.slice_mut().iter_mut().rev() {
+        let a = lhs_iter.next().copied().unwrap_or(1);
+        let b = rhs_iter.next().copied().unwrap_or(1);
+        *d = if a == b || b == 1 {
+            a
+        } else if a == 1 {
+            b
+        } else {
+            bail!(
+                "Broadcast not possible! {:?} and {:?}",
+                lhs.slice(),
+                rhs.slice()
+            );
+        };
     }
+    Ok(dim)
 }
 
 fn assign<X: Scalar, Y: Scalar>(
@@ -284,6 +1398,16 @@ fn assign<X: Scalar, Y: Scalar>(
         bail!("Broadcast not possible! {x:?} -> {y:?}");
     };
 
+    if op.is_div()
+        && !matches!(
+            Y::SCALAR_TYPE,
+            ScalarType::F16 | ScalarType::BF16 | ScalarType::F32 | ScalarType::F64
+        )
+        && y.iter().any(|y| *y == Y::zero())
+    {
+        bail!("Integer division by zero!");
+    }
+
     use ndarray::Zip;
     use rayon::iter::{
         IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator,
@@ -321,6 +1445,53 @@ fn scalar_assign(
     alpha: ScalarElem,
     x: ScalarTensorViewD,
     mut y: ScalarTensorViewMutD,
+) -> Result<()> {
+    #[cfg(not(feature = "device"))]
+    {
+        scalar_assign_impl(op, alpha, x, y)
+    }
+    #[cfg(feature = "device")]
+    {
+        let op = op.as_u32();
+        let result = scalar_assign_impl(
+            BinaryOp::try_from(op).unwrap(),
+            alpha,
+            x.view(),
+            y.view_mut(),
+        );
+        if let Err(error) = result.as_ref() {
+            if !(x.device().is_host() && y.device().is_host())
+                && fallback::host_fallback(
+                    "assign",
+                    &if x.device().is_device() {
+                        x.device()
+                    } else {
+                        y.device()
+                    },
+                    &[x.scalar_type(), y.scalar_type()],
+                    error,
+                )
+            {
+                let x = fallback::raw_to_host(x)?;
+                let mut y_host = fallback::raw_to_host(y.view())?;
+                scalar_assign_impl(
+                    BinaryOp::try_from(op).unwrap(),
+                    alpha,
+                    x.view(),
+                    y_host.view_mut(),
+                )?;
+                return fallback::raw_copy_from_host(y, &y_host);
+            }
+        }
+        result
+    }
+}
+
+fn scalar_assign_impl(
+    op: BinaryOp,
+    alpha: ScalarElem,
+    x: ScalarTensorViewD,
+    mut y: ScalarTensorViewMutD,
 ) -> Result<()> {
     if alpha.scalar_type() != y.scalar_type() {
         bail!(
@@ -683,6 +1854,135 @@ impl<S: ScalarData, D: Dimension> ScalarTensorBase<S, D> {
     }
 }
 
+impl<T: Scalar + Float, S: Data<Elem = T>, D: Dimension> TensorBase<S, D> {
+    /// The exponential of each element, returning a new tensor.
+    ///
+    /// # Errors
+    /// - The operation could not be executed on the device.
+    pub fn exp(&self) -> Result<Tensor<T, D>> {
+        self.unary(UnaryOp::Exp)
+    }
+    /// The natural logarithm of each element, returning a new tensor.
+    ///
+    /// # Errors
+    /// - The operation could not be executed on the device.
+    pub fn ln(&self) -> Result<Tensor<T, D>> {
+        self.unary(UnaryOp::Ln)
+    }
+    /// The square root of each element, returning a new tensor.
+    ///
+    /// # Errors
+    /// - The operation could not be executed on the device.
+    pub fn sqrt(&self) -> Result<Tensor<T, D>> {
+        self.unary(UnaryOp::Sqrt)
+    }
+    /// The absolute value of each element, returning a new tensor.
+    ///
+    /// # Errors
+    /// - The operation could not be executed on the device.
+    pub fn abs(&self) -> Result<Tensor<T, D>> {
+        self.unary(UnaryOp::Abs)
+    }
+    /// The reciprocal `1 / x` of each element, returning a new tensor.
+    ///
+    /// # Errors
+    /// - The operation could not be executed on the device.
+    pub fn recip(&self) -> Result<Tensor<T, D>> {
+        let mut output = unsafe { Tensor::uninit(self.device(), self.raw_dim())? };
+        output.assign(self)?;
+        let one = Tensor::from_elem(self.device(), (), T::one())?;
+        assign(
+            BinaryOp::Div,
+            T::one(),
+            one.view().into_dyn(),
+            output.view_mut().into_dyn(),
+        )?;
+        Ok(output)
+    }
+    /// Raises each element to the integer power `n`, returning a new tensor.
+    ///
+    /// Small powers are computed with repeated multiplication on the device, otherwise falls back
+    /// to [`.powf()`](TensorBase::powf()).
+    ///
+    /// # Errors
+    /// - The operation could not be executed on the device.
+    pub fn powi(&self, n: i32) -> Result<Tensor<T, D>> {
+        if n.unsigned_abs() > 4 {
+            return self.powf(T::from_i32(n).unwrap());
+        }
+        let mut output = Tensor::from_elem(self.device(), self.raw_dim(), T::one())?;
+        for _ in 0..n.unsigned_abs() {
+            assign(
+                BinaryOp::Mul,
+                T::one(),
+                self.view().into_dyn(),
+                output.view_mut().into_dyn(),
+            )?;
+        }
+        if n < 0 {
+            output.recip()
+        } else {
+            Ok(output)
+        }
+    }
+    /// Raises each element to the power `exp`, returning a new tensor.
+    ///
+    /// # Errors
+    /// - The operation could not be executed on the device.
+    pub fn powf(&self, exp: T) -> Result<Tensor<T, D>> {
+        if let Some(x) = self.as_array() {
+            return Ok(x.map(|x| x.powf(exp)).into());
+        }
+        #[cfg(not(feature = "device"))]
+        {
+            unreachable!()
+        }
+        #[cfg(feature = "device")]
+        {
+            let x = self.as_standard_layout()?;
+            macro_for!($T in [f16, bf16, f32, f64] {
+                if T::SCALAR_TYPE == $T::SCALAR_TYPE {
+                    let x = ScalarTensorView::from(x.view()).try_into_tensor_view::<$T>().unwrap();
+                    let mut y = unsafe { Tensor::<$T, _>::uninit(x.device(), x.raw_dim())? };
+                    let kernel = paste! {
+                        kernels::[<powf_ $T>]::builder()?.build(y.device())?
+                    };
+                    kernel.dispatch(exp.cast(), x.as_slice().unwrap(), y.as_slice_mut().unwrap())?;
+                    return Ok(y.cast_into().unwrap());
+                }
+            });
+            bail!("powf {:?} unimplemented!", T::SCALAR_TYPE)
+        }
+    }
+    fn unary(&self, op: UnaryOp) -> Result<Tensor<T, D>> {
+        if let Some(x) = self.as_array() {
+            return Ok(x.map(|x| op.eval(*x)).into());
+        }
+        #[cfg(not(feature = "device"))]
+        {
+            unreachable!()
+        }
+        #[cfg(feature = "device")]
+        {
+            let x = self.as_standard_layout()?;
+            macro_for!($T in [f16, bf16, f32, f64] {
+                if T::SCALAR_TYPE == $T::SCALAR_TYPE {
+                    let x = ScalarTensorView::from(x.view()).try_into_tensor_view::<$T>().unwrap();
+                    let mut y = unsafe { Tensor::<$T, _>::uninit(x.device(), x.raw_dim())? };
+                    let kernel = paste! {
+                        kernels::[<unary_ $T>]::builder()?
+                            .specialize(op.as_u32())
+                            .build(y.device())?
+                    };
+                    kernel.dispatch(x.as_slice().unwrap(), y.as_slice_mut().unwrap())?;
+                    return Ok(y.cast_into().unwrap());
+                }
+            });
+            bail!("{op:?} {:?} unimplemented!", T::SCALAR_TYPE)
+        }
+    }
+}
+
 impl<T: Scalar + Unsigned, S: Data<Elem = T>, D: Dimension> TensorBase<S, D> {
     /// A one hot vector given class labels.
     ///
@@ -736,6 +2036,138 @@ impl<T: Scalar + Unsigned, S: Data<Elem = T>, D: Dimension> TensorBase<S, D> {
     }
 }
 
+fn check_repeat_interleave_axis(Axis(axis): Axis, ndim: usize) -> Result<()> {
+    if axis >= ndim {
+        bail!("repeat_interleave axis {axis} out of bounds for tensor with ndim {ndim}!");
+    }
+    Ok(())
+}
+
+fn repeat_interleave_dim_strides<D: Dimension>(
+    dim: &D,
+    strides: &D,
+    repeats: usize,
+    Axis(axis): Axis,
+) -> Result<(IxDyn, IxDyn)> {
+    check_repeat_interleave_axis(Axis(axis), dim.ndim())?;
+    let mut output_dim = IxDyn::zeros(dim.ndim() + 1);
+    let mut output_strides = IxDyn::zeros(dim.ndim() + 1);
+    for (i, (d, s)) in dim.slice().iter().zip(strides.slice()).enumerate() {
+        let i = if i > axis { i + 1 } else { i };
+        output_dim[i] = *d;
+        output_strides[i] = *s;
+    }
+    output_dim[axis + 1] = repeats;
+    Ok((output_dim, output_strides))
+}
+
+fn repeat_interleave_indices(len: usize, repeats: &[usize]) -> Result<Vec<usize>> {
+    if repeats.len() != len {
+        bail!(
+            "repeat_interleave expected {len} repeats, found {}!",
+            repeats.len()
+        );
+    }
+    Ok(repeats
+        .iter()
+        .copied()
+        .enumerate()
+        .flat_map(|(i, r)| std::iter::repeat(i).take(r))
+        .collect())
+}
+
+impl<S: ScalarData, D: Dimension> ScalarTensorBase<S, D> {
+    /// Repeats each element `repeats` times along `axis`.
+    ///
+    /// See [`TensorBase::repeat_interleave()`].
+    pub fn repeat_interleave(&self, repeats: usize, axis: Axis) -> Result<ScalarTensor<D>> {
+        let (dim, strides) =
+            repeat_interleave_dim_strides(&self.dim, &self.strides, repeats, axis)?;
+        let mut output_dim = self.raw_dim();
+        output_dim[axis.0] *= repeats;
+        let input = ScalarTensorView {
+            dim,
+            strides,
+            buffer: self.buffer.as_scalar_slice(),
+            offset: self.offset,
+        };
+        Ok(input
+            .into_standard_layout()?
+            .into_shape(output_dim)
+            .unwrap())
+    }
+    /// Repeats each element `repeats[i]` times along `axis`.
+    ///
+    /// See [`TensorBase::repeat_interleave_with()`].
+    pub fn repeat_interleave_with(&self, repeats: &[usize], axis: Axis) -> Result<ScalarTensor<D>> {
+        macro_for!($T in [u8, i8, u16, i16, f16, bf16, u32, i32, f32, u64, i64, f64] {
+            if self.scalar_type() == $T::SCALAR_TYPE {
+                let input = self.view().try_into_tensor_view::<$T>().unwrap();
+                return input.repeat_interleave_with(repeats, axis).map(Into::into);
+            }
+        });
+        bail!("repeat_interleave {:?} unimplemented!", self.scalar_type());
+    }
+}
+
+impl<T: Scalar, S: Data<Elem = T>, D: Dimension> TensorBase<S, D> {
+    /// Repeats each element `repeats` times along `axis`.
+    ///
+    /// The output shape is the input shape with `axis` multiplied by `repeats`.
+    /// Equivalent to [`numpy.repeat`](https://numpy.org/doc/stable/reference/generated/numpy.repeat.html)
+    /// with an integer `repeats`.
+    ///
+    /// # Errors
+    /// - `axis` is out of bounds.
+    /// - Tensors with more than 5 dimensions are not supported on the device.
+    /// - The operation could not be executed on the device.
+    pub fn repeat_interleave(&self, repeats: usize, axis: Axis) -> Result<Tensor<T, D>> {
+        let (dim, strides) =
+            repeat_interleave_dim_strides(&self.dim, &self.strides, repeats, axis)?;
+        let mut output_dim = self.raw_dim();
+        output_dim[axis.0] *= repeats;
+        let input = TensorView {
+            dim,
+            strides,
+            buffer: self.buffer.as_slice(),
+            offset: self.offset,
+        };
+        Ok(input
+            .into_standard_layout()?
+            .into_shape(output_dim)
+            .unwrap())
+    }
+    /// Repeats each element `repeats[i]` times along `axis`.
+    ///
+    /// The output shape is the input shape with `axis` equal to the sum of `repeats`.
+    /// Equivalent to [`numpy.repeat`](https://numpy.org/doc/stable/reference/generated/numpy.repeat.html)
+    /// with an array of `repeats`.
+    ///
+    /// Computed on the host.
+    ///
+    /// # Errors
+    /// - `axis` is out of bounds.
+    /// - The length of `repeats` does not equal the length of `axis`.
+    /// - The tensor could not be transferred to or from the host.
+    pub fn repeat_interleave_with(&self, repeats: &[usize], axis: Axis) -> Result<Tensor<T, D>> {
+        check_repeat_interleave_axis(axis, self.ndim())?;
+        let indices = repeat_interleave_indices(self.dim[axis.0], repeats)?;
+        let input = if self.device().is_host() {
+            CowTensor::from(self.view())
+        } else {
+            self.to_device(Device::host())?.into()
+        };
+        let output = input
+            .as_array()
+            .unwrap()
+            .into_dyn()
+            .select(axis, &indices)
+            .into_dimensionality()
+            .unwrap();
+        Tensor::from(output).into_device(self.device())
+    }
+}
+
 #[cfg(feature = "neural-network")]
 impl<T: Scalar, S: ArrayData<Elem = T>> Im2ColConv2 for ArrayBase<S, Ix4> {
     type Output = Array2<T>;
@@ -1330,11 +2762,126 @@ mod binary_op {
 }
 use binary_op::BinaryOp;
 
+#[cfg_attr(feature = "device", module)]
+mod compare_op {
+    #[cfg(not(target_arch = "spirv"))]
+    use krnl::krnl_core;
+    use krnl_core::scalar::Scalar;
+
+    #[derive(Clone, Copy)]
+    #[repr(u32)]
+    pub enum CompareOp {
+        Eq = 1,
+        Ne = 2,
+        Gt = 3,
+        Ge = 4,
+        Lt = 5,
+        Le = 6,
+    }
+
+    #[cfg(feature = "device")]
+    impl CompareOp {
+        #[inline]
+        #[allow(clippy::wrong_self_convention)]
+        pub fn as_u32(self) -> u32 {
+            self as u32
+        }
+    }
+
+    impl TryFrom<u32> for CompareOp {
+        type Error = ();
+        #[inline]
+        fn try_from(x: u32) -> Result<Self, ()> {
+            Ok(match x {
+                1 => Self::Eq,
+                2 => Self::Ne,
+                3 => Self::Gt,
+                4 => Self::Ge,
+                5 => Self::Lt,
+                6 => Self::Le,
+                _ => {
+                    return Err(());
+                }
+            })
+        }
+    }
+
+    impl CompareOp {
+        #[inline]
+        pub fn eval<T: Scalar>(&self, a: T, b: T) -> bool {
+            match self {
+                Self::Eq => a == b,
+                Self::Ne => a != b,
+                Self::Gt => a > b,
+                Self::Ge => a >= b,
+                Self::Lt => a < b,
+                Self::Le => a <= b,
+            }
+        }
+    }
+}
+use compare_op::CompareOp;
+
+#[cfg_attr(feature = "device", module)]
+mod unary_op {
+    #[cfg(not(target_arch = "spirv"))]
+    use krnl::krnl_core;
+    use krnl_core::num_traits::Float;
+
+    #[cfg_attr(not(target_arch = "spirv"), derive(Debug))]
+    #[derive(Clone, Copy)]
+    #[repr(u32)]
+    pub enum UnaryOp {
+        Exp = 1,
+        Ln = 2,
+        Sqrt = 3,
+        Abs = 4,
+    }
+
+    #[cfg(feature = "device")]
+    impl UnaryOp {
+        #[inline]
+        #[allow(clippy::wrong_self_convention)]
+        pub fn as_u32(self) -> u32 {
+            self as u32
+        }
+    }
+
+    impl TryFrom<u32> for UnaryOp {
+        type Error = ();
+        #[inline]
+        fn try_from(x: u32) -> Result<Self, ()> {
+            Ok(match x {
+                1 => Self::Exp,
+                2 => Self::Ln,
+                3 => Self::Sqrt,
+                4 => Self::Abs,
+                _ => {
+                    return Err(());
+                }
+            })
+        }
+    }
+
+    impl UnaryOp {
+        #[inline]
+        pub fn eval<T: Float>(&self, x: T) -> T {
+            match self {
+                Self::Exp => x.exp(),
+                Self::Ln => x.ln(),
+                Self::Sqrt => x.sqrt(),
+                Self::Abs => x.abs(),
+            }
+        }
+    }
+}
+use unary_op::UnaryOp;
+
 #[cfg(feature = "device")]
 #[module]
 mod kernels {
     #[cfg(target_arch = "spirv")]
-    use crate::tensor::ops::binary_op::BinaryOp;
+    use crate::tensor::ops::{binary_op::BinaryOp, compare_op::CompareOp, unary_op::UnaryOp};
     use dry::macro_for;
     #[cfg(not(target_arch = "spirv"))]
     use krnl::krnl_core;
@@ -1345,6 +2892,7 @@ mod kernels {
     #[allow(unused_imports)]
     use krnl_core::{
         half::{bf16, f16},
+        num_traits::Float,
         scalar::Scalar,
     };
     use paste::paste;
@@ -1491,6 +3039,127 @@ mod kernels {
         });
     });
 
+    macro_for!($T in [u8, i8, u16, i16, f16, bf16, u32, i32, f32, u64, i64, f64] {
+        paste! {
+            // x has shape [outer, len, inner] and indices and y have shape [outer, m, inner].
+            #[kernel]
+            pub fn [<gather_ $T>](
+                len: u32,
+                m: u32,
+                inner: u32,
+                #[global] x: Slice<$T>,
+                #[item] index: u32,
+                #[item] y: &mut $T,
+            ) {
+                let [len, m, inner] = [len as usize, m as usize, inner as usize];
+                let idx = kernel.item_id();
+                let outer = idx / (m * inner);
+                *y = x[(outer * len + index as usize) * inner + idx % inner];
+            }
+
+            // indices and src have shape [outer, m, inner] and y has shape [outer, len, inner].
+            #[kernel]
+            pub fn [<scatter_add_ $T>](
+                len: u32,
+                m: u32,
+                inner: u32,
+                #[global] indices: Slice<u32>,
+                #[global] src: Slice<$T>,
+                #[item] y: &mut $T,
+            ) {
+                let [len, m, inner] = [len as usize, m as usize, inner as usize];
+                let idx = kernel.item_id();
+                let outer = idx / (len * inner);
+                let index = ((idx / inner) % len) as u32;
+                let start = outer * m * inner + idx % inner;
+                let mut acc = $T::default();
+                for k in 0..m {
+                    let i = start + k * inner;
+                    if indices[i] == index {
+                        acc = acc + src[i];
+                    }
+                }
+                *y = acc;
+            }
+
+            #[kernel]
+            pub fn [<compare_ $T>]<const OP: u32>(
+                #[item] a: $T,
+                #[item] b: $T,
+                #[item] y: &mut u8,
+            ) {
+                let op = CompareOp::try_from(OP).ok().unwrap();
+                *y = op.eval(a, b) as u8;
+            }
+
+            #[kernel]
+            pub fn [<masked_fill_ $T>](value: $T, #[item] mask: u8, #[item] y: &mut $T) {
+                if mask != 0 {
+                    *y = value;
+                }
+            }
+
+            // Zeros elements of a standard layout matrix outside of the triangle.
+            #[kernel]
+            pub fn [<triangle_ $T>](
+                cols: u32,
+                diagonal: i32,
+                lower: u32,
+                #[item] y: &mut $T,
+            ) {
+                let idx = kernel.item_id() as u32;
+                let k = (idx % cols) as i32 - (idx / cols) as i32;
+                let inside = if lower != 0 { k <= diagonal } else { k >= diagonal };
+                if !inside {
+                    *y = $T::default();
+                }
+            }
+
+            #[kernel]
+            pub fn [<where_ $T>](
+                #[item] cond: u8,
+                #[item] a: $T,
+                #[item] b: $T,
+                #[item] y: &mut $T,
+            ) {
+                *y = if cond != 0 { a } else { b };
+            }
+
+            #[kernel]
+            pub fn [<clamp_ $T>](
+                has_min: u32,
+                min: $T,
+                has_max: u32,
+                max: $T,
+                #[item] x: $T,
+                #[item] y: &mut $T,
+            ) {
+                *y = if has_min == 1 && x < min {
+                    min
+                } else if has_max == 1 && x > max {
+                    max
+                } else {
+                    x
+                };
+            }
+        }
+    });
+
+    macro_for!($T in [f16, bf16, f32, f64] {
+        paste! {
+            #[kernel]
+            pub fn [<unary_ $T>]<const OP: u32>(#[item] x: $T, #[item] y: &mut $T) {
+                let op = UnaryOp::try_from(OP).ok().unwrap();
+                *y = op.eval(x);
+            }
+
+            #[kernel]
+            pub fn [<powf_ $T>](exp: $T, #[item] x: $T, #[item] y: &mut $T) {
+                *y = x.powf(exp);
+            }
+        }
+    });
+
     macro_for!($X in [u8, u16, u32, u64] {
         macro_for!($Y in [u8, i8, u16, i16, f16, bf16, u32, i32, f32, u64, i64, f64] {
             paste! {
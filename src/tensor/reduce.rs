@@ -3,6 +3,8 @@ use super::*;
 use half::f16;
 #[cfg(feature = "device")]
 use krnl::macros::module;
+use ndarray::ArrayView1;
+use num_traits::Float;
 use parallel::parallel_size;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use std::mem::size_of;
@@ -21,10 +23,49 @@ impl<T: Scalar, S: Data<Elem = T>, D: Dimension> TensorBase<S, D> {
             }
         } else {
             let mut output = unsafe { Tensor::uninit(self.device(), ())? };
-            self.sum_with(T::default(), &mut output)?;
+            let result = self.sum_with(T::default(), &mut output);
+            #[cfg(feature = "device")]
+            if let Err(error) = result.as_ref() {
+                if fallback::host_fallback("sum", &self.device(), &[T::SCALAR_TYPE], error) {
+                    return fallback::raw_to_host(self.view().into())?
+                        .try_into_tensor::<T>()
+                        .unwrap()
+                        .sum();
+                }
+            }
+            result?;
             Ok(output.into_array()?.into_scalar())
         }
     }
+    /// Sums the tensor, accumulating in `A`.
+    ///
+    /// Accumulating low precision types like bf16 in f32 is more accurate. On device, the
+    /// tensor is cast to `A` and then summed.
+    ///
+    /// # Example
+    /// ```
+    /// # use autograph::{tensor::Tensor, half::bf16};
+    /// # fn main() -> anyhow::Result<()> {
+    /// let x = Tensor::from(vec![bf16::ONE; 1000]);
+    /// assert_eq!(x.sum_with_acc::<f32>()?, 1000.);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn sum_with_acc<A: Scalar>(&self) -> Result<A> {
+        if let Some(input) = self.as_array() {
+            let acc = |a: A, b: A| a + b;
+            if input.len() * size_of::<T>() > parallel_size() && rayon::current_num_threads() > 1 {
+                Ok(input
+                    .into_par_iter()
+                    .map(|x| x.cast::<A>())
+                    .reduce(A::default, acc))
+            } else {
+                Ok(input.iter().map(|x| x.cast::<A>()).fold(A::default(), acc))
+            }
+        } else {
+            self.cast::<A>()?.sum()
+        }
+    }
     /// Sums the tensor with `output`.
     pub fn sum_with<S2: DataMut<Elem = T>>(
         &self,
@@ -45,8 +86,10 @@ impl<T: Scalar, S: Data<Elem = T>, D: Dimension> TensorBase<S, D> {
         }
         #[cfg(feature = "device")]
         {
-            sum(
+            reduce(
                 self.view().into_dyn().into(),
+                ReduceOp::Sum,
+                T::default().into(),
                 beta.into(),
                 output.view_mut().into_dyn().into(),
             )
@@ -56,21 +99,10 @@ impl<T: Scalar, S: Data<Elem = T>, D: Dimension> TensorBase<S, D> {
 
 impl<T: Scalar, S: Data<Elem = T>, D: RemoveAxis> TensorBase<S, D> {
     /// Sums the tensor along `axis`.
+    ///
+    /// See [`.reduce()`](TensorBase::reduce()).
     pub fn sum_axis(&self, axis: Axis) -> Result<Tensor<T, D::Smaller>> {
-        if let Some(input) = self.as_array() {
-            return Ok(input.sum_axis(axis).into());
-        }
-        #[cfg(not(feature = "device"))]
-        {
-            unreachable!()
-        }
-        #[cfg(feature = "device")]
-        {
-            let mut output =
-                unsafe { Tensor::uninit(self.device(), self.raw_dim().remove_axis(axis))? };
-            self.sum_axis_with(axis, T::default(), &mut output)?;
-            Ok(output)
-        }
+        self.reduce(axis, T::default(), ReduceOp::Sum)
     }
     /// Sums the tensor along `axis` with `output`.
     pub fn sum_axis_with<S2: DataMut<Elem = T>>(
@@ -103,9 +135,11 @@ impl<T: Scalar, S: Data<Elem = T>, D: RemoveAxis> TensorBase<S, D> {
         }
         #[cfg(feature = "device")]
         {
-            sum_axis(
+            reduce_axis(
                 self.view().into_dyn().into(),
                 axis,
+                ReduceOp::Sum,
+                T::default().into(),
                 beta.into(),
                 output.view_mut().into_dyn().into(),
             )
@@ -113,8 +147,525 @@ impl<T: Scalar, S: Data<Elem = T>, D: RemoveAxis> TensorBase<S, D> {
     }
 }
 
+impl<T: Scalar, S: Data<Elem = T>, D: Dimension> TensorBase<S, D> {
+    /// The maximum element of the tensor.
+    ///
+    /// # Errors
+    /// - The tensor is empty.
+    /// - The operation could not be executed on the device.
+    pub fn max(&self) -> Result<T> {
+        self.extremum(ReduceOp::Max)
+    }
+    /// The minimum element of the tensor.
+    ///
+    /// See [`.max()`](TensorBase::max()).
+    pub fn min(&self) -> Result<T> {
+        self.extremum(ReduceOp::Min)
+    }
+    /// The product of the tensor.
+    ///
+    /// Integer overflow wraps around. The product of an empty tensor is 1. On the device, f16
+    /// and bf16 are accumulated in f32.
+    ///
+    /// # Errors
+    /// The operation could not be executed on the device.
+    pub fn prod(&self) -> Result<T> {
+        if let Some(input) = self.as_array() {
+            return Ok(prod(input.iter().copied()));
+        }
+        #[cfg(not(feature = "device"))]
+        {
+            unreachable!()
+        }
+        #[cfg(feature = "device")]
+        {
+            let mut output = unsafe { Tensor::uninit(self.device(), ())? };
+            reduce(
+                self.view().into_dyn().into(),
+                ReduceOp::Prod,
+                T::one().into(),
+                T::default().into(),
+                output.view_mut().into_dyn().into(),
+            )?;
+            output.item()
+        }
+    }
+    fn extremum(&self, op: ReduceOp) -> Result<T> {
+        if self.is_empty() {
+            bail!("TensorBase::{op:?} of an empty tensor!");
+        }
+        if let Some(input) = self.as_array() {
+            return Ok(input.iter().copied().reduce(|a, b| op.eval(a, b)).unwrap());
+        }
+        #[cfg(not(feature = "device"))]
+        {
+            unreachable!()
+        }
+        #[cfg(feature = "device")]
+        {
+            // Starts from the first element, so that the reduction does not depend on the range
+            // of T.
+            let mut first = self.view().into_dyn();
+            while first.ndim() > 0 {
+                first = first.index_axis_into(Axis(0), 0);
+            }
+            let init = first.item()?;
+            let mut output = unsafe { Tensor::uninit(self.device(), ())? };
+            reduce(
+                self.view().into_dyn().into(),
+                op,
+                init.into(),
+                T::default().into(),
+                output.view_mut().into_dyn().into(),
+            )?;
+            output.item()
+        }
+    }
+}
+
+impl<T: Scalar, S: Data<Elem = T>, D: RemoveAxis> TensorBase<S, D> {
+    /// The maximum along `axis`.
+    ///
+    /// # Errors
+    /// - `axis` is out of bounds.
+    /// - The length of `axis` is 0.
+    /// - The operation could not be executed on the device.
+    pub fn max_axis(&self, axis: Axis) -> Result<Tensor<T, D::Smaller>> {
+        Ok(self
+            .extremum_axis(axis, ReduceOp::Max, false)?
+            .try_into_tensor()
+            .unwrap())
+    }
+    /// The minimum along `axis`.
+    ///
+    /// See [`.max_axis()`](TensorBase::max_axis()).
+    pub fn min_axis(&self, axis: Axis) -> Result<Tensor<T, D::Smaller>> {
+        Ok(self
+            .extremum_axis(axis, ReduceOp::Min, false)?
+            .try_into_tensor()
+            .unwrap())
+    }
+    /// The index of the maximum along `axis`.
+    ///
+    /// Ties return the first index.
+    ///
+    /// See [`.max_axis()`](TensorBase::max_axis()).
+    pub fn argmax_axis(&self, axis: Axis) -> Result<Tensor<u32, D::Smaller>> {
+        Ok(self
+            .extremum_axis(axis, ReduceOp::Max, true)?
+            .try_into_tensor()
+            .unwrap())
+    }
+    /// The index of the minimum along `axis`.
+    ///
+    /// Ties return the first index.
+    ///
+    /// See [`.max_axis()`](TensorBase::max_axis()).
+    pub fn argmin_axis(&self, axis: Axis) -> Result<Tensor<u32, D::Smaller>> {
+        Ok(self
+            .extremum_axis(axis, ReduceOp::Min, true)?
+            .try_into_tensor()
+            .unwrap())
+    }
+    /// The product along `axis`.
+    ///
+    /// See [`.prod()`](TensorBase::prod()).
+    ///
+    /// # Errors
+    /// - `axis` is out of bounds.
+    /// - The operation could not be executed on the device.
+    pub fn prod_axis(&self, axis: Axis) -> Result<Tensor<T, D::Smaller>> {
+        check_axis(axis, self.ndim())?;
+        if let Some(input) = self.as_array() {
+            return Ok(input
+                .map_axis(axis, |lane| prod(lane.iter().copied()))
+                .into());
+        }
+        self.reduce(axis, T::one(), ReduceOp::Prod)
+    }
+    // The extremum along `axis`, or its index if `arg`.
+    fn extremum_axis(
+        &self,
+        axis: Axis,
+        op: ReduceOp,
+        arg: bool,
+    ) -> Result<ScalarTensor<D::Smaller>> {
+        check_axis(axis, self.ndim())?;
+        let n = self.shape()[axis.0];
+        if n == 0 {
+            bail!("TensorBase::{op:?} along empty axis {}!", axis.0);
+        }
+        if let Some(input) = self.as_array() {
+            let index = |lane: ArrayView1<T>| {
+                (1..lane.len()).fold(0, |index, i| {
+                    let replace = if op == ReduceOp::Max {
+                        lane[i] > lane[index]
+                    } else {
+                        lane[i] < lane[index]
+                    };
+                    if replace {
+                        i
+                    } else {
+                        index
+                    }
+                })
+            };
+            return Ok(if arg {
+                Tensor::from(input.map_axis(axis, |lane| index(lane) as u32)).into()
+            } else {
+                Tensor::from(input.map_axis(axis, |lane| lane[index(lane)])).into()
+            });
+        }
+        #[cfg(not(feature = "device"))]
+        {
+            unreachable!()
+        }
+        #[cfg(feature = "device")]
+        {
+            let input = self.as_standard_layout()?;
+            let inner: usize = self.shape()[axis.0 + 1..].iter().product();
+            let n = n.to_u32().unwrap();
+            let inner = inner.to_u32().unwrap();
+            let device = self.device();
+            let dim = self.raw_dim().remove_axis(axis);
+            let scalar_type = if arg { ScalarType::U32 } else { T::SCALAR_TYPE };
+            let mut output = unsafe { ScalarTensor::uninit(device.clone(), dim, scalar_type)? };
+            if output.is_empty() {
+                return Ok(output);
+            }
+            macro_for!($T in [u8, i8, u16, i16, f16, bf16, u32, i32, f32, u64, i64, f64] {
+                if T::SCALAR_TYPE == $T::SCALAR_TYPE {
+                    let x = ScalarTensorView::from(input.view()).try_into_tensor_view::<$T>().unwrap();
+                    let x = x.as_slice().unwrap();
+                    let y = output.as_scalar_slice_mut().unwrap();
+                    if arg {
+                        let kernel = paste! {
+                            kernels::[<arg_extremum_axis_ $T>]::builder()?
+                                .specialize(op.as_u32())
+                                .build(device)?
+                        };
+                        kernel.dispatch(n, inner, x, SliceMut::try_from(y).unwrap())?;
+                    } else {
+                        let kernel = paste! {
+                            kernels::[<extremum_axis_ $T>]::builder()?
+                                .specialize(op.as_u32())
+                                .build(device)?
+                        };
+                        kernel.dispatch(n, inner, x, SliceMut::try_from(y).unwrap())?;
+                    }
+                    return Ok(output);
+                }
+            });
+            unreachable!()
+        }
+    }
+}
+
+fn check_axis(Axis(axis): Axis, ndim: usize) -> Result<()> {
+    if axis >= ndim {
+        bail!("axis {axis} out of bounds for tensor with ndim {ndim}!");
+    }
+    Ok(())
+}
+
+fn is_float<T: Scalar>() -> bool {
+    matches!(
+        T::SCALAR_TYPE,
+        ScalarType::F16 | ScalarType::BF16 | ScalarType::F32 | ScalarType::F64
+    )
+}
+
+fn prod<T: Scalar>(iter: impl Iterator<Item = T>) -> T {
+    iter.fold(T::one(), |a, b| ReduceOp::Prod.eval(a, b))
+}
+
+impl<T: Scalar + Float, S: Data<Elem = T>, D: Dimension> TensorBase<S, D> {
+    /// The mean of the tensor.
+    ///
+    /// Computed as the sum divided by the number of elements. Accumulates in f32, or f64 for f64.
+    /// The mean of an empty tensor is NaN.
+    ///
+    /// # Errors
+    /// See [`.sum()`](TensorBase::sum()).
+    pub fn mean(&self) -> Result<T> {
+        let n = self.len();
+        if T::SCALAR_TYPE == ScalarType::F64 {
+            Ok((self.sum_with_acc::<f64>()? / n as f64).cast())
+        } else {
+            Ok((self.sum_with_acc::<f32>()? / n as f32).cast())
+        }
+    }
+}
+
+impl<T: Scalar + Float, S: Data<Elem = T>, D: RemoveAxis> TensorBase<S, D> {
+    /// The mean of the tensor along `axis`.
+    ///
+    /// Computed as the sum along `axis` divided by its length. For bf16 and f16, sums in f32
+    /// and casts back.
+    ///
+    /// # Errors
+    /// See [`.sum_axis()`](TensorBase::sum_axis()).
+    pub fn mean_axis(&self, axis: Axis) -> Result<Tensor<T, D::Smaller>> {
+        check_axis(axis, self.ndim())?;
+        let n = self.shape()[axis.0];
+        if matches!(T::SCALAR_TYPE, ScalarType::F32 | ScalarType::F64) {
+            self.sum_axis(axis)?
+                .scaled_cast(T::one() / T::from_usize(n).unwrap())
+        } else {
+            self.cast::<f32>()?
+                .sum_axis(axis)?
+                .scaled_cast(1. / n as f32)?
+                .cast_into()
+        }
+    }
+    /// The variance along `axis`.
+    ///
+    /// The sum of squared deviations from the mean is divided by `n - ddof`, where `n` is the
+    /// length of `axis`. A `ddof` of 0 is the population variance, 1 is the sample variance.
+    /// Accumulates in f32, or f64 for f64.
+    ///
+    /// # Errors
+    /// - `axis` is out of bounds.
+    /// - `ddof` is less than 0 or greater than `n`.
+    /// - The operation could not be executed on the device.
+    ///
+    /// See [`ArrayBase::var_axis()`].
+    pub fn var_axis(&self, axis: Axis, ddof: T) -> Result<Tensor<T, D::Smaller>> {
+        self.var_axis_impl(axis, ddof, false)
+    }
+    /// The standard deviation along `axis`.
+    ///
+    /// The square root of the [variance](TensorBase::var_axis()).
+    ///
+    /// See [`ArrayBase::std_axis()`].
+    pub fn std_axis(&self, axis: Axis, ddof: T) -> Result<Tensor<T, D::Smaller>> {
+        self.var_axis_impl(axis, ddof, true)
+    }
+    fn var_axis_impl(&self, axis: Axis, ddof: T, std: bool) -> Result<Tensor<T, D::Smaller>> {
+        check_axis(axis, self.ndim())?;
+        let n = self.shape()[axis.0];
+        if !(ddof >= T::zero() && ddof <= T::from_usize(n).unwrap()) {
+            bail!("TensorBase::var_axis ddof {ddof:?} out of range for axis with length {n}!");
+        }
+        if let Some(input) = self.as_array() {
+            let output = if T::SCALAR_TYPE == ScalarType::F64 {
+                var_axis_host::<T, f64, D>(input, axis, ddof.cast(), std)
+            } else {
+                var_axis_host::<T, f32, D>(input, axis, ddof.cast(), std)
+            };
+            return Ok(output.into());
+        }
+        #[cfg(not(feature = "device"))]
+        {
+            unreachable!()
+        }
+        #[cfg(feature = "device")]
+        {
+            if T::SCALAR_TYPE == ScalarType::F64 {
+                var_axis_device::<T, f64, D>(self.view(), axis, ddof.cast(), std)
+            } else {
+                var_axis_device::<T, f32, D>(self.view(), axis, ddof.cast(), std)
+            }
+        }
+    }
+}
+
+// Subtracts the mean and sums the squared deviations along `axis`, accumulating in A.
+#[cfg(feature = "device")]
+fn var_axis_device<T: Scalar, A: Scalar + Float, D: RemoveAxis>(
+    input: TensorView<T, D>,
+    axis: Axis,
+    ddof: A,
+    std: bool,
+) -> Result<Tensor<T, D::Smaller>> {
+    let n = A::from_usize(input.shape()[axis.0]).unwrap();
+    let mut x = input.cast::<A>()?;
+    let mut sum_dim = input.raw_dim();
+    sum_dim[axis.0] = 1;
+    let sum = x.sum_axis(axis)?.into_shape(sum_dim).unwrap();
+    x.scaled_add(-A::one() / n, &sum)?;
+    let var = x
+        .mul(&x)?
+        .sum_axis(axis)?
+        .scaled_cast(A::one() / (n - ddof))?;
+    if std {
+        var.sqrt()?.cast_into()
+    } else {
+        var.cast_into()
+    }
+}
+
+fn var_axis_host<T: Scalar, A: Scalar + Float, D: RemoveAxis>(
+    input: ArrayView<T, D>,
+    axis: Axis,
+    ddof: A,
+    std: bool,
+) -> Array<T, D::Smaller> {
+    let input = input.mapv(|x| x.cast::<A>());
+    let output = if std {
+        input.std_axis(axis, ddof)
+    } else {
+        input.var_axis(axis, ddof)
+    };
+    output.mapv(|x| x.cast())
+}
+
+/// Associative operations for [`TensorBase::reduce()`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+#[repr(u32)]
+pub enum ReduceOp {
+    /// `a + b`, integer overflow wraps around.
+    Sum = 1,
+    /// `a * b`, integer overflow wraps around.
+    Prod = 2,
+    /// The maximum of `a` and `b`.
+    Max = 3,
+    /// The minimum of `a` and `b`.
+    Min = 4,
+    /// 1 if both `a` and `b` are nonzero, otherwise 0.
+    And = 5,
+    /// 1 if `a` or `b` is nonzero, otherwise 0.
+    Or = 6,
+}
+
+impl ReduceOp {
+    #[cfg(feature = "device")]
+    fn as_u32(self) -> u32 {
+        self as u32
+    }
+    fn eval<T: Scalar>(self, a: T, b: T) -> T {
+        let bool_to_scalar = |x: bool| if x { T::one() } else { T::default() };
+        match self {
+            // Integers are added and multiplied as u64, which wraps like T.
+            Self::Sum | Self::Prod if !is_float::<T>() => {
+                let (a, b) = (a.cast::<u64>(), b.cast::<u64>());
+                if self == Self::Sum {
+                    a.wrapping_add(b).cast()
+                } else {
+                    a.wrapping_mul(b).cast()
+                }
+            }
+            Self::Sum => a + b,
+            Self::Prod => a * b,
+            Self::Max => {
+                if b > a {
+                    b
+                } else {
+                    a
+                }
+            }
+            Self::Min => {
+                if b < a {
+                    b
+                } else {
+                    a
+                }
+            }
+            Self::And => bool_to_scalar(a != T::default() && b != T::default()),
+            Self::Or => bool_to_scalar(a != T::default() || b != T::default()),
+        }
+    }
+}
+
+impl<T: Scalar, S: Data<Elem = T>, D: RemoveAxis> TensorBase<S, D> {
+    /// Reduces the tensor along `axis` with `op`, starting from `init`.
+    ///
+    /// Equivalent to [`ArrayBase::fold_axis()`] with `op`. On the device, 8 and 16 bit integers
+    /// are accumulated in 32 bits, and f16 and bf16 in f32.
+    ///
+    /// # Errors
+    /// - `axis` is out of bounds.
+    /// - The operation could not be executed on the device.
+    pub fn reduce(&self, axis: Axis, init: T, op: ReduceOp) -> Result<Tensor<T, D::Smaller>> {
+        check_axis(axis, self.ndim())?;
+        if let Some(input) = self.as_array() {
+            if op == ReduceOp::Sum && init == T::default() && is_float::<T>() {
+                return Ok(input.sum_axis(axis).into());
+            }
+            return Ok(input.fold_axis(axis, init, |a, b| op.eval(*a, *b)).into());
+        }
+        #[cfg(not(feature = "device"))]
+        {
+            unreachable!()
+        }
+        #[cfg(feature = "device")]
+        {
+            let mut output =
+                unsafe { Tensor::uninit(self.device(), self.raw_dim().remove_axis(axis))? };
+            if let Err(error) = reduce_axis(
+                self.view().into_dyn().into(),
+                axis,
+                op,
+                init.into(),
+                T::default().into(),
+                output.view_mut().into_dyn().into(),
+            ) {
+                if fallback::host_fallback("reduce", &self.device(), &[T::SCALAR_TYPE], &error) {
+                    return fallback::raw_to_host(self.view().into())?
+                        .try_into_tensor::<T>()
+                        .unwrap()
+                        .reduce(axis, init, op)?
+                        .into_device(self.device());
+                }
+                return Err(error);
+            }
+            Ok(output)
+        }
+    }
+}
+
+/// Permutes the axes in order of decreasing stride.
+///
+/// A tensor with permuted axes that is contiguous in memory, like a transposed
+/// matrix, becomes contiguous.
+#[cfg(feature = "device")]
+fn into_memory_order(x: ScalarTensorViewD) -> ScalarTensorViewD {
+    if x.is_contiguous() {
+        return x;
+    }
+    let mut axes: Vec<usize> = (0..x.ndim()).collect();
+    {
+        let strides = x.strides();
+        axes.sort_by_key(|axis| std::cmp::Reverse(strides[*axis]));
+    }
+    x.permuted_axes(axes)
+}
+
+// Reduces `x` with `op` into `y`, starting from `init`.
+//
+// For ReduceOp::Sum, `beta * y` is added to the output.
 #[cfg(feature = "device")]
-fn sum(x: ScalarTensorViewD, beta: ScalarElem, mut y: ScalarTensorViewMutD) -> Result<()> {
+fn reduce(
+    x: ScalarTensorViewD,
+    op: ReduceOp,
+    init: ScalarElem,
+    beta: ScalarElem,
+    mut y: ScalarTensorViewMutD,
+) -> Result<()> {
+    // The reduction does not depend on the order of the elements.
+    let x = into_memory_order(x);
+    if !x.is_contiguous() {
+        // Reduce the strided input along the first axis instead of copying it.
+        let scalar_type = x.scalar_type();
+        let mut x_reduced = unsafe {
+            ScalarTensor::uninit(x.device(), x.raw_dim().remove_axis(Axis(0)), scalar_type)?
+        };
+        // init is only applied once, except for idempotent operations.
+        let partial_init = match op {
+            ReduceOp::Sum => ScalarElem::zero(scalar_type),
+            ReduceOp::Prod => ScalarElem::one(scalar_type),
+            _ => init,
+        };
+        reduce_axis(
+            x,
+            Axis(0),
+            op,
+            partial_init,
+            ScalarElem::zero(scalar_type),
+            x_reduced.view_mut(),
+        )?;
+        return reduce(x_reduced.view(), op, init, beta, y);
+    }
     let device = y.device();
     let info = device.info().unwrap();
     let subgroup_threads = if info.min_subgroup_threads() == info.max_subgroup_threads() {
@@ -124,22 +675,21 @@ fn sum(x: ScalarTensorViewD, beta: ScalarElem, mut y: ScalarTensorViewMutD) -> R
     };
     let threads = info.max_subgroup_threads();
     let groups = y.len().to_u32().unwrap();
-    let x = if x.is_contiguous() {
-        x.into()
-    } else {
-        x.as_standard_layout()?
-    };
-    let x = x.as_scalar_slice().unwrap();
+    let x = x.as_scalar_slice_memory_order().unwrap();
     let y = y.as_scalar_slice_mut().unwrap();
     macro_for!($T in [u8, i8, u16, i16, f16, bf16, u32, i32, f32, u64, i64, f64] {
         if x.scalar_type() == $T::SCALAR_TYPE {
             let x = Slice::try_from(x).unwrap();
             let y = SliceMut::try_from(y).unwrap();
             let kernel = paste! {
-                kernels::[<sum_ $T>]::builder()?.with_threads(threads).specialize(subgroup_threads).build(device)?
+                kernels::[<reduce_ $T>]::builder()?
+                    .with_threads(threads)
+                    .specialize(subgroup_threads, op.as_u32())
+                    .build(device)?
             };
             kernel.with_groups(groups).dispatch(
                 x,
+                init.cast(),
                 beta.cast(),
                 y,
             )?;
@@ -149,10 +699,15 @@ fn sum(x: ScalarTensorViewD, beta: ScalarElem, mut y: ScalarTensorViewMutD) -> R
     unreachable!()
 }
 
+// Reduces `x` along `axis` with `op` into `y`, starting from `init`.
+//
+// For ReduceOp::Sum, `beta * y` is added to the output.
 #[cfg(feature = "device")]
-fn sum_axis(
+fn reduce_axis(
     x: ScalarTensorViewD,
     axis: Axis,
+    op: ReduceOp,
+    init: ScalarElem,
     beta: ScalarElem,
     mut y: ScalarTensorViewMutD,
 ) -> Result<()> {
@@ -198,9 +753,9 @@ fn sum_axis(
                 let x = Slice::try_from(x).unwrap();
                 let y = SliceMut::try_from(y).unwrap();
                 let kernel = paste! {
-                    kernels::[<sum_axis2_ $T>]::builder()?
+                    kernels::[<reduce_axis2_ $T>]::builder()?
                         .with_threads(threads)
-                        .specialize(subgroups, subgroup_threads, axis)
+                        .specialize(subgroups, subgroup_threads, axis, op.as_u32())
                         .build(device)?
                 };
                 kernel.with_groups(groups).dispatch(
@@ -210,6 +765,7 @@ fn sum_axis(
                     sx0,
                     sx1,
                     offset_x,
+                    init.cast(),
                     beta.cast(),
                     y,
                     sy0,
@@ -270,9 +826,9 @@ fn sum_axis(
                 let x = Slice::try_from(x).unwrap();
                 let y = SliceMut::try_from(y).unwrap();
                 let kernel = paste! {
-                    kernels::[<sum_axis4_ $T>]::builder()?
+                    kernels::[<reduce_axis4_ $T>]::builder()?
                         .with_threads(threads)
-                        .specialize(subgroups, subgroup_threads, axis)
+                        .specialize(subgroups, subgroup_threads, axis, op.as_u32())
                         .build(device)?
                 };
                 kernel.with_groups(groups).dispatch(
@@ -286,6 +842,7 @@ fn sum_axis(
                     sx2,
                     sx3,
                     offset_x,
+                    init.cast(),
                     beta.cast(),
                     y,
                     sy0,
@@ -364,9 +921,9 @@ fn sum_axis(
                 let x = Slice::try_from(x).unwrap();
                 let y = SliceMut::try_from(y).unwrap();
                 let kernel = paste! {
-                    kernels::[<sum_axis6_ $T>]::builder()?
+                    kernels::[<reduce_axis6_ $T>]::builder()?
                         .with_threads(threads)
-                        .specialize(subgroups, subgroup_threads, axis)
+                        .specialize(subgroups, subgroup_threads, axis, op.as_u32())
                         .build(device)?
                 };
                 kernel.with_groups(groups).dispatch(
@@ -384,6 +941,7 @@ fn sum_axis(
                     sx4,
                     sx5,
                     offset_x,
+                    init.cast(),
                     beta.cast(),
                     y,
                     sy0,
@@ -398,7 +956,7 @@ fn sum_axis(
         });
     }
     bail!(
-        "sum_axis{ndim}<{}>(axis={axis}) unimplemented!",
+        "reduce_axis{ndim}<{}>(axis={axis}, op={op:?}) unimplemented!",
         x.scalar_type().name()
     )
 }
@@ -419,116 +977,88 @@ mod kernels {
     };
     use paste::paste;
 
-    #[cfg(target_arch = "spirv")]
-    unsafe fn subgroup_add_u32(x: u32) -> u32 {
-        use core::arch::asm;
-
-        let mut y = 0u32;
-        asm! {
-            "%u32 = OpTypeInt 32 0",
-            "%subgroup = OpConstant %u32 3",
-            "%y = OpGroupNonUniformIAdd _ %subgroup Reduce {x}",
-            "OpStore {y} %y",
-            x = in(reg) x,
-            y = in(reg) &mut y,
-        }
-        y
-    }
+    macro_rules! impl_subgroup_op {
+        ($($f:ident($t:ty) => $op:literal),* $(,)?) => {
+            $(
+                #[cfg(target_arch = "spirv")]
+                unsafe fn $f(x: $t) -> $t {
+                    use core::arch::asm;
 
-    #[cfg(target_arch = "spirv")]
-    unsafe fn subgroup_add_i32(x: i32) -> i32 {
-        use core::arch::asm;
-
-        let mut y = 0i32;
-        asm! {
-            "%u32 = OpTypeInt 32 0",
-            "%subgroup = OpConstant %u32 3",
-            "%y = OpGroupNonUniformIAdd _ %subgroup Reduce {x}",
-            "OpStore {y} %y",
-            x = in(reg) x,
-            y = in(reg) &mut y,
-        }
-        y
+                    let mut y = <$t>::default();
+                    asm! {
+                        "%u32 = OpTypeInt 32 0",
+                        "%subgroup = OpConstant %u32 3",
+                        $op,
+                        "OpStore {y} %y",
+                        x = in(reg) x,
+                        y = in(reg) &mut y,
+                    }
+                    y
+                }
+            )*
+        };
     }
 
-    #[cfg(target_arch = "spirv")]
-    unsafe fn subgroup_add_f32(x: f32) -> f32 {
-        use core::arch::asm;
-
-        let mut y = 0f32;
-        asm! {
-            "%u32 = OpTypeInt 32 0",
-            "%subgroup = OpConstant %u32 3",
-            "%y = OpGroupNonUniformFAdd _ %subgroup Reduce {x}",
-            "OpStore {y} %y",
-            x = in(reg) x,
-            y = in(reg) &mut y,
-        }
-        y
+    impl_subgroup_op! {
+        subgroup_add_u32(u32) => "%y = OpGroupNonUniformIAdd _ %subgroup Reduce {x}",
+        subgroup_mul_u32(u32) => "%y = OpGroupNonUniformIMul _ %subgroup Reduce {x}",
+        subgroup_max_u32(u32) => "%y = OpGroupNonUniformUMax _ %subgroup Reduce {x}",
+        subgroup_min_u32(u32) => "%y = OpGroupNonUniformUMin _ %subgroup Reduce {x}",
+        subgroup_add_i32(i32) => "%y = OpGroupNonUniformIAdd _ %subgroup Reduce {x}",
+        subgroup_mul_i32(i32) => "%y = OpGroupNonUniformIMul _ %subgroup Reduce {x}",
+        subgroup_max_i32(i32) => "%y = OpGroupNonUniformSMax _ %subgroup Reduce {x}",
+        subgroup_min_i32(i32) => "%y = OpGroupNonUniformSMin _ %subgroup Reduce {x}",
+        subgroup_add_f32(f32) => "%y = OpGroupNonUniformFAdd _ %subgroup Reduce {x}",
+        subgroup_mul_f32(f32) => "%y = OpGroupNonUniformFMul _ %subgroup Reduce {x}",
+        subgroup_max_f32(f32) => "%y = OpGroupNonUniformFMax _ %subgroup Reduce {x}",
+        subgroup_min_f32(f32) => "%y = OpGroupNonUniformFMin _ %subgroup Reduce {x}",
+        subgroup_add_u64(u64) => "%y = OpGroupNonUniformIAdd _ %subgroup Reduce {x}",
+        subgroup_mul_u64(u64) => "%y = OpGroupNonUniformIMul _ %subgroup Reduce {x}",
+        subgroup_max_u64(u64) => "%y = OpGroupNonUniformUMax _ %subgroup Reduce {x}",
+        subgroup_min_u64(u64) => "%y = OpGroupNonUniformUMin _ %subgroup Reduce {x}",
+        subgroup_add_i64(i64) => "%y = OpGroupNonUniformIAdd _ %subgroup Reduce {x}",
+        subgroup_mul_i64(i64) => "%y = OpGroupNonUniformIMul _ %subgroup Reduce {x}",
+        subgroup_max_i64(i64) => "%y = OpGroupNonUniformSMax _ %subgroup Reduce {x}",
+        subgroup_min_i64(i64) => "%y = OpGroupNonUniformSMin _ %subgroup Reduce {x}",
+        subgroup_add_f64(f64) => "%y = OpGroupNonUniformFAdd _ %subgroup Reduce {x}",
+        subgroup_mul_f64(f64) => "%y = OpGroupNonUniformFMul _ %subgroup Reduce {x}",
+        subgroup_max_f64(f64) => "%y = OpGroupNonUniformFMax _ %subgroup Reduce {x}",
+        subgroup_min_f64(f64) => "%y = OpGroupNonUniformFMin _ %subgroup Reduce {x}",
     }
 
+    // ReduceOp::as_u32()
     #[cfg(target_arch = "spirv")]
-    unsafe fn subgroup_add_u64(x: u64) -> u64 {
-        use core::arch::asm;
-
-        let mut y = 0u64;
-        asm! {
-            "%u32 = OpTypeInt 32 0",
-            "%subgroup = OpConstant %u32 3",
-            "%y = OpGroupNonUniformIAdd _ %subgroup Reduce {x}",
-            "OpStore {y} %y",
-            x = in(reg) x,
-            y = in(reg) &mut y,
-        }
-        y
-    }
-
+    const SUM: u32 = 1;
     #[cfg(target_arch = "spirv")]
-    unsafe fn subgroup_add_i64(x: i64) -> i64 {
-        use core::arch::asm;
-
-        let mut y = 0i64;
-        asm! {
-            "%u32 = OpTypeInt 32 0",
-            "%subgroup = OpConstant %u32 3",
-            "%y = OpGroupNonUniformIAdd _ %subgroup Reduce {x}",
-            "OpStore {y} %y",
-            x = in(reg) x,
-            y = in(reg) &mut y,
-        }
-        y
-    }
-
+    const PROD: u32 = 2;
     #[cfg(target_arch = "spirv")]
-    unsafe fn subgroup_add_f64(x: f64) -> f64 {
-        use core::arch::asm;
-
-        let mut y = 0f64;
-        asm! {
-            "%u32 = OpTypeInt 32 0",
-            "%subgroup = OpConstant %u32 3",
-            "%y = OpGroupNonUniformFAdd _ %subgroup Reduce {x}",
-            "OpStore {y} %y",
-            x = in(reg) x,
-            y = in(reg) &mut y,
-        }
-        y
-    }
+    const MAX: u32 = 3;
+    #[cfg(target_arch = "spirv")]
+    const MIN: u32 = 4;
+    #[cfg(target_arch = "spirv")]
+    const AND: u32 = 5;
+    #[cfg(target_arch = "spirv")]
+    const OR: u32 = 6;
 
     #[cfg(target_arch = "spirv")]
-    trait SubgroupAdd: Sized {
-        unsafe fn subgroup_add(self) -> Self;
+    trait SubgroupReduce: Scalar {
+        unsafe fn subgroup_reduce(self, op: u32) -> Self;
     }
 
-    macro_rules! impl_subgroup_add {
+    macro_rules! impl_subgroup_reduce {
         ($($t:ty),*) => {
             $(
                 #[cfg(target_arch = "spirv")]
-                impl SubgroupAdd for $t {
-                    unsafe fn subgroup_add(self) -> Self {
+                impl SubgroupReduce for $t {
+                    unsafe fn subgroup_reduce(self, op: u32) -> Self {
                         unsafe {
                             paste! {
-                                [<subgroup_add_ $t>](self)
+                                match op {
+                                    SUM => [<subgroup_add_ $t>](self),
+                                    PROD => [<subgroup_mul_ $t>](self),
+                                    MAX | OR => [<subgroup_max_ $t>](self),
+                                    _ => [<subgroup_min_ $t>](self),
+                                }
                             }
                         }
                     }
@@ -537,7 +1067,61 @@ mod kernels {
         };
     }
 
-    impl_subgroup_add!(u32, i32, f32, u64, i64, f64);
+    impl_subgroup_reduce!(u32, i32, f32, u64, i64, f64);
+
+    // And and Or are computed as the minimum and maximum of 0 or 1.
+    #[cfg(target_arch = "spirv")]
+    fn reduce_map<A: Scalar>(op: u32, x: A) -> A {
+        if op == AND || op == OR {
+            if x != A::default() {
+                1u32.cast()
+            } else {
+                A::default()
+            }
+        } else {
+            x
+        }
+    }
+
+    #[cfg(target_arch = "spirv")]
+    fn reduce_eval<A: Scalar>(op: u32, a: A, b: A) -> A {
+        match op {
+            SUM => a + b,
+            PROD => a * b,
+            MAX | OR => {
+                if b > a {
+                    b
+                } else {
+                    a
+                }
+            }
+            _ => {
+                if b < a {
+                    b
+                } else {
+                    a
+                }
+            }
+        }
+    }
+
+    // The initial value of each thread.
+    //
+    // Only the first thread starts from init for Sum and Prod, other operations are idempotent.
+    #[cfg(target_arch = "spirv")]
+    fn reduce_thread_init<A: Scalar>(op: u32, init: A, n: usize, thread_id: usize) -> A {
+        if thread_id == 0 || !(op == SUM || op == PROD) {
+            if n > 0 {
+                reduce_map(op, init)
+            } else {
+                init
+            }
+        } else if op == PROD {
+            1u32.cast()
+        } else {
+            A::default()
+        }
+    }
 
     #[cfg(target_arch = "spirv")]
     fn remove_from_array4<T: Copy + Default>(x: [T; 4], index: usize) -> [T; 3] {
@@ -571,12 +1155,13 @@ mod kernels {
         y
     }
 
-    macro_rules! impl_sum {
+    macro_rules! impl_reduce {
         ($t:ty => $a:ty) => {
             paste! {
                 #[kernel]
-                pub fn [<sum_ $t>]<const SUBGROUP_THREADS: u32>(
+                pub fn [<reduce_ $t>]<const SUBGROUP_THREADS: u32, const OP: u32>(
                     #[global] x: Slice<$t>,
+                    init: $a,
                     beta: $a,
                     #[global] y: UnsafeSlice<$t>,
                 ) {
@@ -590,21 +1175,21 @@ mod kernels {
                     } else {
                         kernel.threads() / kernel.subgroups()
                     };
-                    let mut y_thread = A::default();
-                    let mut idx = 0;
                     let n = x.len() / y.len();
+                    let mut y_thread = reduce_thread_init(OP, init, n, kernel.thread_id());
+                    let mut idx = 0;
                     while idx < n {
                         let x_idx = idx + kernel.thread_id();
                         if x_idx < n {
-                            y_thread += x[x_idx].cast::<A>();
+                            y_thread = reduce_eval(OP, y_thread, reduce_map(OP, x[x_idx].cast::<A>()));
                         }
                         idx += subgroup_threads;
                     }
                     unsafe {
-                        y_thread = y_thread.subgroup_add();
+                        y_thread = y_thread.subgroup_reduce(OP);
                     };
                     if kernel.thread_id() == 0 {
-                        if beta != A::default() {
+                        if OP == SUM && beta != A::default() {
                             unsafe {
                                 y_thread += beta * y.unsafe_index(0).cast::<A>();
                             }
@@ -617,13 +1202,14 @@ mod kernels {
 
                 #[allow(clippy::too_many_arguments)]
                 #[kernel]
-                pub fn [<sum_axis2_ $t>]<const SUBGROUPS: u32, const SUBGROUP_THREADS: u32, const AXIS: u32>(
+                pub fn [<reduce_axis2_ $t>]<const SUBGROUPS: u32, const SUBGROUP_THREADS: u32, const AXIS: u32, const OP: u32>(
                     d0: u32,
                     d1: u32,
                     #[global] x: Slice<$t>,
                     sx0: i32,
                     sx1: i32,
                     offset_x: u32,
+                    init: $a,
                     beta: $a,
                     #[global] y: UnsafeSlice<$t>,
                     sy0: i32,
@@ -649,21 +1235,22 @@ mod kernels {
                     let stride_group = [sx1, sx0][axis];
                     let stride_axis = [sx0, sx1][axis];
                     let mut x_start = global_subgroup_id as i32 * stride_group + offset_x as i32;
-                    let mut y_thread = A::default();
+                    let mut y_thread = reduce_thread_init(OP, init, n, kernel.subgroup_thread_id());
                     let mut idx = 0;
                     while idx < n {
                         let x_idx = idx + kernel.subgroup_thread_id();
                         if x_idx < n {
-                            y_thread += x[(x_start + x_idx as i32 * stride_axis) as usize].cast::<A>();
+                            let x = x[(x_start + x_idx as i32 * stride_axis) as usize].cast::<A>();
+                            y_thread = reduce_eval(OP, y_thread, reduce_map(OP, x));
                         }
                         idx += subgroup_threads;
                     }
                     unsafe {
-                        y_thread = y_thread.subgroup_add();
+                        y_thread = y_thread.subgroup_reduce(OP);
                     };
                     let y_idx = (global_subgroup_id as i32 * sy0 + offset_y as i32) as usize;
                     if kernel.subgroup_thread_id() == 0 {
-                        if beta != A::default() {
+                        if OP == SUM && beta != A::default() {
                             unsafe {
                                 y_thread += beta * y.unsafe_index(y_idx).cast::<A>();
                             }
@@ -676,7 +1263,7 @@ mod kernels {
 
                 #[allow(clippy::too_many_arguments)]
                 #[kernel]
-                pub fn [<sum_axis4_ $t>]<const SUBGROUPS: u32, const SUBGROUP_THREADS: u32, const AXIS: u32>(
+                pub fn [<reduce_axis4_ $t>]<const SUBGROUPS: u32, const SUBGROUP_THREADS: u32, const AXIS: u32, const OP: u32>(
                     d0: u32,
                     d1: u32,
                     d2: u32,
@@ -687,6 +1274,7 @@ mod kernels {
                     sx2: i32,
                     sx3: i32,
                     offset_x: u32,
+                    init: $a,
                     beta: $a,
                     #[global] y: UnsafeSlice<$t>,
                     sy0: i32,
@@ -723,21 +1311,22 @@ mod kernels {
                         [i0 as i32, i1 as i32, i2 as i32]
                     };
                     let mut x_start = i0 * sg0 + i1 * sg1 + i2 * sg2 + offset_x as i32;
-                    let mut y_thread = A::default();
+                    let mut y_thread = reduce_thread_init(OP, init, n, kernel.subgroup_thread_id());
                     let mut idx = 0;
                     while idx < n {
                         let x_idx = idx + kernel.subgroup_thread_id();
                         if x_idx < n {
-                            y_thread += x[(x_start + x_idx as i32 * stride_axis) as usize].cast::<A>();
+                            let x = x[(x_start + x_idx as i32 * stride_axis) as usize].cast::<A>();
+                            y_thread = reduce_eval(OP, y_thread, reduce_map(OP, x));
                         }
                         idx += subgroup_threads;
                     }
                     unsafe {
-                        y_thread = y_thread.subgroup_add();
+                        y_thread = y_thread.subgroup_reduce(OP);
                     };
                     let y_idx = (i0 * sy0 + i1 * sy1 + i2 * sy2 + offset_y as i32) as usize;
                     if kernel.subgroup_thread_id() == 0 {
-                        if beta != A::default() {
+                        if OP == SUM && beta != A::default() {
                             unsafe {
                                 y_thread += beta * y.unsafe_index(y_idx).cast::<A>();
                             }
@@ -750,7 +1339,7 @@ mod kernels {
 
                 #[allow(clippy::too_many_arguments)]
                 #[kernel]
-                pub fn [<sum_axis6_ $t>]<const SUBGROUPS: u32, const SUBGROUP_THREADS: u32, const AXIS: u32>(
+                pub fn [<reduce_axis6_ $t>]<const SUBGROUPS: u32, const SUBGROUP_THREADS: u32, const AXIS: u32, const OP: u32>(
                     d0: u32,
                     d1: u32,
                     d2: u32,
@@ -765,6 +1354,7 @@ mod kernels {
                     sx4: i32,
                     sx5: i32,
                     offset_x: u32,
+                    init: $a,
                     beta: $a,
                     #[global] y: UnsafeSlice<$t>,
                     sy0: i32,
@@ -807,21 +1397,22 @@ mod kernels {
                         [i0 as i32, i1 as i32, i2 as i32, i3 as i32, i4 as i32]
                     };
                     let mut x_start = i0 * sg0 + i1 * sg1 + i2 * sg2 + i3 * sg3 + i4 * sg4 + offset_x as i32;
-                    let mut y_thread = A::default();
+                    let mut y_thread = reduce_thread_init(OP, init, n, kernel.subgroup_thread_id());
                     let mut idx = 0;
                     while idx < n {
                         let x_idx = idx + kernel.subgroup_thread_id();
                         if x_idx < n {
-                            y_thread += x[(x_start + x_idx as i32 * stride_axis) as usize].cast::<A>();
+                            let x = x[(x_start + x_idx as i32 * stride_axis) as usize].cast::<A>();
+                            y_thread = reduce_eval(OP, y_thread, reduce_map(OP, x));
                         }
                         idx += subgroup_threads;
                     }
                     unsafe {
-                        y_thread = y_thread.subgroup_add();
+                        y_thread = y_thread.subgroup_reduce(OP);
                     };
                     let y_idx = (i0 * sy0 + i1 * sy1 + i2 * sy2 + i3 * sy3 + i4 * sy4 + offset_y as i32) as usize;
                     if kernel.subgroup_thread_id() == 0 {
-                        if beta != A::default() {
+                        if OP == SUM && beta != A::default() {
                             unsafe {
                                 y_thread += beta * y.unsafe_index(y_idx).cast::<A>();
                             }
@@ -835,18 +1426,72 @@ mod kernels {
         };
         ($($t:ty),*) => {
             $(
-                impl_sum!($t => $t);
+                impl_reduce!($t => $t);
             )*
         };
         ($($t:ty),* => $a:ty) => {
             $(
-                impl_sum!($t => $a);
+                impl_reduce!($t => $a);
             )*
         }
     }
 
-    impl_sum!(u8, u16 => u32);
-    impl_sum!(i8, i16 => i32);
-    impl_sum!(f16, bf16 => f32);
-    impl_sum!(u32, i32, f32, u64, i64, f64);
+    // The index of the first maximum or minimum of x(0), .., x(n - 1).
+    #[cfg(target_arch = "spirv")]
+    fn extremum_index<T: Scalar>(op: u32, n: usize, x: impl Fn(usize) -> T) -> usize {
+        let mut index = 0;
+        let mut value = x(0);
+        for i in 1..n {
+            let x = x(i);
+            if (op == MAX && x > value) || (op == MIN && x < value) {
+                index = i;
+                value = x;
+            }
+        }
+        index
+    }
+
+    macro_rules! impl_extremum_axis {
+        ($($t:ty),*) => {
+            $(
+                paste! {
+                    // x is in standard layout, with `n` elements along the axis and `inner`
+                    // elements after it.
+                    #[kernel]
+                    pub fn [<extremum_axis_ $t>]<const OP: u32>(
+                        n: u32,
+                        inner: u32,
+                        #[global] x: Slice<$t>,
+                        #[item] y: &mut $t,
+                    ) {
+                        let [n, inner] = [n as usize, inner as usize];
+                        let idx = kernel.item_id();
+                        let start = (idx / inner) * n * inner + idx % inner;
+                        let index = extremum_index(OP, n, |i| x[start + i * inner]);
+                        *y = x[start + index * inner];
+                    }
+
+                    #[kernel]
+                    pub fn [<arg_extremum_axis_ $t>]<const OP: u32>(
+                        n: u32,
+                        inner: u32,
+                        #[global] x: Slice<$t>,
+                        #[item] y: &mut u32,
+                    ) {
+                        let [n, inner] = [n as usize, inner as usize];
+                        let idx = kernel.item_id();
+                        let start = (idx / inner) * n * inner + idx % inner;
+                        *y = extremum_index(OP, n, |i| x[start + i * inner]) as u32;
+                    }
+                }
+            )*
+        };
+    }
+
+    impl_extremum_axis!(u8, i8, u16, i16, f16, bf16, u32, i32, f32, u64, i64, f64);
+
+    impl_reduce!(u8, u16 => u32);
+    impl_reduce!(i8, i16 => i32);
+    impl_reduce!(f16, bf16 => f32);
+    impl_reduce!(u32, i32, f32, u64, i64, f64);
 }
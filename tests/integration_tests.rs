@@ -1,6 +1,6 @@
 use autograph::{
     krnl::scalar::ScalarElem,
-    tensor::{ScalarTensorViewD, Tensor, TensorView},
+    tensor::{ScalarArcTensor, ScalarArcTensor1, ScalarTensorViewD, Tensor, TensorView},
 };
 use dry::{macro_for, macro_wrap};
 use half::{bf16, f16};
@@ -224,6 +224,18 @@ fn tensor_tests(device: &Device) -> Vec<Trial> {
             );
             Ok(())
         }),
+        Trial::test("tensor_buffer", || {
+            tensor_buffer();
+            Ok(())
+        }),
+        Trial::test("tensor_from_buffer", || {
+            tensor_from_buffer();
+            Ok(())
+        }),
+        Trial::test("tensor_cast_shared", || {
+            tensor_cast_shared();
+            Ok(())
+        }),
         Trial::test("tensor_from_arrayD", || {
             tensor_from_array(
                 Array::from_shape_vec(
@@ -235,6 +247,42 @@ fn tensor_tests(device: &Device) -> Vec<Trial> {
             Ok(())
         }),
     ]);
+    tests.push(device_test(device, "tensor_item", tensor_item));
+    tests.push(device_test(device, "tensor_fill", tensor_fill));
+    tests.push(device_test(device, "tensor_zero", tensor_zero));
+    tests.push(device_test(
+        device,
+        "scalar_arc_tensor_clone_to_device",
+        scalar_arc_tensor_clone_to_device,
+    ));
+    tests.push(device_test(
+        device,
+        "scalar_tensor_fill",
+        scalar_tensor_fill,
+    ));
+    tests.push(device_test(device, "tensor_triangle", tensor_triangle));
+    tests.push(device_test(device, "host_fallback", host_fallback));
+    tests.push(device_test(
+        device,
+        "tensor_assign_slice",
+        tensor_assign_slice,
+    ));
+    tests.push(device_test(device, "tensor_stack", tensor_stack));
+    tests.push(device_test(device, "tensor_split", tensor_split));
+    tests.push(device_test(device, "tensor_gather", tensor_gather));
+    tests.push(device_test(
+        device,
+        "tensor_scatter_add",
+        tensor_scatter_add,
+    ));
+    tests.push(device_test(device, "tensor_compare", tensor_compare));
+    tests.push(device_test(device, "tensor_where", tensor_where));
+    tests.push(device_test(
+        device,
+        "tensor_from_shape_fn",
+        tensor_from_shape_fn,
+    ));
+    tests.push(device_test(device, "tensor_mapv", tensor_mapv));
     tests.extend(
         linalg::linalg_tests(device)
             .into_iter()
@@ -242,11 +290,578 @@ fn tensor_tests(device: &Device) -> Vec<Trial> {
             .chain(reduce::reduce_tests(device))
             .chain(ops::ops_tests(device)),
     );
+    #[cfg(feature = "dataset")]
+    tests.extend(dataset::dataset_tests(device));
     #[cfg(feature = "learn")]
     tests.extend(learn::learn_tests(device));
     tests
 }
 
+fn tensor_buffer() {
+    let x = Tensor::from(Array::from_shape_vec([2, 3], (0..6u32).collect()).unwrap());
+    assert_eq!(x.offset(), 0);
+    assert_eq!(x.buffer().len(), x.len());
+    assert_eq!(
+        x.buffer().as_host_slice().unwrap().as_ptr(),
+        x.as_array().unwrap().as_ptr()
+    );
+    let row = x.index_axis(Axis(0), 1);
+    assert_eq!(row.offset(), 3);
+    assert_eq!(row.buffer().len(), x.len());
+    let host_slice = row.buffer().as_host_slice().unwrap();
+    assert_eq!(
+        &host_slice[row.offset()..],
+        row.as_array().unwrap().as_slice().unwrap()
+    );
+    let y = ScalarArcTensor::from(x);
+    assert_eq!(y.buffer().scalar_type(), ScalarType::U32);
+    assert_eq!(y.buffer().len(), y.len());
+}
+
+fn tensor_from_buffer() {
+    use autograph::tensor::ScalarTensor2;
+    use krnl::buffer::{Buffer, ScalarBuffer};
+
+    let buffer = Buffer::from((0..6u32).collect::<Vec<_>>());
+    let ptr = buffer.as_host_slice().unwrap().as_ptr();
+    let x = Tensor::from_buffer(buffer, [2, 3]).unwrap();
+    assert_eq!(
+        x.as_array().unwrap(),
+        Array::from_shape_vec([2, 3], (0..6u32).collect()).unwrap()
+    );
+    assert_eq!(x.buffer().as_host_slice().unwrap().as_ptr(), ptr);
+    assert!(Tensor::from_buffer(Buffer::from(vec![0u32; 5]), [2, 3]).is_err());
+    let buffer = ScalarBuffer::from(Buffer::from(vec![1f32; 6]));
+    let x = ScalarTensor2::from_buffer(buffer, [3, 2]).unwrap();
+    assert_eq!(x.scalar_type(), ScalarType::F32);
+    assert_eq!(x.shape(), [3, 2]);
+    let x = x.try_into_tensor::<u32>().unwrap_err();
+    assert!(x.try_into_tensor::<f32>().is_ok());
+    assert!(
+        ScalarTensor2::from_buffer(ScalarBuffer::from(Buffer::from(vec![1f32; 5])), [3, 2])
+            .is_err()
+    );
+}
+
+fn tensor_item(device: &Device) {
+    use autograph::tensor::ScalarTensor;
+
+    let x = Tensor::from(vec![1f32, 2., 3.])
+        .into_device(device.clone())
+        .unwrap();
+    assert_eq!(x.index_axis(Axis(0), 1).item().unwrap(), 2.);
+    assert!(x.item().is_err());
+    let x = ScalarTensor::from(Tensor::from(Array::from_elem((), 7u8)))
+        .into_device(device.clone())
+        .unwrap();
+    assert!(matches!(x.item().unwrap(), ScalarElem::U8(7)));
+}
+
+fn tensor_fill(device: &Device) {
+    let x_array = Array::from_shape_vec([2, 3], (0..6u32).collect()).unwrap();
+    let mut x = Tensor::from(x_array.clone())
+        .into_device(device.clone())
+        .unwrap();
+    x.view_mut()
+        .reversed_axes()
+        .index_axis_mut(Axis(0), 1)
+        .fill(7)
+        .unwrap();
+    let mut y_array = x_array.clone();
+    y_array.index_axis_mut(Axis(1), 1).fill(7);
+    assert_eq!(x.into_array().unwrap(), y_array);
+    let mut x = Tensor::from(x_array.clone())
+        .into_device(device.clone())
+        .unwrap();
+    x.index_axis_mut(Axis(0), 1).fill(9).unwrap();
+    let mut y_array = x_array.clone();
+    y_array.index_axis_mut(Axis(0), 1).fill(9);
+    assert_eq!(x.into_array().unwrap(), y_array);
+    let mut x = Tensor::from(x_array.clone())
+        .into_device(device.clone())
+        .unwrap();
+    x.view_mut().reversed_axes().fill_diagonal(0).unwrap();
+    let mut y_array = x_array;
+    y_array.diag_mut().fill(0);
+    assert_eq!(x.into_array().unwrap(), y_array);
+}
+
+fn tensor_zero(device: &Device) {
+    let mut x = Tensor::from(vec![1f32, 2., 3., 4.])
+        .into_device(device.clone())
+        .unwrap();
+    let ptr = x.buffer().as_host_slice().map(|slice| slice.as_ptr());
+    x.zero().unwrap();
+    if let Some(ptr) = ptr {
+        assert_eq!(x.buffer().as_host_slice().unwrap().as_ptr(), ptr);
+    }
+    assert_eq!(x.into_array().unwrap(), Array::from_elem(4, 0f32));
+}
+
+fn scalar_tensor_fill(device: &Device) {
+    use autograph::tensor::ScalarTensor;
+
+    let mut x = ScalarTensor::from(Tensor::from(vec![1u32, 2, 3]))
+        .into_device(device.clone())
+        .unwrap();
+    let ptr = x
+        .view()
+        .try_into_tensor_view::<u32>()
+        .unwrap()
+        .buffer()
+        .as_host_slice()
+        .map(|slice| slice.as_ptr());
+    x.fill(ScalarElem::U32(5)).unwrap();
+    let y = x.view().try_into_tensor_view::<u32>().unwrap();
+    if let Some(ptr) = ptr {
+        assert_eq!(y.buffer().as_host_slice().unwrap().as_ptr(), ptr);
+    }
+    assert_eq!(y.into_array().unwrap(), Array::from_elem(3, 5u32));
+    assert!(x.fill(ScalarElem::F32(1.)).is_err());
+    x.zero().unwrap();
+    assert_eq!(
+        x.view()
+            .try_into_tensor_view::<u32>()
+            .unwrap()
+            .into_array()
+            .unwrap(),
+        Array::from_elem(3, 0u32)
+    );
+}
+
+fn scalar_arc_tensor_clone_to_device(device: &Device) {
+    use autograph::tensor::ScalarArcTensor;
+
+    let x_array = Array::from(vec![1f32, 2., 3.]);
+    let x = ScalarArcTensor::from(
+        Tensor::from(x_array.clone())
+            .into_device(device.clone())
+            .unwrap(),
+    );
+    let host_ptr = |x: &ScalarArcTensor<_>| {
+        x.view()
+            .try_into_tensor_view::<f32>()
+            .unwrap()
+            .as_array()
+            .map(|array| array.as_ptr())
+    };
+    let y = x.clone_to_device(device.clone()).unwrap();
+    assert_eq!(y.device(), x.device());
+    if device.is_host() {
+        assert_eq!(host_ptr(&y).unwrap(), host_ptr(&x).unwrap());
+    }
+    let y = x.clone_to_device(Device::host()).unwrap();
+    assert!(y.device().is_host());
+    if device.is_device() {
+        assert!(host_ptr(&x).is_none());
+    }
+    assert_eq!(
+        y.try_into_arc_tensor::<f32>()
+            .unwrap()
+            .into_array()
+            .unwrap(),
+        x_array
+    );
+}
+
+fn tensor_triangle(device: &Device) {
+    let x_array = Array::from_shape_vec([3, 4], (1..=12u32).collect()).unwrap();
+    let x = Tensor::from(x_array.clone())
+        .into_device(device.clone())
+        .unwrap();
+    for diagonal in [-4, -1, 0, 1, 2, 5] {
+        let tril = Array::from_shape_fn(x_array.raw_dim(), |(i, j)| {
+            if j as isize - i as isize <= diagonal {
+                x_array[(i, j)]
+            } else {
+                0
+            }
+        });
+        let triu = Array::from_shape_fn(x_array.raw_dim(), |(i, j)| {
+            if j as isize - i as isize >= diagonal {
+                x_array[(i, j)]
+            } else {
+                0
+            }
+        });
+        assert_eq!(x.tril(diagonal).unwrap().into_array().unwrap(), tril);
+        assert_eq!(x.triu(diagonal).unwrap().into_array().unwrap(), triu);
+        assert_eq!(
+            x.t().triu(-diagonal).unwrap().into_array().unwrap(),
+            tril.t()
+        );
+        let tril_mask =
+            Array::from_shape_fn([4, 4], |(i, j)| (j as isize - i as isize <= diagonal) as u8);
+        assert_eq!(
+            Tensor::<u8, _>::tril_mask(device.clone(), 4, diagonal)
+                .unwrap()
+                .into_array()
+                .unwrap(),
+            tril_mask
+        );
+        let triu_mask =
+            Array::from_shape_fn([4, 4], |(i, j)| (j as isize - i as isize >= diagonal) as u8);
+        assert_eq!(
+            Tensor::<u8, _>::triu_mask(device.clone(), 4, diagonal)
+                .unwrap()
+                .into_array()
+                .unwrap(),
+            triu_mask
+        );
+    }
+}
+
+fn host_fallback(device: &Device) {
+    use autograph::tensor::{host_fallback_ops, set_allow_host_fallback, ScalarTensor};
+    use ndarray::linalg::Dot;
+
+    let Some(info) = device.info() else {
+        return;
+    };
+    let features = info.features();
+    // 64 bit types that the device does not support.
+    let Some(scalar_type) = [ScalarType::F64, ScalarType::U64, ScalarType::I64]
+        .into_iter()
+        .find(|x| !features.contains(features_for_scalar(*x)))
+    else {
+        return;
+    };
+    let x_array = Array::from_shape_vec([2, 3], (1..=6).map(|x| x as f32).collect()).unwrap();
+    let x = ScalarTensor::from(Tensor::from(x_array.clone()))
+        .into_device(device.clone())
+        .unwrap();
+    assert!(x.clone().cast_into(scalar_type).is_err());
+    set_allow_host_fallback(true);
+    let y = x
+        .cast_into(scalar_type)
+        .and_then(|x| x.dot(&x.t()))
+        .and_then(|y| y.cast_into(ScalarType::F32));
+    set_allow_host_fallback(false);
+    let y = y.unwrap();
+    assert_eq!(y.device(), device.clone());
+    let y = y
+        .into_device(Device::host())
+        .unwrap()
+        .try_into_tensor::<f32>()
+        .unwrap()
+        .into_array()
+        .unwrap();
+    assert_eq!(y, x_array.dot(&x_array.t()));
+    let ops = host_fallback_ops();
+    assert!(ops.contains(&"assign"));
+    assert!(ops.contains(&"dot"));
+}
+
+fn tensor_assign_slice(device: &Device) {
+    let src_array = Array::from_shape_vec([3, 2], (1..=6u32).collect()).unwrap();
+    let src = Tensor::from(src_array.clone())
+        .into_device(device.clone())
+        .unwrap();
+    let mut y_array = Array::<u32, _>::zeros([3, 4]);
+    y_array
+        .slice_axis_mut(Axis(1), (1..3).into())
+        .assign(&src_array);
+    let mut y = Tensor::<u32, _>::zeros(device.clone(), [3, 4]).unwrap();
+    y.assign_slice(Axis(1), 1..3, &src).unwrap();
+    assert_eq!(y.into_array().unwrap(), y_array);
+    let mut y_array = Array::<u32, _>::zeros([4, 3]);
+    y_array
+        .slice_axis_mut(Axis(0), (2..4).into())
+        .assign(&src_array.t());
+    let mut y = Tensor::<u32, _>::zeros(device.clone(), [3, 4]).unwrap();
+    y.view_mut()
+        .reversed_axes()
+        .assign_slice(Axis(0), 2..4, &src.t())
+        .unwrap();
+    assert_eq!(y.into_array().unwrap(), y_array.t());
+    let mut y = Tensor::<u32, _>::zeros(device.clone(), [3, 4]).unwrap();
+    assert!(y.assign_slice(Axis(1), 0..3, &src).is_err());
+    assert!(y.assign_slice(Axis(1), 3..5, &src).is_err());
+    assert!(y.assign_slice(Axis(2), 0..2, &src).is_err());
+}
+
+fn tensor_stack(device: &Device) {
+    use autograph::tensor::stack;
+
+    let x_arrays: Vec<_> = (0..4u32)
+        .map(|i| Array::from_shape_fn([3, 3], |(j, k)| i * 9 + j as u32 * 3 + k as u32))
+        .collect();
+    let xs: Vec<_> = x_arrays
+        .iter()
+        .map(|x| Tensor::from(x.clone()).into_device(device.clone()).unwrap())
+        .collect();
+    let x_views: Vec<_> = xs.iter().map(|x| x.view()).collect();
+    let x_array_views: Vec<_> = x_arrays.iter().map(|x| x.view()).collect();
+    for axis in [Axis(0), Axis(1)] {
+        let y = stack(axis, &x_views).unwrap();
+        assert_eq!(y.device(), device.clone());
+        let y_array = ndarray::stack(axis, &x_array_views).unwrap();
+        assert_eq!(y.into_array().unwrap(), y_array);
+    }
+    assert_eq!(stack(Axis(0), &x_views).unwrap().shape(), &[4, 3, 3]);
+    assert_eq!(stack(Axis(1), &x_views).unwrap().shape(), &[3, 4, 3]);
+    assert!(stack(Axis(3), &x_views).is_err());
+    assert!(stack::<u32, _>(Axis(0), &[]).is_err());
+    assert!(stack(Axis(0), &[xs[0].view(), xs[1].t()]).is_ok());
+    let z = Tensor::<u32, _>::zeros(device.clone(), [3, 2]).unwrap();
+    assert!(stack(Axis(0), &[xs[0].view(), z.view()]).is_err());
+    if device.is_device() {
+        let x_host = xs[0].to_device(Device::host()).unwrap();
+        assert!(stack(Axis(0), &[xs[0].view(), x_host.view()]).is_err());
+    }
+}
+
+fn tensor_split(device: &Device) {
+    let x_array = Array::from_shape_vec([6, 4], (0..24u32).collect()).unwrap();
+    let x = Tensor::from(x_array.clone())
+        .into_device(device.clone())
+        .unwrap();
+    let ys = x.split(Axis(0), &[2, 3, 1]).unwrap();
+    assert_eq!(ys.len(), 3);
+    for (y, range) in ys.into_iter().zip([0..2, 2..5, 5..6]) {
+        assert_eq!(y.device(), device.clone());
+        assert_eq!(
+            y.into_array().unwrap(),
+            x_array.slice_axis(Axis(0), range.into())
+        );
+    }
+    let ys = x.t().split(Axis(1), &[4, 0, 2]).unwrap();
+    for (y, range) in ys.into_iter().zip([0..4, 4..4, 4..6]) {
+        assert_eq!(
+            y.into_array().unwrap(),
+            x_array.t().slice_axis(Axis(1), range.into())
+        );
+    }
+    assert!(x.split(Axis(0), &[2, 3]).is_err());
+    assert!(x.split(Axis(0), &[2, 3, 2]).is_err());
+    assert!(x.split(Axis(2), &[6]).is_err());
+}
+
+fn tensor_gather(device: &Device) {
+    let x_array = Array::from_shape_vec([3, 4], (0..12).map(|x| x as f32).collect()).unwrap();
+    let indices_array = Array::from_shape_vec([3, 2], vec![3u32, 0, 1, 1, 2, 0]).unwrap();
+    let x = Tensor::from(x_array.clone())
+        .into_device(device.clone())
+        .unwrap();
+    let indices = Tensor::from(indices_array.clone())
+        .into_device(device.clone())
+        .unwrap();
+    let y = x.gather(Axis(1), indices.view()).unwrap();
+    assert_eq!(y.device(), device.clone());
+    let mut y_array = Array::zeros([3, 2]);
+    for i in 0..3 {
+        for j in 0..2 {
+            y_array[(i, j)] = x_array[(i, indices_array[(i, j)] as usize)];
+        }
+    }
+    assert_eq!(y.into_array().unwrap(), y_array);
+    let indices_mod = Tensor::from(indices_array.map(|i| i % 3))
+        .into_device(device.clone())
+        .unwrap();
+    let mut y_array = Array::zeros([3, 2]);
+    for i in 0..3 {
+        for j in 0..2 {
+            y_array[(i, j)] = x_array[(indices_array[(i, j)] as usize % 3, j)];
+        }
+    }
+    assert_eq!(
+        x.gather(Axis(0), indices_mod.view())
+            .unwrap()
+            .into_array()
+            .unwrap(),
+        y_array
+    );
+    assert!(x.gather(Axis(0), indices.view()).is_err());
+    assert!(x.gather(Axis(2), indices.view()).is_err());
+    let indices_large = Tensor::<u32, _>::zeros(device.clone(), [4, 2]).unwrap();
+    assert!(x.gather(Axis(1), indices_large.view()).is_err());
+}
+
+fn tensor_scatter_add(device: &Device) {
+    let indices_array = Array::from_shape_vec([2, 4], vec![0u32, 2, 0, 0, 1, 1, 3, 1]).unwrap();
+    let src_array = Array::from_shape_vec([2, 4], (1..=8).map(|x| x as f32).collect()).unwrap();
+    let mut y_array = Array::from_elem([2, 5], 1f32);
+    for i in 0..2 {
+        for j in 0..4 {
+            y_array[(i, indices_array[(i, j)] as usize)] += src_array[(i, j)];
+        }
+    }
+    assert_eq!(y_array[(0, 0)], 1. + 1. + 3. + 4.);
+    assert_eq!(y_array[(1, 1)], 1. + 5. + 6. + 8.);
+    let scatter_add = |device: &Device| {
+        let indices = Tensor::from(indices_array.clone())
+            .into_device(device.clone())
+            .unwrap();
+        let src = Tensor::from(src_array.clone())
+            .into_device(device.clone())
+            .unwrap();
+        let mut y = Tensor::from_elem(device.clone(), [2, 5], 1f32).unwrap();
+        y.scatter_add(Axis(1), indices.view(), src.view()).unwrap();
+        assert_eq!(y.device(), device.clone());
+        y.into_array().unwrap()
+    };
+    let y_host = scatter_add(&Device::host());
+    let y = scatter_add(device);
+    assert_eq!(y, y_host);
+    assert_eq!(y, y_array);
+    let mut y = Tensor::from_elem(device.clone(), [2, 3], 1f32).unwrap();
+    let indices = Tensor::from(indices_array)
+        .into_device(device.clone())
+        .unwrap();
+    let src = Tensor::from(src_array).into_device(device.clone()).unwrap();
+    assert!(y.scatter_add(Axis(1), indices.view(), src.view()).is_err());
+    assert_eq!(y.into_array().unwrap(), Array::from_elem([2, 3], 1f32));
+}
+
+fn tensor_compare(device: &Device) {
+    let x_array = Array::from_shape_vec([3, 4], (0..12).map(|x| (x % 5) as f32).collect()).unwrap();
+    let x = Tensor::from(x_array.clone())
+        .into_device(device.clone())
+        .unwrap();
+    let threshold = Tensor::from_elem(device.clone(), (), 2f32).unwrap();
+    let mask = x.gt(&threshold).unwrap();
+    assert_eq!(mask.device(), device.clone());
+    assert_eq!(
+        mask.to_device(Device::host())
+            .unwrap()
+            .into_array()
+            .unwrap(),
+        x_array.map(|x| (*x > 2.) as u8)
+    );
+    let row_array = Array::from_vec(vec![0f32, 2., 4., 1.]);
+    let row = Tensor::from(row_array.clone())
+        .into_device(device.clone())
+        .unwrap();
+    let check = |y: Tensor<u8, ndarray::Ix2>, f: fn(&f32, &f32) -> bool| {
+        let y_array = ndarray::Zip::from(&x_array)
+            .and_broadcast(&row_array)
+            .map_collect(|a, b| f(a, b) as u8);
+        assert_eq!(y.into_array().unwrap(), y_array);
+    };
+    check(x.eq(&row).unwrap(), |a, b| a == b);
+    check(x.ne(&row).unwrap(), |a, b| a != b);
+    check(x.gt(&row).unwrap(), |a, b| a > b);
+    check(x.ge(&row).unwrap(), |a, b| a >= b);
+    check(x.lt(&row).unwrap(), |a, b| a < b);
+    check(x.le(&row).unwrap(), |a, b| a <= b);
+    let y = x.masked_fill(&mask, -1.).unwrap();
+    assert_eq!(y.device(), device.clone());
+    assert_eq!(
+        y.into_array().unwrap(),
+        x_array.map(|x| if *x > 2. { -1. } else { *x })
+    );
+    let column_mask = Tensor::from(Array::from_shape_vec([3, 1], vec![1u8, 0, 1]).unwrap())
+        .into_device(device.clone())
+        .unwrap();
+    let y = x
+        .masked_fill(&column_mask, 9.)
+        .unwrap()
+        .into_array()
+        .unwrap();
+    let mut y_array = x_array.clone();
+    y_array.row_mut(0).fill(9.);
+    y_array.row_mut(2).fill(9.);
+    assert_eq!(y, y_array);
+    let bad_mask = Tensor::<u8, _>::zeros(device.clone(), [5]).unwrap();
+    assert!(x.masked_fill(&bad_mask, 0.).is_err());
+    assert!(x
+        .gt(&Tensor::<f32, _>::zeros(device.clone(), [5]).unwrap())
+        .is_err());
+}
+
+fn tensor_where(device: &Device) {
+    use autograph::tensor::where_;
+    use ndarray::{Array2, Zip};
+
+    let cond_array = Array2::from_shape_fn([3, 4], |(i, j)| ((i + j) % 2) as u8);
+    let a_array = Array2::from_shape_fn([3, 4], |(i, j)| (i * 4 + j) as f32);
+    let b_array = Array::from_vec(vec![-1f32, -2., -3., -4.]);
+    let cond = Tensor::from(cond_array.clone())
+        .into_device(device.clone())
+        .unwrap();
+    let a = Tensor::from(a_array.clone())
+        .into_device(device.clone())
+        .unwrap();
+    let b = Tensor::from(b_array.clone())
+        .into_device(device.clone())
+        .unwrap();
+    let y = where_(cond.view(), a.view(), b.view()).unwrap();
+    assert_eq!(y.device(), device.clone());
+    let y_array = Zip::from(&cond_array)
+        .and(&a_array)
+        .and_broadcast(&b_array)
+        .map_collect(|c, a, b| if *c != 0 { *a } else { *b });
+    assert_eq!(y.into_array().unwrap(), y_array);
+    let b = Tensor::<f32, _>::zeros(device.clone(), [3]).unwrap();
+    assert!(where_(cond.view(), a.view(), b.view()).is_err());
+    if device.is_device() {
+        let b = Tensor::from(b_array).into_device(Device::host()).unwrap();
+        assert!(where_(cond.view(), a.view(), b.view()).is_err());
+    }
+}
+
+fn tensor_from_shape_fn(device: &Device) {
+    use ndarray::ShapeBuilder;
+
+    let f = |i: usize| (i * 7 % 5) as f32 - 2.;
+    let x = Tensor::from_shape_fn(device.clone(), 10, f).unwrap();
+    assert_eq!(x.into_array().unwrap(), Array::from_shape_fn(10, f));
+    let f = |(i, j): (usize, usize)| (i * 8 + j) as u32;
+    let x = Tensor::from_shape_fn(device.clone(), [3, 5], f).unwrap();
+    assert_eq!(x.into_array().unwrap(), Array::from_shape_fn([3, 5], f));
+    let x = Tensor::from_shape_fn(device.clone(), [3, 5].f(), f).unwrap();
+    assert_eq!(x.into_array().unwrap(), Array::from_shape_fn([3, 5].f(), f));
+    let f = |(i, j, k): (usize, usize, usize)| (i == j || j == k) as u8;
+    let x = Tensor::from_shape_fn(device.clone(), [2, 3, 4], f).unwrap();
+    assert_eq!(x.into_array().unwrap(), Array::from_shape_fn([2, 3, 4], f));
+}
+
+fn tensor_mapv(device: &Device) {
+    let x_array = Array::from_shape_fn([3, 4], |(i, j)| (i * 4 + j) as f32 - 5.);
+    let x = Tensor::from(x_array.clone())
+        .into_device(device.clone())
+        .unwrap();
+    if device.is_host() {
+        assert_eq!(
+            x.t().mapv(|x| x * x).unwrap().into_array().unwrap(),
+            x_array.t().mapv(|x| x * x)
+        );
+        assert_eq!(
+            x.map(|x| (*x > 0.) as u8).unwrap().into_array().unwrap(),
+            x_array.map(|x| (*x > 0.) as u8)
+        );
+    } else {
+        assert!(x.mapv(|x| x * x).is_err());
+        assert!(x.map(|x| *x as u8).is_err());
+    }
+}
+
+fn tensor_cast_shared() {
+    let as_ptr = |x: &ScalarArcTensor1| {
+        x.view()
+            .try_into_tensor_view::<f32>()
+            .unwrap()
+            .as_array()
+            .unwrap()
+            .as_ptr()
+    };
+    let x = ScalarArcTensor::from(Tensor::from(Array::from_vec(vec![1f32, 2., 3.])));
+    let y = x.cast_shared(ScalarType::F32).unwrap();
+    assert_eq!(as_ptr(&x), as_ptr(&y));
+    let y = x.clone().cast_into_shared(ScalarType::F32).unwrap();
+    assert_eq!(as_ptr(&x), as_ptr(&y));
+    let y = x
+        .cast_shared(ScalarType::BF16)
+        .unwrap()
+        .cast_into_shared(ScalarType::F32)
+        .unwrap();
+    assert_ne!(as_ptr(&x), as_ptr(&y));
+    assert_eq!(
+        x.view().try_into_tensor_view::<f32>().unwrap().as_array(),
+        y.view().try_into_tensor_view::<f32>().unwrap().as_array()
+    );
+}
+
 fn tensor_from_array<D: Dimension>(x: Array<u32, D>) {
     let y = TensorView::try_from(x.view()).unwrap();
     assert_eq!(x.view(), y.as_array().unwrap());
@@ -285,6 +900,36 @@ mod linalg {
                 }
             }
         });
+        for [m, n] in [[1, 1], [3, 5], [17, 9], [64, 33]] {
+            tests.push(device_test(
+                device,
+                &format!("tensor_outer_m{m}_n{n}"),
+                move |device| tensor_outer(device, [m, n]),
+            ));
+        }
+        for [m, k] in [[1, 1], [3, 5], [17, 9], [64, 33]] {
+            for t in [Transpose::N, Transpose::T] {
+                let name = format!("tensor_dot_matvec_f32_m{m}_k{k}_{t}");
+                tests.push(device_test(device, &name, move |device| {
+                    tensor_dot_matvec(device, [m, k], t)
+                }));
+            }
+        }
+        macro_for!($T in [bf16, u32, f32] {
+            let scalar_type = $T::SCALAR_TYPE;
+            let type_name = scalar_type.name();
+            let ignore = device.is_device() &&
+                    !features.contains(features_for_scalar(scalar_type));
+            for batch_size in [1, 3, 8] {
+                use Transpose::*;
+                for (ta, tb) in [(N, N), (T, N), (N, T), (T, T)] {
+                    let name = format!("tensor_bmm_{type_name}_b{batch_size}_{ta}{tb}");
+                    tests.push(device_test(device, &name, move |device| {
+                        tensor_bmm::<$T>(device, batch_size, [5, 7, 4], [ta, tb])
+                    }).with_ignored_flag(ignore));
+                }
+            }
+        });
         tests
     }
 
@@ -321,6 +966,85 @@ mod linalg {
         }
     }
 
+    fn tensor_outer(device: &Device, [m, n]: [usize; 2]) {
+        use autograph::tensor::outer;
+
+        let a = Array1::from_shape_fn(m, |i| (i % 7) as f32 - 3.);
+        let b = Array1::from_shape_fn(n, |i| (i % 5) as f32 + 1.);
+        let c_true = Array2::from_shape_fn([m, n], |(i, j)| a[i] * b[j]);
+        let a = Tensor::from(a).into_device(device.clone()).unwrap();
+        let b = Tensor::from(b).into_device(device.clone()).unwrap();
+        let c = outer(a.view(), b.view()).unwrap();
+        assert_eq!(c.device(), device.clone());
+        assert_eq!(c.into_array().unwrap(), c_true);
+    }
+
+    fn tensor_dot_matvec(device: &Device, [m, k]: [usize; 2], t: Transpose) {
+        let dim = match t {
+            Transpose::N => [m, k],
+            Transpose::T => [k, m],
+        };
+        let a = gen_array::<f32>(dim);
+        let x = Array1::from_shape_fn(k, |i| (i % 7) as f32 - 3.);
+        let a_tensor = Tensor::from(a.clone()).into_device(device.clone()).unwrap();
+        let x_tensor = Tensor::from(x.clone()).into_device(device.clone()).unwrap();
+        let (a, a_tensor) = match t {
+            Transpose::N => (a.view(), a_tensor.view()),
+            Transpose::T => (a.t(), a_tensor.t()),
+        };
+        let y_true = a.dot(&x);
+        let y = a_tensor.dot(&x_tensor).unwrap();
+        assert_eq!(y.device(), device.clone());
+        assert_relative_eq!(y.into_array().unwrap(), y_true);
+        let x_tensor = Tensor::from(vec![1f32; k + 1])
+            .into_device(device.clone())
+            .unwrap();
+        assert!(a_tensor.dot(&x_tensor).is_err());
+    }
+
+    fn tensor_bmm<T: Scalar>(
+        device: &Device,
+        batch_size: usize,
+        [m, k, n]: [usize; 3],
+        [a_t, b_t]: [Transpose; 2],
+    ) {
+        let gen = |[rows, cols]: [usize; 2], t: Transpose| {
+            let dim = match t {
+                Transpose::N => [rows, cols],
+                Transpose::T => [cols, rows],
+            };
+            let arrays: Vec<_> = (0..batch_size).map(|_| gen_array::<T>(dim)).collect();
+            let views: Vec<_> = arrays.iter().map(|x| x.view()).collect();
+            let array = ndarray::stack(Axis(0), &views).unwrap();
+            let tensor = Tensor::from(array.clone())
+                .into_device(device.clone())
+                .unwrap();
+            match t {
+                Transpose::N => (array, tensor),
+                Transpose::T => (
+                    array.permuted_axes([0, 2, 1]),
+                    tensor.permuted_axes([0, 2, 1]),
+                ),
+            }
+        };
+        let (a1, t1) = gen([m, k], a_t);
+        let (a2, t2) = gen([k, n], b_t);
+        let a_out = t1.bmm(&t2).unwrap().into_array().unwrap();
+        assert_eq!(a_out.shape(), &[batch_size, m, n]);
+        for ((a1, a2), a_out) in a1.outer_iter().zip(a2.outer_iter()).zip(a_out.outer_iter()) {
+            let a_true = a1.dot(&a2);
+            if matches!(T::SCALAR_TYPE, ScalarType::BF16) {
+                let a_true = a_true.map(|x| x.to_f32().unwrap());
+                let a_out = a_out.map(|x| x.to_f32().unwrap());
+                assert_relative_eq!(a_true, a_out, epsilon = k as f32);
+            } else {
+                assert_eq!(a_out, a_true);
+            }
+        }
+        let t3 = Tensor::<T, _>::zeros(device.clone(), [batch_size + 1, k, n]).unwrap();
+        assert!(t1.bmm(&t3).is_err());
+    }
+
     pub fn tensor_dot<T: Scalar>(
         device: &Device,
         [m, k, n]: [usize; 3],
@@ -379,7 +1103,7 @@ mod linalg {
 #[cfg(not(target_family = "wasm"))]
 mod ops {
     use super::*;
-    use num_traits::Unsigned;
+    use num_traits::{Float, Unsigned};
 
     pub fn ops_tests(device: &Device) -> Vec<Trial> {
         let mut tests = Vec::new();
@@ -400,6 +1124,61 @@ mod ops {
                     }
                     scaled_add::<$T>(device, &[3, 5]);
                     scaled_add::<$T>(device, &[21, 14]);
+                    scaled_add_transposed::<$T>(device, [21, 14]);
+                }).with_ignored_flag(ignore)
+            );
+            tests.push(
+                device_test(device, &format!("add_{ty}"), |device| {
+                    add::<$T>(device, &[2, 3, 4, 5], &[3, 1, 1]);
+                    add::<$T>(device, &[3, 1, 1], &[2, 3, 4, 5]);
+                    add::<$T>(device, &[4, 1], &[1, 5]);
+                    add::<$T>(device, &[], &[2, 3, 4]);
+                    add::<$T>(device, &[2, 3, 4], &[]);
+                }).with_ignored_flag(ignore)
+            );
+            tests.push(
+                device_test(device, &format!("mul_div_{ty}"), |device| {
+                    mul_div::<$T>(device, &[2, 3, 4, 5], &[3, 1, 1]);
+                    mul_div::<$T>(device, &[4, 1], &[1, 5]);
+                    mul_div::<$T>(device, &[], &[7]);
+                    div_by_zero::<$T>(device);
+                }).with_ignored_flag(ignore)
+            );
+            tests.push(
+                device_test(device, &format!("clamp_{ty}"), |device| {
+                    clamp::<$T>(device);
+                }).with_ignored_flag(ignore)
+            );
+            tests.push(
+                device_test(device, &format!("repeat_interleave_{ty}"), |device| {
+                    for axis in 0..3 {
+                        for repeats in [0, 1, 3] {
+                            repeat_interleave::<$T>(device, &[2, 3, 4], axis, repeats);
+                        }
+                        repeat_interleave_with::<$T>(device, &[2, 3, 4], axis);
+                    }
+                }).with_ignored_flag(ignore)
+            );
+        });
+        macro_for!($T in [bf16, f32, f64] {
+            let scalar_type = $T::SCALAR_TYPE;
+            let ignore = device.is_device() &&
+                !features.contains(features_for_scalar(scalar_type));
+            tests.push(
+                device_test(device, &format!("unary_{}", scalar_type.name()), |device| {
+                    unary::<$T>(device, &[7]);
+                    unary::<$T>(device, &[3, 5, 4]);
+                }).with_ignored_flag(ignore)
+            );
+        });
+        macro_for!($T in [bf16, f32] {
+            let scalar_type = $T::SCALAR_TYPE;
+            let ignore = device.is_device() &&
+                !features.contains(features_for_scalar(scalar_type));
+            tests.push(
+                device_test(device, &format!("pow_{}", scalar_type.name()), |device| {
+                    pow::<$T>(device, &[7]);
+                    pow::<$T>(device, &[3, 5, 4]);
                 }).with_ignored_flag(ignore)
             );
         });
@@ -451,59 +1230,356 @@ mod ops {
         assert_eq!(y, y_array);
     }
 
-    fn one_hot<X: Scalar + Unsigned, Y: Scalar>(device: &Device, shape: &[usize], classes: usize) {
-        let dim = shape.into_dimension();
-        let x_array = (0..classes)
+    fn scaled_add_transposed<T: Scalar>(device: &Device, [rows, cols]: [usize; 2]) {
+        let alpha = T::from_u32(2).unwrap();
+        let x_array = (1..10)
             .cycle()
-            .take(dim.size())
-            .map(|x| X::from_usize(x).unwrap())
+            .take(rows * cols)
+            .map(|x| T::from_usize(x).unwrap())
             .collect::<Array1<_>>()
-            .into_shape(shape)
+            .into_shape([rows, cols])
             .unwrap();
-        let y_shape: Vec<_> = shape.iter().copied().chain([classes]).collect();
-        let y_array = x_array
-            .iter()
-            .copied()
-            .flat_map(|x| {
-                (0..classes).map(move |i| Y::from_u32((i == x.to_usize().unwrap()) as u32).unwrap())
-            })
-            .collect::<Array<Y, _>>()
-            .into_shape(y_shape.as_slice())
+        let mut y_array = (11..20)
+            .cycle()
+            .take(x_array.len())
+            .map(|x| T::from_usize(x).unwrap())
+            .collect::<Array1<_>>()
+            .into_shape([cols, rows])
             .unwrap();
-        let x = Tensor::from(x_array).into_device(device.clone()).unwrap();
-        let y = x.to_one_hot::<Y>(classes).unwrap().into_array().unwrap();
+        let x = Tensor::from(x_array.clone())
+            .into_device(device.clone())
+            .unwrap();
+        let mut y = Tensor::from(y_array.clone())
+            .into_device(device.clone())
+            .unwrap();
+        y_array.scaled_add(alpha, &x_array.t());
+        y.scaled_add(alpha, &x.t()).unwrap();
+        let y = y.into_array().unwrap();
         assert_eq!(y, y_array);
     }
-}
-
-#[cfg(not(target_family = "wasm"))]
-mod reorder {
-    use super::*;
 
-    pub fn reorder_tests(device: &Device) -> Vec<Trial> {
-        let mut tests = Vec::new();
+    fn add<T: Scalar>(device: &Device, a_shape: &[usize], b_shape: &[usize]) {
+        let array = |shape: &[usize], start: usize| {
+            let shape = shape.into_dimension();
+            (start..start + 9)
+                .cycle()
+                .take(shape.size())
+                .map(|x| T::from_usize(x).unwrap())
+                .collect::<Array1<_>>()
+                .into_shape(shape)
+                .unwrap()
+        };
+        let a_array = array(a_shape, 1);
+        let b_array = array(b_shape, 11);
+        let c_array = &a_array + &b_array;
+        let a = Tensor::from(a_array).into_device(device.clone()).unwrap();
+        let b = Tensor::from(b_array).into_device(device.clone()).unwrap();
+        let c = a.add(&b).unwrap();
+        assert_eq!(c.device(), device.clone());
+        assert_eq!(c.into_array().unwrap(), c_array);
+        let a_scalar = a.to_device(device.clone()).unwrap().into_scalar_tensor();
+        let b_scalar = b.to_device(device.clone()).unwrap().into_scalar_tensor();
+        let c = a_scalar
+            .add(&b_scalar)
+            .unwrap()
+            .try_into_tensor::<T>()
+            .unwrap();
+        assert_eq!(c.into_array().unwrap(), c_array);
+        if let Some(len) = a_shape.last().filter(|len| **len > 1) {
+            let b = Tensor::from(vec![T::one(); len + 1])
+                .into_device(device.clone())
+                .unwrap();
+            assert!(a.add(&b).is_err());
+        }
+    }
 
-        let features = if let Some(info) = device.info() {
-            info.features()
-        } else {
-            Features::empty()
+    fn mul_div<T: Scalar>(device: &Device, a_shape: &[usize], b_shape: &[usize]) {
+        let array = |shape: &[usize], start: usize, len: usize| {
+            let shape = shape.into_dimension();
+            (start..start + len)
+                .cycle()
+                .take(shape.size())
+                .map(|x| T::from_usize(x).unwrap())
+                .collect::<Array1<_>>()
+                .into_shape(shape)
+                .unwrap()
         };
-        macro_for!($T in [u8, i8, u16, i16, f16, bf16, u32, i32, f32, u64, i64, f64] {
-                let scalar_type = $T::SCALAR_TYPE;
-                let ignore = device.is_device() &&
-                    !features.contains(features_for_scalar(scalar_type));
-                let ty = scalar_type.name();
-                tests.extend([
-                    device_test(device, &format!("into_standard_layout2_{ty}"), |device| {
-                        into_standard_layout::<$T, _>(device, [3, 3], [1, 0]);
-                        into_standard_layout::<$T, _>(device, [21, 30], [1, 0]);
-                    }),
-                    device_test(device, &format!("into_standard_layout3_{ty}"), |device| {
-                        into_standard_layout::<$T, _>(device, [1, 2, 3], [0, 2, 1]);
-                        into_standard_layout::<$T, _>(device, [2, 21, 3], [1, 2, 0]);
-                    }),
-                    device_test(device, &format!("into_standard_layout4_{ty}"), |device| {
-                        into_standard_layout::<$T, _>(device, [1, 2, 3, 3], [0, 2, 3, 1]);
+        let a_array = array(a_shape, 1, 11);
+        let b_array = array(b_shape, 1, 5);
+        let a = Tensor::from(a_array.clone())
+            .into_device(device.clone())
+            .unwrap();
+        let b = Tensor::from(b_array.clone())
+            .into_device(device.clone())
+            .unwrap();
+        assert_eq!(
+            a.mul(&b).unwrap().into_array().unwrap(),
+            &a_array * &b_array
+        );
+        assert_eq!(
+            a.div(&b).unwrap().into_array().unwrap(),
+            &a_array / &b_array
+        );
+        let a = a.into_scalar_tensor();
+        let b = b.into_scalar_tensor();
+        let c = a.div(&b).unwrap().try_into_tensor::<T>().unwrap();
+        assert_eq!(c.into_array().unwrap(), &a_array / &b_array);
+    }
+
+    fn div_by_zero<T: Scalar>(device: &Device) {
+        let a = Tensor::from(vec![T::one(), T::zero()])
+            .into_device(device.clone())
+            .unwrap();
+        let b = Tensor::from(vec![T::zero(); 2])
+            .into_device(device.clone())
+            .unwrap();
+        let float = matches!(
+            T::SCALAR_TYPE,
+            ScalarType::F16 | ScalarType::BF16 | ScalarType::F32 | ScalarType::F64
+        );
+        if float {
+            let c = a.div(&b).unwrap().into_array().unwrap();
+            assert!(c[0].cast::<f32>().is_infinite());
+            assert!(c[1].cast::<f32>().is_nan());
+        } else if device.is_host() {
+            assert!(a.div(&b).is_err());
+        }
+    }
+
+    fn unary<T: Scalar + Float>(device: &Device, shape: &[usize]) {
+        let shape = shape.into_dimension();
+        let x_array = (0..shape.size())
+            .map(|x| T::from_f32(x as f32 * 0.25 - 1.0).unwrap())
+            .collect::<Array1<_>>()
+            .into_shape(shape)
+            .unwrap();
+        let x = Tensor::from(x_array.clone())
+            .into_device(device.clone())
+            .unwrap();
+        // the device may differ from the host by a few ulps
+        let epsilon = if device.is_host() {
+            T::zero()
+        } else {
+            T::from_f32(4.).unwrap() * T::epsilon()
+        };
+        let check = |y: Tensor<T, _>, y_array: Array<T, _>| {
+            let y = y.into_array().unwrap();
+            for (y, y_true) in y.iter().zip(y_array.iter()) {
+                assert!(
+                    y == y_true
+                        || (y.is_nan() && y_true.is_nan())
+                        || (*y - *y_true).abs() <= epsilon * y_true.abs().max(T::one()),
+                    "{y:?} != {y_true:?}"
+                );
+            }
+        };
+        check(x.exp().unwrap(), x_array.mapv(T::exp));
+        check(x.ln().unwrap(), x_array.mapv(T::ln));
+        check(x.sqrt().unwrap(), x_array.mapv(T::sqrt));
+        check(x.abs().unwrap(), x_array.mapv(T::abs));
+        check(x.recip().unwrap(), x_array.mapv(T::recip));
+        let y = x.t().exp().unwrap();
+        assert!(y.is_standard_layout());
+        check(y, x_array.t().mapv(T::exp));
+    }
+
+    fn pow<T: Scalar + Float>(device: &Device, shape: &[usize]) {
+        let shape = shape.into_dimension();
+        let x_array = (0..shape.size())
+            .map(|x| T::from_f32(x as f32 * 0.25 + 0.5).unwrap())
+            .collect::<Array1<_>>()
+            .into_shape(shape)
+            .unwrap();
+        let x = Tensor::from(x_array.clone())
+            .into_device(device.clone())
+            .unwrap();
+        // the device may differ from the host by a few ulps
+        let epsilon = if device.is_host() {
+            T::zero()
+        } else {
+            T::from_f32(4.).unwrap() * T::epsilon()
+        };
+        let check = |y: Tensor<T, _>, y_array: Array<T, _>| {
+            let y = y.into_array().unwrap();
+            for (y, y_true) in y.iter().zip(y_array.iter()) {
+                assert!(
+                    (*y - *y_true).abs() <= epsilon * y_true.abs().max(T::one()),
+                    "{y:?} != {y_true:?}"
+                );
+            }
+        };
+        assert_eq!(
+            x.powi(2).unwrap().into_array().unwrap(),
+            x.mul(&x).unwrap().into_array().unwrap()
+        );
+        check(
+            x.powf(T::from_f32(0.5).unwrap()).unwrap(),
+            x_array.mapv(T::sqrt),
+        );
+        assert_eq!(
+            x.powi(0).unwrap().into_array().unwrap(),
+            x_array.mapv(|_| T::one())
+        );
+        assert_eq!(
+            x.powi(-1).unwrap().into_array().unwrap(),
+            x.recip().unwrap().into_array().unwrap()
+        );
+        check(
+            x.powi(7).unwrap(),
+            x_array.mapv(|x| x.powf(T::from_f32(7.0).unwrap())),
+        );
+        check(
+            x.powf(T::from_f32(1.5).unwrap()).unwrap(),
+            x_array.mapv(|x| x.powf(T::from_f32(1.5).unwrap())),
+        );
+    }
+
+    fn clamp<T: Scalar>(device: &Device) {
+        let x_array = (0..10u8)
+            .map(|x| T::from_u8(x).unwrap())
+            .collect::<Array1<_>>();
+        let x = Tensor::from(x_array.clone())
+            .into_device(device.clone())
+            .unwrap();
+        let [min, max] = [3, 6].map(|x| T::from_u8(x).unwrap());
+        let clamp = |x: T| {
+            if x < min {
+                min
+            } else if x > max {
+                max
+            } else {
+                x
+            }
+        };
+        let y = x.clamp(min.into(), max.into()).unwrap();
+        assert_eq!(y.device(), device.clone());
+        assert_eq!(y.into_array().unwrap(), x_array.mapv(clamp));
+        let y = x.clamp_min(min.into()).unwrap().into_array().unwrap();
+        assert_eq!(y, x_array.mapv(|x| if x < min { min } else { x }));
+        let y = x.clamp_max(max.into()).unwrap().into_array().unwrap();
+        assert_eq!(y, x_array.mapv(|x| if x > max { max } else { x }));
+        assert!(x.clamp(max.into(), min.into()).is_err());
+        let float = matches!(
+            T::SCALAR_TYPE,
+            ScalarType::F16 | ScalarType::BF16 | ScalarType::F32 | ScalarType::F64
+        );
+        if float {
+            let x = Tensor::from(vec![T::from_f32(f32::NAN).unwrap()])
+                .into_device(device.clone())
+                .unwrap();
+            let y = x
+                .clamp(min.into(), max.into())
+                .unwrap()
+                .into_array()
+                .unwrap();
+            assert!(y[0].cast::<f32>().is_nan());
+        }
+    }
+
+    fn repeat_interleave<T: Scalar>(device: &Device, shape: &[usize], axis: usize, repeats: usize) {
+        let shape = shape.into_dimension();
+        let x_array = (1..100)
+            .cycle()
+            .take(shape.size())
+            .map(|x| T::from_usize(x).unwrap())
+            .collect::<Array1<_>>()
+            .into_shape(shape.clone())
+            .unwrap();
+        let indices: Vec<usize> = (0..shape[axis])
+            .flat_map(|i| std::iter::repeat(i).take(repeats))
+            .collect();
+        let y_array = x_array.select(Axis(axis), &indices);
+        let x = Tensor::from(x_array).into_device(device.clone()).unwrap();
+        let y = x
+            .repeat_interleave(repeats, Axis(axis))
+            .unwrap()
+            .into_array()
+            .unwrap();
+        assert_eq!(y, y_array);
+        assert!(x.repeat_interleave(repeats, Axis(shape.ndim())).is_err());
+    }
+
+    fn repeat_interleave_with<T: Scalar>(device: &Device, shape: &[usize], axis: usize) {
+        let shape = shape.into_dimension();
+        let x_array = (1..100)
+            .cycle()
+            .take(shape.size())
+            .map(|x| T::from_usize(x).unwrap())
+            .collect::<Array1<_>>()
+            .into_shape(shape.clone())
+            .unwrap();
+        let repeats: Vec<usize> = (0..shape[axis]).map(|i| i % 3).collect();
+        let indices: Vec<usize> = repeats
+            .iter()
+            .enumerate()
+            .flat_map(|(i, r)| std::iter::repeat(i).take(*r))
+            .collect();
+        let y_array = x_array.select(Axis(axis), &indices);
+        let x = Tensor::from(x_array).into_device(device.clone()).unwrap();
+        let y = x
+            .repeat_interleave_with(&repeats, Axis(axis))
+            .unwrap()
+            .into_array()
+            .unwrap();
+        assert_eq!(y, y_array);
+        assert!(x
+            .repeat_interleave_with(&repeats, Axis(shape.ndim()))
+            .is_err());
+    }
+
+    fn one_hot<X: Scalar + Unsigned, Y: Scalar>(device: &Device, shape: &[usize], classes: usize) {
+        let dim = shape.into_dimension();
+        let x_array = (0..classes)
+            .cycle()
+            .take(dim.size())
+            .map(|x| X::from_usize(x).unwrap())
+            .collect::<Array1<_>>()
+            .into_shape(shape)
+            .unwrap();
+        let y_shape: Vec<_> = shape.iter().copied().chain([classes]).collect();
+        let y_array = x_array
+            .iter()
+            .copied()
+            .flat_map(|x| {
+                (0..classes).map(move |i| Y::from_u32((i == x.to_usize().unwrap()) as u32).unwrap())
+            })
+            .collect::<Array<Y, _>>()
+            .into_shape(y_shape.as_slice())
+            .unwrap();
+        let x = Tensor::from(x_array).into_device(device.clone()).unwrap();
+        let y = x.to_one_hot::<Y>(classes).unwrap().into_array().unwrap();
+        assert_eq!(y, y_array);
+    }
+}
+
+#[cfg(not(target_family = "wasm"))]
+mod reorder {
+    use super::*;
+
+    pub fn reorder_tests(device: &Device) -> Vec<Trial> {
+        let mut tests = Vec::new();
+
+        let features = if let Some(info) = device.info() {
+            info.features()
+        } else {
+            Features::empty()
+        };
+        macro_for!($T in [u8, i8, u16, i16, f16, bf16, u32, i32, f32, u64, i64, f64] {
+                let scalar_type = $T::SCALAR_TYPE;
+                let ignore = device.is_device() &&
+                    !features.contains(features_for_scalar(scalar_type));
+                let ty = scalar_type.name();
+                tests.extend([
+                    device_test(device, &format!("into_standard_layout2_{ty}"), |device| {
+                        into_standard_layout::<$T, _>(device, [3, 3], [1, 0]);
+                        into_standard_layout::<$T, _>(device, [21, 30], [1, 0]);
+                    }),
+                    device_test(device, &format!("into_standard_layout3_{ty}"), |device| {
+                        into_standard_layout::<$T, _>(device, [1, 2, 3], [0, 2, 1]);
+                        into_standard_layout::<$T, _>(device, [2, 21, 3], [1, 2, 0]);
+                    }),
+                    device_test(device, &format!("into_standard_layout4_{ty}"), |device| {
+                        into_standard_layout::<$T, _>(device, [1, 2, 3, 3], [0, 2, 3, 1]);
                         into_standard_layout::<$T, _>(device, [2, 21, 3, 30], [0, 3, 1, 2]);
                     }),
                     device_test(device, &format!("into_standard_layout5_{ty}"), |device| {
@@ -516,10 +1592,23 @@ mod reorder {
                     }),
                 ].into_iter().map(|trial| trial.with_ignored_flag(ignore)));
         });
-
+        tests.push(device_test(device, "permute", permute));
         tests
     }
 
+    fn permute(device: &Device) {
+        let x_array = Array::from_shape_vec([2, 3, 4], (0..24u32).collect()).unwrap();
+        let x = Tensor::from(x_array).into_device(device.clone()).unwrap();
+        for axes in [[0, 1, 2], [0, 2, 1], [2, 0, 1], [1, 2, 0]] {
+            let y = x.permute(axes).unwrap();
+            assert!(y.is_standard_layout());
+            let y_true = x.view().permuted_axes(axes).into_standard_layout().unwrap();
+            assert_eq!(y.into_array().unwrap(), y_true.into_array().unwrap());
+        }
+        assert!(x.permute([0, 0, 1]).is_err());
+        assert!(x.permute([0, 1, 3]).is_err());
+    }
+
     fn into_standard_layout<T: Scalar, E: IntoDimension>(device: &Device, shape: E, axes: E) {
         let shape = shape.into_dimension();
         let x_vec = (1..100)
@@ -550,6 +1639,9 @@ mod reorder {
 #[cfg(not(target_family = "wasm"))]
 mod reduce {
     use super::*;
+    use approx::assert_relative_eq;
+    use autograph::tensor::ReduceOp;
+    use num_traits::Float;
     use std::mem::size_of;
 
     pub fn reduce_tests(device: &Device) -> Vec<Trial> {
@@ -580,6 +1672,11 @@ mod reduce {
                         sum::<$T, _>(device, vec![2; ndim]);
                     }
                 }).with_ignored_flag(ignore),
+                device_test(device, &format!("sum_strided_{ty_name}"), |device| {
+                    for n in ns.iter().copied() {
+                        sum_strided::<$T>(device, n);
+                    }
+                }).with_ignored_flag(ignore),
                 device_test(device, &format!("sum_axis1_{ty_name}"), |device| {
                     for n in ns.iter().copied() {
                         sum_axis::<$T, _>(device, [n], Axis(0));
@@ -630,41 +1727,123 @@ mod reduce {
                         }
                     }
                 }).with_ignored_flag(ignore),
+                device_test(device, &format!("reduce_{ty_name}"), |device| {
+                    use autograph::tensor::ReduceOp::*;
+                    for op in [Sum, Prod, Max, Min, And, Or] {
+                        for axis in 0 .. 2 {
+                            reduce::<$T, _>(device, [2, 3], Axis(axis), op);
+                        }
+                        reduce::<$T, _>(device, [3, 2, 4], Axis(1), op);
+                        reduce::<$T, _>(device, [4, 70], Axis(1), op);
+                    }
+                }).with_ignored_flag(ignore),
+                device_test(device, &format!("prod_{ty_name}"), |device| {
+                    prod::<$T>(device);
+                }).with_ignored_flag(ignore),
+                device_test(device, &format!("max_min_{ty_name}"), |device| {
+                    for shape in [[1, 1], [3, 5], [7, 2]] {
+                        max_min::<$T>(device, shape);
+                    }
+                }).with_ignored_flag(ignore),
+            ]);
+        });
+        let ignore =
+            device.is_device() && !features.contains(features_for_scalar(ScalarType::BF16));
+        tests.push(
+            device_test(device, "sum_with_acc_bf16", sum_with_acc_bf16).with_ignored_flag(ignore),
+        );
+        macro_for!($T in [bf16, f32, f64] {
+            let scalar_type = $T::SCALAR_TYPE;
+            let ignore = device.is_device() &&
+                !features.contains(features_for_scalar(scalar_type));
+            let ty_name = scalar_type.name();
+            tests.extend([
+                device_test(device, &format!("mean_{ty_name}"), |device| {
+                    for n in [4, 11, 33, 517] {
+                        mean::<$T, _>(device, n);
+                    }
+                    for ndim in 0 ..= 6 {
+                        mean::<$T, _>(device, vec![2; ndim]);
+                    }
+                }).with_ignored_flag(ignore),
+                device_test(device, &format!("mean_axis_{ty_name}"), |device| {
+                    for n in [4, 11, 33, 517] {
+                        for axis in 0 .. 3 {
+                            let mut shape = [3; 3];
+                            shape[axis] = n;
+                            mean_axis::<$T, _>(device, shape, Axis(axis));
+                        }
+                    }
+                }).with_ignored_flag(ignore),
+                device_test(device, &format!("var_std_axis_{ty_name}"), |device| {
+                    var_std_axis::<$T>(device);
+                }).with_ignored_flag(ignore),
             ]);
         });
         tests
     }
 
-    fn sum<T: Scalar, E: IntoDimension>(device: &Device, shape: E) {
+    fn mean<T: Scalar + Float, E: IntoDimension>(device: &Device, shape: E) {
         let shape = shape.into_dimension();
         let x_array = (1..10)
             .cycle()
             .take(shape.size())
-            .map(|x| {
-                let size = size_of::<T>();
-                let x = if size == 1 { (x == 1) as usize } else { x };
-                T::from_usize(x).unwrap()
-            })
+            .map(|x| T::from_usize(x).unwrap())
             .collect::<Array1<_>>()
-            .into_shape(shape.clone())
+            .into_shape(shape)
             .unwrap();
-        let y_array = x_array.sum();
+        let y_true =
+            x_array.iter().map(|x| x.to_f64().unwrap()).sum::<f64>() / x_array.len() as f64;
         let x = Tensor::from(x_array).into_device(device.clone()).unwrap();
-        let y = x.sum().unwrap();
-        let y = Tensor::from(vec![y]).into_shape(()).unwrap().into_dyn();
-        let y_array = Tensor::from(vec![y_array])
-            .into_shape(())
-            .unwrap()
-            .into_dyn();
-        let epsilon = if matches!(T::SCALAR_TYPE, ScalarType::F16 | ScalarType::BF16) {
-            Some(ScalarElem::F32(shape.size() as f32))
+        let y = x.mean().unwrap().to_f64().unwrap();
+        let epsilon = if T::SCALAR_TYPE == ScalarType::BF16 {
+            1e-2
         } else {
-            None
+            1e-5
         };
-        check_approx_eq(y.view().into(), y_array.view().into(), epsilon);
+        assert_relative_eq!(y, y_true, max_relative = epsilon);
     }
 
-    fn sum_axis<T: Scalar, E: IntoDimension>(device: &Device, shape: E, axis: Axis)
+    fn var_std_axis<T: Scalar + Float>(device: &Device) {
+        let x_array =
+            Array::from_shape_vec([3, 4], vec![1., 2., 4., 7., 0., 5., 3., 3., 6., 1., 2., 8.])
+                .unwrap();
+        let x = Tensor::from(x_array.map(|x| T::from_f64(*x).unwrap()))
+            .into_device(device.clone())
+            .unwrap();
+        let epsilon = if T::SCALAR_TYPE == ScalarType::BF16 {
+            1e-2
+        } else {
+            1e-5
+        };
+        for axis in [Axis(0), Axis(1)] {
+            for ddof in [0., 1.] {
+                let var = x
+                    .var_axis(axis, T::from_f64(ddof).unwrap())
+                    .unwrap()
+                    .into_array()
+                    .unwrap();
+                let std = x
+                    .std_axis(axis, T::from_f64(ddof).unwrap())
+                    .unwrap()
+                    .into_array()
+                    .unwrap();
+                let var_true = x_array.var_axis(axis, ddof);
+                let std_true = x_array.std_axis(axis, ddof);
+                for (y, y_true) in var.iter().zip(var_true.iter()) {
+                    assert_relative_eq!(y.to_f64().unwrap(), *y_true, max_relative = epsilon);
+                }
+                for (y, y_true) in std.iter().zip(std_true.iter()) {
+                    assert_relative_eq!(y.to_f64().unwrap(), *y_true, max_relative = epsilon);
+                }
+            }
+        }
+        assert!(x.var_axis(Axis(0), T::from_f64(4.).unwrap()).is_err());
+        assert!(x.var_axis(Axis(0), T::from_f64(-1.).unwrap()).is_err());
+        assert!(x.std_axis(Axis(2), T::zero()).is_err());
+    }
+
+    fn mean_axis<T: Scalar + Float, E: IntoDimension>(device: &Device, shape: E, axis: Axis)
     where
         E::Dim: RemoveAxis,
     {
@@ -672,258 +1851,1492 @@ mod reduce {
         let x_array = (1..16)
             .cycle()
             .take(shape.size())
-            .map(|x| {
-                let size = size_of::<T>();
-                let x = if size == 1 { (x == 1) as usize } else { x };
-                T::from_usize(x).unwrap()
-            })
+            .map(|x| T::from_usize(x).unwrap())
             .collect::<Array1<_>>()
             .into_shape(shape.clone())
             .unwrap();
-        let y_array = x_array.sum_axis(axis);
+        let y_array = x_array
+            .map(|x| x.to_f64().unwrap())
+            .mean_axis(axis)
+            .unwrap();
         let x = Tensor::from(x_array).into_device(device.clone()).unwrap();
-        let y_array = Tensor::from(y_array).into_dyn();
         let y = x
-            .sum_axis(axis)
+            .mean_axis(axis)
             .unwrap()
-            .into_device(Device::host())
+            .into_array()
             .unwrap()
-            .into_dyn();
-        let epsilon = if matches!(T::SCALAR_TYPE, ScalarType::F16 | ScalarType::BF16) {
-            Some(ScalarElem::F32(shape[axis.0] as f32))
+            .map(|x| x.to_f64().unwrap());
+        let epsilon = if T::SCALAR_TYPE == ScalarType::BF16 {
+            1e-2
         } else {
-            None
+            1e-5
         };
-        check_approx_eq(y.view().into(), y_array.view().into(), epsilon);
+        for (y, y_true) in y.iter().zip(y_array.iter()) {
+            assert_relative_eq!(*y, *y_true, max_relative = epsilon);
+        }
     }
-}
-
-#[cfg(all(not(target_family = "wasm"), feature = "learn"))]
-mod learn {
-    use super::*;
-    use approx::assert_relative_eq;
-    use autograph::learn::criterion::CrossEntropyLoss;
 
-    pub fn learn_tests(device: &Device) -> Vec<Trial> {
-        let mut tests = Vec::new();
-        tests.extend(criterion::criterion_tests(device));
-        #[cfg(feature = "neural-network")]
-        {
-            tests.extend(neural_network::neural_network_tests(device));
+    fn prod<T: Scalar>(device: &Device) {
+        let x_array = (1..6)
+            .map(|x| T::from_usize(x).unwrap())
+            .collect::<Array1<_>>();
+        let x = Tensor::from(x_array.clone())
+            .into_device(device.clone())
+            .unwrap();
+        assert_eq!(x.prod().unwrap(), x_array.product());
+        let x_array = (0..12)
+            .map(|x| T::from_usize(x % 3 + 1).unwrap())
+            .collect::<Array1<_>>()
+            .into_shape([3, 4])
+            .unwrap();
+        let x = Tensor::from(x_array.clone())
+            .into_device(device.clone())
+            .unwrap();
+        for axis in [Axis(0), Axis(1)] {
+            let y_array = x_array.fold_axis(axis, T::one(), |a, b| *a * *b);
+            assert_eq!(x.prod_axis(axis).unwrap().into_array().unwrap(), y_array);
+        }
+        assert!(x.prod_axis(Axis(2)).is_err());
+        if T::SCALAR_TYPE == ScalarType::U8 {
+            let x = Tensor::from(vec![T::from_u8(16).unwrap(); 3])
+                .into_device(device.clone())
+                .unwrap();
+            assert_eq!(x.prod().unwrap(), T::zero());
+            let y = x.reduce(Axis(0), T::one(), ReduceOp::Prod).unwrap();
+            assert_eq!(y.into_array().unwrap().into_scalar(), T::zero());
+            let x = Tensor::from(vec![T::from_u8(200).unwrap(); 3])
+                .into_device(device.clone())
+                .unwrap();
+            let y = x.reduce(Axis(0), T::zero(), ReduceOp::Sum).unwrap();
+            assert_eq!(y.into_array().unwrap().into_scalar(), T::from_u8(88).unwrap());
         }
-        tests
     }
 
-    mod criterion {
-        use super::*;
-        use autograph::learn::criterion::Accuracy;
-        use num_traits::{Float, Unsigned};
-
-        pub fn criterion_tests(device: &Device) -> Vec<Trial> {
-            let mut tests = Vec::new();
-            let features = device
-                .info()
-                .map(|info| info.features())
-                .unwrap_or(Features::empty());
-            macro_for!($X in [bf16, f32] {
-                macro_for!($T in [u8, u16, u32] {
-                    let ignore = device.is_device()
-                        && (
-                            !features.contains(features_for_scalar($X::SCALAR_TYPE))
-                            || !features.contains(features_for_scalar($T::SCALAR_TYPE))
-                        );
-                    tests.push(device_test(device, &format!("accuracy_{}_{}", $X::SCALAR_TYPE.name(), $T::SCALAR_TYPE.name()), |device| {
-                        for (batch_size, classes) in [
-                            (1, 8),
-                            (31, 16),
-                            (1000, 100),
-                        ] {
-                            accuracy::<$X, $T>(device, batch_size, classes);
-                        }
-                    }).with_ignored_flag(ignore));
-                });
+    fn max_min<T: Scalar>(device: &Device, shape: [usize; 2]) {
+        let x_array = (0..shape[0] * shape[1])
+            .map(|x| T::from_usize(x * 7 % 5).unwrap())
+            .collect::<Array1<_>>()
+            .into_shape(shape)
+            .unwrap();
+        let x = Tensor::from(x_array.clone())
+            .into_device(device.clone())
+            .unwrap();
+        let max = |a: T, b: T| if b > a { b } else { a };
+        let min = |a: T, b: T| if b < a { b } else { a };
+        let first = x_array[(0, 0)];
+        assert_eq!(x.max().unwrap(), x_array.iter().copied().fold(first, max));
+        assert_eq!(x.min().unwrap(), x_array.iter().copied().fold(first, min));
+        for axis in [Axis(0), Axis(1)] {
+            let argmax = x_array.map_axis(axis, |lane| {
+                let y = lane.iter().copied().fold(lane[0], max);
+                lane.iter().position(|x| *x == y).unwrap() as u32
             });
-            macro_for!($X in [bf16, f32] {
-                macro_for!($T in [u8, u16, u32] {
-                    let ignore = device.is_device()
-                        && (
-                            !features.contains(features_for_scalar($X::SCALAR_TYPE))
-                            || !features.contains(features_for_scalar($T::SCALAR_TYPE))
-                        );
-                    tests.push(device_test(device, &format!("cross_entropy_loss_{}_{}", $X::SCALAR_TYPE.name(), $T::SCALAR_TYPE.name()), |device| {
-                        for (batch_size, classes) in [
-                            (1, 8),
-                            (31, 16),
-                            (1000, 100),
-                        ] {
-                            cross_entropy_loss::<$X, $T>(device, batch_size, classes);
-                        }
-                    }).with_ignored_flag(ignore));
-                });
+            let argmin = x_array.map_axis(axis, |lane| {
+                let y = lane.iter().copied().fold(lane[0], min);
+                lane.iter().position(|x| *x == y).unwrap() as u32
             });
-            tests
+            assert_eq!(
+                x.max_axis(axis).unwrap().into_array().unwrap(),
+                x_array.map_axis(axis, |lane| lane.iter().copied().fold(lane[0], max))
+            );
+            assert_eq!(
+                x.min_axis(axis).unwrap().into_array().unwrap(),
+                x_array.map_axis(axis, |lane| lane.iter().copied().fold(lane[0], min))
+            );
+            assert_eq!(x.argmax_axis(axis).unwrap().into_array().unwrap(), argmax);
+            assert_eq!(x.argmin_axis(axis).unwrap().into_array().unwrap(), argmin);
         }
+        assert!(x.max_axis(Axis(2)).is_err());
+        assert!(x.argmin_axis(Axis(2)).is_err());
+        assert!(x.sum_axis(Axis(2)).is_err());
+        let empty = Tensor::from(Array::<T, _>::zeros([0, 2]))
+            .into_device(device.clone())
+            .unwrap();
+        assert!(empty.max().is_err());
+        assert!(empty.argmax_axis(Axis(0)).is_err());
+        assert_eq!(empty.argmax_axis(Axis(1)).unwrap().shape(), &[0]);
+    }
 
-        fn accuracy<X: Scalar + Float, T: Scalar + Unsigned>(
-            device: &Device,
-            batch_size: usize,
-            classes: usize,
-        ) {
-            let x_vec: Vec<X> = (0..classes)
-                .map(|x| X::from_usize(x).unwrap())
-                .cycle()
-                .skip(classes / 2 + 1)
-                .take(batch_size * classes)
-                .collect();
-            let t_vec: Vec<T> = (0..classes)
-                .cycle()
-                .map(|t| T::from_usize(t).unwrap())
-                .take(batch_size)
-                .collect();
-            let x_array = Array::from(x_vec)
-                .into_shape([batch_size, classes])
-                .unwrap();
-            let t_array = Array::from(t_vec);
-            let x_host = Tensor::from(x_array);
-            let t_host = Tensor::from(t_array);
-            let x_device = x_host.to_device(device.clone()).unwrap();
-            let t_device = t_host.to_device(device.clone()).unwrap();
-            let y_host = x_host.accuracy(t_host).unwrap();
-            let y_device = x_device.accuracy(t_device).unwrap();
-            assert_eq!(y_host, y_device);
-        }
+    fn sum_with_acc_bf16(device: &Device) {
+        let n = 10_000;
+        let x_array = Array1::from_elem(n, bf16::from_f32(0.1));
+        let y_true = n as f64 * x_array[0].to_f64();
+        let x = Tensor::from(x_array).into_device(device.clone()).unwrap();
+        let y_bf16 = x.sum().unwrap().to_f64();
+        let y_f32 = x.sum_with_acc::<f32>().unwrap() as f64;
+        assert!((y_f32 - y_true).abs() <= (y_bf16 - y_true).abs());
+        assert!((y_f32 - y_true).abs() / y_true < 1e-4);
+    }
 
-        fn cross_entropy_loss<X: Scalar + Float, T: Scalar + Unsigned>(
-            device: &Device,
-            batch_size: usize,
-            classes: usize,
-        ) {
-            let x_vec: Vec<X> = (0..10u8)
-                .map(|x| X::from_u8(x).unwrap())
-                .cycle()
-                .take(batch_size * classes)
-                .collect();
-            let t_vec: Vec<T> = (0..classes)
-                .cycle()
-                .map(|t| T::from_usize(t).unwrap())
-                .take(batch_size)
-                .collect();
-            let x_array = Array::from(x_vec)
-                .into_shape([batch_size, classes])
-                .unwrap();
-            let t_array = Array::from(t_vec);
-            let x_host = Tensor::from(x_array);
-            let t_host = Tensor::from(t_array);
-            let x_device = x_host.to_device(device.clone()).unwrap();
-            let t_device = t_host.to_device(device.clone()).unwrap();
-            let y_host = x_host.cross_entropy_loss(t_host).unwrap();
-            let y_device = x_device.cross_entropy_loss(t_device).unwrap();
-            let epsilon = if X::SCALAR_TYPE == ScalarType::BF16 {
-                batch_size as f32 * 0.001
-            } else {
-                batch_size as f32 * f32::EPSILON
-            };
-            assert_relative_eq!(y_host, y_device, epsilon = epsilon, max_relative = epsilon);
-        }
+    fn sum<T: Scalar, E: IntoDimension>(device: &Device, shape: E) {
+        let shape = shape.into_dimension();
+        let x_array = (1..10)
+            .cycle()
+            .take(shape.size())
+            .map(|x| {
+                let size = size_of::<T>();
+                let x = if size == 1 { (x == 1) as usize } else { x };
+                T::from_usize(x).unwrap()
+            })
+            .collect::<Array1<_>>()
+            .into_shape(shape.clone())
+            .unwrap();
+        let y_array = x_array.sum();
+        let x = Tensor::from(x_array).into_device(device.clone()).unwrap();
+        let y = x.sum().unwrap();
+        let y = Tensor::from(vec![y]).into_shape(()).unwrap().into_dyn();
+        let y_array = Tensor::from(vec![y_array])
+            .into_shape(())
+            .unwrap()
+            .into_dyn();
+        let epsilon = if matches!(T::SCALAR_TYPE, ScalarType::F16 | ScalarType::BF16) {
+            Some(ScalarElem::F32(shape.size() as f32))
+        } else {
+            None
+        };
+        check_approx_eq(y.view().into(), y_array.view().into(), epsilon);
     }
 
-    #[cfg(feature = "neural-network")]
-    mod neural_network {
-        use super::*;
-        use autograph::{
-            learn::neural_network::{
-                self,
-                autograd::Variable,
-                layer::{Forward, MaxPool2, Relu, __private::Conv2Options},
-            },
-            ops::__private::{Col2ImConv2, Col2ImConv2Options, Im2ColConv2, Im2ColConv2Options},
-            tensor::ScalarArcTensor,
+    fn sum_strided<T: Scalar>(device: &Device, n: usize) {
+        let x_array = (1..10)
+            .cycle()
+            .take(2 * n * 3)
+            .map(|x| {
+                let size = size_of::<T>();
+                let x = if size == 1 { (x == 1) as usize } else { x };
+                T::from_usize(x).unwrap()
+            })
+            .collect::<Array1<_>>()
+            .into_shape([2, n, 3])
+            .unwrap();
+        let x = Tensor::from(x_array.clone())
+            .into_device(device.clone())
+            .unwrap();
+        let epsilon = if matches!(T::SCALAR_TYPE, ScalarType::F16 | ScalarType::BF16) {
+            Some(ScalarElem::F32(x_array.len() as f32))
+        } else {
+            None
         };
-        use ndarray::{Array4, ArrayView4};
-        use num_traits::{Float, Unsigned};
-        use std::sync::Arc;
+        let check = |y: T, y_array: T| {
+            let y = Tensor::from(vec![y]).into_shape(()).unwrap().into_dyn();
+            let y_array = Tensor::from(vec![y_array])
+                .into_shape(())
+                .unwrap()
+                .into_dyn();
+            check_approx_eq(y.view().into(), y_array.view().into(), epsilon);
+        };
+        check(x.t().sum().unwrap(), x_array.sum());
+        check(
+            x.view().permuted_axes([1, 0, 2]).sum().unwrap(),
+            x_array.sum(),
+        );
+        check(
+            x.index_axis(Axis(2), 1).sum().unwrap(),
+            x_array.index_axis(Axis(2), 1).sum(),
+        );
+        check(
+            x.index_axis(Axis(0), 1).t().sum().unwrap(),
+            x_array.index_axis(Axis(0), 1).sum(),
+        );
+    }
 
-        fn conv2_string(filter: [usize; 2], options: &Conv2Options) -> String {
-            let [fh, fw] = filter;
-            let Conv2Options {
-                padding,
-                stride,
-                dilation,
-            } = options;
-            let (ph, pw) = padding.into_pattern();
-            let (sh, sw) = stride.into_pattern();
-            let (dh, dw) = dilation.into_pattern();
-            format!("fh{fh}_fw{fw}_ph{ph}_pw{pw}_sh{sh}_sw{sw}_dh{dh}_dw{dw}")
+    fn reduce<T: Scalar, E: IntoDimension>(device: &Device, shape: E, axis: Axis, op: ReduceOp)
+    where
+        E::Dim: RemoveAxis,
+    {
+        let shape = shape.into_dimension();
+        let x_array = (0..4)
+            .cycle()
+            .take(shape.size())
+            .map(|x| T::from_usize(x).unwrap())
+            .collect::<Array1<_>>()
+            .into_shape(shape.clone())
+            .unwrap();
+        let (init, f): (T, fn(T, T) -> T) = match op {
+            ReduceOp::Sum => (T::zero(), |a, b| a + b),
+            ReduceOp::Prod => (T::one(), |a, b| a * b),
+            ReduceOp::Max => (T::zero(), |a, b| if b > a { b } else { a }),
+            ReduceOp::Min => (T::from_u8(3).unwrap(), |a, b| if b < a { b } else { a }),
+            ReduceOp::And => (T::one(), |a, b| {
+                T::from_u8((a != T::zero() && b != T::zero()) as u8).unwrap()
+            }),
+            ReduceOp::Or => (T::zero(), |a, b| {
+                T::from_u8((a != T::zero() || b != T::zero()) as u8).unwrap()
+            }),
+        };
+        let y_array = x_array.fold_axis(axis, init, |a, b| f(*a, *b));
+        let x = Tensor::from(x_array).into_device(device.clone()).unwrap();
+        let y = x.reduce(axis, init, op).unwrap().into_array().unwrap();
+        assert_eq!(y, y_array);
+    }
+
+    fn sum_axis<T: Scalar, E: IntoDimension>(device: &Device, shape: E, axis: Axis)
+    where
+        E::Dim: RemoveAxis,
+    {
+        let shape = shape.into_dimension();
+        let x_array = (1..16)
+            .cycle()
+            .take(shape.size())
+            .map(|x| {
+                let size = size_of::<T>();
+                let x = if size == 1 { (x == 1) as usize } else { x };
+                T::from_usize(x).unwrap()
+            })
+            .collect::<Array1<_>>()
+            .into_shape(shape.clone())
+            .unwrap();
+        let y_array = x_array.sum_axis(axis);
+        let x = Tensor::from(x_array).into_device(device.clone()).unwrap();
+        let y_array = Tensor::from(y_array).into_dyn();
+        let y = x
+            .sum_axis(axis)
+            .unwrap()
+            .into_device(Device::host())
+            .unwrap()
+            .into_dyn();
+        let epsilon = if matches!(T::SCALAR_TYPE, ScalarType::F16 | ScalarType::BF16) {
+            Some(ScalarElem::F32(shape[axis.0] as f32))
+        } else {
+            None
+        };
+        check_approx_eq(y.view().into(), y_array.view().into(), epsilon);
+    }
+}
+
+#[cfg(all(not(target_family = "wasm"), feature = "dataset"))]
+mod dataset {
+    use super::*;
+    use autograph::dataset::{kfold_split, sampler::WeightedRandomSampler, Dataset};
+
+    pub fn dataset_tests(device: &Device) -> Vec<Trial> {
+        let mut tests = Vec::new();
+        if device.is_host() {
+            tests.extend([
+                device_test(device, "dataset_subset", dataset_subset),
+                device_test(device, "dataset_concat", dataset_concat),
+                device_test(device, "dataset_random_split", dataset_random_split),
+                device_test(device, "kfold_split", kfold_split_test),
+                device_test(device, "csv_from_reader", csv_from_reader),
+                device_test(
+                    device,
+                    "weighted_random_sampler_replacement",
+                    weighted_random_sampler_replacement,
+                ),
+                device_test(
+                    device,
+                    "weighted_random_sampler_no_replacement",
+                    weighted_random_sampler_no_replacement,
+                ),
+            ]);
         }
+        #[cfg(feature = "image-folder")]
+        if device.is_host() {
+            tests.push(device_test(device, "image_folder", image_folder));
+        }
+        tests.push(device_test(device, "pad_sequence", pad_sequence_test));
+        tests.push(device_test(device, "data_loader", data_loader));
+        tests.push(device_test(
+            device,
+            "random_horizontal_flip",
+            random_horizontal_flip,
+        ));
+        tests.push(device_test(device, "normalize", normalize));
+        tests.push(device_test(device, "random_crop", random_crop));
+        tests
+    }
 
-        pub fn neural_network_tests(device: &Device) -> Vec<Trial> {
-            let mut tests = Vec::new();
-            let features = device
-                .info()
-                .map(|info| info.features())
-                .unwrap_or(Features::empty());
+    #[cfg(feature = "image-folder")]
+    fn image_folder(_device: &Device) {
+        use autograph::dataset::image_folder::ImageFolder;
+        use image::{Rgb, RgbImage};
 
-            macro_for!($X in [bf16, f32] {
-                macro_for!($T in [u8, u16, u32] {
-                    let ignore = device.is_device()
-                    && (
-                        !features.contains(features_for_scalar($X::SCALAR_TYPE))
-                        || !features.contains(features_for_scalar($T::SCALAR_TYPE))
-                    );
-                    tests.push(device_test(device, &format!("cross_entropy_loss_backward_{}_{}", $X::SCALAR_TYPE.name(), $T::SCALAR_TYPE.name()), |device| {
-                        for (batch_size, classes) in [
-                            (1, 8),
-                            (31, 16),
+        let dir = tempfile::tempdir().unwrap();
+        let images = [("dog", 3, [0, 0, 255]), ("cat", 2, [255, 0, 0])];
+        for (class, count, pixel) in images {
+            let class_dir = dir.path().join(class);
+            std::fs::create_dir(&class_dir).unwrap();
+            for i in 0..count {
+                let extension = if i % 2 == 0 { "png" } else { "jpg" };
+                RgbImage::from_pixel(4 + i, 2, Rgb(pixel))
+                    .save(class_dir.join(format!("{i}.{extension}")))
+                    .unwrap();
+            }
+        }
+        std::fs::write(dir.path().join("cat").join("notes.txt"), "").unwrap();
+        let dataset = ImageFolder::builder(dir.path())
+            .resize([3, 5])
+            .build()
+            .unwrap();
+        assert_eq!(dataset.classes(), ["cat", "dog"]);
+        assert_eq!(dataset.len(), 5);
+        for index in 0..dataset.len() {
+            let (image, class) = dataset.get(index).unwrap();
+            assert_eq!(class, (index >= 2) as u32);
+            assert_eq!(image.shape(), [3, 3, 5]);
+            let image = image.into_array().unwrap();
+            let (_, _, pixel) = images[1 - class as usize];
+            // jpeg is lossy
+            for (channel, value) in pixel.iter().enumerate() {
+                assert!(image
+                    .index_axis(Axis(0), channel)
+                    .iter()
+                    .all(|x| x.abs_diff(*value) <= 4));
+            }
+        }
+        assert!(dataset.get(5).is_err());
+        let dataset = ImageFolder::builder(dir.path()).build().unwrap();
+        let (image, _) = dataset.get(1).unwrap();
+        assert_eq!(image.shape(), [3, 2, 5]);
+        assert!(ImageFolder::builder(dir.path().join("cat"))
+            .build()
+            .is_err());
+    }
+
+    fn csv_from_reader(_device: &Device) {
+        use autograph::dataset::csv::Csv;
+
+        let data = "a;label;b\n1;0;2.5\n\n-3;1;4\n";
+        let csv = Csv::builder()
+            .has_header(true)
+            .label_column(1)
+            .delimiter(';')
+            .from_reader(data.as_bytes())
+            .unwrap();
+        assert_eq!(csv.header.unwrap(), ["a", "label", "b"]);
+        assert_eq!(
+            csv.features.into_array().unwrap(),
+            ndarray::arr2(&[[1f32, 2.5], [-3., 4.]])
+        );
+        assert_eq!(csv.labels.unwrap().into_array().unwrap().to_vec(), [0., 1.]);
+        let csv = Csv::builder().from_reader("1,2\n3,4".as_bytes()).unwrap();
+        assert!(csv.header.is_none() && csv.labels.is_none());
+        assert_eq!(csv.features.shape(), [2, 2]);
+        let error = Csv::builder()
+            .from_reader("1,2\n3,x\n".as_bytes())
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("row 2 column 1"), "{error}");
+        assert!(Csv::builder().from_reader("1,2\n3\n".as_bytes()).is_err());
+        assert!(Csv::builder()
+            .label_column(2)
+            .from_reader("1,2\n".as_bytes())
+            .is_err());
+    }
+
+    fn random_horizontal_flip(device: &Device) {
+        use autograph::dataset::transforms::RandomHorizontalFlip;
+        use ndarray::{s, Array4};
+
+        fn test<T: Scalar>(device: &Device) {
+            let x_array = Array4::from_shape_fn([4, 2, 3, 5], |(n, c, h, w)| {
+                T::from_usize(((n * 2 + c) * 3 + h) * 5 + w).unwrap()
+            });
+            let x = Tensor::from(x_array.clone())
+                .into_device(device.clone())
+                .unwrap();
+            let flipped_array = x_array.slice(s![.., .., .., ..;-1]).to_owned();
+            let y = RandomHorizontalFlip::new(1.)
+                .unwrap()
+                .apply(x.view())
+                .unwrap();
+            assert_eq!(y.device(), device.clone());
+            assert_eq!(y.into_array().unwrap(), flipped_array);
+            let y = RandomHorizontalFlip::new(0.)
+                .unwrap()
+                .apply(x.view())
+                .unwrap();
+            assert_eq!(y.into_array().unwrap(), x_array);
+            let y = RandomHorizontalFlip::new(0.5)
+                .unwrap()
+                .seed(0)
+                .apply(x.view())
+                .unwrap()
+                .into_array()
+                .unwrap();
+            for ((y, x), x_flipped) in y
+                .outer_iter()
+                .zip(x_array.outer_iter())
+                .zip(flipped_array.outer_iter())
+            {
+                assert!(y == x || y == x_flipped);
+            }
+        }
+
+        test::<u8>(device);
+        test::<f32>(device);
+        assert!(RandomHorizontalFlip::new(1.5).is_err());
+    }
+
+    fn normalize(device: &Device) {
+        use approx::assert_relative_eq;
+        use autograph::dataset::transforms::Normalize;
+        use ndarray::Array4;
+
+        let mean = vec![0.5f32, 0.25, 0.75];
+        let std = vec![0.2f32, 0.5, 1.5];
+        let normalize = Normalize::new(mean.clone(), std.clone()).unwrap();
+        let x_array = Array4::from_shape_fn([2, 3, 4, 5], |(n, c, h, w)| {
+            (((n * 3 + c) * 4 + h) * 5 + w) as u8
+        });
+        let mut y_true = x_array.map(|x| *x as f32 / 255.);
+        for (c, mut y) in y_true.axis_iter_mut(Axis(1)).enumerate() {
+            y.map_inplace(|y| *y = (*y - mean[c]) / std[c]);
+        }
+        let x = Tensor::from(x_array.map(|x| *x as f32 / 255.))
+            .into_device(device.clone())
+            .unwrap();
+        let y = normalize.apply(x.view()).unwrap();
+        assert_eq!(y.device(), device.clone());
+        assert_relative_eq!(y.into_array().unwrap(), y_true, epsilon = 1e-6);
+        let x = Tensor::from(x_array).into_device(device.clone()).unwrap();
+        let y = normalize
+            .apply(x.scaled_cast::<f32>(1. / 255.).unwrap().view())
+            .unwrap();
+        assert_relative_eq!(y.into_array().unwrap(), y_true, epsilon = 1e-5);
+        let x = Tensor::<f32, _>::zeros(device.clone(), [1, 2, 1, 1]).unwrap();
+        assert!(normalize.apply(x.view()).is_err());
+        assert!(Normalize::new(vec![0.], vec![0.]).is_err());
+        assert!(Normalize::new(vec![0., 1.], vec![1.]).is_err());
+    }
+
+    fn random_crop(device: &Device) {
+        use autograph::dataset::transforms::{PaddingMode, RandomCrop};
+        use ndarray::{s, Array4};
+
+        fn pad(x: &Array4<f32>, padding: usize, reflect: bool) -> Array4<f32> {
+            let (n, c, h, w) = x.dim();
+            let index = |i: usize, len: usize| {
+                let i = i as isize - padding as isize;
+                let len = len as isize;
+                if (0..len).contains(&i) {
+                    Some(i as usize)
+                } else if reflect {
+                    let i = if i < 0 { -i } else { 2 * (len - 1) - i };
+                    Some(i as usize)
+                } else {
+                    None
+                }
+            };
+            Array4::from_shape_fn(
+                [n, c, h + 2 * padding, w + 2 * padding],
+                |(n, c, i, j)| match (index(i, h), index(j, w)) {
+                    (Some(i), Some(j)) => x[[n, c, i, j]],
+                    _ => 0.,
+                },
+            )
+        }
+
+        let x_array = Array4::from_shape_fn([2, 2, 4, 5], |(n, c, h, w)| {
+            (((n * 2 + c) * 4 + h) * 5 + w + 1) as f32
+        });
+        let x = Tensor::from(x_array.clone())
+            .into_device(device.clone())
+            .unwrap();
+        let y = RandomCrop::new([4, 5]).apply(x.view()).unwrap();
+        assert_eq!(y.device(), device.clone());
+        assert_eq!(y.into_array().unwrap(), x_array);
+        for (padding_mode, reflect) in [(PaddingMode::Zero, false), (PaddingMode::Reflect, true)] {
+            let y = RandomCrop::new([8, 9])
+                .padding(2)
+                .padding_mode(padding_mode)
+                .apply(x.view())
+                .unwrap()
+                .into_array()
+                .unwrap();
+            assert_eq!(y, pad(&x_array, 2, reflect), "{padding_mode:?}");
+            assert_eq!(y.slice(s![.., .., 2..6, 2..7]), x_array);
+            let y = RandomCrop::new([3, 4])
+                .padding(2)
+                .padding_mode(padding_mode)
+                .seed(0)
+                .apply(x.view())
+                .unwrap()
+                .into_array()
+                .unwrap();
+            assert_eq!(y.shape(), [2, 2, 3, 4]);
+            let padded = pad(&x_array, 2, reflect);
+            for (y, x) in y.outer_iter().zip(padded.outer_iter()) {
+                let found =
+                    (0..=5).any(|i| (0..=5).any(|j| x.slice(s![.., i..i + 3, j..j + 4]) == y));
+                assert!(found, "{padding_mode:?}");
+            }
+        }
+        assert!(RandomCrop::new([9, 5]).padding(2).apply(x.view()).is_err());
+        assert!(RandomCrop::new([4, 5])
+            .padding(4)
+            .padding_mode(PaddingMode::Reflect)
+            .apply(x.view())
+            .is_err());
+    }
+
+    fn data_loader(device: &Device) {
+        use autograph::{dataset::DataLoader, tensor::ArcTensor};
+
+        let dataset: Vec<_> = (0..10u32)
+            .map(|i| {
+                (
+                    ArcTensor::from(vec![i as f32, 2. * i as f32]),
+                    ArcTensor::from(ndarray::arr0(i)),
+                )
+            })
+            .collect();
+        let mut loader = DataLoader::new(&dataset, 4).unwrap().device(device.clone());
+        assert_eq!(loader.num_batches(), 3);
+        let batches: Vec<_> = loader.iter().map(Result::unwrap).collect();
+        assert_eq!(batches.len(), 3);
+        let batch_sizes: Vec<_> = batches.iter().map(|(x, _)| x.shape()[0]).collect();
+        assert_eq!(batch_sizes, [4, 4, 2]);
+        let mut targets = Vec::new();
+        for (x, t) in batches {
+            assert_eq!(x.device(), device.clone());
+            assert_eq!(t.device(), device.clone());
+            let x = x.into_array().unwrap();
+            let t = t.into_array().unwrap();
+            for (x, t) in x.outer_iter().zip(t.iter()) {
+                assert_eq!(x.to_vec(), [*t as f32, 2. * *t as f32]);
+            }
+            targets.extend(t);
+        }
+        assert_eq!(targets, (0..10).collect::<Vec<_>>());
+        let mut loader = DataLoader::new(&dataset, 4)
+            .unwrap()
+            .shuffle(true)
+            .drop_last(true)
+            .seed(0)
+            .device(device.clone());
+        assert_eq!(loader.num_batches(), 2);
+        for _ in 0..2 {
+            let mut targets = Vec::new();
+            for batch in loader.iter() {
+                let (_, t) = batch.unwrap();
+                assert_eq!(t.shape(), [4]);
+                targets.extend(t.into_array().unwrap());
+            }
+            assert_eq!(targets.len(), 8);
+            targets.sort();
+            targets.dedup();
+            assert_eq!(targets.len(), 8);
+        }
+        assert!(DataLoader::new(&dataset, 0).is_err());
+    }
+
+    fn pad_sequence_test(device: &Device) {
+        use autograph::dataset::{pad_sequence, sequence_mask};
+
+        let sequences: Vec<_> = [2, 0, 4, 1]
+            .into_iter()
+            .map(|len| {
+                let x = Array::from_shape_fn([len, 3], |(i, j)| (i * 3 + j + 1) as f32);
+                Tensor::from(x).into_device(device.clone()).unwrap()
+            })
+            .collect();
+        let (x, lengths) = pad_sequence(&sequences, -1.).unwrap();
+        assert_eq!(x.shape(), [4, 4, 3]);
+        assert_eq!(lengths, [2, 0, 4, 1]);
+        let x = x.into_array().unwrap();
+        for ((b, i, j), x) in x.indexed_iter() {
+            if i < lengths[b] {
+                assert_eq!(*x, (i * 3 + j + 1) as f32);
+            } else {
+                assert_eq!(*x, -1.);
+            }
+        }
+        let mask = sequence_mask(&lengths, device.clone())
+            .unwrap()
+            .into_array()
+            .unwrap();
+        assert_eq!(mask.shape(), [4, 4]);
+        for ((b, i), m) in mask.indexed_iter() {
+            assert_eq!(*m, (i < lengths[b]) as u8);
+        }
+        let y = Tensor::from(Array::<f32, _>::zeros([2, 2]))
+            .into_device(device.clone())
+            .unwrap();
+        assert!(pad_sequence(&[sequences[0].clone(), y], 0.).is_err());
+        assert!(pad_sequence::<f32, _, ndarray::Ix2>(&Vec::<Tensor<f32, _>>::new(), 0.).is_err());
+    }
+
+    fn dataset_subset(_device: &Device) {
+        let dataset: Vec<u32> = (10..20).collect();
+        let indices = vec![3, 0, 9, 3];
+        let subset = (&dataset).subset(indices.clone()).unwrap();
+        assert_eq!(subset.len(), indices.len());
+        for (i, index) in indices.iter().enumerate() {
+            assert_eq!(subset.get(i).unwrap(), dataset[*index]);
+        }
+        assert!(subset.get(indices.len()).is_err());
+        let subset2 = subset.clone().subset(vec![2, 1]).unwrap();
+        assert_eq!(subset2.get(0).unwrap(), 19);
+        assert_eq!(subset2.get(1).unwrap(), 10);
+        assert!(subset.subset(vec![4]).is_err());
+        assert!(dataset.subset(vec![10]).is_err());
+    }
+
+    fn dataset_concat(_device: &Device) {
+        let first: Vec<u32> = (0..3).collect();
+        let second: Vec<u32> = (10..15).collect();
+        let dataset = (&first).concat(second.as_slice());
+        assert_eq!(dataset.len(), 8);
+        let items: Vec<_> = (0..dataset.len())
+            .map(|i| dataset.get(i).unwrap())
+            .collect();
+        assert_eq!(items, [0, 1, 2, 10, 11, 12, 13, 14]);
+        assert!(dataset.get(8).is_err());
+    }
+
+    fn dataset_random_split(_device: &Device) {
+        let dataset: Vec<u32> = (0..11).collect();
+        let splits = (&dataset).random_split(&[0.6, 0.2, 0.2], 0).unwrap();
+        let lens: Vec<_> = splits.iter().map(|x| x.len()).collect();
+        assert_eq!(lens, [7, 2, 2]);
+        let mut items: Vec<u32> = splits
+            .iter()
+            .flat_map(|x| (0..x.len()).map(|i| x.get(i).unwrap()))
+            .collect();
+        items.sort();
+        assert_eq!(items, dataset);
+        let splits2 = (&dataset).random_split(&[0.6, 0.2, 0.2], 0).unwrap();
+        for (split, split2) in splits.iter().zip(splits2.iter()) {
+            assert_eq!(split.indices(), split2.indices());
+        }
+        assert!((&dataset).random_split(&[0.6, 0.2], 0).is_err());
+        assert!((&dataset).random_split(&[1.2, -0.2], 0).is_err());
+    }
+
+    fn kfold_split_test(_device: &Device) {
+        let n_samples = 11;
+        let k = 3;
+        let folds = kfold_split(n_samples, k, 0).unwrap();
+        assert_eq!(folds.len(), k);
+        let mut all_val_indices = Vec::new();
+        for (i, (train_indices, val_indices)) in folds.iter().enumerate() {
+            assert_eq!(val_indices.len(), if i < 2 { 4 } else { 3 });
+            assert_eq!(train_indices.len() + val_indices.len(), n_samples);
+            let mut indices: Vec<_> = train_indices.iter().chain(val_indices).copied().collect();
+            indices.sort();
+            assert_eq!(indices, (0..n_samples).collect::<Vec<_>>());
+            all_val_indices.extend_from_slice(val_indices);
+        }
+        all_val_indices.sort();
+        assert_eq!(all_val_indices, (0..n_samples).collect::<Vec<_>>());
+        assert_eq!(folds, kfold_split(n_samples, k, 0).unwrap());
+        assert_ne!(folds, kfold_split(n_samples, k, 1).unwrap());
+        assert!(kfold_split(n_samples, 1, 0).is_err());
+        assert!(kfold_split(n_samples, 12, 0).is_err());
+    }
+
+    fn weighted_random_sampler_replacement(_device: &Device) {
+        let weights = vec![1., 2., 0., 3., 4.];
+        let total: f32 = weights.iter().sum();
+        let num_samples = 100_000;
+        let mut sampler = WeightedRandomSampler::new(weights.clone(), num_samples, true)
+            .unwrap()
+            .seed(0);
+        let mut counts = vec![0usize; weights.len()];
+        let mut len = 0;
+        for batch in sampler.batches(64) {
+            assert!(batch.len() <= 64);
+            len += batch.len();
+            for index in batch {
+                counts[index] += 1;
+            }
+        }
+        assert_eq!(len, num_samples);
+        for (count, weight) in counts.iter().zip(weights.iter()) {
+            let frequency = *count as f32 / num_samples as f32;
+            assert!(
+                (frequency - weight / total).abs() < 0.01,
+                "{counts:?} {weights:?}"
+            );
+        }
+        let mut sampler2 = WeightedRandomSampler::new(weights, num_samples, true)
+            .unwrap()
+            .seed(0);
+        let mut sampler = sampler.seed(0);
+        assert_eq!(sampler.sample(), sampler2.sample());
+    }
+
+    fn weighted_random_sampler_no_replacement(_device: &Device) {
+        let weights = vec![1., 0., 2., 3.];
+        let mut sampler = WeightedRandomSampler::new(weights.clone(), 3, false)
+            .unwrap()
+            .seed(0);
+        let mut first_counts = vec![0usize; weights.len()];
+        let iterations = 10_000;
+        for _ in 0..iterations {
+            let mut indices = sampler.sample();
+            first_counts[indices[0]] += 1;
+            indices.sort();
+            assert_eq!(indices, [0, 2, 3]);
+        }
+        let total: f32 = weights.iter().sum();
+        for (count, weight) in first_counts.iter().zip(weights.iter()) {
+            let frequency = *count as f32 / iterations as f32;
+            assert!(
+                (frequency - weight / total).abs() < 0.02,
+                "{first_counts:?} {weights:?}"
+            );
+        }
+        assert!(WeightedRandomSampler::new(weights, 4, false).is_err());
+        assert!(WeightedRandomSampler::new(vec![0., 0.], 1, true).is_err());
+        assert!(WeightedRandomSampler::new(vec![1., -1.], 1, true).is_err());
+    }
+}
+
+#[cfg(all(not(target_family = "wasm"), feature = "learn"))]
+mod learn {
+    use super::*;
+    use approx::assert_relative_eq;
+    use autograph::learn::criterion::CrossEntropyLoss;
+
+    pub fn learn_tests(device: &Device) -> Vec<Trial> {
+        let mut tests = Vec::new();
+        tests.extend(criterion::criterion_tests(device));
+        tests.extend(cluster::cluster_tests(device));
+        tests.extend(decomposition::decomposition_tests(device));
+        tests.extend(linear_model::linear_model_tests(device));
+        #[cfg(feature = "neural-network")]
+        {
+            tests.extend(neural_network::neural_network_tests(device));
+        }
+        tests
+    }
+
+    mod cluster {
+        use super::*;
+        use autograph::learn::cluster::KMeans;
+        use ndarray::{Array2, ArrayView1};
+
+        pub fn cluster_tests(device: &Device) -> Vec<Trial> {
+            vec![device_test(device, "kmeans", kmeans)]
+        }
+
+        fn kmeans(device: &Device) {
+            let centers = [[0f32, 0.], [10., 10.], [-10., 10.]];
+            let offsets = [[0.5f32, 0.], [-0.5, 0.], [0., 0.5], [0., -0.5]];
+            let x_array =
+                Array2::from_shape_fn([12, 2], |(i, j)| centers[i % 3][j] + offsets[i / 3][j]);
+            let x = Tensor::from(x_array.clone())
+                .into_device(device.clone())
+                .unwrap();
+            let mut kmeans = KMeans::new(3).seed(0);
+            assert!(kmeans.predict(x.view()).is_err());
+            kmeans.fit(x.view()).unwrap();
+            assert!(kmeans.iterations() <= 300);
+            let labels = kmeans
+                .predict(x.view())
+                .unwrap()
+                .into_device(Device::host())
+                .unwrap()
+                .into_array()
+                .unwrap();
+            for i in 0..12 {
+                assert_eq!(labels[i], labels[i % 3]);
+            }
+            assert_ne!(labels[0], labels[1]);
+            assert_ne!(labels[0], labels[2]);
+            assert_ne!(labels[1], labels[2]);
+            let centroids = kmeans.centroids().unwrap().as_array().unwrap().to_owned();
+            for (i, center) in centers.iter().enumerate() {
+                let centroid = centroids.row(labels[i] as usize);
+                assert_relative_eq!(centroid, ArrayView1::from(&center[..]), epsilon = 1e-4);
+            }
+            let mut kmeans2 = KMeans::new(3).seed(0);
+            kmeans2.fit(x.view()).unwrap();
+            assert_eq!(
+                kmeans2.centroids().unwrap().as_array().unwrap(),
+                centroids.view()
+            );
+            assert!(KMeans::new(13).fit(x.view()).is_err());
+            assert!(KMeans::new(0).fit(x.view()).is_err());
+        }
+    }
+
+    mod linear_model {
+        use super::*;
+        use autograph::learn::linear_model::{LinearRegression, LogisticRegression};
+        use ndarray::Array2;
+
+        pub fn linear_model_tests(device: &Device) -> Vec<Trial> {
+            vec![
+                device_test(device, "linear_regression", linear_regression),
+                device_test(device, "logistic_regression", logistic_regression),
+            ]
+        }
+
+        fn linear_regression(device: &Device) {
+            let x_array = Array2::from_shape_fn([8, 2], |(i, j)| ((i * 3 + j * 5) % 7) as f32);
+            let y_array = x_array.map_axis(Axis(1), |x| 2. * x[0] - 3. * x[1] + 1.);
+            let x = Tensor::from(x_array.clone())
+                .into_device(device.clone())
+                .unwrap();
+            let y = Tensor::from(y_array.clone())
+                .into_device(device.clone())
+                .unwrap();
+            let mut model = LinearRegression::new();
+            assert!(model.predict(x.view()).is_err());
+            model.fit(x.view(), y.view()).unwrap();
+            assert_relative_eq!(
+                model.weights().unwrap().as_array().unwrap(),
+                Array1::from_vec(vec![2., -3.]),
+                epsilon = 1e-4
+            );
+            assert_relative_eq!(model.intercept().unwrap(), 1., epsilon = 1e-4);
+            let y_pred = model
+                .predict(x.view())
+                .unwrap()
+                .into_device(Device::host())
+                .unwrap()
+                .into_array()
+                .unwrap();
+            assert_relative_eq!(y_pred, y_array, epsilon = 1e-3);
+            let mut model = LinearRegression::new().fit_intercept(false);
+            model.fit(x.view(), y.view()).unwrap();
+            assert_eq!(model.intercept(), Some(0.));
+            let y_invalid = Tensor::from(vec![0f32; 3])
+                .into_device(device.clone())
+                .unwrap();
+            assert!(model.fit(x.view(), y_invalid.view()).is_err());
+        }
+
+        fn logistic_regression(device: &Device) {
+            let x_vec = vec![-3f32, -2., -1., -0.5, 0., 0.5, 1., 2., 3.];
+            let y_vec = vec![0u8, 0, 1, 0, 0, 1, 0, 1, 1];
+            let x_array = Array2::from_shape_vec([9, 1], x_vec.clone()).unwrap();
+            let x = Tensor::from(x_array).into_device(device.clone()).unwrap();
+            let y = Tensor::from(y_vec.clone())
+                .into_device(device.clone())
+                .unwrap();
+            let mut model = LogisticRegression::new();
+            model.fit(x.view(), y.view()).unwrap();
+            assert!(model.iterations() < 100);
+            let p = model
+                .predict_proba(x.view())
+                .unwrap()
+                .into_device(Device::host())
+                .unwrap()
+                .into_array()
+                .unwrap();
+            // the gradient of the loss is 0 at the optimum
+            let residuals: Vec<f32> = p
+                .iter()
+                .zip(y_vec.iter())
+                .map(|(p, y)| p - *y as f32)
+                .collect();
+            let grad_intercept: f32 = residuals.iter().sum();
+            let grad_weight: f32 = residuals.iter().zip(x_vec.iter()).map(|(r, x)| r * x).sum();
+            assert_relative_eq!(grad_intercept, 0., epsilon = 1e-4);
+            assert_relative_eq!(grad_weight, 0., epsilon = 1e-4);
+            assert!(model.weights().unwrap().as_array().unwrap()[0] > 0.);
+            let classes = model
+                .predict(x.view())
+                .unwrap()
+                .into_device(Device::host())
+                .unwrap()
+                .into_array()
+                .unwrap();
+            for (class, p) in classes.iter().zip(p.iter()) {
+                assert_eq!(*class, (*p > 0.5) as u8);
+            }
+            let y_invalid = Tensor::from(vec![0u8, 1, 2, 0, 0, 1, 0, 1, 1])
+                .into_device(device.clone())
+                .unwrap();
+            assert!(model.fit(x.view(), y_invalid.view()).is_err());
+        }
+    }
+
+    mod decomposition {
+        use super::*;
+        use autograph::learn::decomposition::Pca;
+        use ndarray::{Array2, Axis};
+
+        pub fn decomposition_tests(device: &Device) -> Vec<Trial> {
+            vec![device_test(device, "pca", pca)]
+        }
+
+        fn pca(device: &Device) {
+            let d1 = Array1::from_vec(vec![1., 1., 0., 0.]) / 2f32.sqrt();
+            let d2 = Array1::from_vec(vec![0., 0., 1., -1.]) / 2f32.sqrt();
+            let mean = Array1::from_vec(vec![1., -2., 3., 0.5]);
+            let samples = 20;
+            // uncorrelated scores with mean 0
+            let s1: Array1<f32> = (0..samples).map(|i| 10. * (i as f32 - 9.5)).collect();
+            let s2: Array1<f32> = s1.mapv(|x| x * x / 100. - 33.25);
+            let x_array = Array2::from_shape_fn([samples, 4], |(i, j)| {
+                mean[j] + s1[i] * d1[j] + s2[i] * d2[j]
+            });
+            let x = Tensor::from(x_array.clone())
+                .into_device(device.clone())
+                .unwrap();
+            let mut pca = Pca::new(2);
+            assert!(pca.transform(x.view()).is_err());
+            pca.fit(x.view()).unwrap();
+            assert_relative_eq!(
+                pca.mean().unwrap().as_array().unwrap(),
+                mean.view(),
+                epsilon = 1e-4
+            );
+            let explained_variance = pca.explained_variance().unwrap();
+            let explained_variance = explained_variance.as_array().unwrap();
+            let variance = |s: &Array1<f32>| s.dot(s) / (samples - 1) as f32;
+            assert_relative_eq!(explained_variance[0], variance(&s1), max_relative = 1e-3);
+            assert_relative_eq!(explained_variance[1], variance(&s2), max_relative = 1e-3);
+            let components = pca.components().unwrap().as_array().unwrap().to_owned();
+            assert_relative_eq!(components.row(0).dot(&d1).abs(), 1., epsilon = 1e-4);
+            assert_relative_eq!(components.row(1).dot(&d2).abs(), 1., epsilon = 1e-4);
+            let y = pca
+                .transform(x.view())
+                .unwrap()
+                .into_device(Device::host())
+                .unwrap()
+                .into_array()
+                .unwrap();
+            assert_eq!(y.dim(), (samples, 2));
+            assert_relative_eq!(
+                y.column(0).mapv(f32::abs),
+                s1.mapv(f32::abs),
+                epsilon = 1e-2
+            );
+            let x_reconstructed = y.dot(&components) + &mean;
+            assert_relative_eq!(x_reconstructed, x_array, epsilon = 1e-2);
+            assert_relative_eq!(
+                y.mean_axis(Axis(0)).unwrap(),
+                Array1::zeros(2),
+                epsilon = 1e-3
+            );
+            assert!(Pca::new(5).fit(x.view()).is_err());
+        }
+    }
+
+    mod criterion {
+        use super::*;
+        use autograph::learn::criterion::{Accuracy, AccuracyCounter, Evaluator};
+        use num_traits::{Float, Unsigned};
+
+        pub fn criterion_tests(device: &Device) -> Vec<Trial> {
+            let mut tests = Vec::new();
+            let features = device
+                .info()
+                .map(|info| info.features())
+                .unwrap_or(Features::empty());
+            macro_for!($X in [bf16, f32] {
+                macro_for!($T in [u8, u16, u32] {
+                    let ignore = device.is_device()
+                        && (
+                            !features.contains(features_for_scalar($X::SCALAR_TYPE))
+                            || !features.contains(features_for_scalar($T::SCALAR_TYPE))
+                        );
+                    tests.push(device_test(device, &format!("accuracy_{}_{}", $X::SCALAR_TYPE.name(), $T::SCALAR_TYPE.name()), |device| {
+                        for (batch_size, classes) in [
+                            (1, 8),
+                            (31, 16),
                             (1000, 100),
                         ] {
-                            cross_entropy_loss_backward::<$X, $T>(device, batch_size, classes);
+                            accuracy::<$X, $T>(device, batch_size, classes);
                         }
                     }).with_ignored_flag(ignore));
                 });
             });
-            {
-                let batch_size_list = [2, 3, 13];
-                let filter_list = [[1, 1], [3, 3], [5, 5], [7, 7], [1, 3], [3, 5], [1, 7]];
-                let padding_list = [[0, 1], [1, 1]];
-                let stride_list = [[1, 2], [2, 2]];
-                let dilation_list = stride_list;
-                let output_size_list = [1, 2, 3, 5];
-                let filter_conv2_options_list = filter_list
-                    .iter()
-                    .copied()
-                    .flat_map(move |filter| {
-                        std::iter::once(Conv2Options::default())
-                            .chain(padding_list.into_iter().map(move |padding| Conv2Options {
-                                padding: padding.into_dimension(),
-                                ..Conv2Options::default()
-                            }))
-                            .chain(stride_list.into_iter().map(move |stride| Conv2Options {
-                                stride: stride.into_dimension(),
-                                ..Conv2Options::default()
-                            }))
-                            .chain(dilation_list.into_iter().map(move |dilation| Conv2Options {
-                                dilation: dilation.into_dimension(),
-                                ..Conv2Options::default()
-                            }))
-                            .map(move |options| (filter, options))
-                    })
-                    .collect::<Vec<_>>();
-                let batch_size_inputs_outputs_list = Arc::new(
-                    std::iter::once((1, 1, 1))
-                        .chain(
-                            batch_size_list
-                                .into_iter()
-                                .flat_map(move |x| [(x, 1, 1), (1, x, 1), (1, 1, x)]),
-                        )
-                        .collect::<Vec<_>>(),
-                );
-                let output_shapes = output_size_list
-                    .into_iter()
-                    .flat_map(move |oh| output_size_list.into_iter().map(move |ow| (oh, ow)))
-                    .collect::<Vec<_>>();
-                macro_for!($T in [bf16, f32] {
+            macro_for!($X in [bf16, f32] {
+                macro_for!($T in [u8, u16, u32] {
                     let ignore = device.is_device()
-                    && !features.contains(features_for_scalar($T::SCALAR_TYPE));
+                        && (
+                            !features.contains(features_for_scalar($X::SCALAR_TYPE))
+                            || !features.contains(features_for_scalar($T::SCALAR_TYPE))
+                        );
+                    tests.push(device_test(device, &format!("cross_entropy_loss_{}_{}", $X::SCALAR_TYPE.name(), $T::SCALAR_TYPE.name()), |device| {
+                        for (batch_size, classes) in [
+                            (1, 8),
+                            (31, 16),
+                            (1000, 100),
+                        ] {
+                            cross_entropy_loss::<$X, $T>(device, batch_size, classes);
+                        }
+                    }).with_ignored_flag(ignore));
+                });
+            });
+            macro_for!($X in [bf16, f32] {
+                let ignore = device.is_device() && !features.contains(features_for_scalar($X::SCALAR_TYPE));
+                tests.push(device_test(device, &format!("mse_loss_{}", $X::SCALAR_TYPE.name()), |device| {
+                    for shape in [[1, 8], [31, 16], [1000, 100]] {
+                        mse_loss::<$X>(device, shape);
+                    }
+                }).with_ignored_flag(ignore));
+            });
+            macro_for!($X in [bf16, f32] {
+                let ignore = device.is_device() && !features.contains(features_for_scalar($X::SCALAR_TYPE));
+                tests.push(device_test(device, &format!("huber_loss_{}", $X::SCALAR_TYPE.name()), |device| {
+                    for shape in [[1, 8], [31, 16], [1000, 100]] {
+                        huber_loss::<$X>(device, shape);
+                    }
+                }).with_ignored_flag(ignore));
+            });
+            macro_for!($X in [bf16, f32] {
+                let ignore = device.is_device() && !features.contains(features_for_scalar($X::SCALAR_TYPE));
+                tests.push(device_test(device, &format!("l1_loss_{}", $X::SCALAR_TYPE.name()), |device| {
+                    for shape in [[1, 8], [31, 16], [1000, 100]] {
+                        l1_loss::<$X>(device, shape);
+                    }
+                }).with_ignored_flag(ignore));
+            });
+            macro_for!($X in [bf16, f32] {
+                let ignore = device.is_device() && !features.contains(features_for_scalar($X::SCALAR_TYPE));
+                tests.push(device_test(device, &format!("bce_loss_{}", $X::SCALAR_TYPE.name()), |device| {
+                    for shape in [[1, 8], [31, 16], [1000, 100]] {
+                        bce_loss::<$X>(device, shape);
+                    }
+                }).with_ignored_flag(ignore));
+            });
+            tests.push(device_test(device, "confusion_matrix", confusion_matrix));
+            tests.push(device_test(
+                device,
+                "evaluator_streaming",
+                evaluator_streaming,
+            ));
+            tests
+        }
+
+        fn accuracy<X: Scalar + Float, T: Scalar + Unsigned>(
+            device: &Device,
+            batch_size: usize,
+            classes: usize,
+        ) {
+            let x_vec: Vec<X> = (0..classes)
+                .map(|x| X::from_usize(x).unwrap())
+                .cycle()
+                .skip(classes / 2 + 1)
+                .take(batch_size * classes)
+                .collect();
+            let t_vec: Vec<T> = (0..classes)
+                .cycle()
+                .map(|t| T::from_usize(t).unwrap())
+                .take(batch_size)
+                .collect();
+            let x_array = Array::from(x_vec)
+                .into_shape([batch_size, classes])
+                .unwrap();
+            let t_array = Array::from(t_vec);
+            let x_host = Tensor::from(x_array);
+            let t_host = Tensor::from(t_array);
+            let x_device = x_host.to_device(device.clone()).unwrap();
+            let t_device = t_host.to_device(device.clone()).unwrap();
+            let y_host = x_host.accuracy(t_host).unwrap();
+            let y_device = x_device.accuracy(t_device.view()).unwrap();
+            assert_eq!(y_host, y_device);
+            let mut counter = AccuracyCounter::new(device.clone()).unwrap();
+            for _ in 0..2 {
+                x_device
+                    .accuracy_with(t_device.view(), &mut counter)
+                    .unwrap();
+            }
+            assert_eq!(counter.correct().unwrap(), 2 * y_host);
+            counter.reset().unwrap();
+            assert_eq!(counter.correct().unwrap(), 0);
+        }
+
+        fn cross_entropy_loss<X: Scalar + Float, T: Scalar + Unsigned>(
+            device: &Device,
+            batch_size: usize,
+            classes: usize,
+        ) {
+            let x_vec: Vec<X> = (0..10u8)
+                .map(|x| X::from_u8(x).unwrap())
+                .cycle()
+                .take(batch_size * classes)
+                .collect();
+            let t_vec: Vec<T> = (0..classes)
+                .cycle()
+                .map(|t| T::from_usize(t).unwrap())
+                .take(batch_size)
+                .collect();
+            let x_array = Array::from(x_vec)
+                .into_shape([batch_size, classes])
+                .unwrap();
+            let t_array = Array::from(t_vec);
+            let x_host = Tensor::from(x_array);
+            let t_host = Tensor::from(t_array);
+            let x_device = x_host.to_device(device.clone()).unwrap();
+            let t_device = t_host.to_device(device.clone()).unwrap();
+            let y_host = x_host.cross_entropy_loss(t_host).unwrap();
+            let y_device = x_device.cross_entropy_loss(t_device).unwrap();
+            let epsilon = if X::SCALAR_TYPE == ScalarType::BF16 {
+                batch_size as f32 * 0.001
+            } else {
+                batch_size as f32 * f32::EPSILON
+            };
+            assert_relative_eq!(y_host, y_device, epsilon = epsilon, max_relative = epsilon);
+        }
+
+        fn mse_loss<X: Scalar + Float>(device: &Device, shape: [usize; 2]) {
+            use autograph::learn::criterion::MeanSquaredError;
+
+            let x_array =
+                Array::from_shape_fn(shape, |(i, j)| X::from_usize((i + 2 * j) % 10).unwrap());
+            let t_array =
+                Array::from_shape_fn(shape, |(i, j)| X::from_usize((3 * i + j) % 7).unwrap());
+            let y_true = x_array
+                .iter()
+                .zip(t_array.iter())
+                .map(|(x, t)| (x.to_f32().unwrap() - t.to_f32().unwrap()).powi(2))
+                .sum::<f32>()
+                / x_array.len() as f32;
+            let x_host = Tensor::from(x_array);
+            let t_host = Tensor::from(t_array);
+            let x_device = x_host.to_device(device.clone()).unwrap();
+            let t_device = t_host.to_device(device.clone()).unwrap();
+            let y_host = x_host.mse_loss(t_host).unwrap();
+            let y_device = x_device.mse_loss(t_device.view()).unwrap();
+            assert_relative_eq!(y_host, y_true, epsilon = 1e-4, max_relative = 1e-4);
+            assert_relative_eq!(y_host, y_device, epsilon = 1e-4, max_relative = 1e-4);
+        }
+
+        fn l1_loss<X: Scalar + Float>(device: &Device, shape: [usize; 2]) {
+            use autograph::learn::criterion::{MeanAbsoluteError, Reduction};
+
+            let x_array =
+                Array::from_shape_fn(shape, |(i, j)| X::from_usize((i + 2 * j) % 10).unwrap());
+            let t_array =
+                Array::from_shape_fn(shape, |(i, j)| X::from_usize((3 * i + j) % 7).unwrap());
+            let y_true = x_array
+                .iter()
+                .zip(t_array.iter())
+                .map(|(x, t)| (x.to_f32().unwrap() - t.to_f32().unwrap()).abs())
+                .sum::<f32>()
+                / x_array.len() as f32;
+            let x_host = Tensor::from(x_array);
+            let t_host = Tensor::from(t_array);
+            let x_device = x_host.to_device(device.clone()).unwrap();
+            let t_device = t_host.to_device(device.clone()).unwrap();
+            let y_host = x_host.l1_loss(t_host.view()).unwrap();
+            let y_device = x_device.l1_loss(t_device.view()).unwrap();
+            assert_relative_eq!(y_host, y_true, epsilon = 1e-4, max_relative = 1e-4);
+            assert_relative_eq!(y_host, y_device, epsilon = 1e-4, max_relative = 1e-4);
+            let y_none = x_device
+                .l1_loss_with(t_device.view(), Reduction::None)
+                .unwrap()
+                .into_device(Device::host())
+                .unwrap()
+                .into_array()
+                .unwrap();
+            assert_eq!(y_none.shape(), shape.as_slice());
+            let y_sum = x_device
+                .l1_loss_with(t_device.view(), Reduction::Sum)
+                .unwrap()
+                .into_device(Device::host())
+                .unwrap()
+                .into_array()
+                .unwrap();
+            assert_relative_eq!(y_sum.sum(), y_none.sum(), epsilon = 1e-4, max_relative = 1e-4);
+            assert_relative_eq!(
+                y_host,
+                x_host
+                    .l1_loss_with(t_host, Reduction::Mean)
+                    .unwrap()
+                    .into_array()
+                    .unwrap()
+                    .into_iter()
+                    .next()
+                    .unwrap()
+            );
+        }
+
+        fn huber_loss<X: Scalar + Float>(device: &Device, shape: [usize; 2]) {
+            use autograph::learn::criterion::{HuberLoss, Reduction};
+
+            let delta = 2.;
+            let x_array =
+                Array::from_shape_fn(shape, |(i, j)| X::from_usize((i + 2 * j) % 10).unwrap());
+            let t_array =
+                Array::from_shape_fn(shape, |(i, j)| X::from_usize((3 * i + j) % 7).unwrap());
+            let y_true = x_array
+                .iter()
+                .zip(t_array.iter())
+                .map(|(x, t)| {
+                    let d = (x.to_f32().unwrap() - t.to_f32().unwrap()).abs();
+                    if d <= delta {
+                        0.5 * d * d
+                    } else {
+                        delta * (d - 0.5 * delta)
+                    }
+                })
+                .sum::<f32>()
+                / x_array.len() as f32;
+            let x_host = Tensor::from(x_array);
+            let t_host = Tensor::from(t_array);
+            let x_device = x_host.to_device(device.clone()).unwrap();
+            let t_device = t_host.to_device(device.clone()).unwrap();
+            let y_host = x_host.huber_loss(t_host.view(), delta).unwrap();
+            let y_device = x_device.huber_loss(t_device.view(), delta).unwrap();
+            assert_relative_eq!(y_host, y_true, epsilon = 1e-4, max_relative = 1e-4);
+            assert_relative_eq!(y_host, y_device, epsilon = 1e-4, max_relative = 1e-4);
+            let y_sum = x_device
+                .huber_loss_with(t_device.view(), delta, Reduction::Sum)
+                .unwrap()
+                .into_device(Device::host())
+                .unwrap()
+                .into_array()
+                .unwrap()
+                .into_iter()
+                .next()
+                .unwrap();
+            let n = shape[0] * shape[1];
+            assert_relative_eq!(y_sum, y_true * n as f32, epsilon = 1e-3, max_relative = 1e-3);
+            assert!(x_host.huber_loss(t_host.view(), 0.).is_err());
+            assert!(x_host
+                .huber_loss_with(t_host.view(), 0., Reduction::None)
+                .is_err());
+        }
+
+        fn bce_loss<X: Scalar + Float>(device: &Device, shape: [usize; 2]) {
+            use autograph::learn::criterion::{BinaryCrossEntropyLoss, Reduction};
+
+            let x_array = Array::from_shape_fn(shape, |(i, j)| {
+                X::from_f32(((i + 2 * j) % 9) as f32 - 4.).unwrap()
+            });
+            let t_array = Array::from_shape_fn(shape, |(i, j)| {
+                X::from_f32(((3 * i + j) % 5) as f32 / 4.).unwrap()
+            });
+            let y_true = x_array
+                .iter()
+                .zip(t_array.iter())
+                .map(|(x, t)| {
+                    let (x, t) = (x.to_f32().unwrap(), t.to_f32().unwrap());
+                    let p = 1. / (1. + (-x).exp());
+                    -(t * p.ln() + (1. - t) * (1. - p).ln())
+                })
+                .sum::<f32>()
+                / x_array.len() as f32;
+            let x_host = Tensor::from(x_array);
+            let t_host = Tensor::from(t_array);
+            let x_device = x_host.to_device(device.clone()).unwrap();
+            let t_device = t_host.to_device(device.clone()).unwrap();
+            let y_host = x_host.bce_loss(t_host.view()).unwrap();
+            let y_device = x_device.bce_loss(t_device.view()).unwrap();
+            assert_relative_eq!(y_host, y_true, epsilon = 1e-4, max_relative = 1e-4);
+            assert_relative_eq!(y_host, y_device, epsilon = 1e-4, max_relative = 1e-4);
+            let y_none = x_device
+                .bce_loss_with(t_device.view(), Reduction::None)
+                .unwrap();
+            assert_eq!(y_none.shape(), shape.as_slice());
+            let t_invalid = t_host.map(|t| *t + X::one() + X::one()).unwrap();
+            assert!(x_host.bce_loss(t_invalid.view()).is_err());
+            let t_invalid = t_invalid.to_device(device.clone()).unwrap();
+            assert!(x_device
+                .bce_loss_with(t_invalid.view(), Reduction::Sum)
+                .is_err());
+        }
+
+        fn confusion_matrix(device: &Device) {
+            use autograph::learn::criterion::confusion_matrix;
+            use ndarray::Array2;
+
+            let x = Tensor::from(
+                Array2::from_shape_vec(
+                    [5, 3],
+                    vec![
+                        0.9f32, 0.1, 0.0, // 0
+                        0.2, 0.7, 0.1, // 1
+                        0.3, 0.3, 0.4, // 2
+                        0.5, 0.5, 0.0, // 0
+                        0.0, 0.1, 0.2, // 2
+                    ],
+                )
+                .unwrap(),
+            )
+            .into_device(device.clone())
+            .unwrap()
+            .into_scalar_tensor();
+            let y = Tensor::from(vec![0u8, 1, 2, 0, 2])
+                .into_device(device.clone())
+                .unwrap()
+                .into_scalar_tensor();
+            let t = Tensor::from(vec![0u8, 1, 1, 2, 2])
+                .into_device(device.clone())
+                .unwrap()
+                .into_scalar_tensor();
+            let expected =
+                Array2::from_shape_vec([3, 3], vec![1u32, 0, 0, 0, 1, 1, 1, 0, 1]).unwrap();
+            let from_logits = confusion_matrix(&x, &t, 3).unwrap();
+            let from_classes = confusion_matrix(&y, &t, 3).unwrap();
+            assert_eq!(from_logits.device(), device.clone());
+            assert_eq!(from_logits.into_array().unwrap(), expected);
+            assert_eq!(from_classes.into_array().unwrap(), expected);
+            assert!(confusion_matrix(&x, &t, 4).is_err());
+            assert!(confusion_matrix(&y, &t, 2).is_err());
+            let t_short = Tensor::from(vec![0u8, 1])
+                .into_device(device.clone())
+                .unwrap()
+                .into_scalar_tensor();
+            assert!(confusion_matrix(&y, &t_short, 3).is_err());
+        }
+
+        fn evaluator_streaming(device: &Device) {
+            use autograph::tensor::{ScalarTensor1, ScalarTensor2};
+            use ndarray::{s, Array2};
+
+            let (batch_size, classes) = (20, 4);
+            let x_array =
+                Array::from_shape_fn([batch_size, classes], |(i, j)| ((i * 5 + j * 3) % 7) as f32);
+            let t_array = Array::from_shape_fn(batch_size, |i| (i * 3 % classes) as u8);
+            let to_device = |i: usize, j: usize| {
+                let x = ScalarTensor2::from(
+                    Tensor::from(x_array.slice(s![i..j, ..]).to_owned())
+                        .into_device(device.clone())
+                        .unwrap(),
+                );
+                let t = ScalarTensor1::from(
+                    Tensor::from(t_array.slice(s![i..j]).to_owned())
+                        .into_device(device.clone())
+                        .unwrap(),
+                );
+                (x, t)
+            };
+            let mut evaluator = Evaluator::new(device.clone(), classes).unwrap();
+            let (x, t) = to_device(0, batch_size);
+            evaluator.add(&x, &t).unwrap();
+            let metrics = evaluator.finish().unwrap();
+            let mut confusion_matrix = Array2::<u32>::zeros([classes, classes]);
+            for (x, t) in x_array.outer_iter().zip(t_array.iter()) {
+                let max = x.fold(f32::NEG_INFINITY, |a, b| a.max(*b));
+                let y = x.iter().position(|x| *x == max).unwrap();
+                confusion_matrix[(*t as usize, y)] += 1;
+            }
+            assert_eq!(metrics.samples, batch_size as u64);
+            assert_eq!(metrics.confusion_matrix.as_array().unwrap(), confusion_matrix);
+            let correct = confusion_matrix.diag().sum();
+            assert_eq!(metrics.accuracy, correct as f32 / batch_size as f32);
+            evaluator.reset().unwrap();
+            assert_eq!(evaluator.samples(), 0);
+            for (i, j) in [(0, 7), (7, 14), (14, 20)] {
+                let (x, t) = to_device(i, j);
+                evaluator.add(&x, &t).unwrap();
+            }
+            let streaming_metrics = evaluator.finish().unwrap();
+            assert_relative_eq!(streaming_metrics.loss, metrics.loss, epsilon = 1e-5);
+            assert_eq!(streaming_metrics.samples, metrics.samples);
+            assert_eq!(streaming_metrics.accuracy, metrics.accuracy);
+            assert_eq!(streaming_metrics.precision, metrics.precision);
+            assert_eq!(streaming_metrics.recall, metrics.recall);
+            assert_eq!(
+                streaming_metrics.confusion_matrix.as_array().unwrap(),
+                metrics.confusion_matrix.as_array().unwrap()
+            );
+            let (x, _) = to_device(0, 2);
+            let t = ScalarTensor1::from(
+                Tensor::from(vec![0u8, classes as u8])
+                    .into_device(device.clone())
+                    .unwrap(),
+            );
+            if device.is_host() {
+                assert!(evaluator.add(&x, &t).is_err());
+            } else {
+                evaluator.add(&x, &t).unwrap();
+                assert!(evaluator.finish().is_err());
+            }
+        }
+    }
+
+    #[cfg(feature = "neural-network")]
+    mod neural_network {
+        use super::*;
+        use autograph::{
+            learn::neural_network::{
+                self,
+                autograd::{Variable, Variable2, Variable4, VariableD},
+                layer::{
+                    Checkpointed, Conv2, Dense, Flatten, Forward, Functional, Hooked, Init, Layer,
+                    MaxPool2, PaddingMode, Relu, __private::Conv2Options,
+                },
+            },
+            ops::__private::{Col2ImConv2, Col2ImConv2Options, Im2ColConv2, Im2ColConv2Options},
+            tensor::{ScalarArcTensor, ScalarArcTensorD, ScalarTensor},
+        };
+        use ndarray::{s, Array2, Array4, ArrayView4, Ix4};
+        use num_traits::{Float, Unsigned};
+        use std::sync::Arc;
+
+        fn conv2_string(filter: [usize; 2], options: &Conv2Options) -> String {
+            let [fh, fw] = filter;
+            let Conv2Options {
+                padding,
+                stride,
+                dilation,
+            } = options;
+            let (ph, pw) = padding.into_pattern();
+            let (sh, sw) = stride.into_pattern();
+            let (dh, dw) = dilation.into_pattern();
+            format!("fh{fh}_fw{fw}_ph{ph}_pw{pw}_sh{sh}_sw{sw}_dh{dh}_dw{dw}")
+        }
+
+        pub fn neural_network_tests(device: &Device) -> Vec<Trial> {
+            let mut tests = Vec::new();
+            let features = device
+                .info()
+                .map(|info| info.features())
+                .unwrap_or(Features::empty());
+
+            macro_for!($X in [bf16, f32] {
+                macro_for!($T in [u8, u16, u32] {
+                    let ignore = device.is_device()
+                    && (
+                        !features.contains(features_for_scalar($X::SCALAR_TYPE))
+                        || !features.contains(features_for_scalar($T::SCALAR_TYPE))
+                    );
+                    tests.push(device_test(device, &format!("cross_entropy_loss_backward_{}_{}", $X::SCALAR_TYPE.name(), $T::SCALAR_TYPE.name()), |device| {
+                        for (batch_size, classes) in [
+                            (1, 8),
+                            (31, 16),
+                            (1000, 100),
+                        ] {
+                            cross_entropy_loss_backward::<$X, $T>(device, batch_size, classes);
+                        }
+                    }).with_ignored_flag(ignore));
+                });
+            });
+            {
+                let batch_size_list = [2, 3, 13];
+                let filter_list = [[1, 1], [3, 3], [5, 5], [7, 7], [1, 3], [3, 5], [1, 7]];
+                let padding_list = [[0, 1], [1, 1]];
+                let stride_list = [[1, 2], [2, 2]];
+                let dilation_list = stride_list;
+                let output_size_list = [1, 2, 3, 5];
+                let filter_conv2_options_list = filter_list
+                    .iter()
+                    .copied()
+                    .flat_map(move |filter| {
+                        std::iter::once(Conv2Options::default())
+                            .chain(padding_list.into_iter().map(move |padding| Conv2Options {
+                                padding: padding.into_dimension(),
+                                ..Conv2Options::default()
+                            }))
+                            .chain(stride_list.into_iter().map(move |stride| Conv2Options {
+                                stride: stride.into_dimension(),
+                                ..Conv2Options::default()
+                            }))
+                            .chain(dilation_list.into_iter().map(move |dilation| Conv2Options {
+                                dilation: dilation.into_dimension(),
+                                ..Conv2Options::default()
+                            }))
+                            .map(move |options| (filter, options))
+                    })
+                    .collect::<Vec<_>>();
+                let batch_size_inputs_outputs_list = Arc::new(
+                    std::iter::once((1, 1, 1))
+                        .chain(
+                            batch_size_list
+                                .into_iter()
+                                .flat_map(move |x| [(x, 1, 1), (1, x, 1), (1, 1, x)]),
+                        )
+                        .collect::<Vec<_>>(),
+                );
+                let output_shapes = output_size_list
+                    .into_iter()
+                    .flat_map(move |oh| output_size_list.into_iter().map(move |ow| (oh, ow)))
+                    .collect::<Vec<_>>();
+                macro_for!($T in [bf16, f32] {
+                    let ignore = device.is_device()
+                    && !features.contains(features_for_scalar($T::SCALAR_TYPE));
                     let scalar_name = $T::SCALAR_TYPE.name();
                     for (filter, options) in filter_conv2_options_list.iter() {
                         let filter = *filter;
@@ -943,7 +3356,27 @@ mod learn {
                             {
                                 let batch_size_inputs_outputs_list = batch_size_inputs_outputs_list.clone();
                                 let input_shapes = input_shapes.clone();
-                                device_test(device, &format!("im2col_conv2_{scalar_name}_{conv2_name}"), move |device| {
+                                device_test(device, &format!("im2col_conv2_{scalar_name}_{conv2_name}"), move |device| {
+                                    let im2col_options = Im2ColConv2Options {
+                                        filter,
+                                        padding: [ph, pw],
+                                        stride: [sh, sw],
+                                        dilation: [dh, dw],
+                                    };
+                                    for (batch_size, inputs, outputs) in batch_size_inputs_outputs_list.iter().copied() {
+                                        if outputs == 1 {
+                                            for (ih, iw) in input_shapes.iter().copied() {
+                                                im2col_conv2::<$T>(device, [batch_size, inputs, ih, iw], &im2col_options);
+                                            }
+                                        }
+                                    }
+                                }).with_ignored_flag(ignore)
+                            },
+                            {
+                                let batch_size_inputs_outputs_list = batch_size_inputs_outputs_list.clone();
+                                let input_shapes = input_shapes.clone();
+
+                                device_test(device, &format!("col2im_conv2_{scalar_name}_{conv2_name}"), move |device| {
                                     let im2col_options = Im2ColConv2Options {
                                         filter,
                                         padding: [ph, pw],
@@ -953,745 +3386,3953 @@ mod learn {
                                     for (batch_size, inputs, outputs) in batch_size_inputs_outputs_list.iter().copied() {
                                         if outputs == 1 {
                                             for (ih, iw) in input_shapes.iter().copied() {
-                                                im2col_conv2::<$T>(device, [batch_size, inputs, ih, iw], &im2col_options);
+                                                col2im_conv2::<$T>(device, [batch_size, inputs, ih, iw], &im2col_options);
+                                            }
+                                        }
+                                    }
+                                }).with_ignored_flag(ignore)
+                            },
+                            {
+                                let batch_size_inputs_outputs_list = batch_size_inputs_outputs_list.clone();
+                                let input_shapes = input_shapes.clone();
+                                let options = options.clone();
+                                device_test(device, &format!("conv2_im2col_{scalar_name}_{conv2_name}"), move |device| {
+                                    for (batch_size, inputs, outputs) in batch_size_inputs_outputs_list.iter().copied() {
+                                        for (ih, iw) in input_shapes.iter().copied() {
+                                            conv2::<$T>(device, [batch_size, inputs, ih, iw], outputs, filter, &options, ConvAlg::Im2Col);
+                                        }
+                                    }
+                                }).with_ignored_flag(ignore)
+                            },
+                        ]);
+                        if $T::SCALAR_TYPE == ScalarType::F32 && device.is_host() && options.is_default() {
+                            tests.extend([
+                                {
+                                    let batch_size_inputs_outputs_list = batch_size_inputs_outputs_list.clone();
+                                    let input_shapes = input_shapes.clone();
+                                    let options = options.clone();
+                                    device_test(device, &format!("conv2_direct_{scalar_name}_{conv2_name}"), move |device| {
+                                        for (batch_size, inputs, outputs) in batch_size_inputs_outputs_list.iter().copied() {
+                                            for (ih, iw) in input_shapes.iter().copied() {
+                                                conv2::<$T>(device, [batch_size, inputs, ih, iw], outputs, filter, &options, ConvAlg::Direct);
+                                            }
+                                        }
+                                    })
+                                },
+                                {
+                                    let batch_size_inputs_outputs_list = batch_size_inputs_outputs_list.clone();
+                                    let input_shapes = input_shapes.clone();
+                                    let options = options.clone();
+                                    device_test(device, &format!("conv2_direct_backward_input_{scalar_name}_{conv2_name}"), move |device| {
+                                        for (batch_size, inputs, outputs) in batch_size_inputs_outputs_list.iter().copied() {
+                                            for (ih, iw) in input_shapes.iter().copied() {
+                                                conv2_backward_input::<$T>(device, [batch_size, inputs, ih, iw], outputs, filter, &options, ConvAlg::Direct);
+                                            }
+                                        }
+                                    })
+                                },
+                                {
+                                    let batch_size_inputs_outputs_list = batch_size_inputs_outputs_list.clone();
+                                    let input_shapes = input_shapes.clone();
+                                    let options = options.clone();
+                                    device_test(device, &format!("conv2_direct_backward_weight_{scalar_name}_{conv2_name}"), move |device| {
+                                        for (batch_size, inputs, outputs) in batch_size_inputs_outputs_list.iter().copied() {
+                                            for (ih, iw) in input_shapes.iter().copied() {
+                                                conv2_backward_weight::<$T>(device, [batch_size, inputs, ih, iw], outputs, filter, &options, ConvAlg::Direct);
+                                            }
+                                        }
+                                    })
+                                },
+                            ]);
+                        }
+                    }
+                });
+            }
+            macro_for!($T in [bf16, f32] {
+                let ignore = device.is_device()
+                && !features.contains(features_for_scalar($T::SCALAR_TYPE));
+                let input_shapes = [
+                    [1, 1, 4, 4],
+                    [1, 1, 12, 12],
+                    [2, 3, 4, 4],
+                    [1, 1, 24, 24],
+                ];
+                tests.extend([
+                    device_test(device, &format!("max_pool2_{}", $T::SCALAR_TYPE.name()), move |device| {
+                        let pool = MaxPool2::builder().filter([2, 2]).build();
+                        for input_shape in input_shapes {
+                            max_pool2::<$T>(device, input_shape, &pool);
+                        }
+                    }).with_ignored_flag(ignore),
+                    device_test(device, &format!("max_pool2_backward_{}", $T::SCALAR_TYPE.name()), move |device| {
+                        let pool = MaxPool2::builder().filter([2, 2]).build();
+                        for input_shape in input_shapes {
+                            max_pool2_backward::<$T>(device, input_shape, &pool);
+                        }
+                    }).with_ignored_flag(ignore),
+                ]);
+            });
+            macro_for!($T in [bf16, f32] {
+                let ignore = device.is_device()
+                && !features.contains(features_for_scalar($T::SCALAR_TYPE));
+                let input_shapes = [[1, 8], [15, 20]];
+                tests.extend([
+                    device_test(device, &format!("relu_{}", $T::SCALAR_TYPE.name()), move |device| {
+                        for input_shape in input_shapes {
+                            relu::<$T>(device, input_shape);
+                        }
+                    }).with_ignored_flag(ignore),
+                    device_test(device, &format!("relu_backward_{}", $T::SCALAR_TYPE.name()), move |device| {
+                        for input_shape in input_shapes {
+                            relu_backward::<$T>(device, input_shape);
+                        }
+                    }).with_ignored_flag(ignore),
+                ]);
+            });
+            macro_for!($T in [bf16, f32] {
+                let ignore = device.is_device()
+                && !features.contains(features_for_scalar($T::SCALAR_TYPE));
+                tests.push(
+                    device_test(device, &format!("tanh_{}", $T::SCALAR_TYPE.name()), move |device| {
+                        for input_shape in [[1, 8], [15, 20]] {
+                            tanh::<$T>(device, input_shape);
+                        }
+                    }).with_ignored_flag(ignore),
+                );
+            });
+            tests.extend([device_test(device, "broadcast", move |device| {
+                broadcast(device, [2], [4, 2]);
+                broadcast(device, [2], [4, 3, 2]);
+                broadcast(device, [2], [5, 4, 3, 2]);
+                broadcast(device, [2], [6, 5, 4, 3, 2]);
+                broadcast(device, [2], [7, 6, 5, 4, 3, 2]);
+                broadcast(device, [3, 2], [5, 4, 3, 2]);
+                broadcast(device, [4, 1, 1, 3], [4, 2, 1, 3]);
+            })]);
+            tests.push(device_test(device, "checkpointed", checkpointed));
+            tests.push(device_test(device, "padding_mode_same", padding_mode_same));
+            if device.is_host() {
+                tests.push(device_test(device, "conv_same_padding", conv_same_padding));
+            }
+            tests.push(device_test(device, "pad2", pad2));
+            tests.push(device_test(device, "conv2_kernel_kind", conv2_kernel_kind));
+            tests.push(device_test(
+                device,
+                "conv2_stride_padding",
+                conv2_stride_padding,
+            ));
+            tests.push(device_test(device, "conv1", conv1));
+            tests.push(device_test(device, "conv_transpose2", conv_transpose2));
+            tests.push(device_test(device, "flatten_range", flatten_range));
+            tests.push(device_test(device, "pixel_shuffle", pixel_shuffle));
+            tests.push(device_test(
+                device,
+                "cross_entropy_loss_ignore_index",
+                cross_entropy_loss_ignore_index,
+            ));
+            tests.push(device_test(device, "loss_per_sample", loss_per_sample));
+            tests.push(device_test(
+                device,
+                "cross_entropy_loss_reduction",
+                cross_entropy_loss_reduction,
+            ));
+            tests.push(device_test(device, "dense_init", dense_init));
+            tests.push(device_test(device, "layer_features", layer_features));
+            tests.push(device_test(device, "maxout", maxout));
+            tests.push(device_test(device, "dropout_seed", dropout_seed));
+            tests.push(device_test(
+                device,
+                "dropout_expected_value",
+                dropout_expected_value,
+            ));
+            tests.push(device_test(device, "sigmoid_backward", sigmoid_backward));
+            tests.push(device_test(device, "softmax", softmax));
+            tests.push(device_test(device, "nll_loss", nll_loss));
+            tests.push(device_test(device, "batch_norm2", batch_norm2));
+            tests.push(device_test(device, "layer_norm", layer_norm));
+            tests.push(device_test(device, "embedding", embedding));
+            tests.push(device_test(device, "avg_pool2", avg_pool2));
+            tests.push(device_test(
+                device,
+                "adaptive_avg_pool2",
+                adaptive_avg_pool2,
+            ));
+            tests.push(device_test(device, "adam", adam));
+            tests.push(device_test(device, "radam", radam));
+            tests.push(device_test(device, "rmsprop", rmsprop));
+            tests.push(device_test(device, "lamb", lamb));
+            tests.push(
+                device_test(device, "quantize", quantize).with_ignored_flag(
+                    device.is_device()
+                        && !features.contains(Features::INT8 | Features::BUFFER8),
+                ),
+            );
+            tests.push(device_test(
+                device,
+                "concurrent_inference",
+                concurrent_inference,
+            ));
+            tests.push(device_test(
+                device,
+                "derive_layer_generic",
+                derive_layer_generic,
+            ));
+            tests.push(device_test(device, "forward_hook", forward_hook));
+            tests.push(device_test(device, "backward_hook", backward_hook));
+            tests.push(device_test(device, "backward_for", backward_for));
+            tests.push(device_test(device, "graph_stats", graph_stats));
+            tests.push(device_test(
+                device,
+                "triplet_margin_loss",
+                triplet_margin_loss,
+            ));
+            tests.push(device_test(device, "info_nce_loss", info_nce_loss));
+            tests.push(device_test(device, "mse_loss_backward", mse_loss_backward));
+            tests.push(device_test(device, "mse_loss_reduction", mse_loss_reduction));
+            tests.push(device_test(device, "l1_loss_backward", l1_loss_backward));
+            tests.push(device_test(
+                device,
+                "huber_loss_continuity",
+                huber_loss_continuity,
+            ));
+            tests.push(device_test(device, "bce_loss_backward", bce_loss_backward));
+            tests.push(device_test(
+                device,
+                "adversarial_example",
+                adversarial_example,
+            ));
+            tests.push(device_test(
+                device,
+                "variable_batch_size",
+                variable_batch_size,
+            ));
+            #[cfg(feature = "serde")]
+            tests.push(device_test(device, "layer_save_load", layer_save_load));
+            tests
+        }
+
+        fn cross_entropy_loss_backward<X: Scalar + Float, T: Scalar + Unsigned>(
+            device: &Device,
+            batch_size: usize,
+            classes: usize,
+        ) {
+            use neural_network::__private::criterion::cross_entropy_loss_backward as backward;
+            let x_vec: Vec<X> = (0..10u8)
+                .map(|x| X::from_u8(x).unwrap())
+                .cycle()
+                .take(batch_size * classes)
+                .collect();
+            let t_vec: Vec<T> = (0..classes)
+                .cycle()
+                .map(|t| T::from_usize(t).unwrap())
+                .take(batch_size)
+                .collect();
+            let x_array = Array::from(x_vec)
+                .into_shape([batch_size, classes])
+                .unwrap();
+            let t_array = Array::from(t_vec);
+            let x_host = Tensor::from(x_array);
+            let t_host = Tensor::from(t_array);
+            let x_device = x_host.to_device(device.clone()).unwrap();
+            let t_device = t_host.to_device(device.clone()).unwrap();
+            let dy = 1f32;
+            let dx_host = backward(x_host.view(), t_host.view(), dy)
+                .unwrap()
+                .into_dyn();
+            let dx_device = backward(x_device.view(), t_device.view(), dy)
+                .unwrap()
+                .into_device(Device::host())
+                .unwrap()
+                .into_dyn();
+            check_approx_eq(dx_host.view().into(), dx_device.view().into(), None);
+        }
+
+        fn conv2_direct_naive<T: Scalar>(
+            x: ArrayView4<T>,
+            w: ArrayView4<T>,
+            options: &Conv2Options,
+        ) -> Array4<T> {
+            let (bs, ic, ih, iw) = x.dim();
+            let (oc, _ic, fh, fw) = w.dim();
+            let (oh, ow) = options
+                .output_shape([ih, iw].into_dimension(), &[fh, fw].into_dimension())
+                .unwrap()
+                .into_pattern();
+            let Conv2Options {
+                padding,
+                dilation,
+                stride,
+            } = options;
+            let (ph, pw) = padding.into_pattern();
+            let (sh, sw) = stride.into_pattern();
+            let (dh, dw) = dilation.into_pattern();
+            let mut y = Array::zeros([bs, oc, oh, ow]);
+            for bid in 0..bs {
+                for cidy in 0..oc {
+                    for cidx in 0..ic {
+                        for hidy in 0..oh {
+                            for widy in 0..ow {
+                                for fi in 0..fh {
+                                    let hidx =
+                                        -(ph as isize) + (sh * hidy) as isize + (dh * fi) as isize;
+                                    if (0..ih as isize).contains(&hidx) {
+                                        for fj in 0..fw {
+                                            let widx = -(pw as isize)
+                                                + (sw * widy) as isize
+                                                + (dw * fj) as isize;
+                                            if (0..iw as isize).contains(&widx) {
+                                                y[(bid, cidy, hidy, widy)] += x
+                                                    [(bid, cidx, hidx as usize, widx as usize)]
+                                                    * w[(cidy, cidx, fi, fj)];
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            y
+        }
+
+        #[derive(Debug, Copy, Clone)]
+        enum ConvAlg {
+            Im2Col,
+            Direct,
+        }
+
+        fn conv2<T: Scalar>(
+            device: &Device,
+            x_shape: [usize; 4],
+            outputs: usize,
+            filter: [usize; 2],
+            options: &Conv2Options,
+            alg: ConvAlg,
+        ) {
+            let [_bs, inputs, _ih, _iw] = x_shape;
+            let [fh, fw] = filter;
+            let w_shape = [outputs, inputs, fh, fw];
+            let x_shape = x_shape.into_dimension();
+            let w_shape = w_shape.into_dimension();
+            let x_host = Array::from_vec(
+                (1..=10)
+                    .cycle()
+                    .map(|x| T::from_usize(x).unwrap())
+                    .take(x_shape.size())
+                    .collect(),
+            )
+            .into_shape(x_shape)
+            .unwrap();
+            let w_host = Array::from_vec(
+                (1..=10)
+                    .cycle()
+                    .map(|x| T::from_usize(x).unwrap())
+                    .take(w_shape.size())
+                    .collect(),
+            )
+            .into_shape(w_shape)
+            .unwrap();
+            let x_device = TensorView::try_from(x_host.view())
+                .unwrap()
+                .to_device(device.clone())
+                .unwrap();
+            let w_device = TensorView::try_from(w_host.view())
+                .unwrap()
+                .to_device(device.clone())
+                .unwrap();
+            let y_host = Tensor::from(conv2_direct_naive(x_host.view(), w_host.view(), options));
+            let y_device = match alg {
+                ConvAlg::Im2Col => neural_network::layer::__private::conv2_im2col_forward(
+                    x_device.view().into(),
+                    w_device.view().into(),
+                    options,
+                )
+                .unwrap(),
+                ConvAlg::Direct => neural_network::layer::__private::conv2_direct_forward(
+                    x_device.view().into(),
+                    w_device.view().into(),
+                    options,
+                )
+                .unwrap(),
+            };
+            let y_device = y_device
+                .try_into_tensor::<T>()
+                .unwrap()
+                .into_device(Device::host())
+                .unwrap();
+            let epsilon = if T::SCALAR_TYPE == ScalarType::BF16 {
+                Some(ScalarElem::F32((fh * fw) as f32))
+            } else {
+                None
+            };
+            check_approx_eq(
+                y_host.view().into_dyn().into(),
+                y_device.view().into_dyn().into(),
+                epsilon,
+            );
+        }
+
+        fn conv2_direct_backward_input_naive<T: Scalar>(
+            w: ArrayView4<T>,
+            dy: ArrayView4<T>,
+            options: &Conv2Options,
+        ) -> Array4<T> {
+            let (oc, ic, fh, fw) = w.dim();
+            let (bs, _oc, oh, ow) = dy.dim();
+            assert_eq!(oc, _oc);
+            let (ih, iw) = options
+                .input_shape([oh, ow].into_dimension(), &[fh, fw].into_dimension())
+                .unwrap()
+                .into_pattern();
+            let Conv2Options {
+                padding,
+                dilation,
+                stride,
+            } = options;
+            let (ph, pw) = padding.into_pattern();
+            let (sh, sw) = stride.into_pattern();
+            let (dh, dw) = dilation.into_pattern();
+            let mut dx = Array::zeros([bs, ic, ih, iw]);
+            for bid in 0..bs {
+                for cidx in 0..ic {
+                    for cidy in 0..oc {
+                        for hidy in 0..oh {
+                            for widy in 0..ow {
+                                for fi in 0..fh {
+                                    let hidx =
+                                        -(ph as isize) + (sh * hidy) as isize + (dh * fi) as isize;
+                                    if (0..ih as isize).contains(&hidx) {
+                                        for fj in 0..fw {
+                                            let widx = -(pw as isize)
+                                                + (sw * widy) as isize
+                                                + (dw * fj) as isize;
+                                            if (0..iw as isize).contains(&widx) {
+                                                dx[(bid, cidx, hidx as usize, widx as usize)] += w
+                                                    [(cidy, cidx, fi, fj)]
+                                                    * dy[(bid, cidy, hidy, widy)];
                                             }
                                         }
                                     }
-                                }).with_ignored_flag(ignore)
-                            },
-                            {
-                                let batch_size_inputs_outputs_list = batch_size_inputs_outputs_list.clone();
-                                let input_shapes = input_shapes.clone();
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            dx
+        }
+
+        fn conv2_backward_input<T: Scalar>(
+            device: &Device,
+            x_shape: [usize; 4],
+            outputs: usize,
+            filter: [usize; 2],
+            options: &Conv2Options,
+            alg: ConvAlg,
+        ) {
+            let [bs, inputs, ih, iw] = x_shape;
+            let [fh, fw] = filter;
+            let w_shape = [outputs, inputs, fh, fw];
+            let w_shape = w_shape.into_dimension();
+            let (oh, ow) = options
+                .output_shape([ih, iw].into_dimension(), &filter.into_dimension())
+                .unwrap()
+                .into_pattern();
+            let y_shape = [bs, outputs, oh, ow].into_dimension();
+            let w_host = Array::from_vec(
+                (1..=10)
+                    .cycle()
+                    .map(|x| T::from_usize(x).unwrap())
+                    .take(w_shape.size())
+                    .collect(),
+            )
+            .into_shape(w_shape)
+            .unwrap();
+            let dy_host = Array::from_vec(
+                (1..=10)
+                    .cycle()
+                    .map(|x| T::from_usize(x).unwrap())
+                    .take(y_shape.size())
+                    .collect(),
+            )
+            .into_shape(y_shape)
+            .unwrap();
+            let w_device = TensorView::try_from(w_host.view())
+                .unwrap()
+                .to_device(device.clone())
+                .unwrap();
+            let dy_device = TensorView::try_from(dy_host.view())
+                .unwrap()
+                .to_device(device.clone())
+                .unwrap();
+            let dx_host = Tensor::from(conv2_direct_backward_input_naive(
+                w_host.view(),
+                dy_host.view(),
+                options,
+            ));
+            let dx_device = match alg {
+                ConvAlg::Im2Col => todo!(),
+                ConvAlg::Direct => neural_network::layer::__private::conv2_direct_backward_input(
+                    w_device.view().into(),
+                    dy_device.view().into(),
+                    options,
+                )
+                .unwrap(),
+            };
+            let dx_device = dx_device
+                .try_into_tensor::<T>()
+                .unwrap()
+                .into_device(Device::host())
+                .unwrap();
+            let epsilon = if T::SCALAR_TYPE == ScalarType::BF16 {
+                Some(ScalarElem::F32((fh * fw) as f32))
+            } else {
+                None
+            };
+            check_approx_eq(
+                dx_host.view().into_dyn().into(),
+                dx_device.view().into_dyn().into(),
+                epsilon,
+            );
+        }
+
+        fn conv2_direct_backward_weight_naive<T: Scalar>(
+            x: ArrayView4<T>,
+            dy: ArrayView4<T>,
+            filter: [usize; 2],
+            options: &Conv2Options,
+        ) -> Array4<T> {
+            let (bs, ic, ih, iw) = x.dim();
+            let (_bs, oc, oh, ow) = dy.dim();
+            assert_eq!(bs, _bs);
+            let [fh, fw] = filter;
+            let Conv2Options {
+                padding,
+                dilation,
+                stride,
+            } = options;
+            let (ph, pw) = padding.into_pattern();
+            let (sh, sw) = stride.into_pattern();
+            let (dh, dw) = dilation.into_pattern();
+            let mut w_grad = Array::zeros([oc, ic, fh, fw]);
+            for bid in 0..bs {
+                for cidx in 0..ic {
+                    for cidy in 0..oc {
+                        for hidy in 0..oh {
+                            for widy in 0..ow {
+                                for fi in 0..fh {
+                                    let hidx =
+                                        -(ph as isize) + (sh * hidy) as isize + (fi * dh) as isize;
+                                    if (0..ih as isize).contains(&hidx) {
+                                        for fj in 0..fw {
+                                            let widx = -(pw as isize)
+                                                + (sw * widy) as isize
+                                                + (fj * dw) as isize;
+                                            if (0..iw as isize).contains(&widx) {
+                                                w_grad[(cidy, cidx, fi, fj)] += x
+                                                    [(bid, cidx, hidx as usize, widx as usize)]
+                                                    * dy[(bid, cidy, hidy, widy)];
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            w_grad
+        }
+
+        fn conv2_backward_weight<T: Scalar>(
+            device: &Device,
+            x_shape: [usize; 4],
+            outputs: usize,
+            filter: [usize; 2],
+            options: &Conv2Options,
+            alg: ConvAlg,
+        ) {
+            let [bs, inputs, ih, iw] = x_shape;
+            let x_shape = x_shape.into_dimension();
+            let (oh, ow) = options
+                .output_shape([ih, iw].into_dimension(), &filter.into_dimension())
+                .unwrap()
+                .into_pattern();
+            let y_shape = [bs, outputs, oh, ow].into_dimension();
+            let x_host = Array::from_vec(
+                (1..=10)
+                    .cycle()
+                    .map(|x| T::from_usize(x).unwrap())
+                    .take(x_shape.size())
+                    .collect(),
+            )
+            .into_shape(x_shape)
+            .unwrap();
+            let dy_host = Array::from_vec(
+                (1..=10)
+                    .cycle()
+                    .map(|x| T::from_usize(x).unwrap())
+                    .take(y_shape.size())
+                    .collect(),
+            )
+            .into_shape(y_shape)
+            .unwrap();
+            let x_device = TensorView::try_from(x_host.view())
+                .unwrap()
+                .to_device(device.clone())
+                .unwrap();
+            let dy_device = TensorView::try_from(dy_host.view())
+                .unwrap()
+                .to_device(device.clone())
+                .unwrap();
+            let dw_host = Tensor::from(conv2_direct_backward_weight_naive(
+                x_host.view(),
+                dy_host.view(),
+                filter,
+                options,
+            ));
+            let dw_device = match alg {
+                ConvAlg::Im2Col => todo!(),
+                ConvAlg::Direct => neural_network::layer::__private::conv2_direct_backward_weight(
+                    x_device.view().into(),
+                    dy_device.view().into(),
+                    filter,
+                    options,
+                )
+                .unwrap(),
+            };
+            let dw_device = dw_device
+                .try_into_tensor::<T>()
+                .unwrap()
+                .into_device(Device::host())
+                .unwrap();
+            let epsilon = if T::SCALAR_TYPE == ScalarType::BF16 {
+                Some(ScalarElem::F32(inputs as f32))
+            } else {
+                None
+            };
+            check_approx_eq(
+                dw_host.view().into_dyn().into(),
+                dw_device.view().into_dyn().into(),
+                epsilon,
+            );
+        }
+
+        fn im2col_conv2<T: Scalar>(
+            device: &Device,
+            x_shape: [usize; 4],
+            options: &Im2ColConv2Options,
+        ) {
+            let len = x_shape.iter().product();
+            let x_vec: Vec<T> = (1..=len).map(|x| T::from_usize(x).unwrap()).collect();
+            let x_array = Array::from(x_vec).into_shape(x_shape).unwrap();
+            let x_host = Tensor::from(x_array);
+            let x_device = x_host.to_device(device.clone()).unwrap();
+            let y_host = x_host.im2col_conv2(options).unwrap();
+            let y_device = x_device.im2col_conv2(options).unwrap();
+            assert_eq!(y_host.into_array().unwrap(), y_device.into_array().unwrap());
+        }
+
+        fn col2im_conv2<T: Scalar>(
+            device: &Device,
+            input_shape: [usize; 4],
+            options: &Im2ColConv2Options,
+        ) {
+            let [_batch_size, _channels, ih, iw] = input_shape;
+            let len = input_shape.iter().product();
+            let x_vec: Vec<T> = (1..=len).map(|x| T::from_usize(x).unwrap()).collect();
+            let x_array = Array::from(x_vec).into_shape(input_shape).unwrap();
+            let x_host = Tensor::from(x_array);
+            let y_host = x_host.im2col_conv2(options).unwrap();
+            let [oh, ow] = options.output_shape([ih, iw]);
+            let col2im_options = Col2ImConv2Options {
+                shape: [oh, ow],
+                filter: options.filter,
+                padding: options.padding,
+                stride: options.stride,
+                dilation: options.dilation,
+            };
+            let dy_vec: Vec<T> = (1..=y_host.len())
+                .map(|x| T::from_usize(x).unwrap())
+                .collect();
+            let dy_array = Array::from(dy_vec).into_shape(y_host.raw_dim()).unwrap();
+            let dy_host = Tensor::from(dy_array);
+            let dy_device = dy_host.to_device(device.clone()).unwrap();
+            let dx_host = dy_host.col2im_conv2(&col2im_options).unwrap();
+            let dx_device = dy_device.col2im_conv2(&col2im_options).unwrap();
+            let [fh, fw] = options.filter;
+            let epsilon = if T::SCALAR_TYPE == ScalarType::BF16 {
+                Some(ScalarElem::F32((fh * fw) as f32))
+            } else {
+                None
+            };
+            check_approx_eq(
+                dx_host.view().into_dyn().into(),
+                dx_device.view().into_dyn().into(),
+                epsilon,
+            );
+        }
+
+        fn max_pool2<T: Scalar>(device: &Device, input_shape: [usize; 4], pool: &MaxPool2) {
+            let len = input_shape.iter().product();
+            let x_vec: Vec<T> = (0..10u8)
+                .map(|x| T::from_u8(x).unwrap())
+                .cycle()
+                .take(len)
+                .collect();
+            let x_array = Array::from(x_vec).into_shape(input_shape).unwrap();
+            let x_host = Tensor::from(x_array);
+            let x_device = x_host.to_device(device.clone()).unwrap();
+            let y_host = pool
+                .forward(Variable::from(x_host))
+                .unwrap()
+                .into_value()
+                .into_owned()
+                .unwrap()
+                .try_into_tensor::<T>()
+                .unwrap();
+            let y_device = pool
+                .forward(Variable::from(x_device))
+                .unwrap()
+                .into_value()
+                .into_owned()
+                .unwrap()
+                .try_into_tensor::<T>()
+                .unwrap();
+            assert_eq!(y_host.into_array().unwrap(), y_device.into_array().unwrap());
+        }
+
+        fn max_pool2_backward<T: Scalar>(
+            device: &Device,
+            input_shape: [usize; 4],
+            pool: &MaxPool2,
+        ) {
+            use neural_network::layer::__private::max_pool2_backward as backward;
+            let len = input_shape.iter().product();
+            let x_vec: Vec<T> = (0..10u8)
+                .map(|x| T::from_u8(x).unwrap())
+                .cycle()
+                .take(len)
+                .collect();
+            let x_array = Array::from(x_vec).into_shape(input_shape).unwrap();
+            let x_host = Tensor::from(x_array).into_shared().unwrap();
+            let y_host = pool
+                .forward(Variable::from(x_host.clone()))
+                .unwrap()
+                .into_value()
+                .into_owned()
+                .unwrap()
+                .try_into_tensor::<T>()
+                .unwrap();
+            let dy_vec: Vec<T> = (0..y_host.len())
+                .map(|x| T::from_usize(x).unwrap())
+                .collect();
+            let dy_array = Array::from(dy_vec).into_shape(y_host.raw_dim()).unwrap();
+            let dy_host = Tensor::from(dy_array).into_shared().unwrap();
+            let x_device = x_host.to_device_shared(device.clone()).unwrap();
+            let dy_device = dy_host.to_device_shared(device.clone()).unwrap();
+            let dx_host = backward(pool, x_host.into(), dy_host.into())
+                .unwrap()
+                .into_owned()
+                .unwrap()
+                .try_into_tensor::<T>()
+                .unwrap();
+            let dx_device = backward(pool, x_device.into(), dy_device.into())
+                .unwrap()
+                .into_owned()
+                .unwrap()
+                .try_into_tensor::<T>()
+                .unwrap();
+            assert_eq!(
+                dx_host.into_array().unwrap(),
+                dx_device.into_array().unwrap()
+            );
+        }
+
+        fn relu<T: Scalar>(device: &Device, input_shape: [usize; 2]) {
+            let len = input_shape.iter().product();
+            let x_vec: Vec<T> = (-10i8..10)
+                .map(|x| T::from_i8(x).unwrap())
+                .cycle()
+                .take(len)
+                .collect();
+            let x_array = Array::from(x_vec).into_shape(input_shape).unwrap();
+            let x_host = Tensor::from(x_array);
+            let x_device = x_host.to_device(device.clone()).unwrap();
+            let y_host = Relu
+                .forward(Variable::from(x_host))
+                .unwrap()
+                .into_value()
+                .into_owned()
+                .unwrap()
+                .try_into_tensor::<T>()
+                .unwrap();
+            let y_device = Relu
+                .forward(Variable::from(x_device))
+                .unwrap()
+                .into_value()
+                .into_owned()
+                .unwrap()
+                .try_into_tensor::<T>()
+                .unwrap();
+            assert_eq!(y_host.into_array().unwrap(), y_device.into_array().unwrap());
+        }
+
+        fn relu_backward<T: Scalar>(device: &Device, input_shape: [usize; 2]) {
+            use neural_network::layer::__private::relu_backward as backward;
+            let len = input_shape.iter().product();
+            let y_vec: Vec<T> = (-1i8..1)
+                .map(|x| T::from_i8(x).unwrap())
+                .cycle()
+                .take(len)
+                .collect();
+            let dy_vec: Vec<T> = (0..len).map(|x| T::from_usize(x).unwrap()).collect();
+            let y_array = Array::from(y_vec).into_shape(input_shape).unwrap();
+            let dy_array = Array::from(dy_vec).into_shape(input_shape).unwrap();
+            let y_host = Tensor::from(y_array).into_shared().unwrap();
+            let dy_host = Tensor::from(dy_array).into_shared().unwrap();
+            let y_device = y_host.to_device_shared(device.clone()).unwrap();
+            let dy_device = dy_host.to_device_shared(device.clone()).unwrap();
+            for (dy_host, dy_device) in [
+                (dy_host.clone(), dy_device.clone()), // relu_backward
+                (dy_host, dy_device),                 // relu_backward_mut
+            ] {
+                let dx_host = backward(y_host.clone().into(), dy_host.into())
+                    .unwrap()
+                    .into_owned()
+                    .unwrap()
+                    .try_into_tensor::<T>()
+                    .unwrap();
+                let dx_device = backward(y_device.clone().into(), dy_device.into())
+                    .unwrap()
+                    .into_owned()
+                    .unwrap()
+                    .try_into_tensor::<T>()
+                    .unwrap();
+                assert_eq!(
+                    dx_host.into_array().unwrap(),
+                    dx_device.into_array().unwrap()
+                );
+            }
+        }
+
+        fn tanh<T: Scalar>(device: &Device, input_shape: [usize; 2]) {
+            use autograph::learn::neural_network::layer::Tanh;
+
+            let len = input_shape.iter().product();
+            let x_vec: Vec<T> = (-10i8..10)
+                .map(|x| T::from_f32(x as f32 / 4.).unwrap())
+                .cycle()
+                .take(len)
+                .collect();
+            let x_array = Array::from(x_vec).into_shape(input_shape).unwrap();
+            let dy_vec: Vec<T> = (0..len).map(|x| T::from_usize(x % 7).unwrap()).collect();
+            let dy_array = Array::from(dy_vec).into_shape(input_shape).unwrap();
+            let [(y_host, dx_host), (y_device, dx_device)] =
+                [Device::host(), device.clone()].map(|device| {
+                    let x = Variable::builder().node().build(
+                        Tensor::from(x_array.clone())
+                            .into_device(device.clone())
+                            .unwrap()
+                            .into_shared()
+                            .unwrap()
+                            .into(),
+                    );
+                    let y = Tanh.forward(x.clone()).unwrap();
+                    let dy = Tensor::from(dy_array.clone())
+                        .into_device(device)
+                        .unwrap()
+                        .into_shared()
+                        .unwrap();
+                    y.node().unwrap().backward_grad(dy.into()).unwrap();
+                    let to_host = |x: ScalarArcTensor<ndarray::Ix2>| {
+                        x.into_device(Device::host())
+                            .unwrap()
+                            .try_into_tensor::<T>()
+                            .unwrap()
+                    };
+                    (to_host(y.into_value()), to_host(x.grad().unwrap()))
+                });
+            let epsilon = if T::SCALAR_TYPE == ScalarType::BF16 {
+                Some(ScalarElem::F32(0.01))
+            } else {
+                None
+            };
+            check_approx_eq(
+                y_host.view().into_dyn().into(),
+                y_device.view().into_dyn().into(),
+                epsilon,
+            );
+            check_approx_eq(
+                dx_host.view().into_dyn().into(),
+                dx_device.view().into_dyn().into(),
+                epsilon,
+            );
+            let y_true = x_array.map(|x| T::from_f32(x.to_f32().unwrap().tanh()).unwrap());
+            assert_eq!(y_host.into_array().unwrap(), y_true);
+        }
 
-                                device_test(device, &format!("col2im_conv2_{scalar_name}_{conv2_name}"), move |device| {
-                                    let im2col_options = Im2ColConv2Options {
-                                        filter,
-                                        padding: [ph, pw],
-                                        stride: [sh, sw],
-                                        dilation: [dh, dw],
-                                    };
-                                    for (batch_size, inputs, outputs) in batch_size_inputs_outputs_list.iter().copied() {
-                                        if outputs == 1 {
-                                            for (ih, iw) in input_shapes.iter().copied() {
-                                                col2im_conv2::<$T>(device, [batch_size, inputs, ih, iw], &im2col_options);
-                                            }
-                                        }
-                                    }
-                                }).with_ignored_flag(ignore)
-                            },
-                            {
-                                let batch_size_inputs_outputs_list = batch_size_inputs_outputs_list.clone();
-                                let input_shapes = input_shapes.clone();
-                                let options = options.clone();
-                                device_test(device, &format!("conv2_im2col_{scalar_name}_{conv2_name}"), move |device| {
-                                    for (batch_size, inputs, outputs) in batch_size_inputs_outputs_list.iter().copied() {
-                                        for (ih, iw) in input_shapes.iter().copied() {
-                                            conv2::<$T>(device, [batch_size, inputs, ih, iw], outputs, filter, &options, ConvAlg::Im2Col);
-                                        }
-                                    }
-                                }).with_ignored_flag(ignore)
-                            },
-                        ]);
-                        if $T::SCALAR_TYPE == ScalarType::F32 && device.is_host() && options.is_default() {
-                            tests.extend([
-                                {
-                                    let batch_size_inputs_outputs_list = batch_size_inputs_outputs_list.clone();
-                                    let input_shapes = input_shapes.clone();
-                                    let options = options.clone();
-                                    device_test(device, &format!("conv2_direct_{scalar_name}_{conv2_name}"), move |device| {
-                                        for (batch_size, inputs, outputs) in batch_size_inputs_outputs_list.iter().copied() {
-                                            for (ih, iw) in input_shapes.iter().copied() {
-                                                conv2::<$T>(device, [batch_size, inputs, ih, iw], outputs, filter, &options, ConvAlg::Direct);
-                                            }
-                                        }
-                                    })
-                                },
-                                {
-                                    let batch_size_inputs_outputs_list = batch_size_inputs_outputs_list.clone();
-                                    let input_shapes = input_shapes.clone();
-                                    let options = options.clone();
-                                    device_test(device, &format!("conv2_direct_backward_input_{scalar_name}_{conv2_name}"), move |device| {
-                                        for (batch_size, inputs, outputs) in batch_size_inputs_outputs_list.iter().copied() {
-                                            for (ih, iw) in input_shapes.iter().copied() {
-                                                conv2_backward_input::<$T>(device, [batch_size, inputs, ih, iw], outputs, filter, &options, ConvAlg::Direct);
-                                            }
-                                        }
-                                    })
-                                },
-                                {
-                                    let batch_size_inputs_outputs_list = batch_size_inputs_outputs_list.clone();
-                                    let input_shapes = input_shapes.clone();
-                                    let options = options.clone();
-                                    device_test(device, &format!("conv2_direct_backward_weight_{scalar_name}_{conv2_name}"), move |device| {
-                                        for (batch_size, inputs, outputs) in batch_size_inputs_outputs_list.iter().copied() {
-                                            for (ih, iw) in input_shapes.iter().copied() {
-                                                conv2_backward_weight::<$T>(device, [batch_size, inputs, ih, iw], outputs, filter, &options, ConvAlg::Direct);
-                                            }
-                                        }
-                                    })
-                                },
-                            ]);
+        fn checkpointed(device: &Device) {
+            let dense = Dense::builder()
+                .inputs(4)
+                .outputs(3)
+                .bias(true)
+                .device(device.clone())
+                .build()
+                .unwrap();
+            let x = ScalarArcTensor::from(
+                Tensor::from(Array::from_shape_fn([5, 4], |(i, j)| (i * 4 + j) as f32))
+                    .into_device(device.clone())
+                    .unwrap(),
+            );
+            let grads = |layer: &mut dyn FnMut(Variable2) -> Variable2| {
+                let x = Variable::builder().node().build(x.clone());
+                let y = layer(x.clone());
+                y.node().unwrap().backward().unwrap();
+                x.node().unwrap().grad().unwrap()
+            };
+            let mut dense1 = dense.clone();
+            dense1.init_parameter_grads().unwrap();
+            let dx1 = grads(&mut |x| dense1.forward(x).unwrap());
+            let mut dense2 = Checkpointed::new(dense);
+            dense2.init_parameter_grads().unwrap();
+            let dx2 = grads(&mut |x| dense2.forward(x).unwrap());
+            check_approx_eq(dx1.view().into_dyn(), dx2.view().into_dyn(), None);
+            let mut parameter_grads1 = Vec::new();
+            dense1.for_each_parameter(|p| parameter_grads1.push(p.grad().unwrap()));
+            let mut parameter_grads2 = Vec::new();
+            dense2.for_each_parameter(|p| parameter_grads2.push(p.grad().unwrap()));
+            assert_eq!(parameter_grads1.len(), parameter_grads2.len());
+            for (dw1, dw2) in parameter_grads1.iter().zip(parameter_grads2.iter()) {
+                check_approx_eq(dw1.view(), dw2.view(), None);
+            }
+        }
+
+        fn concurrent_inference(device: &Device) {
+            #[derive(Layer, Forward)]
+            #[autograph(forward(Variable4, Output=Variable2))]
+            struct Model {
+                conv: Conv2,
+                relu: Relu,
+                flatten: Flatten,
+                dense: Dense,
+            }
+            let model = Model {
+                conv: Conv2::builder()
+                    .inputs(1)
+                    .outputs(2)
+                    .filter([3, 3])
+                    .bias(true)
+                    .seed(0)
+                    .device(device.clone())
+                    .build()
+                    .unwrap(),
+                relu: Relu,
+                flatten: Flatten::default(),
+                dense: Dense::builder()
+                    .inputs(2 * 6 * 6)
+                    .outputs(10)
+                    .bias(true)
+                    .seed(1)
+                    .device(device.clone())
+                    .build()
+                    .unwrap(),
+            };
+            let x = Tensor::from(Array::from_shape_fn([4, 1, 8, 8], |(n, _, h, w)| {
+                (n * 64 + h * 8 + w) as f32 / 256.
+            }))
+            .into_device(device.clone())
+            .unwrap();
+            let x = ScalarArcTensor::from(x);
+            let y = model
+                .forward_values(x.view())
+                .unwrap()
+                .into_device(Device::host())
+                .unwrap();
+            std::thread::scope(|scope| {
+                for _ in 0..4 {
+                    scope.spawn(|| {
+                        for _ in 0..4 {
+                            let y2 = model
+                                .forward_values(x.view())
+                                .unwrap()
+                                .into_device(Device::host())
+                                .unwrap();
+                            check_eq(y.view().into_dyn(), y2.view().into_dyn());
+                        }
+                    });
+                }
+            });
+        }
+
+        fn backward_for(device: &Device) {
+            let mut dense = Dense::builder()
+                .inputs(4)
+                .outputs(3)
+                .bias(true)
+                .device(device.clone())
+                .build()
+                .unwrap();
+            dense.init_parameter_grads().unwrap();
+            let x = ScalarArcTensor::from(
+                Tensor::from(Array::from_shape_fn([2, 4], |(i, j)| (i * 4 + j) as f32))
+                    .into_device(device.clone())
+                    .unwrap(),
+            );
+            let parameter_grads = |dense: &Dense| {
+                let mut grads = Vec::new();
+                dense.for_each_parameter(|p| grads.push(p.grad().is_some()));
+                grads
+            };
+            {
+                let x = Variable::builder().node().build(x.clone());
+                let y = dense.forward(x.clone()).unwrap();
+                y.node()
+                    .unwrap()
+                    .backward_for(&[x.clone().into_dyn()])
+                    .unwrap();
+                assert!(x.node().unwrap().grad().is_some());
+                assert_eq!(parameter_grads(&dense), [false, false]);
+            }
+            {
+                let mut weight = None;
+                dense.for_each_parameter(|p| {
+                    weight.get_or_insert(p.to_variable());
+                });
+                let x = Variable::builder().node().build(x.clone());
+                let y = dense.forward(x.clone()).unwrap();
+                y.node().unwrap().backward_for(&[weight.unwrap()]).unwrap();
+                assert!(x.node().unwrap().grad().is_none());
+                assert_eq!(parameter_grads(&dense), [true, false]);
+            }
+        }
+
+        fn triplet_margin_loss(device: &Device) {
+            use autograph::learn::criterion::{
+                Distance, Reduction, TripletMarginLoss, TripletMarginLossOptions,
+            };
+
+            let arrays: [Array2<f32>; 3] = [
+                Array::from_shape_vec([2, 3], vec![1., 2., 0., -1., 0.5, 1.]).unwrap(),
+                Array::from_shape_vec([2, 3], vec![0., 1., 1., -2., 1., 0.]).unwrap(),
+                Array::from_shape_vec([2, 3], vec![1., 1.5, 0.5, -1., 1., 1.5]).unwrap(),
+            ];
+            for distance in [Distance::L2, Distance::Cosine] {
+                for reduction in [Reduction::Mean, Reduction::Sum] {
+                    let options = TripletMarginLossOptions {
+                        margin: 1.,
+                        distance,
+                        reduction,
+                    };
+                    let [anchor, positive, negative] = arrays.clone().map(|x| {
+                        Variable::from(Tensor::from(x).into_device(device.clone()).unwrap())
+                            .requires_grad()
+                    });
+                    let loss = anchor
+                        .triplet_margin_loss(positive.clone(), negative.clone(), &options)
+                        .unwrap();
+                    loss.backward().unwrap();
+                    let loss_fn = |arrays: &[Array2<f32>; 3]| {
+                        let [anchor, positive, negative] = arrays.clone().map(Tensor::from);
+                        anchor
+                            .triplet_margin_loss(positive, negative, &options)
+                            .unwrap()
+                    };
+                    let loss_value = loss
+                        .value()
+                        .to_device(Device::host())
+                        .unwrap()
+                        .cast_into_tensor::<f32>()
+                        .unwrap()
+                        .into_array()
+                        .unwrap()
+                        .into_scalar();
+                    assert_relative_eq!(loss_value, loss_fn(&arrays), epsilon = 1e-5);
+                    let grads = [anchor, positive, negative].map(|x| {
+                        x.grad()
+                            .unwrap()
+                            .to_device(Device::host())
+                            .unwrap()
+                            .try_into_tensor::<f32>()
+                            .unwrap()
+                            .into_array()
+                            .unwrap()
+                    });
+                    let h = 1e-2;
+                    for (i, grad) in grads.iter().enumerate() {
+                        let mut grad_numeric = Array2::<f32>::zeros([2, 3]);
+                        for (index, dx) in grad_numeric.indexed_iter_mut() {
+                            let mut arrays_plus = arrays.clone();
+                            arrays_plus[i][index] += h;
+                            let mut arrays_minus = arrays.clone();
+                            arrays_minus[i][index] -= h;
+                            *dx = (loss_fn(&arrays_plus) - loss_fn(&arrays_minus)) / (2. * h);
                         }
+                        assert_relative_eq!(*grad, grad_numeric, epsilon = 1e-2);
                     }
+                }
+            }
+        }
+
+        fn mse_loss_backward(device: &Device) {
+            use autograph::learn::criterion::MeanSquaredError;
+
+            let x_array =
+                Array::from_shape_fn([4, 3, 2], |(i, j, k)| ((i * 6 + j * 2 + k) % 5) as f32 / 2.);
+            let t_array =
+                Array::from_shape_fn([4, 3, 2], |(i, j, k)| ((i + j * 3 + k * 5) % 4) as f32 / 3.);
+            let x = Variable::builder().node().build(
+                ScalarTensor::from(
+                    Tensor::from(x_array.clone())
+                        .into_device(device.clone())
+                        .unwrap(),
+                )
+                .into_shared()
+                .unwrap(),
+            );
+            let t = ScalarTensor::from(
+                Tensor::from(t_array.clone())
+                    .into_device(device.clone())
+                    .unwrap(),
+            )
+            .into_shared()
+            .unwrap();
+            let n = x_array.len() as f32;
+            let y = x.mse_loss(t).unwrap();
+            let y_value = y
+                .value()
+                .clone()
+                .into_device(Device::host())
+                .unwrap()
+                .try_into_tensor::<f32>()
+                .unwrap()
+                .into_array()
+                .unwrap()
+                .into_scalar();
+            let diff = &x_array - &t_array;
+            assert_relative_eq!(y_value, diff.mapv(|d| d * d).sum() / n, epsilon = 1e-6);
+            y.backward().unwrap();
+            let dx = x
+                .grad()
+                .unwrap()
+                .into_device(Device::host())
+                .unwrap()
+                .try_into_tensor::<f32>()
+                .unwrap()
+                .into_array()
+                .unwrap();
+            assert_relative_eq!(dx, diff.mapv(|d| 2. * d / n), epsilon = 1e-6);
+        }
+
+        fn mse_loss_reduction(device: &Device) {
+            use autograph::learn::criterion::{MeanSquaredError, Reduction};
+
+            let x_array = Array::from_shape_fn([4, 3], |(i, j)| ((i * 3 + j) % 5) as f32 / 2.);
+            let t_array = Array::from_shape_fn([4, 3], |(i, j)| ((i + j * 3) % 4) as f32 / 3.);
+            let t = ScalarTensor::from(
+                Tensor::from(t_array.clone())
+                    .into_device(device.clone())
+                    .unwrap(),
+            )
+            .into_shared()
+            .unwrap();
+            let to_array = |x: ScalarArcTensorD| {
+                x.into_device(Device::host())
+                    .unwrap()
+                    .cast_into_tensor::<f32>()
+                    .unwrap()
+                    .into_array()
+                    .unwrap()
+            };
+            let diff = &x_array - &t_array;
+            let loss_true = diff.mapv(|d| d * d);
+            let dx_true = diff.mapv(|d| 2. * d);
+            for reduction in [Reduction::Mean, Reduction::Sum, Reduction::None] {
+                let x = Variable::builder().node().build(
+                    ScalarTensor::from(
+                        Tensor::from(x_array.clone())
+                            .into_device(device.clone())
+                            .unwrap(),
+                    )
+                    .into_shared()
+                    .unwrap(),
+                );
+                let y = x.mse_loss_with(t.clone(), reduction).unwrap();
+                let y_array = to_array(y.value().clone());
+                let (y_true, scale) = match reduction {
+                    Reduction::Mean => (
+                        Array::from_elem((), loss_true.mean().unwrap()).into_dyn(),
+                        1. / 12.,
+                    ),
+                    Reduction::Sum => (Array::from_elem((), loss_true.sum()).into_dyn(), 1.),
+                    Reduction::None => (loss_true.clone().into_dyn(), 1.),
+                };
+                assert_relative_eq!(y_array, y_true, epsilon = 1e-5);
+                let dy = ScalarTensor::ones(device.clone(), y.raw_dim(), ScalarType::F32)
+                    .unwrap()
+                    .into_shared()
+                    .unwrap();
+                y.node().unwrap().backward_grad(dy).unwrap();
+                let dx = to_array(x.grad().unwrap().into_dyn());
+                assert_relative_eq!(dx, (&dx_true * scale).into_dyn(), epsilon = 1e-5);
+                let y_tensor = x.value().mse_loss_with(t.clone(), reduction).unwrap();
+                assert_relative_eq!(
+                    y_tensor
+                        .into_device(Device::host())
+                        .unwrap()
+                        .into_array()
+                        .unwrap(),
+                    y_true,
+                    epsilon = 1e-5
+                );
+            }
+        }
+
+        fn l1_loss_backward(device: &Device) {
+            use autograph::learn::criterion::MeanAbsoluteError;
+
+            let x_array = Array::from_shape_fn([4, 5], |(i, j)| ((i * 5 + j) % 7) as f32 / 2.);
+            let t_array = Array::from_shape_fn([4, 5], |(i, j)| ((i + j * 3) % 5) as f32 / 2.);
+            let x = Variable::builder().node().build(
+                ScalarTensor::from(
+                    Tensor::from(x_array.clone())
+                        .into_device(device.clone())
+                        .unwrap(),
+                )
+                .into_shared()
+                .unwrap(),
+            );
+            let t = ScalarTensor::from(
+                Tensor::from(t_array.clone())
+                    .into_device(device.clone())
+                    .unwrap(),
+            )
+            .into_shared()
+            .unwrap();
+            let y = x.l1_loss(t).unwrap();
+            y.backward().unwrap();
+            let dx = x
+                .grad()
+                .unwrap()
+                .into_device(Device::host())
+                .unwrap()
+                .try_into_tensor::<f32>()
+                .unwrap()
+                .into_array()
+                .unwrap();
+            // finite differences, the subgradient is 0 where x == t
+            let loss = |x: &Array2<f32>| {
+                Tensor::from(x.clone())
+                    .l1_loss(Tensor::from(t_array.clone()))
+                    .unwrap()
+            };
+            let h = 1e-2;
+            let mut equal = 0;
+            for (index, dx) in dx.indexed_iter() {
+                if x_array[index] == t_array[index] {
+                    assert_eq!(*dx, 0.);
+                    equal += 1;
+                    continue;
+                }
+                let mut x1 = x_array.clone();
+                x1[index] += h;
+                let mut x2 = x_array.clone();
+                x2[index] -= h;
+                let dx_approx = (loss(&x1) - loss(&x2)) / (2. * h);
+                assert_relative_eq!(*dx, dx_approx, epsilon = 1e-3);
+            }
+            assert!(equal > 0);
+        }
+
+        fn huber_loss_continuity(device: &Device) {
+            use autograph::learn::criterion::HuberLoss;
+
+            let delta = 1.5f32;
+            let eps = 1e-3f32;
+            let loss_and_grad = |x: f32| {
+                let x = Variable::builder().node().build(
+                    ScalarTensor::from(
+                        Tensor::from(Array1::from(vec![x]))
+                            .into_device(device.clone())
+                            .unwrap(),
+                    )
+                    .into_shared()
+                    .unwrap(),
+                );
+                let t = ScalarTensor::from(
+                    Tensor::from(Array1::from(vec![0f32]))
+                        .into_device(device.clone())
+                        .unwrap(),
+                )
+                .into_shared()
+                .unwrap();
+                let y = x.huber_loss(t, delta).unwrap();
+                y.backward().unwrap();
+                let y = y
+                    .into_value()
+                    .into_device(Device::host())
+                    .unwrap()
+                    .try_into_tensor::<f32>()
+                    .unwrap()
+                    .into_array()
+                    .unwrap()
+                    .into_scalar();
+                let dx = x
+                    .grad()
+                    .unwrap()
+                    .into_device(Device::host())
+                    .unwrap()
+                    .try_into_tensor::<f32>()
+                    .unwrap()
+                    .into_array()
+                    .unwrap()[0];
+                (y, dx)
+            };
+            for sign in [1f32, -1.] {
+                let (y, dx) = loss_and_grad(sign * delta);
+                assert_relative_eq!(y, 0.5 * delta * delta, epsilon = 1e-6);
+                assert_relative_eq!(dx, sign * delta, epsilon = 1e-6);
+                let (y_inner, dx_inner) = loss_and_grad(sign * (delta - eps));
+                let (y_outer, dx_outer) = loss_and_grad(sign * (delta + eps));
+                // the value and the gradient are continuous at |x - t| = delta
+                assert_relative_eq!(y_inner, y, epsilon = 2. * delta * eps);
+                assert_relative_eq!(y_outer, y, epsilon = 2. * delta * eps);
+                assert_relative_eq!(dx_inner, dx, epsilon = 2. * eps);
+                assert_relative_eq!(dx_outer, dx, epsilon = 1e-6);
+                // quadratic inside, linear outside
+                assert_relative_eq!(dx_inner, sign * (delta - eps), epsilon = 1e-6);
+            }
+        }
+
+        fn bce_loss_backward(device: &Device) {
+            use autograph::learn::criterion::BinaryCrossEntropyLoss;
+
+            let x_array = Array::from_shape_fn([5, 3], |(i, j)| ((i * 3 + j) % 7) as f32 - 3.);
+            let t_array = Array::from_shape_fn([5, 3], |(i, j)| ((i + j * 2) % 3) as f32 / 2.);
+            let x = Variable::builder().node().build(
+                ScalarTensor::from(
+                    Tensor::from(x_array.clone())
+                        .into_device(device.clone())
+                        .unwrap(),
+                )
+                .into_shared()
+                .unwrap(),
+            );
+            let t = ScalarTensor::from(
+                Tensor::from(t_array.clone())
+                    .into_device(device.clone())
+                    .unwrap(),
+            )
+            .into_shared()
+            .unwrap();
+            let y = x.bce_loss(t).unwrap();
+            y.backward().unwrap();
+            let dx = x
+                .grad()
+                .unwrap()
+                .into_device(Device::host())
+                .unwrap()
+                .try_into_tensor::<f32>()
+                .unwrap()
+                .into_array()
+                .unwrap();
+            let n = x_array.len() as f32;
+            let dx_true = Array::from_shape_fn([5, 3], |(i, j)| {
+                (1. / (1. + (-x_array[(i, j)]).exp()) - t_array[(i, j)]) / n
+            });
+            assert_relative_eq!(dx, dx_true, epsilon = 1e-6);
+            // finite differences
+            let loss = |x: &Array2<f32>| {
+                Tensor::from(x.clone())
+                    .bce_loss(Tensor::from(t_array.clone()))
+                    .unwrap()
+            };
+            let h = 1e-2;
+            for (index, dx) in dx.indexed_iter() {
+                let mut x1 = x_array.clone();
+                x1[index] += h;
+                let mut x2 = x_array.clone();
+                x2[index] -= h;
+                let dx_approx = (loss(&x1) - loss(&x2)) / (2. * h);
+                assert_relative_eq!(*dx, dx_approx, epsilon = 1e-3);
+            }
+        }
+
+        fn info_nce_loss(device: &Device) {
+            use autograph::learn::criterion::InfoNCELoss;
+
+            let arrays: [Array2<f32>; 2] = [
+                Array::from_shape_vec([3, 2], vec![1., 2., -1., 0.5, 0., 1.]).unwrap(),
+                Array::from_shape_vec([3, 2], vec![1., 1.5, -2., 1., 0.5, 1.]).unwrap(),
+            ];
+            let temperature = 0.5;
+            let [anchor, positive] = arrays.clone().map(|x| {
+                Variable::from(Tensor::from(x).into_device(device.clone()).unwrap()).requires_grad()
+            });
+            let loss = anchor.info_nce_loss(positive.clone(), temperature).unwrap();
+            loss.backward().unwrap();
+            let loss_fn = |arrays: &[Array2<f32>; 2]| {
+                let [anchor, positive] = arrays.clone().map(Tensor::from);
+                anchor.info_nce_loss(positive, temperature).unwrap()
+            };
+            let loss_value = loss
+                .value()
+                .to_device(Device::host())
+                .unwrap()
+                .cast_into_tensor::<f32>()
+                .unwrap()
+                .into_array()
+                .unwrap()
+                .into_scalar();
+            assert_relative_eq!(loss_value, loss_fn(&arrays), epsilon = 1e-5);
+            let grads = [anchor, positive].map(|x| {
+                x.grad()
+                    .unwrap()
+                    .to_device(Device::host())
+                    .unwrap()
+                    .try_into_tensor::<f32>()
+                    .unwrap()
+                    .into_array()
+                    .unwrap()
+            });
+            let h = 1e-2;
+            for (i, grad) in grads.iter().enumerate() {
+                let mut grad_numeric = Array2::<f32>::zeros([3, 2]);
+                for (index, dx) in grad_numeric.indexed_iter_mut() {
+                    let mut arrays_plus = arrays.clone();
+                    arrays_plus[i][index] += h;
+                    let mut arrays_minus = arrays.clone();
+                    arrays_minus[i][index] -= h;
+                    *dx = (loss_fn(&arrays_plus) - loss_fn(&arrays_minus)) / (2. * h);
+                }
+                assert_relative_eq!(*grad, grad_numeric, epsilon = 1e-2);
+            }
+        }
+
+        fn adversarial_example(device: &Device) {
+            let dense = Dense::builder()
+                .inputs(4)
+                .outputs(3)
+                .bias(true)
+                .seed(0)
+                .device(device.clone())
+                .build()
+                .unwrap();
+            let x_array = Array::from_shape_fn([6, 4], |(i, j)| ((i * 4 + j) % 7) as f32 / 7.);
+            let t = ScalarArcTensor::from(
+                Tensor::from(Array::from_shape_fn(6, |i| (i % 3) as u8))
+                    .into_device(device.clone())
+                    .unwrap(),
+            );
+            let x = Variable::from(
+                Tensor::from(x_array.clone())
+                    .into_device(device.clone())
+                    .unwrap(),
+            )
+            .requires_grad();
+            let loss = dense
+                .forward(x.clone())
+                .unwrap()
+                .cross_entropy_loss(t.clone())
+                .unwrap();
+            loss.backward().unwrap();
+            let loss = loss
+                .value()
+                .to_device(Device::host())
+                .unwrap()
+                .cast_into_tensor::<f32>()
+                .unwrap()
+                .into_array()
+                .unwrap()
+                .into_scalar();
+            let dx = x
+                .grad()
+                .unwrap()
+                .to_device(Device::host())
+                .unwrap()
+                .try_into_tensor::<f32>()
+                .unwrap()
+                .into_array()
+                .unwrap();
+            // Fast Gradient Sign Method
+            let epsilon = 0.1;
+            let x_adv = x_array + dx.mapv(|dx| epsilon * dx.signum());
+            let x_adv = Tensor::from(x_adv).into_device(device.clone()).unwrap();
+            let loss_adv = dense
+                .forward_values(ScalarTensor::from(x_adv))
+                .unwrap()
+                .cross_entropy_loss(t.view())
+                .unwrap();
+            assert!(loss_adv > loss, "{loss_adv} <= {loss}");
+        }
+
+        fn variable_batch_size(device: &Device) {
+            #[derive(Layer, Forward)]
+            #[autograph(forward(Variable4, Output=Variable2))]
+            struct Model {
+                conv: Conv2,
+                relu: Relu,
+                pool: MaxPool2,
+                flatten: Flatten,
+                dense: Dense,
+            }
+            let model = Model {
+                conv: Conv2::builder()
+                    .inputs(1)
+                    .outputs(2)
+                    .filter([3, 3])
+                    .bias(true)
+                    .seed(0)
+                    .device(device.clone())
+                    .build()
+                    .unwrap(),
+                relu: Relu,
+                pool: MaxPool2::builder().filter([2, 2]).build(),
+                flatten: Flatten::default(),
+                dense: Dense::builder()
+                    .inputs(2 * 3 * 3)
+                    .outputs(4)
+                    .bias(true)
+                    .seed(1)
+                    .device(device.clone())
+                    .build()
+                    .unwrap(),
+            };
+            let x = Array::from_shape_fn([8, 1, 8, 8], |(n, _, h, w)| {
+                ((n * 64 + h * 8 + w) % 17) as f32 / 17.
+            });
+            let forward = |x: Array4<f32>| {
+                let x = Tensor::from(x).into_device(device.clone()).unwrap();
+                model
+                    .forward_values(ScalarTensor::from(x))
+                    .unwrap()
+                    .into_device(Device::host())
+                    .unwrap()
+                    .try_into_tensor::<f32>()
+                    .unwrap()
+                    .into_array()
+                    .unwrap()
+            };
+            let y = forward(x.clone());
+            for batch_size in [1, 3, 5, 8, 3, 5, 1] {
+                let y2 = forward(x.slice(s![..batch_size, .., .., ..]).to_owned());
+                let y1 = y.slice(s![..batch_size, ..]).to_owned();
+                check_approx_eq(
+                    Tensor::from(y1).into_dyn().view().into(),
+                    Tensor::from(y2).into_dyn().view().into(),
+                    None,
+                );
+            }
+        }
+
+        fn derive_layer_generic(device: &Device) {
+            #[derive(Layer, Forward)]
+            #[autograph(forward(Variable2, Output=Variable2))]
+            struct Model<F, E>
+            where
+                F: Fn(Variable2) -> autograph::anyhow::Result<Variable2> + Send + Sync,
+            {
+                dense: Dense,
+                activation: Functional<F>,
+                #[autograph(skip)]
+                _m: std::marker::PhantomData<E>,
+            }
+            let model = Model::<_, ()> {
+                dense: Dense::builder()
+                    .inputs(4)
+                    .outputs(3)
+                    .bias(true)
+                    .seed(0)
+                    .device(device.clone())
+                    .build()
+                    .unwrap(),
+                activation: Functional::new(|x: Variable2| x.forward(&Relu)),
+                _m: std::marker::PhantomData,
+            };
+            let mut names = Vec::new();
+            model
+                .try_for_each_named_parameter(|name, _| {
+                    names.push(name.to_string());
+                    Ok::<_, ()>(())
+                })
+                .unwrap();
+            assert_eq!(names, ["dense.weight", "dense.bias"]);
+            let x = Tensor::from(Array::from_shape_fn([2, 4], |(i, j)| (i * 4 + j) as f32))
+                .into_device(device.clone())
+                .unwrap();
+            let x = ScalarArcTensor::from(x);
+            let y = model
+                .forward_values(x.view())
+                .unwrap()
+                .into_device(Device::host())
+                .unwrap();
+            let y_true = Relu
+                .forward(model.dense.forward(Variable::from(x)).unwrap())
+                .unwrap()
+                .into_value()
+                .into_device(Device::host())
+                .unwrap();
+            check_eq(y.view().into_dyn(), y_true.view().into_dyn());
+        }
+
+        fn forward_hook(device: &Device) {
+            #[derive(Layer, Forward)]
+            #[autograph(forward(Variable2, Output=Variable2))]
+            struct Model {
+                layers: Vec<Dense>,
+                relu: Relu,
+            }
+            let dense = |inputs, outputs| {
+                Dense::builder()
+                    .inputs(inputs)
+                    .outputs(outputs)
+                    .seed(0)
+                    .device(device.clone())
+                    .build()
+                    .unwrap()
+            };
+            let mut model = Model {
+                layers: vec![dense(4, 3), dense(3, 2)],
+                relu: Relu,
+            };
+            let features = Arc::new(std::sync::Mutex::new(Vec::new()));
+            model
+                .register_forward_hook("layers.0", {
+                    let features = features.clone();
+                    Arc::new(move |output: &VariableD| {
+                        features.lock().unwrap().push(output.value().clone());
+                    })
+                })
+                .unwrap();
+            assert!(model
+                .register_forward_hook("layers.2", Arc::new(|_: &VariableD| ()))
+                .is_err());
+            assert!(model
+                .register_forward_hook("relu", Arc::new(|_: &VariableD| ()))
+                .is_err());
+            let x = Tensor::from(Array::from_shape_fn([2, 4], |(i, j)| (i * 4 + j) as f32))
+                .into_device(device.clone())
+                .unwrap();
+            let x = ScalarArcTensor::from(x);
+            model.forward_values(x.view()).unwrap();
+            let y = model.layers[0]
+                .forward_values(x.view())
+                .unwrap()
+                .into_device(Device::host())
+                .unwrap();
+            let features = features.lock().unwrap();
+            assert_eq!(features.len(), 1);
+            let y_hook = features[0].to_device(Device::host()).unwrap();
+            check_eq(y.view().into_dyn(), y_hook.view());
+            let mut relu = Hooked::new(Relu);
+            let relu_features = Arc::new(std::sync::Mutex::new(Vec::new()));
+            relu.register_forward_hook("", {
+                let relu_features = relu_features.clone();
+                Arc::new(move |output: &VariableD| {
+                    relu_features.lock().unwrap().push(output.value().clone());
+                })
+            })
+            .unwrap();
+            assert!(relu
+                .register_forward_hook("0", Arc::new(|_: &VariableD| ()))
+                .is_err());
+            let y = relu
+                .forward_values(x.view())
+                .unwrap()
+                .into_device(Device::host())
+                .unwrap();
+            let relu_features = relu_features.lock().unwrap();
+            assert_eq!(relu_features.len(), 1);
+            let y_hook = relu_features[0].to_device(Device::host()).unwrap();
+            check_eq(y.view().into_dyn(), y_hook.view());
+        }
+
+        #[cfg(feature = "serde")]
+        fn layer_save_load(device: &Device) {
+            #[derive(Layer)]
+            struct LeNet5 {
+                conv1: Conv2,
+                relu1: Relu,
+                pool1: MaxPool2,
+                conv2: Conv2,
+                relu2: Relu,
+                pool2: MaxPool2,
+                flatten: Flatten,
+                dense1: Dense,
+                relu3: Relu,
+                dense2: Dense,
+                relu4: Relu,
+                dense3: Option<Dense>,
+            }
+            let lenet5 = |seed| {
+                let conv2 = |inputs, outputs, seed| {
+                    Conv2::builder()
+                        .inputs(inputs)
+                        .outputs(outputs)
+                        .filter([5, 5])
+                        .bias(true)
+                        .seed(seed)
+                        .device(device.clone())
+                        .build()
+                        .unwrap()
+                };
+                let dense = |inputs, outputs, seed| {
+                    Dense::builder()
+                        .inputs(inputs)
+                        .outputs(outputs)
+                        .bias(true)
+                        .seed(seed)
+                        .device(device.clone())
+                        .build()
+                        .unwrap()
+                };
+                LeNet5 {
+                    conv1: conv2(1, 6, seed),
+                    relu1: Relu,
+                    pool1: MaxPool2::builder().filter([2, 2]).build(),
+                    conv2: conv2(6, 16, seed + 1),
+                    relu2: Relu,
+                    pool2: MaxPool2::builder().filter([2, 2]).build(),
+                    flatten: Flatten::default(),
+                    dense1: dense(16 * 4 * 4, 128, seed + 2),
+                    relu3: Relu,
+                    dense2: dense(128, 84, seed + 3),
+                    relu4: Relu,
+                    dense3: Some(dense(84, 10, seed + 4)),
+                }
+            };
+            let parameters = |model: &LeNet5| {
+                let mut parameters = Vec::new();
+                model
+                    .try_for_each_named_parameter(|name, parameter| {
+                        let value = parameter.value().to_device(Device::host())?;
+                        parameters.push((name.to_string(), value));
+                        Ok::<_, anyhow::Error>(())
+                    })
+                    .unwrap();
+                parameters
+            };
+            let model = lenet5(0);
+            let names: Vec<_> = parameters(&model)
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect();
+            assert_eq!(
+                names,
+                [
+                    "conv1.weight",
+                    "conv1.bias",
+                    "conv2.weight",
+                    "conv2.bias",
+                    "dense1.weight",
+                    "dense1.bias",
+                    "dense2.weight",
+                    "dense2.bias",
+                    "dense3.weight",
+                    "dense3.bias",
+                ]
+            );
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("lenet5.bin");
+            model.save(&path).unwrap();
+            let mut model2 = lenet5(10);
+            model2.load(&path).unwrap();
+            for ((name, a), (name2, b)) in parameters(&model).iter().zip(parameters(&model2).iter())
+            {
+                assert_eq!(name, name2);
+                check_eq(a.view(), b.view());
+            }
+            let mut model3 = lenet5(0);
+            model3.dense3 = None;
+            assert!(model3.load(&path).is_err());
+            let mut model4 = lenet5(0);
+            model4.dense3 = Some(
+                Dense::builder()
+                    .inputs(84)
+                    .outputs(2)
+                    .bias(true)
+                    .device(device.clone())
+                    .build()
+                    .unwrap(),
+            );
+            assert!(model4.load(&path).is_err());
+        }
+
+        fn backward_hook(device: &Device) {
+            let dense = |inputs, outputs| {
+                Dense::builder()
+                    .inputs(inputs)
+                    .outputs(outputs)
+                    .seed(0)
+                    .device(device.clone())
+                    .build()
+                    .unwrap()
+            };
+            let mut model = vec![dense(4, 3), dense(3, 2)];
+            let x = ScalarArcTensor::from(
+                Tensor::from(Array::from_shape_fn([2, 4], |(i, j)| (i * 4 + j) as f32))
+                    .into_device(device.clone())
+                    .unwrap(),
+            );
+            let dy = ScalarArcTensor::from(
+                Tensor::from(Array::from_shape_fn([2, 2], |(i, j)| (i * 2 + j) as f32))
+                    .into_device(device.clone())
+                    .unwrap(),
+            );
+            let dy0 = {
+                let y0 = model[0]
+                    .forward_values(x.view())
+                    .unwrap()
+                    .into_shared()
+                    .unwrap();
+                let y0 = Variable::builder().node().build(y0);
+                let y = model[1].forward(y0.clone()).unwrap();
+                y.node().unwrap().backward_grad(dy.clone()).unwrap();
+                y0.node().unwrap().grad().unwrap()
+            };
+            let grads = Arc::new(std::sync::Mutex::new(Vec::new()));
+            for name in ["1", "0"] {
+                let grads = grads.clone();
+                model
+                    .register_backward_hook(
+                        name,
+                        Arc::new(move |grad: &ScalarArcTensorD| {
+                            grads.lock().unwrap().push(grad.clone());
+                        }),
+                    )
+                    .unwrap();
+            }
+            let x = Variable::builder().node().build(x);
+            let y = model.forward(x).unwrap();
+            y.node().unwrap().backward_grad(dy.clone()).unwrap();
+            let grads = grads.lock().unwrap();
+            assert_eq!(grads.len(), 2);
+            check_approx_eq(dy.view().into_dyn(), grads[0].view(), None);
+            check_approx_eq(dy0.view().into_dyn(), grads[1].view(), None);
+            let mut relu = Hooked::new(Relu);
+            let relu_grads = Arc::new(std::sync::Mutex::new(Vec::new()));
+            relu.register_backward_hook("", {
+                let relu_grads = relu_grads.clone();
+                Arc::new(move |grad: &ScalarArcTensorD| {
+                    relu_grads.lock().unwrap().push(grad.clone());
+                })
+            })
+            .unwrap();
+            let y = relu.forward(Variable::builder().node().build(dy.clone())).unwrap();
+            y.node().unwrap().backward_grad(dy.clone()).unwrap();
+            let relu_grads = relu_grads.lock().unwrap();
+            assert_eq!(relu_grads.len(), 1);
+            check_approx_eq(dy.view().into_dyn(), relu_grads[0].view(), None);
+        }
+
+        fn dense_init(device: &Device) {
+            let weight = |init: Init, seed: u64| {
+                let dense = Dense::builder()
+                    .inputs(100)
+                    .outputs(1000)
+                    .init_weight(init)
+                    .seed(seed)
+                    .device(device.clone())
+                    .build()
+                    .unwrap();
+                let mut weight = None;
+                dense.for_each_parameter(|p| {
+                    weight.replace(
+                        p.value()
+                            .to_device(Device::host())
+                            .unwrap()
+                            .try_into_tensor::<f32>()
+                            .unwrap()
+                            .into_array()
+                            .unwrap(),
+                    );
                 });
+                weight.unwrap()
+            };
+            for (init, mean, std) in [
+                (Init::Normal { mean: 1., std: 2. }, 1., 2.),
+                (Init::Uniform { low: -1., high: 3. }, 1., 4. / 12f32.sqrt()),
+            ] {
+                let w = weight(init, 0);
+                assert_relative_eq!(w.mean().unwrap(), mean, epsilon = 0.05);
+                assert_relative_eq!(w.std(0.), std, epsilon = 0.05);
+                assert_eq!(w, weight(init, 0));
+                assert_ne!(w, weight(init, 1));
             }
-            macro_for!($T in [bf16, f32] {
-                let ignore = device.is_device()
-                && !features.contains(features_for_scalar($T::SCALAR_TYPE));
-                let input_shapes = [
-                    [1, 1, 4, 4],
-                    [1, 1, 12, 12],
-                    [2, 3, 4, 4],
-                    [1, 1, 24, 24],
-                ];
-                tests.extend([
-                    device_test(device, &format!("max_pool2_{}", $T::SCALAR_TYPE.name()), move |device| {
-                        let pool = MaxPool2::builder().filter([2, 2]).build();
-                        for input_shape in input_shapes {
-                            max_pool2::<$T>(device, input_shape, &pool);
-                        }
-                    }).with_ignored_flag(ignore),
-                    device_test(device, &format!("max_pool2_backward_{}", $T::SCALAR_TYPE.name()), move |device| {
-                        let pool = MaxPool2::builder().filter([2, 2]).build();
-                        for input_shape in input_shapes {
-                            max_pool2_backward::<$T>(device, input_shape, &pool);
-                        }
-                    }).with_ignored_flag(ignore),
-                ]);
+            assert!(weight(Init::Zeros, 0).iter().all(|x| *x == 0.));
+            assert!(weight(Init::Constant { value: 0.5 }, 0)
+                .iter()
+                .all(|x| *x == 0.5));
+            assert!(Dense::builder()
+                .inputs(1)
+                .outputs(1)
+                .init_weight(Init::Uniform { low: 1., high: 1. })
+                .device(device.clone())
+                .build()
+                .is_err());
+        }
+
+        fn layer_features(device: &Device) {
+            use autograph::learn::neural_network::layer::{Conv1, ConvTranspose2};
+
+            let mut dense = Dense::builder()
+                .inputs(3)
+                .outputs(5)
+                .device(device.clone())
+                .build()
+                .unwrap();
+            assert_eq!(dense.in_features(), 3);
+            assert_eq!(dense.out_features(), 5);
+            // [inputs, outputs]
+            assert_eq!(dense.weight_view_mut().unwrap().shape(), [3, 5]);
+            let mut conv = Conv2::builder()
+                .inputs(2)
+                .outputs(4)
+                .filter([3, 3])
+                .device(device.clone())
+                .build()
+                .unwrap();
+            assert_eq!(conv.in_channels(), 2);
+            assert_eq!(conv.out_channels(), 4);
+            // [outputs, inputs, fh, fw]
+            assert_eq!(conv.weight_view_mut().unwrap().shape(), [4, 2, 3, 3]);
+            let conv = Conv1::builder()
+                .inputs(6)
+                .outputs(1)
+                .filter([2])
+                .device(device.clone())
+                .build()
+                .unwrap();
+            assert_eq!(conv.in_channels(), 6);
+            assert_eq!(conv.out_channels(), 1);
+            let mut conv = ConvTranspose2::builder()
+                .inputs(3)
+                .outputs(2)
+                .filter([3, 3])
+                .device(device.clone())
+                .build()
+                .unwrap();
+            assert_eq!(conv.in_channels(), 3);
+            assert_eq!(conv.out_channels(), 2);
+            // [inputs, outputs, fh, fw]
+            assert_eq!(conv.weight_view_mut().unwrap().shape(), [3, 2, 3, 3]);
+        }
+
+        fn maxout(device: &Device) {
+            use autograph::learn::neural_network::layer::Maxout;
+            use ndarray::{Ix1, Ix2};
+
+            let (batch_size, inputs, outputs, num_pieces) = (4, 3, 2, 3);
+            let maxout = Maxout::builder()
+                .inputs(inputs)
+                .outputs(outputs)
+                .num_pieces(num_pieces)
+                .bias(true)
+                .init_bias(Init::Uniform { low: -1., high: 1. })
+                .seed(0)
+                .device(device.clone())
+                .build()
+                .unwrap();
+            assert_eq!(maxout.out_features(), outputs);
+            let mut parameters = Vec::new();
+            maxout.for_each_parameter(|p| {
+                parameters.push(
+                    p.value()
+                        .to_device(Device::host())
+                        .unwrap()
+                        .try_into_tensor::<f32>()
+                        .unwrap()
+                        .into_array()
+                        .unwrap(),
+                );
             });
-            macro_for!($T in [bf16, f32] {
-                let ignore = device.is_device()
-                && !features.contains(features_for_scalar($T::SCALAR_TYPE));
-                let input_shapes = [[1, 8], [15, 20]];
-                tests.extend([
-                    device_test(device, &format!("relu_{}", $T::SCALAR_TYPE.name()), move |device| {
-                        for input_shape in input_shapes {
-                            relu::<$T>(device, input_shape);
-                        }
-                    }).with_ignored_flag(ignore),
-                    device_test(device, &format!("relu_backward_{}", $T::SCALAR_TYPE.name()), move |device| {
-                        for input_shape in input_shapes {
-                            relu_backward::<$T>(device, input_shape);
-                        }
-                    }).with_ignored_flag(ignore),
-                ]);
+            let weight = parameters[0].clone().into_dimensionality::<Ix2>().unwrap();
+            let bias = parameters[1].clone().into_dimensionality::<Ix1>().unwrap();
+            let x_array = Array::from_shape_fn([batch_size, inputs], |(i, j)| {
+                ((i * inputs + j) * 7 % 11) as f32 / 5. - 1.
             });
-            tests.extend([device_test(device, "broadcast", move |device| {
-                broadcast(device, [2], [4, 2]);
-                broadcast(device, [2], [4, 3, 2]);
-                broadcast(device, [2], [5, 4, 3, 2]);
-                broadcast(device, [2], [6, 5, 4, 3, 2]);
-                broadcast(device, [2], [7, 6, 5, 4, 3, 2]);
-                broadcast(device, [3, 2], [5, 4, 3, 2]);
-                broadcast(device, [4, 1, 1, 3], [4, 2, 1, 3]);
-            })]);
-            tests
+            let x = Variable::builder().node().build(
+                ScalarTensor::from(
+                    Tensor::from(x_array.clone())
+                        .into_device(device.clone())
+                        .unwrap(),
+                )
+                .into_shared()
+                .unwrap(),
+            );
+            let y = x.clone().forward(&maxout).unwrap();
+            let dy = ScalarArcTensor::ones(device.clone(), [batch_size, outputs], ScalarType::F32)
+                .unwrap();
+            y.node().unwrap().backward_grad(dy).unwrap();
+            let z = x_array.dot(&weight) + &bias;
+            let mut y_true = Array2::<f32>::zeros([batch_size, outputs]);
+            let mut dx_true = Array2::<f32>::zeros([batch_size, inputs]);
+            for i in 0..batch_size {
+                for j in 0..outputs {
+                    let (k, z) = (0..num_pieces)
+                        .map(|k| (k, z[(i, j * num_pieces + k)]))
+                        .reduce(|a, b| if b.1 > a.1 { b } else { a })
+                        .unwrap();
+                    y_true[(i, j)] = z;
+                    let mut dx = dx_true.row_mut(i);
+                    dx += &weight.column(j * num_pieces + k);
+                }
+            }
+            let y = y
+                .into_value()
+                .into_device(Device::host())
+                .unwrap()
+                .try_into_tensor::<f32>()
+                .unwrap()
+                .into_array()
+                .unwrap();
+            assert_relative_eq!(y, y_true, epsilon = 1e-5);
+            let dx = x
+                .grad()
+                .unwrap()
+                .into_device(Device::host())
+                .unwrap()
+                .try_into_tensor::<f32>()
+                .unwrap()
+                .into_array()
+                .unwrap();
+            assert_relative_eq!(dx, dx_true, epsilon = 1e-5);
         }
 
-        fn cross_entropy_loss_backward<X: Scalar + Float, T: Scalar + Unsigned>(
-            device: &Device,
-            batch_size: usize,
-            classes: usize,
-        ) {
-            use neural_network::__private::criterion::cross_entropy_loss_backward as backward;
-            let x_vec: Vec<X> = (0..10u8)
-                .map(|x| X::from_u8(x).unwrap())
-                .cycle()
-                .take(batch_size * classes)
-                .collect();
-            let t_vec: Vec<T> = (0..classes)
-                .cycle()
-                .map(|t| T::from_usize(t).unwrap())
-                .take(batch_size)
-                .collect();
-            let x_array = Array::from(x_vec)
-                .into_shape([batch_size, classes])
+        fn sigmoid_backward(device: &Device) {
+            use autograph::learn::neural_network::layer::Sigmoid;
+
+            let x_array = Array::from_shape_fn([3, 4], |(i, j)| (i * 4 + j) as f32 / 3. - 2.);
+            let dy_array = Array::from_shape_fn([3, 4], |(i, j)| ((i + 2 * j) % 5) as f32 - 2.);
+            let to_device = |x: &Array2<f32>| {
+                ScalarTensor::from(Tensor::from(x.clone()).into_device(device.clone()).unwrap())
+                    .into_shared()
+                    .unwrap()
+            };
+            let to_array = |x: ScalarArcTensor<ndarray::Ix2>| {
+                x.into_device(Device::host())
+                    .unwrap()
+                    .try_into_tensor::<f32>()
+                    .unwrap()
+                    .into_array()
+                    .unwrap()
+            };
+            let x = Variable::builder().node().build(to_device(&x_array));
+            let y = x.clone().forward(&Sigmoid).unwrap();
+            assert_relative_eq!(
+                to_array(y.value().clone()),
+                x_array.mapv(|x| 1. / (1. + (-x).exp())),
+                epsilon = 1e-6
+            );
+            y.node()
+                .unwrap()
+                .backward_grad(to_device(&dy_array))
                 .unwrap();
-            let t_array = Array::from(t_vec);
-            let x_host = Tensor::from(x_array);
-            let t_host = Tensor::from(t_array);
-            let x_device = x_host.to_device(device.clone()).unwrap();
-            let t_device = t_host.to_device(device.clone()).unwrap();
-            let dy = 1f32;
-            let dx_host = backward(x_host.view(), t_host.view(), dy)
+            let dx = to_array(x.grad().unwrap());
+            let h = 1e-2;
+            let loss = |x_array: &Array2<f32>| {
+                let y = to_array(
+                    Variable::from(to_device(x_array))
+                        .forward(&Sigmoid)
+                        .unwrap()
+                        .into_value(),
+                );
+                (y * &dy_array).sum()
+            };
+            let dx_numerical = Array::from_shape_fn(x_array.raw_dim(), |index| {
+                let mut x_plus = x_array.clone();
+                x_plus[index] += h;
+                let mut x_minus = x_array.clone();
+                x_minus[index] -= h;
+                (loss(&x_plus) - loss(&x_minus)) / (2. * h)
+            });
+            assert_relative_eq!(dx, dx_numerical, epsilon = 1e-3);
+        }
+
+        fn nll_loss(device: &Device) {
+            use autograph::learn::{criterion::NllLoss, neural_network::layer::LogSoftmax};
+
+            let x_array = Array::from_shape_fn([6, 4], |(i, j)| ((i * 4 + j) % 7) as f32 / 3.);
+            let t_array = Array1::from(vec![1u8, 0, 3, 2, 3, 1]);
+            let t = ScalarTensor::from(
+                Tensor::from(t_array.clone())
+                    .into_device(device.clone())
+                    .unwrap(),
+            )
+            .into_shared()
+            .unwrap();
+            let x = || {
+                Variable::builder().node().build(
+                    ScalarTensor::from(
+                        Tensor::from(x_array.clone())
+                            .into_device(device.clone())
+                            .unwrap(),
+                    )
+                    .into_shared()
+                    .unwrap(),
+                )
+            };
+            let to_array = |x: ScalarArcTensorD| {
+                x.into_device(Device::host())
+                    .unwrap()
+                    .try_into_tensor::<f32>()
+                    .unwrap()
+                    .into_array()
+                    .unwrap()
+            };
+            let log_softmax = LogSoftmax::default();
+            // log softmax + nll == cross entropy
+            let x_nll = x();
+            let y_nll = log_softmax
+                .forward(x_nll.clone())
                 .unwrap()
-                .into_dyn();
-            let dx_device = backward(x_device.view(), t_device.view(), dy)
+                .nll_loss(t.clone())
+                .unwrap();
+            y_nll.backward().unwrap();
+            let x_ce = x();
+            let y_ce = x_ce.cross_entropy_loss(t.clone()).unwrap();
+            y_ce.backward().unwrap();
+            assert_relative_eq!(
+                to_array(y_nll.value().clone().into_dyn()),
+                to_array(y_ce.value().clone().into_dyn()),
+                epsilon = 1e-5
+            );
+            assert_relative_eq!(
+                to_array(x_nll.grad().unwrap().into_dyn()),
+                to_array(x_ce.grad().unwrap().into_dyn()),
+                epsilon = 1e-5
+            );
+            // weighted
+            let w_array = Array1::from(vec![0.5f32, 2., 1., 0.25]);
+            let w = ScalarTensor::from(
+                Tensor::from(w_array.clone())
+                    .into_device(device.clone())
+                    .unwrap(),
+            );
+            let x = x();
+            let y = x.nll_loss_with_weight(t.clone(), Some(w.view())).unwrap();
+            y.backward().unwrap();
+            let total: f32 = t_array.iter().map(|t| w_array[*t as usize]).sum();
+            let y_true = -t_array
+                .iter()
+                .enumerate()
+                .map(|(i, t)| w_array[*t as usize] * x_array[(i, *t as usize)])
+                .sum::<f32>()
+                / total;
+            let mut dx_true = Array2::<f32>::zeros([6, 4]);
+            for (i, t) in t_array.iter().map(|t| *t as usize).enumerate() {
+                dx_true[(i, t)] = -w_array[t] / total;
+            }
+            assert_relative_eq!(
+                to_array(y.value().clone().into_dyn())
+                    .into_iter()
+                    .next()
+                    .unwrap(),
+                y_true,
+                epsilon = 1e-5
+            );
+            assert_relative_eq!(
+                to_array(x.grad().unwrap().into_dyn()),
+                dx_true.into_dyn(),
+                epsilon = 1e-5
+            );
+            let y_tensor = x
+                .value()
+                .nll_loss_with_weight(t.clone(), Some(w.view()))
+                .unwrap();
+            assert_relative_eq!(y_tensor, y_true, epsilon = 1e-5);
+            let t_invalid = ScalarTensor::from(
+                Tensor::from(Array1::from(vec![4u8; 6]))
+                    .into_device(device.clone())
+                    .unwrap(),
+            )
+            .into_shared()
+            .unwrap();
+            assert!(x.nll_loss(t_invalid).is_err());
+        }
+
+        fn softmax(device: &Device) {
+            use autograph::learn::neural_network::layer::Softmax;
+
+            let x_array = Array::from_shape_fn([3, 5], |(i, j)| (i * 5 + j) as f32 * 10. - 70.);
+            let dy_array = Array::from_shape_fn([3, 5], |(i, j)| ((i + 2 * j) % 5) as f32 - 2.);
+            let to_device = |x: &Array2<f32>| {
+                ScalarTensor::from(Tensor::from(x.clone()).into_device(device.clone()).unwrap())
+                    .into_shared()
+                    .unwrap()
+            };
+            let to_array = |x: ScalarArcTensor<ndarray::Ix2>| {
+                x.into_device(Device::host())
+                    .unwrap()
+                    .try_into_tensor::<f32>()
+                    .unwrap()
+                    .into_array()
+                    .unwrap()
+            };
+            for axis in [0, 1] {
+                let softmax = if axis == 1 {
+                    Softmax::default()
+                } else {
+                    Softmax::builder().axis(axis).build()
+                };
+                let x = Variable::builder().node().build(to_device(&x_array));
+                let y = x.clone().forward(&softmax).unwrap();
+                let y_array = to_array(y.value().clone());
+                let max = x_array.fold_axis(Axis(axis), f32::NEG_INFINITY, |a, b| a.max(*b));
+                let exp = (&x_array - &max.insert_axis(Axis(axis))).mapv(f32::exp);
+                let y_true = &exp / &exp.sum_axis(Axis(axis)).insert_axis(Axis(axis));
+                assert_relative_eq!(y_array, y_true, epsilon = 1e-6);
+                for sum in y_array.sum_axis(Axis(axis)) {
+                    assert_relative_eq!(sum, 1., epsilon = 1e-6);
+                }
+                y.node()
+                    .unwrap()
+                    .backward_grad(to_device(&dy_array))
+                    .unwrap();
+                let dx = to_array(x.grad().unwrap());
+                let dot = (&dy_array * &y_true)
+                    .sum_axis(Axis(axis))
+                    .insert_axis(Axis(axis));
+                let dx_true = &y_true * &(&dy_array - &dot);
+                assert_relative_eq!(dx, dx_true, epsilon = 1e-6);
+            }
+        }
+
+        fn batch_norm2(device: &Device) {
+            use autograph::learn::neural_network::layer::BatchNorm2;
+
+            let x_array = Array::from_shape_fn([2, 3, 2, 2], |(n, c, h, w)| {
+                ((n * 7 + c * 5 + h * 3 + w) % 11) as f32 / 4. - 1.
+            });
+            let dy_array = Array::from_shape_fn(x_array.raw_dim(), |(n, c, h, w)| {
+                ((n + 2 * c + 3 * h + w) % 5) as f32 - 2.
+            });
+            let to_device = |x: &Array4<f32>| {
+                ScalarTensor::from(Tensor::from(x.clone()).into_device(device.clone()).unwrap())
+                    .into_shared()
+                    .unwrap()
+            };
+            let to_array = |x: ScalarArcTensorD| {
+                x.into_device(Device::host())
+                    .unwrap()
+                    .try_into_tensor::<f32>()
+                    .unwrap()
+                    .into_array()
+                    .unwrap()
+            };
+            let mut batch_norm = BatchNorm2::builder()
+                .channels(3)
+                .momentum(0.5)
+                .device(device.clone())
+                .build()
+                .unwrap();
+            let epsilon = batch_norm.epsilon();
+            let len = 8.;
+            let mean = x_array
+                .axis_iter(Axis(1))
+                .map(|x| x.mean().unwrap())
+                .collect::<Array1<f32>>();
+            let var = x_array
+                .axis_iter(Axis(1))
+                .zip(mean.iter())
+                .map(|(x, mean)| x.mapv(|x| (x - mean).powi(2)).sum() / len)
+                .collect::<Array1<f32>>();
+            let shape = [1, 3, 1, 1];
+            let x_hat = (&x_array - &mean.view().into_shape(shape).unwrap())
+                / &var
+                    .mapv(|v| (v + epsilon).sqrt())
+                    .into_shape(shape)
+                    .unwrap();
+            batch_norm.init_parameter_grads().unwrap();
+            let x = Variable::builder().node().build(to_device(&x_array));
+            let y = batch_norm.forward(x.clone()).unwrap();
+            assert_relative_eq!(
+                to_array(y.value().clone().into_dyn()),
+                x_hat.clone().into_dyn(),
+                epsilon = 1e-5
+            );
+            let running_mean = &mean * 0.5;
+            let running_var = var.mapv(|v| 0.5 + 0.5 * v * len / (len - 1.));
+            assert_relative_eq!(batch_norm.running_mean().unwrap(), running_mean, epsilon = 1e-6);
+            assert_relative_eq!(batch_norm.running_var().unwrap(), running_var, epsilon = 1e-6);
+            y.node()
                 .unwrap()
-                .into_device(Device::host())
+                .backward_grad(to_device(&dy_array))
+                .unwrap();
+            let dx = to_array(x.grad().unwrap().into_dyn());
+            let mut grads = Vec::new();
+            batch_norm.for_each_parameter(|parameter| {
+                grads.push(to_array(parameter.grad().unwrap()));
+            });
+            let dgamma_true = (&dy_array * &x_hat)
+                .sum_axis(Axis(0))
+                .sum_axis(Axis(1))
+                .sum_axis(Axis(1));
+            let dbeta_true = dy_array
+                .sum_axis(Axis(0))
+                .sum_axis(Axis(1))
+                .sum_axis(Axis(1));
+            assert_relative_eq!(grads[0], dgamma_true.into_dyn(), epsilon = 1e-4);
+            assert_relative_eq!(grads[1], dbeta_true.into_dyn(), epsilon = 1e-4);
+            let h = 1e-2;
+            let loss = |x_array: &Array4<f32>| {
+                let y = to_array(
+                    batch_norm
+                        .forward(Variable::from(to_device(x_array)))
+                        .unwrap()
+                        .into_value()
+                        .into_dyn(),
+                );
+                (y * &dy_array.view().into_dyn()).sum()
+            };
+            let dx_numerical = Array::from_shape_fn(x_array.raw_dim(), |index| {
+                let mut x_plus = x_array.clone();
+                x_plus[index] += h;
+                let mut x_minus = x_array.clone();
+                x_minus[index] -= h;
+                (loss(&x_plus) - loss(&x_minus)) / (2. * h)
+            });
+            assert_relative_eq!(dx, dx_numerical.into_dyn(), epsilon = 1e-2);
+            batch_norm.set_training(false);
+            let running_mean = batch_norm.running_mean().unwrap();
+            let running_var = batch_norm.running_var().unwrap();
+            let y = to_array(
+                batch_norm
+                    .forward(Variable::from(to_device(&x_array)))
+                    .unwrap()
+                    .into_value()
+                    .into_dyn(),
+            );
+            let y_true = (&x_array - &running_mean.view().into_shape(shape).unwrap())
+                / &running_var
+                    .mapv(|v| (v + epsilon).sqrt())
+                    .into_shape(shape)
+                    .unwrap();
+            assert_relative_eq!(y, y_true.into_dyn(), epsilon = 1e-5);
+            assert_eq!(batch_norm.running_mean().unwrap(), running_mean);
+        }
+
+        fn layer_norm(device: &Device) {
+            use autograph::learn::neural_network::layer::LayerNorm;
+            use ndarray::{ArrayD, IxDyn};
+
+            let to_device = |x: &ArrayD<f32>| {
+                ScalarTensor::from(Tensor::from(x.clone()).into_device(device.clone()).unwrap())
+                    .into_shared()
+                    .unwrap()
+            };
+            let to_array = |x: ScalarArcTensorD| {
+                x.into_device(Device::host())
+                    .unwrap()
+                    .try_into_tensor::<f32>()
+                    .unwrap()
+                    .into_array()
+                    .unwrap()
+            };
+            let layer_norm = LayerNorm::builder()
+                .normalized_shape(4)
+                .device(device.clone())
+                .build()
+                .unwrap();
+            let x = Array::from_shape_vec([2, 4], vec![1f32, 2., 3., 4., 2., 2., 2., 2.])
                 .unwrap()
                 .into_dyn();
-            check_approx_eq(dx_host.view().into(), dx_device.view().into(), None);
+            let y = to_array(
+                layer_norm
+                    .forward(Variable::from(to_device(&x)))
+                    .unwrap()
+                    .into_value(),
+            );
+            let y_true = Array::from_shape_vec(
+                [2, 4],
+                vec![-1.3416355, -0.4472118, 0.4472118, 1.3416355, 0., 0., 0., 0.],
+            )
+            .unwrap()
+            .into_dyn();
+            assert_relative_eq!(y, y_true, epsilon = 1e-5);
+
+            let mut layer_norm = LayerNorm::builder()
+                .normalized_shape([3, 4])
+                .device(device.clone())
+                .build()
+                .unwrap();
+            let shape = IxDyn(&[2, 3, 4]);
+            let x_array = Array::from_shape_fn(shape.clone(), |i| {
+                ((i[0] * 7 + i[1] * 5 + i[2] * 3) % 11) as f32 / 4.
+            });
+            let dy_array =
+                Array::from_shape_fn(shape, |i| ((i[0] + 2 * i[1] + 3 * i[2]) % 5) as f32 - 2.);
+            layer_norm.init_parameter_grads().unwrap();
+            let x = Variable::builder().node().build(to_device(&x_array));
+            let y = layer_norm.forward(x.clone()).unwrap();
+            let x_hat = to_array(y.value().clone());
+            for x_hat in x_hat.outer_iter() {
+                assert_relative_eq!(x_hat.mean().unwrap(), 0., epsilon = 1e-5);
+                assert_relative_eq!(x_hat.mapv(|x| x * x).mean().unwrap(), 1., epsilon = 1e-3);
+            }
+            y.node()
+                .unwrap()
+                .backward_grad(to_device(&dy_array))
+                .unwrap();
+            let dx = to_array(x.grad().unwrap());
+            let mut grads = Vec::new();
+            layer_norm.for_each_parameter(|parameter| {
+                grads.push(to_array(parameter.grad().unwrap()));
+            });
+            assert_relative_eq!(
+                grads[0],
+                (&dy_array * &x_hat).sum_axis(Axis(0)),
+                epsilon = 1e-4
+            );
+            assert_relative_eq!(grads[1], dy_array.sum_axis(Axis(0)), epsilon = 1e-4);
+            let h = 1e-2;
+            let loss = |x_array: &ArrayD<f32>| {
+                let y = to_array(
+                    layer_norm
+                        .forward(Variable::from(to_device(x_array)))
+                        .unwrap()
+                        .into_value(),
+                );
+                (y * &dy_array).sum()
+            };
+            let dx_numerical = Array::from_shape_fn(x_array.raw_dim(), |index| {
+                let mut x_plus = x_array.clone();
+                x_plus[index.clone()] += h;
+                let mut x_minus = x_array.clone();
+                x_minus[index] -= h;
+                (loss(&x_plus) - loss(&x_minus)) / (2. * h)
+            });
+            assert_relative_eq!(dx, dx_numerical, epsilon = 1e-2);
+        }
+
+        fn embedding(device: &Device) {
+            use autograph::learn::neural_network::layer::Embedding;
+            use ndarray::Array3;
+
+            let mut embedding = Embedding::builder()
+                .num_embeddings(5)
+                .embedding_dim(3)
+                .padding_idx(1)
+                .seed(0)
+                .device(device.clone())
+                .build()
+                .unwrap();
+            let mut weight = None;
+            embedding.for_each_parameter(|parameter| {
+                weight.replace(
+                    parameter
+                        .value()
+                        .to_device(Device::host())
+                        .unwrap()
+                        .try_into_tensor::<f32>()
+                        .unwrap()
+                        .into_array()
+                        .unwrap()
+                        .into_dimensionality::<ndarray::Ix2>()
+                        .unwrap(),
+                );
+            });
+            let weight = weight.unwrap();
+            assert!(weight.row(1).iter().all(|x| *x == 0.));
+            let indices = Array::from_shape_vec([2, 3], vec![4u32, 1, 0, 4, 2, 1]).unwrap();
+            let y_true =
+                Array3::from_shape_fn([2, 3, 3], |(i, j, k)| weight[(indices[(i, j)] as usize, k)]);
+            let dy_array = Array3::from_shape_fn([2, 3, 3], |(i, j, k)| (i * 9 + j * 3 + k) as f32);
+            let mut dw_true = Array2::<f32>::zeros([5, 3]);
+            for ((i, j, k), dy) in dy_array.indexed_iter() {
+                let index = indices[(i, j)] as usize;
+                if index != 1 {
+                    dw_true[(index, k)] += dy;
+                }
+            }
+            embedding.init_parameter_grads().unwrap();
+            let x = Variable::from(
+                ScalarTensor::from(Tensor::from(indices).into_device(device.clone()).unwrap())
+                    .into_shared()
+                    .unwrap(),
+            );
+            let y = embedding.forward(x).unwrap();
+            let y_array = y
+                .value()
+                .to_device(Device::host())
+                .unwrap()
+                .try_into_tensor::<f32>()
+                .unwrap()
+                .into_array()
+                .unwrap();
+            assert_eq!(y_array, y_true);
+            let dy =
+                ScalarTensor::from(Tensor::from(dy_array).into_device(device.clone()).unwrap())
+                    .into_shared()
+                    .unwrap();
+            y.node().unwrap().backward_grad(dy).unwrap();
+            let mut dw = None;
+            embedding.for_each_parameter(|parameter| {
+                dw.replace(
+                    parameter
+                        .grad()
+                        .unwrap()
+                        .into_device(Device::host())
+                        .unwrap()
+                        .try_into_tensor::<f32>()
+                        .unwrap()
+                        .into_array()
+                        .unwrap(),
+                );
+            });
+            assert_eq!(dw.unwrap(), dw_true.into_dyn());
+            let out_of_bounds = Variable::from(
+                ScalarTensor::from(
+                    Tensor::from(vec![5u32])
+                        .into_device(device.clone())
+                        .unwrap(),
+                )
+                .into_shared()
+                .unwrap(),
+            );
+            assert!(embedding.forward(out_of_bounds).is_err());
         }
 
-        fn conv2_direct_naive<T: Scalar>(
-            x: ArrayView4<T>,
-            w: ArrayView4<T>,
-            options: &Conv2Options,
-        ) -> Array4<T> {
-            let (bs, ic, ih, iw) = x.dim();
-            let (oc, _ic, fh, fw) = w.dim();
-            let (oh, ow) = options
-                .output_shape([ih, iw].into_dimension(), &[fh, fw].into_dimension())
-                .unwrap()
-                .into_pattern();
-            let Conv2Options {
-                padding,
-                dilation,
-                stride,
-            } = options;
-            let (ph, pw) = padding.into_pattern();
-            let (sh, sw) = stride.into_pattern();
-            let (dh, dw) = dilation.into_pattern();
-            let mut y = Array::zeros([bs, oc, oh, ow]);
-            for bid in 0..bs {
-                for cidy in 0..oc {
-                    for cidx in 0..ic {
-                        for hidy in 0..oh {
-                            for widy in 0..ow {
-                                for fi in 0..fh {
-                                    let hidx =
-                                        -(ph as isize) + (sh * hidy) as isize + (dh * fi) as isize;
-                                    if (0..ih as isize).contains(&hidx) {
-                                        for fj in 0..fw {
-                                            let widx = -(pw as isize)
-                                                + (sw * widy) as isize
-                                                + (dw * fj) as isize;
-                                            if (0..iw as isize).contains(&widx) {
-                                                y[(bid, cidy, hidy, widy)] += x
-                                                    [(bid, cidx, hidx as usize, widx as usize)]
-                                                    * w[(cidy, cidx, fi, fj)];
-                                            }
-                                        }
-                                    }
-                                }
-                            }
+        fn avg_pool2(device: &Device) {
+            use autograph::learn::neural_network::layer::AvgPool2;
+
+            let x_array = Array::from_shape_fn([2, 3, 5, 6], |(n, c, h, w)| {
+                ((n * 7 + c * 5 + h * 3 + w) % 11) as f32 - 5.
+            });
+            for (filter, stride, padding_mode) in [
+                ([2, 2], [2, 2], PaddingMode::Valid),
+                ([3, 2], [1, 2], PaddingMode::Valid),
+                ([3, 3], [2, 1], PaddingMode::Same),
+            ] {
+                let pool = AvgPool2::builder()
+                    .filter(filter)
+                    .stride(stride)
+                    .padding_mode(padding_mode)
+                    .build();
+                let [(fh, fw), (sh, sw)] = [filter, stride].map(|[a, b]| (a, b));
+                let (oh, ow, ph, pw) = if padding_mode == PaddingMode::Same {
+                    let [oh, ow] = [(5 + sh - 1) / sh, (6 + sw - 1) / sw];
+                    let ph = ((oh - 1) * sh + fh).saturating_sub(5) / 2;
+                    let pw = ((ow - 1) * sw + fw).saturating_sub(6) / 2;
+                    (oh, ow, ph, pw)
+                } else {
+                    ((5 - fh) / sh + 1, (6 - fw) / sw + 1, 0, 0)
+                };
+                let mut y_true = Array4::<f32>::zeros([2, 3, oh, ow]);
+                let mut dx_true = Array4::<f32>::zeros(x_array.raw_dim());
+                for ((n, c, i, j), y) in y_true.indexed_iter_mut() {
+                    let rows = (i * sh).saturating_sub(ph)..(i * sh + fh).saturating_sub(ph).min(5);
+                    let cols = (j * sw).saturating_sub(pw)..(j * sw + fw).saturating_sub(pw).min(6);
+                    let count = (rows.len() * cols.len()) as f32;
+                    for h in rows {
+                        for w in cols.clone() {
+                            *y += x_array[(n, c, h, w)] / count;
+                            dx_true[(n, c, h, w)] += 1. / count;
                         }
                     }
                 }
+                let x = Variable::builder().node().build(
+                    ScalarTensor::from(
+                        Tensor::from(x_array.clone())
+                            .into_device(device.clone())
+                            .unwrap(),
+                    )
+                    .into_shared()
+                    .unwrap(),
+                );
+                let y = pool.forward(x.clone()).unwrap();
+                let y_array = y
+                    .value()
+                    .to_device(Device::host())
+                    .unwrap()
+                    .try_into_tensor::<f32>()
+                    .unwrap()
+                    .into_array()
+                    .unwrap();
+                assert_relative_eq!(y_array, y_true, epsilon = 1e-5);
+                let dy = ScalarTensor::ones(device.clone(), y.raw_dim(), ScalarType::F32)
+                    .unwrap()
+                    .into_shared()
+                    .unwrap();
+                y.node().unwrap().backward_grad(dy).unwrap();
+                let dx = x.grad().unwrap();
+                assert_eq!(dx.shape(), x_array.shape());
+                let dx = dx
+                    .into_device(Device::host())
+                    .unwrap()
+                    .try_into_tensor::<f32>()
+                    .unwrap()
+                    .into_array()
+                    .unwrap();
+                assert_relative_eq!(dx, dx_true, epsilon = 1e-5);
             }
-            y
         }
 
-        #[derive(Debug, Copy, Clone)]
-        enum ConvAlg {
-            Im2Col,
-            Direct,
+        fn adaptive_avg_pool2(device: &Device) {
+            use autograph::{learn::neural_network::layer::AdaptiveAvgPool2, tensor::ScalarArcTensor4};
+
+            let x_array = Array::from_shape_fn([2, 3, 5, 7], |(n, c, h, w)| {
+                ((n * 7 + c * 5 + h * 3 + w) % 11) as f32 - 5.
+            });
+            let dy_array = Array::from_shape_fn([2, 3], |(n, c)| (n * 3 + c) as f32 - 2.);
+            let to_array = |x: ScalarArcTensor4| {
+                x.into_device(Device::host())
+                    .unwrap()
+                    .try_into_tensor::<f32>()
+                    .unwrap()
+                    .into_array()
+                    .unwrap()
+            };
+            let x = Variable::builder().node().build(
+                ScalarTensor::from(
+                    Tensor::from(x_array.clone())
+                        .into_device(device.clone())
+                        .unwrap(),
+                )
+                .into_shared()
+                .unwrap(),
+            );
+            // global
+            let pool = AdaptiveAvgPool2::new([1, 1]).unwrap();
+            let y = pool.forward(x.clone()).unwrap();
+            assert_eq!(y.shape(), [2, 3, 1, 1]);
+            let y_true = x_array
+                .mean_axis(Axis(3))
+                .unwrap()
+                .mean_axis(Axis(2))
+                .unwrap();
+            assert_relative_eq!(
+                to_array(y.value().clone()),
+                y_true.clone().into_shape([2, 3, 1, 1]).unwrap(),
+                epsilon = 1e-5
+            );
+            let dy = ScalarTensor::from(
+                Tensor::from(dy_array.clone().into_shape([2, 3, 1, 1]).unwrap())
+                    .into_device(device.clone())
+                    .unwrap(),
+            )
+            .into_shared()
+            .unwrap();
+            y.node().unwrap().backward_grad(dy).unwrap();
+            let dx = to_array(x.grad().unwrap());
+            let dx_true =
+                Array::from_shape_fn(x_array.raw_dim(), |(n, c, _, _)| dy_array[(n, c)] / 35.);
+            assert_relative_eq!(dx, dx_true, epsilon = 1e-6);
+            // not divisible
+            let pool = AdaptiveAvgPool2::new([2, 3]).unwrap();
+            let y = to_array(pool.forward(x.clone()).unwrap().into_value());
+            let y_true = Array::from_shape_fn([2, 3, 2, 3], |(n, c, i, j)| {
+                let rows = [0..3, 2..5][i].clone();
+                let cols = [0..3, 2..5, 4..7][j].clone();
+                x_array.slice(s![n, c, rows, cols]).mean().unwrap()
+            });
+            assert_relative_eq!(y, y_true, epsilon = 1e-5);
+            assert!(AdaptiveAvgPool2::new([0, 1]).is_err());
         }
 
-        fn conv2<T: Scalar>(
-            device: &Device,
-            x_shape: [usize; 4],
-            outputs: usize,
-            filter: [usize; 2],
-            options: &Conv2Options,
-            alg: ConvAlg,
-        ) {
-            let [_bs, inputs, _ih, _iw] = x_shape;
-            let [fh, fw] = filter;
-            let w_shape = [outputs, inputs, fh, fw];
-            let x_shape = x_shape.into_dimension();
-            let w_shape = w_shape.into_dimension();
-            let x_host = Array::from_vec(
-                (1..=10)
-                    .cycle()
-                    .map(|x| T::from_usize(x).unwrap())
-                    .take(x_shape.size())
-                    .collect(),
+        fn dropout_expected_value(device: &Device) {
+            use autograph::learn::neural_network::layer::Dropout;
+
+            #[derive(Layer, Forward)]
+            #[autograph(forward(Variable2, Output=Variable2))]
+            struct Net {
+                dropout: Dropout,
+                relu: Relu,
+            }
+
+            let x_array = Array::from_shape_fn([100, 1000], |(i, _)| (i % 10) as f32 + 1.);
+            let x = ScalarTensor::from(
+                Tensor::from(x_array.clone())
+                    .into_device(device.clone())
+                    .unwrap(),
             )
-            .into_shape(x_shape)
+            .into_shared()
             .unwrap();
-            let w_host = Array::from_vec(
-                (1..=10)
-                    .cycle()
-                    .map(|x| T::from_usize(x).unwrap())
-                    .take(w_shape.size())
-                    .collect(),
+            let mut net = Net {
+                dropout: Dropout::new(0.25).unwrap(),
+                relu: Relu,
+            };
+            let forward = |net: &Net| {
+                net.forward(Variable::from(x.clone()))
+                    .unwrap()
+                    .into_value()
+                    .into_device(Device::host())
+                    .unwrap()
+                    .try_into_tensor::<f32>()
+                    .unwrap()
+                    .into_array()
+                    .unwrap()
+            };
+            let samples = 10;
+            let mut y_mean = Array2::<f32>::zeros(x_array.raw_dim());
+            for _ in 0..samples {
+                y_mean += &forward(&net);
+            }
+            y_mean /= samples as f32;
+            let mean_true = x_array.mean_axis(Axis(1)).unwrap();
+            let mean = y_mean.mean_axis(Axis(1)).unwrap();
+            assert_relative_eq!(mean, mean_true, max_relative = 0.05);
+            net.set_training(false);
+            assert_eq!(forward(&net), x_array);
+            net.set_training(true);
+            assert_ne!(forward(&net), x_array);
+        }
+
+        fn dropout_seed(device: &Device) {
+            use autograph::{learn::neural_network::layer::Dropout, tensor::ScalarArcTensor2};
+            use ndarray::Zip;
+
+            fn to_array(x: ScalarArcTensor2) -> Array2<f32> {
+                x.into_device(Device::host())
+                    .unwrap()
+                    .try_into_tensor::<f32>()
+                    .unwrap()
+                    .into_array()
+                    .unwrap()
+            }
+
+            let p = 0.5;
+            let x_array = Array::from_shape_fn([8, 16], |(i, j)| (i * 16 + j) as f32 + 1.);
+            let x = ScalarTensor::from(
+                Tensor::from(x_array.clone())
+                    .into_device(device.clone())
+                    .unwrap(),
             )
-            .into_shape(w_shape)
+            .into_shared()
             .unwrap();
-            let x_device = TensorView::try_from(x_host.view())
-                .unwrap()
-                .to_device(device.clone())
-                .unwrap();
-            let w_device = TensorView::try_from(w_host.view())
-                .unwrap()
-                .to_device(device.clone())
-                .unwrap();
-            let y_host = Tensor::from(conv2_direct_naive(x_host.view(), w_host.view(), options));
-            let y_device = match alg {
-                ConvAlg::Im2Col => neural_network::layer::__private::conv2_im2col_forward(
-                    x_device.view().into(),
-                    w_device.view().into(),
-                    options,
-                )
-                .unwrap(),
-                ConvAlg::Direct => neural_network::layer::__private::conv2_direct_forward(
-                    x_device.view().into(),
-                    w_device.view().into(),
-                    options,
-                )
-                .unwrap(),
+            let forward = |dropout: &Dropout| {
+                let x = Variable::builder().node().build(x.clone());
+                let y = x.clone().forward(dropout).unwrap();
+                let dy = ScalarArcTensor::ones(device.clone(), [8, 16], ScalarType::F32).unwrap();
+                y.node().unwrap().backward_grad(dy).unwrap();
+                (to_array(y.into_value()), to_array(x.grad().unwrap()))
             };
-            let y_device = y_device
-                .try_into_tensor::<T>()
-                .unwrap()
-                .into_device(Device::host())
-                .unwrap();
-            let epsilon = if T::SCALAR_TYPE == ScalarType::BF16 {
-                Some(ScalarElem::F32((fh * fw) as f32))
-            } else {
-                None
+            let dropout = Dropout::new(p).unwrap().with_seed(0);
+            let (y1, dx1) = forward(&dropout);
+            let (y2, _) = forward(&dropout);
+            assert_ne!(y1, y2);
+            let dropout2 = Dropout::new(p).unwrap().with_seed(0);
+            let (y1_2, dx1_2) = forward(&dropout2);
+            assert_eq!(y1, y1_2);
+            assert_eq!(dx1, dx1_2);
+            assert_eq!(y2, forward(&dropout2).0);
+            let scale = 1. / (1. - p);
+            let mask = dx1.mapv(|dx| dx != 0.);
+            assert!(mask.iter().any(|m| *m) && !mask.iter().all(|m| *m));
+            Zip::from(&y1).and(&dx1).and(&x_array).for_each(|y, dx, x| {
+                if *dx == 0. {
+                    assert_eq!(*y, 0.);
+                } else {
+                    assert_eq!(*dx, scale);
+                    assert_eq!(*y, x * scale);
+                }
+            });
+            let (y3, _) = forward(&Dropout::new(p).unwrap().with_seed(1));
+            assert_ne!(y1, y3);
+            let mut dropout = Dropout::new(p).unwrap().with_seed(0);
+            dropout.set_training(false);
+            let (y, dx) = forward(&dropout);
+            assert_eq!(y, x_array);
+            assert!(dx.iter().all(|dx| *dx == 1.));
+            let dropout = dropout.with_mc_dropout(true);
+            let (y, _) = forward(&dropout);
+            assert_eq!(y, y1);
+        }
+
+        fn adam(device: &Device) {
+            use autograph::learn::neural_network::{
+                autograd::Parameter1,
+                optimizer::{Adam, Optimizer},
             };
-            check_approx_eq(
-                y_host.view().into_dyn().into(),
-                y_device.view().into_dyn().into(),
-                epsilon,
+            use ndarray::Array1;
+
+            let (lr, beta1, beta2, epsilon, weight_decay) =
+                (0.01f32, 0.8f32, 0.99f32, 1e-8f32, 0.1f32);
+            let adam = Adam::builder()
+                .beta1(beta1)
+                .beta2(beta2)
+                .epsilon(epsilon)
+                .weight_decay(weight_decay)
+                .build()
+                .unwrap();
+            let mut w_true = Array1::from_vec(vec![1f32, -2., 3., 0.5]);
+            let mut m = Array1::<f32>::zeros(4);
+            let mut v = Array1::<f32>::zeros(4);
+            let mut parameter = Parameter1::from(
+                Tensor::from(w_true.clone())
+                    .into_device(device.clone())
+                    .unwrap(),
             );
+            for t in 1..=5 {
+                let dy = Array1::from_shape_fn(4, |i| ((i + t as usize) % 3) as f32 - 1.);
+                parameter.init_grad();
+                parameter
+                    .to_variable()
+                    .node()
+                    .unwrap()
+                    .backward_grad(
+                        ScalarTensor::from(
+                            Tensor::from(dy.clone())
+                                .into_device(device.clone())
+                                .unwrap(),
+                        )
+                        .into_shared()
+                        .unwrap(),
+                    )
+                    .unwrap();
+                adam.update(lr, parameter.make_view_mut().unwrap().into_dyn())
+                    .unwrap();
+                let g = dy + weight_decay * &w_true;
+                m = beta1 * &m + (1. - beta1) * &g;
+                v = beta2 * &v + (1. - beta2) * &g * &g;
+                let m_hat = &m / (1. - beta1.powi(t));
+                let v_hat = (&v / (1. - beta2.powi(t))).mapv(f32::sqrt) + epsilon;
+                w_true = w_true - lr * m_hat / v_hat;
+                let w = parameter
+                    .value()
+                    .to_device(Device::host())
+                    .unwrap()
+                    .try_into_tensor::<f32>()
+                    .unwrap()
+                    .into_array()
+                    .unwrap();
+                assert_relative_eq!(w, w_true, epsilon = 1e-5);
+            }
         }
 
-        fn conv2_direct_backward_input_naive<T: Scalar>(
-            w: ArrayView4<T>,
-            dy: ArrayView4<T>,
-            options: &Conv2Options,
-        ) -> Array4<T> {
-            let (oc, ic, fh, fw) = w.dim();
-            let (bs, _oc, oh, ow) = dy.dim();
-            assert_eq!(oc, _oc);
-            let (ih, iw) = options
-                .input_shape([oh, ow].into_dimension(), &[fh, fw].into_dimension())
-                .unwrap()
-                .into_pattern();
-            let Conv2Options {
-                padding,
-                dilation,
-                stride,
-            } = options;
-            let (ph, pw) = padding.into_pattern();
-            let (sh, sw) = stride.into_pattern();
-            let (dh, dw) = dilation.into_pattern();
-            let mut dx = Array::zeros([bs, ic, ih, iw]);
-            for bid in 0..bs {
-                for cidx in 0..ic {
-                    for cidy in 0..oc {
-                        for hidy in 0..oh {
-                            for widy in 0..ow {
-                                for fi in 0..fh {
-                                    let hidx =
-                                        -(ph as isize) + (sh * hidy) as isize + (dh * fi) as isize;
-                                    if (0..ih as isize).contains(&hidx) {
-                                        for fj in 0..fw {
-                                            let widx = -(pw as isize)
-                                                + (sw * widy) as isize
-                                                + (dw * fj) as isize;
-                                            if (0..iw as isize).contains(&widx) {
-                                                dx[(bid, cidx, hidx as usize, widx as usize)] += w
-                                                    [(cidy, cidx, fi, fj)]
-                                                    * dy[(bid, cidy, hidy, widy)];
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
+        fn rmsprop(device: &Device) {
+            use autograph::learn::neural_network::{
+                autograd::Parameter1,
+                optimizer::{Optimizer, RMSprop},
+            };
+            use ndarray::Array1;
+
+            let (lr, alpha, epsilon) = (0.01f32, 0.9f32, 1e-8f32);
+            let w0 = Array1::from_vec(vec![1f32, -2., 3., 0.5]);
+            let grad = |t: usize| Array1::from_shape_fn(4, |i| ((i + t) % 3) as f32 - 0.5);
+            for (centered, momentum) in [(false, 0.), (true, 0.), (false, 0.5), (true, 0.5)] {
+                let rmsprop = RMSprop::builder()
+                    .alpha(alpha)
+                    .epsilon(epsilon)
+                    .momentum(momentum)
+                    .centered(centered)
+                    .build()
+                    .unwrap();
+                let mut parameters = [Device::host(), device.clone()].map(|device| {
+                    Parameter1::from(Tensor::from(w0.clone()).into_device(device).unwrap())
+                });
+                let mut w_true = w0.clone();
+                let mut v = Array1::<f32>::zeros(4);
+                let mut g_avg = Array1::<f32>::zeros(4);
+                let mut buf = Array1::<f32>::zeros(4);
+                for t in 0..4 {
+                    let g = grad(t);
+                    for parameter in parameters.iter_mut() {
+                        parameter.init_grad();
+                        let dy = ScalarTensor::from(
+                            Tensor::from(g.clone())
+                                .into_device(parameter.device())
+                                .unwrap(),
+                        )
+                        .into_shared()
+                        .unwrap();
+                        parameter
+                            .to_variable()
+                            .node()
+                            .unwrap()
+                            .backward_grad(dy)
+                            .unwrap();
+                        rmsprop
+                            .update(lr, parameter.make_view_mut().unwrap().into_dyn())
+                            .unwrap();
+                    }
+                    v = alpha * &v + (1. - alpha) * &g * &g;
+                    let avg = if centered {
+                        g_avg = alpha * &g_avg + (1. - alpha) * &g;
+                        &v - &g_avg * &g_avg
+                    } else {
+                        v.clone()
+                    };
+                    let mut update = &g / (avg.mapv(f32::sqrt) + epsilon);
+                    if momentum > 0. {
+                        buf = momentum * &buf + &update;
+                        update = buf.clone();
                     }
+                    w_true = w_true - lr * update;
+                    let w: Vec<_> = parameters
+                        .iter()
+                        .map(|parameter| {
+                            parameter
+                                .value()
+                                .to_device(Device::host())
+                                .unwrap()
+                                .try_into_tensor::<f32>()
+                                .unwrap()
+                                .into_array()
+                                .unwrap()
+                        })
+                        .collect();
+                    assert_eq!(w[0], w[1]);
+                    assert_relative_eq!(w[0], w_true, epsilon = 1e-5);
                 }
             }
-            dx
         }
 
-        fn conv2_backward_input<T: Scalar>(
-            device: &Device,
-            x_shape: [usize; 4],
-            outputs: usize,
-            filter: [usize; 2],
-            options: &Conv2Options,
-            alg: ConvAlg,
-        ) {
-            let [bs, inputs, ih, iw] = x_shape;
-            let [fh, fw] = filter;
-            let w_shape = [outputs, inputs, fh, fw];
-            let w_shape = w_shape.into_dimension();
-            let (oh, ow) = options
-                .output_shape([ih, iw].into_dimension(), &filter.into_dimension())
-                .unwrap()
-                .into_pattern();
-            let y_shape = [bs, outputs, oh, ow].into_dimension();
-            let w_host = Array::from_vec(
-                (1..=10)
-                    .cycle()
-                    .map(|x| T::from_usize(x).unwrap())
-                    .take(w_shape.size())
-                    .collect(),
-            )
-            .into_shape(w_shape)
-            .unwrap();
-            let dy_host = Array::from_vec(
-                (1..=10)
-                    .cycle()
-                    .map(|x| T::from_usize(x).unwrap())
-                    .take(y_shape.size())
-                    .collect(),
+        fn radam(device: &Device) {
+            use autograph::learn::neural_network::{
+                autograd::Parameter1,
+                optimizer::{Optimizer, RAdam},
+            };
+            use ndarray::Array1;
+
+            let radam = RAdam::default();
+            assert!((1..=5).all(|t| radam.rectification(t).is_none()));
+            let r = (6..=100)
+                .map(|t| radam.rectification(t).unwrap())
+                .collect::<Vec<_>>();
+            assert!(r.windows(2).all(|r| r[0] < r[1] && r[1] < 1.));
+            assert_relative_eq!(radam.rectification(1_000_000).unwrap(), 1., epsilon = 1e-3);
+
+            let (lr, beta1, beta2, epsilon) = (0.1f32, 0.9f32, 0.999f32, 1e-8f32);
+            let mut w_true = Array1::from_vec(vec![1f32, -2., 3., 0.5]);
+            let g = Array1::from_vec(vec![0.5f32, -1., 0.25, 2.]);
+            let mut m = Array1::<f32>::zeros(4);
+            let mut v = Array1::<f32>::zeros(4);
+            let mut parameter = Parameter1::from(
+                Tensor::from(w_true.clone())
+                    .into_device(device.clone())
+                    .unwrap(),
+            );
+            for t in 1..=8 {
+                parameter.init_grad();
+                let dy = ScalarTensor::from(
+                    Tensor::from(g.clone()).into_device(device.clone()).unwrap(),
+                )
+                .into_shared()
+                .unwrap();
+                parameter
+                    .to_variable()
+                    .node()
+                    .unwrap()
+                    .backward_grad(dy)
+                    .unwrap();
+                radam
+                    .update(lr, parameter.make_view_mut().unwrap().into_dyn())
+                    .unwrap();
+                m = beta1 * &m + (1. - beta1) * &g;
+                v = beta2 * &v + (1. - beta2) * &g * &g;
+                let m_hat = &m / (1. - beta1.powi(t));
+                if let Some(r) = radam.rectification(t as u32) {
+                    let v_hat = (&v / (1. - beta2.powi(t))).mapv(f32::sqrt) + epsilon;
+                    w_true = w_true - lr * r * m_hat / v_hat;
+                } else {
+                    w_true = w_true - lr * m_hat;
+                }
+                let w = parameter
+                    .value()
+                    .to_device(Device::host())
+                    .unwrap()
+                    .try_into_tensor::<f32>()
+                    .unwrap()
+                    .into_array()
+                    .unwrap();
+                assert_relative_eq!(w, w_true, epsilon = 1e-5);
+            }
+        }
+
+        fn lamb(device: &Device) {
+            use autograph::learn::neural_network::{
+                autograd::Parameter1,
+                optimizer::{Lamb, Optimizer},
+            };
+            use ndarray::Array1;
+
+            let lamb = Lamb::builder().epsilon(0.).build().unwrap();
+            let lr = 0.1f32;
+            let mut parameter = Parameter1::from(
+                Tensor::from(Array1::from_vec(vec![3f32, 4.]))
+                    .into_device(device.clone())
+                    .unwrap(),
+            );
+            parameter.init_grad();
+            let dy = ScalarTensor::from(
+                Tensor::from(Array1::from_vec(vec![0.5f32, -2.]))
+                    .into_device(device.clone())
+                    .unwrap(),
             )
-            .into_shape(y_shape)
+            .into_shared()
             .unwrap();
-            let w_device = TensorView::try_from(w_host.view())
+            parameter
+                .to_variable()
+                .node()
                 .unwrap()
-                .to_device(device.clone())
+                .backward_grad(dy)
                 .unwrap();
-            let dy_device = TensorView::try_from(dy_host.view())
+            lamb.update(lr, parameter.make_view_mut().unwrap().into_dyn())
+                .unwrap();
+            // The first update is sign(grad), with norm sqrt(2), and ||w|| = 5.
+            let trust_ratio = 5. / 2f32.sqrt();
+            let w_true = Array1::from_vec(vec![3. - lr * trust_ratio, 4. + lr * trust_ratio]);
+            let w = parameter
+                .value()
+                .to_device(Device::host())
                 .unwrap()
-                .to_device(device.clone())
+                .try_into_tensor::<f32>()
+                .unwrap()
+                .into_array()
                 .unwrap();
-            let dx_host = Tensor::from(conv2_direct_backward_input_naive(
-                w_host.view(),
-                dy_host.view(),
-                options,
-            ));
-            let dx_device = match alg {
-                ConvAlg::Im2Col => todo!(),
-                ConvAlg::Direct => neural_network::layer::__private::conv2_direct_backward_input(
-                    w_device.view().into(),
-                    dy_device.view().into(),
-                    options,
+            assert_relative_eq!(w, w_true, epsilon = 1e-5);
+        }
+
+        fn graph_stats(device: &Device) {
+            use autograph::learn::neural_network::autograd::GraphStats;
+            use ndarray::linalg::Dot;
+
+            let variable = |shape: [usize; 2]| {
+                Variable::from(
+                    ScalarArcTensor::zeros(device.clone(), shape, ScalarType::F32).unwrap(),
                 )
-                .unwrap(),
             };
-            let dx_device = dx_device
-                .try_into_tensor::<T>()
+            assert_eq!(variable([2, 3]).graph_stats(), GraphStats::default());
+            let x = variable([2, 3]).requires_grad();
+            let w = variable([3, 4]).requires_grad();
+            let y = x.dot(&w).unwrap();
+            assert_eq!(
+                y.graph_stats(),
+                GraphStats {
+                    nodes: 3,
+                    edges: 2,
+                    bytes: (6 + 12 + 8) * 4,
+                }
+            );
+            y.node()
                 .unwrap()
-                .into_device(Device::host())
+                .backward_grad(
+                    ScalarArcTensor::ones(device.clone(), [2, 4], ScalarType::F32).unwrap(),
+                )
                 .unwrap();
-            let epsilon = if T::SCALAR_TYPE == ScalarType::BF16 {
-                Some(ScalarElem::F32((fh * fw) as f32))
-            } else {
-                None
-            };
-            check_approx_eq(
-                dx_host.view().into_dyn().into(),
-                dx_device.view().into_dyn().into(),
-                epsilon,
+            assert_eq!(
+                y.graph_stats(),
+                GraphStats {
+                    nodes: 1,
+                    edges: 0,
+                    bytes: 2 * 8 * 4,
+                }
             );
         }
 
-        fn conv2_direct_backward_weight_naive<T: Scalar>(
-            x: ArrayView4<T>,
-            dy: ArrayView4<T>,
-            filter: [usize; 2],
-            options: &Conv2Options,
-        ) -> Array4<T> {
-            let (bs, ic, ih, iw) = x.dim();
-            let (_bs, oc, oh, ow) = dy.dim();
-            assert_eq!(bs, _bs);
-            let [fh, fw] = filter;
-            let Conv2Options {
-                padding,
-                dilation,
-                stride,
-            } = options;
-            let (ph, pw) = padding.into_pattern();
-            let (sh, sw) = stride.into_pattern();
-            let (dh, dw) = dilation.into_pattern();
-            let mut w_grad = Array::zeros([oc, ic, fh, fw]);
-            for bid in 0..bs {
-                for cidx in 0..ic {
-                    for cidy in 0..oc {
-                        for hidy in 0..oh {
-                            for widy in 0..ow {
-                                for fi in 0..fh {
-                                    let hidx =
-                                        -(ph as isize) + (sh * hidy) as isize + (fi * dh) as isize;
-                                    if (0..ih as isize).contains(&hidx) {
-                                        for fj in 0..fw {
-                                            let widx = -(pw as isize)
-                                                + (sw * widy) as isize
-                                                + (fj * dw) as isize;
-                                            if (0..iw as isize).contains(&widx) {
-                                                w_grad[(cidy, cidx, fi, fj)] += x
-                                                    [(bid, cidx, hidx as usize, widx as usize)]
-                                                    * dy[(bid, cidy, hidy, widy)];
-                                            }
-                                        }
-                                    }
-                                }
+        fn padding_mode_same(device: &Device) {
+            for input_shape in [[1, 1, 5, 5], [2, 3, 8, 7]] {
+                let [_, _, ih, iw] = input_shape;
+                let x = Variable::builder().node().build(
+                    ScalarArcTensor::zeros(device.clone(), input_shape, ScalarType::F32).unwrap(),
+                );
+                for filter in [[1, 1], [3, 3], [4, 2]] {
+                    for stride in [[1, 1], [2, 2], [1, 3]] {
+                        let [sh, sw] = stride;
+                        let output_shape = [(ih + sh - 1) / sh, (iw + sw - 1) / sw];
+                        let conv = Conv2::builder()
+                            .inputs(input_shape[1])
+                            .outputs(2)
+                            .filter(filter)
+                            .stride(stride)
+                            .padding_mode(PaddingMode::Same)
+                            .device(device.clone())
+                            .build()
+                            .unwrap();
+                        let y = conv.forward(x.clone()).unwrap();
+                        assert_eq!(&y.shape()[2..], output_shape.as_ref());
+                        y.node().unwrap().backward().unwrap();
+                        let pool = MaxPool2::builder()
+                            .filter(filter)
+                            .stride(stride)
+                            .padding_mode(PaddingMode::Same)
+                            .build();
+                        let y = pool.forward(x.clone()).unwrap();
+                        assert_eq!(&y.shape()[2..], output_shape.as_ref());
+                    }
+                }
+            }
+        }
+
+        fn conv2_stride_padding(device: &Device) {
+            let options = Conv2Options {
+                padding: [1, 1].into_dimension(),
+                stride: [2, 2].into_dimension(),
+                dilation: [1, 1].into_dimension(),
+            };
+            let im2col_options = Im2ColConv2Options {
+                filter: [3, 3],
+                padding: [1, 1],
+                stride: [2, 2],
+                dilation: [1, 1],
+            };
+            let mut conv = Conv2::builder()
+                .inputs(2)
+                .outputs(3)
+                .filter([3, 3])
+                .padding([1, 1])
+                .stride([2, 2])
+                .seed(0)
+                .device(device.clone())
+                .build()
+                .unwrap();
+            let mut weight = None;
+            conv.for_each_parameter(|parameter| {
+                weight.replace(
+                    parameter
+                        .value()
+                        .to_device(Device::host())
+                        .unwrap()
+                        .try_into_tensor::<f32>()
+                        .unwrap()
+                        .into_array()
+                        .unwrap()
+                        .into_dimensionality::<Ix4>()
+                        .unwrap(),
+                );
+            });
+            let w_array = weight.unwrap();
+            let to_array = |x: ScalarArcTensorD| {
+                x.into_device(Device::host())
+                    .unwrap()
+                    .try_into_tensor::<f32>()
+                    .unwrap()
+                    .into_array()
+                    .unwrap()
+                    .into_dimensionality::<Ix4>()
+                    .unwrap()
+            };
+            // the last row and column of [6, 8] are not covered by a window
+            for [ih, iw] in [[7, 7], [6, 8]] {
+                let x_array = Array::from_shape_fn([2, 2, ih, iw], |(n, c, h, w)| {
+                    ((n * 7 + c * 5 + h * 3 + w) % 11) as f32 / 4. - 1.
+                });
+                let [oh, ow] = im2col_options.output_shape([ih, iw]);
+                let dy_array = Array::from_shape_fn([2, 3, oh, ow], |(n, c, h, w)| {
+                    ((n + 2 * c + 3 * h + w) % 5) as f32 - 2.
+                });
+                conv.init_parameter_grads().unwrap();
+                let x = Variable::builder().node().build(
+                    ScalarTensor::from(
+                        Tensor::from(x_array.clone())
+                            .into_device(device.clone())
+                            .unwrap(),
+                    )
+                    .into_shared()
+                    .unwrap(),
+                );
+                let y = conv.forward(x.clone()).unwrap();
+                assert_eq!(y.shape(), [2, 3, oh, ow]);
+                let y_true = conv2_direct_naive(x_array.view(), w_array.view(), &options);
+                assert_relative_eq!(
+                    to_array(y.value().clone().into_dyn()),
+                    y_true,
+                    epsilon = 1e-4
+                );
+                let dy = ScalarTensor::from(
+                    Tensor::from(dy_array.clone())
+                        .into_device(device.clone())
+                        .unwrap(),
+                )
+                .into_shared()
+                .unwrap();
+                y.node().unwrap().backward_grad(dy).unwrap();
+                let dx = to_array(x.grad().unwrap().into_dyn());
+                assert_eq!(dx.shape(), x_array.shape());
+                let dx_naive =
+                    conv2_direct_backward_input_naive(w_array.view(), dy_array.view(), &options);
+                let (_, _, h, w) = dx_naive.dim();
+                let mut dx_true = Array4::<f32>::zeros(x_array.raw_dim());
+                dx_true.slice_mut(s![.., .., ..h, ..w]).assign(&dx_naive);
+                assert_relative_eq!(dx, dx_true, epsilon = 1e-4);
+            }
+        }
+
+        fn conv1(device: &Device) {
+            use autograph::learn::neural_network::layer::Conv1;
+            use ndarray::{Array3, Ix3};
+
+            let x_array = Array::from_shape_fn([2, 2, 9], |(n, c, l)| {
+                ((n * 7 + c * 5 + l * 3) % 11) as f32 - 5.
+            });
+            let to_array = |x: ScalarArcTensorD| {
+                x.into_device(Device::host())
+                    .unwrap()
+                    .try_into_tensor::<f32>()
+                    .unwrap()
+                    .into_array()
+                    .unwrap()
+                    .into_dimensionality::<Ix3>()
+                    .unwrap()
+            };
+            // filter, padding, stride, dilation
+            for (filter, padding, stride, dilation) in
+                [(3, 0, 1, 1), (3, 1, 2, 1), (2, 2, 1, 2), (5, 1, 3, 1)]
+            {
+                let mut conv = Conv1::builder()
+                    .inputs(2)
+                    .outputs(3)
+                    .filter([filter])
+                    .padding([padding])
+                    .stride([stride])
+                    .dilation([dilation])
+                    .bias(true)
+                    .seed(0)
+                    .device(device.clone())
+                    .build()
+                    .unwrap();
+                let mut parameters = Vec::new();
+                conv.for_each_parameter(|parameter| {
+                    parameters.push(
+                        parameter
+                            .value()
+                            .to_device(Device::host())
+                            .unwrap()
+                            .try_into_tensor::<f32>()
+                            .unwrap()
+                            .into_array()
+                            .unwrap(),
+                    );
+                });
+                let w_array = parameters[0].clone().into_dimensionality::<Ix3>().unwrap();
+                let b_array = parameters[1].clone();
+                let ol = (9 + 2 * padding - dilation * (filter - 1) - 1) / stride + 1;
+                let mut y_true = Array3::<f32>::zeros([2, 3, ol]);
+                let mut dx_true = Array3::<f32>::zeros(x_array.raw_dim());
+                for ((n, o, l), y) in y_true.indexed_iter_mut() {
+                    *y = b_array[o];
+                    for i in 0..2 {
+                        for f in 0..filter {
+                            let lx = (l * stride + f * dilation) as isize - padding as isize;
+                            if (0..9).contains(&lx) {
+                                *y += x_array[(n, i, lx as usize)] * w_array[(o, i, f)];
+                                dx_true[(n, i, lx as usize)] += w_array[(o, i, f)];
                             }
                         }
                     }
                 }
+                conv.init_parameter_grads().unwrap();
+                let x = Variable::builder().node().build(
+                    ScalarTensor::from(
+                        Tensor::from(x_array.clone())
+                            .into_device(device.clone())
+                            .unwrap(),
+                    )
+                    .into_shared()
+                    .unwrap(),
+                );
+                let y = conv.forward(x.clone()).unwrap();
+                assert_relative_eq!(
+                    to_array(y.value().clone().into_dyn()),
+                    y_true,
+                    epsilon = 1e-4
+                );
+                let dy = ScalarTensor::ones(device.clone(), y.raw_dim(), ScalarType::F32)
+                    .unwrap()
+                    .into_shared()
+                    .unwrap();
+                y.node().unwrap().backward_grad(dy).unwrap();
+                let dx = to_array(x.grad().unwrap().into_dyn());
+                assert_relative_eq!(dx, dx_true, epsilon = 1e-4);
             }
-            w_grad
         }
 
-        fn conv2_backward_weight<T: Scalar>(
-            device: &Device,
-            x_shape: [usize; 4],
-            outputs: usize,
-            filter: [usize; 2],
-            options: &Conv2Options,
-            alg: ConvAlg,
-        ) {
-            let [bs, inputs, ih, iw] = x_shape;
-            let x_shape = x_shape.into_dimension();
-            let (oh, ow) = options
-                .output_shape([ih, iw].into_dimension(), &filter.into_dimension())
-                .unwrap()
-                .into_pattern();
-            let y_shape = [bs, outputs, oh, ow].into_dimension();
-            let x_host = Array::from_vec(
-                (1..=10)
-                    .cycle()
-                    .map(|x| T::from_usize(x).unwrap())
-                    .take(x_shape.size())
-                    .collect(),
-            )
-            .into_shape(x_shape)
-            .unwrap();
-            let dy_host = Array::from_vec(
-                (1..=10)
-                    .cycle()
-                    .map(|x| T::from_usize(x).unwrap())
-                    .take(y_shape.size())
-                    .collect(),
+        fn conv_transpose2(device: &Device) {
+            use autograph::learn::neural_network::layer::ConvTranspose2;
+
+            // filter, stride, padding, output_padding
+            for (filter, stride, padding, output_padding) in [
+                ([3, 3], [1, 1], [0, 0], [0, 0]),
+                ([4, 4], [2, 2], [1, 1], [0, 0]),
+                ([3, 2], [2, 3], [1, 0], [1, 2]),
+            ] {
+                let conv = ConvTranspose2::builder()
+                    .inputs(3)
+                    .outputs(2)
+                    .filter(filter)
+                    .stride(stride)
+                    .padding(padding)
+                    .output_padding(output_padding)
+                    .device(device.clone())
+                    .build()
+                    .unwrap();
+                let x = Variable::from(
+                    ScalarArcTensor::zeros(device.clone(), [2, 3, 5, 4], ScalarType::F32).unwrap(),
+                );
+                let y = conv.forward(x).unwrap();
+                let [oh, ow] = [0, 1].map(|i| {
+                    ([5, 4][i] - 1) * stride[i] + filter[i] + output_padding[i] - 2 * padding[i]
+                });
+                assert_eq!(y.shape(), [2, 2, oh, ow]);
+            }
+            assert!(ConvTranspose2::builder()
+                .stride([2, 2])
+                .output_padding([2, 0])
+                .build()
+                .is_err());
+
+            let x_array = Array::from_shape_fn([2, 3, 4, 5], |(n, c, i, j)| {
+                ((n * 7 + c * 5 + i * 3 + j) % 11) as f32 - 5.
+            });
+            let [sh, sw] = [2, 2];
+            let [ph, pw] = [1, 1];
+            let [fh, fw] = [3, 3];
+            let [oph, opw] = [1, 0];
+            let mut conv = ConvTranspose2::builder()
+                .inputs(3)
+                .outputs(2)
+                .filter([fh, fw])
+                .stride([sh, sw])
+                .padding([ph, pw])
+                .output_padding([oph, opw])
+                .bias(true)
+                .seed(0)
+                .device(device.clone())
+                .build()
+                .unwrap();
+            let mut parameters = Vec::new();
+            conv.for_each_parameter(|parameter| {
+                parameters.push(
+                    parameter
+                        .value()
+                        .to_device(Device::host())
+                        .unwrap()
+                        .try_into_tensor::<f32>()
+                        .unwrap()
+                        .into_array()
+                        .unwrap(),
+                );
+            });
+            let w_array = parameters[0].clone().into_dimensionality::<Ix4>().unwrap();
+            let b_array = parameters[1].clone();
+            let oh = 3 * sh + fh + oph - 2 * ph;
+            let ow = 4 * sw + fw + opw - 2 * pw;
+            let dy_array = Array::from_shape_fn([2, 2, oh, ow], |(n, o, i, j)| {
+                ((n * 5 + o * 3 + i * 2 + j) % 7) as f32 - 3.
+            });
+            let mut y_true = Array4::<f32>::zeros([2, 2, oh, ow]);
+            for ((_, o, _, _), y) in y_true.indexed_iter_mut() {
+                *y = b_array[o];
+            }
+            let mut dx_true = Array4::<f32>::zeros(x_array.raw_dim());
+            let mut dw_true = Array4::<f32>::zeros(w_array.raw_dim());
+            for ((n, c, i, j), x) in x_array.indexed_iter() {
+                for ((_, o, a, b), w) in w_array.slice(s![c..c + 1, .., .., ..]).indexed_iter() {
+                    let yi = (i * sh + a) as isize - ph as isize;
+                    let yj = (j * sw + b) as isize - pw as isize;
+                    if (0..oh as isize).contains(&yi) && (0..ow as isize).contains(&yj) {
+                        let index = (n, o, yi as usize, yj as usize);
+                        y_true[index] += x * w;
+                        dx_true[(n, c, i, j)] += dy_array[index] * w;
+                        dw_true[(c, o, a, b)] += dy_array[index] * x;
+                    }
+                }
+            }
+            conv.init_parameter_grads().unwrap();
+            let x = Variable::builder().node().build(
+                ScalarTensor::from(
+                    Tensor::from(x_array.clone())
+                        .into_device(device.clone())
+                        .unwrap(),
+                )
+                .into_shared()
+                .unwrap(),
+            );
+            let y = conv.forward(x.clone()).unwrap();
+            let to_array = |x: ScalarArcTensorD| {
+                x.into_device(Device::host())
+                    .unwrap()
+                    .try_into_tensor::<f32>()
+                    .unwrap()
+                    .into_array()
+                    .unwrap()
+                    .into_dimensionality::<Ix4>()
+                    .unwrap()
+            };
+            assert_relative_eq!(
+                to_array(y.value().clone().into_dyn()),
+                y_true,
+                epsilon = 1e-4
+            );
+            let dy =
+                ScalarTensor::from(Tensor::from(dy_array).into_device(device.clone()).unwrap())
+                    .into_shared()
+                    .unwrap();
+            y.node().unwrap().backward_grad(dy).unwrap();
+            let dx = to_array(x.grad().unwrap().into_dyn());
+            assert_relative_eq!(dx, dx_true, epsilon = 1e-4);
+            let mut grads = Vec::new();
+            conv.for_each_parameter(|parameter| grads.push(parameter.grad().unwrap()));
+            let dw = to_array(grads[0].clone());
+            assert_relative_eq!(dw, dw_true, epsilon = 1e-4);
+        }
+
+        fn conv2_kernel_kind(device: &Device) {
+            use autograph::{learn::neural_network::layer::KernelKind, tensor::ScalarArcTensor4};
+
+            let x_array = Array::from_shape_fn([2, 3, 6, 5], |(n, c, i, j)| {
+                (((n * 3 + c) * 6 + i) * 5 + j) as f32 / 100.
+            });
+            let w_array = Array::from_shape_fn([4, 3, 3, 2], |(o, c, i, j)| {
+                (((o * 3 + c) * 3 + i) * 2 + j) as f32 / 10. - 3.
+            });
+            let w_flipped = w_array
+                .slice(s![.., .., ..;-1, ..;-1])
+                .as_standard_layout()
+                .into_owned();
+            let x = Variable::from(Tensor::from(x_array).into_device(device.clone()).unwrap());
+            let to_array = |x: ScalarArcTensor4| {
+                x.into_device(Device::host())
+                    .unwrap()
+                    .try_into_tensor::<f32>()
+                    .unwrap()
+                    .into_array()
+                    .unwrap()
+            };
+            let forward = |kernel_kind: KernelKind, w: Array4<f32>| {
+                let mut conv = Conv2::builder()
+                    .inputs(3)
+                    .outputs(4)
+                    .filter([3, 2])
+                    .kernel_kind(kernel_kind)
+                    .device(device.clone())
+                    .build()
+                    .unwrap();
+                assert_eq!(conv.kernel_kind(), kernel_kind);
+                conv.weight_view_mut()
+                    .unwrap()
+                    .value_view_mut()
+                    .assign(&ScalarTensor::from(
+                        Tensor::from(w).into_device(device.clone()).unwrap(),
+                    ))
+                    .unwrap();
+                conv.init_parameter_grads().unwrap();
+                let y = conv.forward(x.clone()).unwrap();
+                y.node().unwrap().backward().unwrap();
+                let w_grad = conv.weight_view_mut().unwrap().grad().unwrap();
+                (to_array(y.into_value()), to_array(w_grad))
+            };
+            let (y_true, w_grad_true) = forward(KernelKind::CrossCorrelation, w_array);
+            let (y, w_grad) = forward(KernelKind::Convolution, w_flipped);
+            assert_relative_eq!(y, y_true, epsilon = 1e-4);
+            assert_relative_eq!(
+                w_grad,
+                w_grad_true.slice(s![.., .., ..;-1, ..;-1]),
+                epsilon = 1e-4
+            );
+        }
+
+        fn cross_entropy_loss_reduction(device: &Device) {
+            use autograph::learn::criterion::Reduction;
+
+            let x_array = Array::from_shape_fn([5, 3], |(i, j)| ((i * 3 + j) % 4) as f32 / 2.);
+            let t_array = Array1::from(vec![2u8, 0, 1, 1, 0]);
+            let t = ScalarTensor::from(
+                Tensor::from(t_array.clone())
+                    .into_device(device.clone())
+                    .unwrap(),
             )
-            .into_shape(y_shape)
+            .into_shared()
             .unwrap();
-            let x_device = TensorView::try_from(x_host.view())
-                .unwrap()
-                .to_device(device.clone())
-                .unwrap();
-            let dy_device = TensorView::try_from(dy_host.view())
-                .unwrap()
-                .to_device(device.clone())
-                .unwrap();
-            let dw_host = Tensor::from(conv2_direct_backward_weight_naive(
-                x_host.view(),
-                dy_host.view(),
-                filter,
-                options,
-            ));
-            let dw_device = match alg {
-                ConvAlg::Im2Col => todo!(),
-                ConvAlg::Direct => neural_network::layer::__private::conv2_direct_backward_weight(
-                    x_device.view().into(),
-                    dy_device.view().into(),
-                    filter,
-                    options,
+            let to_array = |x: ScalarArcTensorD| {
+                x.into_device(Device::host())
+                    .unwrap()
+                    .cast_into_tensor::<f32>()
+                    .unwrap()
+                    .into_array()
+                    .unwrap()
+            };
+            let mut loss_true = Array1::<f32>::zeros(5);
+            let mut dx_true = Array2::<f32>::zeros([5, 3]);
+            for (i, t) in t_array.iter().map(|t| *t as usize).enumerate() {
+                let x = x_array.row(i);
+                let s: f32 = x.iter().map(|x| x.exp()).sum();
+                loss_true[i] = s.ln() - x[t];
+                for j in 0..3 {
+                    dx_true[(i, j)] = x[j].exp() / s - (j == t) as u8 as f32;
+                }
+            }
+            for reduction in [Reduction::Mean, Reduction::Sum, Reduction::None] {
+                let x = Variable::builder().node().build(
+                    ScalarTensor::from(
+                        Tensor::from(x_array.clone())
+                            .into_device(device.clone())
+                            .unwrap(),
+                    )
+                    .into_shared()
+                    .unwrap(),
+                );
+                let y = x.cross_entropy_loss_with(t.clone(), reduction).unwrap();
+                let y_array = to_array(y.value().clone());
+                let (y_true, scale) = match reduction {
+                    Reduction::Mean => (
+                        Array::from_elem((), loss_true.mean().unwrap()).into_dyn(),
+                        1. / 5.,
+                    ),
+                    Reduction::Sum => (Array::from_elem((), loss_true.sum()).into_dyn(), 1.),
+                    Reduction::None => (loss_true.clone().into_dyn(), 1.),
+                };
+                assert_relative_eq!(y_array, y_true, epsilon = 1e-5);
+                let dy = ScalarTensor::ones(device.clone(), y.raw_dim(), ScalarType::F32)
+                    .unwrap()
+                    .into_shared()
+                    .unwrap();
+                y.node().unwrap().backward_grad(dy).unwrap();
+                let dx = to_array(x.grad().unwrap().into_dyn());
+                assert_relative_eq!(dx, (&dx_true * scale).into_dyn(), epsilon = 1e-5);
+                let y_tensor = x
+                    .value()
+                    .cross_entropy_loss_with(t.clone(), reduction)
+                    .unwrap();
+                assert_relative_eq!(
+                    y_tensor
+                        .into_device(Device::host())
+                        .unwrap()
+                        .into_array()
+                        .unwrap(),
+                    y_true,
+                    epsilon = 1e-5
+                );
+            }
+        }
+
+        fn loss_per_sample(device: &Device) {
+            use autograph::learn::criterion::{
+                CrossEntropyLossOptions, Reduction, TripletMarginLoss, TripletMarginLossOptions,
+            };
+
+            fn to_array<D: ndarray::Dimension>(x: ScalarArcTensor<D>) -> Array<f32, D> {
+                x.into_device(Device::host())
+                    .unwrap()
+                    .try_into_tensor::<f32>()
+                    .unwrap()
+                    .into_array()
+                    .unwrap()
+            }
+
+            let x_array = Array::from_shape_fn([6, 4], |(i, j)| ((i * 4 + j) % 7) as f32 / 3.);
+            let t_array = Array1::from(vec![1u8, 255, 3, 0, 255, 2]);
+            let t = ScalarTensor::from(Tensor::from(t_array).into_device(device.clone()).unwrap())
+                .into_shared()
+                .unwrap();
+            for ignore_index in [None, Some(255)] {
+                let options = CrossEntropyLossOptions { ignore_index };
+                let x = || {
+                    Variable::builder().node().build(
+                        ScalarTensor::from(
+                            Tensor::from(x_array.clone())
+                                .into_device(device.clone())
+                                .unwrap(),
+                        )
+                        .into_shared()
+                        .unwrap(),
+                    )
+                };
+                let x_sum = x();
+                let y_sum = x_sum
+                    .cross_entropy_loss_with_options(t.clone(), &options)
+                    .unwrap();
+                y_sum.node().unwrap().backward().unwrap();
+                let x_none = x();
+                let y_none = x_none
+                    .cross_entropy_loss_per_sample(t.clone(), &options)
+                    .unwrap();
+                assert_eq!(y_none.shape(), [6]);
+                let loss_none = to_array(y_none.value().clone());
+                if ignore_index.is_some() {
+                    assert_eq!(loss_none[1], 0.);
+                    assert_eq!(loss_none[4], 0.);
+                }
+                let loss_sum = to_array(y_sum.value().clone()).into_scalar();
+                assert_relative_eq!(loss_none.sum(), loss_sum, epsilon = 1e-5);
+                // the backward of the sum reduction averages the gradient over the samples
+                let count = if ignore_index.is_some() { 4. } else { 6. };
+                let dy = ScalarTensor::from(
+                    Tensor::from(Array::from_elem(6, 1f32 / count))
+                        .into_device(device.clone())
+                        .unwrap(),
                 )
-                .unwrap(),
+                .into_shared()
+                .unwrap();
+                y_none.node().unwrap().backward_grad(dy).unwrap();
+                let dx_sum = to_array(x_sum.grad().unwrap());
+                let dx_none = to_array(x_none.grad().unwrap());
+                assert_relative_eq!(dx_none, dx_sum, epsilon = 1e-5);
+            }
+
+            let arrays: [Array2<f32>; 3] = [
+                Array::from_shape_vec([2, 3], vec![1., 2., 0., -1., 0.5, 1.]).unwrap(),
+                Array::from_shape_vec([2, 3], vec![0., 1., 1., -2., 1., 0.]).unwrap(),
+                Array::from_shape_vec([2, 3], vec![1., 1.5, 0.5, -1., 1., 1.5]).unwrap(),
+            ];
+            let [anchor, positive, negative] = arrays
+                .map(|x| Variable::from(Tensor::from(x).into_device(device.clone()).unwrap()));
+            let options = TripletMarginLossOptions {
+                reduction: Reduction::Sum,
+                ..TripletMarginLossOptions::default()
             };
-            let dw_device = dw_device
-                .try_into_tensor::<T>()
-                .unwrap()
-                .into_device(Device::host())
+            let loss_sum = anchor
+                .triplet_margin_loss(positive.clone(), negative.clone(), &options)
                 .unwrap();
-            let epsilon = if T::SCALAR_TYPE == ScalarType::BF16 {
-                Some(ScalarElem::F32(inputs as f32))
-            } else {
-                None
+            let options = TripletMarginLossOptions {
+                reduction: Reduction::None,
+                ..options
             };
-            check_approx_eq(
-                dw_host.view().into_dyn().into(),
-                dw_device.view().into_dyn().into(),
-                epsilon,
+            assert!(anchor
+                .triplet_margin_loss(positive.clone(), negative.clone(), &options)
+                .is_err());
+            let loss_none = anchor
+                .triplet_margin_loss_per_sample(positive, negative, &options)
+                .unwrap();
+            assert_eq!(loss_none.shape(), [2]);
+            assert_relative_eq!(
+                to_array(loss_none.into_value()).sum(),
+                to_array(loss_sum.into_value()).into_scalar(),
+                epsilon = 1e-5
             );
         }
 
-        fn im2col_conv2<T: Scalar>(
-            device: &Device,
-            x_shape: [usize; 4],
-            options: &Im2ColConv2Options,
-        ) {
-            let len = x_shape.iter().product();
-            let x_vec: Vec<T> = (1..=len).map(|x| T::from_usize(x).unwrap()).collect();
-            let x_array = Array::from(x_vec).into_shape(x_shape).unwrap();
-            let x_host = Tensor::from(x_array);
-            let x_device = x_host.to_device(device.clone()).unwrap();
-            let y_host = x_host.im2col_conv2(options).unwrap();
-            let y_device = x_device.im2col_conv2(options).unwrap();
-            assert_eq!(y_host.into_array().unwrap(), y_device.into_array().unwrap());
-        }
+        fn cross_entropy_loss_ignore_index(device: &Device) {
+            use autograph::learn::criterion::CrossEntropyLossOptions;
 
-        fn col2im_conv2<T: Scalar>(
-            device: &Device,
-            input_shape: [usize; 4],
-            options: &Im2ColConv2Options,
-        ) {
-            let [_batch_size, _channels, ih, iw] = input_shape;
-            let len = input_shape.iter().product();
-            let x_vec: Vec<T> = (1..=len).map(|x| T::from_usize(x).unwrap()).collect();
-            let x_array = Array::from(x_vec).into_shape(input_shape).unwrap();
-            let x_host = Tensor::from(x_array);
-            let y_host = x_host.im2col_conv2(options).unwrap();
-            let [oh, ow] = options.output_shape([ih, iw]);
-            let col2im_options = Col2ImConv2Options {
-                shape: [oh, ow],
-                filter: options.filter,
-                padding: options.padding,
-                stride: options.stride,
-                dilation: options.dilation,
-            };
-            let dy_vec: Vec<T> = (1..=y_host.len())
-                .map(|x| T::from_usize(x).unwrap())
-                .collect();
-            let dy_array = Array::from(dy_vec).into_shape(y_host.raw_dim()).unwrap();
-            let dy_host = Tensor::from(dy_array);
-            let dy_device = dy_host.to_device(device.clone()).unwrap();
-            let dx_host = dy_host.col2im_conv2(&col2im_options).unwrap();
-            let dx_device = dy_device.col2im_conv2(&col2im_options).unwrap();
-            let [fh, fw] = options.filter;
-            let epsilon = if T::SCALAR_TYPE == ScalarType::BF16 {
-                Some(ScalarElem::F32((fh * fw) as f32))
-            } else {
-                None
+            let x_array = Array::from_shape_fn([6, 4], |(i, j)| ((i * 4 + j) % 7) as f32 / 3.);
+            let t_array = Array1::from(vec![1u8, 255, 3, 0, 255, 2]);
+            let keep = [0, 2, 3, 5];
+            let forward = |x: Array2<f32>, t: Array1<u8>, options: CrossEntropyLossOptions| {
+                let x = Variable::builder().node().build(
+                    ScalarTensor::from(Tensor::from(x).into_device(device.clone()).unwrap())
+                        .into_shared()
+                        .unwrap(),
+                );
+                let t = ScalarTensor::from(Tensor::from(t).into_device(device.clone()).unwrap())
+                    .into_shared()
+                    .unwrap();
+                let y = x.cross_entropy_loss_with_options(t, &options).unwrap();
+                y.node().unwrap().backward().unwrap();
+                let loss = y
+                    .value()
+                    .view()
+                    .try_into_tensor_view::<f32>()
+                    .unwrap()
+                    .item()
+                    .unwrap();
+                let dx = x
+                    .grad()
+                    .unwrap()
+                    .into_device(Device::host())
+                    .unwrap()
+                    .try_into_tensor::<f32>()
+                    .unwrap()
+                    .into_array()
+                    .unwrap();
+                (loss, dx)
             };
-            check_approx_eq(
-                dx_host.view().into_dyn().into(),
-                dx_device.view().into_dyn().into(),
-                epsilon,
+            let (loss, dx) = forward(
+                x_array.clone(),
+                t_array.clone(),
+                CrossEntropyLossOptions {
+                    ignore_index: Some(255),
+                },
             );
+            let (loss_true, dx_true) = forward(
+                x_array.select(Axis(0), &keep),
+                t_array.select(Axis(0), &keep),
+                CrossEntropyLossOptions::default(),
+            );
+            assert_relative_eq!(loss, loss_true, epsilon = 1e-5);
+            assert_relative_eq!(dx.select(Axis(0), &keep), dx_true, epsilon = 1e-6);
+            assert!(dx.row(1).iter().chain(dx.row(4)).all(|x| *x == 0.));
         }
 
-        fn max_pool2<T: Scalar>(device: &Device, input_shape: [usize; 4], pool: &MaxPool2) {
-            let len = input_shape.iter().product();
-            let x_vec: Vec<T> = (0..10u8)
-                .map(|x| T::from_u8(x).unwrap())
-                .cycle()
-                .take(len)
-                .collect();
-            let x_array = Array::from(x_vec).into_shape(input_shape).unwrap();
-            let x_host = Tensor::from(x_array);
-            let x_device = x_host.to_device(device.clone()).unwrap();
-            let y_host = pool
-                .forward(Variable::from(x_host))
-                .unwrap()
-                .into_value()
-                .into_owned()
-                .unwrap()
-                .try_into_tensor::<T>()
-                .unwrap();
-            let y_device = pool
-                .forward(Variable::from(x_device))
-                .unwrap()
-                .into_value()
-                .into_owned()
-                .unwrap()
-                .try_into_tensor::<T>()
+        fn quantize(device: &Device) {
+            fn to_array<D: ndarray::Dimension>(x: Variable<D>) -> Array<f32, D> {
+                x.into_value()
+                    .into_device(Device::host())
+                    .unwrap()
+                    .try_into_tensor::<f32>()
+                    .unwrap()
+                    .into_array()
+                    .unwrap()
+            }
+            fn check<D: ndarray::Dimension>(y: Array<f32, D>, y_true: Array<f32, D>) {
+                let max = y_true.iter().fold(0f32, |m, x| m.max(x.abs()));
+                let error = y
+                    .iter()
+                    .zip(y_true.iter())
+                    .fold(0f32, |m, (a, b)| m.max((a - b).abs()));
+                assert!(error <= 0.05 * max, "{error} {max}");
+            }
+
+            let dense = Dense::builder()
+                .inputs(32)
+                .outputs(10)
+                .bias(true)
+                .seed(0)
+                .device(device.clone())
+                .build()
                 .unwrap();
-            assert_eq!(y_host.into_array().unwrap(), y_device.into_array().unwrap());
+            let quantized = dense.quantize().unwrap();
+            assert_eq!(quantized.in_features(), 32);
+            assert_eq!(quantized.out_features(), 10);
+            let x = Array::from_shape_fn([7, 32], |(i, j)| ((i * 32 + j) % 13) as f32 / 6. - 1.);
+            let x = Variable::from(Tensor::from(x).into_device(device.clone()).unwrap());
+            check(
+                to_array(quantized.forward(x.clone()).unwrap()),
+                to_array(dense.forward(x.clone()).unwrap()),
+            );
+            assert!(quantized
+                .forward(Variable::from(
+                    Tensor::from(Array2::<f32>::zeros([7, 31]))
+                        .into_device(device.clone())
+                        .unwrap()
+                ))
+                .is_err());
+
+            let x = Array::from_shape_fn([2, 3, 9, 8], |(n, c, i, j)| {
+                (((n * 3 + c) * 9 + i) * 8 + j) as f32 % 11. / 5. - 1.
+            });
+            let x = Variable::from(Tensor::from(x).into_device(device.clone()).unwrap());
+            for (stride, padding_mode) in
+                [([1, 1], PaddingMode::Valid), ([2, 2], PaddingMode::Same)]
+            {
+                let conv = Conv2::builder()
+                    .inputs(3)
+                    .outputs(4)
+                    .filter([3, 2])
+                    .stride(stride)
+                    .padding_mode(padding_mode)
+                    .bias(true)
+                    .seed(1)
+                    .device(device.clone())
+                    .build()
+                    .unwrap();
+                let quantized = conv.quantize().unwrap();
+                assert_eq!(quantized.in_channels(), 3);
+                assert_eq!(quantized.out_channels(), 4);
+                let y = to_array(quantized.forward(x.clone()).unwrap());
+                let y_true = to_array(conv.forward(x.clone()).unwrap());
+                assert_eq!(y.shape(), y_true.shape());
+                check(y, y_true);
+            }
         }
 
-        fn max_pool2_backward<T: Scalar>(
-            device: &Device,
-            input_shape: [usize; 4],
-            pool: &MaxPool2,
-        ) {
-            use neural_network::layer::__private::max_pool2_backward as backward;
-            let len = input_shape.iter().product();
-            let x_vec: Vec<T> = (0..10u8)
-                .map(|x| T::from_u8(x).unwrap())
-                .cycle()
-                .take(len)
-                .collect();
-            let x_array = Array::from(x_vec).into_shape(input_shape).unwrap();
-            let x_host = Tensor::from(x_array).into_shared().unwrap();
-            let y_host = pool
-                .forward(Variable::from(x_host.clone()))
-                .unwrap()
-                .into_value()
-                .into_owned()
-                .unwrap()
-                .try_into_tensor::<T>()
+        fn flatten_range(device: &Device) {
+            use ndarray::{Ix3, Ix5};
+
+            let x_array = Array::from_shape_fn([2, 3, 4, 2, 5], |(n, t, c, i, j)| {
+                ((((n * 3 + t) * 4 + c) * 2 + i) * 5 + j) as f32
+            });
+            let x = Variable::builder().node().build(
+                ScalarTensor::from(
+                    Tensor::from(x_array.clone())
+                        .into_device(device.clone())
+                        .unwrap(),
+                )
+                .into_shared()
+                .unwrap(),
+            );
+            let y = Flatten::<Ix3>::builder()
+                .start_dim(2)
+                .build()
+                .forward(x.clone())
                 .unwrap();
-            let dy_vec: Vec<T> = (0..y_host.len())
-                .map(|x| T::from_usize(x).unwrap())
-                .collect();
-            let dy_array = Array::from(dy_vec).into_shape(y_host.raw_dim()).unwrap();
-            let dy_host = Tensor::from(dy_array).into_shared().unwrap();
-            let x_device = x_host.to_device_shared(device.clone()).unwrap();
-            let dy_device = dy_host.to_device_shared(device.clone()).unwrap();
-            let dx_host = backward(pool, x_host.into(), dy_host.into())
+            assert_eq!(y.shape(), [2, 3, 4 * 2 * 5]);
+            let y_array = y
+                .value()
+                .clone()
+                .into_device(Device::host())
                 .unwrap()
-                .into_owned()
+                .try_into_tensor::<f32>()
                 .unwrap()
-                .try_into_tensor::<T>()
+                .into_array()
                 .unwrap();
-            let dx_device = backward(pool, x_device.into(), dy_device.into())
-                .unwrap()
-                .into_owned()
+            assert_eq!(y_array, x_array.clone().into_shape([2, 3, 40]).unwrap());
+            let dy_array = Array::from_shape_fn([2, 3, 40], |(n, t, k)| (k * 6 + n * 3 + t) as f32);
+            let dy = ScalarTensor::from(
+                Tensor::from(dy_array.clone())
+                    .into_device(device.clone())
+                    .unwrap(),
+            )
+            .into_shared()
+            .unwrap();
+            y.node().unwrap().backward_grad(dy).unwrap();
+            let dx_array = x
+                .grad()
                 .unwrap()
-                .try_into_tensor::<T>()
-                .unwrap();
-            assert_eq!(
-                dx_host.into_array().unwrap(),
-                dx_device.into_array().unwrap()
-            );
-        }
-
-        fn relu<T: Scalar>(device: &Device, input_shape: [usize; 2]) {
-            let len = input_shape.iter().product();
-            let x_vec: Vec<T> = (-10i8..10)
-                .map(|x| T::from_i8(x).unwrap())
-                .cycle()
-                .take(len)
-                .collect();
-            let x_array = Array::from(x_vec).into_shape(input_shape).unwrap();
-            let x_host = Tensor::from(x_array);
-            let x_device = x_host.to_device(device.clone()).unwrap();
-            let y_host = Relu
-                .forward(Variable::from(x_host))
+                .into_device(Device::host())
                 .unwrap()
-                .into_value()
-                .into_owned()
+                .try_into_tensor::<f32>()
                 .unwrap()
-                .try_into_tensor::<T>()
+                .into_array()
                 .unwrap();
-            let y_device = Relu
-                .forward(Variable::from(x_device))
-                .unwrap()
-                .into_value()
-                .into_owned()
-                .unwrap()
-                .try_into_tensor::<T>()
+            assert_eq!(dx_array, dy_array.into_shape([2, 3, 4, 2, 5]).unwrap());
+
+            let y: Variable2 = Flatten::default().forward(x.clone()).unwrap();
+            assert_eq!(y.shape(), [2, 3 * 4 * 2 * 5]);
+            let y = Flatten::<Ix4>::builder()
+                .start_dim(2)
+                .end_dim(3)
+                .build()
+                .forward(x.clone())
                 .unwrap();
-            assert_eq!(y_host.into_array().unwrap(), y_device.into_array().unwrap());
+            assert_eq!(y.shape(), [2, 3, 4 * 2, 5]);
+            assert!(Flatten::<Ix3>::builder()
+                .start_dim(1)
+                .build()
+                .forward(x.clone())
+                .is_err());
+            assert!(Flatten::<Ix5>::builder()
+                .start_dim(3)
+                .end_dim(5)
+                .build()
+                .forward(x)
+                .is_err());
         }
 
-        fn relu_backward<T: Scalar>(device: &Device, input_shape: [usize; 2]) {
-            use neural_network::layer::__private::relu_backward as backward;
-            let len = input_shape.iter().product();
-            let y_vec: Vec<T> = (-1i8..1)
-                .map(|x| T::from_i8(x).unwrap())
-                .cycle()
-                .take(len)
-                .collect();
-            let dy_vec: Vec<T> = (0..len).map(|x| T::from_usize(x).unwrap()).collect();
-            let y_array = Array::from(y_vec).into_shape(input_shape).unwrap();
-            let dy_array = Array::from(dy_vec).into_shape(input_shape).unwrap();
-            let y_host = Tensor::from(y_array).into_shared().unwrap();
-            let dy_host = Tensor::from(dy_array).into_shared().unwrap();
-            let y_device = y_host.to_device_shared(device.clone()).unwrap();
-            let dy_device = dy_host.to_device_shared(device.clone()).unwrap();
-            for (dy_host, dy_device) in [
-                (dy_host.clone(), dy_device.clone()), // relu_backward
-                (dy_host, dy_device),                 // relu_backward_mut
+        fn pixel_shuffle(device: &Device) {
+            use autograph::learn::neural_network::layer::{PixelShuffle, PixelUnshuffle};
+
+            let r = 2;
+            let x_array = Array::from_shape_fn([2, 3 * r * r, 4, 5], |(n, c, i, j)| {
+                (((n * 12 + c) * 4 + i) * 5 + j) as f32
+            });
+            let to_array = |x: Variable4| {
+                x.into_value()
+                    .into_device(Device::host())
+                    .unwrap()
+                    .try_into_tensor::<f32>()
+                    .unwrap()
+                    .into_array()
+                    .unwrap()
+            };
+            let x = Variable::builder().node().build(
+                ScalarTensor::from(
+                    Tensor::from(x_array.clone())
+                        .into_device(device.clone())
+                        .unwrap(),
+                )
+                .into_shared()
+                .unwrap(),
+            );
+            let y = PixelShuffle::new(r).forward(x.clone()).unwrap();
+            assert_eq!(y.shape(), [2, 3, 4 * r, 5 * r]);
+            let y_array = to_array(y.clone());
+            for ((n, c, i, j), y) in y_array.indexed_iter() {
+                let x = x_array[(n, c * r * r + (i % r) * r + j % r, i / r, j / r)];
+                assert_eq!(*y, x);
+            }
+            let x2 = PixelUnshuffle::new(r).forward(y).unwrap();
+            x2.node().unwrap().backward().unwrap();
+            assert!(x.grad().is_some());
+            assert_eq!(to_array(x2), x_array);
+            assert!(PixelShuffle::new(3).forward(x.clone()).is_err());
+            assert!(PixelUnshuffle::new(3).forward(x).is_err());
+        }
+
+        fn conv_same_padding(_device: &Device) {
+            use autograph::learn::neural_network::layer::conv_same_padding;
+
+            // [input, filter, stride, dilation] => [pad_before, pad_after], from TensorFlow
+            for ([input, filter, stride, dilation], padding) in [
+                ([5, 3, 1, 1], [1, 1]),
+                ([5, 3, 2, 1], [1, 1]),
+                ([6, 3, 2, 1], [0, 1]),
+                ([7, 4, 1, 1], [1, 2]),
+                ([10, 3, 1, 2], [2, 2]),
+                ([224, 7, 2, 1], [2, 3]),
+                ([4, 1, 2, 1], [0, 0]),
             ] {
-                let dx_host = backward(y_host.clone().into(), dy_host.into())
+                assert_eq!(
+                    conv_same_padding([input], [filter], [stride], [dilation]),
+                    [padding]
+                );
+            }
+            assert_eq!(
+                conv_same_padding([224, 6], [7, 3], [2, 2], [1, 1]),
+                [[2, 3], [0, 1]]
+            );
+        }
+
+        fn pad2(device: &Device) {
+            use autograph::learn::neural_network::layer::Pad2;
+
+            let x_array = Array::from_shape_fn([2, 3, 4, 5], |(n, c, i, j)| {
+                (((n * 3 + c) * 4 + i) * 5 + j) as f32
+            });
+            let to_array = |x: ScalarArcTensorD| {
+                x.into_device(Device::host())
                     .unwrap()
-                    .into_owned()
+                    .try_into_tensor::<f32>()
                     .unwrap()
-                    .try_into_tensor::<T>()
-                    .unwrap();
-                let dx_device = backward(y_device.clone().into(), dy_device.into())
+                    .into_array()
                     .unwrap()
-                    .into_owned()
+                    .into_dimensionality::<Ix4>()
                     .unwrap()
-                    .try_into_tensor::<T>()
-                    .unwrap();
-                assert_eq!(
-                    dx_host.into_array().unwrap(),
-                    dx_device.into_array().unwrap()
+            };
+            let crop = Pad2::new([[-1, -2], [0, -1]])
+                .forward(Variable::from(
+                    Tensor::from(x_array.clone())
+                        .into_device(device.clone())
+                        .unwrap(),
+                ))
+                .unwrap();
+            assert_eq!(
+                to_array(crop.into_value().into_dyn()),
+                x_array.slice(s![.., .., 1..2, 0..4])
+            );
+            let value = -1.;
+            for padding in [
+                [[-1, -2], [0, -1]],
+                [[1, 2], [0, 1]],
+                [[2, -1], [-2, 1]],
+                [[-3, 0], [3, -4]],
+            ] {
+                let [[ph1, ph2], [pw1, pw2]] = padding;
+                let (n, c, ih, iw) = x_array.dim();
+                let oh = (ih as isize + ph1 + ph2) as usize;
+                let ow = (iw as isize + pw1 + pw2) as usize;
+                let y_expected = Array::from_shape_fn([n, c, oh, ow], |(n, c, i, j)| {
+                    let i = i as isize - ph1;
+                    let j = j as isize - pw1;
+                    if (0..ih as isize).contains(&i) && (0..iw as isize).contains(&j) {
+                        x_array[[n, c, i as usize, j as usize]]
+                    } else {
+                        value
+                    }
+                });
+                let dy_array = Array::from_shape_fn([n, c, oh, ow], |(n, c, i, j)| {
+                    (n * 1000 + c * 100 + i * 10 + j) as f32
+                });
+                let dx_expected = Array::from_shape_fn([n, c, ih, iw], |(n, c, i, j)| {
+                    let i = i as isize + ph1;
+                    let j = j as isize + pw1;
+                    if (0..oh as isize).contains(&i) && (0..ow as isize).contains(&j) {
+                        dy_array[[n, c, i as usize, j as usize]]
+                    } else {
+                        0.
+                    }
+                });
+                let x = Variable::builder().node().build(ScalarArcTensor::from(
+                    Tensor::from(x_array.clone())
+                        .into_device(device.clone())
+                        .unwrap(),
+                ));
+                let y = Pad2::new(padding).value(value).forward(x.clone()).unwrap();
+                assert_eq!(to_array(y.value().clone().into_dyn()), y_expected);
+                let dy = ScalarArcTensor::from(
+                    Tensor::from(dy_array).into_device(device.clone()).unwrap(),
                 );
+                y.node().unwrap().backward_grad(dy).unwrap();
+                let dx = x.node().unwrap().grad().unwrap();
+                assert_eq!(to_array(dx.into_dyn()), dx_expected);
             }
+            assert!(Pad2::new([[-3, -3], [0, 0]])
+                .forward(Variable::from(
+                    Tensor::from(x_array).into_device(device.clone()).unwrap()
+                ))
+                .is_err());
         }
 
         fn broadcast<D1: IntoDimension + 'static, D2: IntoDimension + 'static>(